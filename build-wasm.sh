@@ -9,10 +9,11 @@ echo "Building Mozart Core WASM..."
 
 cd crates/mozart-core
 
-# Build with wasm-pack
+# Build with wasm-pack (the generated .d.ts also carries the JSON payload
+# types from src/typescript.rs)
 wasm-pack build \
     --target web \
-    --features wasm \
+    --features wasm,packed-notes \
     --out-dir ../../web/src/wasm/pkg
 
 echo "WASM build complete!"
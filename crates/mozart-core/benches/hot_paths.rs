@@ -0,0 +1,9 @@
+//! Hot-path timings for large songs
+//!
+//! Run with `cargo bench -p mozart-core --features bench`.
+
+use mozart_core::bench::{report, run_benchmarks, BenchSizes};
+
+fn main() {
+    print!("{}", report(&run_benchmarks(&BenchSizes::default())));
+}
@@ -0,0 +1,54 @@
+//! JSON vs packed note transfer for a large song
+//!
+//! Run with `cargo bench -p mozart-core --features packed-notes`.
+
+use mozart_core::packed::{decode_notes, encode_notes};
+use mozart_core::Note;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    f(); // Warm up
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    println!("{:<24} {:>10.3} ms", label, per_iteration.as_secs_f64() * 1000.0);
+    per_iteration
+}
+
+fn main() {
+    for count in [100, 2_000, 20_000] {
+        let notes: Vec<Note> = (0..count)
+            .map(|i| Note::with_velocity(36 + (i % 48) as u8, i * 120, 240, 64 + (i % 64) as u8))
+            .collect();
+        let json = serde_json::to_string(&notes).unwrap();
+        let packed = encode_notes(&notes);
+        println!(
+            "\n{} notes: JSON {} bytes, packed {} bytes",
+            count,
+            json.len(),
+            packed.len()
+        );
+
+        let json_encode = time("json encode", || {
+            black_box(serde_json::to_string(black_box(&notes)).unwrap());
+        });
+        let json_decode = time("json decode", || {
+            black_box(serde_json::from_str::<Vec<Note>>(black_box(&json)).unwrap());
+        });
+        let packed_encode = time("packed encode", || {
+            black_box(encode_notes(black_box(&notes)));
+        });
+        let packed_decode = time("packed decode", || {
+            black_box(decode_notes(black_box(&packed)).unwrap());
+        });
+
+        let speedup = (json_encode + json_decode).as_secs_f64()
+            / (packed_encode + packed_decode).as_secs_f64().max(f64::EPSILON);
+        println!("{:<24} {:>10.1}x", "round trip speedup", speedup);
+    }
+}
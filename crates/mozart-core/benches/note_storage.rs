@@ -0,0 +1,80 @@
+//! Vec<Note> vs structure-of-arrays storage for a large song
+//!
+//! Run with `cargo bench -p mozart-core --features soa-notes`.
+
+use mozart_core::columns::NoteColumns;
+use mozart_core::scale::Scale;
+use mozart_core::transpose::{transpose_notes, TransposeMode};
+use mozart_core::Note;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    f(); // Warm up
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_iteration = start.elapsed() / ITERATIONS;
+    println!("{:<24} {:>10.3} ms", label, per_iteration.as_secs_f64() * 1000.0);
+    per_iteration
+}
+
+fn compare(label: &str, before: Duration, after: Duration) {
+    let speedup = before.as_secs_f64() / after.as_secs_f64().max(f64::EPSILON);
+    println!("{:<24} {:>10.1}x", label, speedup);
+}
+
+fn main() {
+    let modes = [
+        ("chromatic", TransposeMode::chromatic(5)),
+        ("diatonic", TransposeMode::diatonic(Scale::c_major(), 2)),
+    ];
+    for count in [2_000, 50_000] {
+        let notes: Vec<Note> = (0..count)
+            .map(|i| Note::with_velocity(36 + (i % 48) as u8, i * 120, 240, 64 + (i % 64) as u8))
+            .collect();
+        let columns = NoteColumns::from_notes(&notes);
+        let json = serde_json::to_string(&notes).unwrap();
+        let columns_json = columns.to_json().unwrap();
+        println!(
+            "\n{} notes: Vec<Note> JSON {} bytes, columns JSON {} bytes",
+            count,
+            json.len(),
+            columns_json.len()
+        );
+
+        for (name, mode) in &modes {
+            let before = time(&format!("vec transpose ({})", name), || {
+                black_box(transpose_notes(black_box(&notes), mode).unwrap());
+            });
+            let after = time(&format!("soa transpose ({})", name), || {
+                let mut transposed = columns.clone();
+                transposed.transpose(mode).unwrap();
+                black_box(transposed);
+            });
+            compare("transpose speedup", before, after);
+            // What a Song keeping Vec<Note> would pay to use columns for one pass
+            let converted = time(&format!("via soa ({})", name), || {
+                let mut transposed = NoteColumns::from_notes(black_box(&notes));
+                transposed.transpose(mode).unwrap();
+                black_box(transposed.to_notes());
+            });
+            compare("converted speedup", before, converted);
+        }
+
+        let before = time("vec serialize", || {
+            black_box(serde_json::to_string(black_box(&notes)).unwrap());
+        }) + time("vec deserialize", || {
+            black_box(serde_json::from_str::<Vec<Note>>(black_box(&json)).unwrap());
+        });
+        let after = time("soa serialize", || {
+            black_box(black_box(&columns).to_json().unwrap());
+        }) + time("soa deserialize", || {
+            black_box(NoteColumns::from_json(black_box(&columns_json)).unwrap());
+        });
+        compare("round trip speedup", before, after);
+    }
+}
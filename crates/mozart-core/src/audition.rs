@@ -0,0 +1,245 @@
+//! Scale and chord auditions
+//!
+//! Short note patterns for previewing a scale or chord before using it, timed
+//! in seconds at a tempo so a frontend can schedule them directly: scales
+//! run in eighth notes, and chords are broken upward in eighths and then
+//! struck together for a half note; the chords of a key are voiced the
+//! same way from their scale tones. Passages of a song (such as candidate
+//! variations) are timed through its tempo map instead.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::PitchClass;
+use crate::scale::{Scale, ScaleType};
+use crate::song::Song;
+use crate::theory::{chord_intervals, diatonic_chord};
+
+/// Middle C; auditions start from the root above it
+const AUDITION_BASE_PITCH: u8 = 60;
+const AUDITION_VELOCITY: u8 = 90;
+
+/// Which way a scale audition runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleDirection {
+    Ascending,
+    Descending,
+    /// Up to the octave and back down
+    Both,
+}
+
+impl ScaleDirection {
+    /// Parse "up"/"ascending", "down"/"descending", or "both"
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "up" | "ascending" => Ok(ScaleDirection::Ascending),
+            "down" | "descending" => Ok(ScaleDirection::Descending),
+            "both" | "up_down" | "updown" => Ok(ScaleDirection::Both),
+            _ => Err(MozartError::ParseError(format!("Unknown scale direction: {}", s))),
+        }
+    }
+}
+
+/// A note of an audition, timed from the audition's start
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AuditionNote {
+    pub pitch: u8,
+    pub velocity: u8,
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+fn eighth_seconds(tempo: u16) -> f64 {
+    30.0 / tempo.max(1) as f64
+}
+
+// One note per eighth, each held until the next
+fn in_eighths(pitches: impl IntoIterator<Item = u8>, tempo: u16) -> Vec<AuditionNote> {
+    let eighth = eighth_seconds(tempo);
+    pitches
+        .into_iter()
+        .enumerate()
+        .map(|(i, pitch)| AuditionNote {
+            pitch,
+            velocity: AUDITION_VELOCITY,
+            start_seconds: i as f64 * eighth,
+            duration_seconds: eighth,
+        })
+        .collect()
+}
+
+/// A scale from its root above middle C to the octave, in eighth notes
+///
+/// Melodic minor comes down in its natural minor form, as it is usually sung.
+pub fn scale_audition(
+    root: PitchClass,
+    scale_type: ScaleType,
+    direction: ScaleDirection,
+    tempo: u16,
+) -> Vec<AuditionNote> {
+    let tonic = AUDITION_BASE_PITCH + root.semitones();
+    let octave = |intervals: &[u8]| -> Vec<u8> {
+        intervals.iter().map(|&i| tonic + i).chain(std::iter::once(tonic + 12)).collect()
+    };
+    let up = octave(scale_type.intervals());
+    let down_form = match scale_type {
+        ScaleType::MelodicMinor => ScaleType::NaturalMinor,
+        other => other,
+    };
+    let down: Vec<u8> = octave(down_form.intervals()).into_iter().rev().collect();
+
+    let pitches = match direction {
+        ScaleDirection::Ascending => up,
+        ScaleDirection::Descending => down,
+        // The top note once
+        ScaleDirection::Both => up.into_iter().chain(down.into_iter().skip(1)).collect(),
+    };
+    in_eighths(pitches, tempo)
+}
+
+/// A chord from its root above middle C, broken upward and then struck
+///
+/// `quality` is a chord symbol suffix ("m7"; "" for a major triad), and
+/// `inversion` moves that many of the lowest tones up an octave.
+pub fn chord_audition(root: PitchClass, quality: &str, inversion: u8, tempo: u16) -> Result<Vec<AuditionNote>> {
+    let intervals = chord_intervals(quality)
+        .ok_or_else(|| MozartError::HarmonyError(format!("Unknown chord quality: {}", quality)))?;
+    if inversion as usize >= intervals.len() {
+        return Err(MozartError::HarmonyError(format!(
+            "A {}-note chord has no inversion {}",
+            intervals.len(),
+            inversion
+        )));
+    }
+
+    let tonic = AUDITION_BASE_PITCH + root.semitones();
+    let mut pitches: Vec<u8> = intervals.iter().map(|&i| tonic + i).collect();
+    pitches.rotate_left(inversion as usize);
+    let len = pitches.len();
+    for pitch in &mut pitches[len - inversion as usize..] {
+        *pitch += 12;
+    }
+    // Ninths already sit above the octave
+    pitches.sort_unstable();
+    Ok(broken_then_struck(&pitches, tempo))
+}
+
+/// The triad, or seventh chord, on a degree (1-7) of a key, from its root
+/// above middle C, broken upward and then struck
+///
+/// Built from the key's scale tones, so it matches the chord ladder of
+/// [`crate::harmony::diatonic_chords`] even for chords without a symbol in
+/// the chord table (such as harmonic minor's augmented major seventh).
+pub fn diatonic_chord_audition(key: &Scale, degree: u8, seventh: bool, tempo: u16) -> Result<Vec<AuditionNote>> {
+    if !(1..=7).contains(&degree) {
+        return Err(MozartError::HarmonyError(format!("No scale degree {} (use 1-7)", degree)));
+    }
+    let (_, _, tones) = diatonic_chord(key, degree, seventh);
+    // Each tone stacked on the one before
+    let mut pitches = vec![AUDITION_BASE_PITCH + tones[0].semitones()];
+    for tone in &tones[1..] {
+        let below = pitches[pitches.len() - 1];
+        pitches.push(below + match (tone.semitones() + 12 - below % 12) % 12 {
+            0 => 12,
+            step => step,
+        });
+    }
+    Ok(broken_then_struck(&pitches, tempo))
+}
+
+// Pitches one per eighth, then all together for a half note
+fn broken_then_struck(pitches: &[u8], tempo: u16) -> Vec<AuditionNote> {
+    let mut notes = in_eighths(pitches.iter().copied(), tempo);
+    let struck = notes.len() as f64 * eighth_seconds(tempo);
+    notes.extend(pitches.iter().map(|&pitch| AuditionNote {
+        pitch,
+        velocity: AUDITION_VELOCITY,
+        start_seconds: struck,
+        duration_seconds: 4.0 * eighth_seconds(tempo),
+    }));
+    notes
+}
+
+/// Notes of a song passage, timed from the first one's start through the
+/// song's tempo map
+pub fn phrase_audition(song: &Song, notes: &[Note]) -> Vec<AuditionNote> {
+    let origin = notes.iter().map(|n| n.start_tick).min().unwrap_or(0);
+    let origin_seconds = song.tick_to_seconds(origin);
+    notes
+        .iter()
+        .map(|note| {
+            let start = song.tick_to_seconds(note.start_tick);
+            AuditionNote {
+                pitch: note.pitch,
+                velocity: note.velocity,
+                start_seconds: start - origin_seconds,
+                duration_seconds: song.tick_to_seconds(note.end_tick()) - start,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn pitches(notes: &[AuditionNote]) -> Vec<u8> {
+        notes.iter().map(|n| n.pitch).collect()
+    }
+
+    #[test]
+    fn test_scale_audition() {
+        let up = scale_audition(PitchClass::D, ScaleType::Major, ScaleDirection::Ascending, 120);
+        assert_eq!(pitches(&up), vec![62, 64, 66, 67, 69, 71, 73, 74]);
+        // Eighths at 120 BPM
+        assert_eq!((up[1].start_seconds, up[1].duration_seconds), (0.25, 0.25));
+
+        let melodic = scale_audition(PitchClass::A, ScaleType::MelodicMinor, ScaleDirection::Both, 60);
+        assert_eq!(pitches(&melodic), vec![69, 71, 72, 74, 76, 78, 80, 81, 79, 77, 76, 74, 72, 71, 69]);
+        assert_eq!(melodic.last().unwrap().start_seconds, 7.0);
+
+        assert_eq!(ScaleDirection::parse("Down").unwrap(), ScaleDirection::Descending);
+        assert!(ScaleDirection::parse("sideways").is_err());
+    }
+
+    #[test]
+    fn test_chord_audition() {
+        let notes = chord_audition(PitchClass::C, "", 1, 120).unwrap();
+        // E G C broken, then struck together for a half note
+        assert_eq!(pitches(&notes), vec![64, 67, 72, 64, 67, 72]);
+        assert_eq!((notes[3].start_seconds, notes[3].duration_seconds), (0.75, 1.0));
+
+        let seventh = chord_audition(PitchClass::G, "7", 0, 120).unwrap();
+        assert_eq!(pitches(&seventh)[..4], [67, 71, 74, 77]);
+
+        assert!(chord_audition(PitchClass::C, "m", 3, 120).is_err());
+        assert!(chord_audition(PitchClass::C, "13#11", 0, 120).is_err());
+    }
+
+    #[test]
+    fn test_diatonic_chord_audition() {
+        let c = Scale::c_major();
+        let supertonic = diatonic_chord_audition(&c, 2, false, 120).unwrap();
+        assert_eq!(pitches(&supertonic), vec![62, 65, 69, 62, 65, 69]);
+        let leading = diatonic_chord_audition(&c, 7, true, 120).unwrap();
+        assert_eq!(pitches(&leading)[..4], [71, 74, 77, 81]);
+
+        // Harmonic minor's III+(maj7) has no symbol in the chord table, but still plays
+        let minor = Scale::new(PitchClass::A, ScaleType::HarmonicMinor);
+        let mediant = diatonic_chord_audition(&minor, 3, true, 120).unwrap();
+        assert_eq!(pitches(&mediant)[..4], [60, 64, 68, 71]);
+        assert!(diatonic_chord_audition(&c, 8, false, 120).is_err());
+    }
+
+    #[test]
+    fn test_phrase_audition() {
+        let mut song = Song::new();
+        song.add_tempo_change(1440, 60);
+        let notes = phrase_audition(&song, &[Note::new(64, 1440, 480), Note::new(60, 960, 480)]);
+        // From the earliest note, a beat slower after the tempo change
+        assert_eq!((notes[0].start_seconds, notes[0].duration_seconds), (0.5, 1.0));
+        assert_eq!((notes[1].start_seconds, notes[1].duration_seconds), (0.0, 0.5));
+    }
+}
@@ -0,0 +1,189 @@
+//! Performance benchmarks for the hot paths (`bench` feature)
+//!
+//! Times the operations large songs stress: parsing long melodies,
+//! transposing many notes, exporting MIDI, and preparing dense chords for
+//! playback. `cargo bench -p mozart-core --features bench` and the CLI's
+//! `--bench` flag both print [`report`] of [`run_benchmarks`].
+//!
+//! Timing is hand-rolled (warm-up, then the mean of a fixed number of
+//! runs) so the suite needs no extra dependencies.
+
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use crate::midi::MidiExporter;
+use crate::note::{parse_melody, Note};
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::strum::StrumDirection;
+use crate::transpose::{transpose_notes, TransposeMode};
+
+/// How big each benchmark's input is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchSizes {
+    pub melody_tokens: usize,
+    pub transpose_notes: usize,
+    pub export_notes: usize,
+    /// Chords of `chord_voices` notes prepared for playback
+    pub chords: usize,
+    pub chord_voices: usize,
+    /// Runs timed per benchmark, after one warm-up
+    pub iterations: u32,
+}
+
+impl Default for BenchSizes {
+    fn default() -> Self {
+        BenchSizes {
+            melody_tokens: 10_000,
+            transpose_notes: 50_000,
+            export_notes: 50_000,
+            chords: 2_000,
+            chord_voices: 8,
+            iterations: 20,
+        }
+    }
+}
+
+impl BenchSizes {
+    /// Tiny inputs, to check the suite runs
+    pub fn smoke() -> Self {
+        BenchSizes { melody_tokens: 10, transpose_notes: 10, export_notes: 10, chords: 2, chord_voices: 3, iterations: 1 }
+    }
+}
+
+/// One benchmark's timing
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub name: &'static str,
+    /// Items handled per run (tokens, notes)
+    pub items: usize,
+    pub per_iteration: Duration,
+}
+
+impl BenchResult {
+    /// Items handled per second
+    pub fn throughput(&self) -> f64 {
+        self.items as f64 / self.per_iteration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<28} {:>8} items {:>10.3} ms {:>12.0} items/s",
+            self.name,
+            self.items,
+            self.per_iteration.as_secs_f64() * 1000.0,
+            self.throughput()
+        )
+    }
+}
+
+fn time(name: &'static str, items: usize, iterations: u32, mut f: impl FnMut()) -> BenchResult {
+    f(); // Warm up
+    let iterations = iterations.max(1);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let result = BenchResult { name, items, per_iteration: start.elapsed() / iterations };
+    tracing::debug!("Benchmark: {}", result);
+    result
+}
+
+// A melody walking up and down two octaves, with rests and dotted values
+fn melody_text(tokens: usize) -> String {
+    const NAMES: [&str; 7] = ["C", "D", "E", "F", "G", "A", "B"];
+    const DURATIONS: [&str; 4] = ["q", "e", "e", "q."];
+    (0..tokens)
+        .map(|i| match i % 16 {
+            15 => "Rq".to_string(),
+            _ => format!("{}{}{}", NAMES[i % 7], 4 + (i / 7) % 2, DURATIONS[i % 4]),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn many_notes(count: usize) -> Vec<Note> {
+    (0..count as u32).map(|i| Note::with_velocity(48 + (i % 36) as u8, i * 120, 240, 64 + (i % 64) as u8)).collect()
+}
+
+/// Time every benchmark once at `sizes`
+pub fn run_benchmarks(sizes: &BenchSizes) -> Vec<BenchResult> {
+    let n = sizes.iterations;
+    let mut results = Vec::new();
+
+    let melody = melody_text(sizes.melody_tokens);
+    results.push(time("parse_melody", sizes.melody_tokens, n, || {
+        black_box(parse_melody(black_box(&melody)).unwrap());
+    }));
+
+    let notes = many_notes(sizes.transpose_notes);
+    let chromatic = TransposeMode::chromatic(5);
+    results.push(time("transpose_notes (chromatic)", notes.len(), n, || {
+        black_box(transpose_notes(black_box(&notes), &chromatic).unwrap());
+    }));
+    let diatonic = TransposeMode::diatonic(Scale::c_major(), 2);
+    results.push(time("transpose_notes (diatonic)", notes.len(), n, || {
+        black_box(transpose_notes(black_box(&notes), &diatonic).unwrap());
+    }));
+
+    let mut song = Song::new();
+    song.add_notes(many_notes(sizes.export_notes));
+    let exporter = MidiExporter::new().with_accent_dynamics(true);
+    results.push(time("midi export", sizes.export_notes, n, || {
+        black_box(exporter.export(black_box(&song)).unwrap());
+    }));
+
+    // Dense strummed chords with accent dynamics: the work done before notes reach the audio engine
+    let mut chords = Song::new();
+    chords.settings.accent_dynamics = true;
+    for c in 0..sizes.chords as u32 {
+        let tick = c * 480;
+        chords.add_notes((0..sizes.chord_voices as u8).map(|v| Note::new(40 + v * 4, tick, 480)));
+        chords.set_chord_strum(tick, 60, StrumDirection::Down);
+    }
+    let voices = sizes.chords * sizes.chord_voices;
+    results.push(time("playback notes (polyphony)", voices, n, || {
+        black_box(black_box(&chords).playback_notes());
+    }));
+
+    tracing::info!("Ran {} benchmarks", results.len());
+    results
+}
+
+/// Results as a table
+pub fn report(results: &[BenchResult]) -> String {
+    let mut out = format!("{:<28} {:>14} {:>13} {:>19}\n", "benchmark", "size", "time", "throughput");
+    for result in results {
+        out.push_str(&format!("{}\n", result));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_benchmarks_run() {
+        let results = run_benchmarks(&BenchSizes::smoke());
+        let names: Vec<&str> = results.iter().map(|r| r.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "parse_melody",
+                "transpose_notes (chromatic)",
+                "transpose_notes (diatonic)",
+                "midi export",
+                "playback notes (polyphony)"
+            ]
+        );
+        assert_eq!(results.last().unwrap().items, 6);
+        assert_eq!(report(&results).lines().count(), 6);
+        // The generated melody parses to its notes (every 16th token is a rest)
+        assert_eq!(parse_melody(&melody_text(32)).unwrap().len(), 30);
+    }
+}
@@ -3,24 +3,61 @@
 //! A command-line tool for testing the Mozart music engine
 
 use mozart_core::*;
+use std::collections::VecDeque;
 use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
 
 fn main() {
     // Initialize logging
     init_logging();
+    if std::env::args().any(|arg| arg == "--bench") {
+        run_bench();
+        return;
+    }
     println!("Mozart Test CLI v0.1.0");
     println!("Type 'help' for available commands\n");
 
     let mut song = Song::new();
+    let mut defaults = SongDefaults::default();
+    let mut tutorial: Option<Tutorial> = None;
+    let mut modulations: Vec<modulation::ModulationPlan> = Vec::new();
+    let mut groove_template: Option<groove::GrooveTemplate> = None;
+    let mut variations: Vec<variation::Variation> = Vec::new();
+    let mut varied_phrase: Vec<Note> = Vec::new();
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    let mut checked = song.clone();
+    // Last saved or loaded, for confirming `new` over unsaved changes
+    let mut saved = song.clone();
+    let mut recorder = MacroRecorder::new();
+    let mut macro_library = MacroLibrary::new();
+    let mut sample_libraries = samples::SampleLibraries::new();
+    let mut folder_watch: Option<watch::WatchHandle> = None;
+    // Practice speed; the song's tempo is left alone
+    let mut playback_rate = PlaybackRate::default();
+    // Lines of a macro being played, run before reading more input
+    let mut queued: VecDeque<String> = VecDeque::new();
+    // Exports run as jobs; each one's progress prints before the next command
+    let (job_events, export_events) = mpsc::channel();
+    let jobs = jobs::JobManager::new(1, move |event| {
+        let _ = job_events.send(event.clone());
+    });
 
     loop {
+        // Warn about notes the last command put outside the instrument range
+        for warning in register::range_warnings(&checked, &song) {
+            println!("Warning: {}", warning.message);
+        }
+        checked = song.clone();
+
         print!("mozart> ");
         stdout.flush().unwrap();
 
         let mut line = String::new();
-        if stdin.lock().read_line(&mut line).is_err() {
+        if let Some(next) = queued.pop_front() {
+            println!("{}", next);
+            line = next;
+        } else if stdin.lock().read_line(&mut line).is_err() {
             break;
         }
 
@@ -33,13 +70,113 @@ fn main() {
         let cmd = parts[0];
         let args = parts.get(1).copied().unwrap_or("");
 
+        if !matches!(cmd, "help" | "quit" | "exit" | "q" | "record" | "play" | "macros") {
+            recorder.record(MacroStep::new(cmd, &parts[1..]));
+        }
+
         match cmd {
             "help" => print_help(),
             "quit" | "exit" | "q" => break,
 
             "new" => {
-                song = Song::new();
-                println!("Created new song");
+                let loss = confirm::new_song_loss(&song, !diff_songs(&saved, &song).is_empty());
+                match confirm::confirm_or_run(loss, args == "confirm", || Song::new_with_defaults(&defaults)) {
+                    Outcome::Done { result: Ok(new_song) } => {
+                        song = new_song;
+                        saved = song.clone();
+                        println!("Created new song");
+                    }
+                    Outcome::Done { result: Err(e) } => println!("Error: {}", e),
+                    Outcome::NeedsConfirmation { summary } => println!("{} Type 'new confirm' to go ahead.", summary.message),
+                }
+            }
+
+            "defaults" => {
+                let (field, value) = args.split_once(' ').unwrap_or((args, ""));
+                let mut changed = defaults.clone();
+                let parsed = match field {
+                    "" => Ok(()),
+                    "tempo" => value.parse().map(|t| changed.tempo = t).map_err(|_| format!("Invalid tempo: {}", value)),
+                    "time" => {
+                        changed.time_signature = value.to_string();
+                        Ok(())
+                    }
+                    "key" => {
+                        changed.key = value.to_string();
+                        Ok(())
+                    }
+                    "instrument" => {
+                        changed.instrument = if value == "none" { String::new() } else { value.to_string() };
+                        Ok(())
+                    }
+                    "pickup" => value.parse().map(|p| changed.pickup_beats = p).map_err(|_| format!("Invalid pickup: {}", value)),
+                    _ => Err("Usage: defaults [tempo|time|key|instrument|pickup <value>]".to_string()),
+                };
+                match parsed.and_then(|_| Song::new_with_defaults(&changed).map_err(|e| e.to_string())) {
+                    Ok(_) => defaults = changed,
+                    Err(e) => println!("Error: {}", e),
+                }
+                println!(
+                    "New songs: {} BPM, {}, {}, instrument {}, pickup {} beats",
+                    defaults.tempo,
+                    defaults.time_signature,
+                    defaults.key,
+                    if defaults.instrument.is_empty() { "none" } else { &defaults.instrument },
+                    defaults.pickup_beats
+                );
+            }
+
+            "record" => {
+                let (action, rest) = args.split_once(' ').unwrap_or((args, ""));
+                match action {
+                    "" | "start" => {
+                        recorder.start();
+                        println!("Recording; 'record stop <name> [shortcut]' saves the commands as a macro");
+                    }
+                    "stop" => {
+                        let (name, shortcut) = match rest.rsplit_once(' ') {
+                            Some((name, shortcut)) if macros::parse_shortcut(shortcut).is_ok() => (name, Some(shortcut)),
+                            _ => (rest, None),
+                        };
+                        let steps = recorder.stop();
+                        let count = steps.len();
+                        match macro_library.save(name, shortcut, steps) {
+                            Ok(()) => println!("Saved macro '{}' ({} commands)", name.trim(), count),
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    }
+                    "cancel" => {
+                        recorder.stop();
+                        println!("Recording discarded");
+                    }
+                    _ => println!("Usage: record [start|stop <name> [shortcut]|cancel]"),
+                }
+            }
+
+            "play" => match macro_library.get(args).or_else(|| macro_library.by_shortcut(args)) {
+                Some(saved_macro) => {
+                    println!("Playing '{}'", saved_macro.name);
+                    for step in saved_macro.steps.iter().rev() {
+                        let line = std::iter::once(step.command.as_str()).chain(step.args.iter().map(String::as_str));
+                        queued.push_front(line.collect::<Vec<_>>().join(" "));
+                    }
+                }
+                None => println!("No macro '{}'", args),
+            },
+
+            "macros" => {
+                if macro_library.macros.is_empty() {
+                    println!("No macros (record one with 'record')");
+                }
+                for saved_macro in &macro_library.macros {
+                    let steps: Vec<String> = saved_macro
+                        .steps
+                        .iter()
+                        .map(|s| std::iter::once(s.command.clone()).chain(s.args.iter().cloned()).collect::<Vec<_>>().join(" "))
+                        .collect();
+                    let shortcut = saved_macro.shortcut.as_deref().map(|s| format!(" ({})", s)).unwrap_or_default();
+                    println!("  {}{}: {}", saved_macro.name, shortcut, steps.join("; "));
+                }
             }
 
             "info" => {
@@ -48,8 +185,14 @@ fn main() {
                 println!("Time Signature: {}", song.settings.time_signature);
                 println!("Key: {}", song.settings.key);
                 println!("Notes: {}", song.notes.len());
-                println!("Duration: {:.2}s", song.duration_seconds());
-                println!("Measures: {}", song.measure_count());
+                let info = song.info();
+                println!("Duration: {} (to last barline: {})", info.duration_display, info.total_display);
+                println!("Measures: {}", info.measure_count);
+                if let (Some(low), Some(high)) = (&info.lowest_pitch, &info.highest_pitch) {
+                    println!("Range: {} - {}", low, high);
+                }
+                println!("Notes per measure: {:.1}", info.notes_per_measure);
+                println!("Out of key: {}", info.out_of_key_count);
             }
 
             "title" => {
@@ -72,10 +215,187 @@ fn main() {
                 }
             }
 
+            "pulse" => {
+                if !args.is_empty() {
+                    let groups: Option<Vec<u8>> = if args == "off" {
+                        Some(Vec::new())
+                    } else {
+                        args.split('+').map(|g| g.trim().parse().ok()).collect()
+                    };
+                    match groups {
+                        Some(groups) => {
+                            if let Err(e) = song.settings.time_signature.set_pulse_grouping(&groups) {
+                                println!("Error: {}", e);
+                            }
+                        }
+                        None => println!("Usage: pulse [groups|off] (e.g., pulse 3+3)"),
+                    }
+                }
+                let pulse: Vec<String> =
+                    song.settings.time_signature.pulse_grouping().iter().map(|g| g.to_string()).collect();
+                println!("Pulse grouping: {}", pulse.join("+"));
+            }
+
+            "poly" => {
+                match time::Polyrhythm::parse(args) {
+                    Ok(poly) => {
+                        let ts = &song.settings.time_signature;
+                        println!(
+                            "{}: {} clicks every {} ticks at {:?}",
+                            poly,
+                            poly.beats,
+                            poly.cycle_ticks(ts),
+                            poly.click_ticks(ts)
+                        );
+                    }
+                    Err(e) => println!("Error: {} (usage: poly <n:m>, e.g., poly 3:2)", e),
+                }
+            }
+
+            "rate" => {
+                if !args.is_empty() {
+                    match PlaybackRate::parse(args) {
+                        Ok(rate) => playback_rate = rate,
+                        Err(e) => println!("Error: {} (usage: rate <0.25-2>, e.g., rate 0.5 or rate 75%)", e),
+                    }
+                }
+                let seconds = song.info().total_seconds / playback_rate.value();
+                println!(
+                    "Playback rate: {} ({:.0} BPM heard, song stays at {} BPM; {} long)",
+                    playback_rate,
+                    playback_rate.effective_tempo(song.settings.tempo),
+                    song.settings.tempo,
+                    song::format_duration(seconds)
+                );
+            }
+
+            "loop" => {
+                // "loop 5" loops measure 5; next/prev/extend step from the current loop
+                let result = match args {
+                    "" => Ok(song.view.loop_region),
+                    "next" => song.loop_next_measure(0).map(Some),
+                    "prev" => song.loop_previous_measure(0).map(Some),
+                    "extend" => song.extend_loop_by_measure(0).map(Some),
+                    "clear" => Ok(song.clear_loop().and(None)),
+                    measure => match measure.parse::<u32>() {
+                        Ok(measure) => song.mbt_to_tick(measure, 1, 0).and_then(|tick| song.loop_current_measure(tick)).map(Some),
+                        Err(_) => {
+                            println!("Usage: loop [<measure>|next|prev|extend|clear]");
+                            continue;
+                        }
+                    },
+                };
+                match result {
+                    Ok(Some((start, end))) => {
+                        println!("Looping {} to {}", song.position_to_mbt(start), song.position_to_mbt(end))
+                    }
+                    Ok(None) => println!("Not looping"),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "range" => {
+                let numbers: Vec<u8> = args.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+                let range = match (args, &numbers[..]) {
+                    ("", _) => Ok(song.settings.pitch_range.clone()),
+                    ("off", _) => Ok(None),
+                    (_, &[low, high]) => register::PitchRange::new("", low, high).map(Some),
+                    _ => register::PitchRange::preset(args).map(Some),
+                };
+                match range {
+                    Ok(range) => {
+                        song.set_pitch_range(range);
+                        match &song.settings.pitch_range {
+                            Some(range) => {
+                                let outside = register::out_of_range_notes(&song);
+                                println!("Pitch range: {} ({} notes outside)", range, outside.len());
+                            }
+                            None => println!("Pitch range: off"),
+                        }
+                    }
+                    Err(e) => {
+                        let presets: Vec<String> = register::PitchRange::presets().into_iter().map(|r| r.name).collect();
+                        println!("Error: {} (presets: {})", e, presets.join(", "));
+                    }
+                }
+            }
+
+            "fold" => {
+                // Fold notes into a range by octaves: "fold 60 79", "fold Flute", or the song's range
+                let numbers: Vec<u8> = args.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+                let range = match (args, &numbers[..]) {
+                    ("", _) => song.settings.pitch_range.clone().ok_or_else(|| {
+                        MozartError::InvalidPitch("No pitch range set; give one (e.g., 'fold 60 79')".to_string())
+                    }),
+                    (_, &[low, high]) => register::PitchRange::new("", low, high),
+                    _ => register::PitchRange::preset(args),
+                };
+                match range.and_then(|range| register::fold_into_range(&mut song.notes, range.low, range.high)) {
+                    Ok(folded) => {
+                        for f in &folded {
+                            println!("  Note {}: {} -> {}", f.note_index, f.from, f.to);
+                        }
+                        println!("Folded {} notes", folded.len());
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "rows" => {
+                // Piano roll rows: "rows auto [margin]", "rows C1-C7", or "rows up|down" an octave
+                let current = song.view.pitch_view.unwrap_or_default();
+                let view = match args {
+                    "" => Ok(current),
+                    "up" => Ok(current.shifted(1)),
+                    "down" => Ok(current.shifted(-1)),
+                    _ => PitchView::parse(args).map(|view| PitchView { octave_shift: current.octave_shift, ..view }),
+                };
+                match view {
+                    Ok(view) => {
+                        song.view.pitch_view = Some(view).filter(|v| *v != PitchView::default());
+                        let (low, high) = view.visible_range(&song.notes);
+                        let name = |midi: u8| Pitch::from_midi(midi).map(|p| p.to_string()).unwrap_or_default();
+                        println!("Piano roll rows: {}-{} ({} to {})", name(low), name(high), low, high);
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "dynamics" => {
+                match args {
+                    "" => {}
+                    "on" => song.settings.accent_dynamics = true,
+                    "off" => song.settings.accent_dynamics = false,
+                    _ => println!("Usage: dynamics [on|off]"),
+                }
+                let state = if song.settings.accent_dynamics { "on" } else { "off" };
+                println!("Accent dynamics: {}", state);
+            }
+
+            "instrument" => {
+                if !args.is_empty() {
+                    match Instrument::parse(args) {
+                        Ok(instrument) => song.set_instrument(instrument),
+                        Err(e) => {
+                            let names: Vec<&str> = Instrument::all().iter().map(|i| i.name()).collect();
+                            println!("Error: {} (instruments: {})", e, names.join(", "));
+                        }
+                    }
+                }
+                let instrument = song.settings.instrument;
+                match instrument.gm_program() {
+                    Some(program) => println!("Instrument: {} (MIDI program {})", instrument, program + 1),
+                    None => println!("Instrument: {}", instrument),
+                }
+            }
+
             "time" => {
                 if args.is_empty() {
                     println!("Current time signature: {}", song.settings.time_signature);
                     println!("Accents: {}", song.settings.time_signature.accents);
+                    let groups: Vec<String> =
+                        song.settings.time_signature.groupings().iter().map(|g| g.to_string()).collect();
+                    println!("Groupings: {}", groups.join("+"));
                 } else {
                     match time::TimeSignature::parse(args) {
                         Ok(ts) => {
@@ -87,6 +407,105 @@ fn main() {
                 }
             }
 
+            "track" => {
+                // "track add Bassline", "track 1 instrument strings", "track 1 mute", "track 1 remove"
+                let (first, rest) = args.split_once(' ').unwrap_or((args, ""));
+                let (action, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                let result = match (first, first.parse::<u8>()) {
+                    ("", _) => Ok(()),
+                    ("add", _) => song.add_track(rest).map(|voice| println!("Added track on voice {}", voice)),
+                    (_, Ok(voice)) => match action {
+                        "remove" => song.remove_track(voice).map(|count| println!("Removed the track and {} notes", count)),
+                        "name" => song.rename_track(voice, value),
+                        "instrument" => match value {
+                            "" | "default" => song.set_track_instrument(voice, None),
+                            name => Instrument::parse(name).and_then(|i| song.set_track_instrument(voice, Some(i))),
+                        },
+                        "volume" => match value.parse() {
+                            Ok(volume) => song.set_track_volume(voice, volume),
+                            Err(_) => Err(MozartError::TrackError(format!("Invalid volume: {}", value))),
+                        },
+                        "mute" | "unmute" => song.mute_track(voice, action == "mute"),
+                        "solo" | "unsolo" => song.solo_track(voice, action == "solo"),
+                        _ => Err(MozartError::TrackError(
+                            "Usage: track [add <name> | <voice> remove|name <text>|instrument <name>|volume <0-127>|mute|unmute|solo|unsolo]".to_string(),
+                        )),
+                    },
+                    (other, Err(_)) => Err(MozartError::TrackError(format!("Invalid voice: {}", other))),
+                };
+                if let Err(e) = result {
+                    println!("Error: {}", e);
+                }
+                for track in &song.settings.tracks {
+                    let mut flags = Vec::new();
+                    if track.mute {
+                        flags.push("muted");
+                    }
+                    if track.solo {
+                        flags.push("solo");
+                    }
+                    let line = format!(
+                        "  Voice {:2}: {:<16} {:<8} volume {:3}, {} notes {}",
+                        track.voice,
+                        track.name,
+                        song.voice_instrument(track.voice).to_string(),
+                        track.volume,
+                        song.track_notes(track.voice).len(),
+                        flags.join(" ")
+                    );
+                    println!("{}", line.trim_end());
+                }
+            }
+
+            "meter" => {
+                // "meter 1 7/8" gives voice 1 its own meter; "meter 1 default" follows the song's again
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                match parts.as_slice() {
+                    [] => {
+                        println!("Song: {}", song.settings.time_signature);
+                        for meter in &song.settings.voice_meters {
+                            println!("Voice {}: {} ({})", meter.voice, meter.time_signature, meter.time_signature.accents);
+                        }
+                    }
+                    [voice, ts] => {
+                        let ts = match *ts {
+                            "default" => Ok(None),
+                            ts => time::TimeSignature::parse(ts).map(Some),
+                        };
+                        match (voice.parse::<u8>(), ts) {
+                            (Ok(voice), Ok(ts)) => {
+                                song.set_voice_time_signature(voice, ts);
+                                println!("Voice {} is in {}", voice, song.voice_time_signature(voice));
+                            }
+                            (Err(_), _) => println!("Invalid voice: {}", voice),
+                            (_, Err(e)) => println!("Error: {}", e),
+                        }
+                    }
+                    _ => println!("Usage: meter [<voice> <n/d|default>]"),
+                }
+            }
+
+            "groove-preset" => {
+                // "groove-preset Rachenitsa" loads the meter, accents, tempo, and drums
+                if args.is_empty() {
+                    for preset in presets::groove_presets() {
+                        let drums = if preset.drums.is_some() { ", drums" } else { "" };
+                        println!("  {} ({}, {} BPM{}) - {}", preset.name, preset.time_signature, preset.tempo, drums, preset.description);
+                    }
+                } else {
+                    match presets::load_groove_preset(&mut song, args) {
+                        Ok(preset) => {
+                            let groups: Vec<String> = preset.grouping.iter().map(|g| g.to_string()).collect();
+                            println!("Loaded {}: {} as {} at {} BPM", preset.name, preset.time_signature, groups.join("+"), preset.tempo);
+                            if preset.drums.is_some() {
+                                println!("Drums in voice {}", presets::DRUM_VOICE);
+                            }
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
             "key" => {
                 if args.is_empty() {
                     println!("Current key: {}", song.settings.key);
@@ -106,7 +525,7 @@ fn main() {
                 if args.is_empty() {
                     println!("Current melody: {}", note::format_melody(&song.notes));
                 } else {
-                    match note::parse_melody(args) {
+                    match song.parse_melody(args) {
                         Ok(notes) => {
                             song.clear_notes();
                             song.add_notes(notes);
@@ -117,22 +536,139 @@ fn main() {
                 }
             }
 
+            "rhythm" => {
+                // "rhythm 39" (MIDI), "rhythm Eb2" (a note), or "rhythm default"
+                let pitch = match args {
+                    "" => Ok(song.settings.rhythm_pitch),
+                    "default" => Ok(None),
+                    _ => args.parse::<u8>().or_else(|_| Pitch::parse(args).map(|p| p.midi())).map(Some),
+                };
+                match pitch.and_then(|pitch| song.set_rhythm_pitch(pitch)) {
+                    Ok(()) => println!("Rhythm hits (x) enter at MIDI {}", song.rhythm_pitch()),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
             "notes" => {
                 if song.notes.is_empty() {
                     println!("No notes");
                 } else {
                     for (i, note) in song.notes.iter().enumerate() {
+                        let mut chance = String::new();
+                        if note.probability < Note::ALWAYS_PLAYS {
+                            chance.push_str(&format!(", {}% chance", note.probability));
+                        }
+                        if let Some(take) = note.take {
+                            chance.push_str(&format!(", take {}", take));
+                        }
+                        if let Some(color) = note.color {
+                            chance.push_str(&format!(", {}", color));
+                        }
                         println!(
-                            "  [{}] {} at tick {} (duration {})",
-                            i, note, note.start_tick, note.duration_ticks
+                            "  [{}] {} at tick {} (duration {}{})",
+                            i, note, note.start_tick, note.duration_ticks, chance
                         );
                     }
                 }
             }
 
+            "chance" => {
+                // "chance 3 50" (percent), "chance 3 A" (take lane), "chance 3 -" (always plays)
+                let (index, spec) = args.split_once(' ').unwrap_or((args, ""));
+                match (index.parse::<usize>().ok().filter(|&i| i < song.notes.len()), spec.trim()) {
+                    (Some(i), "-") => {
+                        song.notes[i].take = None;
+                        song.notes[i].set_probability(Note::ALWAYS_PLAYS);
+                        println!("Note {} always plays", i);
+                    }
+                    (Some(i), spec) if !spec.is_empty() => match spec.trim_end_matches('%').parse::<u8>() {
+                        Ok(percent) => {
+                            song.notes[i].set_probability(percent);
+                            println!("Note {} plays on {}% of passes", i, song.notes[i].probability);
+                        }
+                        Err(_) => match Take::parse(spec) {
+                            Ok(take) => {
+                                song.notes[i].take = take;
+                                println!("Note {} is in take {}", i, spec.to_uppercase());
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        },
+                    },
+                    _ => println!("Usage: chance <note index> <percent|A|B|->"),
+                }
+            }
+
+            "color" => {
+                // "color 0,2,5 blue", "color all -", or "color section Chorus orange"
+                let (target, label) = args.rsplit_once(' ').unwrap_or(("", args));
+                match ColorLabel::parse(label) {
+                    Ok(color) if !target.is_empty() => {
+                        if let Some(name) = target.strip_prefix("section ") {
+                            match song.set_section_color(name.trim(), color) {
+                                Ok(()) => println!("Section '{}' is {}", name.trim(), color.map_or("uncolored", |c| c.name())),
+                                Err(e) => println!("Error: {}", e),
+                            }
+                        } else {
+                            let indices: std::result::Result<Vec<usize>, _> = if target == "all" {
+                                Ok((0..song.notes.len()).collect())
+                            } else {
+                                target.split(',').map(|i| i.trim().parse::<usize>()).collect()
+                            };
+                            match indices.map_err(|e| e.to_string()).and_then(|indices| {
+                                song.set_note_color(&indices, color).map_err(|e| e.to_string())
+                            }) {
+                                Ok(changed) => println!("Colored {} notes", changed),
+                                Err(e) => println!("Error: {}", e),
+                            }
+                        }
+                    }
+                    Ok(_) => println!("Usage: color <i,j,...|all|section <marker>> <color|->"),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "freeze" => {
+                let pass = if args.is_empty() { Ok(0) } else { args.parse::<u32>() };
+                match pass {
+                    Ok(pass) => println!("Froze pass {}: {} notes dropped", pass, song.freeze_arrangement(pass)),
+                    Err(_) => println!("Usage: freeze [pass]"),
+                }
+            }
+
+            "flatten" => {
+                if song.has_playback_effects() {
+                    println!("Flattened playback effects: {} notes changed", song.flatten_playback_effects());
+                } else {
+                    println!("No strums, accent dynamics, or track volumes to flatten");
+                }
+            }
+
             "clear" => {
-                song.clear_notes();
-                println!("Notes cleared");
+                match confirm::confirm_or_run(confirm::clear_notes_loss(&song), args == "confirm", || song.clear_notes()) {
+                    Outcome::Done { .. } => println!("Notes cleared"),
+                    Outcome::NeedsConfirmation { summary } => println!("{} Type 'clear confirm' to go ahead.", summary.message),
+                }
+            }
+
+            "delete" => {
+                let mut parts = args.split_whitespace();
+                let range = parts.next().unwrap_or("");
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let confirmed = parts.next() == Some("confirm");
+                match (first.parse::<u32>(), last.parse::<u32>()) {
+                    (Ok(first), Ok(last)) if last >= first => {
+                        let count = last - first + 1;
+                        let loss = confirm::delete_measures_loss(&song, first, count);
+                        match confirm::confirm_or_run(loss, confirmed, || song.delete_measures(first, count)) {
+                            Outcome::Done { result: Ok(removed) } => println!("Deleted {} measures ({} notes)", count, removed),
+                            Outcome::Done { result: Err(e) } => println!("Error: {}", e),
+                            Outcome::NeedsConfirmation { summary } => {
+                                println!("{} Type 'delete {} confirm' to go ahead.", summary.message, range)
+                            }
+                        }
+                    }
+                    _ => println!("Usage: delete <measure>[-<last>] [confirm] (e.g., delete 3-4)"),
+                }
             }
 
             "transpose" => {
@@ -204,7 +740,10 @@ fn main() {
                         format!("{}.mozart.json", args)
                     };
                     match song.save(&path) {
-                        Ok(()) => println!("Saved to {}", path),
+                        Ok(()) => {
+                            saved = song.clone();
+                            println!("Saved to {}", path);
+                        }
                         Err(e) => println!("Error: {}", e),
                     }
                 }
@@ -214,9 +753,15 @@ fn main() {
                 if args.is_empty() {
                     println!("Usage: load <filename>");
                 } else {
-                    match Song::load(args) {
+                    let loaded = if args.ends_with(".mid") || args.ends_with(".midi") {
+                        midi::import_from_midi_file(args)
+                    } else {
+                        Song::load(args)
+                    };
+                    match loaded {
                         Ok(loaded) => {
                             song = loaded;
+                            saved = song.clone();
                             println!("Loaded: {} ({} notes)", song.metadata.title, song.notes.len());
                         }
                         Err(e) => println!("Error: {}", e),
@@ -224,6 +769,66 @@ fn main() {
                 }
             }
 
+            "merge" => {
+                // "merge a.mozart.json b.mid" layers the files on their own voices and MIDI channels;
+                // "merge keep ..." keeps each file's ticks instead of its timing in seconds
+                let (tempo, paths) = match args.split_once(' ').map(|(word, rest)| (merge::TempoAlignment::parse(word), rest)) {
+                    Some((Ok(tempo), rest)) => (tempo, rest),
+                    _ => (merge::TempoAlignment::Align, args),
+                };
+                let paths: Vec<&str> = paths.split_whitespace().collect();
+                if paths.len() < 2 {
+                    println!("Usage: merge [align|keep] <file> <file> ...");
+                } else {
+                    match merge::merge_files(&paths, tempo) {
+                        Ok(merged) => {
+                            song = merged;
+                            let mut voices: Vec<u8> = song.notes.iter().map(|n| n.voice).collect();
+                            voices.sort_unstable();
+                            voices.dedup();
+                            println!("Merged: {} ({} notes in voices {:?})", song.metadata.title, song.notes.len(), voices);
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "split" => {
+                // "split 5 9 13" previews pieces starting at those measures, "split sections" one per
+                // marker; "... save <dir>" writes them as numbered files
+                let (spec, dir) = match args.split_once(" save ") {
+                    Some((spec, dir)) => (spec.trim(), Some(dir.trim())),
+                    None => (args, None),
+                };
+                let plan = if spec == "sections" {
+                    merge::plan_sections(&song)
+                } else {
+                    spec.split_whitespace()
+                        .map(|m| {
+                            let measure = m.parse().map_err(|_| MozartError::ParseError(format!("Invalid measure: {}", m)))?;
+                            song.measure_start_tick(measure)
+                        })
+                        .collect::<std::result::Result<Vec<u32>, MozartError>>()
+                        .and_then(|ticks| merge::plan_split(&song, &ticks))
+                };
+                match plan {
+                    Ok(_) if spec.is_empty() => println!("Usage: split <measure>... | sections [save <dir>]"),
+                    Ok(plan) => {
+                        for (i, piece) in plan.iter().enumerate() {
+                            println!("  {:2}. {} from {} ({} notes)", i + 1, piece.title, piece.start_position, piece.notes);
+                        }
+                        if let Some(dir) = dir {
+                            let stem = song.metadata.title.to_lowercase().replace(' ', "-");
+                            match merge::save_pieces(&merge::split_song(&song, &plan), dir, &stem) {
+                                Ok(paths) => println!("Saved {} files to {}", paths.len(), dir),
+                                Err(e) => println!("Error: {}", e),
+                            }
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
             "midi" => {
                 if args.is_empty() {
                     println!("Usage: midi <filename>");
@@ -233,8 +838,136 @@ fn main() {
                     } else {
                         format!("{}.mid", args)
                     };
-                    match midi::export_to_midi_file(&song, &path) {
-                        Ok(()) => println!("Exported MIDI to {}", path),
+                    match wait_for_export(&export_events, export::spawn_midi_export(&jobs, song.clone(), &path)) {
+                        Ok(_) => println!("Exported MIDI to {}", path),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "wav" => {
+                // "wav <file> 0.5" renders at that speed; otherwise at the practice speed
+                let (file, rate) = match args.rsplit_once(' ').map(|(file, rate)| (file, PlaybackRate::parse(rate))) {
+                    Some((file, Ok(rate))) => (file.trim(), rate),
+                    _ => (args, playback_rate),
+                };
+                if file.is_empty() {
+                    println!("Usage: wav <filename> [rate]");
+                } else {
+                    let path = if file.ends_with(".wav") { file.to_string() } else { format!("{}.wav", file) };
+                    let id = export::spawn_practice_audio_export(&jobs, song.clone(), &path, rate);
+                    match wait_for_export(&export_events, id) {
+                        Ok(result) => println!("Exported {} practice audio to {} ({})", rate, path, song::format_duration(result.duration_seconds)),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "lyrics" => {
+                if args.is_empty() {
+                    println!("{}", song.lyrics_text());
+                } else {
+                    println!("Placed {} syllables", song.set_lyrics(args));
+                }
+            }
+
+            "annotate" => {
+                // "annotate 960 breathe here", or "annotate 0-1920 crescendo" for a range
+                let (at, text) = args.split_once(' ').unwrap_or((args, ""));
+                let (tick, end_tick) = match at.split_once('-') {
+                    Some((tick, end)) => (tick.parse::<u32>(), end.parse::<u32>().map(Some)),
+                    None => (at.parse::<u32>(), Ok(None)),
+                };
+                match (tick, end_tick) {
+                    (Ok(tick), Ok(end_tick)) => match song.add_annotation(tick, end_tick, text) {
+                        Ok(index) => println!("Added annotation {}", index),
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    _ => println!("Usage: annotate <tick>[-<end tick>] <text>"),
+                }
+            }
+
+            "annotations" => {
+                if song.annotations.is_empty() {
+                    println!("No annotations");
+                }
+                for (i, annotation) in song.annotations.iter().enumerate() {
+                    let at = song.position_to_mbt(annotation.tick);
+                    match annotation.end_tick {
+                        Some(end) => println!("  [{}] {} to {}: {}", i, at, song.position_to_mbt(end), annotation.text),
+                        None => println!("  [{}] {}: {}", i, at, annotation.text),
+                    }
+                }
+            }
+
+            "unannotate" => {
+                match args.parse::<usize>().ok().and_then(|i| song.remove_annotation(i)) {
+                    Some(annotation) => println!("Removed '{}'", annotation.text),
+                    None => println!("Usage: unannotate <annotation index>"),
+                }
+            }
+
+            "repeats" => {
+                // "repeats add 5-8 x3", "repeats ending 8 1", "repeats sign segno 3", "repeats jump D.C. al Fine 16"
+                let (sub, spec) = args.split_once(' ').unwrap_or((args, ""));
+                let mut repeats = song.repeats.clone();
+                let edited = match sub {
+                    "" => Ok(()),
+                    "add" => Repeat::parse(spec).and_then(|repeat| repeats.add_repeat(repeat)),
+                    "ending" => Ending::parse(spec).and_then(|ending| repeats.add_ending(ending)),
+                    "sign" => RepeatSign::parse(spec).and_then(|(sign, measure)| repeats.place_sign(sign, measure)),
+                    "jump" if spec.trim() == "-" => repeats.set_jump(None),
+                    "jump" => Jump::parse(spec).and_then(|jump| repeats.set_jump(Some(jump))),
+                    "remove" => match spec.trim().parse::<u32>() {
+                        Ok(measure) => {
+                            if !repeats.remove_at(measure) {
+                                println!("No repeat marks at measure {}", measure);
+                            }
+                            Ok(())
+                        }
+                        Err(_) => Err(MozartError::ParseError(format!("Invalid measure: {}", spec))),
+                    },
+                    "clear" => {
+                        repeats = RepeatStructure::default();
+                        Ok(())
+                    }
+                    _ => Err(MozartError::ParseError(format!("Unknown repeats command: {}", sub))),
+                };
+                let edited = edited.and_then(|()| if repeats == song.repeats { Ok(()) } else { song.set_repeats(repeats) });
+                match edited {
+                    Ok(()) => print_repeats(&song),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "lrc" => {
+                // "lrc <file> lines" writes plain LRC without syllable stamps
+                let (file, lines_only) = match args.strip_suffix(" lines") {
+                    Some(file) => (file.trim(), true),
+                    None => (args, false),
+                };
+                if file.is_empty() {
+                    println!("Usage: lrc <filename> [lines]");
+                } else {
+                    let path = if file.ends_with(".lrc") { file.to_string() } else { format!("{}.lrc", file) };
+                    match lrc::LrcExporter::new().with_syllable_timing(!lines_only).export_to_file(&song, &path) {
+                        Ok(()) => println!("Exported lyrics to {}", path),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "chordpro" => {
+                if args.is_empty() {
+                    println!("Usage: chordpro <filename>");
+                } else {
+                    let path = if args.ends_with(".cho") || args.ends_with(".chordpro") {
+                        args.to_string()
+                    } else {
+                        format!("{}.cho", args)
+                    };
+                    match chordpro::export_chordpro(&song, &path) {
+                        Ok(()) => println!("Exported chords and lyrics to {}", path),
                         Err(e) => println!("Error: {}", e),
                     }
                 }
@@ -247,6 +980,23 @@ fn main() {
                 }
             }
 
+            "pipeline" => {
+                if args.is_empty() {
+                    println!("Usage: pipeline <program.json>");
+                } else {
+                    match std::fs::read_to_string(args) {
+                        Ok(program) => match run_pipeline(&program, song.clone()) {
+                            Ok(result) => {
+                                song = result;
+                                println!("Pipeline done: {} notes", song.notes.len());
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        },
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
             "scales" => {
                 println!("Available scales:");
                 for scale_type in scale::ScaleType::all() {
@@ -254,6 +1004,210 @@ fn main() {
                 }
             }
 
+            "worksheet" => {
+                let mut parts = args.split_whitespace();
+                match parts.next() {
+                    None => println!("Usage: worksheet <dir> [easy|medium|hard] [count]"),
+                    Some(dir) => {
+                        // Exercises use the current song's key, meter, and tempo
+                        let mut config = worksheet::WorksheetConfig {
+                            time_signature: song.settings.time_signature.to_string(),
+                            key: song.settings.key.to_string(),
+                            tempo: song.settings.tempo,
+                            ..Default::default()
+                        };
+                        let difficulty = parts.next().map(worksheet::Difficulty::parse).transpose();
+                        let count = parts.next().map(|n| n.parse::<usize>()).transpose();
+                        match (difficulty, count) {
+                            (Ok(difficulty), Ok(count)) => {
+                                config.difficulty = difficulty.unwrap_or(config.difficulty);
+                                config.count = count.unwrap_or(config.count);
+                                match worksheet::generate_worksheet(&config, dir) {
+                                    Ok(report) => println!(
+                                        "Wrote {} exercises and {}",
+                                        report.midi_files.len(),
+                                        report.pdf.display()
+                                    ),
+                                    Err(e) => println!("Error: {}", e),
+                                }
+                            }
+                            (Err(e), _) => println!("Error: {}", e),
+                            (_, Err(_)) => println!("Invalid count"),
+                        }
+                    }
+                }
+            }
+
+            "verify-export" => {
+                // verify-export [dir] [--bless]
+                let bless = args.split_whitespace().any(|a| a == "--bless");
+                let dir = args.split_whitespace().find(|a| *a != "--bless").unwrap_or(corpus::CORPUS_DIR);
+                match corpus::verify_export(dir, bless) {
+                    Ok(reports) => {
+                        for report in &reports {
+                            match &report.verdict {
+                                corpus::Verdict::Match => println!("  ok       {}", report.name),
+                                corpus::Verdict::Blessed => println!("  blessed  {}", report.name),
+                                corpus::Verdict::Missing => println!("  MISSING  {} (no .mid; run with --bless)", report.name),
+                                corpus::Verdict::Changed { offset, expected_len, actual_len } => println!(
+                                    "  CHANGED  {}: first difference at byte {} ({} bytes expected, {} written)",
+                                    report.name, offset, expected_len, actual_len
+                                ),
+                            }
+                            if let Some(difference) = &report.round_trip {
+                                println!("  ROUND-TRIP {}: {}", report.name, difference);
+                            }
+                        }
+                        let failed = reports.iter().filter(|r| !r.passed()).count();
+                        println!("{} fixtures, {} failed", reports.len(), failed);
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "theory" => {
+                if args.is_empty() {
+                    println!("Topics:");
+                    for topic in TheoryTopic::ALL {
+                        println!("  - {}", topic.title());
+                    }
+                } else {
+                    match TheoryTopic::parse(args) {
+                        Ok(topic) => print_theory(&get_theory_reference(topic)),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "grammar" => {
+                let grammar = get_notation_grammar();
+                match args {
+                    "json" => match serde_json::to_string_pretty(&grammar) {
+                        Ok(json) => println!("{}", json),
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    _ => println!("{}", grammar.ebnf),
+                }
+            }
+
+            "groove" => {
+                let words: Vec<&str> = args.split_whitespace().collect();
+                if words.first() == Some(&"apply") {
+                    match &groove_template {
+                        Some(template) => {
+                            let indices: Vec<usize> = (0..song.notes.len()).collect();
+                            println!("Grooved {} notes", song.apply_groove(&indices, template));
+                        }
+                        None => println!("No groove yet; extract one with 'groove [subdivisions] [file.mid]'"),
+                    }
+                    continue;
+                }
+
+                let subdivisions = words.first().and_then(|w| w.parse().ok()).unwrap_or(4);
+                let source = match words.iter().find(|w| w.ends_with(".mid") || w.ends_with(".midi")) {
+                    Some(path) => midi::import_from_midi_file(path),
+                    None => Ok(song.clone()),
+                };
+                let template = source.and_then(|source| {
+                    groove::extract_groove(&source.notes, source.settings.time_signature.ticks_per_beat(), subdivisions)
+                });
+                match template {
+                    Ok(template) => {
+                        for (i, slot) in template.slots.iter().enumerate() {
+                            println!(
+                                "  {}/{}  {:+4} ticks  {:+4} velocity  ({} notes)",
+                                i + 1,
+                                template.subdivisions,
+                                slot.timing_offset_ticks,
+                                slot.velocity_offset,
+                                slot.note_count
+                            );
+                        }
+                        groove_template = Some(template);
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "audition" => {
+                // Scale types can be two words, so an optional direction comes last
+                let words: Vec<&str> = args.split_whitespace().collect();
+                let notes = match words.as_slice() {
+                    ["scale", root, rest @ ..] if !rest.is_empty() => {
+                        let (scale_type, direction) = match audition::ScaleDirection::parse(rest[rest.len() - 1]) {
+                            Ok(direction) if rest.len() > 1 => (rest[..rest.len() - 1].join(" "), direction),
+                            _ => (rest.join(" "), audition::ScaleDirection::Both),
+                        };
+                        PitchClass::parse(root).and_then(|root| {
+                            let scale_type = ScaleType::parse(&scale_type)?;
+                            Ok(audition::scale_audition(root, scale_type, direction, song.settings.tempo))
+                        })
+                    }
+                    ["chord", root, rest @ ..] => PitchClass::parse(root).and_then(|root| {
+                        let quality = rest.first().filter(|q| q.parse::<u8>().is_err()).copied().unwrap_or("");
+                        let inversion = rest.last().and_then(|i| i.parse().ok()).unwrap_or(0);
+                        audition::chord_audition(root, quality, inversion, song.settings.tempo)
+                    }),
+                    ["degree", degree, rest @ ..] => match degree.parse::<u8>() {
+                        Ok(degree) => {
+                            let seventh = rest.first() == Some(&"7");
+                            audition::diatonic_chord_audition(&song.settings.key, degree, seventh, song.settings.tempo)
+                        }
+                        Err(_) => Err(MozartError::ParseError(format!("Not a scale degree: {}", degree))),
+                    },
+                    _ => {
+                        println!("Usage: audition scale <root> <type> [up|down|both] | audition chord <root> [quality] [inversion] | audition degree <1-7> [7]");
+                        continue;
+                    }
+                };
+                match notes {
+                    Ok(notes) => {
+                        for note in notes {
+                            let name = Pitch::from_midi(note.pitch).map(|p| p.to_string()).unwrap_or_default();
+                            println!("  {:>5.2}s  {:<4} {:.2}s", note.start_seconds, name, note.duration_seconds);
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "register" => {
+                let bucket_ticks = if args.is_empty() {
+                    song.settings.time_signature.ticks_per_measure()
+                } else {
+                    match args.parse() {
+                        Ok(ticks) => ticks,
+                        Err(_) => {
+                            println!("Usage: register [bucket_ticks]");
+                            continue;
+                        }
+                    }
+                };
+                let name = |pitch: u8| Pitch::from_midi(pitch).map(|p| p.to_string()).unwrap_or_default();
+                match register::pitch_histogram(&song, bucket_ticks) {
+                    Ok(histogram) => {
+                        for bucket in &histogram.buckets {
+                            let used: Vec<u8> = (histogram.lowest_pitch..=histogram.highest_pitch)
+                                .zip(&bucket.ticks)
+                                .filter(|(_, &ticks)| ticks > 0)
+                                .map(|(pitch, _)| pitch)
+                                .collect();
+                            match (used.first(), used.last(), bucket.mean_pitch) {
+                                (Some(&low), Some(&high), Some(mean)) => println!(
+                                    "  {:>6}  {:<4}- {:<4} mean {}",
+                                    bucket.start_tick,
+                                    name(low),
+                                    name(high),
+                                    name(mean.round() as u8)
+                                ),
+                                _ => println!("  {:>6}  (silent)", bucket.start_tick),
+                            }
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
             "demo" => {
                 println!("Loading demo melody...");
                 song = Song::with_title("Demo Song");
@@ -267,6 +1221,664 @@ fn main() {
                 println!("Melody: {}", note::format_melody(&song.notes));
             }
 
+            "legato" | "gap" => {
+                let ticks = if args.is_empty() { Ok(0) } else { args.parse::<u32>() };
+                match ticks {
+                    Ok(ticks) => {
+                        let all: Vec<usize> = (0..song.notes.len()).collect();
+                        let changed = if cmd == "legato" {
+                            song.make_legato(&all, ticks)
+                        } else {
+                            song.add_gap(&all, ticks)
+                        };
+                        println!("Adjusted {} notes", changed);
+                    }
+                    Err(_) => println!("Usage: {} [ticks]", cmd),
+                }
+            }
+
+            "voicing" => match voicing::Voicing::parse(args) {
+                Ok(voicing) => {
+                    let all: Vec<usize> = (0..song.notes.len()).collect();
+                    println!("{}: {} chords changed", voicing, song.voice_chords(&all, voicing));
+                }
+                Err(_) => println!("Usage: voicing <up|down|open|close>"),
+            },
+
+            "ladder" => {
+                println!("Chords of {}:", song.settings.key);
+                for rung in harmony::diatonic_chords(&song.settings.key) {
+                    println!(
+                        "  {}. {:<6} {:<6} {:<8} {:<10} {}",
+                        rung.degree,
+                        rung.triad.numeral,
+                        rung.triad.symbol,
+                        rung.seventh.numeral,
+                        rung.seventh.symbol,
+                        rung.tone_names.join(" ")
+                    );
+                }
+            }
+
+            "suggest" => {
+                let progression: Vec<String> = args.split_whitespace().map(String::from).collect();
+                match harmony::suggest_next_chords(&progression, &song.settings.key) {
+                    Ok(suggestions) => {
+                        for s in suggestions {
+                            println!("  {:<6} {:<6} {:>5.1}  {}", s.chord.numeral, s.chord.symbol, s.score, s.reason);
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "reharmonize" => {
+                let mut parts = args.split_whitespace().map(|p| p.parse::<u32>());
+                match (parts.next(), parts.next().unwrap_or(Ok(1))) {
+                    (Some(Ok(measure)), Ok(chords_per_measure)) => {
+                        let constraints = harmony::ReharmonizeConstraints { chords_per_measure, ..Default::default() };
+                        match harmony::reharmonize_measure(&song, measure, &constraints) {
+                            Ok(options) if options.is_empty() => println!("No diatonic chords hold that melody"),
+                            Ok(options) => {
+                                for option in options {
+                                    let chords: Vec<&str> =
+                                        option.chords.iter().map(|c| c.chord.symbol.as_str()).collect();
+                                    println!("  {:<20} {:>5.1}", chords.join(" "), option.score);
+                                }
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    }
+                    _ => println!("Usage: reharmonize <measure> [chords per measure]"),
+                }
+            }
+
+            "modulate" => {
+                if let Some(n) = args.strip_prefix("apply") {
+                    match n.trim().parse::<usize>().ok().and_then(|n| modulations.get(n.wrapping_sub(1))) {
+                        Some(plan) => match modulation::apply_modulation(&mut song, plan) {
+                            Ok(count) => {
+                                println!("Added {} notes; marked {} at measure {}", count, plan.to, plan.end_measure)
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        },
+                        None => println!("Usage: modulate apply <n> (after listing routes with 'modulate <key>')"),
+                    }
+                } else {
+                    // An optional start measure after the key
+                    let (key, measure) = match args.rsplit_once(' ').map(|(k, m)| (k, m.parse::<u32>())) {
+                        Some((key, Ok(measure))) => (key, measure),
+                        _ => (args, song.measure_count() + 1),
+                    };
+                    match scale::Scale::parse(key)
+                        .and_then(|to| modulation::plan_modulation(&song.settings.key, &to, measure))
+                    {
+                        Ok(plans) => {
+                            for (i, plan) in plans.iter().enumerate() {
+                                let chords: Vec<&str> = plan.chords.iter().map(|c| c.chord.symbol.as_str()).collect();
+                                println!(
+                                    "  {}. {:<24} m{}-{}  {}",
+                                    i + 1,
+                                    chords.join(" "),
+                                    plan.start_measure,
+                                    plan.end_measure,
+                                    plan.description
+                                );
+                            }
+                            modulations = plans;
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "vary" => {
+                if let Some(n) = args.strip_prefix("apply") {
+                    match n.trim().parse::<usize>().ok().and_then(|n| variations.get(n.wrapping_sub(1))) {
+                        Some(chosen) => match variation::apply_variation(&mut song, &varied_phrase, chosen) {
+                            Ok(count) => {
+                                println!("Wrote {} notes", count);
+                                variations.clear();
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        },
+                        None => println!("Usage: vary apply <n> (after listing variations with 'vary [count]')"),
+                    }
+                } else {
+                    let count = args.parse().unwrap_or(3);
+                    let phrase: Vec<Note> = song.notes.iter().filter(|n| n.voice == 0).cloned().collect();
+                    let params = variation::VariationParams::default();
+                    match variation::generate_variations(&phrase, &song.settings.key, count, &params) {
+                        Ok(candidates) => {
+                            for (i, candidate) in candidates.iter().enumerate() {
+                                println!("  {}. {}", i + 1, candidate.description);
+                                println!("     {}", note::format_melody(&candidate.notes));
+                            }
+                            variations = candidates;
+                            varied_phrase = phrase;
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "counterpoint" => {
+                let mut config = counterpoint::CounterpointConfig::default();
+                for arg in args.split_whitespace() {
+                    match arg {
+                        "above" => config.above = true,
+                        "below" => config.above = false,
+                        voice => match voice.parse() {
+                            Ok(voice) => config.voice = voice,
+                            Err(_) => println!("Ignoring '{}'", voice),
+                        },
+                    }
+                }
+                match counterpoint::write_counterpoint(&mut song, &config) {
+                    Ok(report) => {
+                        println!(
+                            "Wrote {} notes into voice {} ({:.0}% contrary motion)",
+                            report.notes, report.voice, report.contrary_motion
+                        );
+                        for relaxation in &report.relaxations {
+                            println!("  Relaxed: {}", relaxation.message);
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "continue" => {
+                let mut config = continuation::ContinuationConfig::default();
+                let mut model = continuation::MelodyModel::bundled();
+                for arg in args.split_whitespace() {
+                    match arg.parse() {
+                        Ok(measures) => config.measures = measures,
+                        Err(_) => match model.learn_dir(arg) {
+                            Ok(count) => println!("Learned {} songs from {}", count, arg),
+                            Err(e) => println!("Error: {}", e),
+                        },
+                    }
+                }
+                model.learn(&song);
+                match continuation::continue_melody(&song, &model, &config) {
+                    Ok(continuation) => {
+                        println!("Continuation: {}", note::format_melody(&continuation.notes));
+                        match continuation::apply_continuation(&mut song, &continuation) {
+                            Ok(count) => println!("Added {} notes", count),
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "compare" => {
+                // "compare take2.mid alternate take2-diff.wav" renders the comparison
+                let mut parts = args.split_whitespace();
+                let (file, mode, wav) = (parts.next(), parts.next(), parts.next());
+                let Some(file) = file else {
+                    println!("Usage: compare <file> [together|alternate] [out.wav]");
+                    continue;
+                };
+                let other = if file.ends_with(".mid") || file.ends_with(".midi") {
+                    midi::import_from_midi_file(file)
+                } else {
+                    Song::load(file)
+                };
+                let comparison = mode
+                    .map_or(Ok(ComparisonMode::default()), ComparisonMode::parse)
+                    .and_then(|mode| other.map(|other| compare_versions(&song, &other, mode)));
+                match comparison {
+                    Ok(comparison) => {
+                        let changed = comparison.changed_measures();
+                        if changed.is_empty() {
+                            println!("The versions are the same");
+                        } else {
+                            let measures: Vec<String> = changed.iter().map(|m| m.to_string()).collect();
+                            println!("Measures that differ: {}", measures.join(", "));
+                        }
+                        if let Some(wav) = wav {
+                            match render::render_song(&comparison.to_song(&song), render::DEFAULT_SAMPLE_RATE) {
+                                Ok(audio) => match std::fs::write(wav, audio.to_wav()) {
+                                    Ok(()) => println!("Rendered the comparison to {} ({})", wav, song::format_duration(audio.duration_seconds())),
+                                    Err(e) => println!("Error: {}", e),
+                                },
+                                Err(e) => println!("Error: {}", e),
+                            }
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "similar" => {
+                // "similar other.mid" compares the melodies; "similar dir library/" ranks a library
+                if let Some(dir) = args.strip_prefix("dir ") {
+                    match similarity::similar_in_dir(&song, dir.trim()) {
+                        Ok(matches) => {
+                            for m in matches.iter().take(10) {
+                                println!("  {:5.1}%  {} ({})", m.report.score, m.title, m.path);
+                            }
+                            if matches.is_empty() {
+                                println!("No songs to compare in {}", dir.trim());
+                            }
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                } else if args.is_empty() {
+                    println!("Usage: similar <file> | similar dir <directory>");
+                } else {
+                    let other = if args.ends_with(".mid") || args.ends_with(".midi") {
+                        midi::import_from_midi_file(args)
+                    } else {
+                        Song::load(args)
+                    };
+                    match other.and_then(|other| similarity::similarity(&song, &other)) {
+                        Ok(report) => {
+                            println!(
+                                "Similarity: {:.1}% (intervals {:.1}%, rhythm {:.1}%)",
+                                report.score, report.interval_similarity, report.rhythm_similarity
+                            );
+                            match report.region {
+                                Some(region) => println!(
+                                    "Best match: {} notes from {} here and {} there ({:+} semitones)",
+                                    region.notes, region.a_start_position, region.b_start_position, region.transposition
+                                ),
+                                None => println!("No matching passage"),
+                            }
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
+            "outline" => {
+                let config = melody::MelodyConfig { chart: args.to_string(), ..melody::MelodyConfig::default() };
+                match melody::generate_melody(&mut song, &config) {
+                    Ok(report) => println!(
+                        "Wrote {} notes into voice {} ({} chord tones, {} syncopated)",
+                        report.notes, report.voice, report.chord_tones, report.syncopated
+                    ),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "chart" => match chart::import_chord_chart(&mut song, args) {
+                Ok(chords) => {
+                    let measures = chords.last().map_or(0, |c| c.measure);
+                    let symbols: Vec<&str> = chords.iter().map(|c| c.symbol.as_str()).collect();
+                    println!("Imported {} chords over {} measures: {}", chords.len(), measures, symbols.join(" "));
+                }
+                Err(e) => println!("Error: {}", e),
+            },
+
+            "chord" => {
+                // "chord 3 G/B" (measure 3), "chord 3.3 Am7", "chord 3 -" to remove
+                let mut parts = args.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (None, _) if song.chords.is_empty() => println!("No chords"),
+                    (None, _) => {
+                        for chord in &song.chords {
+                            println!("  {}: {}", song.position_to_mbt(chord.tick), chord.symbol);
+                        }
+                    }
+                    (Some(at), Some(symbol)) => {
+                        let tick = MusicalPosition::parse(at).and_then(|p| song.mbt_to_tick(p.measure, p.beat, p.tick));
+                        match (tick, symbol) {
+                            (Ok(tick), "-") => match song.remove_chord(tick) {
+                                Some(chord) => println!("Removed {}", chord.symbol),
+                                None => println!("No chord at {}", at),
+                            },
+                            (Ok(tick), symbol) => match song.set_chord(tick, symbol) {
+                                Ok(chord) => println!("{} at {}: pitch classes {:?}", symbol, at, chord.pitch_classes()),
+                                Err(e) => println!("Error: {}", e),
+                            },
+                            (Err(e), _) => println!("Error: {}", e),
+                        }
+                    }
+                    _ => println!("Usage: chord [<measure>[.<beat>] <symbol>|-]"),
+                }
+            }
+
+            "pattern" | "patterns" => {
+                // "pattern new A 1 2", "pattern chain A A B A", "pattern move 3 1"
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                let result = match parts.as_slice() {
+                    [] => {
+                        for (i, pattern) in song.patterns.iter().enumerate() {
+                            let measures = pattern.length_ticks as f32 / song.settings.time_signature.ticks_per_measure() as f32;
+                            println!("  {}. {}: {} notes, {} measures", i + 1, pattern.name, pattern.notes.len(), measures);
+                        }
+                        match song.pattern_chain.is_empty() {
+                            true if song.patterns.is_empty() => println!("No patterns"),
+                            true => println!("No chain"),
+                            false => println!("Chain: {} ({} ticks)", song.pattern_chain.join(" "), song.pattern_chain_ticks()),
+                        }
+                        Ok(())
+                    }
+                    ["new", name, measure, rest @ ..] => match (measure.parse(), rest.first().map_or(Ok(1), |c| c.parse())) {
+                        (Ok(measure), Ok(count)) => song
+                            .create_pattern(name, measure, count)
+                            .map(|p| println!("Pattern {}: {} notes cut from measures {}-{}", p.name, p.notes.len(), measure, measure + count.max(1) - 1)),
+                        _ => Err(MozartError::ParseError(format!("Bad measures: {}", parts[2..].join(" ")))),
+                    },
+                    ["clone", name, new_name] => song.clone_pattern(name, new_name).map(|p| println!("Cloned {} as {}", name, p.name)),
+                    ["move", from, to] => match (from.parse::<usize>(), to.parse::<usize>()) {
+                        (Ok(from), Ok(to)) if from > 0 && to > 0 => song.move_pattern(from - 1, to - 1).map(|_| println!("Moved pattern {} to {}", from, to)),
+                        _ => Err(MozartError::ParseError("Pattern positions start at 1".to_string())),
+                    },
+                    ["rm", name] => song
+                        .remove_pattern(name)
+                        .map(|pattern| println!("Removed {}", pattern.name))
+                        .ok_or_else(|| MozartError::InvalidEdit(format!("Unknown pattern: {}", name))),
+                    ["chain", "-"] => song.set_pattern_chain("").map(|_| println!("Chain cleared")),
+                    ["chain", names @ ..] if !names.is_empty() => {
+                        song.set_pattern_chain(&names.join(" ")).map(|_| println!("Chain: {} ({} ticks)", song.pattern_chain.join(" "), song.pattern_chain_ticks()))
+                    }
+                    _ => {
+                        println!("Usage: pattern [new <name> <m> [n]|clone <name> <new>|move <from> <to>|rm <name>|chain <names>|-]");
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    println!("Error: {}", e);
+                }
+            }
+
+            "validate" => {
+                let issues = validate_song(&song);
+                if issues.is_empty() {
+                    println!("No issues found");
+                } else {
+                    for issue in &issues {
+                        println!("  [{}] {}", issue.kind, issue.message);
+                    }
+                    println!("{} issues found. Use 'repair' to fix them.", issues.len());
+                }
+            }
+
+            "repair" => {
+                let options = RepairOptions {
+                    stray_notes: args == "all",
+                    ..RepairOptions::default()
+                };
+                let report = repair_song(&mut song, &options);
+                println!("Removed {} notes, modified {} notes", report.removed, report.modified);
+            }
+
+            "cleanup" => {
+                let report = cleanup_song(&mut song, args != "apply");
+                for &i in &report.duplicates {
+                    println!("  Note {} duplicates an earlier note", i);
+                }
+                for merge in &report.merged {
+                    println!("  Note {} merges into note {}", merge.removed, merge.kept);
+                }
+                for &i in &report.zero_length {
+                    println!("  Note {} has zero length", i);
+                }
+                if report.dry_run && report.removed() > 0 {
+                    println!("{} notes to remove. Use 'cleanup apply' to remove them.", report.removed());
+                } else {
+                    println!("Removed {} notes", report.removed());
+                }
+            }
+
+            "normalize" => {
+                let range: Vec<u8> = args.split(|c: char| c == '-' || c.is_whitespace()).filter_map(|s| s.parse().ok()).collect();
+                let [min, max] = range[..] else {
+                    println!("Usage: normalize <min>-<max>");
+                    continue;
+                };
+                match normalize_velocities(&mut song, min, max) {
+                    Ok(changed) => println!("Normalized {} velocities to {}-{}", changed, min, max),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "compress" => {
+                let mut parts = args.split_whitespace();
+                let (Some(Ok(ratio)), Some(Ok(threshold))) =
+                    (parts.next().map(str::parse::<f32>), parts.next().map(str::parse::<u8>))
+                else {
+                    println!("Usage: compress <ratio> <threshold>");
+                    continue;
+                };
+                match compress_velocities(&mut song, ratio, threshold) {
+                    Ok(changed) => println!("Compressed {} velocities above {}", changed, threshold),
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "tutorial" => {
+                match args {
+                    "" | "status" => {}
+                    "start" => tutorial = Some(Tutorial::new(&song)),
+                    "stop" => tutorial = None,
+                    "skip" => {
+                        if let Some(t) = tutorial.as_mut() {
+                            t.skip(&song);
+                        }
+                    }
+                    "next" => {
+                        if let Some(Err(e)) = tutorial.as_mut().map(|t| t.next(&song)) {
+                            println!("{}", e);
+                        }
+                    }
+                    _ => println!("Usage: tutorial [start|next|skip|stop]"),
+                }
+                match &tutorial {
+                    Some(t) => {
+                        let state = t.state(&song);
+                        let done = if state.completed { " (done - type 'tutorial next')" } else { "" };
+                        if state.step.is_some() {
+                            println!("Step {}/{}: {}{}", state.index + 1, state.total, state.title, done);
+                        } else {
+                            println!("{}", state.title);
+                        }
+                        println!("  {}", state.instruction);
+                    }
+                    None => println!("Tutorial not running (type 'tutorial start')"),
+                }
+            }
+
+            "commands" => {
+                for m in search_commands(args) {
+                    let shortcut = m.command.shortcut.map(|s| format!(" ({})", s)).unwrap_or_default();
+                    println!("  {:<22} {}{} - {}", m.command.id, m.command.name, shortcut, m.command.description);
+                }
+            }
+
+            "jam" => {
+                // "jam host <name> [seconds]" plays the song's tempo from the start;
+                // "jam listen [seconds]" prints what the first master heard is doing
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                let seconds = |arg: Option<&&str>| arg.and_then(|s| s.parse::<u64>().ok()).unwrap_or(10);
+                let mut socket = match jam::JamSocket::lan() {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        println!("Error: {}", e);
+                        continue;
+                    }
+                };
+                let started = std::time::Instant::now();
+                let elapsed_ms = || started.elapsed().as_millis() as u64;
+                match parts.first().copied() {
+                    Some("host") if parts.len() >= 2 => {
+                        let (session, duration) = (parts[1], seconds(parts.get(2)));
+                        let start = jam::TransportState {
+                            playing: true,
+                            tempo: song.settings.tempo,
+                            tick: 0,
+                            song_title: song.metadata.title.clone(),
+                        };
+                        println!("Hosting '{}' at {} BPM for {}s", session, start.tempo, duration);
+                        while elapsed_ms() < duration * 1000 {
+                            let state = jam::TransportState { tick: start.position_after(elapsed_ms()), ..start.clone() };
+                            if let Err(e) = socket.send_transport(session, &state) {
+                                println!("Error: {}", e);
+                                break;
+                            }
+                            std::thread::sleep(std::time::Duration::from_millis(jam::JAM_INTERVAL_MS));
+                        }
+                        socket.send_bye(session).ok();
+                    }
+                    Some("listen") | None => {
+                        let duration = seconds(parts.get(1));
+                        let mut follower = jam::JamFollower::new();
+                        let mut printed_ms = None;
+                        while elapsed_ms() < duration * 1000 {
+                            if let Err(e) = socket.poll(&mut follower, elapsed_ms()) {
+                                println!("Error: {}", e);
+                                break;
+                            }
+                            if follower.following().is_none() {
+                                if let Some(first) = follower.sessions(elapsed_ms()).first().map(|s| s.to_string()) {
+                                    follower.follow(Some(&first));
+                                }
+                            }
+                            let due = printed_ms.is_none_or(|at| elapsed_ms() >= at + 1000);
+                            if let Some(state) = follower.transport(elapsed_ms()).filter(|_| due) {
+                                printed_ms = Some(elapsed_ms());
+                                println!(
+                                    "  {}: '{}' {} at {} BPM, {}",
+                                    follower.following().unwrap_or_default(),
+                                    state.song_title,
+                                    if state.playing { "playing" } else { "stopped" },
+                                    state.tempo,
+                                    song.settings.time_signature.position_at_tick(state.tick)
+                                );
+                            }
+                            std::thread::sleep(std::time::Duration::from_millis(20));
+                        }
+                        if follower.following().is_none() {
+                            println!("No jam sessions heard");
+                        }
+                    }
+                    _ => println!("Usage: jam host <name> [seconds] | jam listen [seconds]"),
+                }
+            }
+
+            "watch" => {
+                // "watch <folder> <library> [ms]" imports MIDI files exported into the
+                // folder in the background; "watch stop" ends it
+                let parts: Vec<&str> = args.split_whitespace().collect();
+                match parts.as_slice() {
+                    [] => match &folder_watch {
+                        Some(handle) => println!("Watching {}", handle.folder().display()),
+                        None => println!("Not watching a folder"),
+                    },
+                    ["stop"] => match folder_watch.take() {
+                        Some(handle) => {
+                            println!("Stopped watching {}", handle.folder().display());
+                            handle.stop();
+                        }
+                        None => println!("Not watching a folder"),
+                    },
+                    [folder, library, rest @ ..] if rest.len() <= 1 => {
+                        let settings = settings::WatchFolderSettings {
+                            enabled: true,
+                            folder: folder.to_string(),
+                            library: library.to_string(),
+                            poll_ms: rest.first().and_then(|ms| ms.parse().ok()).unwrap_or(1_000),
+                        };
+                        // Stop the old watcher first, so a folder isn't imported twice
+                        folder_watch = None;
+                        let started = watch::watch_from_settings(&settings, |event| {
+                            println!("\n{}", event.message());
+                            if let watch::WatchEvent::Imported { song, .. } = event {
+                                println!("Open it with 'load {}'", song.display());
+                            }
+                        });
+                        match started {
+                            Ok(handle) => {
+                                folder_watch = handle;
+                                println!("Watching {} for MIDI files; imports go to {}", folder, library);
+                            }
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    }
+                    _ => println!("Usage: watch [<folder> <library> [ms] | stop]"),
+                }
+            }
+
+            "samples" => {
+                // "samples", "samples add <dir> [instrument]", "samples scan <id>",
+                // "samples remove <id>", "samples install <dir>" (default pack, first run only)
+                let (sub, rest) = args.split_once(' ').map_or((args, ""), |(sub, rest)| (sub, rest.trim()));
+                match (sub, rest) {
+                    ("", _) | ("list", _) => {
+                        if sample_libraries.libraries.is_empty() {
+                            println!("No sample libraries. Add one with 'samples add <dir>', or 'samples install <dir>' for the default pack");
+                        }
+                        for library in &sample_libraries.libraries {
+                            let instrument = if library.instrument.is_empty() { "Piano range" } else { &library.instrument };
+                            println!("  {:<20} {:<12} {}", library.id, instrument, library.path.display());
+                        }
+                    }
+                    ("add", rest) if !rest.is_empty() => {
+                        // The instrument is the last word when it names a range preset
+                        let (dir, instrument) = match rest.rsplit_once(' ') {
+                            Some((dir, name)) if register::PitchRange::preset(name).is_ok() => (dir, name),
+                            _ => (rest, ""),
+                        };
+                        match sample_libraries.add(dir, instrument) {
+                            Ok(library) => println!("Added sample library '{}'", library.id),
+                            Err(e) => println!("Error: {}", e),
+                        }
+                    }
+                    ("scan", id) if !id.is_empty() => match sample_libraries.scan(id) {
+                        Ok(scan) => {
+                            println!(
+                                "{}: {} samples, {:.0}% of {}",
+                                scan.id,
+                                scan.samples.len(),
+                                scan.coverage * 100.0,
+                                scan.range
+                            );
+                            for issue in &scan.issues {
+                                println!("  {}", issue.description());
+                            }
+                        }
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    ("remove", id) if !id.is_empty() => match sample_libraries.remove(id) {
+                        Ok(library) => println!("Removed sample library '{}'", library.id),
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    ("install", dir) if !dir.is_empty() => match sample_libraries.ensure_default_pack(dir) {
+                        Ok(Some(library)) => println!("Installed the default pack as '{}'", library.id),
+                        Ok(None) => println!("Sample libraries are already set up; the default pack installs on first run"),
+                        Err(e) => println!("Error: {}", e),
+                    },
+                    _ => println!("Usage: samples [add <dir> [instrument] | scan <id> | remove <id> | install <dir>]"),
+                }
+            }
+
+            "logs" => {
+                let level = if args.is_empty() { "info" } else { args };
+                match logs::LogLevel::parse(level) {
+                    Ok(level) => {
+                        for entry in logs::recent_logs(level) {
+                            println!("{}", entry);
+                        }
+                    }
+                    Err(e) => println!("Error: {}", e),
+                }
+            }
+
+            "export-logs" => {
+                if args.is_empty() {
+                    println!("Usage: export-logs <filename>");
+                } else {
+                    match logs::export_logs(args) {
+                        Ok(()) => println!("Exported logs to {}", args),
+                        Err(e) => println!("Error: {}", e),
+                    }
+                }
+            }
+
             _ => {
                 println!("Unknown command: {}. Type 'help' for available commands.", cmd);
             }
@@ -276,11 +1888,94 @@ fn main() {
     println!("Goodbye!");
 }
 
+// Wait for an export job to finish, printing its progress along the way
+fn wait_for_export(events: &mpsc::Receiver<jobs::JobEvent>, id: jobs::JobId) -> Result<export::ExportResult, String> {
+    for event in events.iter().filter(|event| event.id() == id) {
+        match event {
+            jobs::JobEvent::Progress { percent, stage, .. } => println!("  {:>3.0}% {}", percent, stage),
+            jobs::JobEvent::Completed { result, .. } => return serde_json::from_value(result).map_err(|e| e.to_string()),
+            jobs::JobEvent::Failed { error, .. } => return Err(error),
+            jobs::JobEvent::Cancelled { .. } => return Err(MozartError::Cancelled.to_string()),
+        }
+    }
+    Err("The export job never finished".to_string())
+}
+
+fn print_repeats(song: &Song) {
+    let repeats = &song.repeats;
+    if repeats.is_empty() {
+        println!("No repeats");
+        return;
+    }
+    for repeat in &repeats.repeats {
+        println!("  Repeat {}", repeat);
+    }
+    for ending in &repeats.endings {
+        println!("  Ending {}", ending);
+    }
+    let signs = [("Segno", repeats.segno), ("Fine", repeats.fine), ("To Coda", repeats.to_coda), ("Coda", repeats.coda)];
+    for (name, measure) in signs {
+        if let Some(measure) = measure {
+            println!("  {} at measure {}", name, measure);
+        }
+    }
+    if let Some(jump) = repeats.jump {
+        println!("  {} at measure {}", jump.name(), jump.at_measure);
+    }
+    let order: Vec<String> = song.measure_order().iter().map(|m| m.to_string()).collect();
+    println!("Played order: {}", order.join(" "));
+}
+
+fn print_theory(reference: &TheoryReference) {
+    match reference {
+        TheoryReference::Intervals(intervals) => {
+            for i in intervals {
+                println!("  {:>2}  {:<3} {:<15} {:<6} inverts to {}", i.semitones, i.short_name, i.name, i.example, i.inversion);
+            }
+        }
+        TheoryReference::Scales(scales) => {
+            for s in scales {
+                println!("  {:<15} {:<20} {:<16} {}", s.name, s.formula.join(" "), s.steps, s.example.join(" "));
+            }
+        }
+        TheoryReference::Chords(chords) => {
+            for c in chords {
+                println!("  C{:<8} {:<24} {:<14} {}", c.symbol, c.name, c.formula.join(" "), c.example.join(" "));
+            }
+        }
+        TheoryReference::CircleOfFifths(keys) => {
+            for k in keys {
+                let enharmonic = k.enharmonic.as_ref().map(|e| format!(" (= {})", e)).unwrap_or_default();
+                println!("  {:>3}  {:<3} {:<3} {}{}", k.fifths, k.major, k.minor.to_lowercase(), k.signature.join(" "), enharmonic);
+            }
+        }
+        TheoryReference::Cadences(cadences) => {
+            for c in cadences {
+                println!("  {:<10} {:<12} {:<12} {}", c.name, c.major_numerals.join("–"), c.minor_numerals.join("–"), c.description);
+            }
+        }
+    }
+}
+
+/// `mozart-test --bench`: time the hot paths at the full benchmark sizes
+#[cfg(feature = "bench")]
+fn run_bench() {
+    let sizes = bench::BenchSizes::default();
+    println!("Benchmarking ({} runs each)...\n", sizes.iterations);
+    print!("{}", bench::report(&bench::run_benchmarks(&sizes)));
+}
+
+#[cfg(not(feature = "bench"))]
+fn run_bench() {
+    println!("Benchmarks need the bench feature: cargo run --release --features bench --bin mozart-test -- --bench");
+}
+
 fn print_help() {
     println!("Available commands:");
     println!();
     println!("  Song Management:");
-    println!("    new                       Create a new song");
+    println!("    new [confirm]             Create a new song from the defaults");
+    println!("    defaults [field value]    Get/set new-song tempo, time, key, instrument, pickup");
     println!("    info                      Show song information");
     println!("    title [name]              Get/set song title");
     println!("    demo                      Load a demo melody");
@@ -288,26 +1983,121 @@ fn print_help() {
     println!("  Settings:");
     println!("    tempo [bpm]               Get/set tempo");
     println!("    time [n/d]                Get/set time signature (e.g., 7/8)");
+    println!("    meter [voice n/d|default] List voice meters, or give a voice its own (e.g., 'meter 1 7/8')");
+    println!("    track [add <name> | <voice> <action>]  List tracks, or add/remove/rename/mute/solo one,");
+    println!("                              or set its instrument or volume (e.g., 'track 1 instrument strings')");
+    println!("    groove-preset [name]      List odd-meter grooves, or load one (meter, tempo, and drums)");
     println!("    key [root scale]          Get/set key (e.g., 'C major', 'F# dorian')");
+    println!("    pulse [groups|off]        Get/set the felt pulse (e.g., 'pulse 3+3' for 6/8 in 2)");
+    println!("    poly <n:m>                Show polyrhythm click times (e.g., 3:2)");
+    println!("    rate [0.25-2]             Get/set the practice speed (leaves the tempo alone)");
+    println!("    loop [m|next|prev|extend|clear]  Loop a measure, step the loop bar by bar, or add a bar to it");
+    println!("    dynamics [on|off]         Get/set accent-shaped playback dynamics");
+    println!("    instrument [name]         Get/set the instrument the song plays with");
+    println!("    range [name|low high|off] Get/set the instrument range notes are checked against");
+    println!("    fold [low high|name]      Move out-of-range notes in by octaves (default the song's range)");
+    println!("    rows [auto [m]|C1-C7|up|down]  Get/set the piano roll rows (fit to the notes, or a fixed range)");
     println!();
     println!("  Notes:");
     println!("    melody [notation]         Get/set melody (e.g., 'C4q D4q E4h')");
     println!("    notes                     List all notes");
-    println!("    clear                     Clear all notes");
+    println!("    rhythm [pitch|default]    Get/set the pitch of rhythm hits (e.g., 'melody xq xe xe Rq xh')");
+    println!("    chance <i> <%|A|B|->      Set a note's chance per pass or take lane (experimental)");
+    println!("    freeze [pass]             Keep the notes a pass plays (default the first)");
+    println!("    flatten                   Bake strums, accents, and track volumes into the notes");
+    println!("    color <i,j,..|all> <color>  Label notes with a color, or '-' to clear (e.g., 'color 0,1 blue')");
+    println!("    color section <m> <color>  Label the section a marker starts");
+    println!("    lyrics [text]             Get/set lyrics, one syllable per note (e.g., 'Hap-py birth-day')");
+    println!("    annotate <t>[-<end>] <text>  Annotate a tick or range (e.g., 'annotate 1920 breathe here')");
+    println!("    annotations               List the annotations");
+    println!("    unannotate <i>            Remove an annotation");
+    println!("    repeats                   Show the repeat marks and the order measures play in");
+    println!("    repeats add <m-n> [xN]    Add repeat barlines (e.g., 'repeats add 5-8 x3')");
+    println!("    repeats ending <m-n> <passes>  Add a numbered ending (e.g., 'repeats ending 8 1')");
+    println!("    repeats sign <sign> <m>   Place segno, fine, to coda, or coda at a measure");
+    println!("    repeats jump <jump> <m>   Set D.C./D.S. (al Fine/al Coda) at a measure; '-' removes it");
+    println!("    repeats remove <m>|clear  Remove the repeat marks at a measure, or all of them");
+    println!("    clear [confirm]           Clear all notes");
+    println!("    delete <m>[-<last>] [confirm]  Delete measures, pulling later music back");
+    println!("    legato [overlap]          Extend notes to the next note (+ overlap ticks)");
+    println!("    gap [ticks]               Shorten notes to leave a gap before the next");
+    println!("    voicing <up|down|open|close>  Invert or revoice every chord");
     println!();
     println!("  Transposition:");
     println!("    transpose chromatic <n>   Transpose by n semitones");
     println!("    transpose diatonic <n>    Transpose by n scale degrees");
     println!("    detect                    Detect the scale from notes");
     println!();
+    println!("  Validation:");
+    println!("    validate                  Check notes for problems");
+    println!("    repair [all]              Fix problems ('all' also removes stray notes)");
+    println!("    cleanup [apply]           Preview (or remove) duplicate and zero-length notes");
+    println!("    normalize <min>-<max>     Rescale velocities onto a range");
+    println!("    compress <ratio> <thresh> Divide velocity above thresh by ratio");
+    println!();
     println!("  Files:");
     println!("    save <file>               Save to .mozart.json file");
-    println!("    load <file>               Load from file");
+    println!("    load <file>               Load from file (.json or .mid)");
+    println!("    merge [keep] <file> <file>..  Merge files onto their own voices and MIDI channels ('keep' keeps each file's ticks)");
+    println!("    split <m>..|sections [save <dir>]  Preview pieces starting at measures (or markers); save as numbered files");
+    println!("    watch <dir> <library> [ms]  Import MIDI files exported into a folder as they appear ('watch stop' ends it)");
     println!("    midi <file>               Export to MIDI file");
+    println!("    wav <file> [rate]         Export practice audio, pitch kept (practice speed by default)");
+    println!("    lrc <file> [lines]        Export timed lyrics (per syllable, or per line)");
+    println!("    chordpro <file>           Export a chord-and-lyrics sheet (.cho) for songbook apps");
+    println!("    worksheet <dir> [level] [n]  Ear-training exercises as MIDI + PDF (current key/meter)");
+    println!("    verify-export [dir] [--bless]  Check MIDI export against the golden corpus");
     println!("    json                      Print song as JSON");
+    println!("    pipeline <program.json>   Run a JSON list of operations on the song");
+    println!();
+    println!("  Macros:");
+    println!("    record [stop <name> [key]|cancel]  Record commands as a macro (key, e.g., Alt+1)");
+    println!("    play <name|key>           Run a macro's commands");
+    println!("    macros                    List macros");
+    println!();
+    println!("  Jam Sync (LAN):");
+    println!("    jam host <name> [secs]    Broadcast the song's tempo and position as transport master");
+    println!("    jam listen [secs]         Follow the first master heard on the network");
+    println!();
+    println!("  Sample Libraries:");
+    println!("    samples                   List registered sample libraries");
+    println!("    samples add <dir> [instr] Register a directory of WAV samples (e.g., samples add ~/cello Cello)");
+    println!("    samples scan <id>         Check a library's samples and coverage of its range");
+    println!("    samples remove <id>       Unregister a library");
+    println!("    samples install <dir>     Install the default pack on first run");
     println!();
     println!("  Other:");
     println!("    scales                    List available scale types");
+    println!("    theory [topic]            Theory reference (intervals, scales, chords, circle, cadences)");
+    println!("    grammar [json]            The melody notation as EBNF (or JSON for editors)");
+    println!("    audition scale <root> <type> [up|down|both]  Notes of a scale preview at the song's tempo");
+    println!("    audition chord <root> [quality] [inversion]  Notes of a chord preview (e.g., audition chord A m7 1)");
+    println!("    audition degree <n> [7]   Notes of the triad (or seventh) on a degree of the key");
+    println!("    groove [n] [file.mid]     Extract a groove (n subdivisions per beat) from the song or a MIDI file");
+    println!("    groove apply              Impose the extracted groove on every note");
+    println!("    register [ticks]          Pitch range and mean per bucket (default: one measure)");
+    println!("    ladder                    The triads and sevenths on each degree of the key");
+    println!("    suggest [chords...]       Rank next chords in the key (e.g., 'suggest I IV')");
+    println!("    reharmonize <m> [n]       Chords for measure m that hold its melody (n per measure)");
+    println!("    modulate <key> [m]        Modulation routes from the song's key, from measure m");
+    println!("    modulate apply <n>        Write route n's chords into the song");
+    println!("    vary [count]              Candidate variations of the melody (rhythm, neighbor tones, octaves)");
+    println!("    vary apply <n>            Replace the melody with variation n");
+    println!("    counterpoint [above|below] [voice]  First-species line against voice 0");
+    println!("    chart <text>              Import a chord chart, e.g. chart | C . . . | Am . F . |");
+    println!("    chord [<pos> <symbol>|-]  List the chord track, or set/remove a chord (e.g., 'chord 3.3 G/B')");
+    println!("    pattern [new <name> <m> [n]]  List patterns, or cut n measures from m into a pattern");
+    println!("    pattern clone|move|rm ...  Copy a pattern, reorder the list (1-based), or delete one");
+    println!("    pattern chain <names>|-   Chain patterns to play from the start (e.g., 'pattern chain A A B A')");
+    println!("    outline <chart>           Melody in voice 1 outlining a chord chart");
+    println!("    continue [n] [dir]        Continue the melody n measures (learns songs in dir)");
+    println!("    similar <file>|dir <dir>  Melodic similarity to another song, or a library ranked");
+    println!("    compare <file> [together|alternate] [out.wav]  Measures that differ from another version;");
+    println!("                              render both to hear them (left this song, right the other)");
+    println!("    commands [query]          Search the command palette registry");
+    println!("    tutorial [start|next|skip|stop]  Guided first steps");
+    println!("    logs [level]              Show recent log entries (default: info)");
+    println!("    export-logs <file>        Save recent log entries for a bug report");
     println!("    help                      Show this help");
     println!("    quit                      Exit the program");
 }
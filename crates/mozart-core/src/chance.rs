@@ -0,0 +1,136 @@
+//! Note probability and alternate takes (experimental)
+//!
+//! For generative sketching, a note can play only some of the time: its
+//! `probability` (0-100%) is rolled on every pass through the song. A note
+//! can also sit in an alternate take lane, A or B; each pass picks one lane
+//! per measure, and only that lane's notes play there. Choices are a pure
+//! function of the note and the pass number, so a pass sounds the same
+//! every time it is replayed, and [`freeze_arrangement`] can make one
+//! permanent. Exports play the first pass.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+/// Alternate take lane of a note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Take {
+    A,
+    B,
+}
+
+impl Take {
+    /// Parse "A" or "B" (any case); "" or "-" for no lane
+    pub fn parse(s: &str) -> Result<Option<Take>> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "" | "-" => Ok(None),
+            "A" => Ok(Some(Take::A)),
+            "B" => Ok(Some(Take::B)),
+            _ => Err(MozartError::ParseError(format!("Unknown take: {} (use A or B)", s))),
+        }
+    }
+}
+
+impl fmt::Display for Take {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", if *self == Take::A { "A" } else { "B" })
+    }
+}
+
+/// Lane the measure plays on a pass
+pub fn take_for_measure(measure: u32, pass: u32) -> Take {
+    // Salted so a measure's lane isn't tied to the roll of a note at its start
+    if mix(measure as u64 | 1 << 48, pass) & 1 == 0 { Take::A } else { Take::B }
+}
+
+/// Whether a note sounds on a pass (0 is the first)
+pub fn plays_on_pass(note: &Note, ticks_per_measure: u32, pass: u32) -> bool {
+    if let Some(take) = note.take {
+        if take_for_measure(note.start_tick / ticks_per_measure.max(1), pass) != take {
+            return false;
+        }
+    }
+    match note.probability {
+        0 => false,
+        p if p >= Note::ALWAYS_PLAYS => true,
+        p => {
+            let key = note.pitch as u64 | (note.voice as u64) << 8 | (note.start_tick as u64) << 16;
+            mix(key, pass) % 100 < p as u64
+        }
+    }
+}
+
+/// The notes a pass plays, as plain notes (no probability or take)
+pub fn freeze_arrangement(notes: &[Note], ticks_per_measure: u32, pass: u32) -> Vec<Note> {
+    notes
+        .iter()
+        .filter(|note| plays_on_pass(note, ticks_per_measure, pass))
+        .map(|note| Note { probability: Note::ALWAYS_PLAYS, take: None, ..note.clone() })
+        .collect()
+}
+
+/// Whether any note plays only on some passes
+pub fn has_chance_notes(notes: &[Note]) -> bool {
+    notes.iter().any(|note| note.probability < Note::ALWAYS_PLAYS || note.take.is_some())
+}
+
+// SplitMix64 of a key and a pass: stable across platforms and releases, unlike std's hasher
+fn mix(key: u64, pass: u32) -> u64 {
+    let mut x = key ^ (pass as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(32);
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_probability() {
+        let mut note = Note::new(60, 0, 480);
+        assert!((0..50).all(|pass| plays_on_pass(&note, 1920, pass)));
+        note.set_probability(0);
+        assert!((0..50).all(|pass| !plays_on_pass(&note, 1920, pass)));
+
+        // About half of the passes, the same ones every time
+        note.set_probability(50);
+        let passes: Vec<bool> = (0..200).map(|pass| plays_on_pass(&note, 1920, pass)).collect();
+        let played = passes.iter().filter(|&&p| p).count();
+        assert!((70..130).contains(&played), "{} of 200", played);
+        assert_eq!((0..200).map(|pass| plays_on_pass(&note, 1920, pass)).collect::<Vec<_>>(), passes);
+    }
+
+    #[test]
+    fn test_takes() {
+        let a = Note { take: Some(Take::A), ..Note::new(60, 0, 480) };
+        let b = Note { take: Some(Take::B), ..Note::new(64, 480, 480) };
+        // Lanes of one measure never play together, and both get heard
+        let a_passes = (0..100).filter(|&pass| plays_on_pass(&a, 1920, pass)).count();
+        assert!((0..100).all(|pass| plays_on_pass(&a, 1920, pass) != plays_on_pass(&b, 1920, pass)));
+        assert!((20..80).contains(&a_passes), "{}", a_passes);
+
+        assert_eq!(Take::parse(" b ").unwrap(), Some(Take::B));
+        assert_eq!(Take::parse("-").unwrap(), None);
+        assert!(Take::parse("C").is_err());
+    }
+
+    #[test]
+    fn test_freeze_arrangement() {
+        let notes = vec![
+            Note { take: Some(Take::A), ..Note::new(60, 0, 480) },
+            Note { take: Some(Take::B), ..Note::new(64, 0, 480) },
+            Note { probability: 0, ..Note::new(67, 480, 480) },
+            Note::new(72, 960, 480),
+        ];
+        assert!(has_chance_notes(&notes));
+        let frozen = freeze_arrangement(&notes, 1920, 3);
+        assert_eq!(frozen.len(), 2);
+        assert!(!has_chance_notes(&frozen));
+        assert_eq!(frozen[1], Note::new(72, 960, 480));
+        assert_eq!(frozen, freeze_arrangement(&notes, 1920, 3));
+    }
+}
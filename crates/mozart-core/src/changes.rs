@@ -0,0 +1,361 @@
+//! Fine-grained song change events
+//!
+//! Instead of refetching the whole song after every command, a frontend can
+//! apply the deltas between two song states: note splices by index, and
+//! settings that changed. [`diff_songs`] produces them from before/after
+//! snapshots, so every edit path (including undo and redo) reports changes
+//! the same way.
+
+use serde::{Deserialize, Serialize};
+use crate::chord::ChordSymbol;
+use crate::instrument::Instrument;
+use crate::note::Note;
+use crate::pattern::Pattern;
+use crate::register::{range_warnings, PitchRange, RangeWarning};
+use crate::repeats::RepeatStructure;
+use crate::song::{Annotation, Lyric, Marker, Song, Track, VoiceMeter};
+
+/// A change to a song, applied in order to the previous state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SongChange {
+    /// Notes inserted at `index` (later notes shift up)
+    #[serde(rename = "notes:added")]
+    NotesAdded { index: usize, notes: Vec<Note> },
+    /// `count` notes removed starting at `index` (later notes shift down)
+    #[serde(rename = "notes:removed")]
+    NotesRemoved { index: usize, count: usize },
+    /// Notes starting at `index` replaced in place, one for one
+    #[serde(rename = "notes:updated")]
+    NotesUpdated { index: usize, notes: Vec<Note> },
+    /// Tick span covering the old and new positions of every changed note
+    ///
+    /// Views that only fetch the notes of a window refetch it when this
+    /// overlaps; the splices above keep their indices current otherwise.
+    #[serde(rename = "notes:dirty")]
+    NotesDirty { start_tick: u32, end_tick: u32 },
+    #[serde(rename = "settings:tempo")]
+    Tempo { tempo: u16 },
+    /// Time signature, accents, or pulse grouping changed
+    #[serde(rename = "settings:timeSignature")]
+    TimeSignature {
+        numerator: u8,
+        denominator: u8,
+        /// 1=weak, 2=medium, 3=strong
+        accents: Vec<u8>,
+        groupings: Vec<u8>,
+        pulse_grouping: Vec<u8>,
+    },
+    /// Voices in their own meter changed
+    #[serde(rename = "settings:voiceMeters")]
+    VoiceMeters { voice_meters: Vec<VoiceMeter> },
+    /// Tracks added, removed, renamed, or remixed (instrument, volume, mute, solo)
+    #[serde(rename = "settings:tracks")]
+    Tracks { tracks: Vec<Track> },
+    #[serde(rename = "settings:key")]
+    Key { key: String },
+    #[serde(rename = "settings:accentDynamics")]
+    AccentDynamics { enabled: bool },
+    /// Pitch rhythm hits are entered at (None for middle C)
+    #[serde(rename = "settings:rhythmPitch")]
+    RhythmPitch { pitch: Option<u8> },
+    #[serde(rename = "settings:pitchRange")]
+    PitchRange { range: Option<PitchRange> },
+    #[serde(rename = "settings:instrument")]
+    Instrument { instrument: Instrument },
+    /// Notes the edit created or moved outside the pitch range (after the note splices)
+    #[serde(rename = "notes:outOfRange")]
+    OutOfRange { warnings: Vec<RangeWarning> },
+    #[serde(rename = "song:metadata")]
+    Metadata { title: String, composer: String },
+    #[serde(rename = "markers:changed")]
+    Markers { markers: Vec<Marker> },
+    #[serde(rename = "lyrics:changed")]
+    Lyrics { lyrics: Vec<Lyric> },
+    /// Chord symbols added, changed, or removed (so did the comping)
+    #[serde(rename = "chords:changed")]
+    Chords { chords: Vec<ChordSymbol> },
+    /// Patterns or the pattern chain changed (so did playback)
+    #[serde(rename = "patterns:changed")]
+    Patterns { patterns: Vec<Pattern>, pattern_chain: Vec<String> },
+    #[serde(rename = "annotations:changed")]
+    Annotations { annotations: Vec<Annotation> },
+    /// Repeats, endings, or jumps changed (so did the played order)
+    #[serde(rename = "repeats:changed")]
+    Repeats { repeats: RepeatStructure },
+    /// Playback-only data (strums, tempo map) changed; the notes did not
+    #[serde(rename = "playback:changed")]
+    Playback,
+    /// The whole song was swapped out; refetch everything
+    #[serde(rename = "song:replaced")]
+    Replaced,
+}
+
+impl SongChange {
+    /// Event name (e.g., "notes:added")
+    pub fn name(&self) -> &'static str {
+        match self {
+            SongChange::NotesAdded { .. } => "notes:added",
+            SongChange::NotesRemoved { .. } => "notes:removed",
+            SongChange::NotesUpdated { .. } => "notes:updated",
+            SongChange::NotesDirty { .. } => "notes:dirty",
+            SongChange::Tempo { .. } => "settings:tempo",
+            SongChange::TimeSignature { .. } => "settings:timeSignature",
+            SongChange::VoiceMeters { .. } => "settings:voiceMeters",
+            SongChange::Tracks { .. } => "settings:tracks",
+            SongChange::Key { .. } => "settings:key",
+            SongChange::AccentDynamics { .. } => "settings:accentDynamics",
+            SongChange::RhythmPitch { .. } => "settings:rhythmPitch",
+            SongChange::PitchRange { .. } => "settings:pitchRange",
+            SongChange::Instrument { .. } => "settings:instrument",
+            SongChange::OutOfRange { .. } => "notes:outOfRange",
+            SongChange::Metadata { .. } => "song:metadata",
+            SongChange::Markers { .. } => "markers:changed",
+            SongChange::Lyrics { .. } => "lyrics:changed",
+            SongChange::Chords { .. } => "chords:changed",
+            SongChange::Patterns { .. } => "patterns:changed",
+            SongChange::Annotations { .. } => "annotations:changed",
+            SongChange::Repeats { .. } => "repeats:changed",
+            SongChange::Playback => "playback:changed",
+            SongChange::Replaced => "song:replaced",
+        }
+    }
+}
+
+/// Changes that turn `before` into `after`
+///
+/// Notes are compared as one splice: the common prefix and suffix are kept
+/// and only the differing middle is reported. View state is ignored.
+pub fn diff_songs(before: &Song, after: &Song) -> Vec<SongChange> {
+    let mut changes = diff_notes(&before.notes, &after.notes);
+    if !changes.is_empty() {
+        let warnings = range_warnings(before, after);
+        if !warnings.is_empty() {
+            changes.push(SongChange::OutOfRange { warnings });
+        }
+    }
+
+    let (old, new) = (&before.settings, &after.settings);
+    if old.tempo != new.tempo {
+        changes.push(SongChange::Tempo { tempo: new.tempo });
+    }
+    if old.time_signature != new.time_signature {
+        let ts = &new.time_signature;
+        changes.push(SongChange::TimeSignature {
+            numerator: ts.numerator,
+            denominator: ts.denominator,
+            accents: ts.accents.accents.iter().map(|a| *a as u8).collect(),
+            groupings: ts.groupings(),
+            pulse_grouping: ts.pulse_grouping(),
+        });
+    }
+    if old.voice_meters != new.voice_meters {
+        changes.push(SongChange::VoiceMeters { voice_meters: new.voice_meters.clone() });
+    }
+    if old.tracks != new.tracks {
+        changes.push(SongChange::Tracks { tracks: new.tracks.clone() });
+    }
+    if old.key != new.key {
+        changes.push(SongChange::Key { key: new.key.to_string() });
+    }
+    if old.accent_dynamics != new.accent_dynamics {
+        changes.push(SongChange::AccentDynamics { enabled: new.accent_dynamics });
+    }
+    if old.rhythm_pitch != new.rhythm_pitch {
+        changes.push(SongChange::RhythmPitch { pitch: new.rhythm_pitch });
+    }
+    if old.pitch_range != new.pitch_range {
+        changes.push(SongChange::PitchRange { range: new.pitch_range.clone() });
+    }
+    if old.instrument != new.instrument {
+        changes.push(SongChange::Instrument { instrument: new.instrument });
+    }
+    if before.metadata.title != after.metadata.title || before.metadata.composer != after.metadata.composer {
+        changes.push(SongChange::Metadata {
+            title: after.metadata.title.clone(),
+            composer: after.metadata.composer.clone(),
+        });
+    }
+    if before.markers != after.markers {
+        changes.push(SongChange::Markers { markers: after.markers.clone() });
+    }
+    if before.lyrics != after.lyrics {
+        changes.push(SongChange::Lyrics { lyrics: after.lyrics.clone() });
+    }
+    if before.chords != after.chords {
+        changes.push(SongChange::Chords { chords: after.chords.clone() });
+    }
+    if before.patterns != after.patterns || before.pattern_chain != after.pattern_chain {
+        changes.push(SongChange::Patterns { patterns: after.patterns.clone(), pattern_chain: after.pattern_chain.clone() });
+    }
+    if before.annotations != after.annotations {
+        changes.push(SongChange::Annotations { annotations: after.annotations.clone() });
+    }
+    if before.repeats != after.repeats {
+        changes.push(SongChange::Repeats { repeats: after.repeats.clone() });
+    }
+    if before.strums != after.strums || old.tempo_map != new.tempo_map {
+        changes.push(SongChange::Playback);
+    }
+    changes
+}
+
+fn diff_notes(before: &[Note], after: &[Note]) -> Vec<SongChange> {
+    let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let removed = &before[prefix..before.len() - suffix];
+    let added = &after[prefix..after.len() - suffix];
+    if removed.is_empty() && added.is_empty() {
+        return Vec::new();
+    }
+
+    let mut changes = Vec::new();
+    if removed.len() == added.len() {
+        changes.push(SongChange::NotesUpdated { index: prefix, notes: added.to_vec() });
+    } else {
+        if !removed.is_empty() {
+            changes.push(SongChange::NotesRemoved { index: prefix, count: removed.len() });
+        }
+        if !added.is_empty() {
+            changes.push(SongChange::NotesAdded { index: prefix, notes: added.to_vec() });
+        }
+    }
+
+    let touched = removed.iter().chain(added);
+    changes.push(SongChange::NotesDirty {
+        start_tick: touched.clone().map(|n| n.start_tick).min().unwrap_or(0),
+        end_tick: touched.map(|n| n.end_tick()).max().unwrap_or(0),
+    });
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::TimeSignature;
+    use pretty_assertions::assert_eq;
+
+    // Apply note changes the way a frontend would
+    fn apply(notes: &mut Vec<Note>, changes: &[SongChange]) {
+        for change in changes {
+            match change {
+                SongChange::NotesAdded { index, notes: added } => {
+                    notes.splice(*index..*index, added.iter().cloned());
+                }
+                SongChange::NotesRemoved { index, count } => {
+                    notes.drain(*index..*index + *count);
+                }
+                SongChange::NotesUpdated { index, notes: updated } => {
+                    notes[*index..*index + updated.len()].clone_from_slice(updated);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn song_with(pitches: &[u8]) -> Song {
+        let mut song = Song::new();
+        for (i, &pitch) in pitches.iter().enumerate() {
+            song.add_note(Note::new(pitch, i as u32 * 480, 480));
+        }
+        song
+    }
+
+    #[test]
+    fn test_note_deltas() {
+        let before = song_with(&[60, 62, 64]);
+
+        let mut after = before.clone();
+        after.add_note(Note::new(67, 600, 240));
+        assert_eq!(
+            diff_songs(&before, &after),
+            vec![
+                SongChange::NotesAdded { index: 2, notes: vec![Note::new(67, 600, 240)] },
+                SongChange::NotesDirty { start_tick: 600, end_tick: 840 },
+            ]
+        );
+
+        let mut after = before.clone();
+        after.set_note_pitch(1, 61).unwrap();
+        assert_eq!(
+            diff_songs(&before, &after)[0],
+            SongChange::NotesUpdated { index: 1, notes: vec![Note::new(61, 480, 480)] }
+        );
+
+        let mut after = before.clone();
+        after.remove_note(0);
+        assert_eq!(
+            diff_songs(&before, &after),
+            vec![
+                SongChange::NotesRemoved { index: 0, count: 1 },
+                SongChange::NotesDirty { start_tick: 0, end_tick: 480 },
+            ]
+        );
+
+        assert!(diff_songs(&before, &before.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_deltas_reproduce_notes() {
+        let before = song_with(&[60, 62, 64, 65, 67]);
+        let mut after = before.clone();
+        after.remove_note(3);
+        after.remove_note(1);
+        after.add_note(Note::new(72, 1000, 100));
+
+        let mut notes = before.notes.clone();
+        apply(&mut notes, &diff_songs(&before, &after));
+        assert_eq!(notes, after.notes);
+    }
+
+    #[test]
+    fn test_settings_deltas() {
+        let before = Song::new();
+        let mut after = before.clone();
+        after.set_tempo(90);
+        after.set_time_signature(TimeSignature::parse("6/8").unwrap());
+        after.settings.accent_dynamics = true;
+        after.set_instrument(Instrument::Piano);
+        after.lyrics.push(Lyric { tick: 0, text: "La".to_string(), new_line: true });
+        after.repeats.add_repeat(crate::repeats::Repeat::new(1, 2)).unwrap();
+
+        let names: Vec<&str> = diff_songs(&before, &after).iter().map(|c| c.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "settings:tempo",
+                "settings:timeSignature",
+                "settings:accentDynamics",
+                "settings:instrument",
+                "lyrics:changed",
+                "repeats:changed",
+            ]
+        );
+
+        let json = serde_json::to_value(SongChange::Tempo { tempo: 90 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "settings:tempo", "tempo": 90 }));
+    }
+
+    #[test]
+    fn test_out_of_range_warnings() {
+        let mut before = song_with(&[60, 62]);
+        before.set_pitch_range(Some(PitchRange::new("", 55, 70).unwrap()));
+
+        let mut after = before.clone();
+        after.add_note(Note::new(72, 960, 480));
+        let changes = diff_songs(&before, &after);
+        let names: Vec<&str> = changes.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["notes:added", "notes:dirty", "notes:outOfRange"]);
+        let SongChange::OutOfRange { warnings } = &changes[2] else { unreachable!() };
+        assert_eq!((warnings[0].note_index, warnings[0].pitch), (2, 72));
+
+        let mut after = before.clone();
+        after.set_pitch_range(None);
+        assert_eq!(diff_songs(&before, &after), vec![SongChange::PitchRange { range: None }]);
+    }
+}
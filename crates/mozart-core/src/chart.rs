@@ -0,0 +1,185 @@
+//! Chord chart import
+//!
+//! Reads plain-text lead-sheet charts, one bar per measure in the song's
+//! meter:
+//!
+//! ```text
+//! | C . . . | Am . F . | G7/B . . . | % |
+//! ```
+//!
+//! Each bar is split evenly between its slots. A slot is a chord symbol,
+//! `.` or `/` to hold the chord before it (across the barline too), or
+//! `N.C.` for silence; a bar of just `%` repeats the one before. Repeat
+//! signs (`|:` and `:|`) are read as plain barlines.
+
+use serde::{Deserialize, Serialize};
+use crate::chord::Chord;
+use crate::error::{MozartError, Result};
+use crate::song::Song;
+
+/// A chord of the chart, placed in the song
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChartChord {
+    /// 1-based
+    pub measure: u32,
+    pub start_tick: u32,
+    pub end_tick: u32,
+    /// As written, e.g., "Am7" or "C/E"
+    pub symbol: String,
+    /// Pitch classes (0 = C), root first
+    pub pitch_classes: Vec<u8>,
+    /// Bass of a slash chord
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bass: Option<u8>,
+}
+
+// Pitch classes and slash bass of a chord symbol
+fn parse_symbol(symbol: &str) -> Option<(Vec<u8>, Option<u8>)> {
+    let chord = Chord::parse(symbol).ok()?;
+    Some((chord.pitch_classes(), chord.bass.map(|bass| bass.semitones())))
+}
+
+/// Read a chart into chords starting at measure 1, one bar per measure
+pub fn parse_chord_chart(song: &Song, text: &str) -> Result<Vec<ChartChord>> {
+    let bars: Vec<Vec<&str>> = text
+        .split(['|', '\n'])
+        .map(|bar| bar.split_whitespace().filter(|slot| !slot.trim_matches(':').is_empty()).collect::<Vec<_>>())
+        .filter(|slots| !slots.is_empty())
+        .collect();
+    if bars.is_empty() {
+        return Err(MozartError::HarmonyError("The chart has no bars".to_string()));
+    }
+
+    let mut chords: Vec<ChartChord> = Vec::new();
+    // The chord sounding at the end of the last slot (None after N.C.)
+    let mut current: Option<(String, Vec<u8>, Option<u8>)> = None;
+    let mut previous_bar: Option<&Vec<&str>> = None;
+
+    for (i, written) in bars.iter().enumerate() {
+        let measure = i as u32 + 1;
+        let slots = match written.as_slice() {
+            ["%"] => previous_bar
+                .ok_or_else(|| MozartError::HarmonyError("Bar 1 cannot repeat a previous bar".to_string()))?,
+            _ => written,
+        };
+        previous_bar = Some(slots);
+
+        let start = song.measure_start_tick(measure)?;
+        let length = song.measure_start_tick(measure + 1)? - start;
+        let slot_tick = |slot: usize| start + (length as u64 * slot as u64 / slots.len() as u64) as u32;
+
+        for (slot, &token) in slots.iter().enumerate() {
+            let (tick, end_tick) = (slot_tick(slot), slot_tick(slot + 1));
+            match token {
+                "." | "/" => {
+                    // Extend the chord written earlier in the bar, or restate it after a barline
+                    match (&current, chords.last_mut()) {
+                        (Some(_), Some(last)) if last.measure == measure => last.end_tick = end_tick,
+                        (Some((symbol, pitch_classes, bass)), _) => chords.push(ChartChord {
+                            measure,
+                            start_tick: tick,
+                            end_tick,
+                            symbol: symbol.clone(),
+                            pitch_classes: pitch_classes.clone(),
+                            bass: *bass,
+                        }),
+                        (None, _) => {}
+                    }
+                }
+                "N.C." | "NC" => current = None,
+                symbol => {
+                    let (pitch_classes, bass) = parse_symbol(symbol).ok_or_else(|| {
+                        MozartError::HarmonyError(format!("Bar {}: unknown chord {}", measure, symbol))
+                    })?;
+                    chords.push(ChartChord {
+                        measure,
+                        start_tick: tick,
+                        end_tick,
+                        symbol: symbol.to_string(),
+                        pitch_classes: pitch_classes.clone(),
+                        bass,
+                    });
+                    current = Some((symbol.to_string(), pitch_classes, bass));
+                }
+            }
+        }
+    }
+
+    tracing::debug!("Parsed {} chords in {} bars", chords.len(), bars.len());
+    Ok(chords)
+}
+
+/// Write a chart's chords into the song (root position, slash basses below)
+/// and return them
+pub fn import_chord_chart(song: &mut Song, text: &str) -> Result<Vec<ChartChord>> {
+    let chords = parse_chord_chart(song, text)?;
+
+    let mut notes = Vec::new();
+    for chord in &chords {
+        notes.extend(Chord::parse(&chord.symbol)?.notes(chord.start_tick, chord.end_tick - chord.start_tick));
+    }
+
+    let count = notes.len();
+    song.add_notes(notes);
+    tracing::info!("Imported a chord chart ({} chords, {} notes)", chords.len(), count);
+    Ok(chords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::TimeSignature;
+    use pretty_assertions::assert_eq;
+
+    fn spans(chords: &[ChartChord]) -> Vec<(u32, &str, u32, u32)> {
+        chords.iter().map(|c| (c.measure, c.symbol.as_str(), c.start_tick, c.end_tick)).collect()
+    }
+
+    #[test]
+    fn test_parse_chart() {
+        let song = Song::new();
+        let chords = parse_chord_chart(&song, "| C . . . | Am . F . |\n| G7 | % | . Dm |").unwrap();
+        assert_eq!(
+            spans(&chords),
+            vec![
+                (1, "C", 0, 1920),
+                (2, "Am", 1920, 2880),
+                (2, "F", 2880, 3840),
+                (3, "G7", 3840, 5760),
+                (4, "G7", 5760, 7680),
+                // A leading dot holds G7 over the barline
+                (5, "G7", 7680, 8640),
+                (5, "Dm", 8640, 9600),
+            ]
+        );
+        assert_eq!(chords[3].pitch_classes, vec![7, 11, 2, 5]);
+
+        // Bars follow the meter; repeat signs and N.C. are understood
+        let mut waltz = Song::new();
+        waltz.set_time_signature(TimeSignature::new(3, 4).unwrap());
+        let chords = parse_chord_chart(&waltz, "|: F . N.C. | . Bbmaj7 . :|").unwrap();
+        assert_eq!(spans(&chords), vec![(1, "F", 0, 960), (2, "Bbmaj7", 1920, 2880)]);
+    }
+
+    #[test]
+    fn test_chart_errors() {
+        let song = Song::new();
+        assert!(parse_chord_chart(&song, " | | ").is_err());
+        assert!(parse_chord_chart(&song, "| % |").is_err());
+        let err = parse_chord_chart(&song, "| C | Hm7 |").unwrap_err();
+        assert!(err.to_string().contains("Bar 2: unknown chord Hm7"));
+    }
+
+    #[test]
+    fn test_import_chart() {
+        let mut song = Song::new();
+        let chords = import_chord_chart(&mut song, "| C/E . Am . |").unwrap();
+        assert_eq!(chords[0].bass, Some(4));
+
+        // E2 under the C triad from C3
+        let pitches: Vec<u8> = song.notes.iter().filter(|n| n.start_tick == 0).map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![40, 48, 52, 55]);
+        assert_eq!(song.notes.len(), 7);
+        assert!(song.notes.iter().all(|n| n.duration_ticks == 960 && n.velocity == 80));
+    }
+}
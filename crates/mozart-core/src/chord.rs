@@ -0,0 +1,214 @@
+//! Chord symbols and the chord track
+//!
+//! A [`Chord`] is a lead-sheet symbol taken apart: "F#m7b5" is a root (F#),
+//! a triad quality (diminished), the tones stacked above the triad (a
+//! minor seventh), and "G/B" adds a slash bass, which makes it an
+//! inversion when the bass is a chord tone. Suffixes are the ones in the
+//! chord reference (see [`chord_intervals`]).
+//!
+//! The song's chord track is a list of [`ChordSymbol`]s. Playback comps
+//! each symbol in [`CHORD_VOICE`] from its tick until the next symbol,
+//! struck again at every barline, so the harmony can be heard under the
+//! melody without writing it out in notes. Give that voice a track to
+//! change the comping instrument or volume, or to mute it.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::harmony::{chord_notes, split_symbol};
+use crate::note::Note;
+use crate::pitch::PitchClass;
+use crate::theory::chord_intervals;
+
+/// Voice the chord track is comped in
+pub const CHORD_VOICE: u8 = 15;
+
+/// The triad a chord is built on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    /// Second in place of the third
+    Sus2,
+    /// Fourth in place of the third
+    Sus4,
+}
+
+impl ChordQuality {
+    const ALL: [ChordQuality; 6] = [
+        ChordQuality::Major,
+        ChordQuality::Minor,
+        ChordQuality::Diminished,
+        ChordQuality::Augmented,
+        ChordQuality::Sus2,
+        ChordQuality::Sus4,
+    ];
+
+    /// Semitones above the root
+    pub fn intervals(&self) -> [u8; 3] {
+        match self {
+            ChordQuality::Major => [0, 4, 7],
+            ChordQuality::Minor => [0, 3, 7],
+            ChordQuality::Diminished => [0, 3, 6],
+            ChordQuality::Augmented => [0, 4, 8],
+            ChordQuality::Sus2 => [0, 2, 7],
+            ChordQuality::Sus4 => [0, 5, 7],
+        }
+    }
+}
+
+/// A chord symbol, e.g., "Cmaj7", "F#m7b5", or "G/B"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chord {
+    pub root: PitchClass,
+    pub quality: ChordQuality,
+    /// Tones above the triad, in semitones above the root (10 for a minor
+    /// seventh, 14 for a ninth)
+    pub extensions: Vec<u8>,
+    /// Bass of a slash chord; None has the root in the bass
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bass: Option<PitchClass>,
+}
+
+impl Chord {
+    /// Parse a chord symbol
+    pub fn parse(symbol: &str) -> Result<Self> {
+        let unknown = || MozartError::HarmonyError(format!("Unknown chord: {}", symbol));
+        let symbol = symbol.trim();
+        let (chord, bass) = match symbol.split_once('/') {
+            Some((chord, bass)) => (chord, Some(PitchClass::parse(bass).map_err(|_| unknown())?)),
+            None => (symbol, None),
+        };
+        let (root, suffix) = split_symbol(chord).ok_or_else(unknown)?;
+        let intervals = chord_intervals(suffix).ok_or_else(unknown)?;
+        let quality = ChordQuality::ALL
+            .into_iter()
+            .find(|q| intervals.starts_with(&q.intervals()))
+            .ok_or_else(unknown)?;
+        Ok(Chord { root, quality, extensions: intervals[3..].to_vec(), bass })
+    }
+
+    /// Semitones above the root of every tone, triad first
+    pub fn intervals(&self) -> Vec<u8> {
+        self.quality.intervals().into_iter().chain(self.extensions.iter().copied()).collect()
+    }
+
+    /// Pitch classes (0 = C), root first
+    pub fn pitch_classes(&self) -> Vec<u8> {
+        self.intervals().iter().map(|&i| (self.root.semitones() + i) % 12).collect()
+    }
+
+    /// Which chord tone is in the bass: 0 in root position, 1 with the
+    /// third (first inversion), 2 with the fifth, 3 with the seventh; 0 for
+    /// a bass outside the chord
+    pub fn inversion(&self) -> usize {
+        let Some(bass) = self.bass else { return 0 };
+        self.pitch_classes().iter().position(|&pc| pc == bass.semitones()).unwrap_or(0)
+    }
+
+    /// The chord as notes from C3 up, root position, with a slash bass
+    /// below the root
+    pub fn notes(&self, start_tick: u32, duration_ticks: u32) -> Vec<Note> {
+        let mut notes = chord_notes(&self.pitch_classes(), start_tick, duration_ticks);
+        if let Some(bass) = self.bass.filter(|&bass| bass != self.root) {
+            // The nearest pitch of the bass below the root
+            let mut note = notes[0].clone();
+            note.pitch -= match (note.pitch % 12 + 12 - bass.semitones()) % 12 {
+                0 => 12,
+                step => step,
+            };
+            notes.insert(0, note);
+        }
+        notes
+    }
+}
+
+/// A chord of the chord track, from its tick until the next one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChordSymbol {
+    pub tick: u32,
+    /// As written, e.g., "Bbmaj7"
+    pub symbol: String,
+}
+
+impl ChordSymbol {
+    /// The symbol taken apart
+    pub fn chord(&self) -> Result<Chord> {
+        Chord::parse(&self.symbol)
+    }
+}
+
+/// Notes comping a chord track: each chord until the next (the last until
+/// `end_tick`, or the end of its measure), restruck at every barline
+pub fn comp_notes(chords: &[ChordSymbol], ticks_per_measure: u32, end_tick: u32) -> Vec<Note> {
+    let mut notes = Vec::new();
+    for (i, symbol) in chords.iter().enumerate() {
+        let Ok(chord) = symbol.chord() else { continue };
+        let end = match chords.get(i + 1) {
+            Some(next) => next.tick,
+            None => end_tick.max((symbol.tick / ticks_per_measure + 1) * ticks_per_measure),
+        };
+        let mut start = symbol.tick;
+        while start < end {
+            let barline = (start / ticks_per_measure + 1) * ticks_per_measure;
+            let stop = barline.min(end);
+            notes.extend(chord.notes(start, stop - start).into_iter().map(|note| Note { voice: CHORD_VOICE, ..note }));
+            start = stop;
+        }
+    }
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_chord() {
+        let chord = Chord::parse("Cmaj7").unwrap();
+        assert_eq!(chord.quality, ChordQuality::Major);
+        assert_eq!(chord.extensions, vec![11]);
+        assert_eq!(chord.pitch_classes(), vec![0, 4, 7, 11]);
+
+        let chord = Chord::parse("F#m7b5").unwrap();
+        assert_eq!((chord.root, chord.quality), (PitchClass::new(6), ChordQuality::Diminished));
+        assert_eq!(chord.pitch_classes(), vec![6, 9, 0, 4]);
+
+        let chord = Chord::parse("G/B").unwrap();
+        assert_eq!(chord.bass, Some(PitchClass::new(11)));
+        assert_eq!(chord.inversion(), 1);
+        assert_eq!(Chord::parse("C7/Bb").unwrap().inversion(), 3);
+        assert_eq!(Chord::parse("Dsus4").unwrap().quality, ChordQuality::Sus4);
+        assert_eq!(Chord::parse("Ebø").unwrap(), Chord::parse("D#m7b5").unwrap());
+
+        for bad in ["", "H7", "Cxyz", "C/H"] {
+            assert!(Chord::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_chord_notes() {
+        let pitches = |chord: &str| Chord::parse(chord).unwrap().notes(0, 480).iter().map(|n| n.pitch).collect::<Vec<_>>();
+        assert_eq!(pitches("C"), vec![48, 52, 55]);
+        assert_eq!(pitches("Am7"), vec![57, 60, 64, 67]);
+        // B2 under the G triad
+        assert_eq!(pitches("G/B"), vec![47, 55, 59, 62]);
+    }
+
+    #[test]
+    fn test_comp_notes() {
+        let chords = vec![
+            ChordSymbol { tick: 0, symbol: "C".to_string() },
+            ChordSymbol { tick: 2880, symbol: "G7".to_string() },
+        ];
+        let notes = comp_notes(&chords, 1920, 0);
+        let spans: Vec<(u32, u32)> = notes.iter().filter(|n| n.pitch % 12 == 7).map(|n| (n.start_tick, n.end_tick())).collect();
+        // C for a bar and a half, G7 restruck at the barline and held to the end of its bar
+        assert_eq!(spans, vec![(0, 1920), (1920, 2880), (2880, 3840)]);
+        assert!(notes.iter().all(|n| n.voice == CHORD_VOICE));
+        assert_eq!(comp_notes(&chords, 1920, 7680).last().unwrap().end_tick(), 7680);
+    }
+}
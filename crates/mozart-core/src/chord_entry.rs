@@ -0,0 +1,132 @@
+//! Chord entry from a keyboard
+//!
+//! Keys pressed close together are one chord: the first press opens a
+//! capture window of [`CAPTURE_WINDOW_MS`], presses within it join the chord,
+//! and once it has passed [`ChordCapture::poll`] hands the chord back for the
+//! host to add in a single edit (one undo step). Times are milliseconds on
+//! any steady clock (e.g., `performance.now()`).
+
+use serde::{Deserialize, Serialize};
+use crate::note::Note;
+
+/// How long after the first key later keys still join the chord
+pub const CAPTURE_WINDOW_MS: f64 = 150.0;
+
+/// A key of a captured chord
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedKey {
+    pub pitch: u8,
+    pub velocity: u8,
+}
+
+/// Collects key presses into chords
+#[derive(Debug, Clone)]
+pub struct ChordCapture {
+    window_ms: f64,
+    opened_at_ms: Option<f64>,
+    keys: Vec<CapturedKey>,
+}
+
+impl Default for ChordCapture {
+    fn default() -> Self {
+        Self::with_window(CAPTURE_WINDOW_MS)
+    }
+}
+
+impl ChordCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_window(window_ms: f64) -> Self {
+        ChordCapture { window_ms: window_ms.max(0.0), opened_at_ms: None, keys: Vec::new() }
+    }
+
+    pub fn window_ms(&self) -> f64 {
+        self.window_ms
+    }
+
+    /// Record a key press; returns the previous chord if its window had already closed
+    ///
+    /// A key pressed twice within one window counts once, at its louder velocity.
+    pub fn press(&mut self, pitch: u8, velocity: u8, now_ms: f64) -> Option<Vec<CapturedKey>> {
+        let closed = self.poll(now_ms);
+        self.opened_at_ms.get_or_insert(now_ms);
+        match self.keys.iter_mut().find(|key| key.pitch == pitch) {
+            Some(key) => key.velocity = key.velocity.max(velocity),
+            None => self.keys.push(CapturedKey { pitch, velocity }),
+        }
+        closed
+    }
+
+    /// The chord, once its capture window has passed
+    pub fn poll(&mut self, now_ms: f64) -> Option<Vec<CapturedKey>> {
+        let opened_at = self.opened_at_ms?;
+        if now_ms - opened_at < self.window_ms {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// Close the window now (e.g., when chord entry is switched off)
+    pub fn flush(&mut self) -> Option<Vec<CapturedKey>> {
+        self.opened_at_ms = None;
+        if self.keys.is_empty() {
+            return None;
+        }
+        let mut keys = std::mem::take(&mut self.keys);
+        keys.sort_by_key(|key| key.pitch);
+        tracing::debug!("Captured chord of {} keys", keys.len());
+        Some(keys)
+    }
+}
+
+/// Notes of a captured chord, all starting at `start_tick`, lowest first
+pub fn chord_notes(keys: &[CapturedKey], start_tick: u32, duration_ticks: u32) -> Vec<Note> {
+    let mut notes: Vec<Note> = keys
+        .iter()
+        .filter(|key| key.pitch <= 127)
+        .map(|key| Note::with_velocity(key.pitch, start_tick, duration_ticks.max(1), key.velocity.clamp(1, 127)))
+        .collect();
+    notes.sort_by_key(|note| note.pitch);
+    notes.dedup_by_key(|note| note.pitch);
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn pitches(keys: &[CapturedKey]) -> Vec<u8> {
+        keys.iter().map(|key| key.pitch).collect()
+    }
+
+    #[test]
+    fn test_capture_window() {
+        let mut capture = ChordCapture::new();
+        assert!(capture.press(64, 80, 0.0).is_none());
+        assert!(capture.press(60, 90, 40.0).is_none());
+        assert!(capture.press(64, 100, 60.0).is_none());
+        assert!(capture.poll(100.0).is_none());
+
+        let chord = capture.poll(150.0).unwrap();
+        assert_eq!(pitches(&chord), vec![60, 64]);
+        assert_eq!(chord[1].velocity, 100);
+        assert!(capture.poll(1_000.0).is_none());
+
+        // A press after the window closes the last chord and starts the next
+        capture.press(67, 80, 2_000.0);
+        let closed = capture.press(72, 80, 2_500.0).unwrap();
+        assert_eq!(pitches(&closed), vec![67]);
+        assert_eq!(pitches(&capture.flush().unwrap()), vec![72]);
+        assert!(capture.flush().is_none());
+    }
+
+    #[test]
+    fn test_chord_notes() {
+        let keys = [CapturedKey { pitch: 67, velocity: 0 }, CapturedKey { pitch: 60, velocity: 100 }];
+        let notes = chord_notes(&keys, 960, 480);
+        assert_eq!(notes, vec![Note::with_velocity(60, 960, 480, 100), Note::with_velocity(67, 960, 480, 1)]);
+    }
+}
@@ -0,0 +1,244 @@
+//! ChordPro export
+//!
+//! Writes a song as a ChordPro chord-and-lyrics sheet, the plain-text format
+//! songbook apps read: `{title: …}`-style directives, each lyric line with
+//! its chords inline before the syllable they fall on (`[Am]Hap-py`), and a
+//! `{start_of_…}` section at every marker (chorus or bridge when the marker
+//! says so, verse otherwise). Chords come from the chord lane when the song
+//! has one; otherwise they are named from the notes struck together,
+//! leaving out a melody note on top that doesn't belong to the chord. A
+//! chord is written again only when the harmony changes. Chords
+//! ahead of the first lyric, or in a section without lyrics, get a line of
+//! their own.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::error::{MozartError, Result};
+use crate::presets::DRUM_VOICE;
+use crate::scale::{Scale, ScaleType};
+use crate::song::Song;
+use crate::theory::chord_symbol;
+
+/// Where the harmony changes, and to what
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChordChange {
+    pub tick: u32,
+    /// e.g., "Am7" or "C/E"
+    pub symbol: String,
+}
+
+// Name the pitches (sorted, low to high) struck at one tick, without the top one if need be
+fn name_pitches(pitches: &[u8]) -> Option<String> {
+    let name = |pitches: &[u8]| {
+        let mut classes: Vec<u8> = pitches.iter().map(|p| p % 12).collect();
+        classes.sort_unstable();
+        classes.dedup();
+        if classes.len() < 3 {
+            return None;
+        }
+        chord_symbol(&classes, pitches[0])
+    };
+    name(pitches).or_else(|| name(&pitches[..pitches.len().saturating_sub(1)]))
+}
+
+/// The song's chord symbols, or else the chords its notes spell (drums
+/// aside), where they change
+pub fn song_chords(song: &Song) -> Vec<ChordChange> {
+    if !song.chords.is_empty() {
+        let mut chords: Vec<ChordChange> = Vec::new();
+        for chord in &song.chords {
+            if chords.last().is_none_or(|last| last.symbol != chord.symbol) {
+                chords.push(ChordChange { tick: chord.tick, symbol: chord.symbol.clone() });
+            }
+        }
+        return chords;
+    }
+
+    let mut onsets: BTreeMap<u32, Vec<u8>> = BTreeMap::new();
+    for note in song.notes.iter().filter(|n| n.voice != DRUM_VOICE) {
+        onsets.entry(note.start_tick).or_default().push(note.pitch);
+    }
+
+    let mut chords: Vec<ChordChange> = Vec::new();
+    for (tick, mut pitches) in onsets {
+        pitches.sort_unstable();
+        let Some(symbol) = name_pitches(&pitches) else { continue };
+        if chords.last().is_none_or(|last| last.symbol != symbol) {
+            chords.push(ChordChange { tick, symbol });
+        }
+    }
+    chords
+}
+
+// "C", "F#m"
+fn key_symbol(key: &Scale) -> String {
+    match key.scale_type {
+        ScaleType::NaturalMinor | ScaleType::HarmonicMinor | ScaleType::MelodicMinor => format!("{}m", key.root),
+        _ => key.root.to_string(),
+    }
+}
+
+// Section environment for a marker name
+fn section_kind(name: &str) -> &'static str {
+    let name = name.to_lowercase();
+    if name.contains("chorus") || name.contains("refrain") {
+        "chorus"
+    } else if name.contains("bridge") {
+        "bridge"
+    } else {
+        "verse"
+    }
+}
+
+/// The song as a ChordPro sheet
+pub fn chordpro_text(song: &Song) -> Result<String> {
+    let chords = song_chords(song);
+    if song.lyrics.is_empty() && chords.is_empty() {
+        return Err(MozartError::LyricsError("The song has no lyrics or chords".to_string()));
+    }
+
+    let mut sheet = format!("{{title: {}}}\n", song.metadata.title);
+    if !song.metadata.composer.is_empty() {
+        sheet.push_str(&format!("{{composer: {}}}\n", song.metadata.composer));
+    }
+    sheet.push_str(&format!("{{key: {}}}\n", key_symbol(&song.settings.key)));
+    sheet.push_str(&format!("{{time: {}}}\n", song.settings.time_signature));
+    sheet.push_str(&format!("{{tempo: {}}}\n", song.settings.tempo));
+
+    // A line starts at each lyric line and each marker (and at 0, for an intro)
+    let mut starts: Vec<u32> = std::iter::once(0)
+        .chain(song.lyrics.iter().enumerate().filter(|(i, l)| l.new_line || *i == 0).map(|(_, l)| l.tick))
+        .chain(song.markers.iter().map(|m| m.tick))
+        .collect();
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut section: Option<&'static str> = None;
+    let mut paragraph = true;
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(u32::MAX);
+        let in_line = |tick: u32| tick >= start && tick < end;
+
+        for marker in song.markers.iter().filter(|m| m.tick == start) {
+            if let Some(kind) = section.take() {
+                sheet.push_str(&format!("{{end_of_{}}}\n", kind));
+            }
+            let kind = section_kind(&marker.name);
+            sheet.push_str(&format!("\n{{start_of_{}: {}}}\n", kind, marker.name));
+            section = Some(kind);
+            paragraph = false;
+        }
+
+        let mut line_chords = chords.iter().filter(|c| in_line(c.tick)).peekable();
+        let mut line = String::new();
+        for lyric in song.lyrics.iter().filter(|l| in_line(l.tick)) {
+            while let Some(chord) = line_chords.next_if(|c| c.tick <= lyric.tick) {
+                line.push_str(&format!("[{}]", chord.symbol));
+            }
+            match lyric.text.strip_suffix('-') {
+                Some(syllable) => line.push_str(syllable),
+                None => {
+                    line.push_str(&lyric.text);
+                    line.push(' ');
+                }
+            }
+        }
+        // Chords after the last syllable trail the line; a line without lyrics is all chords
+        let rest: Vec<String> = line_chords.map(|c| format!("[{}]", c.symbol)).collect();
+        if !rest.is_empty() {
+            line = format!("{} {}", line.trim_end(), rest.join(" "));
+        }
+
+        let line = line.trim();
+        if !line.is_empty() {
+            if paragraph {
+                sheet.push('\n');
+                paragraph = false;
+            }
+            sheet.push_str(line);
+            sheet.push('\n');
+        }
+    }
+    if let Some(kind) = section {
+        sheet.push_str(&format!("{{end_of_{}}}\n", kind));
+    }
+
+    tracing::info!("ChordPro export complete: {} lyric syllables, {} chords", song.lyrics.len(), chords.len());
+    Ok(sheet)
+}
+
+/// Export a song to a ChordPro file (`.cho`)
+pub fn export_chordpro(song: &Song, path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let sheet = chordpro_text(song)?;
+    std::fs::write(path, sheet).map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))?;
+
+    tracing::info!("ChordPro file saved: {:?}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chart::import_chord_chart;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    // A quarter-note melody over C, G, and C chords in voice 1
+    fn song() -> Song {
+        let mut song = Song::with_title("Birthday");
+        song.add_notes((0..8).map(|i| Note::new(72, i * 480, 480)));
+        for (start, pitches) in [(0, [48, 52, 55]), (1920, [43, 47, 50]), (2880, [48, 52, 55])] {
+            song.add_notes(pitches.iter().map(|&p| Note::with_voice(p, start, 960, 80, 1)));
+        }
+        song.set_lyrics("Hap-py birth-day _\nto you");
+        song
+    }
+
+    #[test]
+    fn test_song_chords() {
+        let symbols: Vec<(u32, String)> = song_chords(&song()).into_iter().map(|c| (c.tick, c.symbol)).collect();
+        assert_eq!(symbols, vec![(0, "C".to_string()), (1920, "G".to_string()), (2880, "C".to_string())]);
+
+        // Repeated chords are written once; inversions get a slash bass
+        let mut chart = Song::new();
+        import_chord_chart(&mut chart, "| C | C | G7/B | Am |").unwrap();
+        let symbols: Vec<String> = song_chords(&chart).into_iter().map(|c| c.symbol).collect();
+        assert_eq!(symbols, vec!["C", "G7/B", "Am"]);
+
+        // Symbols in the chord lane win over what the notes spell
+        let mut song = song();
+        song.set_chord(0, "Cmaj7").unwrap();
+        song.set_chord(1920, "Cmaj7").unwrap();
+        song.set_chord(2880, "Dm7").unwrap();
+        let symbols: Vec<(u32, String)> = song_chords(&song).into_iter().map(|c| (c.tick, c.symbol)).collect();
+        assert_eq!(symbols, vec![(0, "Cmaj7".to_string()), (2880, "Dm7".to_string())]);
+        assert!(chordpro_text(&song).unwrap().ends_with("\n[Cmaj7]Happy birthday\nto [Dm7]you\n"));
+    }
+
+    #[test]
+    fn test_chordpro_sheet() {
+        let mut song = song();
+        song.metadata.composer = "Trad.".to_string();
+        assert_eq!(
+            chordpro_text(&song).unwrap(),
+            "{title: Birthday}\n{composer: Trad.}\n{key: C}\n{time: 4/4}\n{tempo: 120}\n\n\
+             [C]Happy birthday [G]\nto [C]you\n"
+        );
+
+        // Markers open sections; chords before the lyrics get their own line
+        song.notes.retain(|n| n.voice == 1 || n.start_tick >= 960);
+        song.set_lyrics("birth-day _\nto you");
+        song.add_marker("Intro", 0);
+        song.add_marker("Chorus", 2400);
+        assert_eq!(
+            chordpro_text(&song).unwrap(),
+            "{title: Birthday}\n{composer: Trad.}\n{key: C}\n{time: 4/4}\n{tempo: 120}\n\n\
+             {start_of_verse: Intro}\n[C]\nbirthday [G]\n{end_of_verse}\n\n\
+             {start_of_chorus: Chorus}\nto [C]you\n{end_of_chorus}\n"
+        );
+
+        assert!(chordpro_text(&Song::new()).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Operation log and CRDT for collaborative note editing
+//!
+//! Groundwork for realtime collaboration (`collab` feature). Each replica
+//! edits its own [`NoteDoc`]; every edit becomes an [`Op`] on a note with a
+//! stable [`NoteId`], stamped with a Lamport [`Timestamp`]. Replicas send
+//! each other [`OpBatch`]es (through a server, in any order, possibly twice)
+//! and all end up with the same notes:
+//!
+//! - Inserts and updates carry the whole note; the latest timestamp wins.
+//! - Deletes leave a tombstone, so a concurrent update can't revive a note.
+//! - Applying an op twice, or ops in a different order, changes nothing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+/// Identifies one editor in a session
+pub type ReplicaId = u32;
+
+/// Lamport clock stamp; ties between replicas break by replica id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub lamport: u64,
+    pub replica: ReplicaId,
+}
+
+/// Stable note identity: the timestamp of the insert that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct NoteId {
+    pub lamport: u64,
+    pub replica: ReplicaId,
+}
+
+/// One edit to the notes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Op {
+    Insert { id: NoteId, note: Note, at: Timestamp },
+    /// Replace a note's fields (pitch, timing, velocity, ...)
+    Update { id: NoteId, note: Note, at: Timestamp },
+    Delete { id: NoteId, at: Timestamp },
+}
+
+impl Op {
+    pub fn id(&self) -> NoteId {
+        match self {
+            Op::Insert { id, .. } | Op::Update { id, .. } | Op::Delete { id, .. } => *id,
+        }
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        match self {
+            Op::Insert { at, .. } | Op::Update { at, .. } | Op::Delete { at, .. } => *at,
+        }
+    }
+}
+
+/// Ops from one replica, as sent over the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpBatch {
+    pub replica: ReplicaId,
+    pub ops: Vec<Op>,
+}
+
+impl OpBatch {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    note: Note,
+    written: Timestamp,
+    deleted: bool,
+}
+
+/// One replica's copy of the notes
+#[derive(Debug, Clone)]
+pub struct NoteDoc {
+    replica: ReplicaId,
+    clock: u64,
+    entries: BTreeMap<NoteId, Entry>,
+    /// Every op applied here, local and remote, in application order
+    log: Vec<Op>,
+    /// Local ops not yet taken by [`NoteDoc::take_batch`]
+    pending: Vec<Op>,
+}
+
+impl NoteDoc {
+    pub fn new(replica: ReplicaId) -> Self {
+        NoteDoc { replica, clock: 0, entries: BTreeMap::new(), log: Vec::new(), pending: Vec::new() }
+    }
+
+    pub fn replica(&self) -> ReplicaId {
+        self.replica
+    }
+
+    /// Add a note, returning its id
+    pub fn insert(&mut self, note: Note) -> NoteId {
+        let at = self.tick();
+        let id = NoteId { lamport: at.lamport, replica: at.replica };
+        self.local(Op::Insert { id, note, at });
+        id
+    }
+
+    /// Replace a live note
+    pub fn update(&mut self, id: NoteId, note: Note) -> Result<()> {
+        self.live(id)?;
+        let at = self.tick();
+        self.local(Op::Update { id, note, at });
+        Ok(())
+    }
+
+    /// Delete a live note
+    pub fn delete(&mut self, id: NoteId) -> Result<()> {
+        self.live(id)?;
+        let at = self.tick();
+        self.local(Op::Delete { id, at });
+        Ok(())
+    }
+
+    /// Apply an op from any replica; returns whether the notes changed
+    ///
+    /// Ops may arrive in any order and more than once.
+    pub fn apply(&mut self, op: Op) -> bool {
+        let at = op.timestamp();
+        self.clock = self.clock.max(at.lamport);
+        let changed = match &op {
+            Op::Insert { id, note, at } | Op::Update { id, note, at } => match self.entries.get_mut(id) {
+                Some(entry) if entry.deleted || entry.written >= *at => false,
+                Some(entry) => {
+                    entry.note = note.clone();
+                    entry.written = *at;
+                    true
+                }
+                // An update can arrive before its insert; it carries the whole note
+                None => {
+                    self.entries.insert(*id, Entry { note: note.clone(), written: *at, deleted: false });
+                    true
+                }
+            },
+            Op::Delete { id, at } => match self.entries.get_mut(id) {
+                Some(entry) if entry.deleted => false,
+                Some(entry) => {
+                    entry.deleted = true;
+                    true
+                }
+                // Tombstone a note not seen yet, so its insert stays dead
+                None => {
+                    let note = Note::new(0, 0, 0);
+                    self.entries.insert(*id, Entry { note, written: *at, deleted: true });
+                    true
+                }
+            },
+        };
+        if changed {
+            self.log.push(op);
+        }
+        changed
+    }
+
+    /// Apply a batch from another replica; returns the number of ops that changed the notes
+    pub fn merge(&mut self, batch: &OpBatch) -> usize {
+        tracing::debug!("Merging {} ops from replica {}", batch.ops.len(), batch.replica);
+        batch.ops.iter().filter(|op| self.apply((*op).clone())).count()
+    }
+
+    /// Local ops since the last call, to send to the other replicas
+    pub fn take_batch(&mut self) -> OpBatch {
+        OpBatch { replica: self.replica, ops: std::mem::take(&mut self.pending) }
+    }
+
+    /// Every op applied so far
+    pub fn log(&self) -> &[Op] {
+        &self.log
+    }
+
+    /// Live notes with their ids, by start tick (then id, so every replica agrees)
+    pub fn notes(&self) -> Vec<(NoteId, &Note)> {
+        let mut notes: Vec<(NoteId, &Note)> =
+            self.entries.iter().filter(|(_, e)| !e.deleted).map(|(id, e)| (*id, &e.note)).collect();
+        notes.sort_by_key(|(id, note)| (note.start_tick, *id));
+        notes
+    }
+
+    pub fn get(&self, id: NoteId) -> Option<&Note> {
+        self.entries.get(&id).filter(|e| !e.deleted).map(|e| &e.note)
+    }
+
+    fn live(&self, id: NoteId) -> Result<()> {
+        match self.get(id) {
+            Some(_) => Ok(()),
+            None => Err(MozartError::InvalidEdit(format!("No note {}:{}", id.replica, id.lamport))),
+        }
+    }
+
+    fn tick(&mut self) -> Timestamp {
+        self.clock += 1;
+        Timestamp { lamport: self.clock, replica: self.replica }
+    }
+
+    fn local(&mut self, op: Op) {
+        self.pending.push(op.clone());
+        self.apply(op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn snapshot(doc: &NoteDoc) -> Vec<(NoteId, Note)> {
+        doc.notes().into_iter().map(|(id, note)| (id, note.clone())).collect()
+    }
+
+    #[test]
+    fn test_two_replicas_converge() {
+        let mut a = NoteDoc::new(1);
+        let mut b = NoteDoc::new(2);
+        let c4 = a.insert(Note::new(60, 0, 480));
+        let e4 = a.insert(Note::new(64, 480, 480));
+        b.merge(&a.take_batch());
+        assert_eq!(snapshot(&a), snapshot(&b));
+
+        // Concurrent edits: both change C4, A deletes E4 while B moves it
+        a.update(c4, Note::new(62, 0, 480)).unwrap();
+        a.delete(e4).unwrap();
+        a.insert(Note::new(67, 960, 240));
+        b.update(c4, Note::new(60, 0, 960)).unwrap();
+        b.update(e4, Note::new(65, 480, 480)).unwrap();
+        b.insert(Note::new(72, 960, 240));
+
+        let (from_a, from_b) = (a.take_batch(), b.take_batch());
+        // Over the wire, and delivered twice to B
+        let from_a = OpBatch::from_json(&from_a.to_json().unwrap()).unwrap();
+        a.merge(&from_b);
+        b.merge(&from_a);
+        assert_eq!(b.merge(&from_a), 0);
+
+        assert_eq!(snapshot(&a), snapshot(&b));
+        // Same lamport time: the higher replica's update wins; the delete beats the move
+        assert_eq!(a.get(c4).unwrap().duration_ticks, 960);
+        assert!(a.get(e4).is_none());
+        let pitches: Vec<u8> = a.notes().iter().map(|(_, n)| n.pitch).collect();
+        assert_eq!(pitches, vec![60, 67, 72]);
+    }
+
+    #[test]
+    fn test_out_of_order_delivery() {
+        let mut a = NoteDoc::new(1);
+        let id = a.insert(Note::new(60, 0, 480));
+        a.update(id, Note::new(61, 0, 480)).unwrap();
+        let ops = a.take_batch().ops;
+
+        // Update before insert, and a delete before either
+        let mut late = NoteDoc::new(3);
+        late.apply(ops[1].clone());
+        late.apply(ops[0].clone());
+        assert_eq!(late.get(id).unwrap().pitch, 61);
+
+        a.delete(id).unwrap();
+        let delete = a.take_batch().ops.remove(0);
+        let mut early = NoteDoc::new(4);
+        early.apply(delete);
+        ops.into_iter().for_each(|op| assert!(!early.apply(op)));
+        assert!(early.notes().is_empty());
+
+        // Local clocks move past what they've seen
+        assert!(early.insert(Note::new(60, 0, 1)).lamport > 3);
+        assert!(a.update(id, Note::new(62, 0, 480)).is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Color labels for notes and sections
+//!
+//! A label marks a phrase visually ("melody in blue, fill in orange"). It is
+//! saved with the song and drawn by the piano roll, but playback and export
+//! ignore it. Labels come from a fixed palette so every host draws the same
+//! names the same way.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+
+/// A color label from the palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Pink,
+    Gray,
+}
+
+impl ColorLabel {
+    /// Every label, in palette order
+    pub const ALL: [ColorLabel; 8] = [
+        ColorLabel::Red,
+        ColorLabel::Orange,
+        ColorLabel::Yellow,
+        ColorLabel::Green,
+        ColorLabel::Blue,
+        ColorLabel::Purple,
+        ColorLabel::Pink,
+        ColorLabel::Gray,
+    ];
+
+    /// Parse a label name (any case; "grey" is accepted); "", "-", or "none" for no label
+    pub fn parse(s: &str) -> Result<Option<ColorLabel>> {
+        let name = s.trim().to_ascii_lowercase();
+        if matches!(name.as_str(), "" | "-" | "none") {
+            return Ok(None);
+        }
+        let name = if name == "grey" { "gray".to_string() } else { name };
+        ColorLabel::ALL
+            .into_iter()
+            .find(|label| label.name() == name)
+            .map(Some)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ColorLabel::ALL.iter().map(|label| label.name()).collect();
+                MozartError::ParseError(format!("Unknown color: {} (use {})", s.trim(), names.join(", ")))
+            })
+    }
+
+    /// Lowercase name, as saved in the file
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorLabel::Red => "red",
+            ColorLabel::Orange => "orange",
+            ColorLabel::Yellow => "yellow",
+            ColorLabel::Green => "green",
+            ColorLabel::Blue => "blue",
+            ColorLabel::Purple => "purple",
+            ColorLabel::Pink => "pink",
+            ColorLabel::Gray => "gray",
+        }
+    }
+}
+
+impl fmt::Display for ColorLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ColorLabel::parse(" Blue ").unwrap(), Some(ColorLabel::Blue));
+        assert_eq!(ColorLabel::parse("grey").unwrap(), Some(ColorLabel::Gray));
+        assert_eq!(ColorLabel::parse("none").unwrap(), None);
+        assert_eq!(ColorLabel::parse("").unwrap(), None);
+        assert!(ColorLabel::parse("teal").is_err());
+
+        for label in ColorLabel::ALL {
+            assert_eq!(ColorLabel::parse(&label.to_string()).unwrap(), Some(label));
+            assert_eq!(serde_json::to_value(label).unwrap(), label.name());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Structure-of-arrays note storage (`soa-notes` feature, experimental)
+//!
+//! Large imported songs spend their time in passes that touch one or two
+//! fields of every note: transposition reads and writes only pitches,
+//! serialization repeats every field name per note. [`NoteColumns`] keeps
+//! each field in its own `Vec`, so those passes walk one dense array and the
+//! JSON is nine arrays instead of an object per note.
+//!
+//! This is a measurement, not yet the storage behind [`Song`]: `Song::notes`
+//! is a public `Vec<Note>` that every module indexes and splices, so moving
+//! to columns means changing that API. Converting to columns and back around
+//! a single pass costs more than the pass saves (see the `note_storage`
+//! bench), so `Song`'s methods don't route through here in the meantime.
+//!
+//! [`Song`]: crate::song::Song
+
+use serde::{Deserialize, Serialize};
+use crate::chance::Take;
+use crate::color::ColorLabel;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::transpose::{transpose_note, TransposeMode};
+
+/// Notes stored one field per array; index `i` of every array is note `i`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteColumns {
+    pub pitch: Vec<u8>,
+    pub start_tick: Vec<u32>,
+    pub duration_ticks: Vec<u32>,
+    pub velocity: Vec<u8>,
+    pub voice: Vec<u8>,
+    pub pan: Vec<u8>,
+    pub probability: Vec<u8>,
+    pub take: Vec<Option<Take>>,
+    pub color: Vec<Option<ColorLabel>>,
+}
+
+impl NoteColumns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_notes(notes: &[Note]) -> Self {
+        let mut columns = NoteColumns::with_capacity(notes.len());
+        notes.iter().for_each(|note| columns.push(note));
+        columns
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        NoteColumns {
+            pitch: Vec::with_capacity(capacity),
+            start_tick: Vec::with_capacity(capacity),
+            duration_ticks: Vec::with_capacity(capacity),
+            velocity: Vec::with_capacity(capacity),
+            voice: Vec::with_capacity(capacity),
+            pan: Vec::with_capacity(capacity),
+            probability: Vec::with_capacity(capacity),
+            take: Vec::with_capacity(capacity),
+            color: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pitch.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pitch.is_empty()
+    }
+
+    pub fn push(&mut self, note: &Note) {
+        self.pitch.push(note.pitch);
+        self.start_tick.push(note.start_tick);
+        self.duration_ticks.push(note.duration_ticks);
+        self.velocity.push(note.velocity);
+        self.voice.push(note.voice);
+        self.pan.push(note.pan);
+        self.probability.push(note.probability);
+        self.take.push(note.take);
+        self.color.push(note.color);
+    }
+
+    /// Note `index`, reassembled
+    pub fn get(&self, index: usize) -> Option<Note> {
+        Some(Note {
+            pitch: *self.pitch.get(index)?,
+            start_tick: self.start_tick[index],
+            duration_ticks: self.duration_ticks[index],
+            velocity: self.velocity[index],
+            voice: self.voice[index],
+            pan: self.pan[index],
+            probability: self.probability[index],
+            take: self.take[index],
+            color: self.color[index],
+        })
+    }
+
+    pub fn to_notes(&self) -> Vec<Note> {
+        (0..self.len()).filter_map(|i| self.get(i)).collect()
+    }
+
+    /// Transpose every note in place, or none if a pitch would leave MIDI range
+    ///
+    /// Only the pitch column is touched, and each distinct pitch is
+    /// transposed once.
+    pub fn transpose(&mut self, mode: &TransposeMode) -> Result<()> {
+        let mut used = [false; 128];
+        for &pitch in &self.pitch {
+            *used.get_mut(pitch as usize).ok_or_else(|| MozartError::InvalidPitch(format!("MIDI {}", pitch)))? = true;
+        }
+        let mut table = [0u8; 128];
+        for pitch in (0..128u8).filter(|&p| used[p as usize]) {
+            table[pitch as usize] = transpose_note(&Note::new(pitch, 0, 0), mode)?.pitch;
+        }
+        tracing::debug!("Transposing {} notes in columns: {}", self.len(), mode.description());
+        self.pitch.iter_mut().for_each(|pitch| *pitch = table[*pitch as usize]);
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parse columns JSON, checking every column has the same length
+    pub fn from_json(json: &str) -> Result<Self> {
+        let columns: NoteColumns = serde_json::from_str(json)?;
+        let len = columns.len();
+        let lengths = [
+            columns.start_tick.len(),
+            columns.duration_ticks.len(),
+            columns.velocity.len(),
+            columns.voice.len(),
+            columns.pan.len(),
+            columns.probability.len(),
+            columns.take.len(),
+            columns.color.len(),
+        ];
+        if lengths.iter().any(|&l| l != len) {
+            return Err(MozartError::ParseError(format!("Note columns differ in length: {} vs {:?}", len, lengths)));
+        }
+        Ok(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::Scale;
+    use crate::transpose::transpose_notes;
+    use pretty_assertions::assert_eq;
+
+    fn notes() -> Vec<Note> {
+        (0..20u32).map(|i| Note::with_voice(60 + (i % 12) as u8, i * 240, 240, 50 + i as u8, (i % 2) as u8)).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let columns = NoteColumns::from_notes(&notes());
+        assert_eq!(columns.len(), 20);
+        assert_eq!(columns.to_notes(), notes());
+        assert_eq!(NoteColumns::from_json(&columns.to_json().unwrap()).unwrap(), columns);
+
+        let ragged = r#"{"pitch":[60],"start_tick":[],"duration_ticks":[1],"velocity":[1],"voice":[0],"pan":[64]}"#;
+        assert!(NoteColumns::from_json(ragged).is_err());
+    }
+
+    #[test]
+    fn test_transpose_matches_notes() {
+        for mode in [TransposeMode::chromatic(-7), TransposeMode::diatonic(Scale::c_major(), 3)] {
+            let mut columns = NoteColumns::from_notes(&notes());
+            columns.transpose(&mode).unwrap();
+            assert_eq!(columns.to_notes(), transpose_notes(&notes(), &mode).unwrap());
+        }
+
+        // Out of range: nothing moves
+        let mut columns = NoteColumns::from_notes(&[Note::new(60, 0, 480), Note::new(120, 480, 480)]);
+        assert!(columns.transpose(&TransposeMode::chromatic(12)).is_err());
+        assert_eq!(columns.pitch, vec![60, 120]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Command registry for menus and the command palette
+//!
+//! Every action a user can invoke, with a stable id the frontend maps to its
+//! handler, plus a fuzzy [`search_commands`] for a Ctrl+K palette. Shortcuts
+//! use `Mod` for Ctrl (⌘ on macOS).
+
+use serde::Serialize;
+
+/// An invokable action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CommandInfo {
+    /// Stable identifier (e.g., "file.save")
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Menu the command lives in
+    pub category: &'static str,
+    pub description: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shortcut: Option<&'static str>,
+}
+
+const fn command(
+    id: &'static str,
+    category: &'static str,
+    name: &'static str,
+    shortcut: Option<&'static str>,
+    description: &'static str,
+) -> CommandInfo {
+    CommandInfo { id, name, category, description, shortcut }
+}
+
+/// All commands, in menu order
+pub const COMMANDS: &[CommandInfo] = &[
+    command("file.new", "File", "New Song", None, "Start an empty song"),
+    command("file.newSongDefaults", "File", "New Song Defaults…", None, "Choose the tempo, meter, key, instrument, and pickup new songs start with"),
+    command("file.open", "File", "Open…", Some("Mod+O"), "Open a .mozart.json or MIDI file"),
+    command("file.mergeSongs", "File", "Merge Songs…", None, "Layer other .mozart.json or MIDI files into this song, each on its own voices and MIDI channel"),
+    command("file.splitSong", "File", "Split into Practice Files…", None, "Cut the song at measures or section markers and save each piece as a numbered file"),
+    command("file.compareSong", "File", "Compare with Song…", None, "Score how alike this melody is to another file's, even in another key, and find the passage they share"),
+    command("file.playComparison", "File", "Play Comparison with Version…", None, "Hear this song against another version of it, together or measure by measure"),
+    command("file.importChordChart", "File", "Import Chord Chart…", None, "Write a text chord chart into the song"),
+    command("file.save", "File", "Save", Some("Mod+S"), "Save the song as .mozart.json"),
+    command("file.exportMidi", "File", "Export MIDI…", None, "Export the song as a Standard MIDI File"),
+    command("file.exportPracticeAudio", "File", "Export Practice Audio…", None, "Export a WAV to play along with, slowed down without changing pitch"),
+    command("file.exportLrc", "File", "Export Lyrics (LRC)…", None, "Export timed lyrics for sing-along players"),
+    command("file.exportChordPro", "File", "Export Chords and Lyrics (ChordPro)…", None, "Export a chord-and-lyrics sheet for songbook apps"),
+    command("file.exportSettings", "File", "Export Settings…", None, "Save every preference to one file, to move them to another machine"),
+    command("file.importSettings", "File", "Import Settings…", None, "Replace the preferences with ones exported from Mozart"),
+    command("edit.undo", "Edit", "Undo", Some("Mod+Z"), "Revert the last edit"),
+    command("edit.redo", "Edit", "Redo", Some("Mod+Shift+Z"), "Reapply the last undone edit"),
+    command("edit.deleteNote", "Edit", "Delete Note", Some("Backspace"), "Remove the selected note"),
+    command("edit.clearNotes", "Edit", "Clear All Notes", None, "Remove every note from the song"),
+    command("edit.deleteMeasures", "Edit", "Delete Measures…", None, "Remove measures and pull the music after them back"),
+    command("edit.legato", "Edit", "Make Legato", None, "Extend each note to the start of the next"),
+    command("edit.lyrics", "Edit", "Edit Lyrics…", None, "Set the melody's lyrics, one syllable per note"),
+    command("edit.addAnnotation", "Edit", "Add Annotation…", None, "Leave a comment at the playhead, or over measures (e.g., \"breathe here\")"),
+    command("edit.removeAnnotation", "Edit", "Remove Annotation…", None, "Delete one of the song's annotations"),
+    command("edit.chordSymbol", "Edit", "Chord Symbol…", None, "Put a chord symbol (Cmaj7, F#m7b5, G/B) on the chord track at the playhead's beat; playback comps it under the melody"),
+    command("edit.invertUp", "Edit", "Invert Chord Up", None, "Move the chord's lowest note to the top"),
+    command("edit.invertDown", "Edit", "Invert Chord Down", None, "Move the chord's highest note to the bottom"),
+    command("edit.openVoicing", "Edit", "Open Voicing", None, "Spread the chord over more than an octave"),
+    command("edit.closeVoicing", "Edit", "Close Voicing", None, "Pack the chord into the octave above its bass"),
+    command("edit.chordSuggestions", "Edit", "Chord Suggestions…", None, "Suggest the next chord and reharmonize a measure"),
+    command("edit.modulation", "Edit", "Plan Modulation…", None, "Find pivot-chord and common-tone routes to a new key"),
+    command("edit.counterpointAbove", "Edit", "Counterpoint Above", None, "Write a first-species line above the melody in voice 2"),
+    command("edit.counterpointBelow", "Edit", "Counterpoint Below", None, "Write a first-species line below the melody in voice 2"),
+    command("edit.continueMelody", "Edit", "Continue Melody…", None, "Preview 1-4 more measures of the melody in its key and meter, learned from folk tunes and the song"),
+    command("edit.generateMelody", "Edit", "Melody from Chords…", None, "Write a melody into voice 2 that outlines a chord chart, chord tones on the accented beats"),
+    command("edit.variations", "Edit", "Variations…", None, "Audition rhythmic, neighbor-tone, and octave variations of the selected voice"),
+    command("edit.extractGroove", "Edit", "Extract Groove…", None, "Take the timing and accent feel of the selected voice"),
+    command("edit.grooveFromMidi", "Edit", "Groove from MIDI File…", None, "Take the timing and accent feel of a recorded performance"),
+    command("edit.applyGroove", "Edit", "Apply Groove", None, "Give the selected voice the extracted groove's feel"),
+    command("edit.noteChance", "Edit", "Note Chance…", None, "Make the selected note play only on some passes, or in take lane A or B (experimental)"),
+    command("edit.freezeArrangement", "Edit", "Freeze Arrangement", None, "Keep the notes the last pass played and drop every chance and take lane"),
+    command("edit.flattenEffects", "Edit", "Flatten Playback Effects", None, "Write strums, accent dynamics, and track volumes into the notes, so export matches what plays"),
+    command("edit.noteColor", "Edit", "Color Label…", None, "Color the selected note's voice in the piano roll (e.g., melody in blue, fill in orange)"),
+    command("edit.sectionColor", "Edit", "Section Color…", None, "Color the section under the playhead, from its marker to the next"),
+    command("edit.addTrack", "Edit", "Add Track…", None, "Add a named layer (bassline, harmony) on a voice of its own, with its own instrument"),
+    command("edit.voiceMeter", "Edit", "Voice Meter…", None, "Give the selected note's voice its own time signature (e.g., an ostinato in 7/8 under 4/4)"),
+    command("repeat.add", "Repeats", "Add Repeat…", None, "Put repeat barlines around measures, played two or more times"),
+    command("repeat.ending", "Repeats", "Add Ending…", None, "Mark measures as a numbered 1st/2nd ending of a repeat"),
+    command("repeat.sign", "Repeats", "Place Sign…", None, "Place a segno, Fine, To Coda, or Coda sign at a measure"),
+    command("repeat.jump", "Repeats", "Set Jump…", None, "Jump back with D.C. or D.S., al Fine or al Coda"),
+    command("repeat.remove", "Repeats", "Remove Repeat Marks…", None, "Remove the repeats, endings, signs, and jump at a measure"),
+    command("repeat.clear", "Repeats", "Clear Repeats", None, "Remove every repeat, ending, sign, and jump"),
+    command("pattern.create", "Patterns", "New Pattern from Measures…", None, "Cut measures into a named pattern (clip) to chain into a song structure"),
+    command("pattern.clone", "Patterns", "Clone Pattern…", None, "Copy a pattern under a new name, to vary one use of it"),
+    command("pattern.move", "Patterns", "Move Pattern…", None, "Reorder the list of patterns"),
+    command("pattern.remove", "Patterns", "Delete Pattern…", None, "Delete a pattern and its places in the chain"),
+    command("pattern.chain", "Patterns", "Pattern Chain…", None, "Set the order patterns play in from the start of the song (e.g., A A B A); playback and export expand it"),
+    command("cleanup.repair", "Cleanup", "Repair Song", None, "Fix overlapping, silent, zero-length, and out-of-range notes"),
+    command("cleanup.removeDuplicates", "Cleanup", "Remove Duplicate Notes…", None, "Preview, then remove doubled and zero-length notes and merge near-identical overlaps"),
+    command("cleanup.normalizeVelocities", "Cleanup", "Normalize Velocities…", None, "Rescale every velocity onto a range, keeping relative dynamics"),
+    command("cleanup.compressVelocities", "Cleanup", "Compress Velocities…", None, "Bring loud notes closer to a threshold"),
+    command("tool.draw", "Edit", "Draw Tool", Some("D"), "Click to add and select notes"),
+    command("tool.split", "Edit", "Split Tool", Some("X"), "Click a note to split it at the cursor"),
+    command("tool.join", "Edit", "Join Tool", Some("G"), "Click notes to glue them together"),
+    command("tool.chordEntry", "Edit", "Chord Entry", None, "Add piano keys pressed together as one chord at the playhead"),
+    command("transpose.up", "Transpose", "Transpose Up a Semitone", None, "Move every note up one semitone"),
+    command("transpose.down", "Transpose", "Transpose Down a Semitone", None, "Move every note down one semitone"),
+    command("transpose.octaveUp", "Transpose", "Transpose Up an Octave", None, "Move every note up twelve semitones"),
+    command("transpose.octaveDown", "Transpose", "Transpose Down an Octave", None, "Move every note down twelve semitones"),
+    command("transpose.stepUp", "Transpose", "Diatonic Step Up", None, "Move every note up one scale degree"),
+    command("transpose.stepDown", "Transpose", "Diatonic Step Down", None, "Move every note down one scale degree"),
+    command("transpose.instrumentRange", "Transpose", "Instrument Range…", None, "Check notes against a flute's, an alto voice's, or another instrument's range; rows outside it are shaded and notes placed there warn"),
+    command("transpose.foldIntoRange", "Transpose", "Fold into Range…", None, "Move notes outside an instrument range in by octaves, keeping the melody's shape"),
+    command("macro.record", "Macro", "Record Macro", None, "Start recording the commands you run"),
+    command("macro.stop", "Macro", "Stop Recording…", None, "Name the recorded commands as a macro, with an optional shortcut"),
+    command("macro.play", "Macro", "Play Macro…", None, "Run a saved macro's commands in order"),
+    command("macro.delete", "Macro", "Delete Macro…", None, "Remove a saved macro"),
+    command("view.zoomIn", "View", "Zoom In", None, "Widen the piano roll"),
+    command("view.zoomOut", "View", "Zoom Out", None, "Narrow the piano roll"),
+    command("view.pitchRange", "View", "Pitch Range…", None, "Fit the piano roll rows to the notes, or show a fixed range such as C1-C7"),
+    command("view.octaveUp", "View", "Scroll Up an Octave", None, "Show the piano roll rows an octave higher"),
+    command("view.octaveDown", "View", "Scroll Down an Octave", None, "Show the piano roll rows an octave lower"),
+    command("view.followPage", "View", "Follow by Page", None, "Flip pages as the playhead leaves the view"),
+    command("view.followContinuous", "View", "Follow Continuously", None, "Keep the playhead centered while playing"),
+    command("view.followOff", "View", "Don't Follow", None, "Leave the view where it is during playback"),
+    command("view.tracks", "View", "Tracks", None, "Show the tracks with their instrument, volume, mute, and solo"),
+    command("view.registerHeatmap", "View", "Register Heatmap", None, "Show where the notes sit in register, measure by measure"),
+    command("playback.playPause", "Playback", "Play / Pause", Some("Space"), "Start or pause playback"),
+    command("playback.stop", "Playback", "Stop", None, "Stop playback and return to the start"),
+    command("playback.metronome", "Playback", "Toggle Metronome", None, "Click along with the beat"),
+    command("playback.groovePreset", "Playback", "Groove Preset…", None, "Load an odd-meter groove (Balkan 7/8 and 11/8, Tuareg 5/4, Brubeck 9/8) with its tempo and drums"),
+    command("playback.rate", "Playback", "Practice Speed…", None, "Play slower or faster without changing the song's tempo"),
+    command("playback.start", "Playback", "Go to Start", Some("Home"), "Move the playhead to the beginning"),
+    command("playback.measure", "Playback", "Go to Measure…", Some("Mod+G"), "Move the playhead to a measure"),
+    command("playback.prevMarker", "Playback", "Previous Marker", Some("["), "Jump to the previous marker"),
+    command("playback.nextMarker", "Playback", "Next Marker", Some("]"), "Jump to the next marker"),
+    command("playback.loopMeasure", "Playback", "Loop Measure", Some("L"), "Loop the measure under the playhead"),
+    command("playback.loopNext", "Playback", "Loop Next Measure", Some("Shift+ArrowRight"), "Move the loop forward by its length"),
+    command("playback.loopPrevious", "Playback", "Loop Previous Measure", Some("Shift+ArrowLeft"), "Move the loop back by its length"),
+    command("playback.extendLoop", "Playback", "Extend Loop by a Measure", Some("Shift+L"), "Add the next measure to the loop"),
+    command("playback.clearLoop", "Playback", "Clear Loop", None, "Stop looping"),
+    command("playback.audioOutput", "Playback", "Audio Output…", None, "Choose the sample rate and buffer latency, and see what the browser granted"),
+    command("playback.callResponse", "Playback", "Call and Response…", None, "Hear a phrase, then play it back on a MIDI keyboard"),
+    command("help.palette", "Help", "Command Palette…", Some("Mod+K"), "Search for a command and run it"),
+    command("help.tutorial", "Help", "Tutorial", None, "Walk through adding notes, changing the meter, and transposing"),
+    command("help.theory", "Help", "Theory Reference", None, "Intervals, scales, chords, the circle of fifths, and cadences"),
+    command("help.notationGrammar", "Help", "Notation Grammar", None, "The melody text notation as EBNF, or JSON for editors"),
+    command("help.shortcuts", "Help", "Keyboard Shortcuts", None, "List the keyboard shortcuts"),
+    command("help.about", "Help", "About Mozart", None, "Show version information"),
+];
+
+/// Look up a command by id
+pub fn find_command(id: &str) -> Option<&'static CommandInfo> {
+    COMMANDS.iter().find(|c| c.id == id)
+}
+
+/// A command that matched a search, best first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandMatch {
+    #[serde(flatten)]
+    pub command: CommandInfo,
+    pub score: i32,
+    /// Character positions in `name` that matched, for highlighting
+    pub matched: Vec<usize>,
+}
+
+/// Commands matching a query, best first
+///
+/// Query characters must appear in order in the name (or "category name");
+/// matches at word starts and runs of consecutive characters rank higher.
+/// Commands whose description contains the query are included below those. An empty
+/// query returns every command in menu order.
+pub fn search_commands(query: &str) -> Vec<CommandMatch> {
+    let phrase = query.trim().to_lowercase();
+    let query: Vec<char> = phrase.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut matches: Vec<CommandMatch> = COMMANDS
+        .iter()
+        .filter_map(|command| {
+            let (score, matched) = if query.is_empty() {
+                (0, Vec::new())
+            } else if let Some((score, matched)) = fuzzy_match(&query, command.name) {
+                (score, matched)
+            } else if let Some((score, _)) = fuzzy_match(&query, &format!("{} {}", command.category, command.name)) {
+                (score - 10, Vec::new())
+            } else if query.len() > 2 && command.description.to_lowercase().contains(&phrase) {
+                (-20, Vec::new())
+            } else {
+                return None;
+            };
+            Some(CommandMatch { command: *command, score, matched })
+        })
+        .collect();
+    // Stable, so ties keep menu order
+    matches.sort_by_key(|m| -m.score);
+    matches
+}
+
+// Subsequence match of a lowercase query against text: (score, matched char positions)
+fn fuzzy_match(query: &[char], text: &str) -> Option<(i32, Vec<usize>)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut matched = Vec::with_capacity(query.len());
+    let mut score = 0;
+    let mut at = 0;
+    for &q in query {
+        let found = (at..chars.len()).find(|&i| chars[i].to_lowercase().eq(std::iter::once(q)))?;
+        let word_start = found == 0 || !chars[found - 1].is_alphanumeric();
+        let consecutive = matched.last().is_some_and(|&last| last + 1 == found);
+        score += 1 + if word_start { 8 } else { 0 } + if consecutive { 5 } else { 0 };
+        // Skipped characters cost a little, so tighter matches win
+        score -= (found - at).min(3) as i32;
+        matched.push(found);
+        at = found + 1;
+    }
+    Some((score, matched))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn ids(query: &str) -> Vec<&'static str> {
+        search_commands(query).iter().map(|m| m.command.id).collect()
+    }
+
+    #[test]
+    fn test_registry_ids_unique() {
+        let mut seen = std::collections::HashSet::new();
+        for command in COMMANDS {
+            assert!(seen.insert(command.id), "Duplicate command id {}", command.id);
+        }
+        assert_eq!(find_command("file.save").unwrap().shortcut, Some("Mod+S"));
+        assert!(find_command("file.print").is_none());
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        assert_eq!(ids("").len(), COMMANDS.len());
+        assert_eq!(ids("save")[0], "file.save");
+        assert_eq!(ids("zi")[0], "view.zoomIn"); // Word starts beat scattered letters
+        assert_eq!(ids("exmid")[0], "file.exportMidi");
+
+        let undo = &search_commands("undo")[0];
+        assert_eq!(undo.command.id, "edit.undo");
+        assert_eq!(undo.matched, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_search_category_and_description() {
+        // Category prefix: "playback stop"
+        assert!(ids("pb stop").contains(&"playback.stop"));
+        // Description word
+        assert_eq!(ids("twelve"), vec!["transpose.octaveUp", "transpose.octaveDown"]);
+        assert!(ids("qqq").is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+//! Audible comparison of two versions of a song
+//!
+//! Plays the song against another version of it (a saved copy, a student's
+//! take) so what changed can be heard instead of read off a diff. Both
+//! versions are played as their playback notes (repeats unfolded, effects
+//! applied), the other one timed onto this song's tempo map like an aligned
+//! merge (see [`crate::merge`]) and moved onto voices of its own. This
+//! version is panned left and the other right, and the other's voices that
+//! would sound like this song's play on a contrasting instrument.
+//!
+//! [`ComparisonMode::Together`] plays both at once. [`ComparisonMode::Alternate`]
+//! plays each measure that differs twice, this version and then the other,
+//! and measures that are the same once, in the middle.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use crate::error::{MozartError, Result};
+use crate::instrument::Instrument;
+use crate::note::Note;
+use crate::presets::DRUM_VOICE;
+use crate::song::{Song, Track, MAX_VOICES};
+
+/// Pan of this version's notes
+pub const CURRENT_PAN: u8 = 24;
+
+/// Pan of the other version's notes
+pub const OTHER_PAN: u8 = 104;
+
+/// How the two versions are played
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonMode {
+    /// Both at once
+    #[default]
+    Together,
+    /// Measure by measure, this version first
+    Alternate,
+}
+
+impl ComparisonMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "together" | "simultaneous" | "both" => Ok(ComparisonMode::Together),
+            "alternate" | "alt" => Ok(ComparisonMode::Alternate),
+            _ => Err(MozartError::ParseError(format!("Unknown comparison mode: {} (together or alternate)", s))),
+        }
+    }
+}
+
+/// What a stretch of a comparison plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparedVersion {
+    /// The measure is the same in both versions
+    Same,
+    /// The versions differ and play together
+    Both,
+    /// This version of a measure that differs
+    Current,
+    /// The other version of a measure that differs
+    Other,
+}
+
+/// A measure of a comparison
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComparisonSegment {
+    /// 1-based, on the played timeline
+    pub measure: u32,
+    pub version: ComparedVersion,
+    /// Where the segment plays in the comparison
+    pub start_tick: u32,
+    pub end_tick: u32,
+    /// Where the measure starts in the song (played timeline)
+    pub song_tick: u32,
+}
+
+/// Two versions of a song laid out to be played
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    pub mode: ComparisonMode,
+    /// Sorted by start tick, with the playback effects already applied
+    pub notes: Vec<Note>,
+    /// The instrument of every voice with one of its own (the other
+    /// version's on voices of their own)
+    pub tracks: Vec<Track>,
+    pub segments: Vec<ComparisonSegment>,
+}
+
+impl Comparison {
+    /// Measures that differ between the versions
+    pub fn changed_measures(&self) -> Vec<u32> {
+        let mut measures: Vec<u32> = self
+            .segments
+            .iter()
+            .filter(|s| s.version != ComparedVersion::Same)
+            .map(|s| s.measure)
+            .collect();
+        measures.dedup();
+        measures
+    }
+
+    /// The comparison as a song to render, with `song`'s meter and tempo
+    /// (its first tempo in alternate mode, where the measures are out of
+    /// order)
+    pub fn to_song(&self, song: &Song) -> Song {
+        let mut compared = song.as_played().into_owned();
+        compared.notes = self.notes.clone();
+        compared.settings.tracks = self.tracks.clone();
+        compared.settings.accent_dynamics = false;
+        compared.strums.clear();
+        compared.chords.clear();
+        if self.mode == ComparisonMode::Alternate {
+            compared.clear_tempo_changes();
+        }
+        compared
+    }
+}
+
+/// The instrument the other version plays in place of `instrument`
+fn contrasting_instrument(instrument: Instrument) -> Instrument {
+    let all = Instrument::all();
+    let i = all.iter().position(|&i| i == instrument).unwrap_or(0);
+    all[(i + 1) % all.len()]
+}
+
+fn panned(notes: Vec<Note>, pan: u8) -> Vec<Note> {
+    notes.into_iter().map(|note| Note { pan, ..note }).collect()
+}
+
+/// Lay out `current` and `other` to be heard against each other; see the
+/// module docs
+pub fn compare_versions(current: &Song, other: &Song, mode: ComparisonMode) -> Comparison {
+    let current = &*current.as_played();
+    let other = &*other.as_played();
+    let mine = current.playback_notes();
+    let theirs_written = other.playback_notes();
+
+    // The other version's voices onto free ones (drums stay on the drum voice)
+    let mut taken: BTreeSet<u8> =
+        mine.iter().map(|n| n.voice).chain(current.settings.tracks.iter().map(|t| t.voice)).collect();
+    taken.insert(DRUM_VOICE);
+    let ours = taken.clone();
+    let sources: BTreeSet<u8> = theirs_written.iter().map(|n| n.voice).collect();
+    let mut voices = Vec::new();
+    for &voice in &sources {
+        // Out of voices, a voice shares this version's (told apart by pan)
+        let to = match voice {
+            DRUM_VOICE => DRUM_VOICE,
+            _ => (0..MAX_VOICES).find(|v| !taken.contains(v)).unwrap_or(voice),
+        };
+        taken.insert(to);
+        voices.push((voice, to));
+    }
+    let to_voice = |from: u8| voices.iter().find(|&&(v, _)| v == from).map_or(from, |&(_, to)| to);
+
+    let mut tracks: Vec<Track> = current
+        .settings
+        .tracks
+        .iter()
+        .map(|t| Track { instrument: Some(current.voice_instrument(t.voice)), ..Track::new(t.voice, t.name.clone()) })
+        .collect();
+    for &(from, to) in voices.iter().filter(|(_, to)| !ours.contains(to)) {
+        let mut instrument = other.voice_instrument(from);
+        if instrument == current.voice_instrument(from) {
+            instrument = contrasting_instrument(instrument);
+        }
+        let name = format!("{} (other)", other.track(from).map_or_else(|| format!("Voice {}", from + 1), |t| t.name.clone()));
+        tracks.push(Track { instrument: Some(instrument), ..Track::new(to, name) });
+    }
+    tracks.sort_by_key(|t| t.voice);
+
+    let retime = |tick: u32| current.seconds_to_tick(other.tick_to_seconds(tick));
+    let theirs: Vec<Note> = theirs_written
+        .iter()
+        .map(|note| {
+            let (start, end) = (retime(note.start_tick), retime(note.end_tick()));
+            Note { start_tick: start, duration_ticks: end.saturating_sub(start).max(1), voice: to_voice(note.voice), ..note.clone() }
+        })
+        .collect();
+
+    let ticks_per_measure = current.settings.time_signature.ticks_per_measure();
+    let end = mine.iter().chain(&theirs).map(|n| n.end_tick()).max().unwrap_or(0);
+    let measures = end.div_ceil(ticks_per_measure);
+    let in_measure = |notes: &[Note], start: u32| -> Vec<Note> {
+        notes.iter().filter(|n| (start..start + ticks_per_measure).contains(&n.start_tick)).cloned().collect()
+    };
+    // Notes of a measure as heard, wherever they are played
+    let sounds = |notes: &[Note], start: u32| {
+        let mut sounds: Vec<_> = notes.iter().map(|n| (n.start_tick - start, n.pitch, n.duration_ticks, n.velocity)).collect();
+        sounds.sort_unstable();
+        sounds
+    };
+
+    let mut notes = Vec::new();
+    let mut segments = Vec::new();
+    let mut at = 0;
+    for measure in 0..measures {
+        let song_tick = measure * ticks_per_measure;
+        let (a, b) = (in_measure(&mine, song_tick), in_measure(&theirs, song_tick));
+        let same = sounds(&a, song_tick) == sounds(&b, song_tick);
+        let mut play = |version: ComparedVersion, played: Vec<Note>, at: u32| {
+            notes.extend(played.into_iter().map(|note| {
+                let start = note.start_tick - song_tick;
+                // Cut off at the barline when the measures are apart
+                let duration = match mode {
+                    ComparisonMode::Together => note.duration_ticks,
+                    ComparisonMode::Alternate => note.duration_ticks.min(ticks_per_measure - start),
+                };
+                Note { start_tick: at + start, duration_ticks: duration, ..note }
+            }));
+            segments.push(ComparisonSegment { measure: measure + 1, version, start_tick: at, end_tick: at + ticks_per_measure, song_tick });
+        };
+        match (mode, same) {
+            (ComparisonMode::Together, _) => {
+                let version = if same { ComparedVersion::Same } else { ComparedVersion::Both };
+                play(version, [panned(a, CURRENT_PAN), panned(b, OTHER_PAN)].concat(), at);
+            }
+            (ComparisonMode::Alternate, true) => play(ComparedVersion::Same, a, at),
+            (ComparisonMode::Alternate, false) => {
+                play(ComparedVersion::Current, panned(a, CURRENT_PAN), at);
+                at += ticks_per_measure;
+                play(ComparedVersion::Other, panned(b, OTHER_PAN), at);
+            }
+        }
+        at += ticks_per_measure;
+    }
+    notes.sort_by_key(|n| n.start_tick);
+
+    let comparison = Comparison { mode, notes, tracks, segments };
+    tracing::info!(
+        "Comparing \"{}\" with \"{}\" ({:?}): measures {:?} differ",
+        current.metadata.title,
+        other.metadata.title,
+        mode,
+        comparison.changed_measures()
+    );
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use pretty_assertions::assert_eq;
+
+    fn song(melody: &str) -> Song {
+        let mut song = Song::new();
+        song.add_notes(parse_melody(melody).unwrap());
+        song
+    }
+
+    #[test]
+    fn test_compare_together() {
+        let current = song("C4q D4q E4q F4q G4h G4h");
+        let other = song("C4q D4q E4q F4q G4h A4h");
+        let comparison = compare_versions(&current, &other, ComparisonMode::Together);
+        assert_eq!(comparison.changed_measures(), vec![2]);
+        assert_eq!(comparison.notes.len(), 12);
+
+        // The other version on a voice of its own, in another sound, panned right
+        let a4 = comparison.notes.iter().find(|n| n.pitch == 69).unwrap();
+        assert_eq!((a4.start_tick, a4.voice, a4.pan), (2880, 1, OTHER_PAN));
+        assert_eq!(comparison.tracks.len(), 1);
+        assert_eq!(comparison.tracks[0].instrument, Some(Instrument::Piano));
+        assert!(comparison.notes.iter().filter(|n| n.voice == 0).all(|n| n.pan == CURRENT_PAN));
+    }
+
+    #[test]
+    fn test_compare_alternate() {
+        let current = song("C4q D4q E4q F4q G4h G4h C5w");
+        let mut other = song("C4q D4q E4q F4q G4h A4h C5w");
+        // Timed onto this song's tempo: the same notes at twice the speed
+        other.settings.tempo *= 2;
+        for note in &mut other.notes {
+            note.start_tick *= 2;
+            note.duration_ticks *= 2;
+        }
+
+        let comparison = compare_versions(&current, &other, ComparisonMode::Alternate);
+        let segments: Vec<(u32, ComparedVersion, u32)> =
+            comparison.segments.iter().map(|s| (s.measure, s.version, s.start_tick)).collect();
+        assert_eq!(
+            segments,
+            vec![
+                (1, ComparedVersion::Same, 0),
+                (2, ComparedVersion::Current, 1920),
+                (2, ComparedVersion::Other, 3840),
+                (3, ComparedVersion::Same, 5760),
+            ]
+        );
+        let heard: Vec<(u32, u8)> = comparison.notes.iter().filter(|n| n.start_tick >= 1920).map(|n| (n.start_tick, n.pitch)).collect();
+        assert_eq!(heard, vec![(1920, 67), (2880, 67), (3840, 67), (4800, 69), (5760, 72)]);
+        assert_eq!(comparison.to_song(&current).duration_ticks(), 7680);
+    }
+
+    #[test]
+    fn test_parse_comparison_mode() {
+        assert_eq!(ComparisonMode::parse("Alternate").unwrap(), ComparisonMode::Alternate);
+        assert_eq!(ComparisonMode::parse("both").unwrap(), ComparisonMode::Together);
+        assert!(ComparisonMode::parse("shuffle").is_err());
+    }
+}
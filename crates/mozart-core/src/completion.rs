@@ -0,0 +1,230 @@
+//! Completions for text notation
+//!
+//! Proposes what to type next in a melody, IDE-style. After a space the
+//! candidates are whole notes: steps and small leaps in the key from the
+//! last note, with extra weight on the resolutions cadences are built from
+//! (leading tone to tonic, supertonic down to tonic, fa down to mi). Once a
+//! token has its pitch ("C4"), they are duration suffixes, favoring the ones
+//! the melody already uses.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::error::Result;
+use crate::note::{parse_melody_token, NoteDuration, NoteValue};
+use crate::pitch::Pitch;
+use crate::scale::Scale;
+
+/// Most suggestions returned
+pub const MAX_SUGGESTIONS: usize = 8;
+
+/// Octave a melody opens in when there is no previous note
+const OPENING_OCTAVE: i8 = 4;
+
+/// What a suggestion completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompletionKind {
+    /// A whole token: pitch and duration ("D4q")
+    Note,
+    /// The partial token plus a duration suffix ("C4" → "C4e")
+    Duration,
+}
+
+/// A candidate for the token being typed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenSuggestion {
+    /// Replaces the partial token
+    pub text: String,
+    pub kind: CompletionKind,
+    /// 0-1; the suggestions of one call add up to 1
+    pub probability: f32,
+    pub reason: String,
+}
+
+/// Suggest how to go on from `partial_text` (a melody, possibly ending in a
+/// half-typed token) in `key`, most likely first
+///
+/// Fails if a finished token before the last one is invalid.
+pub fn suggest_next_tokens(partial_text: &str, key: &Scale) -> Result<Vec<TokenSuggestion>> {
+    let mut tokens: Vec<&str> = partial_text.split_whitespace().collect();
+    let partial = match partial_text.ends_with(char::is_whitespace) {
+        true => "",
+        false => tokens.pop().unwrap_or(""),
+    };
+    let typed = tokens.iter().map(|t| parse_melody_token(t)).collect::<Result<Vec<_>>>()?;
+    let pitches: Vec<u8> = typed.iter().filter_map(|t| t.pitch).collect();
+    let durations: Vec<String> = typed.iter().map(|t| NoteDuration::from_ticks(t.duration_ticks).to_string()).collect();
+
+    let pitch_done = partial.ends_with(|c: char| c.is_ascii_digit()) && Pitch::parse(partial).is_ok();
+    // A rest or rhythm hit only lacks its duration
+    let mut suggestions = if pitch_done || partial.eq_ignore_ascii_case("r") || partial.eq_ignore_ascii_case("x") {
+        duration_suggestions(partial, &durations)
+    } else {
+        let suffix = durations.last().map(String::as_str).unwrap_or("q");
+        note_suggestions(&pitches, key, suffix)
+            .into_iter()
+            .filter(|s| s.text.to_lowercase().starts_with(&partial.to_lowercase()))
+            .collect()
+    };
+
+    let total: f32 = suggestions.iter().map(|s| s.probability).sum();
+    suggestions.iter_mut().for_each(|s| s.probability /= total);
+    suggestions.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    tracing::debug!("{} completions for '{}' in {}", suggestions.len(), partial, key);
+    Ok(suggestions)
+}
+
+// Durations the melody already uses count on top of how common each is
+fn duration_suggestions(partial: &str, used: &[String]) -> Vec<TokenSuggestion> {
+    let common = [
+        (NoteDuration::new(NoteValue::Quarter), 4.0),
+        (NoteDuration::new(NoteValue::Eighth), 3.0),
+        (NoteDuration::new(NoteValue::Half), 2.0),
+        (NoteDuration::new(NoteValue::Sixteenth), 1.0),
+        (NoteDuration::new(NoteValue::Whole), 1.0),
+        (NoteDuration::dotted(NoteValue::Quarter), 1.0),
+        (NoteDuration::dotted(NoteValue::Half), 0.5),
+        (NoteDuration::dotted(NoteValue::Eighth), 0.5),
+    ];
+    common
+        .iter()
+        .map(|(duration, weight)| {
+            let suffix = duration.to_string();
+            let count = used.iter().filter(|u| **u == suffix).count();
+            let name = format!("{}{} note", if duration.dotted { "Dotted " } else { "" }, duration.value);
+            let reason = match count {
+                0 => name,
+                n => format!("{}, used {} times so far", name, n),
+            };
+            TokenSuggestion {
+                text: format!("{}{}", partial, suffix),
+                kind: CompletionKind::Duration,
+                probability: weight + 2.0 * count as f32,
+                reason,
+            }
+        })
+        .collect()
+}
+
+// Next pitches with weights and reasons, merged where two routes land on one pitch
+fn note_suggestions(pitches: &[u8], key: &Scale, suffix: &str) -> Vec<TokenSuggestion> {
+    // Every MIDI pitch in the key, to move along by scale steps
+    let ladder: Vec<u8> = (0..=127u8).filter(|&p| Pitch::from_midi(p).is_ok_and(|p| key.contains(p.pitch_class()))).collect();
+    // Pitch -> total weight, and the heaviest route's weight and reason (which names it)
+    let mut candidates: BTreeMap<u8, (f32, f32, String)> = BTreeMap::new();
+    let mut add = |pitch: Option<u8>, weight: f32, reason: &str| {
+        let Some(pitch) = pitch else { return };
+        let entry = candidates.entry(pitch).or_insert((0.0, 0.0, String::new()));
+        entry.0 += weight;
+        if weight > entry.1 {
+            entry.1 = weight;
+            entry.2 = reason.to_string();
+        }
+    };
+
+    let tonic = key.degree(1).map(|pc| pc.semitones());
+    match pitches.last() {
+        None => {
+            let opening = |degree: u8| {
+                let pc = key.degree(degree)?;
+                Pitch::new(pc, OPENING_OCTAVE).ok().map(|p| p.midi())
+            };
+            add(opening(1), 0.5, "Opens on the tonic");
+            add(opening(5), 0.3, "Opens on the fifth");
+            add(opening(3), 0.2, "Opens on the third");
+        }
+        Some(&last) => match ladder.iter().position(|&p| p == last) {
+            Some(at) => {
+                let step = |by: isize| at.checked_add_signed(by).and_then(|i| ladder.get(i)).copied();
+                add(step(1), 0.3, "Steps up");
+                add(step(-1), 0.3, "Steps down");
+                add(Some(last), 0.1, "Repeats the note");
+                add(step(2), 0.08, "Leaps a third up");
+                add(step(-2), 0.08, "Leaps a third down");
+
+                let degree = Pitch::from_midi(last).ok().and_then(|p| key.degree_of(p.pitch_class()));
+                match degree {
+                    Some(7) if step(1).map(|p| p % 12) == tonic => add(step(1), 0.5, "Leading tone resolves to the tonic"),
+                    Some(2) => add(step(-1), 0.35, "Resolves down to the tonic"),
+                    Some(4) => add(step(-1), 0.32, "Fa falls to mi"),
+                    Some(5) if pitches.len() >= 2 => {
+                        // Authentic cadence: the fifth falls to the tonic below
+                        add(step(-4), 0.15, "Cadences on the tonic");
+                        add(step(3), 0.1, "Cadences on the tonic above");
+                    }
+                    _ => {}
+                }
+            }
+            // A chromatic note resolves to a scale tone a half or whole step away
+            None => {
+                let above = ladder.iter().copied().find(|&p| p > last);
+                let below = ladder.iter().rev().copied().find(|&p| p < last);
+                add(above, 0.4, "Resolves the chromatic note up");
+                add(below, 0.4, "Resolves the chromatic note down");
+            }
+        },
+    }
+
+    candidates
+        .into_iter()
+        .filter_map(|(pitch, (weight, _, reason))| {
+            let pitch = Pitch::from_midi(pitch).ok()?;
+            Some(TokenSuggestion {
+                text: format!("{}{}", pitch, suffix),
+                kind: CompletionKind::Note,
+                probability: weight,
+                reason,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pitch::PitchClass;
+    use crate::scale::ScaleType;
+    use pretty_assertions::assert_eq;
+
+    fn texts(suggestions: &[TokenSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_next_notes() {
+        let key = Scale::c_major();
+        let opening = suggest_next_tokens("", &key).unwrap();
+        assert_eq!(texts(&opening), vec!["C4q", "G4q", "E4q"]);
+        let total: f32 = opening.iter().map(|s| s.probability).sum();
+        assert!((total - 1.0).abs() < 1e-4);
+
+        // The leading tone resolves; the last note's duration carries over
+        let after_b = suggest_next_tokens("G4e A4e B4e ", &key).unwrap();
+        assert_eq!(after_b[0].text, "C5e");
+        assert_eq!(after_b[0].kind, CompletionKind::Note);
+        assert!(after_b[0].reason.contains("Leading tone"), "{}", after_b[0].reason);
+
+        // A half-typed pitch narrows the list
+        assert_eq!(texts(&suggest_next_tokens("E4q D", &key).unwrap()), vec!["D4q"]);
+        // F# isn't in C major: it resolves to G or F
+        let g_major = Scale::new(PitchClass::G, ScaleType::Major);
+        let chromatic = suggest_next_tokens("F#4q ", &key).unwrap();
+        assert_eq!(texts(&chromatic)[..2].iter().filter(|t| ["G4q", "F4q"].contains(t)).count(), 2);
+        assert_eq!(suggest_next_tokens("F#4q ", &g_major).unwrap()[0].text, "G4q");
+    }
+
+    #[test]
+    fn test_durations() {
+        let key = Scale::c_major();
+        let suggestions = suggest_next_tokens("C4e D4e E4", &key).unwrap();
+        assert!(suggestions.iter().all(|s| s.kind == CompletionKind::Duration && s.text.starts_with("E4")));
+        assert_eq!(suggestions[0].text, "E4e");
+        assert_eq!(suggest_next_tokens("C4q R", &key).unwrap()[0].text, "Rq");
+
+        assert!(suggest_next_tokens("C4q X9q ", &key).is_err());
+        assert!(suggest_next_tokens("C4q Z", &key).unwrap().is_empty());
+        // Rhythm hits ("xq") complete like rests
+        assert_eq!(suggest_next_tokens("xe xe x", &key).unwrap()[0].text, "xe");
+    }
+}
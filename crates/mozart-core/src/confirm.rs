@@ -0,0 +1,211 @@
+//! Confirmation for destructive commands
+//!
+//! Clearing the notes, deleting measures, and starting a new song over
+//! unsaved changes throw work away in one click. Run through
+//! [`confirm_or_run`], they change nothing unless called with `confirm`
+//! when there is something to lose: the caller gets
+//! [`Outcome::NeedsConfirmation`] with a [`LossSummary`] to show, and calls
+//! again with `confirm` once the user agrees.
+
+use serde::Serialize;
+use crate::song::Song;
+
+/// What a destructive command would throw away
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct LossSummary {
+    /// e.g., "Clear notes"
+    pub action: String,
+    pub notes: usize,
+    pub chords: usize,
+    pub markers: usize,
+    pub lyrics: usize,
+    pub annotations: usize,
+    /// Repeats, endings, signs, and jumps
+    pub repeat_marks: usize,
+    /// Edits since the last save that would be discarded
+    pub unsaved_changes: bool,
+    /// e.g., "Clear notes removes 12 notes."
+    pub message: String,
+}
+
+impl LossSummary {
+    fn new(action: &str) -> Self {
+        LossSummary { action: action.to_string(), ..Default::default() }
+    }
+
+    // Write the message, or None when there is nothing to lose
+    fn described(self) -> Option<Self> {
+        let counts = [
+            (self.notes, "note"),
+            (self.chords, "chord symbol"),
+            (self.markers, "marker"),
+            (self.lyrics, "lyric"),
+            (self.annotations, "annotation"),
+            (self.repeat_marks, "repeat mark"),
+        ];
+        let items: Vec<String> = counts
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .map(|(n, what)| format!("{} {}{}", n, what, if *n == 1 { "" } else { "s" }))
+            .collect();
+        let message = match (items.len(), self.unsaved_changes) {
+            (0, false) => return None,
+            (0, true) => format!("{} discards unsaved changes.", self.action),
+            (_, unsaved) => format!(
+                "{} removes {}.{}",
+                self.action,
+                join_list(&items),
+                if unsaved { " Unsaved changes will be lost." } else { "" }
+            ),
+        };
+        Some(LossSummary { message, ..self })
+    }
+}
+
+// "a", "a and b", "a, b, and c"
+fn join_list(items: &[String]) -> String {
+    match items {
+        [only] => only.clone(),
+        [first, second] => format!("{} and {}", first, second),
+        [rest @ .., last] => format!("{}, and {}", rest.join(", "), last),
+        [] => String::new(),
+    }
+}
+
+/// Result of a command that may need confirming first
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome<T> {
+    /// The command ran
+    Done { result: T },
+    /// Nothing changed; call again with `confirm` to go ahead
+    NeedsConfirmation { summary: LossSummary },
+}
+
+impl<T, E> Outcome<Result<T, E>> {
+    /// A command's error, or its outcome (like `Option::transpose`)
+    pub fn transpose(self) -> Result<Outcome<T>, E> {
+        match self {
+            Outcome::Done { result } => result.map(|result| Outcome::Done { result }),
+            Outcome::NeedsConfirmation { summary } => Ok(Outcome::NeedsConfirmation { summary }),
+        }
+    }
+}
+
+/// Run `command` if there is nothing to lose or the user confirmed
+pub fn confirm_or_run<T>(loss: Option<LossSummary>, confirm: bool, command: impl FnOnce() -> T) -> Outcome<T> {
+    match loss {
+        Some(summary) if !confirm => {
+            tracing::debug!("Needs confirmation: {}", summary.message);
+            Outcome::NeedsConfirmation { summary }
+        }
+        _ => Outcome::Done { result: command() },
+    }
+}
+
+/// What clearing every note would lose
+pub fn clear_notes_loss(song: &Song) -> Option<LossSummary> {
+    LossSummary { notes: song.notes.len(), ..LossSummary::new("Clear notes") }.described()
+}
+
+/// What deleting `count` measures from `first` (1-indexed) would lose
+///
+/// Empty measures delete without asking.
+pub fn delete_measures_loss(song: &Song, first: u32, count: u32) -> Option<LossSummary> {
+    if count == 0 {
+        return None;
+    }
+    let (start, end) = song.measures_range(first, count).ok()?;
+    let inside = |tick: u32| tick >= start && tick < end;
+    let action = match count {
+        1 => format!("Delete measure {}", first),
+        _ => format!("Delete measures {}-{}", first, first + count - 1),
+    };
+    // Marks that reach past the deleted measures shrink rather than go
+    let mut repeats = song.repeats.clone();
+    repeats.delete_measures(first, count);
+    LossSummary {
+        notes: song.notes.iter().filter(|n| inside(n.start_tick)).count(),
+        chords: song.chords.iter().filter(|c| inside(c.tick)).count(),
+        markers: song.markers.iter().filter(|m| inside(m.tick)).count(),
+        lyrics: song.lyrics.iter().filter(|l| inside(l.tick)).count(),
+        annotations: song.annotations.iter().filter(|a| inside(a.tick)).count(),
+        repeat_marks: song.repeats.mark_count() - repeats.mark_count(),
+        ..LossSummary::new(&action)
+    }
+    .described()
+}
+
+/// What replacing the song with a new one would lose
+///
+/// A saved song is safe on disk, so only unsaved changes need confirming.
+pub fn new_song_loss(song: &Song, unsaved_changes: bool) -> Option<LossSummary> {
+    if !unsaved_changes {
+        return None;
+    }
+    LossSummary { unsaved_changes: true, ..LossSummary::new("New song") }
+        .described()
+        .map(|summary| LossSummary { notes: song.notes.len(), ..summary })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use crate::repeats::{Repeat, RepeatStructure};
+    use pretty_assertions::assert_eq;
+
+    fn song() -> Song {
+        let mut song = Song::new();
+        song.add_notes((0..3).map(|i| Note::new(60 + i, i as u32 * 960, 960)));
+        song.add_marker("Verse", 1920);
+        song
+    }
+
+    #[test]
+    fn test_confirm_or_run() {
+        let mut song = song();
+        let outcome = confirm_or_run(clear_notes_loss(&song), false, || song.clear_notes());
+        let Outcome::NeedsConfirmation { summary } = outcome else { panic!("Cleared without asking") };
+        assert_eq!(summary.message, "Clear notes removes 3 notes.");
+        assert_eq!(song.notes.len(), 3);
+
+        assert_eq!(confirm_or_run(clear_notes_loss(&song), true, || song.clear_notes()), Outcome::Done { result: () });
+        assert!(song.notes.is_empty());
+        // Nothing left to lose
+        assert!(clear_notes_loss(&song).is_none());
+    }
+
+    #[test]
+    fn test_loss_summaries() {
+        let song = song();
+        let summary = delete_measures_loss(&song, 1, 2).unwrap();
+        assert_eq!((summary.notes, summary.markers), (3, 1));
+        assert_eq!(summary.message, "Delete measures 1-2 removes 3 notes and 1 marker.");
+        assert!(delete_measures_loss(&song, 3, 1).is_none());
+
+        assert!(new_song_loss(&song, false).is_none());
+        let summary = new_song_loss(&song, true).unwrap();
+        assert_eq!((summary.notes, summary.message.as_str()), (3, "New song discards unsaved changes."));
+    }
+
+    #[test]
+    fn test_delete_measures_loss_counts_every_kind() {
+        let mut song = song();
+        song.set_chord(0, "C").unwrap();
+        song.add_annotation(960, None, "breathe").unwrap();
+        let mut repeats = RepeatStructure { segno: Some(3), ..Default::default() };
+        repeats.add_repeat(Repeat::new(2, 2)).unwrap();
+        song.set_repeats(repeats).unwrap();
+
+        // The segno after the deleted measures only moves
+        let summary = delete_measures_loss(&song, 1, 2).unwrap();
+        assert_eq!((summary.chords, summary.annotations, summary.repeat_marks), (1, 1, 1));
+        assert_eq!(
+            summary.message,
+            "Delete measures 1-2 removes 3 notes, 1 chord symbol, 1 marker, 1 annotation, and 1 repeat mark."
+        );
+        let summary = delete_measures_loss(&song, 3, 1).unwrap();
+        assert_eq!(summary.message, "Delete measure 3 removes 1 repeat mark.");
+    }
+}
@@ -0,0 +1,425 @@
+//! Melody continuation
+//!
+//! "Continue my melody" learns which moves tend to follow which from a set
+//! of songs (the user's library, the bundled tunes, or both) and extends a
+//! voice's line by a few measures in the song's key and meter. Pitches are
+//! learned as moves in scale steps, so tunes in any key teach every other
+//! key, and rhythm as the time from one note to the next. Both are
+//! second-order Markov chains: the next move follows the two before it,
+//! falling back to one (and then to none) for a context never seen.
+//!
+//! A continuation is a preview until it is applied with
+//! [`apply_continuation`]. Generation is seeded, so the same song, model,
+//! and config always give the same continuation.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::error::{MozartError, Result};
+use crate::note::{parse_melody, Note};
+use crate::pitch::PitchClass;
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::worksheet::Rng;
+
+/// Moves remembered before the next one
+const ORDER: usize = 2;
+/// Longest time between notes that still counts as one phrase (a whole note)
+const MAX_LENGTH: u32 = 1920;
+/// Scale steps the continuation may go beyond the phrase's range
+const RANGE_MARGIN: i32 = 2;
+/// Narrowest range to move in, in scale steps (a ninth)
+const MIN_RANGE: i32 = 8;
+
+/// Public-domain tunes in C major, so a model can be trained without a library
+const BUNDLED_TUNES: [&str; 6] = [
+    // Ode to Joy
+    "E4q E4q F4q G4q G4q F4q E4q D4q C4q C4q D4q E4q E4q. D4e D4h \
+     E4q E4q F4q G4q G4q F4q E4q D4q C4q C4q D4q E4q D4q. C4e C4h",
+    // Twinkle, Twinkle, Little Star
+    "C4q C4q G4q G4q A4q A4q G4h F4q F4q E4q E4q D4q D4q C4h \
+     G4q G4q F4q F4q E4q E4q D4h G4q G4q F4q F4q E4q E4q D4h",
+    // Frère Jacques
+    "C4q D4q E4q C4q C4q D4q E4q C4q E4q F4q G4h E4q F4q G4h \
+     G4e A4e G4e F4e E4q C4q G4e A4e G4e F4e E4q C4q C4q G3q C4h C4q G3q C4h",
+    // Mary Had a Little Lamb
+    "E4q D4q C4q D4q E4q E4q E4h D4q D4q D4h E4q G4q G4h \
+     E4q D4q C4q D4q E4q E4q E4q E4q D4q D4q E4q D4q C4w",
+    // Au clair de la lune
+    "C4q C4q C4q D4q E4h D4h C4q E4q D4q D4q C4w \
+     D4q D4q D4q D4q A3h A3h D4q C4q B3q A3q G3w",
+    // Amazing Grace
+    "G3q C4h E4e C4e E4h D4q C4h A3q G3h G3q C4h E4e C4e E4h D4e E4e G4h. \
+     E4q G4h E4e C4e E4h D4q C4h A3q G3h G3q C4h E4e C4e E4h D4q C4h.",
+];
+
+/// Pitch and rhythm transitions learned from songs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MelodyModel {
+    /// Next move in scale steps, counted after each context of up to `ORDER` moves
+    moves: BTreeMap<Vec<i32>, BTreeMap<i32, u32>>,
+    /// Next time between note starts in ticks, after up to `ORDER` of them
+    lengths: BTreeMap<Vec<u32>, BTreeMap<u32, u32>>,
+    songs: usize,
+    notes: usize,
+}
+
+impl MelodyModel {
+    /// An empty model; [`continue_melody`] needs at least one song learned
+    pub fn new() -> Self {
+        MelodyModel::default()
+    }
+
+    /// A model of the bundled tunes
+    pub fn bundled() -> Self {
+        let mut model = MelodyModel::new();
+        for tune in BUNDLED_TUNES {
+            let mut song = Song::new();
+            song.set_key(Scale::c_major());
+            song.add_notes(parse_melody(tune).unwrap_or_default());
+            model.learn(&song);
+        }
+        model
+    }
+
+    /// Learn the line of every voice in a song
+    pub fn learn(&mut self, song: &Song) {
+        let key = song.settings.key;
+        let mut voices: Vec<u8> = song.notes.iter().map(|n| n.voice).collect();
+        voices.sort_unstable();
+        voices.dedup();
+        for voice in voices {
+            let line = top_line(song, voice);
+            let steps: Vec<i32> = line.iter().map(|n| scale_step(&key, n.pitch)).collect();
+            for i in 1..line.len() {
+                let length = line[i].start_tick - line[i - 1].start_tick;
+                if length > MAX_LENGTH {
+                    continue;
+                }
+                let moves: Vec<i32> = steps[..=i].windows(2).map(|w| w[1] - w[0]).collect();
+                let lengths: Vec<u32> = line[..=i].windows(2).map(|w| w[1].start_tick - w[0].start_tick).collect();
+                let (next_move, earlier_moves) = moves.split_last().unwrap_or((&0, &[]));
+                let (next_length, earlier_lengths) = lengths.split_last().unwrap_or((&0, &[]));
+                for order in 0..=ORDER.min(earlier_moves.len()) {
+                    let context = earlier_moves[earlier_moves.len() - order..].to_vec();
+                    *self.moves.entry(context).or_default().entry(*next_move).or_default() += 1;
+                    let context = earlier_lengths[earlier_lengths.len() - order..].to_vec();
+                    *self.lengths.entry(context).or_default().entry(*next_length).or_default() += 1;
+                }
+            }
+            self.notes += line.len();
+        }
+        self.songs += 1;
+    }
+
+    /// Learn every `.mozart.json` song in a directory, skipping files that
+    /// fail to load; returns how many were learned
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn learn_dir(&mut self, dir: impl AsRef<std::path::Path>) -> Result<usize> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| MozartError::FileError(format!("Failed to read {}: {}", dir.display(), e)))?;
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.to_string_lossy().ends_with(".mozart.json"))
+            .collect();
+        paths.sort();
+        let mut learned = 0;
+        for path in &paths {
+            match Song::load(path) {
+                Ok(song) => {
+                    self.learn(&song);
+                    learned += 1;
+                }
+                Err(e) => tracing::warn!("Skipping {:?}: {}", path, e),
+            }
+        }
+        tracing::info!("Learned {} songs from {:?}", learned, dir);
+        Ok(learned)
+    }
+
+    /// Songs learned
+    pub fn songs(&self) -> usize {
+        self.songs
+    }
+
+    /// Notes learned, across every song
+    pub fn notes(&self) -> usize {
+        self.notes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+/// How far to continue, and which voice; omitted fields use the defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContinuationConfig {
+    /// Measures after the one the line ends in (1-4)
+    pub measures: u32,
+    pub voice: u8,
+    /// Same seed, same continuation
+    pub seed: u64,
+}
+
+impl Default for ContinuationConfig {
+    fn default() -> Self {
+        ContinuationConfig { measures: 2, voice: 0, seed: 1 }
+    }
+}
+
+/// Notes to add after a voice's line, not yet in the song
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Continuation {
+    pub voice: u8,
+    /// Where the line ended
+    pub start_tick: u32,
+    /// A barline
+    pub end_tick: u32,
+    pub notes: Vec<Note>,
+}
+
+// The highest note at each start in a voice, in order
+pub(crate) fn top_line(song: &Song, voice: u8) -> Vec<Note> {
+    let mut line: Vec<Note> = song.notes.iter().filter(|n| n.voice == voice).cloned().collect();
+    line.sort_by_key(|n| (n.start_tick, std::cmp::Reverse(n.pitch)));
+    line.dedup_by_key(|n| n.start_tick);
+    line
+}
+
+// Position of a pitch among the key's scale tones (chromatic notes count as
+// the nearest scale tone)
+fn scale_step(key: &Scale, pitch: u8) -> i32 {
+    let (_, adjustment) = key.nearest_scale_tone(PitchClass::new(pitch % 12));
+    let pitch = pitch as i32 + adjustment as i32;
+    let tones_below = (0..pitch.rem_euclid(12)).filter(|&pc| key.contains(PitchClass::new(pc as u8))).count();
+    pitch.div_euclid(12) * 7 + tones_below as i32
+}
+
+// The pitch of a scale step in the key
+fn step_pitch(key: &Scale, step: i32) -> Option<u8> {
+    let tones: Vec<i32> = (0..12).filter(|&pc| key.contains(PitchClass::new(pc as u8))).collect();
+    let pitch = step.div_euclid(7) * 12 + tones.get(step.rem_euclid(7) as usize)?;
+    u8::try_from(pitch).ok().filter(|&p| p <= 127)
+}
+
+// What follows the longest known suffix of `context`
+fn next_counts<'a, K: Ord + Clone, V>(table: &'a BTreeMap<Vec<K>, BTreeMap<V, u32>>, context: &[K]) -> Option<&'a BTreeMap<V, u32>> {
+    (0..=context.len().min(ORDER)).rev().find_map(|order| table.get(&context[context.len() - order..]))
+}
+
+fn weighted<V: Copy>(counts: &BTreeMap<V, u32>, rng: &mut Rng) -> Option<V> {
+    let total: u32 = counts.values().sum();
+    let mut pick = rng.below(total.max(1) as usize) as u32;
+    for (&value, &count) in counts {
+        if pick < count {
+            return Some(value);
+        }
+        pick -= count;
+    }
+    None
+}
+
+/// Continue a voice's line from where it ends, through the measure it ends
+/// in and `measures` more, ending on a tone of the tonic triad
+pub fn continue_melody(song: &Song, model: &MelodyModel, config: &ContinuationConfig) -> Result<Continuation> {
+    if !(1..=4).contains(&config.measures) {
+        return Err(MozartError::MelodyError(format!("Continue by 1-4 measures, not {}", config.measures)));
+    }
+    if model.is_empty() {
+        return Err(MozartError::MelodyError("The model has not learned any melodies".to_string()));
+    }
+    let line = top_line(song, config.voice);
+    let Some(last) = line.last() else {
+        return Err(MozartError::MelodyError(format!("Voice {} has no melody to continue", config.voice)));
+    };
+
+    let key = song.settings.key;
+    let measure = song.voice_time_signature(config.voice).ticks_per_measure();
+    let start_tick = line.iter().map(|n| n.end_tick()).max().unwrap_or(0);
+    let end_tick = (start_tick.div_ceil(measure) + config.measures) * measure;
+
+    let mut steps: Vec<i32> = line.iter().map(|n| scale_step(&key, n.pitch)).collect();
+    let low = steps.iter().min().copied().unwrap_or(0) - RANGE_MARGIN;
+    let high = steps.iter().max().copied().unwrap_or(0) + RANGE_MARGIN;
+    let widen = (MIN_RANGE - (high - low)).max(0);
+    let (low, high) = (low - widen / 2, high + widen - widen / 2);
+
+    // The last note is held until the continuation starts
+    let mut lengths: Vec<u32> = line.windows(2).map(|w| w[1].start_tick - w[0].start_tick).collect();
+    lengths.push(start_tick - last.start_tick);
+    let known_lengths: Vec<u32> = model.lengths.get(&Vec::new()).map(|c| c.keys().copied().collect()).unwrap_or_default();
+
+    let mut rng = Rng(config.seed);
+    let mut notes = Vec::new();
+    let mut tick = start_tick;
+    while tick < end_tick {
+        let moves: Vec<i32> = steps.windows(2).map(|w| w[1] - w[0]).collect();
+        let step_move = next_counts(&model.moves, &moves).and_then(|c| weighted(c, &mut rng)).unwrap_or(0);
+        let current = steps.last().copied().unwrap_or(0);
+        let step = if (low..=high).contains(&(current + step_move)) { current + step_move } else { current - step_move };
+
+        let remaining = end_tick - tick;
+        let length = next_counts(&model.lengths, &lengths).and_then(|c| weighted(c, &mut rng)).unwrap_or(remaining);
+        let length = if length <= remaining {
+            length
+        } else {
+            known_lengths.iter().copied().filter(|&l| l <= remaining).max().unwrap_or(remaining)
+        };
+
+        let pitch = step_pitch(&key, step).unwrap_or(last.pitch);
+        notes.push(Note::with_voice(pitch, tick, length, last.velocity, config.voice));
+        steps.push(step);
+        lengths.push(length);
+        tick += length;
+    }
+
+    // Close on the nearest tonic triad tone, held to the barline
+    if let Some(note) = notes.last_mut() {
+        let step = *steps.last().unwrap_or(&0);
+        let tonic = scale_step(&key, key.root.semitones());
+        let close = [0, 1, -1, 2, -2]
+            .iter()
+            .map(|d| step + d)
+            .find(|s| (s - tonic).rem_euclid(7) % 2 == 0 && (s - tonic).rem_euclid(7) != 6)
+            .unwrap_or(step);
+        note.pitch = step_pitch(&key, close).unwrap_or(note.pitch);
+        note.duration_ticks = end_tick - note.start_tick;
+    }
+
+    tracing::info!(
+        "Continued voice {} by {} notes from tick {} ({} songs learned)",
+        config.voice,
+        notes.len(),
+        start_tick,
+        model.songs
+    );
+    Ok(Continuation { voice: config.voice, start_tick, end_tick, notes })
+}
+
+/// Add a continuation's notes to the song; returns how many
+///
+/// The voice must still end where the continuation starts.
+pub fn apply_continuation(song: &mut Song, continuation: &Continuation) -> Result<usize> {
+    let end = song.notes.iter().filter(|n| n.voice == continuation.voice).map(|n| n.end_tick()).max();
+    if end != Some(continuation.start_tick) {
+        return Err(MozartError::InvalidEdit("The melody changed since the continuation was made".to_string()));
+    }
+    song.add_notes(continuation.notes.iter().cloned());
+    tracing::info!("Applied a continuation of {} notes", continuation.notes.len());
+    Ok(continuation.notes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::ScaleType;
+    use crate::time::TimeSignature;
+    use pretty_assertions::assert_eq;
+
+    fn song(melody: &str) -> Song {
+        let mut song = Song::new();
+        song.add_notes(parse_melody(melody).unwrap());
+        song
+    }
+
+    #[test]
+    fn test_scale_steps() {
+        let c = Scale::c_major();
+        assert_eq!(scale_step(&c, 60), 35);
+        assert_eq!(scale_step(&c, 62), 36);
+        assert_eq!(scale_step(&c, 72), 42);
+        assert_eq!(step_pitch(&c, 36), Some(62));
+        let d = Scale::new(PitchClass::D, ScaleType::Major);
+        // D to E is one step, as C to D is in C
+        assert_eq!(scale_step(&d, 64) - scale_step(&d, 62), 1);
+        assert_eq!(step_pitch(&d, scale_step(&d, 66)), Some(66));
+    }
+
+    #[test]
+    fn test_learn() {
+        let mut model = MelodyModel::new();
+        model.learn(&song("C4q D4q E4q F4q G4h"));
+        assert_eq!((model.songs(), model.notes()), (1, 5));
+        // Every move is a step up, after any context
+        assert_eq!(model.moves[&vec![1, 1]], BTreeMap::from([(1, 2)]));
+        assert_eq!(model.moves[&vec![]], BTreeMap::from([(1, 4)]));
+        assert_eq!(model.lengths[&vec![480]], BTreeMap::from([(480, 3)]));
+
+        let bundled = MelodyModel::bundled();
+        assert_eq!(bundled.songs(), BUNDLED_TUNES.len());
+        assert!(bundled.notes() > 150);
+    }
+
+    #[test]
+    fn test_learn_dir() {
+        let dir = std::env::temp_dir().join(format!("mozart-continuation-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        song("C4q D4q E4q").save(dir.join("a.mozart.json")).unwrap();
+        std::fs::write(dir.join("b.mozart.json"), "{ not a song").unwrap();
+        song("E4q D4q").save(dir.join("c.mozart.json")).unwrap();
+
+        let mut model = MelodyModel::new();
+        assert_eq!(model.learn_dir(&dir).unwrap(), 2);
+        assert_eq!((model.songs(), model.notes()), (2, 5));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_continue_melody() {
+        let mut model = MelodyModel::new();
+        model.learn(&song("C4q D4q E4q F4q G4q A4q B4q C5q D5q E5q"));
+        let phrase = song("C4q D4q E4q F4q");
+        let continuation = continue_melody(&phrase, &model, &ContinuationConfig::default()).unwrap();
+        assert_eq!((continuation.start_tick, continuation.end_tick), (1920, 5760));
+
+        // A scale keeps climbing, within two steps of the phrase (widened to a ninth)
+        let pitches: Vec<u8> = continuation.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(&pitches[..3], &[67, 69, 71]);
+        let mut tick = 1920;
+        for note in &continuation.notes {
+            assert_eq!(note.start_tick, tick);
+            tick = note.end_tick();
+        }
+        assert_eq!(tick, 5760);
+        // Ends on a tonic triad tone
+        assert!([0, 4, 7].contains(&(pitches.last().unwrap() % 12)));
+        assert!(pitches.iter().all(|&p| (57..=77).contains(&p)), "{:?}", pitches);
+    }
+
+    #[test]
+    fn test_continue_in_key_and_meter() {
+        let model = MelodyModel::bundled();
+        let mut phrase = song("D4q E4q F#4q");
+        phrase.set_key(Scale::new(PitchClass::D, ScaleType::Major));
+        phrase.set_time_signature(TimeSignature::parse("3/4").unwrap());
+        let config = ContinuationConfig { measures: 1, seed: 5, ..ContinuationConfig::default() };
+        let continuation = continue_melody(&phrase, &model, &config).unwrap();
+        assert_eq!((continuation.start_tick, continuation.end_tick), (1440, 2880));
+        let d_major = Scale::new(PitchClass::D, ScaleType::Major);
+        assert!(continuation.notes.iter().all(|n| d_major.contains(PitchClass::new(n.pitch % 12))));
+        assert_eq!(continue_melody(&phrase, &model, &config).unwrap(), continuation);
+    }
+
+    #[test]
+    fn test_apply_continuation() {
+        let model = MelodyModel::bundled();
+        let mut phrase = song("C4q E4q G4q C5q");
+        let continuation = continue_melody(&phrase, &model, &ContinuationConfig::default()).unwrap();
+        assert_eq!(apply_continuation(&mut phrase, &continuation).unwrap(), continuation.notes.len());
+        assert_eq!(phrase.notes.len(), 4 + continuation.notes.len());
+        // Already applied, so the line no longer ends there
+        assert!(apply_continuation(&mut phrase, &continuation).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let model = MelodyModel::bundled();
+        let config = ContinuationConfig::default();
+        assert!(continue_melody(&Song::new(), &model, &config).is_err());
+        assert!(continue_melody(&song("C4q"), &MelodyModel::new(), &config).is_err());
+        let long = ContinuationConfig { measures: 5, ..config };
+        assert!(matches!(continue_melody(&song("C4q"), &model, &long), Err(MozartError::MelodyError(_))));
+    }
+}
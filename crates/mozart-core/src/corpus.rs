@@ -0,0 +1,197 @@
+//! MIDI export conformance corpus
+//!
+//! `fixtures/midi-export` holds songs saved as `.mozart.json` next to the
+//! `.mid` bytes the exporter wrote for them. Verifying the corpus exports
+//! each song again and compares byte for byte, so changes to the writer
+//! (running status, Format 1, controller data) show up as a failing test
+//! instead of silently changing what users get from their existing files.
+//! Each export is also imported back and its notes compared with the song's.
+//!
+//! Songs with accent dynamics turned on are exported with the dynamics
+//! baked into the velocities, as the app's Export MIDI does.
+//!
+//! When a change to the output is intended, re-run with `bless` to rewrite
+//! the golden files and review the diff.
+
+use crate::error::{MozartError, Result};
+use crate::midi::{import_from_midi, MidiExporter};
+use crate::song::Song;
+use std::path::{Path, PathBuf};
+
+/// The corpus shipped with the crate
+pub const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/midi-export");
+
+const FIXTURE_SUFFIX: &str = ".mozart.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Same bytes as the golden file
+    Match,
+    /// The export differs from the golden file, starting at `offset`
+    Changed { offset: usize, expected_len: usize, actual_len: usize },
+    /// No golden file yet
+    Missing,
+    /// The golden file was (re)written
+    Blessed,
+}
+
+/// Outcome for one fixture song
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureReport {
+    /// File name without `.mozart.json`
+    pub name: String,
+    pub verdict: Verdict,
+    /// First difference between the song's notes and the re-imported export
+    pub round_trip: Option<String>,
+}
+
+impl FixtureReport {
+    pub fn passed(&self) -> bool {
+        matches!(self.verdict, Verdict::Match | Verdict::Blessed) && self.round_trip.is_none()
+    }
+}
+
+/// Export every fixture in `dir` and check it against its golden file,
+/// or rewrite the golden files when `bless` is set
+pub fn verify_export(dir: impl AsRef<Path>, bless: bool) -> Result<Vec<FixtureReport>> {
+    let dir = dir.as_ref();
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| MozartError::FileError(format!("Failed to read {}: {}", dir.display(), e)))?;
+
+    let mut fixtures: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.to_string_lossy().ends_with(FIXTURE_SUFFIX))
+        .collect();
+    fixtures.sort();
+    tracing::info!("Verifying {} MIDI export fixtures in {:?}", fixtures.len(), dir);
+
+    fixtures.iter().map(|path| verify_fixture(path, bless)).collect()
+}
+
+/// Check one `.mozart.json` fixture against the `.mid` beside it
+pub fn verify_fixture(path: &Path, bless: bool) -> Result<FixtureReport> {
+    let file_name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = file_name.strip_suffix(FIXTURE_SUFFIX).unwrap_or(&file_name).to_string();
+    let golden_path = path.with_file_name(format!("{}.mid", name));
+
+    let song = Song::load(path)?;
+    let actual = MidiExporter::new().with_accent_dynamics(song.settings.accent_dynamics).export(&song)?;
+    let round_trip = round_trip_difference(&song, &actual)?;
+
+    let verdict = if bless {
+        std::fs::write(&golden_path, &actual)
+            .map_err(|e| MozartError::FileError(format!("Failed to write {}: {}", golden_path.display(), e)))?;
+        Verdict::Blessed
+    } else {
+        match std::fs::read(&golden_path) {
+            Ok(expected) => match first_difference(&expected, &actual) {
+                None => Verdict::Match,
+                Some(offset) => Verdict::Changed { offset, expected_len: expected.len(), actual_len: actual.len() },
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Verdict::Missing,
+            Err(e) => {
+                return Err(MozartError::FileError(format!("Failed to read {}: {}", golden_path.display(), e)));
+            }
+        }
+    };
+
+    if verdict != Verdict::Match {
+        tracing::warn!("MIDI export fixture {}: {:?}", name, verdict);
+    }
+    Ok(FixtureReport { name, verdict, round_trip })
+}
+
+/// Index of the first byte where the two differ (or where the shorter ends)
+pub fn first_difference(expected: &[u8], actual: &[u8]) -> Option<usize> {
+    expected
+        .iter()
+        .zip(actual)
+        .position(|(a, b)| a != b)
+        .or_else(|| (expected.len() != actual.len()).then(|| expected.len().min(actual.len())))
+}
+
+fn round_trip_difference(song: &Song, midi: &[u8]) -> Result<Option<String>> {
+    let imported = import_from_midi(midi)?;
+
+    // (start, pitch, duration, written velocity) of each note, in play order
+    let mut expected: Vec<_> = song
+        .notes
+        .iter()
+        .map(|n| {
+            let velocity = if song.settings.accent_dynamics { song.accented_velocity(n) } else { n.velocity };
+            (n.start_tick, n.pitch, n.duration_ticks, velocity)
+        })
+        .collect();
+    let mut actual: Vec<_> = imported
+        .notes
+        .iter()
+        .map(|n| (n.start_tick, n.pitch, n.duration_ticks, n.velocity))
+        .collect();
+    expected.sort_unstable();
+    actual.sort_unstable();
+
+    if expected.len() != actual.len() {
+        return Ok(Some(format!("{} notes written, {} read back", expected.len(), actual.len())));
+    }
+    Ok(expected.iter().zip(&actual).find(|(e, a)| e != a).map(|(e, a)| {
+        format!(
+            "note at tick {} (pitch {}, {} ticks, velocity {}) read back as tick {} (pitch {}, {} ticks, velocity {})",
+            e.0, e.1, e.2, e.3, a.0, a.1, a.2, a.3
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_corpus_matches_golden() {
+        let reports = verify_export(CORPUS_DIR, false).unwrap();
+        assert!(reports.len() >= 5, "MIDI export corpus is missing fixtures");
+
+        let failures: Vec<&FixtureReport> = reports.iter().filter(|r| !r.passed()).collect();
+        assert!(
+            failures.is_empty(),
+            "MIDI export output changed for {:?}; if intended, run `verify-export --bless` in mozart-test",
+            failures
+        );
+    }
+
+    #[test]
+    fn test_detects_changed_output() {
+        let dir = std::env::temp_dir().join(format!("mozart-corpus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut song = Song::with_title("Changed");
+        song.add_note(Note::new(60, 0, 480));
+        let fixture = dir.join("changed.mozart.json");
+        song.save(&fixture).unwrap();
+
+        assert_eq!(verify_fixture(&fixture, false).unwrap().verdict, Verdict::Missing);
+        assert_eq!(verify_fixture(&fixture, true).unwrap().verdict, Verdict::Blessed);
+        assert!(verify_fixture(&fixture, false).unwrap().passed());
+
+        // A quieter note changes one velocity byte
+        song.notes[0].velocity = 90;
+        song.save(&fixture).unwrap();
+        let golden = std::fs::read(dir.join("changed.mid")).unwrap();
+        let report = verify_fixture(&fixture, false).unwrap();
+        let offset = golden.iter().rposition(|&b| b == 100).unwrap();
+        assert_eq!(
+            report.verdict,
+            Verdict::Changed { offset, expected_len: golden.len(), actual_len: golden.len() }
+        );
+        assert_eq!(report.round_trip, None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference(b"MThd", b"MThd"), None);
+        assert_eq!(first_difference(b"MThd", b"MTrk"), Some(2));
+        assert_eq!(first_difference(b"MThd", b"MT"), Some(2));
+    }
+}
@@ -0,0 +1,401 @@
+//! Species counterpoint
+//!
+//! Writes a first-species line (note against note) above or below a cantus
+//! firmus taken from one voice of the song. Every candidate line is costed
+//! against the rules and the cheapest one wins:
+//! - Strict rules: consonant intervals only, no parallel fifths or octaves,
+//!   unisons only at the ends, open on a perfect consonance, close on the
+//!   octave (or unison) by step, and no dissonant or oversized leaps
+//! - Preferences: contrary motion, imperfect consonances inside the line,
+//!   stepwise motion, no repeated notes, no hidden fifths or octaves
+//!
+//! Strict rules cost so much that one is only broken when every line breaks
+//! it (e.g., a cantus that leaps a tritone); such breaks are reported as
+//! relaxations.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::{Pitch, PitchClass};
+use crate::scale::Scale;
+use crate::song::Song;
+
+/// Cost of breaking a strict rule, far above any sum of preferences
+const STRICT_COST: u32 = 1000;
+const SIMILAR_MOTION_COST: u32 = 3;
+const HIDDEN_PERFECT_COST: u32 = 8;
+const REPEATED_NOTE_COST: u32 = 5;
+const INNER_PERFECT_COST: u32 = 2;
+/// Widest interval between the voices (a twelfth)
+const MAX_SPAN: u8 = 19;
+
+/// A strict rule of first species
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterpointRule {
+    /// Seconds, fourths, tritones, and sevenths between the voices
+    Dissonance,
+    /// Fifths or octaves in a row
+    ParallelPerfect,
+    /// Unison anywhere but the first or last note
+    Unison,
+    /// First interval not a perfect consonance (only the octave or unison below the cantus)
+    Opening,
+    /// Last interval not an octave or unison reached by step
+    Cadence,
+    /// Tritones, sixths (except a rising minor sixth), sevenths, or more than an octave
+    Leap,
+}
+
+impl CounterpointRule {
+    pub fn description(&self) -> &'static str {
+        match self {
+            CounterpointRule::Dissonance => "dissonant interval",
+            CounterpointRule::ParallelPerfect => "parallel perfect interval",
+            CounterpointRule::Unison => "unison inside the line",
+            CounterpointRule::Opening => "opening is not a perfect consonance",
+            CounterpointRule::Cadence => "cadence is not an octave reached by step",
+            CounterpointRule::Leap => "forbidden melodic leap",
+        }
+    }
+}
+
+impl fmt::Display for CounterpointRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+/// Which voice to follow and where to write; omitted fields use the defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CounterpointConfig {
+    /// Only first species (1) is supported
+    pub species: u8,
+    /// Write above the cantus (false: below)
+    pub above: bool,
+    /// Voice holding the cantus firmus
+    pub cantus_voice: u8,
+    /// Voice to write into, replacing its notes
+    pub voice: u8,
+}
+
+impl Default for CounterpointConfig {
+    fn default() -> Self {
+        CounterpointConfig {
+            species: 1,
+            above: true,
+            cantus_voice: 0,
+            voice: 1,
+        }
+    }
+}
+
+/// A strict rule the line had to break
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Relaxation {
+    pub rule: CounterpointRule,
+    /// Index into the cantus firmus
+    pub note: usize,
+    pub message: String,
+}
+
+/// What was written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CounterpointReport {
+    pub voice: u8,
+    pub notes: usize,
+    /// Percent of moves in contrary motion
+    pub contrary_motion: f32,
+    pub relaxations: Vec<Relaxation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Contrary,
+    Similar,
+    Oblique,
+    Static,
+}
+
+fn motion(cantus_step: i16, line_step: i16) -> Motion {
+    match (cantus_step.signum(), line_step.signum()) {
+        (0, 0) => Motion::Static,
+        (0, _) | (_, 0) => Motion::Oblique,
+        (a, b) if a == b => Motion::Similar,
+        _ => Motion::Contrary,
+    }
+}
+
+fn is_consonant(interval: u8) -> bool {
+    matches!(interval % 12, 0 | 3 | 4 | 7 | 8 | 9)
+}
+
+fn is_perfect(interval: u8) -> bool {
+    matches!(interval % 12, 0 | 7)
+}
+
+struct Line<'a> {
+    cantus: &'a [Note],
+    above: bool,
+}
+
+impl Line<'_> {
+    fn interval(&self, i: usize, pitch: u8) -> u8 {
+        self.cantus[i].pitch.abs_diff(pitch)
+    }
+
+    // Strict rules broken by one note on its own
+    fn note_rules(&self, i: usize, pitch: u8) -> Vec<CounterpointRule> {
+        let interval = self.interval(i, pitch);
+        let last = self.cantus.len() - 1;
+        let mut broken = Vec::new();
+        if !is_consonant(interval) {
+            broken.push(CounterpointRule::Dissonance);
+        }
+        if i == 0 && !(interval.is_multiple_of(12) || (self.above && interval % 12 == 7)) {
+            broken.push(CounterpointRule::Opening);
+        }
+        if i == last && !interval.is_multiple_of(12) {
+            broken.push(CounterpointRule::Cadence);
+        }
+        if interval == 0 && i != 0 && i != last {
+            broken.push(CounterpointRule::Unison);
+        }
+        broken
+    }
+
+    // Strict rules broken moving from the previous note
+    fn move_rules(&self, i: usize, previous: u8, pitch: u8) -> Vec<CounterpointRule> {
+        let step = pitch as i16 - previous as i16;
+        let cantus_step = self.cantus[i].pitch as i16 - self.cantus[i - 1].pitch as i16;
+        let (before, now) = (self.interval(i - 1, previous), self.interval(i, pitch));
+        let mut broken = Vec::new();
+        if matches!(step.abs(), 6 | 9 | 10 | 11) || step.abs() > 12 || step == -8 {
+            broken.push(CounterpointRule::Leap);
+        }
+        if is_perfect(before) && before % 12 == now % 12 && motion(cantus_step, step) != Motion::Static {
+            broken.push(CounterpointRule::ParallelPerfect);
+        }
+        if i == self.cantus.len() - 1 && !matches!(step.abs(), 1 | 2) {
+            broken.push(CounterpointRule::Cadence);
+        }
+        broken
+    }
+
+    fn note_cost(&self, i: usize, pitch: u8) -> u32 {
+        let inner = i != 0 && i != self.cantus.len() - 1;
+        let preference = if inner && is_perfect(self.interval(i, pitch)) { INNER_PERFECT_COST } else { 0 };
+        STRICT_COST * self.note_rules(i, pitch).len() as u32 + preference
+    }
+
+    fn move_cost(&self, i: usize, previous: u8, pitch: u8) -> u32 {
+        let step = pitch as i16 - previous as i16;
+        let cantus_step = self.cantus[i].pitch as i16 - self.cantus[i - 1].pitch as i16;
+        let mut cost = STRICT_COST * self.move_rules(i, previous, pitch).len() as u32;
+        cost += match motion(cantus_step, step) {
+            Motion::Similar if is_perfect(self.interval(i, pitch)) => HIDDEN_PERFECT_COST,
+            Motion::Similar => SIMILAR_MOTION_COST,
+            _ => 0,
+        };
+        if step == 0 {
+            cost += REPEATED_NOTE_COST;
+        }
+        // Steps are free; each semitone of leap beyond a whole tone costs one
+        cost + (step.unsigned_abs() as u32).saturating_sub(2)
+    }
+
+    // Pitches in the key (plus the leading tone before the cadence) on the chosen side of the cantus
+    fn candidates(&self, i: usize, key: &Scale) -> Vec<u8> {
+        let cantus = self.cantus[i].pitch;
+        let (low, high) = if self.above {
+            (cantus, cantus.saturating_add(MAX_SPAN).min(127))
+        } else {
+            (cantus.saturating_sub(MAX_SPAN), cantus)
+        };
+        let leading_tone = key.root.transpose(-1);
+        let penultimate = i + 2 == self.cantus.len();
+        (low..=high)
+            .filter(|&p| {
+                let pc = PitchClass::new(p);
+                key.contains(pc) || (penultimate && pc == leading_tone)
+            })
+            .collect()
+    }
+}
+
+/// The cantus firmus: one note per start time from a voice of the song
+fn cantus_firmus(song: &Song, voice: u8) -> Vec<Note> {
+    let mut cantus: Vec<Note> = song.notes.iter().filter(|n| n.voice == voice).cloned().collect();
+    cantus.sort_by_key(|n| (n.start_tick, std::cmp::Reverse(n.pitch)));
+    cantus.dedup_by_key(|n| n.start_tick);
+    cantus
+}
+
+/// Write a counterpoint line for the cantus firmus, replacing the target
+/// voice's notes; the line takes the cantus's rhythm and velocities
+pub fn write_counterpoint(song: &mut Song, config: &CounterpointConfig) -> Result<CounterpointReport> {
+    if config.species != 1 {
+        return Err(MozartError::CounterpointError(format!(
+            "Species {} is not supported; only first species (note against note)",
+            config.species
+        )));
+    }
+    if config.voice == config.cantus_voice {
+        return Err(MozartError::CounterpointError("Write into a different voice than the cantus".to_string()));
+    }
+    let cantus = cantus_firmus(song, config.cantus_voice);
+    if cantus.len() < 2 {
+        return Err(MozartError::CounterpointError(format!(
+            "Voice {} needs at least two notes to use as a cantus firmus",
+            config.cantus_voice
+        )));
+    }
+
+    let key = song.settings.key;
+    let line = Line { cantus: &cantus, above: config.above };
+    let candidates: Vec<Vec<u8>> = (0..cantus.len()).map(|i| line.candidates(i, &key)).collect();
+    let pitches = cheapest_line(&line, &candidates)
+        .ok_or_else(|| MozartError::CounterpointError("No room for a line on that side of the cantus".to_string()))?;
+
+    let mut relaxations = Vec::new();
+    let mut contrary = 0;
+    for (i, &pitch) in pitches.iter().enumerate() {
+        let mut broken = line.note_rules(i, pitch);
+        if i > 0 {
+            broken.extend(line.move_rules(i, pitches[i - 1], pitch));
+            let cantus_step = cantus[i].pitch as i16 - cantus[i - 1].pitch as i16;
+            if motion(cantus_step, pitch as i16 - pitches[i - 1] as i16) == Motion::Contrary {
+                contrary += 1;
+            }
+        }
+        broken.dedup();
+        let against = format!("{} against {}", pitch_name(pitch), pitch_name(cantus[i].pitch));
+        relaxations.extend(broken.into_iter().map(|rule| Relaxation {
+            rule,
+            note: i,
+            message: format!("Note {} ({}): {}", i + 1, against, rule),
+        }));
+    }
+    if !relaxations.is_empty() {
+        tracing::warn!("Counterpoint breaks {} rules", relaxations.len());
+    }
+
+    let notes: Vec<Note> = cantus
+        .iter()
+        .zip(&pitches)
+        .map(|(c, &pitch)| Note::with_voice(pitch, c.start_tick, c.duration_ticks, c.velocity, config.voice))
+        .collect();
+    song.notes.retain(|n| n.voice != config.voice);
+    song.add_notes(notes);
+    tracing::info!("Wrote first-species counterpoint into voice {} ({} notes)", config.voice, pitches.len());
+
+    Ok(CounterpointReport {
+        voice: config.voice,
+        notes: pitches.len(),
+        contrary_motion: 100.0 * contrary as f32 / (pitches.len() - 1) as f32,
+        relaxations,
+    })
+}
+
+fn pitch_name(pitch: u8) -> String {
+    Pitch::from_midi(pitch).map_or_else(|_| pitch.to_string(), |p| p.to_string())
+}
+
+// Cheapest path through the candidates, choosing each note given the one before
+fn cheapest_line(line: &Line, candidates: &[Vec<u8>]) -> Option<Vec<u8>> {
+    if candidates.iter().any(|c| c.is_empty()) {
+        return None;
+    }
+
+    // (cost so far, index of the previous note's candidate)
+    let mut best: Vec<Vec<(u32, usize)>> = vec![candidates[0].iter().map(|&p| (line.note_cost(0, p), 0)).collect()];
+    for i in 1..candidates.len() {
+        let row = candidates[i]
+            .iter()
+            .map(|&pitch| {
+                let (cost, from) = candidates[i - 1]
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &previous)| (best[i - 1][j].0 + line.move_cost(i, previous, pitch), j))
+                    .min()
+                    .unwrap_or((u32::MAX, 0));
+                (cost + line.note_cost(i, pitch), from)
+            })
+            .collect();
+        best.push(row);
+    }
+
+    let mut at = best.last()?.iter().enumerate().min_by_key(|(_, &(cost, _))| cost)?.0;
+    let mut pitches = vec![0; candidates.len()];
+    for i in (0..candidates.len()).rev() {
+        pitches[i] = candidates[i][at];
+        at = best[i][at].1;
+    }
+    Some(pitches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use pretty_assertions::assert_eq;
+
+    fn cantus_song(melody: &str) -> Song {
+        let mut song = Song::new();
+        song.add_notes(parse_melody(melody).unwrap());
+        song
+    }
+
+    fn line(song: &Song, voice: u8) -> Vec<u8> {
+        song.notes.iter().filter(|n| n.voice == voice).map(|n| n.pitch).collect()
+    }
+
+    #[test]
+    fn test_first_species_above() {
+        // Fux's D dorian cantus firmus, moved to C major
+        let mut song = cantus_song("C4w D4w F4w E4w F4w G4w A4w G4w E4w D4w C4w");
+        let report = write_counterpoint(&mut song, &CounterpointConfig::default()).unwrap();
+        assert_eq!(report.notes, 11);
+        assert!(report.relaxations.is_empty(), "{:?}", report.relaxations);
+        assert!(report.contrary_motion >= 50.0);
+
+        let cantus = line(&song, 0);
+        let counterpoint = line(&song, 1);
+        assert!(counterpoint.iter().zip(&cantus).all(|(&p, &c)| p >= c && is_consonant(p - c)));
+        // Ends on the octave, approached by step from the leading tone
+        assert_eq!(&counterpoint[9..], &[71, 72]);
+        assert_eq!(song.notes.iter().filter(|n| n.voice == 1).count(), 11);
+    }
+
+    #[test]
+    fn test_below_and_replaces_voice() {
+        let mut song = cantus_song("C5w D5w E5w D5w C5w");
+        song.add_note(Note::with_voice(40, 0, 480, 100, 1));
+        let config = CounterpointConfig { above: false, ..Default::default() };
+        let report = write_counterpoint(&mut song, &config).unwrap();
+        assert!(report.relaxations.is_empty(), "{:?}", report.relaxations);
+
+        let counterpoint = line(&song, 1);
+        assert_eq!(counterpoint.len(), 5);
+        // Below the cantus the line opens and closes on the octave
+        assert_eq!(counterpoint[0], 60);
+        assert_eq!(counterpoint[4], 60);
+    }
+
+    #[test]
+    fn test_reports_relaxations() {
+        // Opening on a fifth or octave over C and closing on the octave over D
+        // a step away is impossible without parallels, so one rule has to give
+        let mut song = cantus_song("C4w D4w");
+        let report = write_counterpoint(&mut song, &CounterpointConfig::default()).unwrap();
+        assert_eq!(report.relaxations.len(), 1);
+        assert!(report.relaxations[0].message.starts_with("Note "));
+
+        let config = CounterpointConfig { species: 2, ..Default::default() };
+        assert!(matches!(write_counterpoint(&mut song, &config), Err(MozartError::CounterpointError(_))));
+        let mut empty = Song::new();
+        assert!(write_counterpoint(&mut empty, &CounterpointConfig::default()).is_err());
+    }
+}
@@ -14,6 +14,15 @@ pub enum MozartError {
     #[error("Invalid time signature: {numerator}/{denominator}")]
     InvalidTimeSignature { numerator: u8, denominator: u8 },
 
+    #[error("Invalid beat grouping: {0}")]
+    InvalidGrouping(String),
+
+    #[error("Invalid position: {0}")]
+    InvalidPosition(String),
+
+    #[error("Invalid edit: {0}")]
+    InvalidEdit(String),
+
     #[error("Invalid scale: {0}")]
     InvalidScale(String),
 
@@ -26,9 +35,63 @@ pub enum MozartError {
     #[error("File error: {0}")]
     FileError(String),
 
-    #[error("MIDI export error: {0}")]
+    #[error("MIDI error: {0}")]
     MidiError(String),
 
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    #[error("Job error: {0}")]
+    JobError(String),
+
+    #[error("Worksheet error: {0}")]
+    WorksheetError(String),
+
+    #[error("Practice error: {0}")]
+    PracticeError(String),
+
+    #[error("Harmony error: {0}")]
+    HarmonyError(String),
+
+    #[error("Counterpoint error: {0}")]
+    CounterpointError(String),
+
+    #[error("Melody error: {0}")]
+    MelodyError(String),
+
+    #[error("Lyrics error: {0}")]
+    LyricsError(String),
+
+    #[error("Groove error: {0}")]
+    GrooveError(String),
+
+    #[error("Tutorial: {0}")]
+    TutorialError(String),
+
+    #[error("Macro error: {0}")]
+    MacroError(String),
+
+    #[error("Pipeline error: {0}")]
+    PipelineError(String),
+
+    #[error("Jam sync error: {0}")]
+    JamError(String),
+
+    #[error("Merge error: {0}")]
+    MergeError(String),
+
+    #[error("Split error: {0}")]
+    SplitError(String),
+
+    #[error("Track error: {0}")]
+    TrackError(String),
+
+    #[error("Similarity error: {0}")]
+    SimilarityError(String),
+
+    #[error("Render error: {0}")]
+    RenderError(String),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 }
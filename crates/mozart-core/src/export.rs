@@ -0,0 +1,158 @@
+//! File export as background jobs
+//!
+//! Runs exports on the [`JobManager`] so long renders report progress and can
+//! be cancelled instead of blocking the caller. Nothing is written to disk if
+//! an export is cancelled.
+//!
+//! MIDI and WAV are covered. There is no MusicXML writer in the crate yet;
+//! when one lands it should take the same [`JobContext`] and return an
+//! [`ExportResult`] with format "musicxml".
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::error::{MozartError, Result};
+use crate::jobs::{JobContext, JobId, JobManager};
+use crate::midi::MidiExporter;
+use crate::render::{render_song, DEFAULT_SAMPLE_RATE};
+use crate::song::Song;
+use crate::time::PlaybackRate;
+
+/// Outcome of a finished export, delivered as the job result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub path: PathBuf,
+    /// File format ("midi" or "wav")
+    pub format: String,
+    pub size_bytes: u64,
+    /// Playback length of the exported file in seconds
+    pub duration_seconds: f64,
+}
+
+/// Export a song to a MIDI file, reporting progress through the job context
+///
+/// Accents are written into the velocities when the song plays them.
+pub fn export_midi(ctx: &JobContext, song: &Song, path: impl Into<PathBuf>) -> Result<ExportResult> {
+    let path = path.into();
+    let exporter = MidiExporter::new().with_accent_dynamics(song.settings.accent_dynamics);
+    let data = exporter.export_with_progress(song, |percent, stage| {
+        ctx.progress(percent * 0.9, stage);
+        ctx.checkpoint()
+    })?;
+
+    ctx.progress(90.0, "Saving file");
+    ctx.checkpoint()?;
+    std::fs::write(&path, &data)
+        .map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))?;
+    ctx.progress(100.0, "Done");
+
+    tracing::info!("MIDI file saved: {:?}", path);
+    Ok(ExportResult {
+        path,
+        format: "midi".to_string(),
+        size_bytes: data.len() as u64,
+        duration_seconds: song.duration_with_tempo_map(),
+    })
+}
+
+/// Queue a MIDI export; the job's `Completed` event carries an [`ExportResult`]
+pub fn spawn_midi_export(jobs: &JobManager, song: Song, path: impl Into<PathBuf>) -> JobId {
+    let path = path.into();
+    jobs.spawn(move |ctx| {
+        let result = export_midi(ctx, &song, path)?;
+        Ok(serde_json::to_value(result)?)
+    })
+}
+
+/// Export a practice track: the song rendered to WAV at `rate`, pitch unchanged
+pub fn export_practice_audio(ctx: &JobContext, song: &Song, path: impl Into<PathBuf>, rate: PlaybackRate) -> Result<ExportResult> {
+    let path = path.into();
+    ctx.progress(0.0, "Rendering audio");
+    let audio = render_song(song, DEFAULT_SAMPLE_RATE)?;
+    ctx.progress(40.0, "Changing speed");
+    ctx.checkpoint()?;
+    let audio = audio.time_stretch(rate);
+
+    ctx.progress(90.0, "Saving file");
+    ctx.checkpoint()?;
+    let data = audio.to_wav();
+    std::fs::write(&path, &data)
+        .map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))?;
+    ctx.progress(100.0, "Done");
+
+    tracing::info!("Practice audio at {} saved: {:?}", rate, path);
+    Ok(ExportResult {
+        path,
+        format: "wav".to_string(),
+        size_bytes: data.len() as u64,
+        duration_seconds: audio.duration_seconds(),
+    })
+}
+
+/// Queue a practice audio export; the job's `Completed` event carries an [`ExportResult`]
+pub fn spawn_practice_audio_export(jobs: &JobManager, song: Song, path: impl Into<PathBuf>, rate: PlaybackRate) -> JobId {
+    let path = path.into();
+    jobs.spawn(move |ctx| {
+        let result = export_practice_audio(ctx, &song, path, rate)?;
+        Ok(serde_json::to_value(result)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobEvent;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    fn run_export(spawn: impl FnOnce(&JobManager) -> JobId) -> ExportResult {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let jobs = JobManager::new(1, move |event| {
+            let _ = tx.lock().unwrap().send(event.clone());
+        });
+
+        let id = spawn(&jobs);
+        let result = loop {
+            match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+                JobEvent::Completed { result, .. } => break result,
+                JobEvent::Failed { error, .. } => panic!("export failed: {}", error),
+                _ => {}
+            }
+        };
+        assert_eq!(jobs.status(id), Some(crate::jobs::JobStatus::Completed));
+        serde_json::from_value(result).unwrap()
+    }
+
+    #[test]
+    fn test_spawn_midi_export() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 1920));
+        let path = std::env::temp_dir().join(format!("mozart-export-{}.mid", std::process::id()));
+
+        let result = run_export(|jobs| spawn_midi_export(jobs, song, &path));
+        let written = std::fs::metadata(&path).unwrap().len();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(result.format, "midi");
+        assert_eq!(result.size_bytes, written);
+        assert_eq!(result.duration_seconds, 2.0);
+    }
+
+    #[test]
+    fn test_spawn_practice_audio_export() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 960));
+        let path = std::env::temp_dir().join(format!("mozart-export-{}.wav", std::process::id()));
+
+        let rate = PlaybackRate::new(0.5).unwrap();
+        let result = run_export(|jobs| spawn_practice_audio_export(jobs, song, &path, rate));
+        let written = std::fs::metadata(&path).unwrap().len();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(result.format, "wav");
+        assert_eq!(result.size_bytes, written);
+        assert_eq!(result.duration_seconds, 2.0);
+    }
+}
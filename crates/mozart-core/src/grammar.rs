@@ -0,0 +1,254 @@
+//! Text notation grammar
+//!
+//! A machine-readable description of the melody notation ("C4q D4e. Rq
+//! xh"): the kinds of token, pitch spellings and accidentals, the octave
+//! range, and durations. Numeric durations ("R4", "x8") only follow rests
+//! and hits; after a note they would run into the octave. Everything is
+//! read from the tables the parser itself uses ([`PitchClass::SPELLINGS`],
+//! [`NoteValue::aliases`], the prefixes in `note.rs`), so help panels and
+//! external editors built on [`get_notation_grammar`] follow the parser
+//! when it changes.
+
+use serde::{Deserialize, Serialize};
+use crate::note::{NoteDuration, NoteValue, DEFAULT_NOTE_VALUE, DOT, REST_PREFIX, RHYTHM_HIT_PREFIX};
+use crate::pitch::{Pitch, PitchClass};
+
+/// What a token writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// A pitched note ("F#5h.")
+    Note,
+    /// Silence ("Rq")
+    Rest,
+    /// A note without a pitch, played at the song's rhythm pitch ("xe")
+    RhythmHit,
+}
+
+/// One form a token can take
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenRule {
+    pub kind: TokenKind,
+    /// e.g., "<pitch><octave>[duration]"
+    pub syntax: String,
+    pub description: String,
+    /// Tokens of this kind the parser accepts
+    pub examples: Vec<String>,
+}
+
+/// Accidental symbols that raise or lower a letter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Accidental {
+    /// +1 for a sharp, -1 for a flat
+    pub semitones: i8,
+    pub symbols: Vec<String>,
+}
+
+/// A note value and the names it can be written with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationRule {
+    /// Short name, e.g., "q"
+    pub symbol: String,
+    /// e.g., "quarter"
+    pub name: String,
+    /// Names usable in any token, the short one first
+    pub names: Vec<String>,
+    /// Numbers usable after a rest or hit (e.g., "4" in "R4")
+    pub numbers: Vec<String>,
+    pub ticks: u32,
+    /// With a dot after it
+    pub dotted_ticks: u32,
+}
+
+/// Range of the octave number (scientific pitch notation)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OctaveRange {
+    pub lowest_pitch: String,
+    pub highest_pitch: String,
+    pub middle_c: String,
+}
+
+/// The whole notation, as [`get_notation_grammar`] describes it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotationGrammar {
+    /// Tokens are separated by whitespace
+    pub separator: String,
+    /// Letters may be written in either case
+    pub case_sensitive: bool,
+    pub tokens: Vec<TokenRule>,
+    /// Every pitch class spelling, with its pitch class (0 = C)
+    pub pitch_classes: Vec<(String, u8)>,
+    pub accidentals: Vec<Accidental>,
+    pub octaves: OctaveRange,
+    pub durations: Vec<DurationRule>,
+    /// Makes a duration dotted (half again as long)
+    pub dot: String,
+    /// Duration of a token written without one
+    pub default_duration: String,
+    /// The grammar in EBNF
+    pub ebnf: String,
+}
+
+// Sharps and flats, from the spellings that add a symbol to the letter
+fn accidentals() -> Vec<Accidental> {
+    let mut accidentals: Vec<Accidental> = Vec::new();
+    for &(spelling, pitch_class) in PitchClass::SPELLINGS {
+        let mut chars = spelling.chars();
+        let Some(letter) = chars.next().and_then(|c| PitchClass::parse(&c.to_string()).ok()) else { continue };
+        let symbol = chars.as_str();
+        if symbol.is_empty() {
+            continue;
+        }
+        let semitones = (letter.interval_to(pitch_class) as i8 + 6).rem_euclid(12) - 6;
+        match accidentals.iter_mut().find(|a| a.semitones == semitones) {
+            Some(accidental) if !accidental.symbols.iter().any(|s| s == symbol) => {
+                accidental.symbols.push(symbol.to_string())
+            }
+            Some(_) => {}
+            None => accidentals.push(Accidental { semitones, symbols: vec![symbol.to_string()] }),
+        }
+    }
+    accidentals.sort_by_key(|a| -a.semitones);
+    accidentals
+}
+
+// A duration the octave would swallow after a note
+fn is_number(alias: &str) -> bool {
+    alias.starts_with(|c: char| c.is_ascii_digit())
+}
+
+fn quoted(alternatives: impl IntoIterator<Item = String>) -> String {
+    alternatives.into_iter().map(|a| format!("\"{}\"", a)).collect::<Vec<_>>().join(" | ")
+}
+
+/// Describe the text notation the melody parser reads
+pub fn get_notation_grammar() -> NotationGrammar {
+    let default = DEFAULT_NOTE_VALUE.short_name();
+    let durations: Vec<DurationRule> = NoteValue::ALL
+        .iter()
+        .map(|value| DurationRule {
+            symbol: value.short_name().to_string(),
+            name: value.to_string(),
+            names: value.aliases().iter().filter(|a| !is_number(a)).map(|a| a.to_string()).collect(),
+            numbers: value.aliases().iter().filter(|a| is_number(a)).map(|a| a.to_string()).collect(),
+            ticks: value.ticks(),
+            dotted_ticks: NoteDuration::dotted(*value).ticks(),
+        })
+        .collect();
+    let (lowest, highest) = (Pitch::from_midi(0).unwrap(), Pitch::from_midi(127).unwrap());
+
+    let tokens = vec![
+        TokenRule {
+            kind: TokenKind::Note,
+            syntax: "<pitch class><octave>[duration]".to_string(),
+            description: format!("A note; without a duration it is a {}", DEFAULT_NOTE_VALUE),
+            examples: vec!["C4q".to_string(), "F#5h.".to_string(), "Bb3e".to_string(), "G4".to_string()],
+        },
+        TokenRule {
+            kind: TokenKind::Rest,
+            syntax: format!("{}[duration]", REST_PREFIX),
+            description: "A rest".to_string(),
+            examples: vec![format!("{}q", REST_PREFIX), format!("{}h{}", REST_PREFIX, DOT)],
+        },
+        TokenRule {
+            kind: TokenKind::RhythmHit,
+            syntax: format!("{}[duration]", RHYTHM_HIT_PREFIX),
+            description: "A hit without a pitch, played at the song's rhythm pitch".to_string(),
+            examples: vec![format!("{}q", RHYTHM_HIT_PREFIX), format!("{}e", RHYTHM_HIT_PREFIX)],
+        },
+    ];
+
+    let ebnf = [
+        "melody = { token } ;  (* separated by whitespace; letters in either case *)".to_string(),
+        "token = note | rest | rhythm_hit ;".to_string(),
+        "note = pitch_class , octave , [ note_duration ] ;".to_string(),
+        format!("rest = \"{}\" , [ duration ] ;", REST_PREFIX),
+        format!("rhythm_hit = \"{}\" , [ duration ] ;", RHYTHM_HIT_PREFIX),
+        format!("pitch_class = {} ;", quoted(PitchClass::SPELLINGS.iter().map(|(s, _)| s.to_string()))),
+        format!("octave = [ \"-\" ] , digit , {{ digit }} ;  (* {} to {} *)", lowest, highest),
+        format!("duration = ( name | number ) , [ \"{}\" ] ;  (* default {} *)", DOT, default),
+        format!("note_duration = name , [ \"{}\" ] ;  (* a number would run into the octave *)", DOT),
+        format!("name = {} ;", quoted(durations.iter().flat_map(|d| d.names.clone()))),
+        format!("number = {} ;", quoted(durations.iter().flat_map(|d| d.numbers.clone()))),
+    ]
+    .join("\n");
+
+    NotationGrammar {
+        separator: "whitespace".to_string(),
+        case_sensitive: false,
+        tokens,
+        pitch_classes: PitchClass::SPELLINGS.iter().map(|(s, pc)| (s.to_string(), pc.semitones())).collect(),
+        accidentals: accidentals(),
+        octaves: OctaveRange {
+            lowest_pitch: lowest.to_string(),
+            highest_pitch: highest.to_string(),
+            middle_c: Pitch::MIDDLE_C.to_string(),
+        },
+        durations,
+        dot: DOT.to_string(),
+        default_duration: default.to_string(),
+        ebnf,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody_token;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_grammar_matches_parser() {
+        let grammar = get_notation_grammar();
+
+        // Every example parses as its kind
+        for rule in &grammar.tokens {
+            for example in &rule.examples {
+                let token = parse_melody_token(example).unwrap();
+                assert_eq!(token.pitch.is_none(), rule.kind == TokenKind::Rest, "{}", example);
+            }
+        }
+        for (spelling, pitch_class) in &grammar.pitch_classes {
+            assert_eq!(PitchClass::parse(spelling).unwrap().semitones(), *pitch_class);
+            assert_eq!(PitchClass::parse(&spelling.to_lowercase()).unwrap().semitones(), *pitch_class);
+        }
+        for duration in &grammar.durations {
+            for name in &duration.names {
+                let note = parse_melody_token(&format!("C4{}", name)).unwrap();
+                assert_eq!(note.duration_ticks, duration.ticks);
+                let dotted = parse_melody_token(&format!("C4{}{}", name, grammar.dot)).unwrap();
+                assert_eq!(dotted.duration_ticks, duration.dotted_ticks);
+            }
+            for number in &duration.numbers {
+                let rest = parse_melody_token(&format!("R{}{}", number, grammar.dot)).unwrap();
+                assert_eq!(rest.duration_ticks, duration.dotted_ticks);
+                assert!(parse_melody_token(&format!("C4{}", number)).is_err(), "{}", number);
+            }
+        }
+        assert_eq!(parse_melody_token(&grammar.octaves.lowest_pitch).unwrap().pitch, Some(0));
+        assert_eq!(parse_melody_token(&grammar.octaves.highest_pitch).unwrap().pitch, Some(127));
+        assert_eq!(parse_melody_token("C4").unwrap().duration_ticks, 480);
+    }
+
+    #[test]
+    fn test_grammar_tables() {
+        let grammar = get_notation_grammar();
+        assert_eq!(
+            grammar.accidentals,
+            vec![
+                Accidental { semitones: 1, symbols: vec!["#".to_string(), "♯".to_string(), "s".to_string()] },
+                Accidental { semitones: -1, symbols: vec!["b".to_string(), "♭".to_string()] },
+            ]
+        );
+        assert_eq!(grammar.octaves.lowest_pitch, "C-1");
+        assert_eq!(grammar.octaves.highest_pitch, "G9");
+        assert_eq!(grammar.default_duration, "q");
+        assert_eq!(grammar.durations[2].names, vec!["q", "quarter"]);
+        assert_eq!(grammar.durations[2].numbers, vec!["4"]);
+        assert!(grammar.ebnf.contains("rest = \"R\" , [ duration ] ;"));
+
+        let json = serde_json::to_value(&grammar).unwrap();
+        assert_eq!(json["tokens"][2]["kind"], "rhythm_hit");
+        assert_eq!(json["pitch_classes"][1], serde_json::json!(["C#", 1]));
+    }
+}
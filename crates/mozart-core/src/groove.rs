@@ -0,0 +1,161 @@
+//! Groove templates
+//!
+//! The feel of a performance as offsets from a grid: every beat is split
+//! into equal subdivisions, and each subdivision remembers how early or late
+//! its notes were played and how much louder or softer than the passage's
+//! average. Applying a template snaps notes to the same grid and then adds
+//! those offsets, so a step-entered line picks up a human player's timing
+//! and accents.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+/// Average feel of one beat subdivision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct GrooveSlot {
+    /// Ticks after (positive) or before the grid point
+    pub timing_offset_ticks: i32,
+    /// Velocity above (positive) or below the passage's average
+    pub velocity_offset: i16,
+    /// Notes the averages came from (0 leaves notes on this slot alone)
+    pub note_count: u32,
+}
+
+/// Timing and velocity offsets for every subdivision of a beat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrooveTemplate {
+    pub beat_ticks: u32,
+    pub subdivisions: u8,
+    /// One per subdivision, from the beat's start
+    pub slots: Vec<GrooveSlot>,
+}
+
+impl GrooveTemplate {
+    fn step_ticks(&self) -> u32 {
+        self.beat_ticks / self.subdivisions as u32
+    }
+
+    // Nearest grid tick and its slot
+    fn grid_point(&self, tick: u32) -> (u32, usize) {
+        let step = self.step_ticks();
+        let index = (tick + step / 2) / step;
+        (index * step, index as usize % self.subdivisions as usize)
+    }
+}
+
+/// Measure the groove of a performance against a grid of `subdivisions` per beat
+///
+/// Each note counts toward the subdivision nearest its start.
+pub fn extract_groove<'a>(
+    notes: impl IntoIterator<Item = &'a Note>,
+    beat_ticks: u32,
+    subdivisions: u8,
+) -> Result<GrooveTemplate> {
+    if subdivisions == 0 || beat_ticks < subdivisions as u32 {
+        return Err(MozartError::GrooveError(format!(
+            "Cannot split a {}-tick beat into {} subdivisions",
+            beat_ticks, subdivisions
+        )));
+    }
+
+    let mut template = GrooveTemplate {
+        beat_ticks,
+        subdivisions,
+        slots: vec![GrooveSlot::default(); subdivisions as usize],
+    };
+    let mut timing = vec![0i64; subdivisions as usize];
+    let mut velocity = vec![0i64; subdivisions as usize];
+    let (mut total_velocity, mut count) = (0i64, 0i64);
+    for note in notes {
+        let (grid, slot) = template.grid_point(note.start_tick);
+        timing[slot] += note.start_tick as i64 - grid as i64;
+        velocity[slot] += note.velocity as i64;
+        template.slots[slot].note_count += 1;
+        total_velocity += note.velocity as i64;
+        count += 1;
+    }
+    if count == 0 {
+        return Err(MozartError::GrooveError("No notes to take a groove from".to_string()));
+    }
+
+    let average = total_velocity as f64 / count as f64;
+    for (i, slot) in template.slots.iter_mut().enumerate().filter(|(_, s)| s.note_count > 0) {
+        let n = slot.note_count as f64;
+        slot.timing_offset_ticks = (timing[i] as f64 / n).round() as i32;
+        slot.velocity_offset = (velocity[i] as f64 / n - average).round() as i16;
+    }
+
+    tracing::debug!("Extracted a groove of {} subdivisions from {} notes", subdivisions, count);
+    Ok(template)
+}
+
+/// Move notes to the template's grid plus its offsets, and shift their velocities
+///
+/// Notes keep their length. Returns the number of notes changed.
+pub fn apply_groove(template: &GrooveTemplate, notes: &mut [&mut Note]) -> usize {
+    let mut changed = 0;
+    for note in notes.iter_mut() {
+        let (grid, slot) = template.grid_point(note.start_tick);
+        let slot = template.slots[slot];
+        if slot.note_count == 0 {
+            continue;
+        }
+
+        let start = (grid as i64 + slot.timing_offset_ticks as i64).max(0) as u32;
+        let velocity = (note.velocity as i16 + slot.velocity_offset).clamp(1, 127) as u8;
+        if (start, velocity) != (note.start_tick, note.velocity) {
+            note.start_tick = start;
+            note.velocity = velocity;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn note(start_tick: u32, velocity: u8) -> Note {
+        Note::with_velocity(60, start_tick, 120, velocity)
+    }
+
+    // Swung eighths: offbeats late and soft, downbeats a little early
+    fn swung() -> Vec<Note> {
+        vec![note(0, 100), note(300, 70), note(470, 104), note(780, 66)]
+    }
+
+    #[test]
+    fn test_extract_groove() {
+        let template = extract_groove(&swung(), 480, 2).unwrap();
+        assert_eq!(
+            template.slots,
+            vec![
+                GrooveSlot { timing_offset_ticks: -5, velocity_offset: 17, note_count: 2 },
+                GrooveSlot { timing_offset_ticks: 60, velocity_offset: -17, note_count: 2 },
+            ]
+        );
+
+        assert!(extract_groove(&swung(), 480, 0).is_err());
+        assert!(extract_groove(&[], 480, 4).is_err());
+    }
+
+    #[test]
+    fn test_apply_groove() {
+        let template = extract_groove(&swung(), 480, 2).unwrap();
+        let mut straight = [note(960, 80), note(1200, 80), note(1450, 120)];
+        let mut refs: Vec<&mut Note> = straight.iter_mut().collect();
+        assert_eq!(apply_groove(&template, &mut refs), 3);
+
+        let placed: Vec<(u32, u8, u32)> = straight.iter().map(|n| (n.start_tick, n.velocity, n.duration_ticks)).collect();
+        // Snapped to the grid first, then offset; velocity stays in MIDI range
+        assert_eq!(placed, vec![(955, 97, 120), (1260, 63, 120), (1435, 127, 120)]);
+
+        // Slots the performance never played are left alone
+        let sparse = extract_groove(&[note(0, 90)], 480, 4).unwrap();
+        let mut offbeat = note(240, 80);
+        assert_eq!(apply_groove(&sparse, &mut [&mut offbeat]), 0);
+    }
+}
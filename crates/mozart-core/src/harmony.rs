@@ -0,0 +1,521 @@
+//! Harmony suggestions
+//!
+//! Ranks chords to follow a progression and proposes chords for a measure
+//! of melody. Candidates are the diatonic triads of the key (optionally
+//! seventh chords too). Rankings weigh functional harmony (tonic,
+//! predominant, and dominant, plus root motion by falling fifth) against
+//! voice leading: how far the voices move and how many tones they keep.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::PitchClass;
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::theory::{diatonic_chord, diatonic_chord_tone_names};
+
+/// Share of a ranking that comes from functional harmony (the rest is voice leading)
+const FUNCTION_WEIGHT: f32 = 0.6;
+/// Extra function score for root motion by falling fifth
+const FALLING_FIFTH_BONUS: f32 = 0.3;
+/// Share of a reharmonization's score that comes from how well it fits the melody
+const MELODY_WEIGHT: f32 = 0.3;
+/// Most chords a measure can be split into
+const MAX_CHORDS_PER_MEASURE: u32 = 4;
+/// Lowest root of chords written as notes (C3, below the usual melody range)
+const CHORD_ROOT_PITCH: u8 = 48;
+const CHORD_VELOCITY: u8 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HarmonicFunction {
+    /// I, iii, vi: stable
+    Tonic,
+    /// ii, IV: leads to the dominant
+    Predominant,
+    /// V, vii°: wants to resolve to the tonic
+    Dominant,
+}
+
+impl HarmonicFunction {
+    fn of_degree(degree: u8) -> Self {
+        match degree {
+            2 | 4 => HarmonicFunction::Predominant,
+            5 | 7 => HarmonicFunction::Dominant,
+            _ => HarmonicFunction::Tonic,
+        }
+    }
+}
+
+/// A diatonic chord of the key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyChord {
+    /// Scale degree of the root (1-7)
+    pub degree: u8,
+    /// Roman numeral, e.g., "ii", "V7"
+    pub numeral: String,
+    /// Chord symbol, e.g., "Dm", "G7"
+    pub symbol: String,
+    pub function: HarmonicFunction,
+    /// Pitch classes (0 = C), root first
+    pub pitch_classes: Vec<u8>,
+}
+
+impl KeyChord {
+    pub(crate) fn new(key: &Scale, degree: u8, seventh: bool) -> Self {
+        let (numeral, symbol, tones) = diatonic_chord(key, degree, seventh);
+        KeyChord {
+            degree,
+            numeral,
+            symbol,
+            function: HarmonicFunction::of_degree(degree),
+            pitch_classes: tones.iter().map(|t| t.semitones()).collect(),
+        }
+    }
+
+    pub(crate) fn contains(&self, pitch_class: u8) -> bool {
+        self.pitch_classes.contains(&pitch_class)
+    }
+}
+
+/// The diatonic triads of a key (and seventh chords, if asked for), by degree
+pub fn key_chords(key: &Scale, sevenths: bool) -> Vec<KeyChord> {
+    let mut chords: Vec<KeyChord> = (1..=7).map(|degree| KeyChord::new(key, degree, false)).collect();
+    if sevenths {
+        chords.extend((1..=7).map(|degree| KeyChord::new(key, degree, true)));
+    }
+    chords
+}
+
+/// A rung of the diatonic chord ladder: the triad and seventh chord on a degree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiatonicChord {
+    /// Scale degree of the root (1-7)
+    pub degree: u8,
+    pub triad: KeyChord,
+    pub seventh: KeyChord,
+    /// Tones of the seventh chord spelled in the key, root first; the triad is the first three
+    pub tone_names: Vec<String>,
+}
+
+/// The seven rungs of a key's chord ladder (I–ii–iii–IV–V–vi–vii° in major)
+pub fn diatonic_chords(key: &Scale) -> Vec<DiatonicChord> {
+    (1..=7)
+        .map(|degree| DiatonicChord {
+            degree,
+            triad: KeyChord::new(key, degree, false),
+            seventh: KeyChord::new(key, degree, true),
+            tone_names: diatonic_chord_tone_names(key, degree, true),
+        })
+        .collect()
+}
+
+/// Find a chord of the key by Roman numeral ("V7", "vii°" or "viio") or by
+/// symbol ("G7", "Bdim"; enharmonic roots are accepted)
+pub fn parse_chord(name: &str, key: &Scale) -> Result<KeyChord> {
+    let name = name.trim();
+    let numeral = name.replace('o', "°");
+    let symbol = split_symbol(name);
+
+    key_chords(key, true)
+        .into_iter()
+        .find(|chord| {
+            chord.numeral == numeral
+                || symbol.as_ref().is_some_and(|(root, suffix)| {
+                    split_symbol(&chord.symbol).is_some_and(|(r, s)| r == *root && s == *suffix)
+                })
+        })
+        .ok_or_else(|| MozartError::HarmonyError(format!("{} is not a chord of {}", name, key)))
+}
+
+/// Notes of a chord in root position, stacked upward from C3
+pub(crate) fn chord_notes(pitch_classes: &[u8], start_tick: u32, duration_ticks: u32) -> Vec<Note> {
+    let mut pitch = CHORD_ROOT_PITCH - 1;
+    pitch_classes
+        .iter()
+        .map(|&pitch_class| {
+            pitch += match (pitch_class + 12 - pitch % 12) % 12 {
+                0 => 12,
+                step => step,
+            };
+            let mut note = Note::new(pitch, start_tick, duration_ticks);
+            note.velocity = CHORD_VELOCITY;
+            note
+        })
+        .collect()
+}
+
+// Root pitch class and quality suffix of a chord symbol
+pub(crate) fn split_symbol(symbol: &str) -> Option<(PitchClass, &str)> {
+    let root_len = symbol.char_indices().nth(1).map_or(symbol.len(), |(i, c)| {
+        if c == '#' || c == 'b' { i + 1 } else { i }
+    });
+    let root = PitchClass::parse(&symbol[..root_len]).ok()?;
+    Some((root, &symbol[root_len..]))
+}
+
+/// A candidate next chord, with why it ranked where it did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChordSuggestion {
+    pub chord: KeyChord,
+    /// 0-100, higher is better
+    pub score: f32,
+    /// Total semitones the voices move from the previous chord
+    pub voice_movement: u8,
+    /// Tones shared with the previous chord
+    pub common_tones: u8,
+    pub reason: String,
+}
+
+// Semitones between two pitch classes, the short way round
+fn distance(a: u8, b: u8) -> u8 {
+    let up = (b + 12 - a) % 12;
+    up.min(12 - up)
+}
+
+// Each voice of `from` moves to the nearest tone of `to`
+fn voice_movement(from: &KeyChord, to: &KeyChord) -> u8 {
+    from.pitch_classes
+        .iter()
+        .map(|&a| to.pitch_classes.iter().map(|&b| distance(a, b)).min().unwrap_or(0))
+        .sum()
+}
+
+// How strongly functional harmony expects `to` after `from` (0-1), and why
+fn function_score(from: &KeyChord, to: &KeyChord) -> (f32, String) {
+    use HarmonicFunction::*;
+
+    if from.degree == to.degree {
+        return (0.3, "Repeats the chord".to_string());
+    }
+    let (mut score, reason): (f32, &str) = match (from.function, to.function) {
+        (Dominant, Tonic) if to.degree == 1 => (1.0, "Resolves the dominant"),
+        (Dominant, Tonic) if to.degree == 6 => (0.9, "Deceptive resolution"),
+        (Dominant, Tonic) => (0.3, "Weak resolution"),
+        (Predominant, Dominant) if to.degree == 7 => (0.6, "Predominant to leading-tone chord"),
+        (Predominant, Dominant) => (1.0, "Predominant to dominant"),
+        (Tonic, Predominant) => (0.8, "Moves away from the tonic"),
+        (Tonic, Dominant) => (0.7, "Tonic to dominant"),
+        (Predominant, Tonic) if to.degree == 1 => (0.7, "Plagal motion"),
+        (Predominant, Tonic) => (0.3, "Predominant to a tonic substitute"),
+        (Predominant, Predominant) => (0.5, "Stays predominant"),
+        (Tonic, Tonic) => (0.4, "Prolongs the tonic"),
+        (Dominant, Dominant) => (0.4, "Prolongs the dominant"),
+        (Dominant, Predominant) => (0.1, "Retrogression against the pull to the tonic"),
+    };
+    let mut reason = reason.to_string();
+
+    // Root falls a fifth (rises a fourth), the strongest root motion
+    if (to.pitch_classes[0] + 12 - from.pitch_classes[0]) % 12 == 5 {
+        score += FALLING_FIFTH_BONUS;
+        reason.push_str(" by falling fifth");
+    }
+    (score.min(1.0), reason)
+}
+
+fn rank(from: Option<&KeyChord>, to: &KeyChord) -> ChordSuggestion {
+    let Some(from) = from else {
+        let (score, reason) = match to.function {
+            HarmonicFunction::Tonic if to.degree == 1 => (100.0, "Opens on the tonic"),
+            HarmonicFunction::Tonic => (60.0, "Opens on a tonic substitute"),
+            _ => (30.0, "Opens away from the tonic"),
+        };
+        return ChordSuggestion {
+            chord: to.clone(),
+            score,
+            voice_movement: 0,
+            common_tones: 0,
+            reason: reason.to_string(),
+        };
+    };
+
+    let (function, reason) = function_score(from, to);
+    let movement = voice_movement(from, to);
+    // Two semitones per voice is as far as smooth voice leading goes
+    let smoothness = (1.0 - movement as f32 / (2.0 * from.pitch_classes.len() as f32)).max(0.0);
+    let common_tones = from.pitch_classes.iter().filter(|&&pc| to.contains(pc)).count() as u8;
+
+    ChordSuggestion {
+        chord: to.clone(),
+        score: 100.0 * (FUNCTION_WEIGHT * function + (1.0 - FUNCTION_WEIGHT) * smoothness),
+        voice_movement: movement,
+        common_tones,
+        reason,
+    }
+}
+
+/// Rank the key's triads to follow a progression (chord names as for
+/// [`parse_chord`]), best first; the progression's last chord is left out
+pub fn suggest_next_chords(progression: &[String], key: &Scale) -> Result<Vec<ChordSuggestion>> {
+    let last = progression.last().map(|name| parse_chord(name, key)).transpose()?;
+    let mut suggestions: Vec<ChordSuggestion> = key_chords(key, false)
+        .iter()
+        .filter(|chord| last.as_ref().is_none_or(|l| l.degree != chord.degree))
+        .map(|chord| rank(last.as_ref(), chord))
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    tracing::debug!("Suggested {} chords after {:?} in {}", suggestions.len(), progression.last(), key);
+    Ok(suggestions)
+}
+
+/// Limits on [`reharmonize_measure`]; omitted fields use the defaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReharmonizeConstraints {
+    /// Split the measure evenly into this many chords (1-4)
+    pub chords_per_measure: u32,
+    /// Chord before the measure, to lead from
+    pub previous: Option<String>,
+    /// Chord after the measure, to lead into
+    pub next: Option<String>,
+    /// Let notes shorter than a beat that start off the beat be non-chord tones
+    pub passing_tones: bool,
+    /// Consider diatonic seventh chords as well as triads
+    pub sevenths: bool,
+    /// Most alternatives to return
+    pub limit: usize,
+}
+
+impl Default for ReharmonizeConstraints {
+    fn default() -> Self {
+        ReharmonizeConstraints {
+            chords_per_measure: 1,
+            previous: None,
+            next: None,
+            passing_tones: false,
+            sevenths: false,
+            limit: 5,
+        }
+    }
+}
+
+/// A chord over part of the measure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarmonizedSpan {
+    pub start_tick: u32,
+    pub end_tick: u32,
+    pub chord: KeyChord,
+}
+
+/// One way to harmonize a measure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reharmonization {
+    pub chords: Vec<HarmonizedSpan>,
+    /// 0-100, higher is better
+    pub score: f32,
+    /// Percent of the melody's duration that falls on chord tones
+    pub melody_fit: f32,
+}
+
+// A melody note clipped to one span
+struct SpanNote {
+    pitch_class: u8,
+    ticks: u32,
+    required: bool,
+}
+
+/// Propose chords for a measure (1-based) that keep its melody notes as
+/// chord tones, best first
+///
+/// Every melody note must belong to its chord unless `passing_tones` lets
+/// short off-beat notes pass. Alternatives are scored on their progression
+/// (from `previous`, between the chords, into `next`) and on how much of
+/// the melody they hold as chord tones.
+pub fn reharmonize_measure(
+    song: &Song,
+    measure: u32,
+    constraints: &ReharmonizeConstraints,
+) -> Result<Vec<Reharmonization>> {
+    if !(1..=MAX_CHORDS_PER_MEASURE).contains(&constraints.chords_per_measure) {
+        return Err(MozartError::HarmonyError(format!(
+            "Chords per measure must be 1-{}",
+            MAX_CHORDS_PER_MEASURE
+        )));
+    }
+    let key = &song.settings.key;
+    let previous = constraints.previous.as_deref().map(|name| parse_chord(name, key)).transpose()?;
+    let next = constraints.next.as_deref().map(|name| parse_chord(name, key)).transpose()?;
+
+    let start = song.measure_start_tick(measure)?;
+    let end = song.measure_start_tick(measure + 1)?;
+    let span_ticks = (end - start) / constraints.chords_per_measure;
+    let ts = &song.settings.time_signature;
+    let candidates = key_chords(key, constraints.sevenths);
+
+    // Chords that hold every required note of each span
+    let mut spans = Vec::new();
+    for i in 0..constraints.chords_per_measure {
+        let span_start = start + i * span_ticks;
+        let span_end = if i + 1 == constraints.chords_per_measure { end } else { span_start + span_ticks };
+        let notes: Vec<SpanNote> = song
+            .notes
+            .iter()
+            .filter(|n| n.start_tick < span_end && n.end_tick() > span_start)
+            .map(|n| {
+                let ticks = n.end_tick().min(span_end) - n.start_tick.max(span_start);
+                let passing = ticks < ts.ticks_per_beat() && !ts.is_on_beat(n.start_tick.max(span_start));
+                SpanNote { pitch_class: n.pitch % 12, ticks, required: !(constraints.passing_tones && passing) }
+            })
+            .collect();
+        let fitting: Vec<&KeyChord> = candidates
+            .iter()
+            .filter(|chord| notes.iter().all(|n| !n.required || chord.contains(n.pitch_class)))
+            .collect();
+        if fitting.is_empty() {
+            tracing::debug!("No chord of {} holds the melody at tick {}", key, span_start);
+            return Ok(Vec::new());
+        }
+        spans.push((span_start, span_end, notes, fitting));
+    }
+
+    // Every combination of the fitting chords, scored as a whole
+    let mut choices = vec![0usize; spans.len()];
+    let mut results = Vec::new();
+    loop {
+        let chords: Vec<&KeyChord> = spans.iter().zip(&choices).map(|(span, &c)| span.3[c]).collect();
+        results.push(score_reharmonization(&spans, &chords, previous.as_ref(), next.as_ref()));
+
+        // Advance the choices like an odometer
+        let Some(position) = (0..spans.len()).rev().find(|&i| choices[i] + 1 < spans[i].3.len()) else {
+            break;
+        };
+        choices[position] += 1;
+        choices[position + 1..].iter_mut().for_each(|c| *c = 0);
+    }
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(constraints.limit);
+    tracing::info!("Found {} reharmonizations of measure {}", results.len(), measure);
+    Ok(results)
+}
+
+fn score_reharmonization(
+    spans: &[(u32, u32, Vec<SpanNote>, Vec<&KeyChord>)],
+    chords: &[&KeyChord],
+    previous: Option<&KeyChord>,
+    next: Option<&KeyChord>,
+) -> Reharmonization {
+    let mut transitions: Vec<f32> = Vec::new();
+    if let Some(previous) = previous {
+        transitions.push(rank(Some(previous), chords[0]).score);
+    }
+    transitions.extend(chords.windows(2).map(|pair| rank(Some(pair[0]), pair[1]).score));
+    if let Some(next) = next {
+        transitions.push(rank(chords.last().copied(), next).score);
+    }
+    let progression = if transitions.is_empty() {
+        // Nothing to lead from or into: prefer starting from stability
+        rank(None, chords[0]).score
+    } else {
+        transitions.iter().sum::<f32>() / transitions.len() as f32
+    };
+
+    let (held, total) = spans.iter().zip(chords).fold((0, 0), |(held, total), (span, chord)| {
+        let on_chord: u32 = span.2.iter().filter(|n| chord.contains(n.pitch_class)).map(|n| n.ticks).sum();
+        (held + on_chord, total + span.2.iter().map(|n| n.ticks).sum::<u32>())
+    });
+    let melody_fit = if total == 0 { 100.0 } else { 100.0 * held as f32 / total as f32 };
+
+    Reharmonization {
+        chords: spans
+            .iter()
+            .zip(chords)
+            .map(|(span, &chord)| HarmonizedSpan { start_tick: span.0, end_tick: span.1, chord: chord.clone() })
+            .collect(),
+        score: (1.0 - MELODY_WEIGHT) * progression + MELODY_WEIGHT * melody_fit,
+        melody_fit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use crate::scale::ScaleType;
+    use pretty_assertions::assert_eq;
+
+    fn numerals(suggestions: &[ChordSuggestion]) -> Vec<&str> {
+        suggestions.iter().map(|s| s.chord.numeral.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        let c = Scale::c_major();
+        assert_eq!(parse_chord("V7", &c).unwrap().symbol, "G7");
+        assert_eq!(parse_chord("viio", &c).unwrap().symbol, "Bdim");
+        assert_eq!(parse_chord("Am", &c).unwrap().numeral, "vi");
+
+        // Enharmonic roots match the key's spelling
+        let f = Scale::new(PitchClass::F, ScaleType::Major);
+        assert_eq!(parse_chord("A#", &f).unwrap().symbol, "Bb");
+        assert!(matches!(parse_chord("D", &c), Err(MozartError::HarmonyError(_))));
+    }
+
+    #[test]
+    fn test_diatonic_chords() {
+        let ladder = diatonic_chords(&Scale::c_major());
+        let numerals: Vec<&str> = ladder.iter().map(|rung| rung.triad.numeral.as_str()).collect();
+        assert_eq!(numerals, vec!["I", "ii", "iii", "IV", "V", "vi", "vii°"]);
+        assert_eq!(ladder[4].seventh.symbol, "G7");
+        assert_eq!(ladder[6].seventh.numeral, "viiø7");
+        assert_eq!(ladder[1].triad.pitch_classes, vec![2, 5, 9]);
+
+        // Follows the key, including its spelling
+        let d_minor = diatonic_chords(&Scale::new(PitchClass::D, ScaleType::HarmonicMinor));
+        assert_eq!(d_minor[4].triad.symbol, "A");
+        assert_eq!(d_minor[5].triad.symbol, "Bb");
+        assert_eq!(d_minor[0].seventh.symbol, "Dm(maj7)");
+        assert_eq!(d_minor[4].tone_names, vec!["A", "C#", "E", "G"]);
+        assert_eq!(d_minor[5].tone_names, vec!["Bb", "D", "F", "A"]);
+    }
+
+    #[test]
+    fn test_suggest_next_chords() {
+        let c = Scale::c_major();
+        let opening = suggest_next_chords(&[], &c).unwrap();
+        assert_eq!(opening[0].chord.numeral, "I");
+
+        // The dominant resolves home first, then deceptively to vi
+        let after_v = suggest_next_chords(&["C".to_string(), "G".to_string()], &c).unwrap();
+        assert_eq!(&numerals(&after_v)[..2], &["I", "vi"]);
+        assert!(after_v[0].reason.contains("falling fifth"));
+        assert!(!numerals(&after_v).contains(&"V"));
+
+        // ii leads to V by falling fifth, sharing one tone (D)
+        let after_ii = suggest_next_chords(&["ii".to_string()], &c).unwrap();
+        assert_eq!(after_ii[0].chord.numeral, "V");
+        assert_eq!(after_ii[0].common_tones, 1);
+    }
+
+    #[test]
+    fn test_reharmonize_measure() {
+        let mut song = Song::new();
+        // Measure 1: E G over a half note C; measure 2: B D F G (only V7 or vii° hold them all)
+        song.add_notes(parse_melody("E4q G4q C5h B4q D5q F5q G5q").unwrap());
+
+        let options = reharmonize_measure(&song, 1, &ReharmonizeConstraints::default()).unwrap();
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].chords[0].chord.symbol, "C");
+        assert_eq!(options[0].melody_fit, 100.0);
+
+        let strict = reharmonize_measure(&song, 2, &ReharmonizeConstraints::default()).unwrap();
+        assert!(strict.is_empty());
+        let sevenths = ReharmonizeConstraints { sevenths: true, next: Some("I".to_string()), ..Default::default() };
+        let options = reharmonize_measure(&song, 2, &sevenths).unwrap();
+        assert_eq!(options[0].chords[0].chord.symbol, "G7");
+
+        // Two chords per measure: E G under one, C under the other
+        let split = ReharmonizeConstraints {
+            chords_per_measure: 2,
+            previous: Some("V".to_string()),
+            ..Default::default()
+        };
+        let options = reharmonize_measure(&song, 1, &split).unwrap();
+        assert_eq!(options[0].chords.len(), 2);
+        assert_eq!(options[0].chords[1].start_tick, 960);
+        assert!(options.iter().all(|o| o.chords[0].chord.contains(4) && o.chords[0].chord.contains(7)));
+
+        let bad = ReharmonizeConstraints { chords_per_measure: 0, ..Default::default() };
+        assert!(reharmonize_measure(&song, 1, &bad).is_err());
+    }
+}
@@ -0,0 +1,348 @@
+//! Undo/redo history
+//!
+//! Stores labeled song snapshots with a depth limit, a memory budget, and
+//! coalescing of rapid repeated operations (e.g., dragging a note)
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+use crate::note::Note;
+use crate::song::Song;
+
+/// Undo history configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoConfig {
+    /// Maximum number of undo steps kept
+    pub limit: usize,
+    /// Approximate memory budget for all snapshots, in bytes
+    pub memory_limit: usize,
+    /// Consecutive operations with the same label within this window (ms)
+    /// are merged into a single undo step; 0 disables coalescing
+    pub coalesce_window_ms: u64,
+}
+
+impl Default for UndoConfig {
+    fn default() -> Self {
+        UndoConfig {
+            limit: 100,
+            memory_limit: 16 * 1024 * 1024,
+            coalesce_window_ms: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    label: String,
+    song: Song,
+    timestamp_ms: u64,
+    size: usize,
+}
+
+impl Snapshot {
+    fn new(label: &str, song: Song, timestamp_ms: u64) -> Self {
+        let size = estimate_size(&song);
+        Snapshot {
+            label: label.to_string(),
+            song,
+            timestamp_ms,
+            size,
+        }
+    }
+}
+
+/// Estimate the heap footprint of a song snapshot in bytes
+pub fn estimate_size(song: &Song) -> usize {
+    let strings = song.version.len()
+        + song.metadata.title.len()
+        + song.metadata.composer.len()
+        + song.metadata.created.len()
+        + song.metadata.modified.len();
+    let markers: usize = song.markers.iter().map(|m| m.name.len() + size_of::<u32>() * 2).sum();
+    let lyrics: usize = song.lyrics.iter().map(|l| l.text.len() + size_of::<crate::song::Lyric>()).sum();
+    let annotations: usize = song.annotations.iter().map(|a| a.text.len() + size_of::<crate::song::Annotation>()).sum();
+    let chords: usize = song.chords.iter().map(|c| c.symbol.len() + size_of::<crate::chord::ChordSymbol>()).sum();
+    let patterns: usize = song
+        .patterns
+        .iter()
+        .map(|p| p.name.len() + size_of::<crate::pattern::Pattern>() + p.notes.len() * size_of::<Note>())
+        .sum::<usize>()
+        + song.pattern_chain.iter().map(|n| n.len() + size_of::<String>()).sum::<usize>();
+
+    let tracks: usize = song.settings.tracks.iter().map(|t| t.name.len() + size_of::<crate::song::Track>()).sum();
+    let voice_meters: usize = song
+        .settings
+        .voice_meters
+        .iter()
+        .map(|m| size_of::<crate::song::VoiceMeter>() + m.time_signature.accents.len() + m.time_signature.pulse_grouping.len())
+        .sum();
+    let repeats = song.repeats.repeats.len() * size_of::<crate::repeats::Repeat>()
+        + song
+            .repeats
+            .endings
+            .iter()
+            .map(|e| size_of::<crate::repeats::Ending>() + e.passes.len() * size_of::<u32>())
+            .sum::<usize>();
+
+    size_of::<Song>()
+        + strings
+        + song.notes.len() * size_of::<Note>()
+        + song.settings.time_signature.accents.len()
+        + song.settings.tempo_map.len() * size_of::<crate::song::TempoChange>()
+        + tracks
+        + voice_meters
+        + markers
+        + lyrics
+        + annotations
+        + song.strums.len() * size_of::<crate::strum::Strum>()
+        + chords
+        + patterns
+        + repeats
+}
+
+/// Undo/redo stack of song snapshots
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    config: UndoConfig,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: UndoConfig) -> Self {
+        History {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> UndoConfig {
+        self.config
+    }
+
+    /// Set the maximum number of undo steps (at least 1)
+    pub fn set_limit(&mut self, limit: usize) {
+        self.config.limit = limit.max(1);
+        self.trim();
+    }
+
+    /// Set the approximate memory budget in bytes
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.config.memory_limit = bytes;
+        self.trim();
+    }
+
+    /// Set the coalescing window in milliseconds (0 disables coalescing)
+    pub fn set_coalesce_window(&mut self, window_ms: u64) {
+        self.config.coalesce_window_ms = window_ms;
+    }
+
+    /// Record the song state from before an operation
+    ///
+    /// If the previous step has the same label and was recorded within the
+    /// coalescing window, the two operations merge: the older snapshot is kept
+    /// so a single undo reverts both.
+    pub fn record(&mut self, label: &str, before: Song, now_ms: u64) {
+        self.redo.clear();
+
+        if let Some(last) = self.undo.back_mut() {
+            let window = self.config.coalesce_window_ms;
+            if window > 0
+                && last.label == label
+                && now_ms.saturating_sub(last.timestamp_ms) <= window
+            {
+                tracing::trace!("Coalescing '{}' into previous undo step", label);
+                last.timestamp_ms = now_ms;
+                return;
+            }
+        }
+
+        tracing::trace!("Recording undo step '{}'", label);
+        self.undo.push_back(Snapshot::new(label, before, now_ms));
+        self.trim();
+    }
+
+    /// Undo the last operation, returning the song to restore
+    pub fn undo(&mut self, current: &Song) -> Option<Song> {
+        let snapshot = self.undo.pop_back()?;
+        tracing::debug!("Undo: {}", snapshot.label);
+        self.redo.push(Snapshot::new(&snapshot.label, current.clone(), snapshot.timestamp_ms));
+        Some(snapshot.song)
+    }
+
+    /// Redo the last undone operation, returning the song to restore
+    pub fn redo(&mut self, current: &Song) -> Option<Song> {
+        let snapshot = self.redo.pop()?;
+        tracing::debug!("Redo: {}", snapshot.label);
+        // Redone steps are never coalesced with later edits
+        self.undo.push_back(Snapshot::new(&snapshot.label, current.clone(), 0));
+        self.trim();
+        Some(snapshot.song)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Label of the operation that undo would revert
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo.back().map(|s| s.label.as_str())
+    }
+
+    /// Label of the operation that redo would reapply
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo.last().map(|s| s.label.as_str())
+    }
+
+    /// Number of undo steps available
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Approximate memory used by all snapshots, in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.undo.iter().chain(self.redo.iter()).map(|s| s.size).sum()
+    }
+
+    /// Remove all history
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Drop the oldest undo steps until both the depth and memory limits hold
+    fn trim(&mut self) {
+        while self.undo.len() > self.config.limit {
+            self.undo.pop_front();
+        }
+        // Always keep the most recent step, even if it alone exceeds the budget
+        while self.undo.len() > 1 && self.memory_usage() > self.config.memory_limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn song_with_notes(count: u32) -> Song {
+        let mut song = Song::new();
+        for i in 0..count {
+            song.add_note(Note::new(60, i * 480, 480));
+        }
+        song
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::new();
+        let mut song = Song::new();
+
+        history.record("Add note", song.clone(), 0);
+        song.add_note(Note::new(60, 0, 480));
+
+        assert!(history.can_undo());
+        assert_eq!(history.undo_label(), Some("Add note"));
+
+        song = history.undo(&song).unwrap();
+        assert!(song.notes.is_empty());
+        assert!(history.can_redo());
+
+        song = history.redo(&song).unwrap();
+        assert_eq!(song.notes.len(), 1);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_record_clears_redo() {
+        let mut history = History::new();
+        let song = Song::new();
+
+        history.record("Add note", song.clone(), 0);
+        history.undo(&song_with_notes(1));
+        assert!(history.can_redo());
+
+        history.record("Set tempo", song, 10_000);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_undo_limit() {
+        let mut history = History::new();
+        for i in 0..10 {
+            history.record(&format!("Edit {}", i), song_with_notes(i), i as u64 * 1000);
+        }
+        assert_eq!(history.undo_depth(), 10);
+
+        history.set_limit(3);
+        assert_eq!(history.undo_depth(), 3);
+        assert_eq!(history.undo_label(), Some("Edit 9"));
+    }
+
+    #[test]
+    fn test_estimate_size() {
+        let mut song = song_with_notes(4);
+        let notes_only = estimate_size(&song);
+        song.add_annotation(0, Some(1920), "crescendo into the chorus").unwrap();
+        assert!(estimate_size(&song) >= notes_only + "crescendo into the chorus".len());
+
+        let annotated = estimate_size(&song);
+        song.settings.tracks.push(crate::song::Track::new(1, "Backing vocals"));
+        song.set_voice_time_signature(1, Some(crate::time::TimeSignature::parse("7/8").unwrap()));
+        song.repeats.endings.push(crate::repeats::Ending::parse("2 1,2").unwrap());
+        assert!(estimate_size(&song) > annotated + "Backing vocals".len() + 7 + size_of::<crate::repeats::Ending>());
+    }
+
+    #[test]
+    fn test_memory_trimming() {
+        let mut history = History::new();
+        let big = song_with_notes(100);
+        let step_size = estimate_size(&big);
+
+        history.set_memory_limit(step_size * 2 + step_size / 2);
+        for i in 0..5 {
+            history.record(&format!("Edit {}", i), big.clone(), i * 1000);
+        }
+        assert_eq!(history.undo_depth(), 2);
+        assert!(history.memory_usage() <= step_size * 2 + step_size / 2);
+
+        // The latest step survives even when it alone exceeds the budget
+        history.set_memory_limit(1);
+        assert_eq!(history.undo_depth(), 1);
+    }
+
+    #[test]
+    fn test_coalescing() {
+        let mut history = History::new();
+        history.set_coalesce_window(500);
+
+        let original = song_with_notes(1);
+        history.record("Set velocity", original.clone(), 0);
+        history.record("Set velocity", song_with_notes(2), 300);
+        history.record("Set velocity", song_with_notes(3), 700);
+        assert_eq!(history.undo_depth(), 1);
+
+        // A single undo returns to the state before the whole drag
+        let restored = history.undo(&song_with_notes(4)).unwrap();
+        assert_eq!(restored.notes.len(), original.notes.len());
+
+        // Outside the window or with a different label, steps stay separate
+        history.record("Set velocity", song_with_notes(1), 0);
+        history.record("Set velocity", song_with_notes(1), 2000);
+        history.record("Set tempo", song_with_notes(1), 2100);
+        assert_eq!(history.undo_depth(), 3);
+
+        history.set_coalesce_window(0);
+        history.record("Set tempo", song_with_notes(1), 2200);
+        assert_eq!(history.undo_depth(), 4);
+    }
+}
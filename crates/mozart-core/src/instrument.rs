@@ -0,0 +1,97 @@
+//! Playback instruments
+//!
+//! The sound a song plays with, saved in its settings so it reopens the way
+//! it was left. Each instrument is a timbre the web audio engine can
+//! synthesize (a waveform and envelope) and a General MIDI program, written
+//! to exported MIDI files so other players pick a similar sound.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+
+/// Sound a song is played with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Instrument {
+    /// A different waveform per voice, so harmony voices stand apart
+    #[default]
+    Synth,
+    Piano,
+    Organ,
+    Strings,
+    Flute,
+}
+
+impl Instrument {
+    pub fn all() -> &'static [Instrument] {
+        &[Instrument::Synth, Instrument::Piano, Instrument::Organ, Instrument::Strings, Instrument::Flute]
+    }
+
+    /// Parse from string
+    pub fn parse(s: &str) -> Result<Self> {
+        Instrument::all()
+            .iter()
+            .copied()
+            .find(|i| i.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| MozartError::ParseError(format!("Unknown instrument: {}", s)))
+    }
+
+    /// Get short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instrument::Synth => "synth",
+            Instrument::Piano => "piano",
+            Instrument::Organ => "organ",
+            Instrument::Strings => "strings",
+            Instrument::Flute => "flute",
+        }
+    }
+
+    /// General MIDI program written to exports; none for the synth, which has no GM equivalent
+    pub fn gm_program(&self) -> Option<u8> {
+        match self {
+            Instrument::Synth => None,
+            Instrument::Piano => Some(0),    // Acoustic Grand Piano
+            Instrument::Organ => Some(19),   // Church Organ
+            Instrument::Strings => Some(48), // String Ensemble 1
+            Instrument::Flute => Some(73),   // Flute
+        }
+    }
+
+    /// The instrument closest to a General MIDI program, by GM family
+    pub fn from_gm_program(program: u8) -> Instrument {
+        match program {
+            0..=7 => Instrument::Piano,
+            16..=23 => Instrument::Organ,
+            40..=51 => Instrument::Strings,
+            72..=79 => Instrument::Flute,
+            _ => Instrument::Synth,
+        }
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_names_and_programs() {
+        for &instrument in Instrument::all() {
+            assert_eq!(Instrument::parse(instrument.name()).unwrap(), instrument);
+            assert_eq!(serde_json::to_value(instrument).unwrap(), instrument.name());
+            if let Some(program) = instrument.gm_program() {
+                assert_eq!(Instrument::from_gm_program(program), instrument);
+            }
+        }
+        assert_eq!(Instrument::parse(" Piano ").unwrap(), Instrument::Piano);
+        assert!(Instrument::parse("theremin").is_err());
+        assert_eq!(Instrument::from_gm_program(56), Instrument::Synth); // Trumpet
+    }
+}
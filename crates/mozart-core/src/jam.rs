@@ -0,0 +1,306 @@
+//! LAN jam sync: follow another instance's transport
+//!
+//! One instance is the transport master and broadcasts its tempo, play/stop
+//! state, and position to the local network a few times a second; the
+//! others follow it, so people in the same room practice against one click.
+//!
+//! The protocol is one JSON [`JamMessage`] per UDP datagram on
+//! [`JAM_PORT`]. Masters announce themselves in every message, so finding a
+//! session is just listening (discovery by broadcast rather than mDNS, which
+//! would need a resolver). Position is extrapolated from when a message
+//! arrived, which is accurate to within the LAN's few milliseconds of latency.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use crate::error::{MozartError, Result};
+use crate::TICKS_PER_QUARTER;
+
+/// UDP port sessions broadcast on
+pub const JAM_PORT: u16 = 47_470;
+
+/// A master not heard from for this long is considered gone
+pub const JAM_TIMEOUT_MS: u64 = 3_000;
+
+/// How often a master should send its state while nothing changes
+pub const JAM_INTERVAL_MS: u64 = 250;
+
+/// A master's transport at the moment it was sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransportState {
+    pub playing: bool,
+    pub tempo: u16,
+    /// Playhead position in ticks
+    pub tick: u32,
+    /// Title of the song being played, so followers can check they match
+    #[serde(default)]
+    pub song_title: String,
+}
+
+impl TransportState {
+    /// Where the playhead is `elapsed_ms` after this state, at its tempo
+    pub fn position_after(&self, elapsed_ms: u64) -> u32 {
+        if !self.playing {
+            return self.tick;
+        }
+        let ticks = elapsed_ms * self.tempo as u64 * TICKS_PER_QUARTER as u64 / 60_000;
+        self.tick.saturating_add(ticks.min(u32::MAX as u64) as u32)
+    }
+}
+
+/// One datagram
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JamMessage {
+    /// The master's transport; `seq` increases with every message
+    Transport { session: String, seq: u64, state: TransportState },
+    /// The master is leaving the session
+    Bye { session: String },
+}
+
+impl JamMessage {
+    pub fn session(&self) -> &str {
+        match self {
+            JamMessage::Transport { session, .. } | JamMessage::Bye { session } => session,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Heard {
+    seq: u64,
+    state: TransportState,
+    received_ms: u64,
+}
+
+/// Tracks the masters heard on the network and follows one of them
+///
+/// Times are milliseconds on any clock the caller keeps steady.
+#[derive(Debug, Clone, Default)]
+pub struct JamFollower {
+    sessions: BTreeMap<String, Heard>,
+    following: Option<String>,
+}
+
+impl JamFollower {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in a message; returns whether it updated the followed session
+    ///
+    /// Messages older than the last one heard from a session are dropped,
+    /// unless the session has been quiet for [`JAM_TIMEOUT_MS`]: a master that
+    /// restarts under the same name counts again from the start.
+    pub fn receive(&mut self, message: JamMessage, now_ms: u64) -> bool {
+        let followed = self.following.as_deref() == Some(message.session());
+        match message {
+            JamMessage::Transport { session, seq, state } => {
+                let stale = |heard: &Heard| now_ms.saturating_sub(heard.received_ms) > JAM_TIMEOUT_MS;
+                if self.sessions.get(&session).is_some_and(|heard| heard.seq >= seq && !stale(heard)) {
+                    return false;
+                }
+                self.sessions.insert(session, Heard { seq, state, received_ms: now_ms });
+            }
+            JamMessage::Bye { session } => {
+                tracing::info!("Jam session '{}' ended", session);
+                self.sessions.remove(&session);
+            }
+        }
+        followed
+    }
+
+    /// Sessions heard within [`JAM_TIMEOUT_MS`], by name
+    pub fn sessions(&self, now_ms: u64) -> Vec<&str> {
+        self.sessions
+            .iter()
+            .filter(|(_, heard)| now_ms.saturating_sub(heard.received_ms) <= JAM_TIMEOUT_MS)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Follow a session by name (None to stop following)
+    pub fn follow(&mut self, session: Option<&str>) {
+        tracing::info!("Following jam session {:?}", session);
+        self.following = session.map(str::to_string);
+    }
+
+    pub fn following(&self) -> Option<&str> {
+        self.following.as_deref()
+    }
+
+    /// The followed master's transport now, with the position extrapolated
+    ///
+    /// None when not following, or when the master has gone quiet.
+    pub fn transport(&self, now_ms: u64) -> Option<TransportState> {
+        let heard = self.sessions.get(self.following.as_deref()?)?;
+        let elapsed = now_ms.saturating_sub(heard.received_ms);
+        if elapsed > JAM_TIMEOUT_MS {
+            return None;
+        }
+        Some(TransportState { tick: heard.state.position_after(elapsed), ..heard.state.clone() })
+    }
+}
+
+/// A UDP socket for a jam session, as master or follower
+#[derive(Debug)]
+pub struct JamSocket {
+    socket: UdpSocket,
+    target: SocketAddr,
+    seq: u64,
+}
+
+impl JamSocket {
+    /// Join the LAN session: broadcast to, and listen on, [`JAM_PORT`]
+    ///
+    /// The port is bound without `SO_REUSEADDR` (std can't set it), so only
+    /// one instance per machine can join; a second gets an error saying so.
+    pub fn lan() -> Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, JAM_PORT)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AddrInUse => MozartError::JamError(format!(
+                "Port {} is in use; another instance on this machine has already joined the jam session",
+                JAM_PORT
+            )),
+            _ => jam_error(e),
+        })?;
+        socket.set_broadcast(true).map_err(jam_error)?;
+        Self::with_socket(socket, SocketAddr::from((Ipv4Addr::BROADCAST, JAM_PORT)))
+    }
+
+    /// Bind `bind` and send to `target` (e.g., loopback peers in tests)
+    pub fn bind(bind: SocketAddr, target: SocketAddr) -> Result<Self> {
+        Self::with_socket(UdpSocket::bind(bind).map_err(jam_error)?, target)
+    }
+
+    fn with_socket(socket: UdpSocket, target: SocketAddr) -> Result<Self> {
+        socket.set_nonblocking(true).map_err(jam_error)?;
+        Ok(JamSocket { socket, target, seq: 0 })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().map_err(jam_error)
+    }
+
+    /// Send the master's transport (on every change, and every [`JAM_INTERVAL_MS`])
+    pub fn send_transport(&mut self, session: &str, state: &TransportState) -> Result<()> {
+        self.seq += 1;
+        let message = JamMessage::Transport { session: session.to_string(), seq: self.seq, state: state.clone() };
+        self.send(&message)
+    }
+
+    /// Tell followers the master is leaving
+    pub fn send_bye(&self, session: &str) -> Result<()> {
+        self.send(&JamMessage::Bye { session: session.to_string() })
+    }
+
+    fn send(&self, message: &JamMessage) -> Result<()> {
+        self.socket.send_to(&message.to_bytes(), self.target).map_err(jam_error)?;
+        Ok(())
+    }
+
+    /// Hand every waiting message to the follower; returns how many arrived
+    ///
+    /// Datagrams that aren't jam messages are skipped.
+    pub fn poll(&self, follower: &mut JamFollower, now_ms: u64) -> Result<usize> {
+        let mut buf = [0u8; 2048];
+        let mut count = 0;
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => match JamMessage::from_bytes(&buf[..len]) {
+                    Ok(message) => {
+                        follower.receive(message, now_ms);
+                        count += 1;
+                    }
+                    Err(e) => tracing::debug!("Ignoring datagram from {}: {}", from, e),
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(count),
+                Err(e) => return Err(jam_error(e)),
+            }
+        }
+    }
+}
+
+fn jam_error(e: std::io::Error) -> MozartError {
+    MozartError::JamError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn state(playing: bool, tick: u32) -> TransportState {
+        TransportState { playing, tempo: 120, tick, song_title: "Etude".to_string() }
+    }
+
+    fn transport(seq: u64, state: TransportState) -> JamMessage {
+        JamMessage::Transport { session: "Studio".to_string(), seq, state }
+    }
+
+    #[test]
+    fn test_follower() {
+        let mut follower = JamFollower::new();
+        assert!(!follower.receive(transport(2, state(true, 960)), 1_000));
+        assert_eq!(follower.sessions(1_000), vec!["Studio"]);
+        assert!(follower.transport(1_000).is_none());
+
+        // At 120 BPM, half a second is one beat
+        follower.follow(Some("Studio"));
+        assert_eq!(follower.transport(1_500).unwrap().tick, 960 + TICKS_PER_QUARTER);
+        // A late, older message is dropped
+        assert!(!follower.receive(transport(1, state(false, 0)), 1_600));
+        assert!(follower.receive(transport(3, state(false, 2000)), 1_700));
+        // Stopped: the position holds until the master goes quiet
+        assert_eq!(follower.transport(4_000).unwrap().tick, 2000);
+        assert!(follower.transport(1_700 + JAM_TIMEOUT_MS + 1).is_none());
+
+        follower.receive(JamMessage::Bye { session: "Studio".to_string() }, 1_800);
+        assert!(follower.sessions(1_800).is_empty());
+    }
+
+    #[test]
+    fn test_restarted_master() {
+        let mut follower = JamFollower::new();
+        follower.follow(Some("Studio"));
+        assert!(follower.receive(transport(40, state(true, 960)), 1_000));
+
+        // The master crashes and comes back counting from 1; while its old
+        // state is fresh the restart looks like a late message
+        assert!(!follower.receive(transport(1, state(false, 0)), 1_000 + JAM_TIMEOUT_MS));
+        assert!(follower.receive(transport(2, state(false, 0)), 1_000 + JAM_TIMEOUT_MS + 1));
+        assert_eq!(follower.transport(1_000 + JAM_TIMEOUT_MS + 1).unwrap().tick, 0);
+        // and from then on it is followed as usual
+        assert!(follower.receive(transport(3, state(true, 480)), 4_500));
+        assert!(!follower.receive(transport(2, state(false, 0)), 4_600));
+    }
+
+    #[test]
+    fn test_loopback_session() {
+        let loopback = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let listener = JamSocket::bind(loopback, loopback).unwrap();
+        let mut master = JamSocket::bind(loopback, listener.local_addr().unwrap()).unwrap();
+        master.send_transport("Studio", &state(true, 0)).unwrap();
+        master.send_transport("Studio", &state(true, 480)).unwrap();
+
+        let mut follower = JamFollower::new();
+        follower.follow(Some("Studio"));
+        let mut received = 0;
+        for _ in 0..100 {
+            received += listener.poll(&mut follower, 0).unwrap();
+            if received == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(received, 2);
+        assert_eq!(follower.transport(0).unwrap(), state(true, 480));
+    }
+}
@@ -0,0 +1,366 @@
+//! Background job system for long-running operations
+//!
+//! Heavy work (large imports, renders, analysis) is queued onto a fixed pool
+//! of worker threads instead of blocking the caller. Each job gets an id;
+//! progress and completion are reported through a listener, and jobs can be
+//! cancelled cooperatively.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use crate::error::{MozartError, Result};
+
+/// Identifier returned when a job is queued
+pub type JobId = u64;
+
+/// Notification about a job, delivered to the manager's listener
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JobEvent {
+    Progress { id: JobId, percent: f32, stage: String },
+    Completed { id: JobId, result: serde_json::Value },
+    Failed { id: JobId, error: String },
+    Cancelled { id: JobId },
+}
+
+impl JobEvent {
+    pub fn id(&self) -> JobId {
+        match self {
+            JobEvent::Progress { id, .. }
+            | JobEvent::Completed { id, .. }
+            | JobEvent::Failed { id, .. }
+            | JobEvent::Cancelled { id } => *id,
+        }
+    }
+}
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has finished (successfully or not)
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+type Listener = dyn Fn(&JobEvent) + Send + Sync;
+type JobFn = Box<dyn FnOnce(&JobContext) -> Result<serde_json::Value> + Send>;
+
+/// Handle passed to a running job for reporting progress and checking cancellation
+pub struct JobContext {
+    id: JobId,
+    cancelled: Arc<AtomicBool>,
+    listener: Arc<Listener>,
+}
+
+impl JobContext {
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
+    /// Report progress (0-100) with a short description of the current stage
+    pub fn progress(&self, percent: f32, stage: &str) {
+        (self.listener)(&JobEvent::Progress {
+            id: self.id,
+            percent: percent.clamp(0.0, 100.0),
+            stage: stage.to_string(),
+        });
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Return `Err(Cancelled)` if cancellation was requested, for use with `?`
+    pub fn checkpoint(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(MozartError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+struct QueuedJob {
+    id: JobId,
+    work: JobFn,
+}
+
+struct JobState {
+    status: JobStatus,
+    cancelled: Arc<AtomicBool>,
+}
+
+type JobTable = Arc<Mutex<HashMap<JobId, JobState>>>;
+
+/// Fixed-size worker pool running queued jobs in order
+pub struct JobManager {
+    sender: Option<Sender<QueuedJob>>,
+    workers: Vec<JoinHandle<()>>,
+    jobs: JobTable,
+    next_id: AtomicU64,
+    listener: Arc<Listener>,
+}
+
+impl JobManager {
+    /// Create a pool with `workers` threads; `listener` receives all job events
+    pub fn new(workers: usize, listener: impl Fn(&JobEvent) + Send + Sync + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel::<QueuedJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let jobs: JobTable = Arc::new(Mutex::new(HashMap::new()));
+        let listener: Arc<Listener> = Arc::new(listener);
+
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let receiver = Arc::clone(&receiver);
+                let jobs = Arc::clone(&jobs);
+                let listener = Arc::clone(&listener);
+                thread::Builder::new()
+                    .name(format!("mozart-job-{}", i))
+                    .spawn(move || worker_loop(receiver, jobs, listener))
+                    .expect("failed to spawn job worker")
+            })
+            .collect();
+
+        tracing::debug!("Started job pool");
+        JobManager {
+            sender: Some(sender),
+            workers,
+            jobs,
+            next_id: AtomicU64::new(1),
+            listener,
+        }
+    }
+
+    /// Queue a job and return its id immediately
+    pub fn spawn(
+        &self,
+        work: impl FnOnce(&JobContext) -> Result<serde_json::Value> + Send + 'static,
+    ) -> JobId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        lock(&self.jobs).insert(
+            id,
+            JobState {
+                status: JobStatus::Queued,
+                cancelled: Arc::new(AtomicBool::new(false)),
+            },
+        );
+
+        let job = QueuedJob {
+            id,
+            work: Box::new(work),
+        };
+        if let Some(sender) = &self.sender {
+            if sender.send(job).is_err() {
+                self.finish(id, JobStatus::Failed);
+                (self.listener)(&JobEvent::Failed {
+                    id,
+                    error: "Job pool has shut down".to_string(),
+                });
+            }
+        }
+        tracing::debug!("Queued job {}", id);
+        id
+    }
+
+    /// Request cancellation; returns false if the job is unknown or already finished
+    ///
+    /// Queued jobs are skipped; running jobs stop at their next checkpoint.
+    pub fn cancel(&self, id: JobId) -> bool {
+        match lock(&self.jobs).get(&id) {
+            Some(state) if !state.status.is_finished() => {
+                state.cancelled.store(true, Ordering::Relaxed);
+                tracing::debug!("Cancellation requested for job {}", id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn status(&self, id: JobId) -> Option<JobStatus> {
+        lock(&self.jobs).get(&id).map(|s| s.status)
+    }
+
+    /// Forget finished jobs so their ids no longer report a status
+    pub fn prune_finished(&self) {
+        lock(&self.jobs).retain(|_, s| !s.status.is_finished());
+    }
+
+    fn finish(&self, id: JobId, status: JobStatus) {
+        if let Some(state) = lock(&self.jobs).get_mut(&id) {
+            state.status = status;
+        }
+    }
+}
+
+impl Drop for JobManager {
+    /// Waits for queued jobs to finish before the workers exit
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_loop(receiver: Arc<Mutex<Receiver<QueuedJob>>>, jobs: JobTable, listener: Arc<Listener>) {
+    loop {
+        let job = match lock(&receiver).recv() {
+            Ok(job) => job,
+            Err(_) => break, // Manager dropped
+        };
+
+        let cancelled = match lock(&jobs).get_mut(&job.id) {
+            Some(state) => {
+                state.status = JobStatus::Running;
+                Arc::clone(&state.cancelled)
+            }
+            None => continue,
+        };
+
+        let ctx = JobContext {
+            id: job.id,
+            cancelled,
+            listener: Arc::clone(&listener),
+        };
+
+        let outcome = if ctx.is_cancelled() {
+            Err(MozartError::Cancelled)
+        } else {
+            panic::catch_unwind(AssertUnwindSafe(|| (job.work)(&ctx)))
+                .unwrap_or_else(|_| Err(MozartError::JobError("Job panicked".to_string())))
+        };
+
+        let (status, event) = match outcome {
+            Ok(result) => (JobStatus::Completed, JobEvent::Completed { id: job.id, result }),
+            Err(MozartError::Cancelled) => (JobStatus::Cancelled, JobEvent::Cancelled { id: job.id }),
+            Err(e) => {
+                tracing::warn!("Job {} failed: {}", job.id, e);
+                (JobStatus::Failed, JobEvent::Failed { id: job.id, error: e.to_string() })
+            }
+        };
+
+        if let Some(state) = lock(&jobs).get_mut(&job.id) {
+            state.status = status;
+        }
+        listener(&event);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    fn collecting_manager(workers: usize) -> (JobManager, Receiver<JobEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let manager = JobManager::new(workers, move |event| {
+            let _ = lock(&tx).send(event.clone());
+        });
+        (manager, rx)
+    }
+
+    fn wait_for_end(rx: &Receiver<JobEvent>, id: JobId) -> Vec<JobEvent> {
+        let mut events = Vec::new();
+        loop {
+            let event = rx.recv_timeout(Duration::from_secs(5)).expect("job timed out");
+            let done = event.id() == id && !matches!(event, JobEvent::Progress { .. });
+            events.push(event);
+            if done {
+                return events;
+            }
+        }
+    }
+
+    #[test]
+    fn test_job_completes_with_progress() {
+        let (manager, rx) = collecting_manager(2);
+        let id = manager.spawn(|ctx| {
+            ctx.progress(50.0, "Halfway");
+            Ok(serde_json::json!({ "notes": 3 }))
+        });
+
+        let events = wait_for_end(&rx, id);
+        assert_eq!(
+            events,
+            vec![
+                JobEvent::Progress { id, percent: 50.0, stage: "Halfway".to_string() },
+                JobEvent::Completed { id, result: serde_json::json!({ "notes": 3 }) },
+            ]
+        );
+        assert_eq!(manager.status(id), Some(JobStatus::Completed));
+    }
+
+    #[test]
+    fn test_job_failure_and_panic() {
+        let (manager, rx) = collecting_manager(1);
+        let failing = manager.spawn(|_| Err(MozartError::MidiError("bad file".to_string())));
+        let events = wait_for_end(&rx, failing);
+        assert!(matches!(events.last(), Some(JobEvent::Failed { .. })));
+
+        let panicking = manager.spawn(|_| panic!("boom"));
+        wait_for_end(&rx, panicking);
+        assert_eq!(manager.status(panicking), Some(JobStatus::Failed));
+    }
+
+    #[test]
+    fn test_cancel_running_job() {
+        let (manager, rx) = collecting_manager(1);
+        let (started_tx, started_rx) = mpsc::channel();
+        let id = manager.spawn(move |ctx| {
+            started_tx.send(()).unwrap();
+            loop {
+                ctx.checkpoint()?;
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        started_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(manager.cancel(id));
+
+        let events = wait_for_end(&rx, id);
+        assert_eq!(events.last(), Some(&JobEvent::Cancelled { id }));
+        assert!(!manager.cancel(id));
+    }
+
+    #[test]
+    fn test_cancel_queued_job() {
+        let (manager, rx) = collecting_manager(1);
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let blocker = manager.spawn(move |_| {
+            release_rx.recv().ok();
+            Ok(serde_json::Value::Null)
+        });
+        let queued = manager.spawn(|_| Ok(serde_json::Value::Null));
+
+        assert_eq!(manager.status(queued), Some(JobStatus::Queued));
+        assert!(manager.cancel(queued));
+        release_tx.send(()).unwrap();
+
+        wait_for_end(&rx, blocker);
+        let events = wait_for_end(&rx, queued);
+        assert_eq!(events.last(), Some(&JobEvent::Cancelled { id: queued }));
+
+        manager.prune_finished();
+        assert_eq!(manager.status(queued), None);
+    }
+}
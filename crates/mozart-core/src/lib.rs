@@ -2,20 +2,144 @@
 //!
 //! This crate provides the core music theory primitives for the Mozart app:
 //! - Note representation (pitch, duration, velocity)
+//! - Tracks: named voices with their own instrument, volume, mute, and solo
+//! - Completions for text notation while a melody is typed, and a
+//!   machine-readable grammar of the notation
 //! - Scale definitions (major, minor, modes)
+//! - Theory reference tables (intervals, chords, circle of fifths, cadences)
+//! - Scale and chord auditions at the song's tempo
 //! - Transposition (chromatic and diatonic)
-//! - Time signatures with customizable accents
+//! - Time signatures with customizable accents (and a preset library, plus
+//!   odd-meter groove presets)
 //! - File format serialization
-//! - MIDI export
+//! - MIDI export and import (with a golden-file conformance corpus)
+//! - Merging songs onto their own voices and MIDI channels, and splitting
+//!   them into per-phrase practice files
+//! - Lyrics, timed lyric (LRC) export, and ChordPro chord-and-lyrics sheets
+//! - Annotations on the timeline (MIDI text events on export)
+//! - Undo/redo history
+//! - Fine-grained change events for syncing frontends
+//! - Command registry with fuzzy search (command palette)
+//! - Onboarding tutorial checked against the song state
+//! - Ear-training worksheets exported to MIDI and PDF
+//! - Call-and-response practice scored for pitch and rhythm
+//! - Binary note encoding for bulk transfer (`packed-notes` feature)
+//! - Structure-of-arrays note storage experiment (`soa-notes` feature)
+//! - Operation log and CRDT notes for collaborative editing (`collab` feature)
+//! - Hot-path performance benchmarks (`bench` feature)
+//! - Chord strum/roll timing
+//! - Chord entry (keys pressed together become one chord)
+//! - Note probability and alternate take lanes (experimental)
+//! - Color labels for notes and sections (shown in the piano roll only)
+//! - Repeat structures (repeats, 1st/2nd endings, D.C./D.S. jumps) unfolded for playback
+//! - Patterns (named clips) chained into an arrangement ("A A B A") for playback and export
+//! - Playback instruments (saved per song, General MIDI programs on export)
+//! - Offline audio rendering and pitch-preserving time-stretch (practice tracks)
+//! - Groove templates (timing and velocity feel per beat subdivision)
+//! - Chord inversions and open/close voicings
+//! - Diatonic chord ladder of the key (triads and sevenths, auditionable)
+//! - Chord suggestions and reharmonization (function and voice leading)
+//! - Modulation planning (pivot chord, common tone, direct)
+//! - Chord symbols ("Cmaj7", "F#m7b5", "G/B") and a chord track comped in playback
+//! - Chord chart import ("| C . . . | Am . F . |")
+//! - First-species counterpoint against a cantus firmus
+//! - Melodies that outline a chord chart (chord tones on accented beats)
+//! - Melody continuation from a Markov model of a song library or bundled tunes
+//! - Melodic similarity between songs (transposition-invariant, with the
+//!   best-matching region), and duplicate search in a song library
+//! - Audible comparison of two versions (together, or alternating the
+//!   measures that changed)
+//! - Phrase variations to audition before applying
+//! - Register heatmap data (pitch usage over time) and instrument range checks
+//! - Song validation and repair
+//! - Confirmation before destructive commands
+//! - Command macros (record, replay, shortcuts)
+//! - Headless pipelines (JSON batch operations for scripts and CI)
+//! - LAN jam sync (follow another instance's transport; native only)
+//! - Instrument sample library registry, scanning, and coverage (native only)
+//! - Watch-folder import of MIDI exports into a song library (native only)
+//! - Local diagnostic logs (ring buffer + rotating file)
+//! - Audio engine watchdog (stalls, underruns, lost devices)
+//! - Crash detection and safe mode at startup
+//! - Application settings export and import
+//! - Background jobs with progress and cancellation (including file export)
+//! - Piano roll view geometry (playhead follow, visible pitch range)
+//! - TypeScript declarations for the WASM JSON payloads
 
 pub mod note;
+pub mod completion;
+pub mod grammar;
 pub mod pitch;
 pub mod scale;
+pub mod theory;
 pub mod time;
 pub mod transpose;
 pub mod song;
 pub mod midi;
+pub mod merge;
 pub mod error;
+pub mod view;
+pub mod history;
+pub mod changes;
+pub mod commands;
+pub mod tutorial;
+pub mod worksheet;
+pub mod practice;
+pub mod strum;
+pub mod chord_entry;
+pub mod chance;
+pub mod repeats;
+pub mod pattern;
+pub mod color;
+pub mod instrument;
+pub mod render;
+pub mod groove;
+pub mod voicing;
+pub mod chord;
+pub mod harmony;
+pub mod modulation;
+pub mod counterpoint;
+pub mod melody;
+pub mod continuation;
+pub mod similarity;
+pub mod comparison;
+pub mod variation;
+pub mod chart;
+pub mod lrc;
+pub mod chordpro;
+pub mod audition;
+pub mod register;
+pub mod presets;
+pub mod validate;
+pub mod confirm;
+pub mod macros;
+pub mod pipeline;
+pub mod watchdog;
+pub mod startup;
+pub mod settings;
+pub mod typescript;
+#[cfg(feature = "packed-notes")]
+pub mod packed;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "soa-notes")]
+pub mod columns;
+#[cfg(feature = "collab")]
+pub mod collab;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod logs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod jobs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod export;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod corpus;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod jam;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod samples;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
@@ -23,24 +147,72 @@ pub mod wasm;
 pub use note::{Note, NoteDuration, NoteValue};
 pub use pitch::{PitchClass, Pitch};
 pub use scale::{Scale, ScaleType};
-pub use time::{TimeSignature, AccentLevel, AccentPattern};
+pub use theory::{get_theory_reference, TheoryReference, TheoryTopic};
+pub use grammar::{get_notation_grammar, NotationGrammar};
+pub use time::{TimeSignature, AccentLevel, AccentPattern, Click, MusicalPosition, PlaybackRate, Polyrhythm};
 pub use transpose::{TransposeMode, transpose_notes};
-pub use song::{Song, SongMetadata, SongSettings};
+pub use song::{Annotation, IndexedNote, Lyric, Marker, Song, SongDefaults, SongInfo, SongMetadata, SongSettings, TempoChange, Track, VoiceMeter};
+pub use view::{FollowMode, PitchRangeMode, PitchView, TimelineView, ViewState};
+pub use history::{History, UndoConfig};
+pub use changes::{diff_songs, SongChange};
+pub use commands::{search_commands, CommandInfo, CommandMatch};
+pub use tutorial::{Tutorial, TutorialState, TutorialStep};
+pub use strum::{Strum, StrumDirection};
+pub use chord_entry::{CapturedKey, ChordCapture};
+pub use chord::{Chord, ChordQuality, ChordSymbol};
+pub use chance::Take;
+pub use comparison::{compare_versions, Comparison, ComparisonMode};
+pub use repeats::{Ending, Jump, Repeat, RepeatSign, RepeatStructure};
+pub use pattern::Pattern;
+pub use color::ColorLabel;
+pub use instrument::Instrument;
+pub use render::{render_song, RenderedAudio};
+pub use presets::{load_groove_preset, AccentPreset, GroovePreset, PresetLibrary};
+pub use validate::{validate_song, repair_song, cleanup_song, normalize_velocities, compress_velocities, CleanupReport, IssueKind, NoteMerge, RepairOptions, RepairReport, ValidationIssue};
+pub use confirm::{confirm_or_run, LossSummary, Outcome};
+pub use macros::{Macro, MacroLibrary, MacroRecorder, MacroStep};
+pub use pipeline::{run_pipeline, Operation, Pipeline};
 pub use error::MozartError;
 
 /// Ticks per quarter note (standard MIDI resolution)
 pub const TICKS_PER_QUARTER: u32 = 480;
 
 /// Initialize logging for the mozart-core crate
+///
+/// Events go to stderr and to the in-memory buffer read by [`logs::recent_logs`].
 #[cfg(not(target_arch = "wasm32"))]
 pub fn init_logging() {
+    init_logging_with_dir(None::<&std::path::Path>);
+}
+
+/// Initialize logging, also writing a rotating `mozart.log` in `log_dir`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init_logging_with_dir(log_dir: Option<impl AsRef<std::path::Path>>) {
+    use std::sync::Mutex;
     use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
     let filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new("mozart_core=debug"));
 
+    let file_layer = log_dir.and_then(|dir| {
+        let path = dir.as_ref().join("mozart.log");
+        match logs::RotatingFile::new(
+            path,
+            logs::RotatingFile::DEFAULT_MAX_BYTES,
+            logs::RotatingFile::DEFAULT_MAX_FILES,
+        ) {
+            Ok(file) => Some(fmt::layer().with_ansi(false).with_writer(Mutex::new(file))),
+            Err(e) => {
+                eprintln!("Could not open log file: {}", e);
+                None
+            }
+        }
+    });
+
     tracing_subscriber::registry()
         .with(fmt::layer().with_target(true))
+        .with(file_layer)
+        .with(logs::global_buffer().layer())
         .with(filter)
         .try_init()
         .ok();
@@ -0,0 +1,412 @@
+//! Local diagnostic logging
+//!
+//! Keeps recent log events in a bounded in-memory buffer and optionally in a
+//! size-rotated log file, so users can attach diagnostics to bug reports
+//! without running from a terminal. Nothing is sent anywhere.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use crate::error::{MozartError, Result};
+
+/// Number of entries kept by the global log buffer
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// Log severity, ordered from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    /// Parse from string
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            _ => Err(MozartError::ParseError(format!("Unknown log level: {}", s))),
+        }
+    }
+
+    /// Get short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        }
+    }
+}
+
+impl From<&Level> for LogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::ERROR => LogLevel::Error,
+            Level::WARN => LogLevel::Warn,
+            Level::INFO => LogLevel::Info,
+            Level::DEBUG => LogLevel::Debug,
+            Level::TRACE => LogLevel::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A single captured log event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub level: LogLevel,
+    /// Module path that emitted the event
+    pub target: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:5} {}: {}", self.timestamp_ms, self.level.name(), self.target, self.message)
+    }
+}
+
+/// Bounded ring buffer of recent log entries (cheap to clone, shared)
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+    capacity: usize,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        LogBuffer {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Append an entry, dropping the oldest one when full
+    pub fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Get entries at least as severe as `min_level`, oldest first
+    pub fn recent(&self, min_level: LogLevel) -> Vec<LogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().filter(|e| e.level <= min_level).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    /// Write all buffered entries to a text file, one per line
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        for entry in self.recent(LogLevel::Trace) {
+            text.push_str(&entry.to_string());
+            text.push('\n');
+        }
+        fs::write(path, text)
+            .map_err(|e| MozartError::FileError(format!("Failed to export logs: {}", e)))?;
+        tracing::info!("Exported logs to {:?}", path);
+        Ok(())
+    }
+
+    /// Get a tracing layer that records events into this buffer
+    pub fn layer(&self) -> BufferLayer {
+        BufferLayer {
+            buffer: self.clone(),
+        }
+    }
+}
+
+/// Tracing layer that captures events into a [`LogBuffer`]
+#[derive(Debug, Clone)]
+pub struct BufferLayer {
+    buffer: LogBuffer,
+}
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        self.buffer.push(LogEntry {
+            timestamp_ms: now_ms(),
+            level: LogLevel::from(metadata.level()),
+            target: metadata.target().to_string(),
+            message: visitor.message,
+        });
+    }
+}
+
+/// Collects the `message` field plus any extra fields as `key=value`
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        if field.name() == "message" {
+            self.message.push_str(&format!("{:?}", value));
+        } else {
+            self.message.push_str(&format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            if !self.message.is_empty() {
+                self.message.push(' ');
+            }
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
+
+/// Log file writer that rotates once the file grows past a size limit
+///
+/// `mozart.log` is rotated to `mozart.log.1`, `.1` to `.2`, and so on; the
+/// oldest file beyond `max_files` is deleted.
+#[derive(Debug)]
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    /// Default size at which the log file is rotated
+    pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+    /// Default number of rotated files to keep
+    pub const DEFAULT_MAX_FILES: usize = 3;
+
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.max_files));
+            for i in (1..self.max_files).rev() {
+                let from = self.rotated_path(i);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(i + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Get the process-wide log buffer used by [`crate::init_logging`]
+pub fn global_buffer() -> &'static LogBuffer {
+    static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+    BUFFER.get_or_init(LogBuffer::default)
+}
+
+/// Get recent log entries at least as severe as `min_level`
+pub fn recent_logs(min_level: LogLevel) -> Vec<LogEntry> {
+    global_buffer().recent(min_level)
+}
+
+/// Export the recent log entries to a text file
+pub fn export_logs(path: impl AsRef<Path>) -> Result<()> {
+    global_buffer().export(path)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tracing_subscriber::prelude::*;
+
+    fn entry(level: LogLevel, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp_ms: 0,
+            level,
+            target: "test".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mozart-logs-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest() {
+        let buffer = LogBuffer::new(2);
+        buffer.push(entry(LogLevel::Info, "one"));
+        buffer.push(entry(LogLevel::Info, "two"));
+        buffer.push(entry(LogLevel::Info, "three"));
+
+        let messages: Vec<String> = buffer.recent(LogLevel::Trace).into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["two", "three"]);
+    }
+
+    #[test]
+    fn test_level_filter() {
+        let buffer = LogBuffer::new(10);
+        buffer.push(entry(LogLevel::Debug, "debug"));
+        buffer.push(entry(LogLevel::Warn, "warn"));
+        buffer.push(entry(LogLevel::Error, "error"));
+
+        assert_eq!(buffer.recent(LogLevel::Warn).len(), 2);
+        assert_eq!(buffer.recent(LogLevel::Error).len(), 1);
+        assert_eq!(LogLevel::parse("warning").unwrap(), LogLevel::Warn);
+        assert!(LogLevel::parse("loud").is_err());
+    }
+
+    #[test]
+    fn test_layer_captures_events() {
+        let buffer = LogBuffer::new(10);
+        let subscriber = tracing_subscriber::registry().with(buffer.layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(notes = 3, "Something odd");
+        });
+
+        let entries = buffer.recent(LogLevel::Trace);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].level, LogLevel::Warn);
+        assert_eq!(entries[0].message, "Something odd notes=3");
+    }
+
+    #[test]
+    fn test_export() {
+        let buffer = LogBuffer::new(10);
+        buffer.push(entry(LogLevel::Info, "hello"));
+
+        let path = temp_path("export.log");
+        buffer.export(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(text, "0 INFO  test: hello\n");
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let path = temp_path("rotate.log");
+        let mut file = RotatingFile::new(&path, 10, 2).unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.write_all(b"abc").unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "0123456789");
+        assert_eq!(fs::read_to_string(file.rotated_path(1)).unwrap(), "abc");
+        assert_eq!(fs::read_to_string(file.rotated_path(2)).unwrap(), "0123456789");
+
+        for i in 0..=2 {
+            let p = if i == 0 { path.clone() } else { file.rotated_path(i) };
+            fs::remove_file(p).ok();
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Timed lyric (LRC) export
+//!
+//! Writes a song's lyrics for sing-along players: a `[mm:ss.xx]` stamp per
+//! lyric line, and in enhanced LRC a `<mm:ss.xx>` stamp before every
+//! syllable plus one where the line's last note ends. Times come from the
+//! note ticks through the tempo map, the same as the MIDI export.
+
+use std::path::Path;
+use crate::error::{MozartError, Result};
+use crate::song::Song;
+
+/// LRC file writer
+pub struct LrcExporter {
+    /// Stamp every syllable (enhanced LRC), not just every line
+    pub syllable_timing: bool,
+}
+
+impl Default for LrcExporter {
+    fn default() -> Self {
+        LrcExporter { syllable_timing: true }
+    }
+}
+
+// [mm:ss.xx] from seconds
+fn timestamp(seconds: f64) -> String {
+    let centis = (seconds * 100.0).round() as u64;
+    format!("{:02}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+impl LrcExporter {
+    /// Create a new LRC exporter (enhanced)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether every syllable is stamped, or only every line
+    pub fn with_syllable_timing(mut self, enabled: bool) -> Self {
+        self.syllable_timing = enabled;
+        self
+    }
+
+    /// Export a song's lyrics as LRC text
+    pub fn export(&self, song: &Song) -> Result<String> {
+        if song.lyrics.is_empty() {
+            return Err(MozartError::LyricsError("The song has no lyrics".to_string()));
+        }
+
+        let mut lrc = format!("[ti:{}]\n", song.metadata.title);
+        if !song.metadata.composer.is_empty() {
+            lrc.push_str(&format!("[au:{}]\n", song.metadata.composer));
+        }
+        let length = song.duration_with_tempo_map().round() as u64;
+        lrc.push_str(&format!("[length:{:02}:{:02}]\n", length / 60, length % 60));
+
+        let melody: Vec<_> = song.notes.iter().filter(|n| n.voice == 0).collect();
+        let mut line = String::new();
+        for (i, lyric) in song.lyrics.iter().enumerate() {
+            let seconds = song.tick_to_seconds(lyric.tick);
+            if lyric.new_line || i == 0 {
+                if !line.is_empty() {
+                    lrc.push_str(line.trim_end());
+                    lrc.push('\n');
+                }
+                line = format!("[{}]", timestamp(seconds));
+            }
+
+            let syllable = lyric.text.strip_suffix('-');
+            if self.syllable_timing {
+                line.push_str(&format!("<{}>", timestamp(seconds)));
+            }
+            line.push_str(syllable.unwrap_or(&lyric.text));
+            if syllable.is_none() {
+                line.push(' ');
+            }
+
+            // Close an enhanced line where its last note (held syllables included) ends
+            let next = song.lyrics.get(i + 1);
+            if self.syllable_timing && next.is_none_or(|n| n.new_line) {
+                let end = melody
+                    .iter()
+                    .filter(|n| n.start_tick >= lyric.tick && next.is_none_or(|next| n.start_tick < next.tick))
+                    .map(|n| n.end_tick())
+                    .max()
+                    .unwrap_or(lyric.tick);
+                line = line.trim_end().to_string();
+                line.push_str(&format!(" <{}>", timestamp(song.tick_to_seconds(end))));
+            }
+        }
+        lrc.push_str(line.trim_end());
+        lrc.push('\n');
+
+        tracing::info!("LRC export complete: {} lyric syllables", song.lyrics.len());
+        Ok(lrc)
+    }
+
+    /// Export a song's lyrics to an LRC file
+    pub fn export_to_file(&self, song: &Song, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let lrc = self.export(song)?;
+        std::fs::write(path, lrc).map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))?;
+
+        tracing::info!("LRC file saved: {:?}", path);
+        Ok(())
+    }
+}
+
+/// Quick helper to export a song's lyrics to an enhanced LRC file
+pub fn export_lrc(song: &Song, path: impl AsRef<Path>) -> Result<()> {
+    LrcExporter::new().export_to_file(song, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    // Quarter notes at 120 BPM: half a second each
+    fn song() -> Song {
+        let mut song = Song::with_title("Birthday");
+        song.add_notes((0..8).map(|i| Note::new(60, i * 480, 480)));
+        song.set_lyrics("Hap-py birth-day _\nto you");
+        song
+    }
+
+    #[test]
+    fn test_enhanced_lrc() {
+        let lrc = LrcExporter::new().export(&song()).unwrap();
+        assert_eq!(
+            lrc,
+            "[ti:Birthday]\n[length:00:04]\n\
+             [00:00.00]<00:00.00>Hap<00:00.50>py <00:01.00>birth<00:01.50>day <00:02.50>\n\
+             [00:02.50]<00:02.50>to <00:03.00>you <00:04.00>\n"
+        );
+    }
+
+    #[test]
+    fn test_line_lrc_follows_tempo_map() {
+        let mut song = song();
+        song.add_tempo_change(1200, 60);
+        let lrc = LrcExporter::new().with_syllable_timing(false).export(&song).unwrap();
+        // 2.5 beats at 120, then 2.5 at 60
+        assert!(lrc.ends_with("[00:00.00]Happy birthday\n[00:03.75]to you\n"), "{}", lrc);
+
+        assert!(LrcExporter::new().export(&Song::new()).is_err());
+    }
+
+    #[test]
+    fn test_export_lrc_file() {
+        let path = std::env::temp_dir().join(format!("mozart-lrc-{}.lrc", std::process::id()));
+        export_lrc(&song(), &path).unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().starts_with("[ti:Birthday]\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,280 @@
+//! Command macros
+//!
+//! A macro is a recorded run of commands, each with the answers it was given
+//! (a chord chart, a velocity range), replayed as one action and optionally
+//! bound to a shortcut. [`MacroRecorder`] collects the steps as commands run;
+//! [`MacroLibrary`] holds the named macros saved in preferences.
+
+use serde::{Deserialize, Serialize};
+use crate::commands::{find_command, COMMANDS};
+use crate::error::{MozartError, Result};
+
+// Commands that open a panel or a file picker, which a replay can't answer
+const INTERACTIVE_COMMANDS: &[&str] = &[
+    "file.open",
+    "file.newSongDefaults",
+    "file.importSettings",
+    "edit.chordSuggestions",
+    "edit.modulation",
+    "edit.variations",
+    "edit.grooveFromMidi",
+    "playback.callResponse",
+];
+
+/// One command in a macro
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroStep {
+    /// Registry command id (e.g., "transpose.up"), or a CLI command
+    pub command: String,
+    /// Answers to the command's prompts, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl MacroStep {
+    pub fn new(command: &str, args: &[&str]) -> Self {
+        MacroStep { command: command.to_string(), args: args.iter().map(|a| a.to_string()).collect() }
+    }
+}
+
+/// A named sequence of commands
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    /// e.g., "Mod+Alt+1"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shortcut: Option<String>,
+    pub steps: Vec<MacroStep>,
+}
+
+/// Whether a registry command can be recorded into a macro
+///
+/// Help commands, the macro commands themselves, and commands that open a
+/// panel or file picker are left out.
+pub fn is_recordable(command: &str) -> bool {
+    find_command(command)
+        .is_some_and(|c| c.category != "Help" && c.category != "Macro" && !INTERACTIVE_COMMANDS.contains(&c.id))
+}
+
+/// Normalize a shortcut to "Mod+Alt+Shift+K" form
+///
+/// Accepts Ctrl, Cmd, or ⌘ for Mod and Option for Alt, in any order and
+/// case. Macro shortcuts need Mod or Alt so they can't fire while typing.
+pub fn parse_shortcut(shortcut: &str) -> Result<String> {
+    let parts: Vec<&str> = shortcut.split('+').map(str::trim).collect();
+    let (key, modifiers) = parts.split_last().filter(|(key, _)| !key.is_empty()).ok_or_else(|| {
+        MozartError::MacroError(format!("No key in shortcut '{}'", shortcut))
+    })?;
+    let (mut command, mut alt, mut shift) = (false, false, false);
+    for modifier in modifiers {
+        match modifier.to_lowercase().as_str() {
+            "mod" | "ctrl" | "control" | "cmd" | "command" | "meta" | "⌘" => command = true,
+            "alt" | "option" | "opt" | "⌥" => alt = true,
+            "shift" | "⇧" => shift = true,
+            other => return Err(MozartError::MacroError(format!("Unknown modifier '{}' in shortcut", other))),
+        }
+    }
+    if !command && !alt {
+        return Err(MozartError::MacroError(format!(
+            "Shortcut '{}' needs Mod or Alt",
+            shortcut
+        )));
+    }
+
+    let key = if key.chars().count() == 1 { key.to_uppercase() } else { key.to_string() };
+    let mut normalized: Vec<&str> = Vec::new();
+    if command {
+        normalized.push("Mod");
+    }
+    if alt {
+        normalized.push("Alt");
+    }
+    if shift {
+        normalized.push("Shift");
+    }
+    normalized.push(&key);
+    Ok(normalized.join("+"))
+}
+
+/// Captures commands while recording
+#[derive(Debug, Clone, Default)]
+pub struct MacroRecorder {
+    steps: Option<Vec<MacroStep>>,
+}
+
+impl MacroRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new recording, discarding any in progress
+    pub fn start(&mut self) {
+        tracing::debug!("Recording a macro");
+        self.steps = Some(Vec::new());
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.steps.is_some()
+    }
+
+    /// Add a step if recording; returns whether it was recorded
+    pub fn record(&mut self, step: MacroStep) -> bool {
+        match &mut self.steps {
+            Some(steps) => {
+                steps.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps recorded so far
+    pub fn steps(&self) -> &[MacroStep] {
+        self.steps.as_deref().unwrap_or_default()
+    }
+
+    /// End the recording, returning its steps
+    pub fn stop(&mut self) -> Vec<MacroStep> {
+        self.steps.take().unwrap_or_default()
+    }
+}
+
+/// The user's saved macros
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroLibrary {
+    pub macros: Vec<Macro>,
+}
+
+impl MacroLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter().find(|m| m.name == name)
+    }
+
+    /// The macro bound to a shortcut (in any form [`parse_shortcut`] accepts)
+    pub fn by_shortcut(&self, shortcut: &str) -> Option<&Macro> {
+        let shortcut = parse_shortcut(shortcut).ok()?;
+        self.macros.iter().find(|m| m.shortcut.as_deref() == Some(shortcut.as_str()))
+    }
+
+    /// Save a macro, replacing any with the same name
+    ///
+    /// The shortcut may not be one a command or another macro already uses.
+    pub fn save(&mut self, name: &str, shortcut: Option<&str>, steps: Vec<MacroStep>) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(MozartError::MacroError("Macro name cannot be empty".to_string()));
+        }
+        if steps.is_empty() {
+            return Err(MozartError::MacroError("No commands were recorded".to_string()));
+        }
+        let shortcut = shortcut.filter(|s| !s.trim().is_empty()).map(parse_shortcut).transpose()?;
+        if let Some(shortcut) = &shortcut {
+            if let Some(command) = COMMANDS.iter().find(|c| c.shortcut == Some(shortcut.as_str())) {
+                return Err(MozartError::MacroError(format!("{} is the shortcut for {}", shortcut, command.name)));
+            }
+            if let Some(other) = self.macros.iter().find(|m| m.name != name && m.shortcut.as_ref() == Some(shortcut)) {
+                return Err(MozartError::MacroError(format!("{} already plays '{}'", shortcut, other.name)));
+            }
+        }
+
+        tracing::debug!("Saving macro '{}' ({} steps)", name, steps.len());
+        let saved = Macro { name: name.to_string(), shortcut, steps };
+        match self.macros.iter_mut().find(|m| m.name == name) {
+            Some(existing) => *existing = saved,
+            None => self.macros.push(saved),
+        }
+        Ok(())
+    }
+
+    /// Remove a macro; returns false if there was none
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.macros.len();
+        self.macros.retain(|m| m.name != name);
+        self.macros.len() != before
+    }
+
+    /// Serialize the macros for preferences storage
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Load macros from preferences storage (empty for none)
+    pub fn from_json(json: &str) -> Result<Self> {
+        if json.trim().is_empty() {
+            return Ok(Self::new());
+        }
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn steps() -> Vec<MacroStep> {
+        vec![
+            MacroStep::new("cleanup.normalizeVelocities", &["40-110"]),
+            MacroStep::new("transpose.up", &[]),
+            MacroStep::new("file.exportMidi", &[]),
+        ]
+    }
+
+    #[test]
+    fn test_recorder() {
+        let mut recorder = MacroRecorder::new();
+        assert!(!recorder.record(MacroStep::new("transpose.up", &[])));
+
+        recorder.start();
+        for step in steps() {
+            assert!(recorder.record(step));
+        }
+        assert_eq!(recorder.steps().len(), 3);
+        assert_eq!(recorder.stop(), steps());
+        assert!(!recorder.is_recording());
+
+        assert!(is_recordable("transpose.up"));
+        assert!(!is_recordable("help.about"));
+        assert!(!is_recordable("file.open"));
+        assert!(!is_recordable("no.such.command"));
+    }
+
+    #[test]
+    fn test_parse_shortcut() {
+        assert_eq!(parse_shortcut("ctrl+alt+1").unwrap(), "Mod+Alt+1");
+        assert_eq!(parse_shortcut("Shift + ⌘ + k").unwrap(), "Mod+Shift+K");
+        assert_eq!(parse_shortcut("Alt+F5").unwrap(), "Alt+F5");
+        assert!(parse_shortcut("K").is_err());
+        assert!(parse_shortcut("Shift+K").is_err());
+        assert!(parse_shortcut("Hyper+K").is_err());
+        assert!(parse_shortcut("Mod+").is_err());
+    }
+
+    #[test]
+    fn test_library() {
+        let mut library = MacroLibrary::new();
+        library.save("Tidy up", Some("ctrl+alt+t"), steps()).unwrap();
+        assert_eq!(library.by_shortcut("Mod+Alt+T").unwrap().name, "Tidy up");
+
+        // Taken by Save, or by another macro
+        assert!(library.save("Other", Some("Mod+S"), steps()).is_err());
+        assert!(library.save("Other", Some("Mod+Alt+T"), steps()).is_err());
+        assert!(library.save("Empty", None, Vec::new()).is_err());
+        assert!(library.save(" ", None, steps()).is_err());
+
+        // Re-saving replaces, keeping the shortcut free for itself
+        library.save("Tidy up", Some("Mod+Alt+T"), steps()[..1].to_vec()).unwrap();
+        assert_eq!(library.macros.len(), 1);
+        assert_eq!(library.get("Tidy up").unwrap().steps.len(), 1);
+
+        let restored = MacroLibrary::from_json(&library.to_json().unwrap()).unwrap();
+        assert_eq!(restored, library);
+        assert_eq!(MacroLibrary::from_json("").unwrap(), MacroLibrary::new());
+        assert!(library.remove("Tidy up"));
+        assert!(!library.remove("Tidy up"));
+    }
+}
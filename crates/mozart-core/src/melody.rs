@@ -0,0 +1,295 @@
+//! Chord-outlining melodies
+//!
+//! Writes a melody over a chord chart (as read by [`parse_chord_chart`]):
+//! every beat of the voice's meter gets a note, accented beats of its pattern
+//! (and chord changes) land on a tone of the chord sounding there, and the
+//! weak beats between them pass by step through the key, so the line
+//! outlines the harmony. Syncopation anticipates some accented notes by half
+//! the beat before them. Generation is seeded, so the same chart and config
+//! always give the same melody.
+
+use serde::{Deserialize, Serialize};
+use crate::chart::{parse_chord_chart, ChartChord};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::PitchClass;
+use crate::song::Song;
+use crate::time::{AccentLevel, TimeSignature};
+use crate::worksheet::Rng;
+
+/// Widest move between accented notes (a fifth), so the line stays singable
+const MAX_LEAP: u8 = 7;
+const MELODY_VELOCITY: u8 = 100;
+
+/// What to write and where; omitted fields use the defaults
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MelodyConfig {
+    /// Chord chart to follow from measure 1, e.g., "| C . . . | Am . F . |"
+    pub chart: String,
+    /// Lowest pitch (MIDI)
+    pub low: u8,
+    /// Highest pitch (MIDI), at least an octave above `low`
+    pub high: u8,
+    /// Chance (0-1) that an accented note comes half a beat early
+    pub syncopation: f32,
+    /// Voice to write into, replacing its notes
+    pub voice: u8,
+    /// Same seed, same melody
+    pub seed: u64,
+}
+
+impl Default for MelodyConfig {
+    fn default() -> Self {
+        MelodyConfig {
+            chart: String::new(),
+            low: 60,
+            high: 79,
+            syncopation: 0.2,
+            voice: 1,
+            seed: 1,
+        }
+    }
+}
+
+/// What was written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MelodyReport {
+    pub voice: u8,
+    pub notes: usize,
+    /// Notes that are tones of the chord under them
+    pub chord_tones: usize,
+    /// Accented notes moved half a beat early
+    pub syncopated: usize,
+}
+
+// One note of the melody before its pitch is known
+struct Slot {
+    start: u32,
+    end: u32,
+    /// Index into the chords
+    chord: usize,
+    accented: bool,
+}
+
+// A slot per beat under each chord, split where a chord changes off the beat
+fn slots(chords: &[ChartChord], time_signature: &TimeSignature) -> Vec<Slot> {
+    let beat = time_signature.ticks_per_beat();
+    let mut slots = Vec::new();
+    for (i, chord) in chords.iter().enumerate() {
+        let mut tick = chord.start_tick;
+        while tick < chord.end_tick {
+            let end = ((tick / beat + 1) * beat).min(chord.end_tick);
+            let accented = tick == chord.start_tick
+                || (time_signature.is_on_beat(tick) && time_signature.accent_at_tick(tick) != AccentLevel::Weak);
+            slots.push(Slot { start: tick, end, chord: i, accented });
+            tick = end;
+        }
+    }
+    slots
+}
+
+// The next scale tone above or below a pitch
+fn step(scale: &[u8], from: u8, up: bool) -> Option<u8> {
+    if up {
+        scale.iter().copied().find(|&tone| tone > from)
+    } else {
+        scale.iter().rev().copied().find(|&tone| tone < from)
+    }
+}
+
+// One of the two chord tones nearest the last accented note, within a fifth
+// and not repeating it when there's a choice
+fn chord_tone(tones: &[u8], from: u8, rng: &mut Rng) -> u8 {
+    let mut near: Vec<u8> = tones.iter().copied().filter(|&t| t != from && t.abs_diff(from) <= MAX_LEAP).collect();
+    near.sort_by_key(|&t| t.abs_diff(from));
+    match near.len() {
+        0 => tones.iter().copied().min_by_key(|&t| t.abs_diff(from)).unwrap_or(from),
+        n => near[rng.below(n.min(2))],
+    }
+}
+
+// A weak-beat note after `from`: a passing tone stepping toward the next
+// accented note, the step past it when they're adjacent, or a neighbor tone
+// when the line stays put (or ends)
+fn passing_tone(scale: &[u8], from: u8, to: Option<u8>, rng: &mut Rng) -> u8 {
+    let up = match to {
+        Some(to) if to != from => to > from,
+        _ => rng.chance(2),
+    };
+    match (to, step(scale, from, up)) {
+        (Some(to), Some(next)) if next != to && (next < to) == up => next,
+        (Some(to), _) if to != from => step(scale, to, up).unwrap_or(from),
+        (_, next) => next.or_else(|| step(scale, from, !up)).unwrap_or(from),
+    }
+}
+
+/// Write a melody over a chord chart into the target voice, replacing its
+/// notes
+pub fn generate_melody(song: &mut Song, config: &MelodyConfig) -> Result<MelodyReport> {
+    if config.high < config.low.saturating_add(12) || config.high > 127 {
+        return Err(MozartError::MelodyError(format!(
+            "The range needs at least an octave of MIDI pitches, not {}-{}",
+            config.low, config.high
+        )));
+    }
+    if !(0.0..=1.0).contains(&config.syncopation) {
+        return Err(MozartError::MelodyError(format!(
+            "Syncopation must be between 0 and 1, not {}",
+            config.syncopation
+        )));
+    }
+    let chords = parse_chord_chart(song, &config.chart)?;
+    let time_signature = song.voice_time_signature(config.voice).clone();
+    let mut slots = slots(&chords, &time_signature);
+
+    let key = song.settings.key;
+    let scale: Vec<u8> = (config.low..=config.high).filter(|&p| key.contains(PitchClass::new(p % 12))).collect();
+    let chord_tones: Vec<Vec<u8>> = chords
+        .iter()
+        .map(|chord| (config.low..=config.high).filter(|&p| chord.pitch_classes.contains(&(p % 12))).collect())
+        .collect();
+    let mut rng = Rng(config.seed);
+
+    // Chord tones on the accented beats first, then the weak beats between them
+    let mut pitches: Vec<Option<u8>> = vec![None; slots.len()];
+    let mut last = config.low / 2 + config.high / 2;
+    for (slot, pitch) in slots.iter().zip(pitches.iter_mut()).filter(|(slot, _)| slot.accented) {
+        last = chord_tone(&chord_tones[slot.chord], last, &mut rng);
+        *pitch = Some(last);
+    }
+    let mut last = config.low / 2 + config.high / 2;
+    for i in 0..slots.len() {
+        let pitch = match pitches[i] {
+            Some(pitch) => pitch,
+            None => passing_tone(&scale, last, pitches[i..].iter().find_map(|&p| p), &mut rng),
+        };
+        pitches[i] = Some(pitch);
+        last = pitch;
+    }
+    let pitches: Vec<u8> = pitches.into_iter().flatten().collect();
+
+    let mut syncopated = 0;
+    let chance = (config.syncopation * 100.0).round() as usize;
+    for i in 1..slots.len() {
+        let before = &slots[i - 1];
+        let early = (before.end - before.start) / 2;
+        if slots[i].accented && before.end == slots[i].start && early > 0 && rng.below(100) < chance {
+            slots[i - 1].end -= early;
+            slots[i].start -= early;
+            syncopated += 1;
+        }
+    }
+
+    let notes: Vec<Note> = slots
+        .iter()
+        .zip(&pitches)
+        .map(|(slot, &pitch)| Note::with_voice(pitch, slot.start, slot.end - slot.start, MELODY_VELOCITY, config.voice))
+        .collect();
+    let on_chord = notes
+        .iter()
+        .zip(&slots)
+        .filter(|(note, slot)| chords[slot.chord].pitch_classes.contains(&(note.pitch % 12)))
+        .count();
+    let count = notes.len();
+    song.notes.retain(|n| n.voice != config.voice);
+    song.add_notes(notes);
+    tracing::info!("Generated a {}-note melody over {} chords into voice {}", count, chords.len(), config.voice);
+
+    Ok(MelodyReport { voice: config.voice, notes: count, chord_tones: on_chord, syncopated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn config(chart: &str) -> MelodyConfig {
+        MelodyConfig { chart: chart.to_string(), syncopation: 0.0, ..MelodyConfig::default() }
+    }
+
+    fn melody(song: &Song, voice: u8) -> Vec<Note> {
+        song.notes.iter().filter(|n| n.voice == voice).cloned().collect()
+    }
+
+    #[test]
+    fn test_chord_tones_on_accented_beats() {
+        let mut song = Song::new();
+        let report = generate_melody(&mut song, &config("| C . . . | F . G . | C |")).unwrap();
+        let notes = melody(&song, 1);
+        assert_eq!(report.notes, 12);
+        assert_eq!(notes.len(), 12);
+
+        // Beats 1 and 3 of 4/4 carry the chord; beats 2 and 4 move by step (or
+        // step past the next chord tone)
+        let chord_at = |tick: u32| match tick {
+            0..=1919 => [0, 4, 7],
+            1920..=2879 => [5, 9, 0],
+            2880..=3839 => [7, 11, 2],
+            _ => [0, 4, 7],
+        };
+        for (i, note) in notes.iter().enumerate() {
+            assert!((60..=79).contains(&note.pitch));
+            assert_eq!(note.duration_ticks, 480);
+            if i % 2 == 0 {
+                assert!(chord_at(note.start_tick).contains(&(note.pitch % 12)), "{:?}", note);
+            } else {
+                assert!(note.pitch.abs_diff(notes[i - 1].pitch) <= 4, "{:?}", note);
+            }
+        }
+        assert!(report.chord_tones >= 6);
+        assert_eq!(report.syncopated, 0);
+    }
+
+    #[test]
+    fn test_odd_meter_accents() {
+        // 7/8 as 3+2+2: eighth beats, chord tones at the group starts
+        let mut song = Song::new();
+        song.set_time_signature(TimeSignature::parse("7/8").unwrap());
+        generate_melody(&mut song, &config("| Am |")).unwrap();
+        let notes = melody(&song, 1);
+        assert_eq!(notes.len(), 7);
+        for note in notes.iter().filter(|n| [0, 720, 1200].contains(&n.start_tick)) {
+            assert!([9, 0, 4].contains(&(note.pitch % 12)), "{:?}", note);
+        }
+    }
+
+    #[test]
+    fn test_syncopation_and_seed() {
+        let mut song = Song::new();
+        let mut syncopated = MelodyConfig { syncopation: 1.0, ..config("| C . . . | G . . . |") };
+        let report = generate_melody(&mut song, &syncopated).unwrap();
+        // Every accented note after the first comes an eighth early
+        assert_eq!(report.syncopated, 3);
+        let starts: Vec<u32> = melody(&song, 1).iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, vec![0, 480, 720, 1440, 1680, 2400, 2640, 3360]);
+
+        let first = melody(&song, 1);
+        generate_melody(&mut song, &syncopated).unwrap();
+        assert_eq!(melody(&song, 1), first);
+        syncopated.seed = 7;
+        generate_melody(&mut song, &syncopated).unwrap();
+        assert_eq!(melody(&song, 1).len(), first.len());
+    }
+
+    #[test]
+    fn test_replaces_only_the_target_voice() {
+        let mut song = Song::new();
+        song.add_note(Note::new(48, 0, 1920));
+        song.add_note(Note::with_voice(90, 0, 480, 100, 1));
+        generate_melody(&mut song, &config("| C |")).unwrap();
+        assert_eq!(melody(&song, 0).len(), 1);
+        assert!(melody(&song, 1).iter().all(|n| n.pitch != 90));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut song = Song::new();
+        assert!(matches!(generate_melody(&mut song, &config("")), Err(MozartError::HarmonyError(_))));
+        let narrow = MelodyConfig { low: 60, high: 70, ..config("| C |") };
+        assert!(matches!(generate_melody(&mut song, &narrow), Err(MozartError::MelodyError(_))));
+        let wild = MelodyConfig { syncopation: 1.5, ..config("| C |") };
+        assert!(generate_melody(&mut song, &wild).is_err());
+        assert!(song.notes.is_empty());
+    }
+}
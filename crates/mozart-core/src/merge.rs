@@ -0,0 +1,489 @@
+//! Merging and splitting songs
+//!
+//! Layers several songs (short exercises, parts written separately) into one
+//! so they can be printed or exported together. Each source keeps its own
+//! voices, renumbered past the ones already taken, and the merged song is
+//! marked `channel_per_voice` so MIDI export writes every source on its own
+//! channel. A voice in the drum voice stays there, on the General MIDI drum
+//! channel.
+//!
+//! The first song supplies the meter, key, tempo map, markers, and lyrics.
+//! Chord symbols are combined, the first song's winning where both have
+//! one; while there are any, the comping voice stays free for the chord
+//! track.
+//! Sources in another meter keep it as a voice meter. Their timing is either
+//! aligned (re-timed onto the first song's tempo map, so each plays as it
+//! did on its own) or kept (same ticks, at the first song's tempo).
+//!
+//! Splitting goes the other way, cutting a long song (a transcription, say)
+//! into practice chunks at given ticks or at its section markers. A split is
+//! planned first, so the boundaries can be previewed, then each piece becomes
+//! a song of its own starting at tick 0, with the tempo, markers, lyrics,
+//! and annotations that fall inside it. Boundaries are on the played
+//! timeline, with repeats written out.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use crate::chord::{ChordSymbol, CHORD_VOICE};
+use crate::error::{MozartError, Result};
+use crate::presets::DRUM_VOICE;
+use crate::song::{Annotation, Lyric, Marker, Song, TempoChange, Track, MAX_VOICES};
+use crate::strum::Strum;
+use crate::view::ViewState;
+
+/// How merged songs are timed against the first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TempoAlignment {
+    /// Re-time each song onto the first song's tempo map, keeping its
+    /// length in seconds
+    #[default]
+    Align,
+    /// Keep each song's ticks, played at the first song's tempo
+    KeepFirst,
+}
+
+impl TempoAlignment {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "align" => Ok(TempoAlignment::Align),
+            "keep" | "keep_first" => Ok(TempoAlignment::KeepFirst),
+            _ => Err(MozartError::ParseError(format!("Unknown tempo alignment: {} (align or keep)", s))),
+        }
+    }
+}
+
+/// Merge songs into one, the first song's settings leading; see the module
+/// docs
+pub fn merge_songs(songs: Vec<Song>, tempo: TempoAlignment) -> Result<Song> {
+    let mut songs = songs.into_iter();
+    let mut merged = songs
+        .next()
+        .ok_or_else(|| MozartError::MergeError("Nothing to merge".to_string()))?;
+    merged.settings.channel_per_voice = true;
+    for song in songs {
+        merge_into(&mut merged, &song, tempo)?;
+    }
+    Ok(merged)
+}
+
+/// Layer a song into `target` on voices of its own; returns the voices its
+/// notes now use, in the order of the voices they came from
+///
+/// The source's repeats are written out first; anything but its notes,
+/// meter, tracks, chord symbols, and title is left behind. Each voice lands on a track
+/// (the source's own, or one named after it) with the source's instrument.
+pub fn merge_into(target: &mut Song, source: &Song, tempo: TempoAlignment) -> Result<Vec<u8>> {
+    let source = &*source.as_played();
+    let mut taken: BTreeSet<u8> =
+        target.notes.iter().map(|n| n.voice).chain(target.settings.tracks.iter().map(|t| t.voice)).collect();
+    taken.insert(DRUM_VOICE);
+    if !target.chords.is_empty() || !source.chords.is_empty() {
+        taken.insert(CHORD_VOICE);
+    }
+    let sources: BTreeSet<u8> = source.notes.iter().map(|n| n.voice).collect();
+
+    let mut voices = Vec::new();
+    for &voice in &sources {
+        let to = match voice {
+            DRUM_VOICE => DRUM_VOICE,
+            _ => (0..MAX_VOICES).find(|v| !taken.contains(v)).ok_or_else(|| {
+                MozartError::MergeError(format!(
+                    "Merging \"{}\" would need more than {} voices, one per MIDI channel",
+                    source.metadata.title, MAX_VOICES
+                ))
+            })?,
+        };
+        taken.insert(to);
+        voices.push((voice, to));
+    }
+
+    let retime = |tick: u32| match tempo {
+        TempoAlignment::Align => target.seconds_to_tick(source.tick_to_seconds(tick)),
+        TempoAlignment::KeepFirst => tick,
+    };
+    let notes: Vec<_> = source
+        .notes
+        .iter()
+        .map(|note| {
+            let mut note = note.clone();
+            let (start, end) = (retime(note.start_tick), retime(note.end_tick()));
+            note.start_tick = start;
+            note.duration_ticks = end.saturating_sub(start).max(1);
+            note.voice = voices.iter().find(|(from, _)| *from == note.voice).map_or(note.voice, |&(_, to)| to);
+            note
+        })
+        .collect();
+    let chords: Vec<_> =
+        source.chords.iter().map(|chord| ChordSymbol { tick: retime(chord.tick), symbol: chord.symbol.clone() }).collect();
+
+    for &(from, to) in &voices {
+        let meter = source.voice_time_signature(from);
+        if *meter != target.settings.time_signature {
+            target.set_voice_time_signature(to, Some(meter.clone()));
+        }
+        let mut track = source.track(from).cloned().unwrap_or_else(|| {
+            let name = match source.metadata.title.as_str() {
+                "" => format!("Voice {}", to + 1),
+                title if sources.len() > 1 => format!("{} {}", title, from + 1),
+                title => title.to_string(),
+            };
+            Track::new(to, name)
+        });
+        track.voice = to;
+        track.instrument = Some(source.voice_instrument(from)).filter(|&i| i != target.settings.instrument);
+        target.settings.tracks.retain(|t| t.voice != to);
+        target.settings.tracks.push(track);
+    }
+    target.settings.tracks.sort_by_key(|t| t.voice);
+    target.add_notes(notes);
+    for chord in chords {
+        let at = target.chords.partition_point(|c| c.tick < chord.tick);
+        if target.chords.get(at).is_none_or(|c| c.tick != chord.tick) {
+            target.chords.insert(at, chord);
+        }
+    }
+    target.settings.channel_per_voice = true;
+    let title = &source.metadata.title;
+    if !title.is_empty() && *title != target.metadata.title {
+        target.metadata.title = format!("{} + {}", target.metadata.title, title);
+    }
+
+    tracing::info!("Merged \"{}\" into voices {:?}", title, voices.iter().map(|&(_, to)| to).collect::<Vec<_>>());
+    Ok(voices.into_iter().map(|(_, to)| to).collect())
+}
+
+/// Load song files (.mozart.json or MIDI) and merge them in order
+#[cfg(not(target_arch = "wasm32"))]
+pub fn merge_files(paths: &[impl AsRef<std::path::Path>], tempo: TempoAlignment) -> Result<Song> {
+    let songs = paths
+        .iter()
+        .map(|path| {
+            let path = path.as_ref();
+            match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+                Some("mid") | Some("midi") => crate::midi::import_from_midi_file(path),
+                _ => Song::load(path),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    merge_songs(songs, tempo)
+}
+
+/// One piece of a planned split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitPiece {
+    pub title: String,
+    pub start_tick: u32,
+    pub end_tick: u32,
+    /// Where the piece starts in the song, e.g., "5.1.0"
+    pub start_position: String,
+    /// Notes starting in the piece
+    pub notes: usize,
+}
+
+// Pieces between consecutive boundaries, from 0 to the end of the song
+fn plan_pieces(song: &Song, starts: &[(u32, String)]) -> Vec<SplitPiece> {
+    let end = song.duration_ticks();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (start, title))| {
+            let end_tick = starts.get(i + 1).map_or(end, |(next, _)| *next);
+            SplitPiece {
+                title: title.clone(),
+                start_tick: *start,
+                end_tick,
+                start_position: song.position_to_mbt(*start).to_string(),
+                notes: song.notes.iter().filter(|n| (*start..end_tick).contains(&n.start_tick)).count(),
+            }
+        })
+        .collect()
+}
+
+/// Plan pieces cut at the given ticks; ticks outside the song are ignored
+pub fn plan_split(song: &Song, ticks: &[u32]) -> Result<Vec<SplitPiece>> {
+    let song = &*song.as_played();
+    let end = song.duration_ticks();
+    if end == 0 {
+        return Err(MozartError::SplitError("Nothing to split; the song has no notes".to_string()));
+    }
+    let mut cuts: Vec<u32> = ticks.iter().copied().filter(|&t| t > 0 && t < end).collect();
+    cuts.sort_unstable();
+    cuts.dedup();
+    let starts: Vec<(u32, String)> = std::iter::once(0)
+        .chain(cuts)
+        .enumerate()
+        .map(|(i, tick)| (tick, format!("{} (part {})", song.metadata.title, i + 1)))
+        .collect();
+    Ok(plan_pieces(song, &starts))
+}
+
+/// Plan a piece per section, each starting at a marker (plus whatever
+/// comes before the first one)
+pub fn plan_sections(song: &Song) -> Result<Vec<SplitPiece>> {
+    let song = &*song.as_played();
+    let end = song.duration_ticks();
+    if end == 0 {
+        return Err(MozartError::SplitError("Nothing to split; the song has no notes".to_string()));
+    }
+    let mut starts: Vec<(u32, String)> = song
+        .markers
+        .iter()
+        .filter(|m| m.tick < end)
+        .map(|m| (m.tick, format!("{} - {}", song.metadata.title, m.name)))
+        .collect();
+    if starts.is_empty() {
+        return Err(MozartError::SplitError("The song has no section markers to split at".to_string()));
+    }
+    starts.dedup_by_key(|(tick, _)| *tick);
+    if starts[0].0 > 0 {
+        starts.insert(0, (0, format!("{} - Intro", song.metadata.title)));
+    }
+    Ok(plan_pieces(song, &starts))
+}
+
+/// Cut a song into the planned pieces, each starting at tick 0
+///
+/// A piece takes the notes that start in it (shortened to end with it) and
+/// the tempo in effect where it starts.
+pub fn split_song(song: &Song, plan: &[SplitPiece]) -> Vec<Song> {
+    let song = &*song.as_played();
+    plan.iter()
+        .map(|piece| {
+            let range = piece.start_tick..piece.end_tick;
+            let to_piece = |tick: u32| tick - piece.start_tick;
+            let mut part = Song {
+                notes: Vec::new(),
+                markers: Vec::new(),
+                strums: Vec::new(),
+                chords: Vec::new(),
+                lyrics: Vec::new(),
+                annotations: Vec::new(),
+                view: ViewState::default(),
+                ..song.clone()
+            };
+            part.metadata.title = piece.title.clone();
+            part.settings.tempo = song.tempo_at_tick(piece.start_tick);
+            part.settings.tempo_map = song
+                .settings
+                .tempo_map
+                .iter()
+                .filter(|c| c.tick > piece.start_tick && range.contains(&c.tick))
+                .map(|c| TempoChange { tick: to_piece(c.tick), tempo: c.tempo })
+                .collect();
+            part.notes = song
+                .notes
+                .iter()
+                .filter(|n| range.contains(&n.start_tick))
+                .map(|n| {
+                    let mut note = n.clone();
+                    note.start_tick = to_piece(n.start_tick);
+                    note.duration_ticks = n.end_tick().min(piece.end_tick) - n.start_tick;
+                    note
+                })
+                .collect();
+            part.strums = song.strums.iter().filter(|s| range.contains(&s.tick)).map(|s| Strum { tick: to_piece(s.tick), ..*s }).collect();
+            // The chord sounding where the piece begins, then the piece's own
+            let sounding = song.chord_at(piece.start_tick).filter(|c| c.tick < piece.start_tick);
+            part.chords = sounding
+                .map(|c| ChordSymbol { tick: 0, ..c.clone() })
+                .into_iter()
+                .chain(song.chords.iter().filter(|c| range.contains(&c.tick)).map(|c| ChordSymbol { tick: to_piece(c.tick), ..c.clone() }))
+                .collect();
+            part.lyrics = song
+                .lyrics
+                .iter()
+                .filter(|l| range.contains(&l.tick))
+                .map(|l| Lyric { tick: to_piece(l.tick), ..l.clone() })
+                .collect();
+            part.markers = song
+                .markers
+                .iter()
+                .filter(|m| range.contains(&m.tick))
+                .map(|m| Marker { tick: to_piece(m.tick), ..m.clone() })
+                .collect();
+            part.annotations = song
+                .annotations
+                .iter()
+                .filter(|a| range.contains(&a.tick))
+                .map(|a| Annotation {
+                    tick: to_piece(a.tick),
+                    end_tick: a.end_tick.map(|end| to_piece(end.clamp(a.tick, piece.end_tick))),
+                    ..a.clone()
+                })
+                .collect();
+            part
+        })
+        .collect()
+}
+
+/// Cut a song at the given ticks
+pub fn split_song_at(song: &Song, ticks: &[u32]) -> Result<Vec<Song>> {
+    let plan = plan_split(song, ticks)?;
+    tracing::info!("Splitting \"{}\" into {} pieces", song.metadata.title, plan.len());
+    Ok(split_song(song, &plan))
+}
+
+/// Cut a song at its section markers
+pub fn split_by_sections(song: &Song) -> Result<Vec<Song>> {
+    let plan = plan_sections(song)?;
+    tracing::info!("Splitting \"{}\" into {} sections", song.metadata.title, plan.len());
+    Ok(split_song(song, &plan))
+}
+
+/// Save pieces as numbered files in a folder (e.g., `etude-01.mozart.json`);
+/// returns the paths written
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_pieces(pieces: &[Song], dir: impl AsRef<std::path::Path>, stem: &str) -> Result<Vec<std::path::PathBuf>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)
+        .map_err(|e| MozartError::FileError(format!("Failed to create {:?}: {}", dir, e)))?;
+    pieces
+        .iter()
+        .enumerate()
+        .map(|(i, piece)| {
+            let path = dir.join(format!("{}-{:02}.mozart.json", stem, i + 1));
+            piece.save(&path)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use crate::time::TimeSignature;
+    use pretty_assertions::assert_eq;
+
+    fn exercise(title: &str, tempo: u16, notes: &[(u8, u32, u8)]) -> Song {
+        let mut song = Song::new();
+        song.metadata.title = title.to_string();
+        song.set_tempo(tempo);
+        for &(pitch, start, voice) in notes {
+            song.add_note(Note::with_voice(pitch, start, 480, 100, voice));
+        }
+        song
+    }
+
+    fn voices(song: &Song) -> Vec<(u8, u32, u32, u8)> {
+        song.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks, n.voice)).collect()
+    }
+
+    #[test]
+    fn test_merge_songs_onto_their_own_voices() {
+        let first = exercise("Scales", 120, &[(60, 0, 0), (48, 0, 1)]);
+        let second = exercise("Arpeggios", 120, &[(64, 480, 0), (36, 0, 9)]);
+        let mut third = exercise("Ostinato", 120, &[(55, 0, 0)]);
+        third.set_time_signature(TimeSignature::parse("7/8").unwrap());
+
+        let merged = merge_songs(vec![first, second, third], TempoAlignment::Align).unwrap();
+        assert_eq!(voices(&merged), vec![(60, 0, 480, 0), (48, 0, 480, 1), (36, 0, 480, 9), (55, 0, 480, 3), (64, 480, 480, 2)]);
+        assert_eq!(merged.metadata.title, "Scales + Arpeggios + Ostinato");
+        assert!(merged.settings.channel_per_voice);
+        assert_eq!(merged.settings.time_signature.to_string(), "4/4");
+        assert_eq!(merged.voice_time_signature(3).to_string(), "7/8");
+        assert_eq!(merged.voice_time_signature(2).to_string(), "4/4");
+        let tracks: Vec<(u8, &str)> = merged.settings.tracks.iter().map(|t| (t.voice, t.name.as_str())).collect();
+        assert_eq!(tracks, vec![(2, "Arpeggios 1"), (3, "Ostinato"), (9, "Arpeggios 10")]);
+    }
+
+    #[test]
+    fn test_tempo_alignment() {
+        // A quarter at 60 BPM lasts a second, two quarters at 120
+        let slow = exercise("Slow", 60, &[(60, 480, 0)]);
+        let fast = exercise("Fast", 120, &[]);
+
+        let aligned = merge_songs(vec![fast.clone(), slow.clone()], TempoAlignment::Align).unwrap();
+        assert_eq!(voices(&aligned), vec![(60, 960, 960, 0)]);
+        let kept = merge_songs(vec![fast, slow], TempoAlignment::KeepFirst).unwrap();
+        assert_eq!(voices(&kept), vec![(60, 480, 480, 0)]);
+        assert_eq!(kept.settings.tempo, 120);
+        assert_eq!(TempoAlignment::parse("keep").unwrap(), TempoAlignment::KeepFirst);
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        assert!(matches!(merge_songs(Vec::new(), TempoAlignment::Align), Err(MozartError::MergeError(_))));
+        let crowded = exercise("Crowded", 120, &(0..15).map(|v| (60, 0, if v < 9 { v } else { v + 1 })).collect::<Vec<_>>());
+        let mut target = crowded.clone();
+        assert!(merge_into(&mut target, &exercise("One more", 120, &[(72, 0, 0)]), TempoAlignment::Align).is_err());
+        assert_eq!(target.notes, crowded.notes);
+        // Drums join the drum voice however full the rest is
+        assert_eq!(merge_into(&mut target, &exercise("Drums", 120, &[(36, 0, 9)]), TempoAlignment::Align).unwrap(), vec![9]);
+    }
+
+    #[test]
+    fn test_merge_keeps_the_chord_voice_free() {
+        let mut target = exercise("Lead", 120, &(0..14).map(|v| (60, 0, if v < 9 { v } else { v + 1 })).collect::<Vec<_>>());
+        target.set_chord(0, "C").unwrap();
+        target.set_chord(1920, "F").unwrap();
+        let mut source = exercise("Slow", 60, &[(67, 0, 0)]);
+        source.set_chord(0, "Am").unwrap();
+        source.set_chord(1920, "G").unwrap();
+
+        // Voice 15 is the only one left, and the chord track plays on it
+        assert!(merge_into(&mut target.clone(), &source, TempoAlignment::Align).is_err());
+        target.notes.retain(|n| n.voice != 14);
+        assert_eq!(merge_into(&mut target, &source, TempoAlignment::Align).unwrap(), vec![14]);
+        // The source's chords are re-timed too; the target's win where both have one
+        let chords: Vec<(u32, &str)> = target.chords.iter().map(|c| (c.tick, c.symbol.as_str())).collect();
+        assert_eq!(chords, vec![(0, "C"), (1920, "F"), (3840, "G")]);
+    }
+
+    #[test]
+    fn test_split_song_at() {
+        // Four measures of quarters, a tempo change in measure 3, lyrics throughout
+        let mut song = exercise("Etude", 100, &[]);
+        for beat in 0..16 {
+            song.add_note(Note::new(60 + beat as u8, beat * 480, 480));
+        }
+        song.add_note(Note::new(48, 1440, 960)); // Held across the first cut
+        song.add_tempo_change(2880, 80);
+        song.set_lyrics("one two three four five six sev-en eight nine ten e-lev-en twelve");
+        song.add_annotation(3360, Some(5000), "breathe").unwrap();
+        song.set_chord(0, "C").unwrap();
+        song.set_chord(2400, "F").unwrap();
+
+        let plan = plan_split(&song, &[3840, 1920, 1920, 9999]).unwrap();
+        let bounds: Vec<(u32, u32, &str, usize)> =
+            plan.iter().map(|p| (p.start_tick, p.end_tick, p.start_position.as_str(), p.notes)).collect();
+        assert_eq!(bounds, vec![(0, 1920, "1.1.0", 5), (1920, 3840, "2.1.0", 4), (3840, 7680, "3.1.0", 8)]);
+        assert_eq!(plan[2].title, "Etude (part 3)");
+
+        let pieces = split_song_at(&song, &[1920, 3840]).unwrap();
+        assert_eq!(pieces.len(), 3);
+        // The held note ends with its piece
+        assert!(pieces[0].notes.iter().any(|n| n.pitch == 48 && n.start_tick == 1440 && n.duration_ticks == 480));
+        assert_eq!(pieces[1].notes[0].start_tick, 0);
+        assert_eq!((pieces[1].settings.tempo, pieces[1].settings.tempo_map.len()), (100, 1));
+        assert_eq!(pieces[1].settings.tempo_map[0].tick, 960);
+        assert_eq!((pieces[2].settings.tempo, pieces[2].settings.tempo_map.len()), (80, 0));
+        assert_eq!(pieces[1].lyrics[0].text, "five");
+        assert_eq!(pieces[1].annotations[0].end_tick, Some(1920));
+        // Each piece starts with the chord sounding there
+        let chords: Vec<(u32, &str)> = pieces[1].chords.iter().map(|c| (c.tick, c.symbol.as_str())).collect();
+        assert_eq!(chords, vec![(0, "C"), (480, "F")]);
+        assert_eq!(pieces[2].duration_ticks(), 3840);
+    }
+
+    #[test]
+    fn test_split_by_sections() {
+        let mut song = exercise("Tune", 120, &[(60, 0, 0), (62, 1920, 0), (64, 3840, 0), (65, 5760, 0)]);
+        song.add_marker("A", 1920);
+        song.add_marker("B", 3840);
+
+        let plan = plan_sections(&song).unwrap();
+        let titles: Vec<&str> = plan.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, vec!["Tune - Intro", "Tune - A", "Tune - B"]);
+        let pieces = split_by_sections(&song).unwrap();
+        assert_eq!(pieces[2].notes.len(), 2);
+        assert_eq!(pieces[2].markers[0].tick, 0);
+
+        assert!(split_by_sections(&exercise("Plain", 120, &[(60, 0, 0)])).is_err());
+        assert!(split_song_at(&Song::new(), &[480]).is_err());
+        // No cuts: the whole song in one piece
+        let whole = split_song_at(&song, &[]).unwrap();
+        assert_eq!((whole.len(), &whole[0].notes), (1, &song.notes));
+    }
+}
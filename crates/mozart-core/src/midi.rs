@@ -1,22 +1,41 @@
-//! MIDI export functionality
+//! MIDI export and import
 //!
-//! Exports songs to Standard MIDI File (SMF) Format 0
+//! Exports songs to Standard MIDI File (SMF) Format 0, and imports notes,
+//! tempo, time signature, key, title, and instrument from any SMF (Format 0
+//! or 1). Songs with `channel_per_voice` set (such as merged songs) write
+//! each voice on its own channel; others write everything on channel 1.
 
 use crate::error::{MozartError, Result};
+use crate::chance;
+use crate::instrument::Instrument;
+use crate::note::Note;
+use crate::pitch::PitchClass;
+use crate::scale::{Scale, ScaleType};
 use crate::song::Song;
+use crate::time::TimeSignature;
 use crate::TICKS_PER_QUARTER;
+use midly::{MetaMessage, MidiMessage, Smf, Timing, TrackEventKind};
+use std::collections::HashMap;
 use std::path::Path;
 
+/// Number of note events written between progress reports
+const PROGRESS_INTERVAL: usize = 1000;
+/// Channel 10, which General MIDI players sound as drums
+const GM_DRUM_CHANNEL: u8 = 9;
+
 /// MIDI file writer
 pub struct MidiExporter {
     /// Ticks per quarter note in the output file
     pub ticks_per_quarter: u16,
+    /// Bake accent-shaped dynamics into the written note velocities
+    pub accent_dynamics: bool,
 }
 
 impl Default for MidiExporter {
     fn default() -> Self {
         MidiExporter {
             ticks_per_quarter: TICKS_PER_QUARTER as u16,
+            accent_dynamics: false,
         }
     }
 }
@@ -27,17 +46,39 @@ impl MidiExporter {
         Self::default()
     }
 
+    /// Set whether accent-shaped dynamics are baked into velocities
+    pub fn with_accent_dynamics(mut self, enabled: bool) -> Self {
+        self.accent_dynamics = enabled;
+        self
+    }
+
     /// Export a song to MIDI bytes
     pub fn export(&self, song: &Song) -> Result<Vec<u8>> {
+        self.export_with_progress(song, |_, _| Ok(()))
+    }
+
+    /// Export a song to MIDI bytes, reporting progress (percent, stage)
+    ///
+    /// Returning an error from `on_progress` aborts the export, which is how
+    /// callers implement cancellation.
+    pub fn export_with_progress(
+        &self,
+        song: &Song,
+        mut on_progress: impl FnMut(f32, &str) -> Result<()>,
+    ) -> Result<Vec<u8>> {
         tracing::info!("Exporting song '{}' to MIDI", song.metadata.title);
+        // MIDI has no repeat signs, so repeats are written out in full
+        let song = &*song.as_played();
 
         let mut data = Vec::new();
 
         // Write MIDI header
+        on_progress(0.0, "Writing header")?;
         self.write_header(&mut data)?;
 
         // Write single track
-        let track_data = self.build_track(song)?;
+        let track_data = self.build_track(song, &mut on_progress)?;
+        on_progress(100.0, "Finishing")?;
         self.write_track(&mut data, &track_data)?;
 
         tracing::info!("MIDI export complete: {} bytes", data.len());
@@ -72,18 +113,15 @@ impl MidiExporter {
         Ok(())
     }
 
-    fn build_track(&self, song: &Song) -> Result<Vec<u8>> {
+    fn build_track(
+        &self,
+        song: &Song,
+        on_progress: &mut impl FnMut(f32, &str) -> Result<()>,
+    ) -> Result<Vec<u8>> {
         let mut track = Vec::new();
 
         // Tempo meta event (at time 0)
-        let tempo_us = 60_000_000 / song.settings.tempo as u32;
-        self.write_var_length(&mut track, 0); // Delta time
-        track.push(0xFF); // Meta event
-        track.push(0x51); // Tempo
-        track.push(0x03); // Length
-        track.push((tempo_us >> 16) as u8);
-        track.push((tempo_us >> 8) as u8);
-        track.push(tempo_us as u8);
+        self.write_tempo(&mut track, 0, song.settings.tempo);
 
         // Time signature meta event
         let ts = &song.settings.time_signature;
@@ -92,12 +130,13 @@ impl MidiExporter {
         track.push(0x58); // Time signature
         track.push(0x04); // Length
         track.push(ts.numerator);
-        // Denominator as power of 2 (4 = 2^2, 8 = 2^3)
+        // Denominator as power of 2 (4 = 2^2, 8 = 2^3, 32 = 2^5)
         let denom_power = match ts.denominator {
             2 => 1,
             4 => 2,
             8 => 3,
             16 => 4,
+            32 => 5,
             _ => 2,
         };
         track.push(denom_power);
@@ -127,21 +166,56 @@ impl MidiExporter {
         self.write_var_length(&mut track, title.len() as u32);
         track.extend_from_slice(title);
 
+        // The file has one meter; voices in their own are noted as markers
+        for meter in &song.settings.voice_meters {
+            self.write_var_length(&mut track, 0);
+            track.push(0xFF);
+            track.push(0x06); // Marker
+            let text = format!("Voice {} in {}", meter.voice, meter.time_signature);
+            self.write_var_length(&mut track, text.len() as u32);
+            track.extend_from_slice(text.as_bytes());
+        }
+
+        // Each voice on its own channel, or all on channel 0
+        let channel_of = |note: &Note| if song.settings.channel_per_voice { note.voice & 0x0F } else { 0 };
+
+        // Program change for each channel's instrument (its track's, or the song's), so other
+        // players pick a similar sound
+        let mut channels: Vec<u8> = song.notes.iter().map(channel_of).chain([0]).collect();
+        channels.sort_unstable();
+        channels.dedup();
+        // The General MIDI drum channel keeps its kit
+        for channel in channels.into_iter().filter(|&c| c != GM_DRUM_CHANNEL) {
+            let instrument =
+                if song.settings.channel_per_voice { song.voice_instrument(channel) } else { song.settings.instrument };
+            if let Some(program) = instrument.gm_program() {
+                self.write_var_length(&mut track, 0);
+                track.push(0xC0 | channel); // Program change
+                track.push(program);
+            }
+        }
+
         // Build note events sorted by time
         let mut events: Vec<NoteEvent> = Vec::new();
 
-        for note in &song.notes {
+        // Notes with a probability or take lane are written as the first pass plays them
+        let ticks_per_measure = song.settings.time_signature.ticks_per_measure();
+        for note in song.notes.iter().filter(|note| chance::plays_on_pass(note, ticks_per_measure, 0)) {
             events.push(NoteEvent {
                 tick: note.start_tick,
                 is_on: true,
                 pitch: note.pitch,
-                velocity: note.velocity,
+                velocity: if self.accent_dynamics { song.accented_velocity(note) } else { note.velocity },
+                pan: note.pan,
+                channel: channel_of(note),
             });
             events.push(NoteEvent {
                 tick: note.end_tick(),
                 is_on: false,
                 pitch: note.pitch,
                 velocity: 0,
+                pan: note.pan,
+                channel: channel_of(note),
             });
         }
 
@@ -152,22 +226,57 @@ impl MidiExporter {
 
         // Write note events with delta times
         let mut last_tick = 0u32;
-        for event in events {
-            let delta = event.tick.saturating_sub(last_tick);
+        let mut current_pan: [Option<u8>; 16] = [None; 16];
+        let mut tempo_changes = song.settings.tempo_map.iter().peekable();
+        // Annotations are text events at their start tick (MIDI has no ranges)
+        let mut annotations = song.annotations.iter().peekable();
+        let total_events = events.len();
+        for (i, event) in events.into_iter().enumerate() {
+            if i % PROGRESS_INTERVAL == 0 {
+                on_progress(i as f32 / total_events as f32 * 100.0, "Writing notes")?;
+            }
+
+            // Tempo changes take effect before any note event at the same tick
+            while let Some(change) = tempo_changes.next_if(|c| c.tick <= event.tick) {
+                self.write_tempo(&mut track, change.tick.saturating_sub(last_tick), change.tempo);
+                last_tick = last_tick.max(change.tick);
+            }
+            while let Some(annotation) = annotations.next_if(|a| a.tick <= event.tick) {
+                self.write_text(&mut track, annotation.tick.saturating_sub(last_tick), &annotation.text);
+                last_tick = last_tick.max(annotation.tick);
+            }
+
+            let mut delta = event.tick.saturating_sub(last_tick);
+
+            // Emit a pan change (CC10) ahead of any note-on that needs a new position
+            let channel = event.channel as usize;
+            if event.is_on && current_pan[channel] != Some(event.pan) {
+                self.write_var_length(&mut track, delta);
+                track.push(0xB0 | event.channel); // Control change
+                track.push(0x0A); // Pan
+                track.push(event.pan);
+                current_pan[channel] = Some(event.pan);
+                delta = 0;
+            }
+
             self.write_var_length(&mut track, delta);
 
             if event.is_on {
-                track.push(0x90); // Note on, channel 0
+                track.push(0x90 | event.channel); // Note on
                 track.push(event.pitch);
                 track.push(event.velocity);
             } else {
-                track.push(0x80); // Note off, channel 0
+                track.push(0x80 | event.channel); // Note off
                 track.push(event.pitch);
                 track.push(0);
             }
 
             last_tick = event.tick;
         }
+        for annotation in annotations {
+            self.write_text(&mut track, annotation.tick.saturating_sub(last_tick), &annotation.text);
+            last_tick = last_tick.max(annotation.tick);
+        }
 
         // End of track meta event
         self.write_var_length(&mut track, 0);
@@ -179,6 +288,25 @@ impl MidiExporter {
         Ok(track)
     }
 
+    fn write_tempo(&self, track: &mut Vec<u8>, delta: u32, tempo: u16) {
+        let tempo_us = 60_000_000 / tempo as u32;
+        self.write_var_length(track, delta);
+        track.push(0xFF); // Meta event
+        track.push(0x51); // Tempo
+        track.push(0x03); // Length
+        track.push((tempo_us >> 16) as u8);
+        track.push((tempo_us >> 8) as u8);
+        track.push(tempo_us as u8);
+    }
+
+    fn write_text(&self, track: &mut Vec<u8>, delta: u32, text: &str) {
+        self.write_var_length(track, delta);
+        track.push(0xFF); // Meta event
+        track.push(0x01); // Text
+        self.write_var_length(track, text.len() as u32);
+        track.extend_from_slice(text.as_bytes());
+    }
+
     fn write_track(&self, data: &mut Vec<u8>, track_data: &[u8]) -> Result<()> {
         data.extend_from_slice(b"MTrk");
         data.extend_from_slice(&(track_data.len() as u32).to_be_bytes());
@@ -242,6 +370,163 @@ struct NoteEvent {
     is_on: bool,
     pitch: u8,
     velocity: u8,
+    pan: u8,
+    channel: u8,
+}
+
+/// Import a song from Standard MIDI File bytes
+///
+/// Notes from every track and channel are merged, with each track/channel
+/// pair that plays notes becoming its own voice in order of appearance.
+/// Ticks are rescaled to [`TICKS_PER_QUARTER`]. Only the first time
+/// signature, key signature, and program change (as the song's
+/// [`Instrument`]) are kept; every tempo event is, and text events become
+/// annotations.
+pub fn import_from_midi(bytes: &[u8]) -> Result<Song> {
+    let smf = Smf::parse(bytes).map_err(|e| MozartError::MidiError(format!("Invalid MIDI file: {}", e)))?;
+    let ppq = match smf.header.timing {
+        Timing::Metrical(ppq) if ppq.as_int() > 0 => ppq.as_int() as u64,
+        Timing::Metrical(_) => return Err(MozartError::MidiError("MIDI file has zero ticks per quarter".to_string())),
+        Timing::Timecode(..) => {
+            return Err(MozartError::MidiError("SMPTE-timed MIDI files are not supported".to_string()))
+        }
+    };
+    let scale_tick = |tick: u64| (tick * TICKS_PER_QUARTER as u64 / ppq).min(u32::MAX as u64) as u32;
+
+    let mut song = Song::new();
+    let mut title = None;
+    let mut time_signature = None;
+    let mut key = None;
+    let mut instrument = None;
+    let mut tempos: Vec<(u32, u16)> = Vec::new();
+    let mut texts: Vec<(u32, String)> = Vec::new();
+    let mut voices: HashMap<(usize, u8), u8> = HashMap::new();
+    let mut notes = Vec::new();
+
+    for (track_index, track) in smf.tracks.iter().enumerate() {
+        let mut tick = 0u64;
+        let mut pans = [Note::PAN_CENTER; 16];
+        // Sounding notes by (channel, pitch): start tick and velocity, oldest first
+        let mut held: HashMap<(u8, u8), Vec<(u32, u8)>> = HashMap::new();
+
+        for event in track {
+            tick += event.delta.as_int() as u64;
+            let at = scale_tick(tick);
+            match event.kind {
+                TrackEventKind::Midi { channel, message } => {
+                    let channel = channel.as_int();
+                    match message {
+                        MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => {
+                            held.entry((channel, key.as_int())).or_default().push((at, vel.as_int()));
+                        }
+                        MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                            let pitch = key.as_int();
+                            let Some(pending) = held.get_mut(&(channel, pitch)) else { continue };
+                            if pending.is_empty() {
+                                continue;
+                            }
+                            let (start, velocity) = pending.remove(0);
+                            let next_voice = voices.len().min(u8::MAX as usize) as u8;
+                            let voice = *voices.entry((track_index, channel)).or_insert(next_voice);
+                            let mut note = Note::with_voice(pitch, start, (at - start).max(1), velocity, voice);
+                            note.set_pan(pans[channel as usize]);
+                            notes.push(note);
+                        }
+                        MidiMessage::Controller { controller, value } if controller.as_int() == 0x0A => {
+                            pans[channel as usize] = value.as_int();
+                        }
+                        MidiMessage::ProgramChange { program } if instrument.is_none() && channel != GM_DRUM_CHANNEL => {
+                            instrument = Some(Instrument::from_gm_program(program.as_int()));
+                        }
+                        _ => {}
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(us_per_quarter)) => {
+                    let bpm = (60_000_000.0 / us_per_quarter.as_int().max(1) as f64).round();
+                    tempos.push((at, bpm.clamp(1.0, u16::MAX as f64) as u16));
+                }
+                TrackEventKind::Meta(MetaMessage::TimeSignature(numerator, denominator_power, _, _))
+                    if time_signature.is_none() && denominator_power < 8 =>
+                {
+                    time_signature = TimeSignature::new(numerator, 1 << denominator_power).ok();
+                }
+                TrackEventKind::Meta(MetaMessage::KeySignature(sharps, minor))
+                    if key.is_none() && (-7..=7).contains(&sharps) =>
+                {
+                    key = Some(key_from_midi(sharps, minor));
+                }
+                TrackEventKind::Meta(MetaMessage::Text(text)) => {
+                    texts.push((at, String::from_utf8_lossy(text).into_owned()));
+                }
+                TrackEventKind::Meta(MetaMessage::TrackName(name)) => {
+                    let name = String::from_utf8_lossy(name).trim().to_string();
+                    if title.is_none() && !name.is_empty() {
+                        title = Some(name);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Notes never released end at the last event of their track
+        let end = scale_tick(tick);
+        for ((channel, pitch), pending) in held {
+            for (start, velocity) in pending {
+                let next_voice = voices.len().min(u8::MAX as usize) as u8;
+                let voice = *voices.entry((track_index, channel)).or_insert(next_voice);
+                let mut note = Note::with_voice(pitch, start, (end - start).max(1), velocity, voice);
+                note.set_pan(pans[channel as usize]);
+                notes.push(note);
+            }
+        }
+    }
+
+    if let Some(title) = title {
+        song.metadata.title = title;
+    }
+    if let Some(ts) = time_signature {
+        song.set_time_signature(ts);
+    }
+    if let Some(key) = key {
+        song.set_key(key);
+    }
+    if let Some(instrument) = instrument {
+        song.set_instrument(instrument);
+    }
+    tempos.sort_by_key(|&(tick, _)| tick);
+    for (tick, tempo) in tempos {
+        if tick == 0 {
+            song.set_tempo(tempo);
+        } else {
+            song.add_tempo_change(tick, tempo);
+        }
+    }
+    song.add_notes(notes);
+    texts.sort_by_key(|(tick, _)| *tick);
+    for (tick, text) in texts {
+        // Blank text events are skipped
+        let _ = song.add_annotation(tick, None, &text);
+    }
+
+    tracing::info!("Imported MIDI: '{}' ({} notes)", song.metadata.title, song.notes.len());
+    Ok(song)
+}
+
+/// Import a song from a MIDI file
+pub fn import_from_midi_file(path: impl AsRef<Path>) -> Result<Song> {
+    let bytes = std::fs::read(path.as_ref())
+        .map_err(|e| MozartError::FileError(format!("Failed to read file: {}", e)))?;
+    import_from_midi(&bytes)
+}
+
+// Key signature (sharps, or negative flats) to a scale
+fn key_from_midi(sharps: i8, minor: bool) -> Scale {
+    let major_root = PitchClass::C.transpose((sharps * 7).rem_euclid(12));
+    if minor {
+        Scale::new(major_root.transpose(9), ScaleType::NaturalMinor)
+    } else {
+        Scale::new(major_root, ScaleType::Major)
+    }
 }
 
 /// Quick helper to export a song to MIDI
@@ -299,6 +584,220 @@ mod tests {
         assert_eq!(data, vec![0x83, 0x60]);
     }
 
+    #[test]
+    fn test_midi_export_pan_cc10() {
+        let mut song = Song::new();
+        let mut left = Note::new(60, 0, 480);
+        left.set_pan(0);
+        let mut right = Note::new(64, 480, 480);
+        right.set_pan(127);
+        song.add_note(left);
+        song.add_note(right);
+
+        let midi = export_to_midi(&song).unwrap();
+
+        let pan_values: Vec<u8> = midi
+            .windows(3)
+            .filter(|w| w[0] == 0xB0 && w[1] == 0x0A)
+            .map(|w| w[2])
+            .collect();
+        assert_eq!(pan_values, vec![0, 127]);
+    }
+
+    #[test]
+    fn test_midi_export_tempo_map() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(62, 960, 480));
+        song.add_tempo_change(960, 60);
+
+        let midi = export_to_midi(&song).unwrap();
+
+        let tempo_events = midi
+            .windows(3)
+            .filter(|w| w == &[0xFF, 0x51, 0x03])
+            .count();
+        assert_eq!(tempo_events, 2);
+    }
+
+    #[test]
+    fn test_midi_export_accent_dynamics() {
+        let mut song = Song::new();
+        song.add_note(Note::with_velocity(60, 0, 480, 100));
+        song.add_note(Note::with_velocity(62, 480, 480, 100));
+
+        let note_on_velocities = |midi: &[u8]| -> Vec<u8> {
+            midi.windows(3).filter(|w| w[0] == 0x90 && w[2] > 0).map(|w| w[2]).collect()
+        };
+        assert_eq!(note_on_velocities(&export_to_midi(&song).unwrap()), vec![100, 100]);
+
+        let baked = MidiExporter::new().with_accent_dynamics(true).export(&song).unwrap();
+        assert_eq!(note_on_velocities(&baked), vec![100, 70]);
+    }
+
+    #[test]
+    fn test_midi_export_first_pass() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note { probability: 0, ..Note::new(62, 480, 480) });
+
+        let imported = import_from_midi(&export_to_midi(&song).unwrap()).unwrap();
+        let pitches: Vec<u8> = imported.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![60]);
+    }
+
+    #[test]
+    fn test_midi_annotations_as_text_events() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_annotation(240, Some(960), "crescendo").unwrap();
+        song.add_annotation(1920, None, "breathe here").unwrap();
+
+        let midi = export_to_midi(&song).unwrap();
+        let smf = Smf::parse(&midi).unwrap();
+        let texts: Vec<&[u8]> = smf.tracks[0]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Meta(MetaMessage::Text(text)) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts, vec![b"crescendo".as_slice(), b"breathe here".as_slice()]);
+
+        let imported = import_from_midi(&midi).unwrap();
+        let annotations: Vec<(u32, &str)> = imported.annotations.iter().map(|a| (a.tick, a.text.as_str())).collect();
+        assert_eq!(annotations, vec![(240, "crescendo"), (1920, "breathe here")]);
+    }
+
+    #[test]
+    fn test_midi_export_voice_meters() {
+        let mut song = Song::new();
+        song.set_time_signature(TimeSignature::parse("4/4").unwrap());
+        song.set_voice_time_signature(1, Some(TimeSignature::parse("7/8").unwrap()));
+        song.add_note(Note::with_voice(48, 0, 240, 100, 1));
+
+        let midi = export_to_midi(&song).unwrap();
+        let smf = Smf::parse(&midi).unwrap();
+        let markers: Vec<&[u8]> = smf.tracks[0]
+            .iter()
+            .filter_map(|e| match e.kind {
+                TrackEventKind::Meta(MetaMessage::Marker(text)) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(markers, vec![b"Voice 1 in 7/8".as_slice()]);
+        assert_eq!(import_from_midi(&midi).unwrap().settings.time_signature.to_string(), "4/4");
+    }
+
+    #[test]
+    fn test_midi_export_channel_per_voice() {
+        let mut song = Song::new();
+        song.set_instrument(Instrument::Flute);
+        song.add_note(Note::with_voice(72, 0, 480, 100, 0));
+        song.add_note(Note::with_voice(48, 0, 480, 100, 1));
+        song.add_note(Note::with_voice(36, 480, 240, 100, 9));
+        let channels = |midi: &[u8]| -> Vec<(u8, bool)> {
+            Smf::parse(midi).unwrap().tracks[0]
+                .iter()
+                .filter_map(|e| match e.kind {
+                    TrackEventKind::Midi { channel, message: MidiMessage::NoteOn { .. } } => Some((channel.as_int(), false)),
+                    TrackEventKind::Midi { channel, message: MidiMessage::ProgramChange { .. } } => Some((channel.as_int(), true)),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(channels(&export_to_midi(&song).unwrap()), vec![(0, true), (0, false), (0, false), (0, false)]);
+
+        // Every melodic channel gets the instrument; the drum channel keeps its kit
+        song.settings.channel_per_voice = true;
+        let midi = export_to_midi(&song).unwrap();
+        assert_eq!(channels(&midi), vec![(0, true), (1, true), (0, false), (1, false), (9, false)]);
+        let imported = import_from_midi(&midi).unwrap();
+        let voices: Vec<(u8, u8)> = imported.notes.iter().map(|n| (n.pitch, n.voice)).collect();
+        assert_eq!(voices, vec![(72, 0), (48, 1), (36, 2)]);
+
+        // A track's own instrument goes on its channel (the synth has no program)
+        song.set_track_instrument(1, Some(Instrument::Synth)).unwrap();
+        assert_eq!(channels(&export_to_midi(&song).unwrap()), vec![(0, true), (0, false), (1, false), (9, false)]);
+    }
+
+    #[test]
+    fn test_midi_export_unfolds_repeats() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(62, 1920, 480));
+        let mut repeats = crate::repeats::RepeatStructure::default();
+        repeats.add_repeat(crate::repeats::Repeat::parse("1 x3").unwrap()).unwrap();
+        song.set_repeats(repeats).unwrap();
+
+        let imported = import_from_midi(&export_to_midi(&song).unwrap()).unwrap();
+        let starts: Vec<(u8, u32)> = imported.notes.iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(starts, vec![(60, 0), (60, 1920), (60, 3840), (62, 5760)]);
+    }
+
+    #[test]
+    fn test_midi_export_progress_and_cancel() {
+        let mut song = Song::new();
+        for i in 0..1500 {
+            song.add_note(Note::new(60, i * 120, 120));
+        }
+        let exporter = MidiExporter::new();
+
+        let mut reports = Vec::new();
+        let midi = exporter
+            .export_with_progress(&song, |percent, stage| {
+                reports.push((percent, stage.to_string()));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(midi, exporter.export(&song).unwrap());
+        assert_eq!(reports.first().unwrap().0, 0.0);
+        assert_eq!(reports.last().unwrap(), &(100.0, "Finishing".to_string()));
+        assert!(reports.iter().any(|(_, stage)| stage == "Writing notes"));
+
+        let result = exporter.export_with_progress(&song, |percent, _| {
+            if percent > 0.0 { Err(MozartError::Cancelled) } else { Ok(()) }
+        });
+        assert!(matches!(result, Err(MozartError::Cancelled)));
+    }
+
+    #[test]
+    fn test_midi_round_trip() {
+        let mut song = Song::with_title("Round Trip");
+        song.set_tempo(96);
+        song.set_time_signature(TimeSignature::parse("6/8").unwrap());
+        song.set_key(Scale::parse("D major").unwrap());
+        song.add_note(Note::with_velocity(64, 0, 240, 90));
+        let mut panned = Note::new(67, 240, 480);
+        panned.set_pan(20);
+        song.add_note(panned);
+        song.add_note(Note::new(69, 960, 480));
+        song.add_tempo_change(960, 72);
+        song.set_instrument(Instrument::Strings);
+
+        let imported = import_from_midi(&export_to_midi(&song).unwrap()).unwrap();
+        assert_eq!(imported.metadata.title, "Round Trip");
+        assert_eq!(imported.settings.tempo, 96);
+        assert_eq!(imported.tempo_at_tick(960), 72);
+        assert_eq!(imported.settings.time_signature.to_string(), "6/8");
+        assert_eq!(imported.settings.key, song.settings.key);
+        assert_eq!(imported.settings.instrument, Instrument::Strings);
+        assert_eq!(imported.notes, song.notes);
+    }
+
+    #[test]
+    fn test_midi_import_rescales_ticks() {
+        // Format 0, 96 ticks per quarter: one quarter note, left unterminated
+        let mut midi = b"MThd\0\0\0\x06\0\0\0\x01\0\x60MTrk".to_vec();
+        let track = [0x00, 0x90, 60, 100, 0x60, 0xFF, 0x2F, 0x00];
+        midi.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        midi.extend_from_slice(&track);
+
+        let imported = import_from_midi(&midi).unwrap();
+        assert_eq!(imported.notes, vec![Note::new(60, 0, 480)]);
+        assert!(import_from_midi(b"not midi").is_err());
+    }
+
     #[test]
     fn test_midi_export_multiple_notes() {
         let mut song = Song::new();
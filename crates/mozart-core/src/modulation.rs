@@ -0,0 +1,263 @@
+//! Modulation planning
+//!
+//! Suggests ways to get from one key to another, one chord per measure:
+//! - **Pivot chord**: a chord that belongs to both keys, heard first in the
+//!   old key and then reinterpreted in the new one (C major to G major
+//!   through Am, vi in C and ii in G)
+//! - **Common tone**: a chord of the new key that is foreign to the old one
+//!   but shares a tone with its tonic, which can be held across the change
+//! - **Direct**: straight to the new dominant, the fallback for keys with
+//!   nothing in common
+//!
+//! Every route ends on the new dominant seventh and tonic. A song has a
+//! single key, so applying a plan writes its chords as notes and marks
+//! where the new key arrives; it does not change the song's key.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::harmony::{chord_notes, key_chords, KeyChord};
+use crate::pitch::PitchClass;
+use crate::scale::Scale;
+use crate::song::Song;
+
+/// Pivot degrees in the new key, most convincing first: predominants lead
+/// straight on to the new dominant
+const PIVOT_PREFERENCE: [u8; 6] = [2, 4, 6, 3, 7, 5];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModulationKind {
+    PivotChord,
+    CommonTone,
+    Direct,
+}
+
+/// One chord of a route, filling a measure
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteChord {
+    /// 1-based
+    pub measure: u32,
+    /// Key the chord is heard in, e.g., "C Major"
+    pub key: String,
+    pub chord: KeyChord,
+    /// The pivot's numeral in the new key, e.g., "ii" for vi in C going to G
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pivot_numeral: Option<String>,
+}
+
+/// A way to modulate, ready to apply
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModulationPlan {
+    pub kind: ModulationKind,
+    pub from: String,
+    pub to: String,
+    pub description: String,
+    pub chords: Vec<RouteChord>,
+    /// Measures the route fills (inclusive)
+    pub start_measure: u32,
+    pub end_measure: u32,
+    /// Pitch classes (0 = C) shared by the old tonic and the first chord of the new key
+    pub common_tones: Vec<u8>,
+}
+
+// The dominant seventh of a key, with a raised leading tone in minor keys
+fn dominant_seventh(key: &Scale) -> KeyChord {
+    let mut chord = KeyChord::new(key, 5, true);
+    if let Some(root) = chord.symbol.strip_suffix("m7") {
+        chord.symbol = format!("{}7", root);
+        chord.numeral = "V7".to_string();
+        chord.pitch_classes[1] = (chord.pitch_classes[1] + 1) % 12;
+    }
+    chord
+}
+
+fn shared_tones(a: &KeyChord, b: &KeyChord) -> Vec<u8> {
+    a.pitch_classes.iter().copied().filter(|&pc| b.contains(pc)).collect()
+}
+
+/// Routes from one key to another, starting at a measure (1-based), most
+/// convincing first; pivot-chord routes, then common-tone, then direct
+pub fn plan_modulation(from: &Scale, to: &Scale, start_measure: u32) -> Result<Vec<ModulationPlan>> {
+    if from == to {
+        return Err(MozartError::HarmonyError(format!("Already in {}", from)));
+    }
+    if start_measure == 0 {
+        return Err(MozartError::HarmonyError("Measures start at 1".to_string()));
+    }
+
+    let source = key_chords(from, false);
+    let target = key_chords(to, false);
+    let source_tonic = &source[0];
+    let plan = |kind, description: String, route: Vec<(Scale, KeyChord, Option<String>)>, common_tones| {
+        let chords: Vec<RouteChord> = route
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, chord, pivot_numeral))| RouteChord {
+                measure: start_measure + i as u32,
+                key: key.to_string(),
+                chord,
+                pivot_numeral,
+            })
+            .collect();
+        ModulationPlan {
+            kind,
+            from: from.to_string(),
+            to: to.to_string(),
+            description,
+            start_measure,
+            end_measure: start_measure + chords.len() as u32 - 1,
+            chords,
+            common_tones,
+        }
+    };
+    // Every route closes on V7 and I of the new key
+    let cadence = |route: &mut Vec<(Scale, KeyChord, Option<String>)>, last_degree: u8| {
+        if last_degree != 5 {
+            route.push((*to, dominant_seventh(to), None));
+        }
+        route.push((*to, target[0].clone(), None));
+    };
+
+    let mut plans = Vec::new();
+
+    // Chords with the same tones in both keys, except the new tonic itself
+    for &degree in &PIVOT_PREFERENCE {
+        let in_target = &target[degree as usize - 1];
+        let Some(in_source) = source.iter().find(|c| c.pitch_classes == in_target.pitch_classes) else {
+            continue;
+        };
+        let mut route = vec![(*from, source_tonic.clone(), None)];
+        if in_source.degree != 1 {
+            route.push((*from, in_source.clone(), Some(in_target.numeral.clone())));
+        } else {
+            route[0].2 = Some(in_target.numeral.clone());
+        }
+        cadence(&mut route, degree);
+        let description = format!(
+            "{} is {} in {} and {} in {}",
+            in_source.symbol, in_source.numeral, from, in_target.numeral, to
+        );
+        plans.push(plan(ModulationKind::PivotChord, description, route, Vec::new()));
+    }
+
+    // Primary chords of the new key that are foreign to the old one but keep a tone of its tonic
+    let mut common: Vec<(&KeyChord, Vec<u8>)> = [1u8, 4, 5, 6]
+        .iter()
+        .map(|&degree| &target[degree as usize - 1])
+        .filter(|chord| !source.iter().any(|c| c.pitch_classes == chord.pitch_classes))
+        .map(|chord| (chord, shared_tones(source_tonic, chord)))
+        .filter(|(_, tones)| !tones.is_empty())
+        .collect();
+    common.sort_by_key(|(_, tones)| std::cmp::Reverse(tones.len()));
+    for (chord, tones) in common {
+        let mut route = vec![(*from, source_tonic.clone(), None), (*to, chord.clone(), None)];
+        cadence(&mut route, chord.degree);
+        let held: Vec<String> = tones.iter().map(|&pc| PitchClass::new(pc).to_string()).collect();
+        let description = format!(
+            "Hold {} from {} into {}, {} in {}",
+            held.join(" and "),
+            source_tonic.symbol,
+            chord.symbol,
+            chord.numeral,
+            to
+        );
+        plans.push(plan(ModulationKind::CommonTone, description, route, tones));
+    }
+
+    // Unless a pivot on the old tonic already takes this path
+    let mut route = vec![(*from, source_tonic.clone(), None)];
+    cadence(&mut route, 1);
+    let direct = plan(ModulationKind::Direct, format!("Move straight to the dominant of {}", to), route, Vec::new());
+    let same_chords = |p: &ModulationPlan| p.chords.iter().map(|c| &c.chord).eq(direct.chords.iter().map(|c| &c.chord));
+    if !plans.iter().any(same_chords) {
+        plans.push(direct);
+    }
+
+    tracing::debug!("Planned {} modulations from {} to {}", plans.len(), from, to);
+    Ok(plans)
+}
+
+/// Write a plan's chords into the song (root position, one per measure) and
+/// mark where the new key arrives; returns how many notes were added
+pub fn apply_modulation(song: &mut Song, plan: &ModulationPlan) -> Result<usize> {
+    let Some(arrival) = plan.chords.last() else {
+        return Err(MozartError::HarmonyError("The plan has no chords".to_string()));
+    };
+
+    let mut notes = Vec::new();
+    for route_chord in &plan.chords {
+        let start = song.measure_start_tick(route_chord.measure)?;
+        let end = song.measure_start_tick(route_chord.measure + 1)?;
+        notes.extend(chord_notes(&route_chord.chord.pitch_classes, start, end - start));
+    }
+
+    let count = notes.len();
+    let arrival_tick = song.measure_start_tick(arrival.measure)?;
+    song.add_notes(notes);
+    song.add_marker(plan.to.clone(), arrival_tick);
+    tracing::info!("Applied modulation from {} to {} ({} notes)", plan.from, plan.to, count);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::ScaleType;
+    use pretty_assertions::assert_eq;
+
+    fn symbols(plan: &ModulationPlan) -> Vec<&str> {
+        plan.chords.iter().map(|c| c.chord.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pivot_chords() {
+        let g = Scale::new(PitchClass::G, ScaleType::Major);
+        let plans = plan_modulation(&Scale::c_major(), &g, 5).unwrap();
+
+        // Am is vi in C and ii in G
+        let first = &plans[0];
+        assert_eq!(first.kind, ModulationKind::PivotChord);
+        assert_eq!(symbols(first), vec!["C", "Am", "D7", "G"]);
+        assert_eq!(first.chords[1].pivot_numeral.as_deref(), Some("ii"));
+        assert_eq!((first.start_measure, first.end_measure), (5, 8));
+        assert_eq!(first.chords[3].key, "G Major");
+
+        // C itself is IV in G
+        let tonic_pivot = plans.iter().find(|p| p.chords[0].pivot_numeral.is_some()).unwrap();
+        assert_eq!(symbols(tonic_pivot), vec!["C", "D7", "G"]);
+        // ...which is also the direct route, so that is not offered twice
+        assert!(plans.iter().all(|p| p.kind == ModulationKind::PivotChord));
+        assert!(plan_modulation(&g, &g, 1).is_err());
+    }
+
+    #[test]
+    fn test_common_tone_and_minor_keys() {
+        // C major to E major: no chord in common, but E major keeps the E of C major
+        let e = Scale::new(PitchClass::E, ScaleType::Major);
+        let plans = plan_modulation(&Scale::c_major(), &e, 1).unwrap();
+        let common = plans.iter().find(|p| p.kind == ModulationKind::CommonTone).unwrap();
+        assert_eq!(symbols(common), vec!["C", "E", "B7", "E"]);
+        assert_eq!(common.common_tones, vec![4]);
+
+        // The dominant of a minor key has a raised leading tone
+        let a_minor = Scale::a_minor();
+        let pivot = plan_modulation(&Scale::c_major(), &a_minor, 1).unwrap().remove(0);
+        assert_eq!(symbols(&pivot), vec!["C", "Bdim", "E7", "Am"]);
+        assert_eq!(pivot.chords[2].chord.pitch_classes, vec![4, 8, 11, 2]);
+    }
+
+    #[test]
+    fn test_apply_modulation() {
+        let mut song = Song::new();
+        let g = Scale::new(PitchClass::G, ScaleType::Major);
+        let plan = plan_modulation(&Scale::c_major(), &g, 2).unwrap().remove(0);
+
+        assert_eq!(apply_modulation(&mut song, &plan).unwrap(), 13);
+        let first: Vec<u8> = song.notes.iter().filter(|n| n.start_tick == 1920).map(|n| n.pitch).collect();
+        assert_eq!(first, vec![48, 52, 55]);
+        assert!(song.notes.iter().all(|n| n.duration_ticks == 1920 && n.velocity == 80));
+        assert_eq!(song.marker_tick("G Major"), Some(1920 * 4));
+        // The song keeps its key
+        assert_eq!(song.settings.key, Scale::c_major());
+    }
+}
@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use crate::chance::Take;
+use crate::color::ColorLabel;
 use crate::error::{MozartError, Result};
 use crate::pitch::Pitch;
 use crate::TICKS_PER_QUARTER;
@@ -19,6 +21,21 @@ pub enum NoteValue {
 }
 
 impl NoteValue {
+    /// Every value, longest first
+    pub const ALL: [NoteValue; 5] =
+        [NoteValue::Whole, NoteValue::Half, NoteValue::Quarter, NoteValue::Eighth, NoteValue::Sixteenth];
+
+    /// Names [`NoteValue::parse`] accepts, in any letter case; the short name first
+    pub fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            NoteValue::Whole => &["w", "whole", "1"],
+            NoteValue::Half => &["h", "half", "2"],
+            NoteValue::Quarter => &["q", "quarter", "4"],
+            NoteValue::Eighth => &["e", "eighth", "8"],
+            NoteValue::Sixteenth => &["s", "sixteenth", "16"],
+        }
+    }
+
     /// Get duration in ticks
     pub fn ticks(&self) -> u32 {
         match self {
@@ -32,28 +49,18 @@ impl NoteValue {
 
     /// Parse from string (w, h, q, e, s)
     pub fn parse(s: &str) -> Result<Self> {
-        match s.to_lowercase().as_str() {
-            "w" | "whole" | "1" => Ok(NoteValue::Whole),
-            "h" | "half" | "2" => Ok(NoteValue::Half),
-            "q" | "quarter" | "4" => Ok(NoteValue::Quarter),
-            "e" | "eighth" | "8" => Ok(NoteValue::Eighth),
-            "s" | "sixteenth" | "16" => Ok(NoteValue::Sixteenth),
-            _ => Err(MozartError::InvalidDuration(format!(
+        NoteValue::ALL
+            .into_iter()
+            .find(|value| value.aliases().iter().any(|alias| alias.eq_ignore_ascii_case(s)))
+            .ok_or_else(|| MozartError::InvalidDuration(format!(
                 "Unknown note value: {}",
                 s
-            ))),
-        }
+            )))
     }
 
     /// Get short name
     pub fn short_name(&self) -> &'static str {
-        match self {
-            NoteValue::Whole => "w",
-            NoteValue::Half => "h",
-            NoteValue::Quarter => "q",
-            NoteValue::Eighth => "e",
-            NoteValue::Sixteenth => "s",
-        }
+        self.aliases()[0]
     }
 }
 
@@ -105,13 +112,7 @@ impl NoteDuration {
     /// Create from raw ticks (finds closest match)
     pub fn from_ticks(ticks: u32) -> Self {
         // Check dotted values first (they're between regular values)
-        let values = [
-            NoteValue::Whole,
-            NoteValue::Half,
-            NoteValue::Quarter,
-            NoteValue::Eighth,
-            NoteValue::Sixteenth,
-        ];
+        let values = NoteValue::ALL;
 
         let mut best_match = NoteDuration::new(NoteValue::Quarter);
         let mut best_diff = u32::MAX;
@@ -162,9 +163,35 @@ pub struct Note {
     /// Voice/layer (0=main melody, 1+=harmony voices)
     #[serde(default)]
     pub voice: u8,
+    /// Stereo pan (0=hard left, 64=center, 127=hard right), exported as MIDI CC10
+    #[serde(default = "default_pan")]
+    pub pan: u8,
+    /// Chance of sounding on each pass, in percent (experimental; see [`crate::chance`])
+    #[serde(default = "default_probability")]
+    pub probability: u8,
+    /// Alternate take lane; a pass plays one lane per measure (experimental)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub take: Option<Take>,
+    /// Color label shown in the piano roll; playback ignores it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorLabel>,
+}
+
+fn default_pan() -> u8 {
+    Note::PAN_CENTER
+}
+
+fn default_probability() -> u8 {
+    Note::ALWAYS_PLAYS
 }
 
 impl Note {
+    /// Center pan position
+    pub const PAN_CENTER: u8 = 64;
+
+    /// Probability of a note that plays on every pass
+    pub const ALWAYS_PLAYS: u8 = 100;
+
     /// Create a new note
     pub fn new(pitch: u8, start_tick: u32, duration_ticks: u32) -> Self {
         Note {
@@ -173,6 +200,10 @@ impl Note {
             duration_ticks,
             velocity: 100,
             voice: 0,
+            pan: Note::PAN_CENTER,
+            probability: Note::ALWAYS_PLAYS,
+            take: None,
+            color: None,
         }
     }
 
@@ -184,6 +215,10 @@ impl Note {
             duration_ticks,
             velocity: velocity.min(127),
             voice: 0,
+            pan: Note::PAN_CENTER,
+            probability: Note::ALWAYS_PLAYS,
+            take: None,
+            color: None,
         }
     }
 
@@ -195,9 +230,38 @@ impl Note {
             duration_ticks,
             velocity: velocity.min(127),
             voice,
+            pan: Note::PAN_CENTER,
+            probability: Note::ALWAYS_PLAYS,
+            take: None,
+            color: None,
         }
     }
 
+    /// Set the stereo pan (0-127, 64 = center)
+    pub fn set_pan(&mut self, pan: u8) {
+        self.pan = pan.min(127);
+    }
+
+    /// Set the chance of sounding on each pass (0-100%)
+    pub fn set_probability(&mut self, probability: u8) {
+        self.probability = probability.min(Note::ALWAYS_PLAYS);
+    }
+
+    /// Get constant-power (left, right) gains for this note's pan
+    ///
+    /// Center pan yields equal gains of ~0.707 so the summed power stays constant
+    /// as the note moves across the stereo field.
+    pub fn pan_gains(&self) -> (f32, f32) {
+        // Map 0-127 onto 0..=1, treating 64 as exact center
+        let position = if self.pan <= Note::PAN_CENTER {
+            self.pan as f32 / (2.0 * Note::PAN_CENTER as f32)
+        } else {
+            0.5 + (self.pan - Note::PAN_CENTER) as f32 / (2.0 * 63.0)
+        };
+        let angle = position.min(1.0) * std::f32::consts::FRAC_PI_2;
+        (angle.cos(), angle.sin())
+    }
+
     /// Create from Pitch and NoteDuration
     pub fn from_pitch(pitch: Pitch, start_tick: u32, duration: NoteDuration) -> Self {
         Note {
@@ -206,6 +270,10 @@ impl Note {
             duration_ticks: duration.ticks(),
             velocity: 100,
             voice: 0,
+            pan: Note::PAN_CENTER,
+            probability: Note::ALWAYS_PLAYS,
+            take: None,
+            color: None,
         }
     }
 
@@ -251,18 +319,7 @@ impl Note {
 
         let pitch = Pitch::parse(pitch_str)?;
 
-        let duration = if duration_str.is_empty() {
-            NoteDuration::new(NoteValue::Quarter) // Default to quarter note
-        } else {
-            let dotted = duration_str.ends_with('.');
-            let value_str = if dotted {
-                &duration_str[..duration_str.len() - 1]
-            } else {
-                duration_str
-            };
-            let value = NoteValue::parse(value_str)?;
-            NoteDuration { value, dotted }
-        };
+        let duration = parse_duration(duration_str)?;
 
         tracing::debug!(
             "Parsed note: pitch={}, duration={}, ticks={}",
@@ -288,9 +345,39 @@ impl fmt::Display for Note {
     }
 }
 
+/// Pitch of rhythm hits ("xq") when a song doesn't set its own: middle C
+pub const DEFAULT_RHYTHM_PITCH: u8 = 60;
+
+/// Starts a rest ("Rq"), in either letter case
+pub const REST_PREFIX: char = 'R';
+
+/// Starts a rhythm hit ("xq"), in either letter case
+pub const RHYTHM_HIT_PREFIX: char = 'x';
+
+/// Follows a note value to make it dotted ("q.")
+pub const DOT: char = '.';
+
+/// Value of a token written without a duration ("C4", "R", "x")
+pub const DEFAULT_NOTE_VALUE: NoteValue = NoteValue::Quarter;
+
+// The rest of a token after a one-letter prefix, in either case
+fn strip_letter(token: &str, prefix: char) -> Option<&str> {
+    token.strip_prefix(prefix.to_ascii_uppercase()).or_else(|| token.strip_prefix(prefix.to_ascii_lowercase()))
+}
+
 /// Parse a melody string into notes
 /// Format: "C4q D4q E4q F4q" (space-separated)
+///
+/// Rhythm hits ("xq xe xe Rq xh") play at [`DEFAULT_RHYTHM_PITCH`].
 pub fn parse_melody(s: &str) -> Result<Vec<Note>> {
+    parse_melody_with_rhythm_pitch(s, DEFAULT_RHYTHM_PITCH)
+}
+
+/// Parse a melody string into notes, with rhythm hits ("xq") at `rhythm_pitch`
+///
+/// Hits mix freely with pitched notes, so rhythm exercises and claps can
+/// be written without making up pitches.
+pub fn parse_melody_with_rhythm_pitch(s: &str, rhythm_pitch: u8) -> Result<Vec<Note>> {
     tracing::info!("Parsing melody: {}", s);
     let mut notes = Vec::new();
     let mut current_tick: u32 = 0;
@@ -301,26 +388,17 @@ pub fn parse_melody(s: &str) -> Result<Vec<Note>> {
         }
 
         // Handle rest (R or r followed by duration)
-        if token.to_uppercase().starts_with('R') {
-            let duration_str = &token[1..];
-            let duration = if duration_str.is_empty() {
-                NoteDuration::new(NoteValue::Quarter)
-            } else {
-                let dotted = duration_str.ends_with('.');
-                let value_str = if dotted {
-                    &duration_str[..duration_str.len() - 1]
-                } else {
-                    duration_str
-                };
-                let value = NoteValue::parse(value_str)?;
-                NoteDuration { value, dotted }
-            };
+        if let Some(duration) = strip_letter(token, REST_PREFIX) {
+            let duration = parse_duration(duration)?;
             current_tick += duration.ticks();
             tracing::trace!("Rest: duration={}, new_tick={}", duration, current_tick);
             continue;
         }
 
-        let note = Note::parse(token, current_tick)?;
+        let note = match rhythm_hit(token) {
+            Some(duration) => Note::new(rhythm_pitch.min(127), current_tick, duration?.ticks()),
+            None => Note::parse(token, current_tick)?,
+        };
         current_tick = note.end_tick();
         notes.push(note);
     }
@@ -329,6 +407,60 @@ pub fn parse_melody(s: &str) -> Result<Vec<Note>> {
     Ok(notes)
 }
 
+/// Whether a melody is only rhythm hits and rests (so it says nothing about the key)
+pub fn is_rhythm_only(s: &str) -> bool {
+    s.split_whitespace().all(|token| strip_letter(token, REST_PREFIX).is_some() || rhythm_hit(token).is_some())
+}
+
+/// The notes of a melody written with a pitch, where they fall in it; rhythm
+/// hits are left out, so this is what to detect the key from
+pub fn pitched_notes(s: &str) -> Result<Vec<Note>> {
+    let hits = s.split_whitespace().filter(|token| strip_letter(token, REST_PREFIX).is_none()).map(|token| rhythm_hit(token).is_some());
+    let notes = parse_melody_with_rhythm_pitch(s, DEFAULT_RHYTHM_PITCH)?;
+    Ok(notes.into_iter().zip(hits).filter(|(_, hit)| !hit).map(|(note, _)| note).collect())
+}
+
+/// One token of text notation: a note, a rhythm hit, or a rest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MelodyToken {
+    /// MIDI pitch ([`DEFAULT_RHYTHM_PITCH`] for a rhythm hit), or None for a rest
+    pub pitch: Option<u8>,
+    pub duration_ticks: u32,
+}
+
+/// Parse one token of a melody ("C4q", "F#5h.", "xe", "Rq"), e.g., to audition it as it's typed
+pub fn parse_melody_token(token: &str) -> Result<MelodyToken> {
+    let token = token.trim();
+    if token.contains(char::is_whitespace) {
+        return Err(MozartError::ParseError(format!("Expected one note, got '{}'", token)));
+    }
+    if let Some(duration) = strip_letter(token, REST_PREFIX) {
+        let duration = parse_duration(duration)?;
+        return Ok(MelodyToken { pitch: None, duration_ticks: duration.ticks() });
+    }
+    if let Some(duration) = rhythm_hit(token) {
+        return Ok(MelodyToken { pitch: Some(DEFAULT_RHYTHM_PITCH), duration_ticks: duration?.ticks() });
+    }
+    let note = Note::parse(token, 0)?;
+    Ok(MelodyToken { pitch: Some(note.pitch), duration_ticks: note.duration_ticks })
+}
+
+// Duration of a rhythm hit ("x", "xq", "Xe."), or None for another kind of token
+fn rhythm_hit(token: &str) -> Option<Result<NoteDuration>> {
+    strip_letter(token, RHYTHM_HIT_PREFIX).map(parse_duration)
+}
+
+/// Duration suffix of a token ("q", "h.", or "" for a quarter)
+fn parse_duration(s: &str) -> Result<NoteDuration> {
+    if s.is_empty() {
+        return Ok(NoteDuration::new(DEFAULT_NOTE_VALUE));
+    }
+    match s.strip_suffix(DOT) {
+        Some(value) => Ok(NoteDuration::dotted(NoteValue::parse(value)?)),
+        None => Ok(NoteDuration::new(NoteValue::parse(s)?)),
+    }
+}
+
 /// Format notes as melody string
 pub fn format_melody(notes: &[Note]) -> String {
     notes.iter().map(|n| n.to_text()).collect::<Vec<_>>().join(" ")
@@ -372,6 +504,26 @@ mod tests {
         assert_eq!(note.duration_ticks, 720);
     }
 
+    #[test]
+    fn test_pan_gains_constant_power() {
+        let mut note = Note::new(60, 0, 480);
+        assert_eq!(note.pan, Note::PAN_CENTER);
+
+        let (left, right) = note.pan_gains();
+        assert!((left - right).abs() < 0.001);
+        assert!((left * left + right * right - 1.0).abs() < 0.001);
+
+        note.set_pan(0);
+        let (left, right) = note.pan_gains();
+        assert!((left - 1.0).abs() < 0.001);
+        assert!(right.abs() < 0.001);
+
+        note.set_pan(127);
+        let (left, right) = note.pan_gains();
+        assert!(left.abs() < 0.001);
+        assert!((right - 1.0).abs() < 0.001);
+    }
+
     #[test]
     fn test_parse_melody() {
         let melody = parse_melody("C4q D4q E4q").unwrap();
@@ -393,4 +545,33 @@ mod tests {
         assert_eq!(melody[1].pitch, 64);
         assert_eq!(melody[1].start_tick, 960); // After quarter note + quarter rest
     }
+
+    #[test]
+    fn test_parse_rhythm() {
+        let rhythm = parse_melody("xq xe Xe Rq x xh.").unwrap();
+        let hits: Vec<(u8, u32, u32)> = rhythm.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(hits, vec![(60, 0, 480), (60, 480, 240), (60, 720, 240), (60, 1440, 480), (60, 1920, 1440)]);
+
+        // Hits take the configured pitch; pitched notes keep theirs
+        let clap = parse_melody_with_rhythm_pitch("xe G4e xq", 39).unwrap();
+        assert_eq!(clap.iter().map(|n| n.pitch).collect::<Vec<_>>(), vec![39, 67, 39]);
+        assert!(parse_melody("xz").is_err());
+        assert!(is_rhythm_only("xq Re xe"));
+        assert!(!is_rhythm_only("xq C4q"));
+
+        let pitched = pitched_notes("xq C4q Re xe D4h").unwrap();
+        assert_eq!(pitched.iter().map(|n| (n.pitch, n.start_tick)).collect::<Vec<_>>(), vec![(60, 480), (62, 1440)]);
+        assert!(pitched_notes("xq Re xe").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_melody_token() {
+        assert_eq!(parse_melody_token("F#5h.").unwrap(), MelodyToken { pitch: Some(78), duration_ticks: 1440 });
+        assert_eq!(parse_melody_token("re").unwrap(), MelodyToken { pitch: None, duration_ticks: 240 });
+        assert_eq!(parse_melody_token("C4").unwrap().duration_ticks, 480);
+        assert_eq!(parse_melody_token("xe").unwrap(), MelodyToken { pitch: Some(DEFAULT_RHYTHM_PITCH), duration_ticks: 240 });
+        // Half-typed and multiple tokens are errors
+        assert!(parse_melody_token("C").is_err());
+        assert!(parse_melody_token("C4q D4q").is_err());
+    }
 }
@@ -0,0 +1,202 @@
+//! Compact binary encoding for bulk note transfer
+//!
+//! JSON costs roughly 90 bytes and a string parse per note, which dominates
+//! fetching a large imported song. This fixed-width little-endian layout is
+//! 15 bytes per note (19 with an index) and decodes with a `DataView` in the
+//! browser. Small payloads gain little and JSON stays readable in devtools,
+//! so hosts should only switch above [`PACKED_MIN_NOTES`]. Run the
+//! `note_encoding` bench for numbers.
+//!
+//! Layout: an 8-byte header (`"MZ"`, version, flags, note count as u32)
+//! followed by one record per note: `[index: u32]` when the indexed flag is
+//! set, then `start_tick: u32`, `duration_ticks: u32`, `pitch`, `velocity`,
+//! `voice`, `pan`, `probability`, `take` (0 for none, 1 for A, 2 for B), and
+//! `color` (0 for none, else 1 + its place in [`ColorLabel::ALL`]; one byte
+//! each).
+
+use crate::chance::Take;
+use crate::color::ColorLabel;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::song::IndexedNote;
+
+/// Note count below which hosts should keep using JSON
+pub const PACKED_MIN_NOTES: usize = 500;
+
+/// Format version written to the header
+pub const PACKED_VERSION: u8 = 3;
+
+const MAGIC: [u8; 2] = *b"MZ";
+const HEADER_LEN: usize = 8;
+const NOTE_LEN: usize = 15;
+const FLAG_INDEXED: u8 = 1;
+
+/// Encode notes in order
+pub fn encode_notes(notes: &[Note]) -> Vec<u8> {
+    let mut out = header(notes.len(), 0);
+    for note in notes {
+        push_note(&mut out, note);
+    }
+    out
+}
+
+/// Encode notes with their indices (e.g., a viewport window)
+pub fn encode_note_window(notes: &[IndexedNote]) -> Vec<u8> {
+    let mut out = header(notes.len(), FLAG_INDEXED);
+    for indexed in notes {
+        out.extend_from_slice(&(indexed.index as u32).to_le_bytes());
+        push_note(&mut out, &indexed.note);
+    }
+    out
+}
+
+/// Decode notes written by [`encode_notes`] or [`encode_note_window`]
+///
+/// Indices are returned when the payload carries them.
+pub fn decode_notes(bytes: &[u8]) -> Result<Vec<(Option<usize>, Note)>> {
+    if bytes.len() < HEADER_LEN || bytes[..2] != MAGIC {
+        return Err(MozartError::ParseError("Not a packed note payload".to_string()));
+    }
+    if bytes[2] != PACKED_VERSION {
+        return Err(MozartError::ParseError(format!("Unsupported packed note version {}", bytes[2])));
+    }
+    let indexed = bytes[3] & FLAG_INDEXED != 0;
+    let count = read_u32(bytes, 4) as usize;
+    let record_len = if indexed { NOTE_LEN + 4 } else { NOTE_LEN };
+    if bytes.len() != HEADER_LEN + count * record_len {
+        return Err(MozartError::ParseError(format!(
+            "Packed payload of {} bytes does not hold {} notes",
+            bytes.len(),
+            count
+        )));
+    }
+
+    let notes = bytes[HEADER_LEN..]
+        .chunks_exact(record_len)
+        .map(|record| {
+            let (index, record) = if indexed {
+                (Some(read_u32(record, 0) as usize), &record[4..])
+            } else {
+                (None, record)
+            };
+            let note = Note {
+                start_tick: read_u32(record, 0),
+                duration_ticks: read_u32(record, 4),
+                pitch: record[8],
+                velocity: record[9],
+                voice: record[10],
+                pan: record[11],
+                probability: record[12],
+                take: match record[13] {
+                    1 => Some(Take::A),
+                    2 => Some(Take::B),
+                    _ => None,
+                },
+                color: (record[14] as usize).checked_sub(1).and_then(|i| ColorLabel::ALL.get(i).copied()),
+            };
+            (index, note)
+        })
+        .collect();
+    Ok(notes)
+}
+
+fn header(count: usize, flags: u8) -> Vec<u8> {
+    let record_len = if flags & FLAG_INDEXED != 0 { NOTE_LEN + 4 } else { NOTE_LEN };
+    let mut out = Vec::with_capacity(HEADER_LEN + count * record_len);
+    out.extend_from_slice(&MAGIC);
+    out.push(PACKED_VERSION);
+    out.push(flags);
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+    out
+}
+
+fn push_note(out: &mut Vec<u8>, note: &Note) {
+    out.extend_from_slice(&note.start_tick.to_le_bytes());
+    out.extend_from_slice(&note.duration_ticks.to_le_bytes());
+    let take = match note.take {
+        None => 0,
+        Some(Take::A) => 1,
+        Some(Take::B) => 2,
+    };
+    let color = note.color.map_or(0, |color| ColorLabel::ALL.iter().position(|&c| c == color).unwrap_or(0) as u8 + 1);
+    out.extend_from_slice(&[note.pitch, note.velocity, note.voice, note.pan, note.probability, take, color]);
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn notes() -> Vec<Note> {
+        vec![
+            Note::new(60, 0, 480),
+            Note { probability: 40, take: Some(Take::B), ..Note::with_voice(64, 480, 960, 90, 1) },
+            Note { color: Some(ColorLabel::Gray), ..Note::new(67, 960, 480) },
+            Note::with_velocity(127, u32::MAX - 1, 1, 1),
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = encode_notes(&notes());
+        assert_eq!(bytes.len(), HEADER_LEN + 4 * NOTE_LEN);
+
+        let decoded: Vec<Note> = decode_notes(&bytes).unwrap().into_iter().map(|(_, n)| n).collect();
+        assert_eq!(decoded, notes());
+    }
+
+    #[test]
+    fn test_window_round_trip() {
+        let window: Vec<IndexedNote> = notes()
+            .into_iter()
+            .enumerate()
+            .map(|(i, note)| IndexedNote { index: i * 10, note })
+            .collect();
+        let decoded = decode_notes(&encode_note_window(&window)).unwrap();
+        assert_eq!(decoded[1], (Some(10), window[1].note.clone()));
+    }
+
+    #[test]
+    fn test_rejects_bad_payloads() {
+        let bytes = encode_notes(&notes());
+        assert!(decode_notes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_notes(b"{\"notes\": []}").is_err());
+
+        let mut future = bytes.clone();
+        future[2] = PACKED_VERSION + 1;
+        assert!(decode_notes(&future).is_err());
+    }
+
+    // The web decoder (web/src/wasm/packed.ts) keeps its own copy of the format
+    const DECODER: &str = include_str!("../../../web/src/wasm/packed.ts");
+
+    // Value of `const <name> = ...`, or the items of an array literal
+    fn ts_const(name: &str) -> Vec<String> {
+        let start = DECODER.find(&format!("const {}", name)).unwrap_or_else(|| panic!("packed.ts has no {}", name));
+        let value = DECODER[start..].split_once(" = ").unwrap().1;
+        let value = match value.strip_prefix('[') {
+            Some(items) => &items[..items.find(']').unwrap()],
+            None => &value[..value.find('\n').unwrap()],
+        };
+        value.split(',').map(|item| item.trim().trim_matches('\'').to_string()).filter(|item| !item.is_empty()).collect()
+    }
+
+    #[test]
+    fn test_web_decoder_matches() {
+        assert_eq!(ts_const("PACKED_VERSION"), vec![PACKED_VERSION.to_string()]);
+        assert_eq!(ts_const("PACKED_MIN_NOTES"), vec![PACKED_MIN_NOTES.to_string()]);
+        assert_eq!(ts_const("HEADER_LEN"), vec![HEADER_LEN.to_string()]);
+        assert_eq!(ts_const("FLAG_INDEXED"), vec![FLAG_INDEXED.to_string()]);
+        assert!(DECODER.contains(&format!("bytes[0] !== {:#x} || bytes[1] !== {:#x}", MAGIC[0], MAGIC[1])));
+        assert!(DECODER.contains(&format!("indexed ? {} : {}", NOTE_LEN + 4, NOTE_LEN)));
+
+        let takes = [Take::A, Take::B].iter().map(|take| serde_json::to_value(take).unwrap().as_str().unwrap().to_string());
+        assert_eq!(ts_const("TAKES"), std::iter::once("undefined".to_string()).chain(takes).collect::<Vec<_>>());
+        let colors = ColorLabel::ALL.iter().map(|color| color.name().to_string());
+        assert_eq!(ts_const("COLORS"), std::iter::once("undefined".to_string()).chain(colors).collect::<Vec<_>>());
+    }
+}
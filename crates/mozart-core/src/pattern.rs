@@ -0,0 +1,88 @@
+//! Patterns and the pattern chain
+//!
+//! A [`Pattern`] is a short clip of notes kept with the song under a name
+//! ("A", "Chorus"). The song's pattern chain lists pattern names in the
+//! order they play, e.g. "A A B A"; playback and export lay the chain out
+//! from the start of the song, each pattern straight after the one before,
+//! alongside the song's own notes (see [`crate::song::Song::as_played`]).
+//! Cutting measures into patterns and reordering the chain sketches the
+//! structure of a song without copying music around.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+/// A named clip of notes, timed from its own start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub name: String,
+    /// Ticks the pattern takes up in the chain (whole measures when cut from the song)
+    pub length_ticks: u32,
+    /// Sorted by start tick, from 0
+    pub notes: Vec<Note>,
+}
+
+/// Check a new pattern name: one word, so a chain can be written with spaces
+pub fn validate_pattern_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(MozartError::InvalidEdit(format!("Pattern names are one word: \"{}\"", name)));
+    }
+    Ok(name)
+}
+
+/// Pattern names of a chain written as "A A B A" (commas allowed), each checked against `patterns`
+pub fn parse_chain(text: &str, patterns: &[Pattern]) -> Result<Vec<String>> {
+    text.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|name| !name.is_empty())
+        .map(|name| match patterns.iter().find(|p| p.name == name) {
+            Some(pattern) => Ok(pattern.name.clone()),
+            None => Err(MozartError::InvalidEdit(format!("Unknown pattern: {}", name))),
+        })
+        .collect()
+}
+
+/// Notes of a chain laid out from tick 0, and the tick it ends at
+///
+/// Names without a pattern (a hand-edited file) are skipped.
+pub fn expand_chain(patterns: &[Pattern], chain: &[String]) -> (Vec<Note>, u32) {
+    let mut notes = Vec::new();
+    let mut at = 0;
+    for name in chain {
+        let Some(pattern) = patterns.iter().find(|p| &p.name == name) else {
+            tracing::warn!("Pattern chain names an unknown pattern: {}", name);
+            continue;
+        };
+        notes.extend(pattern.notes.iter().map(|note| Note { start_tick: at + note.start_tick, ..note.clone() }));
+        at += pattern.length_ticks;
+    }
+    notes.sort_by_key(|n| n.start_tick);
+    (notes, at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use pretty_assertions::assert_eq;
+
+    fn pattern(name: &str, melody: &str) -> Pattern {
+        Pattern { name: name.to_string(), length_ticks: 1920, notes: parse_melody(melody).unwrap() }
+    }
+
+    #[test]
+    fn test_expand_chain() {
+        let patterns = [pattern("A", "C4h E4h"), pattern("B", "G4w")];
+        let chain = parse_chain("A A, B  A", &patterns).unwrap();
+        assert_eq!(chain, vec!["A", "A", "B", "A"]);
+
+        let (notes, end) = expand_chain(&patterns, &chain);
+        let heard: Vec<(u32, u8)> = notes.iter().map(|n| (n.start_tick, n.pitch)).collect();
+        assert_eq!(heard, vec![(0, 60), (960, 64), (1920, 60), (2880, 64), (3840, 67), (5760, 60), (6720, 64)]);
+        assert_eq!(end, 7680);
+
+        assert!(parse_chain("A C", &patterns).is_err());
+        assert!(validate_pattern_name("Verse 2").is_err());
+        assert_eq!(validate_pattern_name(" Verse2 ").unwrap(), "Verse2");
+    }
+}
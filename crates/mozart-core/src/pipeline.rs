@@ -0,0 +1,270 @@
+//! Headless pipelines for scripts and CI
+//!
+//! A pipeline is a JSON list of operations applied to a song in order, using
+//! the same edits the editor makes, so a build can batch-process songs
+//! without the app:
+//!
+//! ```json
+//! [
+//!   {"op": "parse", "melody": "C4q D4q E4h"},
+//!   {"op": "transpose", "semitones": 2},
+//!   {"op": "quantize", "grid": "e"},
+//!   {"op": "export", "format": "midi", "path": "out.mid"}
+//! ]
+//! ```
+//!
+//! Export paths are relative to the working directory.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::lrc::LrcExporter;
+use crate::midi::MidiExporter;
+use crate::note::{pitched_notes, NoteValue};
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::time::TimeSignature;
+use crate::transpose::{detect_scale, transpose_notes, TransposeMode};
+use crate::validate::{self, RepairOptions};
+
+/// File written by an export operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Standard MIDI File
+    Midi,
+    /// .mozart.json song file
+    Json,
+    /// Timed lyrics
+    Lrc,
+}
+
+/// One step of a pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Add a melody in text notation, taking the key it implies
+    Parse { melody: String },
+    /// Change the tempo, meter, or key (without moving notes)
+    Set {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tempo: Option<u16>,
+        /// e.g., "6/8"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_signature: Option<String>,
+        /// e.g., "G major"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+    },
+    /// Move every note by semitones, or by scale degrees in the song's key
+    Transpose {
+        #[serde(default)]
+        semitones: i8,
+        #[serde(default)]
+        degrees: i8,
+    },
+    /// Snap every note start to a grid (a note value, e.g., "s" or "eighth")
+    Quantize {
+        grid: String,
+        /// Round note lengths to the grid as well
+        #[serde(default)]
+        durations: bool,
+        /// Pull starts toward accented beats (see [`Song::quantize_to_accents`])
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gravity: Option<f32>,
+    },
+    /// Fix note problems (options as in [`RepairOptions`])
+    Repair {
+        #[serde(flatten)]
+        options: RepairOptions,
+    },
+    /// Remove duplicate and zero-length notes
+    Cleanup,
+    /// Rescale velocities onto min..=max
+    NormalizeVelocities { min: u8, max: u8 },
+    /// Divide how far velocities exceed the threshold by ratio
+    CompressVelocities { ratio: f32, threshold: u8 },
+    /// Write the song as it stands to a file
+    Export { format: ExportFormat, path: String },
+}
+
+impl Operation {
+    /// The `op` name, for messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Parse { .. } => "parse",
+            Operation::Set { .. } => "set",
+            Operation::Transpose { .. } => "transpose",
+            Operation::Quantize { .. } => "quantize",
+            Operation::Repair { .. } => "repair",
+            Operation::Cleanup => "cleanup",
+            Operation::NormalizeVelocities { .. } => "normalize_velocities",
+            Operation::CompressVelocities { .. } => "compress_velocities",
+            Operation::Export { .. } => "export",
+        }
+    }
+
+    /// Apply the operation to a song
+    pub fn apply(&self, song: &mut Song) -> Result<()> {
+        match self {
+            Operation::Parse { melody } => {
+                let notes = song.parse_melody(melody)?;
+                // The pitch of rhythm hits says nothing about the key
+                if let Some(scale) = detect_scale(&pitched_notes(melody)?) {
+                    song.set_key(scale);
+                }
+                song.add_notes(notes);
+            }
+            Operation::Set { tempo, time_signature, key } => {
+                // Parse everything first so a bad value leaves the song alone
+                let time_signature = time_signature.as_deref().map(TimeSignature::parse).transpose()?;
+                let key = key.as_deref().map(Scale::parse).transpose()?;
+                if let Some(tempo) = tempo {
+                    song.set_tempo(*tempo);
+                }
+                if let Some(time_signature) = time_signature {
+                    song.set_time_signature(time_signature);
+                }
+                if let Some(key) = key {
+                    song.set_key(key);
+                }
+            }
+            Operation::Transpose { semitones, degrees } => {
+                let mode = match (semitones, degrees) {
+                    (0, 0) => return Ok(()),
+                    (semitones, 0) => TransposeMode::chromatic(*semitones),
+                    (0, degrees) => TransposeMode::diatonic(song.settings.key, *degrees),
+                    _ => {
+                        return Err(MozartError::PipelineError(
+                            "Transpose by semitones or by degrees, not both".to_string(),
+                        ))
+                    }
+                };
+                song.notes = transpose_notes(&song.notes, &mode)?;
+            }
+            Operation::Quantize { grid, durations, gravity } => {
+                let grid = NoteValue::parse(grid)?.ticks();
+                let all: Vec<usize> = (0..song.notes.len()).collect();
+                match gravity {
+                    Some(gravity) => song.quantize_to_accents(&all, grid, *gravity, *durations)?,
+                    None => song.quantize(&all, grid, *durations)?,
+                };
+            }
+            Operation::Repair { options } => {
+                validate::repair_song(song, options);
+            }
+            Operation::Cleanup => {
+                validate::cleanup_song(song, false);
+            }
+            Operation::NormalizeVelocities { min, max } => {
+                validate::normalize_velocities(song, *min, *max)?;
+            }
+            Operation::CompressVelocities { ratio, threshold } => {
+                validate::compress_velocities(song, *ratio, *threshold)?;
+            }
+            Operation::Export { format, path } => match format {
+                // What playback sounds like
+                ExportFormat::Midi => {
+                    MidiExporter::new().with_accent_dynamics(song.settings.accent_dynamics).export_to_file(song, path)?
+                }
+                ExportFormat::Json => song.save(path)?,
+                ExportFormat::Lrc => LrcExporter::new().export_to_file(song, path)?,
+            },
+        }
+        Ok(())
+    }
+}
+
+/// A parsed pipeline program
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pipeline {
+    pub operations: Vec<Operation>,
+}
+
+impl Pipeline {
+    /// Parse a program (a JSON array of operations)
+    pub fn parse(program: &str) -> Result<Self> {
+        serde_json::from_str(program).map_err(|e| MozartError::PipelineError(format!("Invalid program: {}", e)))
+    }
+
+    /// Run every operation in order, stopping at the first that fails
+    pub fn run(&self, mut song: Song) -> Result<Song> {
+        for (i, operation) in self.operations.iter().enumerate() {
+            tracing::debug!("Pipeline step {}: {}", i + 1, operation.name());
+            operation.apply(&mut song).map_err(|e| {
+                MozartError::PipelineError(format!("Step {} ({}): {}", i + 1, operation.name(), e))
+            })?;
+        }
+        tracing::info!("Ran a pipeline of {} steps", self.operations.len());
+        Ok(song)
+    }
+}
+
+/// Run a pipeline program on a song, returning the result
+pub fn run_pipeline(program: &str, song: Song) -> Result<Song> {
+    Pipeline::parse(program)?.run(song)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_run_pipeline() {
+        let program = r#"[
+            {"op": "parse", "melody": "C4q D4q E4h"},
+            {"op": "set", "tempo": 90, "time_signature": "3/4"},
+            {"op": "transpose", "semitones": 2},
+            {"op": "normalize_velocities", "min": 60, "max": 100}
+        ]"#;
+        let song = run_pipeline(program, Song::new()).unwrap();
+        let pitches: Vec<u8> = song.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![62, 64, 66]);
+        assert_eq!(song.settings.tempo, 90);
+        assert_eq!(song.settings.time_signature.to_string(), "3/4");
+    }
+
+    #[test]
+    fn test_quantize_and_repair() {
+        let mut song = Song::new();
+        song.add_notes([Note::new(60, 10, 470), Note::new(60, 10, 470), Note::new(62, 250, 200)]);
+        let program = r#"[{"op": "cleanup"}, {"op": "quantize", "grid": "q", "durations": true}, {"op": "repair"}]"#;
+        let song = run_pipeline(program, song).unwrap();
+        let notes: Vec<(u32, u32)> = song.notes.iter().map(|n| (n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(notes, vec![(0, 480), (480, 480)]);
+
+        let program = r#"[{"op": "set", "time_signature": "7/8"}, {"op": "quantize", "grid": "s", "gravity": 1.0}]"#;
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 650, 240));
+        let song = run_pipeline(program, song).unwrap();
+        assert_eq!(song.notes[0].start_tick, 720);
+    }
+
+    #[test]
+    fn test_pipeline_errors() {
+        assert!(matches!(run_pipeline("{}", Song::new()), Err(MozartError::PipelineError(_))));
+        assert!(run_pipeline(r#"[{"op": "reverse"}]"#, Song::new()).is_err());
+
+        let err = run_pipeline(r#"[{"op": "cleanup"}, {"op": "quantize", "grid": "z"}]"#, Song::new()).unwrap_err();
+        assert!(err.to_string().contains("Step 2 (quantize)"), "{}", err);
+        let both = r#"[{"op": "transpose", "semitones": 1, "degrees": 1}]"#;
+        assert!(run_pipeline(both, Song::new()).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let dir = std::env::temp_dir().join(format!("mozart-pipeline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.mozart.json");
+        let program = serde_json::json!([
+            {"op": "parse", "melody": "G4q A4q"},
+            {"op": "export", "format": "json", "path": path},
+        ])
+        .to_string();
+        let song = run_pipeline(&program, Song::new()).unwrap();
+        assert_eq!(Song::load(&path).unwrap().notes, song.notes);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
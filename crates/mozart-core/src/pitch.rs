@@ -30,6 +30,42 @@ impl PitchClass {
     pub const B_FLAT: PitchClass = PitchClass(10);
     pub const B: PitchClass = PitchClass(11);
 
+    /// Every spelling [`PitchClass::parse`] accepts, in any letter case
+    pub const SPELLINGS: &'static [(&'static str, PitchClass)] = &[
+        ("C", PitchClass::C),
+        ("C#", PitchClass::C_SHARP),
+        ("C♯", PitchClass::C_SHARP),
+        ("Cs", PitchClass::C_SHARP),
+        ("Db", PitchClass::D_FLAT),
+        ("D♭", PitchClass::D_FLAT),
+        ("D", PitchClass::D),
+        ("D#", PitchClass::D_SHARP),
+        ("D♯", PitchClass::D_SHARP),
+        ("Ds", PitchClass::D_SHARP),
+        ("Eb", PitchClass::E_FLAT),
+        ("E♭", PitchClass::E_FLAT),
+        ("E", PitchClass::E),
+        ("F", PitchClass::F),
+        ("F#", PitchClass::F_SHARP),
+        ("F♯", PitchClass::F_SHARP),
+        ("Fs", PitchClass::F_SHARP),
+        ("Gb", PitchClass::G_FLAT),
+        ("G♭", PitchClass::G_FLAT),
+        ("G", PitchClass::G),
+        ("G#", PitchClass::G_SHARP),
+        ("G♯", PitchClass::G_SHARP),
+        ("Gs", PitchClass::G_SHARP),
+        ("Ab", PitchClass::A_FLAT),
+        ("A♭", PitchClass::A_FLAT),
+        ("A", PitchClass::A),
+        ("A#", PitchClass::A_SHARP),
+        ("A♯", PitchClass::A_SHARP),
+        ("As", PitchClass::A_SHARP),
+        ("Bb", PitchClass::B_FLAT),
+        ("B♭", PitchClass::B_FLAT),
+        ("B", PitchClass::B),
+    ];
+
     /// Create a new pitch class from semitones (0-11)
     pub fn new(semitones: u8) -> Self {
         PitchClass(semitones % 12)
@@ -45,26 +81,11 @@ impl PitchClass {
         let s = s.trim();
         tracing::trace!("Parsing pitch class: {}", s);
 
-        let result = match s.to_uppercase().as_str() {
-            "C" => PitchClass::C,
-            "C#" | "C♯" | "CS" => PitchClass::C_SHARP,
-            "DB" | "D♭" => PitchClass::D_FLAT,
-            "D" => PitchClass::D,
-            "D#" | "D♯" | "DS" => PitchClass::D_SHARP,
-            "EB" | "E♭" => PitchClass::E_FLAT,
-            "E" => PitchClass::E,
-            "F" => PitchClass::F,
-            "F#" | "F♯" | "FS" => PitchClass::F_SHARP,
-            "GB" | "G♭" => PitchClass::G_FLAT,
-            "G" => PitchClass::G,
-            "G#" | "G♯" | "GS" => PitchClass::G_SHARP,
-            "AB" | "A♭" => PitchClass::A_FLAT,
-            "A" => PitchClass::A,
-            "A#" | "A♯" | "AS" => PitchClass::A_SHARP,
-            "BB" | "B♭" => PitchClass::B_FLAT,
-            "B" => PitchClass::B,
-            _ => return Err(MozartError::InvalidPitch(format!("Unknown pitch class: {}", s))),
-        };
+        let result = PitchClass::SPELLINGS
+            .iter()
+            .find(|(spelling, _)| spelling.eq_ignore_ascii_case(s))
+            .map(|&(_, pitch_class)| pitch_class)
+            .ok_or_else(|| MozartError::InvalidPitch(format!("Unknown pitch class: {}", s)))?;
 
         tracing::trace!("Parsed {} -> {:?}", s, result);
         Ok(result)
@@ -151,7 +172,7 @@ impl Pitch {
     /// Octave follows scientific pitch notation (middle C = C4)
     pub fn new(pitch_class: PitchClass, octave: i8) -> Result<Self> {
         let midi = (octave + 1) as i16 * 12 + pitch_class.semitones() as i16;
-        if midi < 0 || midi > 127 {
+        if !(0..=127).contains(&midi) {
             return Err(MozartError::InvalidPitch(format!(
                 "Pitch {}{} out of MIDI range",
                 pitch_class, octave
@@ -200,7 +221,7 @@ impl Pitch {
     /// Transpose by semitones
     pub fn transpose(&self, semitones: i8) -> Result<Self> {
         let new_midi = self.midi as i16 + semitones as i16;
-        if new_midi < 0 || new_midi > 127 {
+        if !(0..=127).contains(&new_midi) {
             return Err(MozartError::TranspositionError(format!(
                 "Transposition would put note out of MIDI range: {} + {} = {}",
                 self.midi, semitones, new_midi
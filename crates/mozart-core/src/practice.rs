@@ -0,0 +1,418 @@
+//! Call-and-response practice
+//!
+//! The engine plays a short phrase (the call), then listens for the same
+//! length of time while the user plays it back on a MIDI keyboard (the
+//! response). The response is matched note by note against the call and
+//! scored for pitch and rhythm; only note onsets count, not releases.
+//!
+//! The host owns the clock: [`CallResponse::note_on`] and
+//! [`CallResponse::advance`] take seconds since the call started, and
+//! `advance` reports phase changes and the final result as events.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::song::Song;
+use crate::worksheet::{build_worksheet, Difficulty, ExerciseKind, WorksheetConfig};
+use crate::TICKS_PER_QUARTER;
+
+/// How far from a call note (in multiples of the tolerance) a response note
+/// may land and still be matched to it
+const MATCH_WINDOW: u32 = 4;
+
+/// What to practice; omitted fields use the defaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CallResponseConfig {
+    /// Generate a melody in the song's key and meter instead of using the song
+    pub generate: bool,
+    /// First measure of the phrase taken from the song (1-based)
+    pub start_measure: u32,
+    /// Length of the phrase taken from the song
+    pub measures: u32,
+    /// Length and leaps of a generated phrase
+    pub difficulty: Difficulty,
+    pub seed: u64,
+    /// Onsets within this many ticks of the call count as in time
+    pub tolerance_ticks: u32,
+}
+
+impl Default for CallResponseConfig {
+    fn default() -> Self {
+        CallResponseConfig {
+            generate: false,
+            start_measure: 1,
+            measures: 2,
+            difficulty: Difficulty::Easy,
+            seed: 1,
+            tolerance_ticks: TICKS_PER_QUARTER / 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallResponsePhase {
+    /// The engine is playing the phrase
+    Call,
+    /// The user is playing it back
+    Response,
+    Finished,
+}
+
+/// The phrase to play and its timing, for the host
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallResponseInfo {
+    /// Notes of the call, starting at tick 0
+    pub phrase: Vec<Note>,
+    pub tempo: u16,
+    /// Length of the call (and of the response window)
+    pub duration_ticks: u32,
+    pub duration_seconds: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteVerdict {
+    Correct,
+    WrongPitch,
+    Early,
+    Late,
+    Missed,
+}
+
+/// How one call note was answered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteFeedback {
+    /// Index into the phrase
+    pub index: usize,
+    pub expected_pitch: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub played_pitch: Option<u8>,
+    /// Ticks the response note was late (negative when early)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing_offset_ticks: Option<i32>,
+    pub verdict: NoteVerdict,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallResponseResult {
+    /// One entry per call note, in order
+    pub notes: Vec<NoteFeedback>,
+    /// Response notes that matched nothing in the call
+    pub extra_notes: usize,
+    /// Percent of call notes played at the right pitch
+    pub pitch_accuracy: f32,
+    /// Percent of call notes played within the timing tolerance
+    pub rhythm_accuracy: f32,
+    /// Average of the two, less a share for extra notes
+    pub score: f32,
+}
+
+/// Session updates from [`CallResponse::advance`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CallResponseEvent {
+    #[serde(rename = "practice:phase")]
+    Phase { phase: CallResponsePhase },
+    #[serde(rename = "practice:result")]
+    Result { result: CallResponseResult },
+}
+
+// A response note, with ticks counted from the start of the response window
+#[derive(Debug, Clone, Copy)]
+struct PlayedNote {
+    pitch: u8,
+    start_tick: i64,
+}
+
+/// One call-and-response round
+#[derive(Debug, Clone)]
+pub struct CallResponse {
+    phrase: Vec<Note>,
+    tempo: u16,
+    duration_ticks: u32,
+    tolerance_ticks: u32,
+    phase: CallResponsePhase,
+    played: Vec<PlayedNote>,
+}
+
+impl CallResponse {
+    /// Take the call from the song, or generate one in its key and meter
+    pub fn new(song: &Song, config: &CallResponseConfig) -> Result<Self> {
+        let (phrase, tempo, duration_ticks) = if config.generate {
+            let worksheet = build_worksheet(&WorksheetConfig {
+                count: 1,
+                kinds: vec![ExerciseKind::Melody],
+                difficulty: config.difficulty,
+                time_signature: song.settings.time_signature.to_string(),
+                key: song.settings.key.to_string(),
+                tempo: song.settings.tempo,
+                seed: config.seed,
+                ..WorksheetConfig::default()
+            })?;
+            let generated = &worksheet.exercises[0].song;
+            (generated.notes.clone(), song.settings.tempo, generated.duration_ticks())
+        } else {
+            if config.measures == 0 {
+                return Err(MozartError::PracticeError("The phrase needs at least one measure".to_string()));
+            }
+            let start = song.measure_start_tick(config.start_measure)?;
+            let end = song.measure_start_tick(config.start_measure + config.measures)?;
+            // Notes held over from before the phrase are not part of it
+            let phrase: Vec<Note> = song
+                .notes
+                .iter()
+                .filter(|n| (start..end).contains(&n.start_tick))
+                .map(|n| {
+                    let mut note = n.clone();
+                    note.start_tick -= start;
+                    note.duration_ticks = n.duration_ticks.min(end - n.start_tick);
+                    note
+                })
+                .collect();
+            (phrase, song.tempo_at_tick(start), end - start)
+        };
+
+        if phrase.is_empty() {
+            return Err(MozartError::PracticeError(format!(
+                "No notes in measures {}-{}",
+                config.start_measure,
+                config.start_measure + config.measures - 1
+            )));
+        }
+        tracing::info!("Starting call and response: {} notes over {} ticks", phrase.len(), duration_ticks);
+
+        Ok(CallResponse {
+            phrase,
+            tempo,
+            duration_ticks,
+            tolerance_ticks: config.tolerance_ticks,
+            phase: CallResponsePhase::Call,
+            played: Vec::new(),
+        })
+    }
+
+    pub fn info(&self) -> CallResponseInfo {
+        CallResponseInfo {
+            phrase: self.phrase.clone(),
+            tempo: self.tempo,
+            duration_ticks: self.duration_ticks,
+            duration_seconds: self.ticks_to_seconds(self.duration_ticks),
+        }
+    }
+
+    pub fn phase(&self) -> CallResponsePhase {
+        self.phase
+    }
+
+    fn ticks_to_seconds(&self, ticks: u32) -> f64 {
+        ticks as f64 * 60.0 / (self.tempo as f64 * TICKS_PER_QUARTER as f64)
+    }
+
+    // Ticks since the response window opened (negative during the call)
+    fn response_tick(&self, seconds: f64) -> i64 {
+        let ticks = seconds * self.tempo as f64 * TICKS_PER_QUARTER as f64 / 60.0;
+        ticks.round() as i64 - self.duration_ticks as i64
+    }
+
+    /// A key was pressed; ignored unless the response is (nearly) due
+    pub fn note_on(&mut self, pitch: u8, velocity: u8, seconds: f64) {
+        let start_tick = self.response_tick(seconds);
+        // Eager players start a little before the window opens
+        if self.phase == CallResponsePhase::Finished || velocity == 0 || start_tick < -(self.tolerance_ticks as i64) {
+            return;
+        }
+        tracing::trace!("Response note {} at tick {}", pitch, start_tick);
+        self.played.push(PlayedNote { pitch, start_tick });
+    }
+
+    /// Move the session clock; returns what changed
+    pub fn advance(&mut self, seconds: f64) -> Vec<CallResponseEvent> {
+        let tick = self.response_tick(seconds);
+        let phase = if tick < 0 {
+            CallResponsePhase::Call
+        } else if tick < (self.duration_ticks + self.tolerance_ticks) as i64 {
+            // The window stays open a moment for a late last note
+            CallResponsePhase::Response
+        } else {
+            CallResponsePhase::Finished
+        };
+
+        let mut events = Vec::new();
+        if phase == self.phase {
+            return events;
+        }
+        if self.phase == CallResponsePhase::Call {
+            events.push(CallResponseEvent::Phase { phase: CallResponsePhase::Response });
+        }
+        if phase == CallResponsePhase::Finished {
+            events.push(CallResponseEvent::Phase { phase });
+            events.push(CallResponseEvent::Result { result: self.finish() });
+        }
+        self.phase = phase;
+        events
+    }
+
+    /// End the round now and score what was played
+    pub fn stop(&mut self) -> CallResponseResult {
+        self.phase = CallResponsePhase::Finished;
+        self.finish()
+    }
+
+    fn finish(&self) -> CallResponseResult {
+        let result = self.score();
+        tracing::info!("Call and response scored {:.0}%", result.score);
+        result
+    }
+
+    /// Match the response against the call
+    ///
+    /// Call notes are taken in order, each claiming the nearest unclaimed
+    /// response note within the match window, preferring the right pitch.
+    pub fn score(&self) -> CallResponseResult {
+        let tolerance = self.tolerance_ticks.max(1) as i64;
+        let window = tolerance * MATCH_WINDOW as i64;
+        let mut claimed = vec![false; self.played.len()];
+
+        let notes: Vec<NoteFeedback> = self
+            .phrase
+            .iter()
+            .enumerate()
+            .map(|(index, expected)| {
+                let best = self
+                    .played
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !claimed[*i])
+                    .map(|(i, played)| (i, played, played.start_tick - expected.start_tick as i64))
+                    .filter(|(_, _, offset)| offset.abs() <= window)
+                    .min_by_key(|(_, played, offset)| offset.abs() + if played.pitch == expected.pitch { 0 } else { window });
+
+                let Some((i, played, offset)) = best else {
+                    return NoteFeedback {
+                        index,
+                        expected_pitch: expected.pitch,
+                        played_pitch: None,
+                        timing_offset_ticks: None,
+                        verdict: NoteVerdict::Missed,
+                    };
+                };
+                claimed[i] = true;
+                let verdict = if played.pitch != expected.pitch {
+                    NoteVerdict::WrongPitch
+                } else if offset < -tolerance {
+                    NoteVerdict::Early
+                } else if offset > tolerance {
+                    NoteVerdict::Late
+                } else {
+                    NoteVerdict::Correct
+                };
+                NoteFeedback {
+                    index,
+                    expected_pitch: expected.pitch,
+                    played_pitch: Some(played.pitch),
+                    timing_offset_ticks: Some(offset as i32),
+                    verdict,
+                }
+            })
+            .collect();
+
+        let total = notes.len().max(1) as f32;
+        let percent = |count: usize| 100.0 * count as f32 / total;
+        let pitch_accuracy = percent(notes.iter().filter(|n| n.played_pitch == Some(n.expected_pitch)).count());
+        let rhythm_accuracy = percent(
+            notes
+                .iter()
+                .filter(|n| n.timing_offset_ticks.is_some_and(|offset| (offset as i64).abs() <= tolerance))
+                .count(),
+        );
+        let extra_notes = claimed.iter().filter(|&&c| !c).count();
+        let score = ((pitch_accuracy + rhythm_accuracy) / 2.0 - percent(extra_notes) / 2.0).max(0.0);
+
+        CallResponseResult { notes, extra_notes, pitch_accuracy, rhythm_accuracy, score }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use pretty_assertions::assert_eq;
+
+    // C4q D4q E4q F4q | G4w at 120 bpm: one beat is half a second
+    fn song() -> Song {
+        let mut song = Song::new();
+        song.add_notes(parse_melody("C4q D4q E4q F4q G4w").unwrap());
+        song
+    }
+
+    #[test]
+    fn test_phrase_from_song() {
+        let config = CallResponseConfig { measures: 1, ..CallResponseConfig::default() };
+        let info = CallResponse::new(&song(), &config).unwrap().info();
+        assert_eq!(info.phrase.len(), 4);
+        assert_eq!(info.duration_ticks, 1920);
+        assert_eq!(info.duration_seconds, 2.0);
+
+        let second = CallResponse::new(&song(), &CallResponseConfig { start_measure: 2, ..config.clone() }).unwrap();
+        assert_eq!(second.info().phrase[0].start_tick, 0);
+        assert_eq!(second.info().phrase[0].pitch, 67);
+
+        let empty = CallResponseConfig { start_measure: 3, ..config };
+        assert!(matches!(CallResponse::new(&song(), &empty), Err(MozartError::PracticeError(_))));
+    }
+
+    #[test]
+    fn test_scoring() {
+        let config = CallResponseConfig { measures: 1, ..CallResponseConfig::default() };
+        let mut round = CallResponse::new(&song(), &config).unwrap();
+
+        assert!(round.advance(1.0).is_empty());
+        round.note_on(60, 90, 0.5); // During the call: ignored
+        assert_eq!(
+            round.advance(2.0),
+            vec![CallResponseEvent::Phase { phase: CallResponsePhase::Response }]
+        );
+
+        // The response window opens at 2.0s: C on time, D late, F for E, and a
+        // high C nearly a beat after F should be
+        round.note_on(60, 90, 2.01);
+        round.note_on(62, 90, 2.5 + 0.2);
+        round.note_on(65, 90, 3.0);
+        round.note_on(72, 90, 3.9);
+        let events = round.advance(4.5);
+        assert_eq!(events[0], CallResponseEvent::Phase { phase: CallResponsePhase::Finished });
+        let CallResponseEvent::Result { result } = &events[1] else {
+            panic!("no result");
+        };
+
+        let verdicts: Vec<NoteVerdict> = result.notes.iter().map(|n| n.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![NoteVerdict::Correct, NoteVerdict::Late, NoteVerdict::WrongPitch, NoteVerdict::WrongPitch]
+        );
+        assert_eq!(result.notes[1].timing_offset_ticks, Some(192));
+        assert_eq!(result.notes[2].played_pitch, Some(65));
+        assert_eq!(result.extra_notes, 0);
+        assert_eq!(result.pitch_accuracy, 50.0);
+        assert_eq!(result.rhythm_accuracy, 50.0);
+        assert_eq!(result.score, 50.0);
+        assert_eq!(round.phase(), CallResponsePhase::Finished);
+        assert!(round.advance(5.0).is_empty());
+    }
+
+    #[test]
+    fn test_generated_phrase_and_stop() {
+        let config = CallResponseConfig { generate: true, seed: 7, ..CallResponseConfig::default() };
+        let mut round = CallResponse::new(&Song::new(), &config).unwrap();
+        let info = round.info();
+        assert!(!info.phrase.is_empty());
+        assert_eq!(info.duration_ticks, 1920 * 2);
+
+        // Nothing played: every note missed
+        let result = round.stop();
+        assert!(result.notes.iter().all(|n| n.verdict == NoteVerdict::Missed));
+        assert_eq!(result.score, 0.0);
+    }
+}
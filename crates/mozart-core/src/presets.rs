@@ -0,0 +1,359 @@
+//! Accent pattern presets
+//!
+//! A single registry of named accent patterns for each meter, combining the
+//! built-in groupings with user-defined presets saved in preferences.
+//!
+//! Groove presets go one step further for practice: a named odd-meter feel
+//! (meter, accents, pulse grouping, tempo, and an optional drum pattern)
+//! loaded into a song in one go.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::song::Song;
+use crate::time::{AccentPattern, TimeSignature};
+
+/// Voice a groove preset's drum pattern is written into, clear of the
+/// melody and harmony voices
+pub const DRUM_VOICE: u8 = 9;
+/// Measures of drum pattern written into an empty song
+const MIN_DRUM_MEASURES: u32 = 4;
+
+/// A named accent pattern for a given number of beats
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccentPreset {
+    pub name: String,
+    /// Beats per measure the pattern applies to
+    pub numerator: u8,
+    /// Accent values per beat (1=weak, 2=medium, 3=strong)
+    pub accents: Vec<u8>,
+    #[serde(default)]
+    pub description: String,
+    /// True for presets shipped with Mozart (cannot be overwritten)
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+impl AccentPreset {
+    fn builtin(groups: &[u8], description: &str) -> Self {
+        let pattern = AccentPattern::from_grouping(groups);
+        AccentPreset {
+            name: groups.iter().map(|g| g.to_string()).collect::<Vec<_>>().join("+"),
+            numerator: pattern.len() as u8,
+            accents: pattern.to_values(),
+            description: description.to_string(),
+            builtin: true,
+        }
+    }
+
+    pub fn pattern(&self) -> AccentPattern {
+        AccentPattern::from_values(&self.accents)
+    }
+}
+
+/// Built-in presets for common and odd meters
+pub fn builtin_presets() -> Vec<AccentPreset> {
+    vec![
+        AccentPreset::builtin(&[2, 2], "Common time"),
+        AccentPreset::builtin(&[3, 2], "5/8, long-short"),
+        AccentPreset::builtin(&[2, 3], "5/8, short-long"),
+        AccentPreset::builtin(&[3, 3], "Compound duple (6/8)"),
+        AccentPreset::builtin(&[3, 2, 2], "7/8, long-short-short"),
+        AccentPreset::builtin(&[2, 2, 3], "7/8, short-short-long"),
+        AccentPreset::builtin(&[2, 3, 2], "7/8, short-long-short"),
+        AccentPreset::builtin(&[3, 3, 2], "8/8 'tresillo' feel"),
+        AccentPreset::builtin(&[2, 2, 2, 3], "9/8 Balkan (aksak)"),
+        AccentPreset::builtin(&[3, 3, 3], "Compound triple (9/8)"),
+        AccentPreset::builtin(&[3, 2, 2, 3], "10/8"),
+        AccentPreset::builtin(&[3, 3, 3, 2], "11/8"),
+        AccentPreset::builtin(&[3, 3, 2, 3], "11/8"),
+        AccentPreset::builtin(&[3, 2, 3, 3], "11/8"),
+        AccentPreset::builtin(&[2, 3, 3, 3], "11/8"),
+        AccentPreset::builtin(&[3, 3, 3, 3], "Compound quadruple (12/8)"),
+        AccentPreset::builtin(&[3, 3, 3, 2, 2], "13/8"),
+        AccentPreset::builtin(&[3, 3, 3, 3, 3], "15/8"),
+    ]
+}
+
+/// An odd-meter practice groove
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroovePreset {
+    pub name: String,
+    /// e.g., "7/8"
+    pub time_signature: String,
+    /// Beat groups, accenting the first beat of each (e.g., [2, 2, 3])
+    pub grouping: Vec<u8>,
+    /// Click once per group instead of every beat
+    pub click_groups: bool,
+    /// Quarter notes per minute
+    pub tempo: u16,
+    /// One measure of rhythm hits in text notation (e.g., "xq xe xe xq."),
+    /// looped in [`DRUM_VOICE`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drums: Option<String>,
+    pub description: String,
+}
+
+impl GroovePreset {
+    fn new(name: &str, time_signature: &str, grouping: &[u8], click_groups: bool, tempo: u16, drums: Option<&str>, description: &str) -> Self {
+        GroovePreset {
+            name: name.to_string(),
+            time_signature: time_signature.to_string(),
+            grouping: grouping.to_vec(),
+            click_groups,
+            tempo,
+            drums: drums.map(String::from),
+            description: description.to_string(),
+        }
+    }
+
+    /// The meter with the preset's accents and pulses
+    pub fn to_time_signature(&self) -> Result<TimeSignature> {
+        let mut time_signature = TimeSignature::parse(&self.time_signature)?;
+        let pattern = AccentPattern::from_grouping(&self.grouping);
+        if pattern.len() != time_signature.numerator as usize {
+            return Err(MozartError::InvalidGrouping(format!(
+                "{:?} does not add up to {} beats",
+                self.grouping, time_signature.numerator
+            )));
+        }
+        time_signature.set_accents(pattern);
+        if self.click_groups {
+            time_signature.set_pulse_grouping(&self.grouping)?;
+        }
+        Ok(time_signature)
+    }
+}
+
+/// The bundled odd-meter grooves
+pub fn groove_presets() -> Vec<GroovePreset> {
+    vec![
+        GroovePreset::new(
+            "Rachenitsa",
+            "7/8",
+            &[2, 2, 3],
+            true,
+            150,
+            Some("xq xe xe xq."),
+            "Bulgarian dance in 7/8, short-short-long",
+        ),
+        GroovePreset::new(
+            "Kalamatianos",
+            "7/8",
+            &[3, 2, 2],
+            true,
+            120,
+            Some("xq. xq xe xe"),
+            "Greek line dance in 7/8, long-short-short",
+        ),
+        GroovePreset::new(
+            "Kopanitsa",
+            "11/8",
+            &[2, 2, 3, 2, 2],
+            true,
+            140,
+            Some("xq xq xq. xe xe xq"),
+            "Bulgarian dance in 11/8 with the long beat in the middle",
+        ),
+        GroovePreset::new(
+            "Tuareg 5/4",
+            "5/4",
+            &[3, 2],
+            false,
+            100,
+            Some("xq xe xe xq xq Rq"),
+            "Desert blues in five, felt as three plus two",
+        ),
+        GroovePreset::new(
+            "Brubeck 9/8",
+            "9/8",
+            &[2, 2, 2, 3],
+            true,
+            130,
+            Some("xq xq xq xq."),
+            "Dave Brubeck's aksak 9/8, three short beats and a long one",
+        ),
+        GroovePreset::new("Brubeck 5/4", "5/4", &[3, 2], false, 170, None, "Dave Brubeck's quintuple swing, three plus two"),
+    ]
+}
+
+/// Load a bundled groove into a song by name (case-insensitive)
+///
+/// Sets the meter, accents, and tempo. A drum pattern replaces the notes of
+/// [`DRUM_VOICE`], looped over the song (at least four measures).
+pub fn load_groove_preset(song: &mut Song, name: &str) -> Result<GroovePreset> {
+    let preset = groove_presets()
+        .into_iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| MozartError::ParseError(format!("Unknown groove preset: {}", name)))?;
+    let time_signature = preset.to_time_signature()?;
+    let measure = time_signature.ticks_per_measure();
+    let drums = preset.drums.as_deref().map(|drums| song.parse_melody(drums)).transpose()?.unwrap_or_default();
+
+    song.set_time_signature(time_signature);
+    song.set_tempo(preset.tempo);
+    if !drums.is_empty() {
+        song.notes.retain(|n| n.voice != DRUM_VOICE);
+        let measures = song.measure_count().max(MIN_DRUM_MEASURES);
+        song.add_notes((0..measures).flat_map(|m| {
+            drums.iter().map(move |hit| {
+                let mut hit = hit.clone();
+                hit.start_tick += m * measure;
+                hit.voice = DRUM_VOICE;
+                hit
+            })
+        }));
+    }
+    tracing::info!("Loaded groove preset {} ({}, {} BPM)", preset.name, preset.time_signature, preset.tempo);
+    Ok(preset)
+}
+
+/// Built-in presets plus the user's saved patterns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetLibrary {
+    /// User-defined presets (persisted in preferences)
+    pub user: Vec<AccentPreset>,
+}
+
+impl PresetLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get all presets for a number of beats, built-ins first
+    pub fn list(&self, numerator: u8) -> Vec<AccentPreset> {
+        builtin_presets()
+            .into_iter()
+            .chain(self.user.iter().cloned())
+            .filter(|p| p.numerator == numerator)
+            .collect()
+    }
+
+    /// Find a preset by name for a number of beats
+    pub fn get(&self, numerator: u8, name: &str) -> Option<AccentPreset> {
+        self.list(numerator).into_iter().find(|p| p.name == name)
+    }
+
+    /// Save a user preset, replacing any user preset with the same name and meter
+    pub fn save(&mut self, name: &str, pattern: &AccentPattern, description: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(MozartError::ParseError("Preset name cannot be empty".to_string()));
+        }
+        let numerator = pattern.len() as u8;
+        if builtin_presets().iter().any(|p| p.numerator == numerator && p.name == name) {
+            return Err(MozartError::ParseError(format!(
+                "'{}' is a built-in preset for {} beats",
+                name, numerator
+            )));
+        }
+
+        tracing::debug!("Saving accent preset '{}' for {} beats", name, numerator);
+        self.user.retain(|p| !(p.numerator == numerator && p.name == name));
+        self.user.push(AccentPreset {
+            name: name.to_string(),
+            numerator,
+            accents: pattern.to_values(),
+            description: description.to_string(),
+            builtin: false,
+        });
+        Ok(())
+    }
+
+    /// Remove a user preset; returns false if there was none
+    pub fn remove(&mut self, numerator: u8, name: &str) -> bool {
+        let before = self.user.len();
+        self.user.retain(|p| !(p.numerator == numerator && p.name == name));
+        self.user.len() != before
+    }
+
+    /// Serialize the user presets for preferences storage
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Load user presets from preferences storage
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use crate::time::groupings;
+
+    #[test]
+    fn test_builtins_match_groupings() {
+        let library = PresetLibrary::new();
+        assert_eq!(
+            library.get(11, "3+3+2+3").unwrap().pattern(),
+            groupings::eleven_three_three_two_three()
+        );
+        assert_eq!(library.get(7, "2+2+3").unwrap().pattern(), groupings::seven_two_two_three());
+        assert_eq!(library.list(11).len(), 4);
+        assert!(library.list(2).is_empty());
+    }
+
+    #[test]
+    fn test_save_user_preset() {
+        let mut library = PresetLibrary::new();
+        let pattern = AccentPattern::from_values(&[3, 2, 1, 2, 1, 2, 1]);
+        library.save("Galloping", &pattern, "My seven").unwrap();
+        library.save("Galloping", &pattern, "Updated").unwrap();
+
+        let sevens = library.list(7);
+        assert_eq!(sevens.len(), 4);
+        assert_eq!(sevens.last().unwrap().description, "Updated");
+        assert!(!sevens.last().unwrap().builtin);
+
+        // Built-ins and empty names are protected
+        assert!(library.save("3+2+2", &pattern, "").is_err());
+        assert!(library.save("  ", &pattern, "").is_err());
+
+        let loaded = PresetLibrary::from_json(&library.to_json().unwrap()).unwrap();
+        assert_eq!(loaded, library);
+
+        assert!(library.remove(7, "Galloping"));
+        assert!(!library.remove(7, "Galloping"));
+    }
+
+    #[test]
+    fn test_groove_presets_fill_their_measures() {
+        for preset in groove_presets() {
+            let time_signature = preset.to_time_signature().unwrap();
+            assert_eq!(time_signature.groupings(), preset.grouping, "{}", preset.name);
+            if let Some(drums) = &preset.drums {
+                let hits = crate::note::parse_melody(drums).unwrap();
+                let end = hits.iter().map(|n| n.end_tick()).max().unwrap_or(0);
+                assert!(end <= time_signature.ticks_per_measure(), "{}", preset.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_groove_preset() {
+        let mut song = Song::new();
+        song.add_note(crate::note::Note::new(60, 0, 1680 * 5));
+        let preset = load_groove_preset(&mut song, "rachenitsa").unwrap();
+        assert_eq!(preset.name, "Rachenitsa");
+        assert_eq!(song.settings.tempo, 150);
+        assert_eq!(song.settings.time_signature.to_string(), "7/8");
+        assert_eq!(song.settings.time_signature.accents.to_values(), vec![3, 1, 2, 1, 2, 1, 1]);
+        assert_eq!(song.settings.time_signature.pulse_starts(), vec![0, 2, 4]);
+
+        // Four hits a measure over the song's five measures, then reloaded in place
+        let drums: Vec<u32> = song.notes.iter().filter(|n| n.voice == DRUM_VOICE).map(|n| n.start_tick).collect();
+        assert_eq!(drums.len(), 20);
+        assert_eq!(&drums[..5], &[0, 480, 720, 960, 1680]);
+        load_groove_preset(&mut song, "Rachenitsa").unwrap();
+        assert_eq!(song.notes.iter().filter(|n| n.voice == DRUM_VOICE).count(), 20);
+
+        // No drums: only the meter and tempo change
+        let mut song = Song::new();
+        load_groove_preset(&mut song, "Brubeck 5/4").unwrap();
+        assert!(song.notes.is_empty());
+        assert_eq!(song.settings.time_signature.pulse_starts(), vec![0, 1, 2, 3, 4]);
+        assert!(load_groove_preset(&mut song, "Polka").is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Register analysis
+//!
+//! Pitch usage over time for a heatmap of where the music sits: the song is
+//! cut into equal time buckets, and each bucket counts how many ticks every
+//! pitch sounds in it. Rows span the lowest to the highest pitch used, so
+//! the same grid lines up with an instrument's range.
+//!
+//! A song can also set the [`PitchRange`] of the instrument or voice it is
+//! written for; [`range_warnings`] flags notes an edit pushed outside it, and
+//! [`fold_into_range`] moves such notes back in by octaves.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::Pitch;
+use crate::song::Song;
+
+/// Common instrument and voice ranges: (name, lowest, highest)
+const RANGE_PRESETS: &[(&str, u8, u8)] = &[
+    ("Soprano", 60, 81),
+    ("Alto", 53, 74),
+    ("Tenor", 48, 69),
+    ("Bass", 40, 64),
+    ("Flute", 60, 96),
+    ("Clarinet", 50, 94),
+    ("Trumpet", 54, 82),
+    ("Violin", 55, 103),
+    ("Viola", 48, 88),
+    ("Cello", 36, 76),
+    ("Guitar", 40, 88),
+    ("Piano", 21, 108),
+];
+
+/// Lowest and highest pitch an instrument or voice can play
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PitchRange {
+    /// e.g., "Soprano" (empty for a custom range)
+    pub name: String,
+    pub low: u8,
+    pub high: u8,
+}
+
+impl PitchRange {
+    pub fn new(name: &str, low: u8, high: u8) -> Result<Self> {
+        if low > high || high > 127 {
+            return Err(MozartError::InvalidPitch(format!("Range {}-{} is not within MIDI 0-127", low, high)));
+        }
+        Ok(PitchRange { name: name.to_string(), low, high })
+    }
+
+    /// Look up a preset by name, ignoring case ("alto voice" finds Alto)
+    pub fn preset(name: &str) -> Result<Self> {
+        let wanted = name.trim().to_lowercase();
+        let wanted = wanted.strip_suffix(" voice").unwrap_or(&wanted).trim_end();
+        RANGE_PRESETS
+            .iter()
+            .find(|(preset, _, _)| preset.eq_ignore_ascii_case(wanted))
+            .map(|&(preset, low, high)| PitchRange { name: preset.to_string(), low, high })
+            .ok_or_else(|| MozartError::ParseError(format!("Unknown instrument range: {}", name)))
+    }
+
+    /// Every preset, voices first
+    pub fn presets() -> Vec<PitchRange> {
+        RANGE_PRESETS
+            .iter()
+            .map(|&(name, low, high)| PitchRange { name: name.to_string(), low, high })
+            .collect()
+    }
+
+    pub fn contains(&self, pitch: u8) -> bool {
+        (self.low..=self.high).contains(&pitch)
+    }
+}
+
+impl std::fmt::Display for PitchRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = |midi| Pitch::from_midi(midi).map_or_else(|_| midi.to_string(), |p| p.to_string());
+        if self.name.is_empty() {
+            write!(f, "{}-{}", name(self.low), name(self.high))
+        } else {
+            write!(f, "{} ({}-{})", self.name, name(self.low), name(self.high))
+        }
+    }
+}
+
+/// A note an edit left outside the song's pitch range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeWarning {
+    pub note_index: usize,
+    pub pitch: u8,
+    pub message: String,
+}
+
+/// Indices of notes outside the song's pitch range (none if it has no range)
+pub fn out_of_range_notes(song: &Song) -> Vec<usize> {
+    let Some(range) = &song.settings.pitch_range else {
+        return Vec::new();
+    };
+    (0..song.notes.len()).filter(|&i| !range.contains(song.notes[i].pitch)).collect()
+}
+
+/// Warnings for the notes an edit created or changed outside `after`'s range
+///
+/// Notes carried over unchanged from `before` are not reported again, so
+/// narrowing the range itself warns about nothing; the piano roll shows
+/// those through [`out_of_range_notes`].
+pub fn range_warnings(before: &Song, after: &Song) -> Vec<RangeWarning> {
+    let Some(range) = &after.settings.pitch_range else {
+        return Vec::new();
+    };
+    let key = |i: usize, song: &Song| {
+        let n = &song.notes[i];
+        (n.pitch, n.start_tick, n.duration_ticks, n.voice)
+    };
+    let mut existing: HashMap<_, usize> = HashMap::new();
+    for i in 0..before.notes.len() {
+        *existing.entry(key(i, before)).or_default() += 1;
+    }
+
+    let mut warnings = Vec::new();
+    for i in out_of_range_notes(after) {
+        if let Some(count) = existing.get_mut(&key(i, after)).filter(|count| **count > 0) {
+            *count -= 1;
+            continue;
+        }
+        let pitch = after.notes[i].pitch;
+        let side = if pitch < range.low { "below" } else { "above" };
+        warnings.push(RangeWarning {
+            note_index: i,
+            pitch,
+            message: format!("Note {} is {} the {} range", i, side, range),
+        });
+    }
+    if !warnings.is_empty() {
+        tracing::debug!("{} notes placed outside {}", warnings.len(), range);
+    }
+    warnings
+}
+
+/// A note [`fold_into_range`] moved by octaves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoldedNote {
+    pub note_index: usize,
+    pub from: u8,
+    pub to: u8,
+}
+
+// The octaves of `pitch` within low..=high, lowest first
+fn octaves_within(pitch: u8, low: u8, high: u8) -> impl Iterator<Item = u8> {
+    let first = low as i16 + (pitch as i16 - low as i16).rem_euclid(12);
+    (first..=high as i16).step_by(12).map(|p| p as u8)
+}
+
+/// Move each note outside low..=high by whole octaves until it fits
+///
+/// Notes already in range stay put; the melody as a whole is not
+/// transposed. Of the octaves that fit, a folded note takes the one that
+/// best keeps the step from the note before it in its voice: moving the
+/// same way first, then by the nearest interval, then nearest the original
+/// pitch. The range must span at least an octave less a semitone, so every
+/// pitch class fits.
+pub fn fold_into_range(notes: &mut [Note], low: u8, high: u8) -> Result<Vec<FoldedNote>> {
+    let range = PitchRange::new("", low, high)?;
+    if high - low < 11 {
+        return Err(MozartError::InvalidPitch(format!("Range {} is narrower than an octave", range)));
+    }
+
+    let mut order: Vec<usize> = (0..notes.len()).collect();
+    order.sort_by_key(|&i| (notes[i].voice, notes[i].start_tick, notes[i].pitch));
+    let mut folded = Vec::new();
+    // Original and placed pitch of the voice's last note
+    let mut previous: Option<(u8, u8, u8)> = None;
+    for i in order {
+        let (pitch, voice) = (notes[i].pitch, notes[i].voice);
+        let before = previous.filter(|&(v, _, _)| v == voice);
+        if !range.contains(pitch) {
+            let to = octaves_within(pitch, low, high)
+                .min_by_key(|&candidate| {
+                    let shape = before.map(|(_, from, placed)| {
+                        let wanted = pitch as i16 - from as i16;
+                        let step = candidate as i16 - placed as i16;
+                        (step.signum() != wanted.signum(), (step - wanted).abs())
+                    });
+                    (shape, candidate.abs_diff(pitch))
+                })
+                .expect("a range of an octave holds every pitch class");
+            notes[i].pitch = to;
+            folded.push(FoldedNote { note_index: i, from: pitch, to });
+        }
+        previous = Some((voice, pitch, notes[i].pitch));
+    }
+    folded.sort_by_key(|f| f.note_index);
+    if !folded.is_empty() {
+        tracing::debug!("Folded {} notes into {}", folded.len(), range);
+    }
+    Ok(folded)
+}
+
+/// One time bucket of a [`PitchHistogram`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub start_tick: u32,
+    /// Ticks each pitch sounds within the bucket, from `lowest_pitch` up
+    pub ticks: Vec<u32>,
+    /// Average sounding pitch, weighted by duration (None when silent)
+    pub mean_pitch: Option<f32>,
+}
+
+/// Pitch usage per time bucket across a song
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PitchHistogram {
+    pub bucket_ticks: u32,
+    /// Range of the rows (both 0 for a song without notes)
+    pub lowest_pitch: u8,
+    pub highest_pitch: u8,
+    pub buckets: Vec<HistogramBucket>,
+}
+
+/// Count how long each pitch sounds in every `bucket_ticks` of the song
+pub fn pitch_histogram(song: &Song, bucket_ticks: u32) -> Result<PitchHistogram> {
+    if bucket_ticks == 0 {
+        return Err(MozartError::InvalidDuration("Buckets must be at least one tick long".to_string()));
+    }
+
+    let lowest_pitch = song.notes.iter().map(|n| n.pitch).min().unwrap_or(0);
+    let highest_pitch = song.notes.iter().map(|n| n.pitch).max().unwrap_or(0);
+    let rows = if song.notes.is_empty() { 0 } else { (highest_pitch - lowest_pitch) as usize + 1 };
+    let count = song.duration_ticks().div_ceil(bucket_ticks);
+
+    let mut buckets: Vec<HistogramBucket> = (0..count)
+        .map(|i| HistogramBucket { start_tick: i * bucket_ticks, ticks: vec![0; rows], mean_pitch: None })
+        .collect();
+    for note in &song.notes {
+        let row = (note.pitch - lowest_pitch) as usize;
+        let first = note.start_tick / bucket_ticks;
+        let last = note.end_tick().saturating_sub(1) / bucket_ticks;
+        for bucket in &mut buckets[first as usize..(last as usize + 1).min(count as usize)] {
+            let end = bucket.start_tick + bucket_ticks;
+            bucket.ticks[row] += note.end_tick().min(end) - note.start_tick.max(bucket.start_tick);
+        }
+    }
+
+    for bucket in &mut buckets {
+        let total: u32 = bucket.ticks.iter().sum();
+        if total > 0 {
+            let weighted: f64 = bucket
+                .ticks
+                .iter()
+                .enumerate()
+                .map(|(row, &ticks)| (lowest_pitch as usize + row) as f64 * ticks as f64)
+                .sum();
+            bucket.mean_pitch = Some((weighted / total as f64) as f32);
+        }
+    }
+
+    tracing::debug!("Pitch histogram: {} buckets of {} ticks, {} rows", buckets.len(), bucket_ticks, rows);
+    Ok(PitchHistogram { bucket_ticks, lowest_pitch, highest_pitch, buckets })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_pitch_histogram() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 960));
+        // Crosses into the second measure
+        song.add_note(Note::new(64, 1440, 960));
+        song.add_note(Note::new(72, 2400, 480));
+
+        let histogram = pitch_histogram(&song, 1920).unwrap();
+        assert_eq!((histogram.lowest_pitch, histogram.highest_pitch), (60, 72));
+        assert_eq!(histogram.buckets.len(), 2);
+
+        let first = &histogram.buckets[0];
+        assert_eq!((first.ticks[0], first.ticks[4]), (960, 480));
+        assert_eq!(first.ticks.iter().sum::<u32>(), 1440);
+        assert_eq!(first.mean_pitch, Some(60.0 + 4.0 / 3.0));
+
+        let second = &histogram.buckets[1];
+        assert_eq!(second.start_tick, 1920);
+        assert_eq!((second.ticks[4], second.ticks[12]), (480, 480));
+        assert_eq!(second.mean_pitch, Some(68.0));
+    }
+
+    #[test]
+    fn test_empty_and_silent_buckets() {
+        let mut song = Song::new();
+        assert_eq!(pitch_histogram(&song, 480).unwrap().buckets, Vec::new());
+        assert!(pitch_histogram(&song, 0).is_err());
+
+        song.add_note(Note::new(60, 960, 480));
+        let histogram = pitch_histogram(&song, 480).unwrap();
+        assert_eq!(histogram.buckets.len(), 3);
+        assert_eq!(histogram.buckets[0].mean_pitch, None);
+        assert_eq!(histogram.buckets[2].ticks, vec![480]);
+    }
+
+    #[test]
+    fn test_pitch_range_presets() {
+        let soprano = PitchRange::preset("soprano").unwrap();
+        assert_eq!((soprano.low, soprano.high), (60, 81));
+        assert_eq!(soprano.to_string(), "Soprano (C4-A5)");
+        assert!(soprano.contains(60) && !soprano.contains(59));
+        assert_eq!(PitchRange::presets().len(), RANGE_PRESETS.len());
+
+        assert_eq!(PitchRange::preset(" Alto Voice").unwrap().name, "Alto");
+        assert!(PitchRange::preset("kazoo").is_err());
+        assert!(PitchRange::new("", 70, 60).is_err());
+    }
+
+    #[test]
+    fn test_range_warnings() {
+        let mut before = Song::new();
+        before.settings.pitch_range = Some(PitchRange::preset("Soprano").unwrap());
+        before.add_note(Note::new(84, 0, 480)); // Already out of range
+        before.add_note(Note::new(72, 480, 480));
+        assert_eq!(out_of_range_notes(&before), vec![0]);
+
+        let mut after = before.clone();
+        after.add_note(Note::new(55, 960, 480));
+        after.notes[1].pitch = 83;
+        let warnings = range_warnings(&before, &after);
+        let flagged: Vec<(usize, u8)> = warnings.iter().map(|w| (w.note_index, w.pitch)).collect();
+        assert_eq!(flagged, vec![(1, 83), (2, 55)]);
+        assert_eq!(warnings[1].message, "Note 2 is below the Soprano (C4-A5) range");
+
+        // Narrowing the range moves no notes, so there is nothing to warn about
+        let mut narrowed = before.clone();
+        narrowed.settings.pitch_range = Some(PitchRange::new("", 60, 70).unwrap());
+        assert_eq!(out_of_range_notes(&narrowed), vec![0, 1]);
+        assert!(range_warnings(&before, &narrowed).is_empty());
+    }
+
+    #[test]
+    fn test_fold_into_range() {
+        let pitches = |notes: &[Note]| notes.iter().map(|n| n.pitch).collect::<Vec<u8>>();
+        // D6 E6 C6 G6 in C4-C6: each folded note keeps its step from the one before
+        let mut notes: Vec<Note> = [86, 88, 84, 91].iter().enumerate().map(|(i, &p)| Note::new(p, i as u32 * 480, 480)).collect();
+        let folded = fold_into_range(&mut notes, 60, 84).unwrap();
+        assert_eq!(pitches(&notes), vec![74, 76, 84, 79]);
+        assert_eq!(folded, vec![
+            FoldedNote { note_index: 0, from: 86, to: 74 },
+            FoldedNote { note_index: 1, from: 88, to: 76 },
+            FoldedNote { note_index: 3, from: 91, to: 79 },
+        ]);
+
+        // With two octaves to choose from, a folded note still moves the way
+        // the melody did: G5 up to B6 lands on B5, not the nearer B4 below
+        let mut notes = vec![Note::new(79, 0, 480), Note::new(95, 480, 480)];
+        fold_into_range(&mut notes, 60, 84).unwrap();
+        assert_eq!(pitches(&notes), vec![79, 83]);
+        let mut notes = vec![Note::new(55, 0, 480), Note::new(57, 480, 480), Note::new(60, 960, 480)];
+        fold_into_range(&mut notes, 60, 84).unwrap();
+        assert_eq!(pitches(&notes), vec![67, 69, 60]);
+
+        // Voices are followed separately, in time order
+        let mut notes = vec![Note::with_voice(40, 480, 480, 100, 0), Note::with_voice(64, 0, 480, 100, 1), Note::new(50, 0, 480)];
+        let folded = fold_into_range(&mut notes, 48, 72).unwrap();
+        assert_eq!(pitches(&notes), vec![52, 64, 50]);
+        assert_eq!(folded.len(), 1);
+
+        assert!(fold_into_range(&mut notes, 60, 70).is_err());
+        assert!(fold_into_range(&mut notes, 72, 60).is_err());
+    }
+}
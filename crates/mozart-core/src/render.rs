@@ -0,0 +1,333 @@
+//! Offline audio rendering
+//!
+//! Synthesizes a song to stereo PCM with the same timbres the web audio
+//! engine plays (see [`Instrument`]), so an audio export sounds like the app.
+//! [`RenderedAudio::time_stretch`] changes the speed of rendered audio while
+//! keeping its pitch, using WSOLA (waveform-similarity overlap-add): the
+//! audio is cut into overlapping windowed frames, and each frame is taken
+//! from near its nominal position wherever it best lines up with the
+//! waveform already written, so the cross-fades don't smear or beat.
+//! Practice tracks use it to slow a song down for playing along.
+
+use crate::error::{MozartError, Result};
+use crate::instrument::Instrument;
+use crate::song::{format_duration, Song};
+use crate::time::PlaybackRate;
+
+/// Sample rate of audio exports
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// Longest song [`render_song`] renders, in seconds
+///
+/// Half an hour of stereo at 44.1 kHz is already 600 MB of samples, and a
+/// practice track slowed to a quarter speed is four times that.
+pub const MAX_RENDER_SECONDS: f64 = 30.0 * 60.0;
+
+/// Peak level renders are scaled down to when notes pile up
+const MAX_PEAK: f32 = 0.9;
+
+/// WSOLA frame length in seconds (frames overlap by half)
+const STRETCH_FRAME_SECONDS: f64 = 0.046;
+
+/// How far a WSOLA frame may move from its nominal position, in seconds
+const STRETCH_TOLERANCE_SECONDS: f64 = 0.012;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Waveform {
+    Sine,
+    Triangle,
+    Square,
+    Sawtooth,
+}
+
+impl Waveform {
+    /// Level at `phase` (0-1 through one cycle)
+    fn sample(&self, phase: f64) -> f64 {
+        match self {
+            Waveform::Sine => (phase * std::f64::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Square => {
+                if phase < 0.5 { 1.0 } else { -1.0 }
+            }
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+/// Waveform and envelope (seconds, sustain as a fraction of the peak)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Timbre {
+    waveform: Waveform,
+    attack: f64,
+    decay: f64,
+    sustain: f64,
+    release: f64,
+}
+
+impl Timbre {
+    /// The timbre the web audio engine gives a voice of `instrument`
+    fn of(instrument: Instrument, voice: u8) -> Timbre {
+        let (waveform, attack, decay, sustain, release) = match instrument {
+            Instrument::Piano => (Waveform::Triangle, 0.005, 0.4, 0.25, 0.2),
+            Instrument::Organ => (Waveform::Square, 0.01, 0.02, 0.9, 0.05),
+            Instrument::Strings => (Waveform::Sawtooth, 0.12, 0.2, 0.8, 0.3),
+            Instrument::Flute => (Waveform::Sine, 0.06, 0.1, 0.85, 0.12),
+            Instrument::Synth => {
+                let waveforms = [Waveform::Sine, Waveform::Triangle, Waveform::Square, Waveform::Sawtooth];
+                let waveform = waveforms[voice as usize % waveforms.len()];
+                if voice == 0 { (waveform, 0.01, 0.1, 0.7, 0.15) } else { (waveform, 0.02, 0.1, 0.5, 0.15) }
+            }
+        };
+        Timbre { waveform, attack, decay, sustain, release }
+    }
+
+    /// Envelope level `t` seconds into a note lasting `duration` seconds
+    fn envelope(&self, t: f64, duration: f64) -> f64 {
+        let level = |t: f64| {
+            if t < self.attack {
+                t / self.attack
+            } else if t < self.attack + self.decay {
+                1.0 - (1.0 - self.sustain) * (t - self.attack) / self.decay
+            } else {
+                self.sustain
+            }
+        };
+        let release_start = (duration - self.release).max(0.0);
+        if t < release_start {
+            level(t)
+        } else {
+            level(release_start) * ((duration - t) / (duration - release_start)).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Stereo audio at a sample rate
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedAudio {
+    pub sample_rate: u32,
+    pub left: Vec<f32>,
+    pub right: Vec<f32>,
+}
+
+impl RenderedAudio {
+    pub fn duration_seconds(&self) -> f64 {
+        self.left.len() as f64 / self.sample_rate as f64
+    }
+
+    /// The same audio played at `rate` (0.5 = half speed) without changing its pitch
+    pub fn time_stretch(&self, rate: PlaybackRate) -> RenderedAudio {
+        if rate.value() == 1.0 || self.left.is_empty() {
+            return self.clone();
+        }
+        let frame = ((self.sample_rate as f64 * STRETCH_FRAME_SECONDS) as usize / 2 * 2).max(4);
+        let hop = frame / 2;
+        let tolerance = (self.sample_rate as f64 * STRETCH_TOLERANCE_SECONDS) as isize;
+        // Periodic Hann windows at half overlap sum to one
+        let window: Vec<f32> = (0..frame)
+            .map(|n| (0.5 - 0.5 * (std::f64::consts::TAU * n as f64 / frame as f64).cos()) as f32)
+            .collect();
+
+        // Frames line up on the mid channel, and both channels take the same ones.
+        // Reads are offset by a hop of silence, so the first frame fades in from
+        // before the start instead of over the first notes.
+        let mid: Vec<f32> = self.left.iter().zip(&self.right).map(|(l, r)| (l + r) / 2.0).collect();
+        let at = |samples: &[f32], i: isize| -> f32 {
+            let i = i - hop as isize;
+            if i < 0 { 0.0 } else { samples.get(i as usize).copied().unwrap_or(0.0) }
+        };
+        let similarity = |natural: isize, candidate: isize| -> f32 {
+            let (mut dot, mut energy) = (0.0f32, 0.0f32);
+            for n in (0..hop as isize).step_by(4) {
+                let c = at(&mid, candidate + n);
+                dot += at(&mid, natural + n) * c;
+                energy += c * c;
+            }
+            if energy > 0.0 { dot / energy.sqrt() } else { 0.0 }
+        };
+
+        let out_len = (self.left.len() as f64 / rate.value()).ceil() as usize;
+        let mut left = vec![0.0f32; out_len + 2 * frame];
+        let mut right = vec![0.0f32; out_len + 2 * frame];
+        let mut previous: isize = 0;
+        let mut k = 0usize;
+        while k * hop < out_len + hop {
+            let start = if k == 0 {
+                0
+            } else {
+                // Search coarsely, then refine around the best coarse match
+                let nominal = (k as f64 * hop as f64 * rate.value()).round() as isize;
+                let natural = previous + hop as isize;
+                let best = |candidates: &mut dyn Iterator<Item = isize>| {
+                    candidates
+                        .filter(|&c| c >= 0)
+                        .map(|c| (c, similarity(natural, c)))
+                        .fold((nominal.max(0), f32::MIN), |best, next| if next.1 > best.1 { next } else { best })
+                        .0
+                };
+                let coarse = best(&mut (-tolerance..=tolerance).step_by(4).map(|d| nominal + d));
+                best(&mut (-3..=3).map(|d| coarse + d))
+            };
+            for (n, w) in window.iter().enumerate() {
+                let (source, target) = (start + n as isize, k * hop + n);
+                left[target] += at(&self.left, source) * w;
+                right[target] += at(&self.right, source) * w;
+            }
+            previous = start;
+            k += 1;
+        }
+        // Drop the leading hop of silence
+        left.drain(..hop);
+        right.drain(..hop);
+        left.truncate(out_len);
+        right.truncate(out_len);
+        tracing::debug!("Time-stretched {:.1}s of audio at {}", self.duration_seconds(), rate);
+        RenderedAudio { sample_rate: self.sample_rate, left, right }
+    }
+
+    /// Encode as a 16-bit stereo WAV file
+    pub fn to_wav(&self) -> Vec<u8> {
+        let to_pcm = |s: f32| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        let interleaved: Vec<i16> = self.left.iter().zip(&self.right).flat_map(|(&l, &r)| [to_pcm(l), to_pcm(r)]).collect();
+        encode_wav(self.sample_rate, 2, &interleaved)
+    }
+}
+
+/// Encode 16-bit PCM (interleaved if more than one channel) as a WAV file
+pub fn encode_wav(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    samples.iter().for_each(|s| bytes.extend_from_slice(&s.to_le_bytes()));
+    bytes
+}
+
+/// Render a song the way playback sounds (repeats, accent dynamics, strums, tempo map, pan)
+///
+/// Songs that play for longer than [`MAX_RENDER_SECONDS`] are refused.
+pub fn render_song(song: &Song, sample_rate: u32) -> Result<RenderedAudio> {
+    let song = &*song.as_played();
+    let notes = song.playback_notes();
+    let end = notes.iter().map(|note| song.tick_to_seconds(note.end_tick())).fold(0.0, f64::max);
+    if end > MAX_RENDER_SECONDS {
+        return Err(MozartError::RenderError(format!(
+            "The song plays for {}, longer than the {} that can be rendered",
+            format_duration(end),
+            format_duration(MAX_RENDER_SECONDS)
+        )));
+    }
+    let len = (end * sample_rate as f64).ceil() as usize;
+    let mut left = vec![0.0f32; len];
+    let mut right = vec![0.0f32; len];
+
+    for note in &notes {
+        let timbre = Timbre::of(song.voice_instrument(note.voice), note.voice);
+        let start = song.tick_to_seconds(note.start_tick);
+        let duration = song.tick_to_seconds(note.end_tick()) - start;
+        // Harmony voices are detuned by 5 cents a voice, as in the web engine
+        let cents = note.voice as f64 * 5.0;
+        let frequency = 440.0 * 2f64.powf((note.pitch as f64 - 69.0) / 12.0 + cents / 1200.0);
+        let amp = note.velocity as f64 / 127.0 * 0.5;
+        let (pan_left, pan_right) = note.pan_gains();
+
+        let first = (start * sample_rate as f64).round() as usize;
+        let count = (duration * sample_rate as f64).round() as usize;
+        for i in 0..count.min(len.saturating_sub(first)) {
+            let t = i as f64 / sample_rate as f64;
+            let value = (timbre.waveform.sample((t * frequency).fract()) * timbre.envelope(t, duration) * amp) as f32;
+            left[first + i] += value * pan_left;
+            right[first + i] += value * pan_right;
+        }
+    }
+
+    let peak = left.iter().chain(&right).fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak > MAX_PEAK {
+        let gain = MAX_PEAK / peak;
+        left.iter_mut().chain(right.iter_mut()).for_each(|s| *s *= gain);
+    }
+    tracing::debug!("Rendered {} notes to {:.1}s of audio", notes.len(), end);
+    Ok(RenderedAudio { sample_rate, left, right })
+}
+
+/// A practice track: the song rendered and slowed (or sped up) to `rate`, pitch unchanged, as WAV bytes
+pub fn render_practice_wav(song: &Song, rate: PlaybackRate, sample_rate: u32) -> Result<Vec<u8>> {
+    Ok(render_song(song, sample_rate)?.time_stretch(rate).to_wav())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    /// Cycles per second, from upward zero crossings
+    fn frequency(samples: &[f32], sample_rate: u32) -> f64 {
+        let crossings = samples.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        crossings as f64 / (samples.len() as f64 / sample_rate as f64)
+    }
+
+    #[test]
+    fn test_render_song() {
+        let mut song = Song::new();
+        song.settings.instrument = Instrument::Flute;
+        song.add_note(Note::new(69, 0, 480)); // A4 for half a second at 120 BPM
+        song.add_note(Note::new(69, 480, 480));
+        let audio = render_song(&song, 8_000).unwrap();
+
+        assert_eq!(audio.left.len(), 8_000);
+        assert_eq!(audio.left, audio.right);
+        assert!((frequency(&audio.left, 8_000) - 440.0).abs() < 5.0);
+        assert!(audio.left.iter().all(|s| s.abs() <= MAX_PEAK));
+        // Enveloped in and out of each note
+        assert_eq!(audio.left[0], 0.0);
+        assert!(audio.left[3_990..4_000].iter().all(|s| s.abs() < 0.05));
+        assert!(render_song(&Song::new(), 8_000).unwrap().left.is_empty());
+
+        // A note half an hour in is past the limit
+        song.add_note(Note::new(60, 1_728_000, 480));
+        assert!(matches!(render_song(&song, 8_000), Err(MozartError::RenderError(_))));
+    }
+
+    #[test]
+    fn test_time_stretch_keeps_pitch() {
+        let mut song = Song::new();
+        song.settings.instrument = Instrument::Organ;
+        song.add_note(Note::new(64, 0, 960)); // E4, one second
+        let audio = render_song(&song, 8_000).unwrap();
+        let original = frequency(&audio.left[1_000..7_000], 8_000);
+
+        let slow = audio.time_stretch(PlaybackRate::new(0.5).unwrap());
+        assert_eq!(slow.left.len(), 16_000);
+        assert!((slow.duration_seconds() - 2.0).abs() < 1e-9);
+        assert!((frequency(&slow.left[2_000..14_000], 8_000) - original).abs() < 5.0);
+
+        let fast = audio.time_stretch(PlaybackRate::new(2.0).unwrap());
+        assert_eq!(fast.left.len(), 4_000);
+        assert!((frequency(&fast.left[500..3_500], 8_000) - original).abs() < 5.0);
+
+        assert_eq!(audio.time_stretch(PlaybackRate::default()), audio);
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        let wav = render_practice_wav(&song, PlaybackRate::new(0.5).unwrap(), 8_000).unwrap();
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 2);
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 8_000);
+        // A quarter note at 120 BPM, slowed to half speed: one second of stereo samples
+        assert_eq!(wav.len(), 44 + 8_000 * 4);
+    }
+}
@@ -0,0 +1,577 @@
+//! Repeat structure: repeat barlines, volta brackets, and jumps
+//!
+//! The song keeps its written (compact) form: a repeated section is stored
+//! once, with repeat barlines around it, numbered endings (1st/2nd volta
+//! brackets), and at most one jump (D.C. or D.S., al Fine or al Coda) with
+//! its signs. [`RepeatStructure::measure_order`] lists the measures in the
+//! order they are played, which `Song::flatten_repeats` uses to unfold the
+//! song for playback and MIDI export. Measures are 1-indexed.
+//!
+//! As is customary, repeats are not taken again after the jump, so a
+//! section with endings plays its last ending from there on.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+
+/// Most times a section can be repeated
+pub const MAX_REPEAT_TIMES: u32 = 16;
+
+/// Longest measure order produced, guarding against runaway structures
+const MAX_PLAYED_MEASURES: usize = 10_000;
+
+/// Repeat barlines around measures `start_measure..=end_measure`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Repeat {
+    pub start_measure: u32,
+    pub end_measure: u32,
+    /// How many times the section is played in all (2 for a plain repeat)
+    pub times: u32,
+}
+
+impl Repeat {
+    pub fn new(start_measure: u32, end_measure: u32) -> Self {
+        Repeat { start_measure, end_measure, times: 2 }
+    }
+
+    /// Parse "5-8", "5-8 x3", or "4" (a single measure)
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let (start_measure, end_measure) = parse_span(parts.next().unwrap_or(""))?;
+        let times = match parts.next() {
+            None => 2,
+            Some(times) => times
+                .trim_start_matches(['x', 'X'])
+                .trim_end_matches(['x', 'X'])
+                .parse()
+                .map_err(|_| MozartError::ParseError(format!("Invalid repeat count: {}", times)))?,
+        };
+        if parts.next().is_some() {
+            return Err(MozartError::ParseError(format!("Invalid repeat: {} (use e.g. 5-8 x2)", s)));
+        }
+        Ok(Repeat { start_measure, end_measure, times })
+    }
+}
+
+impl fmt::Display for Repeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} x{}", span_name(self.start_measure, self.end_measure), self.times)
+    }
+}
+
+/// Numbered ending (volta bracket) over measures `start_measure..=end_measure`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ending {
+    pub start_measure: u32,
+    pub end_measure: u32,
+    /// Passes through the enclosing repeat that play this ending (1 is the first)
+    pub passes: Vec<u32>,
+}
+
+impl Ending {
+    /// Parse "8 1", "9-10 2", or "8 1,2"
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let (start_measure, end_measure) = parse_span(parts.next().unwrap_or(""))?;
+        let passes = parts.next().ok_or_else(|| {
+            MozartError::ParseError(format!("Ending needs its pass numbers: {} (use e.g. 8 1)", s))
+        })?;
+        let mut passes = passes
+            .split(',')
+            .map(|pass| {
+                pass.trim_end_matches('.')
+                    .parse()
+                    .map_err(|_| MozartError::ParseError(format!("Invalid ending pass: {}", pass)))
+            })
+            .collect::<Result<Vec<u32>>>()?;
+        if parts.next().is_some() {
+            return Err(MozartError::ParseError(format!("Invalid ending: {} (use e.g. 8 1)", s)));
+        }
+        passes.sort_unstable();
+        passes.dedup();
+        Ok(Ending { start_measure, end_measure, passes })
+    }
+
+    fn contains(&self, measure: u32) -> bool {
+        (self.start_measure..=self.end_measure).contains(&measure)
+    }
+}
+
+impl fmt::Display for Ending {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let passes: Vec<String> = self.passes.iter().map(|pass| pass.to_string()).collect();
+        write!(f, "{} {}", span_name(self.start_measure, self.end_measure), passes.join(","))
+    }
+}
+
+/// Where a jump goes back to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpKind {
+    /// Da capo: back to the first measure
+    DaCapo,
+    /// Dal segno: back to the segno sign
+    DalSegno,
+}
+
+/// How far the music plays after a jump
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JumpEnd {
+    /// To the end of the song
+    #[default]
+    End,
+    /// Up to the Fine sign
+    Fine,
+    /// Up to the To Coda sign, then on from the coda
+    Coda,
+}
+
+/// D.C. or D.S. instruction at the end of a measure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jump {
+    pub kind: JumpKind,
+    #[serde(default)]
+    pub until: JumpEnd,
+    /// Measure the instruction is written at; the jump happens at its end
+    pub at_measure: u32,
+}
+
+impl Jump {
+    /// Parse "D.C. 16", "D.S. al Coda 24", "dc al fine 12", etc.
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || MozartError::ParseError(format!("Invalid jump: {} (use e.g. D.S. al Coda 24)", s));
+        let (name, measure) = s.trim().rsplit_once(char::is_whitespace).ok_or_else(invalid)?;
+        let at_measure = parse_measure(measure)?;
+        let name: String = name.chars().filter(|c| c.is_ascii_alphabetic()).collect::<String>().to_lowercase();
+        let (kind, until) = match name.as_str() {
+            "dc" | "dacapo" => (JumpKind::DaCapo, JumpEnd::End),
+            "dcalfine" | "dacapoalfine" => (JumpKind::DaCapo, JumpEnd::Fine),
+            "dcalcoda" | "dacapoalcoda" => (JumpKind::DaCapo, JumpEnd::Coda),
+            "ds" | "dalsegno" => (JumpKind::DalSegno, JumpEnd::End),
+            "dsalfine" | "dalsegnoalfine" => (JumpKind::DalSegno, JumpEnd::Fine),
+            "dsalcoda" | "dalsegnoalcoda" => (JumpKind::DalSegno, JumpEnd::Coda),
+            _ => return Err(invalid()),
+        };
+        Ok(Jump { kind, until, at_measure })
+    }
+
+    /// Instruction as written, e.g. "D.S. al Coda"
+    pub fn name(&self) -> &'static str {
+        match (self.kind, self.until) {
+            (JumpKind::DaCapo, JumpEnd::End) => "D.C.",
+            (JumpKind::DaCapo, JumpEnd::Fine) => "D.C. al Fine",
+            (JumpKind::DaCapo, JumpEnd::Coda) => "D.C. al Coda",
+            (JumpKind::DalSegno, JumpEnd::End) => "D.S.",
+            (JumpKind::DalSegno, JumpEnd::Fine) => "D.S. al Fine",
+            (JumpKind::DalSegno, JumpEnd::Coda) => "D.S. al Coda",
+        }
+    }
+}
+
+impl fmt::Display for Jump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name(), self.at_measure)
+    }
+}
+
+/// Sign a jump refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatSign {
+    /// Where D.S. goes back to (start of the measure)
+    Segno,
+    /// Where "al Fine" stops (end of the measure)
+    Fine,
+    /// Where "al Coda" leaves for the coda (end of the measure)
+    ToCoda,
+    /// Where the coda begins (start of the measure)
+    Coda,
+}
+
+impl RepeatSign {
+    /// Parse "segno 3", "fine 12", "to coda 16", or "coda 20"
+    pub fn parse(s: &str) -> Result<(RepeatSign, u32)> {
+        let invalid = || MozartError::ParseError(format!("Invalid sign: {} (use e.g. segno 3)", s));
+        let (name, measure) = s.trim().rsplit_once(char::is_whitespace).ok_or_else(invalid)?;
+        let sign = match name.split_whitespace().collect::<String>().to_lowercase().as_str() {
+            "segno" => RepeatSign::Segno,
+            "fine" => RepeatSign::Fine,
+            "tocoda" => RepeatSign::ToCoda,
+            "coda" => RepeatSign::Coda,
+            _ => return Err(invalid()),
+        };
+        Ok((sign, parse_measure(measure)?))
+    }
+}
+
+/// Repeat barlines, endings, jump, and signs of a song
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepeatStructure {
+    /// Sorted by start measure, never overlapping
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repeats: Vec<Repeat>,
+    /// Sorted by start measure, never overlapping
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub endings: Vec<Ending>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jump: Option<Jump>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segno: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fine: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_coda: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coda: Option<u32>,
+}
+
+impl RepeatStructure {
+    pub fn is_empty(&self) -> bool {
+        *self == RepeatStructure::default()
+    }
+
+    /// Repeats, endings, signs, and the jump, each counted once
+    pub fn mark_count(&self) -> usize {
+        let points = [self.segno, self.fine, self.to_coda, self.coda, self.jump.map(|j| j.at_measure)];
+        self.repeats.len() + self.endings.len() + points.iter().flatten().count()
+    }
+
+    /// Check that the structure can be played
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |msg: String| Err(MozartError::InvalidEdit(msg));
+        for (i, repeat) in self.repeats.iter().enumerate() {
+            if repeat.start_measure == 0 || repeat.start_measure > repeat.end_measure {
+                return invalid(format!("Invalid repeat of measures {}", repeat));
+            }
+            if !(2..=MAX_REPEAT_TIMES).contains(&repeat.times) {
+                return invalid(format!("A section repeats 2 to {} times, not {}", MAX_REPEAT_TIMES, repeat.times));
+            }
+            let overlaps = |other: &Repeat| other.start_measure <= repeat.end_measure && repeat.start_measure <= other.end_measure;
+            if self.repeats[i + 1..].iter().any(overlaps) {
+                return invalid(format!("Repeat {} overlaps another repeat", repeat));
+            }
+        }
+        for (i, ending) in self.endings.iter().enumerate() {
+            if ending.start_measure == 0 || ending.start_measure > ending.end_measure {
+                return invalid(format!("Invalid ending over measures {}", ending));
+            }
+            if ending.passes.is_empty() || ending.passes.iter().any(|&p| p == 0 || p > MAX_REPEAT_TIMES) {
+                return invalid(format!("Ending {} needs passes from 1 to {}", ending, MAX_REPEAT_TIMES));
+            }
+            let overlaps = |other: &Ending| other.start_measure <= ending.end_measure && ending.start_measure <= other.end_measure;
+            if self.endings[i + 1..].iter().any(overlaps) {
+                return invalid(format!("Ending {} overlaps another ending", ending));
+            }
+        }
+        if [self.segno, self.fine, self.to_coda, self.coda].contains(&Some(0)) {
+            return invalid("Signs are placed at measure 1 or later".to_string());
+        }
+        if let Some(jump) = self.jump {
+            if jump.at_measure == 0 {
+                return invalid("A jump is placed at measure 1 or later".to_string());
+            }
+            if jump.kind == JumpKind::DalSegno {
+                match self.segno {
+                    None => return invalid(format!("{} needs a segno; place one first", jump.name())),
+                    Some(segno) if segno > jump.at_measure => {
+                        return invalid(format!("The segno (measure {}) must come before the jump", segno))
+                    }
+                    _ => {}
+                }
+            }
+            match jump.until {
+                JumpEnd::Fine if self.fine.is_none() => {
+                    return invalid(format!("{} needs a Fine; place one first", jump.name()))
+                }
+                JumpEnd::Coda if self.to_coda.is_none() || self.coda.is_none() => {
+                    return invalid(format!("{} needs To Coda and Coda signs; place them first", jump.name()))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Put the repeats and endings in measure order, as edits keep them
+    pub fn sort(&mut self) {
+        self.repeats.sort_by_key(|r| r.start_measure);
+        self.endings.sort_by_key(|e| e.start_measure);
+    }
+
+    /// Add a repeat, replacing any it overlaps
+    pub fn add_repeat(&mut self, repeat: Repeat) -> Result<()> {
+        self.edited(|s| {
+            s.repeats.retain(|r| r.end_measure < repeat.start_measure || r.start_measure > repeat.end_measure);
+            s.repeats.push(repeat);
+            s.repeats.sort_by_key(|r| r.start_measure);
+        })
+    }
+
+    /// Add an ending, replacing any it overlaps
+    pub fn add_ending(&mut self, ending: Ending) -> Result<()> {
+        self.edited(|s| {
+            s.endings.retain(|e| e.end_measure < ending.start_measure || e.start_measure > ending.end_measure);
+            s.endings.push(ending);
+            s.endings.sort_by_key(|e| e.start_measure);
+        })
+    }
+
+    /// Set or clear the jump
+    pub fn set_jump(&mut self, jump: Option<Jump>) -> Result<()> {
+        self.edited(|s| s.jump = jump)
+    }
+
+    /// Place a sign, moving it if already placed
+    pub fn place_sign(&mut self, sign: RepeatSign, measure: u32) -> Result<()> {
+        self.edited(|s| *s.sign_mut(sign) = Some(measure))
+    }
+
+    /// Remove everything that starts, ends, or sits at a measure; returns whether anything was removed
+    ///
+    /// A jump whose sign is removed goes with it.
+    pub fn remove_at(&mut self, measure: u32) -> bool {
+        let before = self.clone();
+        self.repeats.retain(|r| r.start_measure != measure && r.end_measure != measure);
+        self.endings.retain(|e| !e.contains(measure));
+        if self.jump.is_some_and(|jump| jump.at_measure == measure) {
+            self.jump = None;
+        }
+        for sign in [RepeatSign::Segno, RepeatSign::Fine, RepeatSign::ToCoda, RepeatSign::Coda] {
+            let placed = self.sign_mut(sign);
+            if *placed == Some(measure) {
+                *placed = None;
+            }
+        }
+        if self.validate().is_err() {
+            self.jump = None;
+        }
+        *self != before
+    }
+
+    /// Follow measures deleted from the song: marks in them go, later ones move back
+    pub fn delete_measures(&mut self, first: u32, count: u32) {
+        let end = first + count;
+        let start_of = |m: u32| if m >= end { m - count } else { m.min(first) };
+        let end_of = |m: u32| if m >= end { m - count } else if m >= first { first - 1 } else { m };
+        let point = |m: Option<u32>| m.and_then(|m| if m >= end { Some(m - count) } else { Some(m).filter(|&m| m < first) });
+
+        for repeat in &mut self.repeats {
+            (repeat.start_measure, repeat.end_measure) = (start_of(repeat.start_measure), end_of(repeat.end_measure));
+        }
+        self.repeats.retain(|r| r.start_measure > 0 && r.start_measure <= r.end_measure);
+        for ending in &mut self.endings {
+            (ending.start_measure, ending.end_measure) = (start_of(ending.start_measure), end_of(ending.end_measure));
+        }
+        self.endings.retain(|e| e.start_measure > 0 && e.start_measure <= e.end_measure);
+        for sign in [RepeatSign::Segno, RepeatSign::Fine, RepeatSign::ToCoda, RepeatSign::Coda] {
+            let placed = self.sign_mut(sign);
+            *placed = point(*placed);
+        }
+        self.jump = self.jump.and_then(|jump| Some(Jump { at_measure: point(Some(jump.at_measure))?, ..jump }));
+        if self.validate().is_err() {
+            self.jump = None;
+        }
+    }
+
+    /// The last measure any mark refers to
+    pub fn last_measure(&self) -> u32 {
+        let spans = self.repeats.iter().map(|r| r.end_measure).chain(self.endings.iter().map(|e| e.end_measure));
+        let points = [self.segno, self.fine, self.to_coda, self.coda, self.jump.map(|j| j.at_measure)];
+        spans.chain(points.into_iter().flatten()).max().unwrap_or(0)
+    }
+
+    /// Written measures in the order they are played, for a song of `measure_count` measures
+    pub fn measure_order(&self, measure_count: u32) -> Vec<u32> {
+        let last = measure_count.max(self.last_measure());
+        // Current pass (1-based) through each repeat
+        let mut passes = vec![1u32; self.repeats.len()];
+        let mut jumped = false;
+        let mut order = Vec::new();
+        let mut measure = 1;
+
+        while measure <= last && order.len() < MAX_PLAYED_MEASURES {
+            // An ending inside a repeat is skipped on the passes it isn't marked for
+            let mut finished = measure;
+            match self.endings.iter().find(|e| e.start_measure == measure) {
+                Some(ending) if self.pass_skips(ending, &passes) => finished = ending.end_measure,
+                _ => order.push(measure),
+            }
+
+            measure = finished + 1;
+            if let Some(r) = self.repeats.iter().position(|r| r.end_measure == finished) {
+                if passes[r] < self.repeats[r].times {
+                    passes[r] += 1;
+                    measure = self.repeats[r].start_measure;
+                    continue;
+                }
+                passes[r] = 1;
+            }
+            match self.jump {
+                Some(jump) if jumped && jump.until == JumpEnd::Fine && self.fine == Some(finished) => break,
+                Some(jump) if jumped && jump.until == JumpEnd::Coda && self.to_coda == Some(finished) => {
+                    measure = self.coda.unwrap_or(measure);
+                }
+                Some(jump) if !jumped && jump.at_measure == finished => {
+                    jumped = true;
+                    // No repeats after the jump: every section is on its last pass
+                    for (pass, repeat) in passes.iter_mut().zip(&self.repeats) {
+                        *pass = repeat.times;
+                    }
+                    measure = match jump.kind {
+                        JumpKind::DaCapo => 1,
+                        JumpKind::DalSegno => self.segno.unwrap_or(1),
+                    };
+                }
+                _ => {}
+            }
+        }
+        order
+    }
+
+    // Apply an edit only if the result is still playable
+    fn edited(&mut self, edit: impl FnOnce(&mut Self)) -> Result<()> {
+        let mut next = self.clone();
+        edit(&mut next);
+        next.validate()?;
+        *self = next;
+        Ok(())
+    }
+
+    fn pass_skips(&self, ending: &Ending, passes: &[u32]) -> bool {
+        self.repeats
+            .iter()
+            .position(|r| (r.start_measure..=r.end_measure).contains(&ending.start_measure))
+            .is_some_and(|r| !ending.passes.contains(&passes[r]))
+    }
+
+    fn sign_mut(&mut self, sign: RepeatSign) -> &mut Option<u32> {
+        match sign {
+            RepeatSign::Segno => &mut self.segno,
+            RepeatSign::Fine => &mut self.fine,
+            RepeatSign::ToCoda => &mut self.to_coda,
+            RepeatSign::Coda => &mut self.coda,
+        }
+    }
+}
+
+fn parse_measure(s: &str) -> Result<u32> {
+    match s.trim().parse() {
+        Ok(measure) if measure > 0 => Ok(measure),
+        _ => Err(MozartError::ParseError(format!("Invalid measure: {}", s))),
+    }
+}
+
+// "5-8" or "5"
+fn parse_span(s: &str) -> Result<(u32, u32)> {
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (parse_measure(start)?, parse_measure(end)?),
+        None => (parse_measure(s)?, parse_measure(s)?),
+    };
+    if start > end {
+        return Err(MozartError::ParseError(format!("Measures out of order: {}", s)));
+    }
+    Ok((start, end))
+}
+
+fn span_name(start: u32, end: u32) -> String {
+    if start == end { start.to_string() } else { format!("{}-{}", start, end) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn structure(repeats: &[&str], endings: &[&str]) -> RepeatStructure {
+        let mut structure = RepeatStructure::default();
+        for repeat in repeats {
+            structure.add_repeat(Repeat::parse(repeat).unwrap()).unwrap();
+        }
+        for ending in endings {
+            structure.add_ending(Ending::parse(ending).unwrap()).unwrap();
+        }
+        structure
+    }
+
+    #[test]
+    fn test_validate_unsorted() {
+        let unsorted = |spans: &[(u32, u32)]| RepeatStructure {
+            repeats: spans.iter().map(|&(start, end)| Repeat::new(start, end)).collect(),
+            endings: spans.iter().map(|&(start, end)| Ending { start_measure: start, end_measure: end, passes: vec![1] }).collect(),
+            ..Default::default()
+        };
+        assert!(unsorted(&[(5, 6), (1, 2)]).validate().is_ok());
+        assert!(unsorted(&[(3, 6), (1, 4)]).validate().is_err());
+        assert!(unsorted(&[(1, 4), (3, 6)]).validate().is_err());
+
+        let mut repeats = unsorted(&[(5, 6), (1, 2)]);
+        repeats.sort();
+        assert_eq!(repeats, unsorted(&[(1, 2), (5, 6)]));
+    }
+
+    #[test]
+    fn test_repeats_and_endings() {
+        assert_eq!(RepeatStructure::default().measure_order(3), vec![1, 2, 3]);
+        assert_eq!(structure(&["2-3 x3"], &[]).measure_order(4), vec![1, 2, 3, 2, 3, 2, 3, 4]);
+
+        // |: 1 2 [1. 3 :| [2. 4 | 5
+        let voltas = structure(&["1-3"], &["3 1", "4 2"]);
+        assert_eq!(voltas.measure_order(5), vec![1, 2, 3, 1, 2, 4, 5]);
+        // |: 1 [1.2. 2 :| [3. 3 (ending at the repeat end)
+        let third = structure(&["1-2 x3"], &["2 1,2", "3 3"]);
+        assert_eq!(third.measure_order(3), vec![1, 2, 1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn test_jumps() {
+        // 1 |: 2 :| 3 D.C. al Fine, Fine at 2: no repeat after the jump
+        let mut dc = structure(&["2"], &[]);
+        dc.place_sign(RepeatSign::Fine, 2).unwrap();
+        dc.set_jump(Some(Jump::parse("D.C. al Fine 3").unwrap())).unwrap();
+        assert_eq!(dc.measure_order(3), vec![1, 2, 2, 3, 1, 2]);
+
+        // 1 segno 2 to-coda 3 4 D.S. al Coda | coda 5 6
+        let mut ds = RepeatStructure::default();
+        assert!(ds.set_jump(Some(Jump::parse("ds al coda 4").unwrap())).is_err());
+        assert!(ds.is_empty());
+        for sign in ["segno 2", "to coda 3", "coda 5"] {
+            let (sign, measure) = RepeatSign::parse(sign).unwrap();
+            ds.place_sign(sign, measure).unwrap();
+        }
+        ds.set_jump(Some(Jump::parse("D.S. al Coda 4").unwrap())).unwrap();
+        assert_eq!(ds.measure_order(6), vec![1, 2, 3, 4, 2, 3, 5, 6]);
+        assert_eq!(ds.jump.unwrap().to_string(), "D.S. al Coda 4");
+
+        // Removing the segno takes the jump with it
+        assert!(ds.remove_at(2));
+        assert_eq!(ds.jump, None);
+        assert_eq!(ds.measure_order(6), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_parse_and_edit() {
+        assert_eq!(Repeat::parse("5-8 x3").unwrap(), Repeat { start_measure: 5, end_measure: 8, times: 3 });
+        assert_eq!(Repeat::parse("4").unwrap(), Repeat::new(4, 4));
+        assert!(Repeat::parse("8-5").is_err());
+        assert_eq!(Ending::parse("9-10 2,1").unwrap().passes, vec![1, 2]);
+        assert!(Ending::parse("9").is_err());
+        assert!(Jump::parse("D.X. 4").is_err());
+
+        // Overlaps replace, out-of-range counts are refused
+        let mut s = structure(&["1-4", "6-8"], &[]);
+        s.add_repeat(Repeat::parse("3-6").unwrap()).unwrap();
+        assert_eq!(s.repeats, vec![Repeat::new(3, 6)]);
+        assert!(s.clone().add_repeat(Repeat::parse("1 x20").unwrap()).is_err());
+
+        // Deleting measures 2-3 shrinks the repeat and moves later marks back
+        s.place_sign(RepeatSign::Coda, 9).unwrap();
+        s.delete_measures(2, 2);
+        assert_eq!(s.repeats, vec![Repeat::new(2, 4)]);
+        assert_eq!(s.coda, Some(7));
+
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(serde_json::from_str::<RepeatStructure>(&json).unwrap(), s);
+        assert_eq!(serde_json::to_string(&RepeatStructure::default()).unwrap(), "{}");
+    }
+}
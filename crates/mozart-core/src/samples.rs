@@ -0,0 +1,417 @@
+//! Instrument sample libraries (native only)
+//!
+//! A sample library is a directory of WAV files, one recorded pitch each,
+//! named after the pitch they hold ("Piano_C4.wav", "F#3-soft.wav", or a
+//! MIDI number like "060.wav"). [`SampleLibraries`] is the registry of the
+//! directories the user has added; [`SampleLibraries::scan`] reads every
+//! file's header and reports how much of the instrument's range the library
+//! covers, with the problems that would make it sound wrong: files without a
+//! pitch in their name, unreadable files, sample rates that differ from the
+//! rest of the library, and pitches too far from any sample to stretch to.
+//!
+//! On first run [`SampleLibraries::ensure_default_pack`] installs a small
+//! default pack so there is always something to play. It is synthesized
+//! locally (decaying triangle tones), which keeps it free to redistribute
+//! and needs no network access.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::error::{MozartError, Result};
+use crate::pitch::Pitch;
+use crate::register::PitchRange;
+use crate::render::encode_wav;
+
+/// Semitones a sample can be resampled up or down before it sounds unnatural
+pub const MAX_STRETCH_SEMITONES: u8 = 2;
+
+/// Directory name and instrument of the default pack
+pub const DEFAULT_PACK_NAME: &str = "mozart-basic";
+const DEFAULT_PACK_INSTRUMENT: &str = "Piano";
+const DEFAULT_PACK_SAMPLE_RATE: u32 = 44_100;
+const DEFAULT_PACK_SECONDS: f32 = 1.0;
+/// A sample every minor third leaves every pitch within a semitone of one
+const DEFAULT_PACK_STEP: usize = 3;
+
+/// A sample directory the user registered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleLibrary {
+    /// Unique, derived from the directory name (e.g., "grand-piano")
+    pub id: String,
+    pub path: PathBuf,
+    /// Range preset the library is meant to cover (e.g., "Cello"); empty for the full piano range
+    pub instrument: String,
+}
+
+impl SampleLibrary {
+    /// The range coverage is measured against
+    pub fn range(&self) -> Result<PitchRange> {
+        match self.instrument.is_empty() {
+            true => PitchRange::preset("Piano"),
+            false => PitchRange::preset(&self.instrument),
+        }
+    }
+}
+
+/// One usable sample file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleFile {
+    pub path: PathBuf,
+    pub pitch: u8,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// A problem found by a scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SampleIssue {
+    /// No pitch in the file name
+    UnknownPitch { path: PathBuf },
+    /// Not a WAV file this can read
+    Unreadable { path: PathBuf, reason: String },
+    /// Recorded at a different rate than most of the library
+    WrongSampleRate { path: PathBuf, sample_rate: u32, expected: u32 },
+    /// A second file for a pitch that already has one (the first is kept)
+    DuplicatePitch { path: PathBuf, pitch: u8 },
+    /// Pitches of the range no sample is close enough to stretch to
+    MissingPitches { low: u8, high: u8 },
+}
+
+impl SampleIssue {
+    pub fn description(&self) -> String {
+        let name = |midi: &u8| Pitch::from_midi(*midi).map_or_else(|_| midi.to_string(), |p| p.to_string());
+        match self {
+            SampleIssue::UnknownPitch { path } => format!("{}: no pitch in the file name", path.display()),
+            SampleIssue::Unreadable { path, reason } => format!("{}: {}", path.display(), reason),
+            SampleIssue::WrongSampleRate { path, sample_rate, expected } => {
+                format!("{}: recorded at {} Hz, the rest at {} Hz", path.display(), sample_rate, expected)
+            }
+            SampleIssue::DuplicatePitch { path, pitch } => format!("{}: second sample for {}", path.display(), name(pitch)),
+            SampleIssue::MissingPitches { low, high } if low == high => format!("No sample near {}", name(low)),
+            SampleIssue::MissingPitches { low, high } => format!("No samples near {}-{}", name(low), name(high)),
+        }
+    }
+}
+
+/// What a scan found in one library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryScan {
+    pub id: String,
+    pub range: PitchRange,
+    /// Usable samples, by pitch
+    pub samples: Vec<SampleFile>,
+    /// Share of the range within [`MAX_STRETCH_SEMITONES`] of a sample, 0-1
+    pub coverage: f32,
+    pub issues: Vec<SampleIssue>,
+}
+
+/// The registered sample libraries (persisted as JSON)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleLibraries {
+    pub libraries: Vec<SampleLibrary>,
+}
+
+impl SampleLibraries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the registry; a missing file is an empty registry (first run)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(MozartError::FileError(format!("Failed to read {:?}: {}", path, e))),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SampleLibrary> {
+        self.libraries.iter().find(|l| l.id == id)
+    }
+
+    /// Register a sample directory for `instrument` (a range preset, or "" for piano range)
+    pub fn add(&mut self, path: impl Into<PathBuf>, instrument: &str) -> Result<&SampleLibrary> {
+        let path = path.into();
+        if !path.is_dir() {
+            return Err(MozartError::FileError(format!("Not a directory: {:?}", path)));
+        }
+        if self.libraries.iter().any(|l| l.path == path) {
+            return Err(MozartError::FileError(format!("Already added: {:?}", path)));
+        }
+        let instrument = match instrument.trim() {
+            "" => String::new(),
+            name => PitchRange::preset(name)?.name,
+        };
+
+        let stem = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let slug: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect();
+        let slug = match slug.trim_matches('-') {
+            "" => "library".to_string(),
+            s => s.to_string(),
+        };
+        let id = (1..)
+            .map(|n| if n == 1 { slug.clone() } else { format!("{}-{}", slug, n) })
+            .find(|id| self.get(id).is_none())
+            .unwrap_or(slug);
+
+        tracing::info!("Added sample library {} at {:?}", id, path);
+        self.libraries.push(SampleLibrary { id, path, instrument });
+        Ok(self.libraries.last().expect("just pushed"))
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<SampleLibrary> {
+        let index = self.libraries.iter().position(|l| l.id == id).ok_or_else(|| unknown(id))?;
+        Ok(self.libraries.remove(index))
+    }
+
+    /// Read every sample of a library and measure its coverage
+    pub fn scan(&self, id: &str) -> Result<LibraryScan> {
+        let library = self.get(id).ok_or_else(|| unknown(id))?;
+        scan_library(library)
+    }
+
+    /// On first run (nothing registered), install the default pack under `dir` and register it
+    pub fn ensure_default_pack(&mut self, dir: impl AsRef<Path>) -> Result<Option<&SampleLibrary>> {
+        if !self.libraries.is_empty() {
+            return Ok(None);
+        }
+        let path = install_default_pack(dir)?;
+        self.add(path, DEFAULT_PACK_INSTRUMENT).map(Some)
+    }
+}
+
+fn unknown(id: &str) -> MozartError {
+    MozartError::FileError(format!("No sample library '{}'", id))
+}
+
+/// The pitch a sample's file name names: a segment such as "C4" or "Bb2", or a MIDI number
+pub fn pitch_from_file_name(path: &Path) -> Option<u8> {
+    let stem = path.file_stem()?.to_string_lossy();
+    stem.split(['_', '-', ' ', '.']).rev().find_map(|segment| match segment.parse::<u8>() {
+        Ok(midi) => (midi <= 127).then_some(midi),
+        Err(_) => Pitch::parse(segment).ok().map(|p| p.midi()),
+    })
+}
+
+/// Scan a library directory (not necessarily registered)
+pub fn scan_library(library: &SampleLibrary) -> Result<LibraryScan> {
+    let range = library.range()?;
+    let mut entries: Vec<PathBuf> = fs::read_dir(&library.path)
+        .map_err(|e| MozartError::FileError(format!("Failed to read {:?}: {}", library.path, e)))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        .collect();
+    entries.sort();
+
+    let mut issues = Vec::new();
+    let mut by_pitch: BTreeMap<u8, SampleFile> = BTreeMap::new();
+    for path in entries {
+        let Some(pitch) = pitch_from_file_name(&path) else {
+            issues.push(SampleIssue::UnknownPitch { path });
+            continue;
+        };
+        let (sample_rate, channels) = match read_wav_format(&path) {
+            Ok(format) => format,
+            Err(e) => {
+                issues.push(SampleIssue::Unreadable { path, reason: e.to_string() });
+                continue;
+            }
+        };
+        if by_pitch.contains_key(&pitch) {
+            issues.push(SampleIssue::DuplicatePitch { path, pitch });
+            continue;
+        }
+        by_pitch.insert(pitch, SampleFile { path, pitch, sample_rate, channels });
+    }
+
+    // The library's rate is the one most of its samples use
+    let mut rates: BTreeMap<u32, usize> = BTreeMap::new();
+    by_pitch.values().for_each(|s| *rates.entry(s.sample_rate).or_default() += 1);
+    if let Some(expected) = rates.iter().max_by_key(|(_, count)| **count).map(|(rate, _)| *rate) {
+        for sample in by_pitch.values().filter(|s| s.sample_rate != expected) {
+            issues.push(SampleIssue::WrongSampleRate { path: sample.path.clone(), sample_rate: sample.sample_rate, expected });
+        }
+    }
+
+    let covered = |pitch: u8| by_pitch.keys().any(|&p| p.abs_diff(pitch) <= MAX_STRETCH_SEMITONES);
+    let mut covered_count = 0;
+    let mut gap: Option<(u8, u8)> = None;
+    for pitch in range.low..=range.high {
+        if covered(pitch) {
+            covered_count += 1;
+            if let Some((low, high)) = gap.take() {
+                issues.push(SampleIssue::MissingPitches { low, high });
+            }
+        } else {
+            gap = Some((gap.map_or(pitch, |(low, _)| low), pitch));
+        }
+    }
+    if let Some((low, high)) = gap {
+        issues.push(SampleIssue::MissingPitches { low, high });
+    }
+
+    let coverage = covered_count as f32 / (range.high - range.low + 1) as f32;
+    tracing::info!("Scanned {}: {} samples, {:.0}% of {}, {} issues", library.id, by_pitch.len(), coverage * 100.0, range, issues.len());
+    Ok(LibraryScan { id: library.id.clone(), range, samples: by_pitch.into_values().collect(), coverage, issues })
+}
+
+/// Header bytes read from each sample; the format chunk comes first in practice
+const WAV_HEADER_BYTES: u64 = 64 * 1024;
+
+/// Sample rate and channel count from a WAV file's header
+fn read_wav_format(path: &Path) -> Result<(u32, u16)> {
+    let mut data = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(WAV_HEADER_BYTES).read_to_end(&mut data))
+        .map_err(|e| MozartError::FileError(format!("Failed to read file: {}", e)))?;
+    let invalid = |reason: &str| MozartError::FileError(reason.to_string());
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("Not a WAV file"));
+    }
+    let mut at = 12;
+    while at + 8 <= data.len() {
+        let size = u32::from_le_bytes([data[at + 4], data[at + 5], data[at + 6], data[at + 7]]) as usize;
+        let body = &data[at + 8..];
+        if &data[at..at + 4] == b"fmt " {
+            if body.len() < 8 {
+                break;
+            }
+            let channels = u16::from_le_bytes([body[2], body[3]]);
+            let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+            return Ok((sample_rate, channels));
+        }
+        // Chunks are padded to an even length
+        at += 8 + size + size % 2;
+    }
+    Err(invalid("WAV file has no format chunk"))
+}
+
+/// Write 16-bit mono PCM
+fn write_wav(path: &Path, sample_rate: u32, samples: &[i16]) -> Result<()> {
+    fs::write(path, encode_wav(sample_rate, 1, samples))
+        .map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", path, e)))
+}
+
+/// Write the default pack into `dir`/[`DEFAULT_PACK_NAME`], returning its directory
+pub fn install_default_pack(dir: impl AsRef<Path>) -> Result<PathBuf> {
+    let pack = dir.as_ref().join(DEFAULT_PACK_NAME);
+    fs::create_dir_all(&pack).map_err(|e| MozartError::FileError(format!("Failed to create {:?}: {}", pack, e)))?;
+    let range = PitchRange::preset(DEFAULT_PACK_INSTRUMENT)?;
+    let length = (DEFAULT_PACK_SAMPLE_RATE as f32 * DEFAULT_PACK_SECONDS) as usize;
+
+    // Every third pitch from the bottom, plus the top so nothing is left past the last step
+    let mut pitches: Vec<u8> = (range.low..=range.high).step_by(DEFAULT_PACK_STEP).collect();
+    if pitches.last() != Some(&range.high) {
+        pitches.push(range.high);
+    }
+    for &pitch in &pitches {
+        let frequency = 440.0 * 2f32.powf((pitch as f32 - 69.0) / 12.0);
+        let samples: Vec<i16> = (0..length)
+            .map(|i| {
+                let t = i as f32 / DEFAULT_PACK_SAMPLE_RATE as f32;
+                let phase = (t * frequency).fract();
+                let triangle = 4.0 * (phase - 0.5).abs() - 1.0;
+                let envelope = (t * 400.0).min(1.0) * (-4.0 * t).exp();
+                (triangle * envelope * 0.5 * i16::MAX as f32) as i16
+            })
+            .collect();
+        let name = Pitch::from_midi(pitch)?.to_string();
+        write_wav(&pack.join(format!("{}_{}.wav", DEFAULT_PACK_NAME, name)), DEFAULT_PACK_SAMPLE_RATE, &samples)?;
+    }
+    tracing::info!("Installed default sample pack ({} samples) at {:?}", pitches.len(), pack);
+    Ok(pack)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mozart-samples-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pitch_from_file_name() {
+        let pitch = |name: &str| pitch_from_file_name(Path::new(name));
+        assert_eq!(pitch("Piano_C4.wav"), Some(60));
+        assert_eq!(pitch("Cello F#2 soft.wav"), Some(42));
+        assert_eq!(pitch("069.wav"), Some(69));
+        assert_eq!(pitch("Bb3-mf.wav"), Some(58));
+        assert_eq!(pitch("ambience.wav"), None);
+    }
+
+    #[test]
+    fn test_default_pack_and_registry() {
+        let dir = temp_dir("default");
+        let mut registry = SampleLibraries::new();
+        let id = registry.ensure_default_pack(&dir).unwrap().unwrap().id.clone();
+        assert_eq!(id, DEFAULT_PACK_NAME);
+        // Not on later runs
+        assert!(registry.ensure_default_pack(&dir).unwrap().is_none());
+
+        let scan = registry.scan(&id).unwrap();
+        assert_eq!(scan.coverage, 1.0);
+        assert_eq!(scan.issues, vec![]);
+        assert!(scan.samples.iter().all(|s| s.sample_rate == 44_100 && s.channels == 1));
+
+        let saved = dir.join("libraries.json");
+        registry.save(&saved).unwrap();
+        assert_eq!(SampleLibraries::load(&saved).unwrap(), registry);
+        assert_eq!(SampleLibraries::load(dir.join("missing.json")).unwrap(), SampleLibraries::new());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_scan_reports_issues() {
+        let dir = temp_dir("issues");
+        let library = dir.join("Cello Samples");
+        fs::create_dir_all(&library).unwrap();
+        let tone = vec![0i16; 16];
+        write_wav(&library.join("C2.wav"), 48_000, &tone).unwrap();
+        write_wav(&library.join("E2.wav"), 48_000, &tone).unwrap();
+        write_wav(&library.join("G2.wav"), 44_100, &tone).unwrap();
+        write_wav(&library.join("036.wav"), 48_000, &tone).unwrap();
+        write_wav(&library.join("room.wav"), 48_000, &tone).unwrap();
+        fs::write(library.join("A2.wav"), b"not audio").unwrap();
+
+        let mut registry = SampleLibraries::new();
+        let id = registry.add(&library, "cello").unwrap().id.clone();
+        assert_eq!(id, "cello-samples");
+        assert!(registry.add(&library, "").is_err());
+
+        let scan = registry.scan(&id).unwrap();
+        assert_eq!(scan.range.name, "Cello");
+        assert_eq!(scan.samples.iter().map(|s| s.pitch).collect::<Vec<_>>(), vec![36, 40, 43]);
+        assert!(scan.samples[0].path.ends_with("036.wav"));
+        let kinds: Vec<&str> = scan
+            .issues
+            .iter()
+            .map(|issue| match issue {
+                SampleIssue::UnknownPitch { .. } => "unknown",
+                SampleIssue::Unreadable { .. } => "unreadable",
+                SampleIssue::WrongSampleRate { .. } => "rate",
+                SampleIssue::DuplicatePitch { .. } => "duplicate",
+                SampleIssue::MissingPitches { .. } => "missing",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["unreadable", "duplicate", "unknown", "rate", "missing"]);
+        // C2 to G2 plus two semitones stretch: 36-45 of the cello's 36-76
+        assert_eq!(scan.issues.last(), Some(&SampleIssue::MissingPitches { low: 46, high: 76 }));
+        assert!((scan.coverage - 10.0 / 41.0).abs() < 1e-6);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
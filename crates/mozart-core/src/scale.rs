@@ -162,7 +162,7 @@ impl Scale {
 
     /// Get the pitch class at a given scale degree (1-7)
     pub fn degree(&self, degree: u8) -> Option<PitchClass> {
-        if degree < 1 || degree > 7 {
+        if !(1..=7).contains(&degree) {
             return None;
         }
         let interval = self.scale_type.intervals()[(degree - 1) as usize];
@@ -0,0 +1,238 @@
+//! Application settings export and import
+//!
+//! Every preference the app persists (new-song defaults, macros and their
+//! shortcuts, saved accent presets, audio and metronome options, playhead
+//! follow, the watch folder) in one JSON file, so a setup can move to another machine. Fields
+//! missing from a file take their defaults, and files written by a newer
+//! version are refused rather than half-read.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use crate::error::{MozartError, Result};
+use crate::macros::MacroLibrary;
+use crate::presets::PresetLibrary;
+use crate::song::{Song, SongDefaults};
+use crate::view::FollowMode;
+
+/// Format version written by [`AppSettings::to_json`]
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Latency categories the audio engine accepts
+pub const LATENCY_CATEGORIES: [&str; 3] = ["interactive", "balanced", "playback"];
+
+/// Requested audio output latency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AudioLatency {
+    /// "interactive", "balanced", or "playback"
+    Category(String),
+    /// Buffer latency in seconds
+    Seconds(f64),
+}
+
+/// Audio engine options
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// None for the device default
+    pub sample_rate: Option<u32>,
+    pub latency: AudioLatency,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings { sample_rate: None, latency: AudioLatency::Category("interactive".to_string()) }
+    }
+}
+
+/// Metronome sounds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetronomeSettings {
+    /// Polyrhythm layer click, in Hz
+    pub polyrhythm_frequency: f32,
+    /// Polyrhythm layer volume, 0-1
+    pub polyrhythm_volume: f32,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        MetronomeSettings { polyrhythm_frequency: 1500.0, polyrhythm_volume: 0.6 }
+    }
+}
+
+/// Folder watched for MIDI exports to import (see `watch.rs`, native only)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchFolderSettings {
+    pub enabled: bool,
+    /// Folder the notation software exports to
+    pub folder: String,
+    /// Directory imported songs are saved in
+    pub library: String,
+    /// How often to check the folder, in milliseconds
+    pub poll_ms: u64,
+}
+
+impl Default for WatchFolderSettings {
+    fn default() -> Self {
+        WatchFolderSettings { enabled: false, folder: String::new(), library: String::new(), poll_ms: 1_000 }
+    }
+}
+
+impl WatchFolderSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && (self.folder.trim().is_empty() || self.library.trim().is_empty()) {
+            return Err(MozartError::ParseError("Watch folder needs a folder and a library".to_string()));
+        }
+        if !(100..=60_000).contains(&self.poll_ms) {
+            return Err(MozartError::ParseError(format!("Watch folder interval out of range: {} ms", self.poll_ms)));
+        }
+        Ok(())
+    }
+}
+
+/// All persisted preferences
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub version: u32,
+    pub song_defaults: SongDefaults,
+    /// Macros, including their keyboard shortcuts
+    pub macros: MacroLibrary,
+    pub accent_presets: PresetLibrary,
+    pub audio: AudioSettings,
+    pub metronome: MetronomeSettings,
+    /// Playhead follow, as [`FollowMode::name`] (e.g., "page-flip")
+    pub follow_mode: String,
+    /// Keep the screen awake while playing
+    pub prevent_sleep: bool,
+    pub watch_folder: WatchFolderSettings,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            version: SETTINGS_VERSION,
+            song_defaults: SongDefaults::default(),
+            macros: MacroLibrary::new(),
+            accent_presets: PresetLibrary::new(),
+            audio: AudioSettings::default(),
+            metronome: MetronomeSettings::default(),
+            follow_mode: FollowMode::default().name().to_string(),
+            prevent_sleep: true,
+            watch_folder: WatchFolderSettings::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    /// Check every value can be applied as is
+    pub fn validate(&self) -> Result<()> {
+        if self.version > SETTINGS_VERSION {
+            return Err(MozartError::ParseError(format!(
+                "Settings are from a newer version of Mozart (format {}, this one reads {})",
+                self.version, SETTINGS_VERSION
+            )));
+        }
+        Song::new_with_defaults(&self.song_defaults)?;
+        FollowMode::parse(&self.follow_mode)?;
+        if let Some(rate) = self.audio.sample_rate.filter(|rate| !(8_000..=192_000).contains(rate)) {
+            return Err(MozartError::ParseError(format!("Unsupported sample rate: {} Hz", rate)));
+        }
+        match &self.audio.latency {
+            AudioLatency::Category(c) if !LATENCY_CATEGORIES.contains(&c.as_str()) => {
+                return Err(MozartError::ParseError(format!("Unknown latency: {}", c)));
+            }
+            AudioLatency::Seconds(s) if !(0.0..=1.0).contains(s) => {
+                return Err(MozartError::ParseError(format!("Latency out of range: {} s", s)));
+            }
+            _ => {}
+        }
+        let volume = self.metronome.polyrhythm_volume;
+        if !(0.0..=1.0).contains(&volume) || self.metronome.polyrhythm_frequency <= 0.0 {
+            return Err(MozartError::ParseError("Metronome sound out of range".to_string()));
+        }
+        self.watch_folder.validate()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&AppSettings { version: SETTINGS_VERSION, ..self.clone() })?)
+    }
+
+    /// Parse and validate exported settings
+    pub fn from_json(json: &str) -> Result<Self> {
+        let settings: AppSettings = serde_json::from_str(json)?;
+        settings.validate()?;
+        tracing::debug!("Read settings: {} macros, {} accent presets", settings.macros.macros.len(), settings.accent_presets.user.len());
+        Ok(settings)
+    }
+
+    /// Export to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        tracing::info!("Exporting settings to {:?}", path);
+        std::fs::write(path, self.to_json()?)
+            .map_err(|e| MozartError::FileError(format!("Failed to write file: {}", e)))
+    }
+
+    /// Import from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        tracing::info!("Importing settings from {:?}", path);
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| MozartError::FileError(format!("Failed to read file: {}", e)))?;
+        Self::from_json(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::{Macro, MacroStep};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = AppSettings::default();
+        settings.song_defaults.time_signature = "6/8".to_string();
+        settings.audio = AudioSettings { sample_rate: Some(48_000), latency: AudioLatency::Seconds(0.02) };
+        settings.follow_mode = FollowMode::Continuous.name().to_string();
+        settings.watch_folder = WatchFolderSettings {
+            enabled: true,
+            folder: "/tmp/exports".to_string(),
+            library: "/tmp/library".to_string(),
+            poll_ms: 500,
+        };
+        settings.macros.macros.push(Macro {
+            name: "Octave up".to_string(),
+            shortcut: Some("Mod+Alt+1".to_string()),
+            steps: vec![MacroStep::new("transpose.octaveUp", &[])],
+        });
+
+        let path = std::env::temp_dir().join(format!("mozart-settings-{}.json", std::process::id()));
+        settings.save(&path).unwrap();
+        assert_eq!(AppSettings::load(&path).unwrap(), settings);
+        std::fs::remove_file(&path).ok();
+
+        // Missing fields take their defaults
+        let partial = AppSettings::from_json(r#"{"prevent_sleep": false}"#).unwrap();
+        assert_eq!(partial, AppSettings { prevent_sleep: false, ..AppSettings::default() });
+    }
+
+    #[test]
+    fn test_rejects_invalid() {
+        for json in [
+            r#"{"version": 99}"#,
+            r#"{"song_defaults": {"time_signature": "4/0"}}"#,
+            r#"{"follow_mode": "sideways"}"#,
+            r#"{"audio": {"latency": "fast"}}"#,
+            r#"{"audio": {"sample_rate": 1000}}"#,
+            r#"{"metronome": {"polyrhythm_volume": 2.0}}"#,
+            r#"{"watch_folder": {"enabled": true, "folder": "~/Exports"}}"#,
+            r#"{"watch_folder": {"poll_ms": 0}}"#,
+        ] {
+            assert!(AppSettings::from_json(json).is_err(), "{}", json);
+        }
+    }
+}
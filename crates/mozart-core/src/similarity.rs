@@ -0,0 +1,341 @@
+//! Melodic similarity
+//!
+//! Scores how alike two melodies are, for teachers checking whether a
+//! student's "original" exercise copies the example and for finding
+//! duplicates in a song library. A melody is a voice's top line, compared as
+//! the intervals from note to note (so a copy in another key still matches)
+//! and the time from one onset to the next. The interval sequences are
+//! aligned by edit distance, an interval a semitone off costing half a
+//! change; the rhythm score compares the onset gaps of the aligned notes.
+//! The best-matching stretch of the two lines is found by local alignment
+//! and reported as tick ranges in both songs.
+
+use serde::{Deserialize, Serialize};
+use crate::continuation::top_line;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::song::Song;
+
+/// Share of the score that comes from the intervals (the rest is rhythm)
+const INTERVAL_WEIGHT: f32 = 0.7;
+
+/// Fewest matching intervals that make a region worth reporting
+const MIN_REGION_INTERVALS: i32 = 3;
+
+/// The stretch where two melodies match best
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchedRegion {
+    pub a_start_tick: u32,
+    pub a_end_tick: u32,
+    pub b_start_tick: u32,
+    pub b_end_tick: u32,
+    /// Where the region starts in the first song, e.g., "5.1.0"
+    pub a_start_position: String,
+    pub b_start_position: String,
+    /// Notes of the first melody in the region
+    pub notes: usize,
+    /// Semitones from the first melody to the second, at the region's start
+    pub transposition: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimilarityReport {
+    /// Percent of the intervals alike, after aligning the two lines
+    pub interval_similarity: f32,
+    /// Percent alike in the onset gaps of the aligned notes
+    pub rhythm_similarity: f32,
+    /// Weighted mix of the two (mostly intervals)
+    pub score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<MatchedRegion>,
+}
+
+/// A library song compared by [`similar_in_dir`]
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryMatch {
+    pub path: String,
+    pub title: String,
+    pub report: SimilarityReport,
+}
+
+// A voice's line, as intervals and onset gaps
+struct Melody {
+    notes: Vec<Note>,
+    intervals: Vec<i32>,
+    gaps: Vec<u32>,
+}
+
+impl Melody {
+    fn of(song: &Song, voice: u8) -> Result<Self> {
+        let notes = top_line(song, voice);
+        if notes.len() < 2 {
+            return Err(MozartError::SimilarityError(format!(
+                "Voice {} of '{}' needs at least two notes to compare",
+                voice, song.metadata.title
+            )));
+        }
+        let intervals = notes.windows(2).map(|w| w[1].pitch as i32 - w[0].pitch as i32).collect();
+        let gaps = notes.windows(2).map(|w| w[1].start_tick - w[0].start_tick).collect();
+        Ok(Melody { notes, intervals, gaps })
+    }
+}
+
+// Cost of aligning two intervals in the edit distance
+fn substitution_cost(a: i32, b: i32) -> f32 {
+    match (a - b).abs() {
+        0 => 0.0,
+        1 => 0.5,
+        _ => 1.0,
+    }
+}
+
+// Score of aligning two intervals in the local alignment (gaps cost 1)
+fn match_score(a: i32, b: i32) -> i32 {
+    match (a - b).abs() {
+        0 => 1,
+        1 => 0,
+        _ => -1,
+    }
+}
+
+// Edit distance between the interval sequences, and the sum of `pair_score`
+// over the index pairs aligned with each other
+//
+// Only two rows of the table are kept: each cell carries the sum along the
+// path a traceback from it would take, so long lines don't need n x m memory.
+fn global_alignment(a: &[i32], b: &[i32], pair_score: impl Fn(usize, usize) -> f32) -> (f32, f32) {
+    let m = b.len();
+    // (cost, sum) per cell
+    let mut prev: Vec<(f32, f32)> = (0..=m).map(|j| (j as f32, 0.0)).collect();
+    let mut row = vec![(0.0f32, 0.0f32); m + 1];
+    for i in 1..=a.len() {
+        row[0] = (i as f32, 0.0);
+        for j in 1..=m {
+            let diagonal = prev[j - 1].0 + substitution_cost(a[i - 1], b[j - 1]);
+            let cost = diagonal.min(prev[j].0 + 1.0).min(row[j - 1].0 + 1.0);
+            // Like a traceback, prefer aligning the two intervals, then skipping one of a's
+            let sum = if cost == diagonal {
+                prev[j - 1].1 + pair_score(i - 1, j - 1)
+            } else if cost == prev[j].0 + 1.0 {
+                prev[j].1
+            } else {
+                row[j - 1].1
+            };
+            row[j] = (cost, sum);
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    prev[m]
+}
+
+// Best-scoring stretch of the interval sequences, as (a range, b range, score)
+//
+// As in the global alignment, two rows are kept, each cell carrying where
+// the stretch ending at it starts.
+fn local_alignment(a: &[i32], b: &[i32]) -> Option<(std::ops::Range<usize>, std::ops::Range<usize>, i32)> {
+    let m = b.len();
+    // (score, start) per cell
+    let mut prev: Vec<(i32, (usize, usize))> = (0..=m).map(|j| (0, (0, j))).collect();
+    let mut row = prev.clone();
+    let mut best = (0, (0, 0), (0, 0));
+    for i in 1..=a.len() {
+        row[0] = (0, (i, 0));
+        for j in 1..=m {
+            let diagonal = prev[j - 1].0 + match_score(a[i - 1], b[j - 1]);
+            let value = diagonal.max(prev[j].0 - 1).max(row[j - 1].0 - 1).max(0);
+            let start = if value == 0 {
+                (i, j)
+            } else if value == diagonal {
+                prev[j - 1].1
+            } else if value == prev[j].0 - 1 {
+                prev[j].1
+            } else {
+                row[j - 1].1
+            };
+            row[j] = (value, start);
+            if value > best.0 {
+                best = (value, start, (i, j));
+            }
+        }
+        std::mem::swap(&mut prev, &mut row);
+    }
+    let (best_score, (start_a, start_b), (end_a, end_b)) = best;
+    if best_score < MIN_REGION_INTERVALS {
+        return None;
+    }
+    Some((start_a..end_a, start_b..end_b, best_score))
+}
+
+fn compare(song_a: &Song, a: &Melody, song_b: &Song, b: &Melody) -> SimilarityReport {
+    let longest = a.intervals.len().max(b.intervals.len()) as f32;
+    let (distance, rhythm) = global_alignment(&a.intervals, &b.intervals, |i, j| {
+        let (x, y) = (a.gaps[i], b.gaps[j]);
+        x.min(y) as f32 / x.max(y) as f32
+    });
+    let interval_similarity = 100.0 * (1.0 - distance / longest).max(0.0);
+    let rhythm_similarity = 100.0 * rhythm / longest;
+
+    let region = local_alignment(&a.intervals, &b.intervals).map(|(range_a, range_b, _)| {
+        // Intervals i..j span notes i..=j
+        let (first_a, last_a) = (&a.notes[range_a.start], &a.notes[range_a.end]);
+        let (first_b, last_b) = (&b.notes[range_b.start], &b.notes[range_b.end]);
+        MatchedRegion {
+            a_start_tick: first_a.start_tick,
+            a_end_tick: last_a.end_tick(),
+            b_start_tick: first_b.start_tick,
+            b_end_tick: last_b.end_tick(),
+            a_start_position: song_a.position_to_mbt(first_a.start_tick).to_string(),
+            b_start_position: song_b.position_to_mbt(first_b.start_tick).to_string(),
+            notes: range_a.len() + 1,
+            transposition: first_b.pitch as i32 - first_a.pitch as i32,
+        }
+    });
+
+    SimilarityReport {
+        interval_similarity,
+        rhythm_similarity,
+        score: INTERVAL_WEIGHT * interval_similarity + (1.0 - INTERVAL_WEIGHT) * rhythm_similarity,
+        region,
+    }
+}
+
+/// Compare the melodies (voice 0) of two songs
+pub fn similarity(song_a: &Song, song_b: &Song) -> Result<SimilarityReport> {
+    voice_similarity(song_a, 0, song_b, 0)
+}
+
+/// Compare a voice of one song with a voice of another
+pub fn voice_similarity(song_a: &Song, voice_a: u8, song_b: &Song, voice_b: u8) -> Result<SimilarityReport> {
+    let report = compare(song_a, &Melody::of(song_a, voice_a)?, song_b, &Melody::of(song_b, voice_b)?);
+    tracing::info!(
+        "Compared '{}' with '{}': {:.0}% similar",
+        song_a.metadata.title,
+        song_b.metadata.title,
+        report.score
+    );
+    Ok(report)
+}
+
+/// Compare a song with every `.mozart.json` song in a directory, most similar
+/// first (songs that can't be read, or whose melody is too short to compare,
+/// are left out)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn similar_in_dir(song: &Song, dir: impl AsRef<std::path::Path>) -> Result<Vec<LibraryMatch>> {
+    let dir = dir.as_ref();
+    let melody = Melody::of(song, 0)?;
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| MozartError::FileError(format!("Failed to read {}: {}", dir.display(), e)))?;
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".mozart.json"))
+        .collect();
+    paths.sort();
+
+    let mut matches = Vec::new();
+    for path in paths {
+        let other = match Song::load(&path) {
+            Ok(other) => other,
+            Err(e) => {
+                tracing::warn!("Skipping {:?}: {}", path, e);
+                continue;
+            }
+        };
+        let Ok(other_melody) = Melody::of(&other, 0) else { continue };
+        let report = compare(song, &melody, &other, &other_melody);
+        matches.push(LibraryMatch { path: path.to_string_lossy().into_owned(), title: other.metadata.title, report });
+    }
+    matches.sort_by(|a, b| b.report.score.total_cmp(&a.report.score));
+    tracing::info!("Compared '{}' with {} songs in {:?}", song.metadata.title, matches.len(), dir);
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::parse_melody;
+    use pretty_assertions::assert_eq;
+
+    fn song(title: &str, melody: &str) -> Song {
+        let mut song = Song::with_title(title);
+        song.add_notes(parse_melody(melody).unwrap());
+        song
+    }
+
+    #[test]
+    fn test_transposed_copy() {
+        let example = song("Example", "C4q D4q E4q F4q G4h E4q C4q");
+        let copy = song("Copy", "F4q G4q A4q Bb4q C5h A4q F4q");
+        let report = similarity(&example, &copy).unwrap();
+        assert_eq!(report.score, 100.0);
+        assert_eq!(
+            report.region,
+            Some(MatchedRegion {
+                a_start_tick: 0,
+                a_end_tick: 3840,
+                b_start_tick: 0,
+                b_end_tick: 3840,
+                a_start_position: "1.1.0".to_string(),
+                b_start_position: "1.1.0".to_string(),
+                notes: 7,
+                transposition: 5,
+            })
+        );
+
+        // Same tune, even eighths: the intervals still match, the rhythm less so
+        let eighths = song("Eighths", "C4e D4e E4e F4e G4e E4e C4e");
+        let report = similarity(&example, &eighths).unwrap();
+        assert_eq!(report.interval_similarity, 100.0);
+        assert!(report.rhythm_similarity < 75.0, "{}", report.rhythm_similarity);
+    }
+
+    #[test]
+    fn test_borrowed_phrase() {
+        // The student's exercise quotes the example's opening a fourth up, after two bars of its own
+        let example = song("Example", "C4q D4q E4q F4q G4h");
+        let exercise = song("Exercise", "A4q A4q C5h B4q B4q D5h F4q G4q A4q Bb4q C5h");
+        let report = similarity(&example, &exercise).unwrap();
+        let region = report.region.unwrap();
+        assert_eq!((region.a_start_tick, region.a_end_tick), (0, 2880));
+        assert_eq!((region.b_start_tick, region.b_end_tick), (3840, 6720));
+        assert_eq!(region.transposition, 5);
+        assert_eq!(region.b_start_position, "3.1.0");
+        assert!(report.score < 70.0, "{}", report.score);
+
+        // Nothing in common
+        let other = song("Other", "C4q C5q C4q C5q C4q C5q");
+        let report = similarity(&example, &other).unwrap();
+        assert_eq!(report.region, None);
+        assert!(report.score < 30.0, "{}", report.score);
+
+        assert!(similarity(&example, &song("Short", "C4q")).is_err());
+    }
+
+    #[test]
+    fn test_long_lines() {
+        let notes = |offset: u8| (0..3000u32).map(move |i| Note::new(60 + offset + (i % 7) as u8, i * 240, 240));
+        let mut long = Song::with_title("Long");
+        long.add_notes(notes(0));
+        let mut copy = Song::with_title("Long copy");
+        copy.add_notes(notes(2));
+        let report = similarity(&long, &copy).unwrap();
+        assert_eq!(report.score, 100.0);
+        assert_eq!(report.region.unwrap().notes, 3000);
+    }
+
+    #[test]
+    fn test_similar_in_dir() {
+        let dir = std::env::temp_dir().join(format!("mozart-similarity-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        song("Copy", "G4q A4q B4q C5q D5h").save(dir.join("copy.mozart.json")).unwrap();
+        song("Other", "C4q C5q C4q C5q").save(dir.join("other.mozart.json")).unwrap();
+        song("Empty", "").save(dir.join("empty.mozart.json")).unwrap();
+        std::fs::write(dir.join("broken.mozart.json"), "{ not a song").unwrap();
+
+        let matches = similar_in_dir(&song("Example", "C4q D4q E4q F4q G4h"), &dir).unwrap();
+        let titles: Vec<&str> = matches.iter().map(|m| m.title.as_str()).collect();
+        assert_eq!(titles, vec!["Copy", "Other"]);
+        assert_eq!(matches[0].report.score, 100.0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,11 +2,25 @@
 //!
 //! Handles the .mozart.json file format
 
+use crate::chance;
+use crate::color::ColorLabel;
 use crate::error::{MozartError, Result};
-use crate::note::Note;
+use crate::note::{parse_melody_with_rhythm_pitch, Note, DEFAULT_RHYTHM_PITCH};
+use crate::pitch::{Pitch, PitchClass};
 use crate::scale::Scale;
-use crate::time::TimeSignature;
+use crate::groove::{apply_groove, GrooveTemplate};
+use crate::pattern::{self, Pattern};
+use crate::instrument::Instrument;
+use crate::register::PitchRange;
+use crate::repeats::RepeatStructure;
+use crate::strum::{strum_chord, Strum, StrumDirection};
+use crate::chord::{self, Chord, ChordSymbol, CHORD_VOICE};
+use crate::voicing::{voice_chord, Voicing};
+use crate::time::{MusicalPosition, TimeSignature};
+use crate::view::ViewState;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
 
 /// Song metadata
@@ -37,6 +51,62 @@ impl Default for SongMetadata {
     }
 }
 
+/// A tempo change at a given tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TempoChange {
+    /// Position in ticks where the new tempo takes effect
+    pub tick: u32,
+    /// Tempo in BPM
+    pub tempo: u16,
+}
+
+/// A voice with its own time signature (polymeter, e.g., an ostinato in 7/8 under a 4/4 melody)
+///
+/// Its measures run from the start of the song in its own meter, so its
+/// barlines drift against the song's.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceMeter {
+    pub voice: u8,
+    pub time_signature: TimeSignature,
+}
+
+/// Voices a song can use, one per MIDI channel
+pub const MAX_VOICES: u8 = 16;
+
+/// Track volume that plays velocities as written
+pub const FULL_VOLUME: u8 = 100;
+
+fn default_volume() -> u8 {
+    FULL_VOLUME
+}
+
+/// A named layer (melody, bassline, harmony): a voice with its own sound and mix
+///
+/// Its notes stay in [`Song::notes`], marked with the track's voice. A voice
+/// without a track plays with the song's instrument at full volume.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Track {
+    pub voice: u8,
+    pub name: String,
+    /// Sound the track plays with, or None for the song's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument: Option<Instrument>,
+    /// 0-127, scaling velocities at playback ([`FULL_VOLUME`] plays them as written)
+    #[serde(default = "default_volume")]
+    pub volume: u8,
+    #[serde(default)]
+    pub mute: bool,
+    /// While any track is soloed, only soloed tracks play
+    #[serde(default)]
+    pub solo: bool,
+}
+
+impl Track {
+    pub fn new(voice: u8, name: impl Into<String>) -> Self {
+        Track { voice, name: name.into(), instrument: None, volume: FULL_VOLUME, mute: false, solo: false }
+    }
+}
+
 /// Song settings (tempo, time signature, key)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SongSettings {
@@ -46,6 +116,31 @@ pub struct SongSettings {
     pub time_signature: TimeSignature,
     /// Key/scale for the song
     pub key: Scale,
+    /// Tempo changes after the start, sorted by tick (initial tempo is `tempo`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tempo_map: Vec<TempoChange>,
+    /// Shape playback velocities by the accent of the beat each note starts on
+    #[serde(default)]
+    pub accent_dynamics: bool,
+    /// Range of the instrument or voice the song is written for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pitch_range: Option<PitchRange>,
+    /// Sound the song plays with
+    #[serde(default)]
+    pub instrument: Instrument,
+    /// Pitch that rhythm hits ("xq") are entered at, if not the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rhythm_pitch: Option<u8>,
+    /// Voices in a meter other than `time_signature`, sorted by voice
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub voice_meters: Vec<VoiceMeter>,
+    /// Named voices with their own instrument and mix, sorted by voice
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<Track>,
+    /// Export each voice on its own MIDI channel (voice 9 on the General MIDI
+    /// drum channel) instead of all on channel 1
+    #[serde(default)]
+    pub channel_per_voice: bool,
 }
 
 impl Default for SongSettings {
@@ -54,10 +149,121 @@ impl Default for SongSettings {
             tempo: 120,
             time_signature: TimeSignature::common(),
             key: Scale::c_major(),
+            tempo_map: Vec::new(),
+            accent_dynamics: false,
+            pitch_range: None,
+            instrument: Instrument::default(),
+            rhythm_pitch: None,
+            voice_meters: Vec::new(),
+            tracks: Vec::new(),
+            channel_per_voice: false,
         }
     }
 }
 
+/// Settings a new song starts with, saved in the user's preferences
+///
+/// Kept as text so preferences stay readable and older files keep loading
+/// when formats change; [`Song::new_with_defaults`] parses them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SongDefaults {
+    pub tempo: u16,
+    /// e.g., "6/8"
+    pub time_signature: String,
+    /// e.g., "G major"
+    pub key: String,
+    /// Pitch range preset (e.g., "Violin"); empty for none
+    pub instrument: String,
+    /// Beats of pickup: the song opens on the last beats of its first measure
+    pub pickup_beats: u8,
+}
+
+impl Default for SongDefaults {
+    fn default() -> Self {
+        SongDefaults {
+            tempo: 120,
+            time_signature: "4/4".to_string(),
+            key: "C major".to_string(),
+            instrument: String::new(),
+            pickup_beats: 0,
+        }
+    }
+}
+
+/// Marker placed where a pickup starts in a song made from [`SongDefaults`]
+pub const PICKUP_MARKER: &str = "Pickup";
+
+/// A note with its index in `song.notes`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedNote {
+    pub index: usize,
+    #[serde(flatten)]
+    pub note: Note,
+}
+
+/// A named navigation point in the song
+///
+/// A marker also starts a section, which runs to the next marker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Marker {
+    pub name: String,
+    pub tick: u32,
+    /// Color label of the section the marker starts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorLabel>,
+}
+
+/// A lyric syllable sung from a tick
+///
+/// Words sung over several notes keep a trailing hyphen on every syllable
+/// but the last ("Hap-", "py").
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lyric {
+    pub tick: u32,
+    pub text: String,
+    /// First syllable of a lyric line
+    #[serde(default)]
+    pub new_line: bool,
+}
+
+/// A comment on the music ("breathe here"), at a tick or over a range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub tick: u32,
+    /// End of the range it covers; none for a single point
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_tick: Option<u32>,
+    pub text: String,
+}
+
+/// Summary of a song for display (e.g., in the transport)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SongInfo {
+    pub title: String,
+    pub tempo: u16,
+    pub time_signature: String,
+    pub key: String,
+    pub note_count: usize,
+    pub measure_count: u32,
+    pub duration_ticks: u32,
+    /// Seconds until the last note ends, honoring the tempo map
+    pub duration_seconds: f64,
+    /// Seconds until the final barline, honoring the tempo map
+    pub total_seconds: f64,
+    /// `duration_seconds` formatted as m:ss
+    pub duration_display: String,
+    /// `total_seconds` formatted as m:ss
+    pub total_display: String,
+    /// Name of the lowest note (e.g., "C4"; None without notes)
+    pub lowest_pitch: Option<String>,
+    pub highest_pitch: Option<String>,
+    /// Notes per measure, on average over `measure_count`
+    pub notes_per_measure: f32,
+    /// Notes whose pitch class is not in the key
+    pub out_of_key_count: usize,
+}
+
 /// A complete song with metadata, settings, and notes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Song {
@@ -69,6 +275,33 @@ pub struct Song {
     pub settings: SongSettings,
     /// The notes in the melody
     pub notes: Vec<Note>,
+    /// Navigation markers, sorted by tick
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub markers: Vec<Marker>,
+    /// Playback-only chord strums, sorted by tick
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strums: Vec<Strum>,
+    /// Chord track, sorted by tick (comped in playback, see [`crate::chord`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chords: Vec<ChordSymbol>,
+    /// Lyric syllables, sorted by tick
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lyrics: Vec<Lyric>,
+    /// Comments on the music, sorted by tick (ignored by playback)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// Repeat barlines, endings, and jumps, in written form (see [`Song::flatten_repeats`])
+    #[serde(default, skip_serializing_if = "RepeatStructure::is_empty")]
+    pub repeats: RepeatStructure,
+    /// Named clips of notes (see [`crate::pattern`])
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<Pattern>,
+    /// Pattern names in the order they play from the start of the song
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pattern_chain: Vec<String>,
+    /// Editor view state, restored on load (ignored by core logic)
+    #[serde(default, skip_serializing_if = "ViewState::is_default")]
+    pub view: ViewState,
 }
 
 impl Song {
@@ -80,6 +313,15 @@ impl Song {
             metadata: SongMetadata::default(),
             settings: SongSettings::default(),
             notes: Vec::new(),
+            markers: Vec::new(),
+            strums: Vec::new(),
+            chords: Vec::new(),
+            lyrics: Vec::new(),
+            annotations: Vec::new(),
+            repeats: RepeatStructure::default(),
+            patterns: Vec::new(),
+            pattern_chain: Vec::new(),
+            view: ViewState::default(),
         }
     }
 
@@ -90,6 +332,34 @@ impl Song {
         song
     }
 
+    /// Create a song from the user's new-song defaults
+    ///
+    /// With a pickup, the first measure is the pickup bar, and a
+    /// [`PICKUP_MARKER`] shows the beat the music starts on.
+    pub fn new_with_defaults(defaults: &SongDefaults) -> Result<Self> {
+        let time_signature = TimeSignature::parse(&defaults.time_signature)?;
+        let key = Scale::parse(&defaults.key)?;
+        let range =
+            if defaults.instrument.is_empty() { None } else { Some(PitchRange::preset(&defaults.instrument)?) };
+        if defaults.pickup_beats >= time_signature.numerator {
+            return Err(MozartError::InvalidPosition(format!(
+                "A pickup of {} beats is a full measure of {}",
+                defaults.pickup_beats, time_signature
+            )));
+        }
+
+        let mut song = Song::new();
+        song.set_tempo(defaults.tempo);
+        song.set_key(key);
+        song.set_pitch_range(range);
+        if defaults.pickup_beats > 0 {
+            let start = time_signature.ticks_per_beat() * (time_signature.numerator - defaults.pickup_beats) as u32;
+            song.add_marker(PICKUP_MARKER, start);
+        }
+        song.set_time_signature(time_signature);
+        Ok(song)
+    }
+
     /// Set the tempo
     pub fn set_tempo(&mut self, tempo: u16) {
         tracing::debug!("Setting tempo to {} BPM", tempo);
@@ -102,12 +372,205 @@ impl Song {
         self.settings.time_signature = ts;
     }
 
+    /// Time signature a voice is written in: its own meter, or the song's
+    pub fn voice_time_signature(&self, voice: u8) -> &TimeSignature {
+        self.settings
+            .voice_meters
+            .iter()
+            .find(|m| m.voice == voice)
+            .map_or(&self.settings.time_signature, |m| &m.time_signature)
+    }
+
+    /// Give a voice its own time signature, or None to follow the song's
+    ///
+    /// A meter is kept even when it matches the song's, so the voice stays in
+    /// it if the song's meter changes later.
+    pub fn set_voice_time_signature(&mut self, voice: u8, ts: Option<TimeSignature>) {
+        tracing::debug!("Setting the time signature of voice {} to {:?}", voice, ts.as_ref().map(|ts| ts.to_string()));
+        let meters = &mut self.settings.voice_meters;
+        meters.retain(|m| m.voice != voice);
+        if let Some(time_signature) = ts {
+            meters.push(VoiceMeter { voice, time_signature });
+            meters.sort_by_key(|m| m.voice);
+        }
+        self.update_modified();
+    }
+
+    /// A voice's track, if it has one
+    pub fn track(&self, voice: u8) -> Option<&Track> {
+        self.settings.tracks.iter().find(|t| t.voice == voice)
+    }
+
+    /// The notes of a voice's track, in order
+    pub fn track_notes(&self, voice: u8) -> Vec<&Note> {
+        self.notes.iter().filter(|n| n.voice == voice).collect()
+    }
+
+    /// Add an empty track on the first voice with neither a track nor notes
+    /// (nor the chord track's comping); returns its voice
+    ///
+    /// Each voice then exports on its own MIDI channel, so the tracks keep
+    /// their instruments in other players.
+    pub fn add_track(&mut self, name: &str) -> Result<u8> {
+        let voice = (0..MAX_VOICES)
+            .filter(|&v| v != CHORD_VOICE || self.chords.is_empty())
+            .find(|&v| self.track(v).is_none() && !self.notes.iter().any(|n| n.voice == v))
+            .ok_or_else(|| MozartError::TrackError(format!("A song has at most {} tracks", MAX_VOICES)))?;
+        let name = match name.trim() {
+            "" => format!("Track {}", voice + 1),
+            name => name.to_string(),
+        };
+        tracing::debug!("Adding track \"{}\" on voice {}", name, voice);
+        self.settings.tracks.push(Track::new(voice, name));
+        self.settings.tracks.sort_by_key(|t| t.voice);
+        self.settings.channel_per_voice = true;
+        self.update_modified();
+        Ok(voice)
+    }
+
+    /// Remove a voice's track and its notes; returns how many notes went
+    pub fn remove_track(&mut self, voice: u8) -> Result<usize> {
+        let before = self.notes.len();
+        if self.track(voice).is_none() && !self.notes.iter().any(|n| n.voice == voice) {
+            return Err(MozartError::TrackError(format!("Voice {} has no track", voice)));
+        }
+        self.settings.tracks.retain(|t| t.voice != voice);
+        self.settings.voice_meters.retain(|m| m.voice != voice);
+        self.notes.retain(|n| n.voice != voice);
+        self.update_modified();
+        tracing::debug!("Removed the track on voice {} ({} notes)", voice, before - self.notes.len());
+        Ok(before - self.notes.len())
+    }
+
+    // A voice's track, made on first use for a voice that only has notes
+    fn track_mut(&mut self, voice: u8) -> Result<&mut Track> {
+        if voice >= MAX_VOICES {
+            return Err(MozartError::TrackError(format!("Voice {} is past the last of {}", voice, MAX_VOICES)));
+        }
+        if self.track(voice).is_none() {
+            self.settings.tracks.push(Track::new(voice, format!("Voice {}", voice + 1)));
+            self.settings.tracks.sort_by_key(|t| t.voice);
+        }
+        self.update_modified();
+        Ok(self.settings.tracks.iter_mut().find(|t| t.voice == voice).expect("track was just added"))
+    }
+
+    pub fn rename_track(&mut self, voice: u8, name: &str) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(MozartError::TrackError("A track needs a name".to_string()));
+        }
+        self.track_mut(voice)?.name = name.trim().to_string();
+        Ok(())
+    }
+
+    /// Give a track its own instrument, or None to play with the song's
+    pub fn set_track_instrument(&mut self, voice: u8, instrument: Option<Instrument>) -> Result<()> {
+        tracing::debug!("Setting the instrument of voice {} to {:?}", voice, instrument);
+        self.track_mut(voice)?.instrument = instrument;
+        Ok(())
+    }
+
+    /// Set a track's volume (0-127; [`FULL_VOLUME`] plays velocities as written)
+    pub fn set_track_volume(&mut self, voice: u8, volume: u8) -> Result<()> {
+        self.track_mut(voice)?.volume = volume.min(127);
+        Ok(())
+    }
+
+    pub fn mute_track(&mut self, voice: u8, mute: bool) -> Result<()> {
+        self.track_mut(voice)?.mute = mute;
+        Ok(())
+    }
+
+    pub fn solo_track(&mut self, voice: u8, solo: bool) -> Result<()> {
+        self.track_mut(voice)?.solo = solo;
+        Ok(())
+    }
+
+    /// Instrument a voice plays with: its track's, or the song's
+    pub fn voice_instrument(&self, voice: u8) -> Instrument {
+        self.track(voice).and_then(|t| t.instrument).unwrap_or(self.settings.instrument)
+    }
+
+    /// Whether a voice is heard, given the tracks' mute and solo
+    pub fn is_voice_audible(&self, voice: u8) -> bool {
+        let track = self.track(voice);
+        if self.settings.tracks.iter().any(|t| t.solo) {
+            track.is_some_and(|t| t.solo)
+        } else {
+            !track.is_some_and(|t| t.mute)
+        }
+    }
+
+    /// Add a tempo change at a tick (replaces any existing change at that tick)
+    pub fn add_tempo_change(&mut self, tick: u32, tempo: u16) {
+        let tempo = tempo.clamp(20, 300);
+        tracing::debug!("Adding tempo change to {} BPM at tick {}", tempo, tick);
+
+        let map = &mut self.settings.tempo_map;
+        map.retain(|c| c.tick != tick);
+        map.push(TempoChange { tick, tempo });
+        map.sort_by_key(|c| c.tick);
+        self.update_modified();
+    }
+
+    /// Remove all tempo changes, keeping only the initial tempo
+    pub fn clear_tempo_changes(&mut self) {
+        self.settings.tempo_map.clear();
+        self.update_modified();
+    }
+
+    /// Get the tempo in effect at a given tick
+    pub fn tempo_at_tick(&self, tick: u32) -> u16 {
+        self.settings
+            .tempo_map
+            .iter()
+            .take_while(|c| c.tick <= tick)
+            .last()
+            .map(|c| c.tempo)
+            .unwrap_or(self.settings.tempo)
+    }
+
     /// Set the key
     pub fn set_key(&mut self, key: Scale) {
         tracing::debug!("Setting key to {}", key);
         self.settings.key = key;
     }
 
+    /// Set (or clear) the instrument range notes are checked against
+    pub fn set_pitch_range(&mut self, range: Option<PitchRange>) {
+        match &range {
+            Some(range) => tracing::debug!("Setting pitch range to {}", range),
+            None => tracing::debug!("Clearing pitch range"),
+        }
+        self.settings.pitch_range = range;
+    }
+
+    /// Set the instrument the song plays with
+    pub fn set_instrument(&mut self, instrument: Instrument) {
+        tracing::debug!("Setting instrument to {}", instrument);
+        self.settings.instrument = instrument;
+    }
+
+    /// Pitch rhythm hits ("xq") are entered at
+    pub fn rhythm_pitch(&self) -> u8 {
+        self.settings.rhythm_pitch.unwrap_or(DEFAULT_RHYTHM_PITCH)
+    }
+
+    /// Set the pitch rhythm hits are entered at (None for the default); notes already entered keep theirs
+    pub fn set_rhythm_pitch(&mut self, pitch: Option<u8>) -> Result<()> {
+        if let Some(pitch) = pitch.filter(|&p| p > 127) {
+            return Err(MozartError::InvalidPitch(format!("MIDI note {} out of range (0-127)", pitch)));
+        }
+        tracing::debug!("Setting rhythm pitch to {:?}", pitch);
+        self.settings.rhythm_pitch = pitch.filter(|&p| p != DEFAULT_RHYTHM_PITCH);
+        Ok(())
+    }
+
+    /// Parse a melody in text notation, with rhythm hits at the song's rhythm pitch
+    pub fn parse_melody(&self, melody: &str) -> Result<Vec<Note>> {
+        parse_melody_with_rhythm_pitch(melody, self.rhythm_pitch())
+    }
+
     /// Add a note
     pub fn add_note(&mut self, note: Note) {
         tracing::trace!("Adding note: {}", note);
@@ -135,6 +598,97 @@ impl Song {
         }
     }
 
+    /// Change the pitch of the note at index
+    pub fn set_note_pitch(&mut self, index: usize, pitch: u8) -> Result<()> {
+        if pitch > 127 {
+            return Err(MozartError::InvalidPitch(format!(
+                "MIDI note {} out of range (0-127)",
+                pitch
+            )));
+        }
+        let note = self.notes.get_mut(index).ok_or_else(|| {
+            MozartError::InvalidPitch(format!("No note at index {}", index))
+        })?;
+
+        tracing::trace!("Setting pitch of note {} to {}", index, pitch);
+        note.pitch = pitch;
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Replace the note at index; returns its new index after re-sorting
+    pub fn update_note(&mut self, index: usize, note: Note) -> Result<usize> {
+        if note.pitch > 127 {
+            return Err(MozartError::InvalidPitch(format!(
+                "MIDI note {} out of range (0-127)",
+                note.pitch
+            )));
+        }
+        let slot = self.notes.get_mut(index).ok_or_else(|| {
+            MozartError::InvalidEdit(format!("No note at index {}", index))
+        })?;
+
+        tracing::trace!("Updating note {} to {}", index, note);
+        *slot = note.clone();
+        self.sort_notes();
+        self.update_modified();
+        Ok(self.notes.iter().position(|n| *n == note).unwrap_or(index))
+    }
+
+    /// Shift notes in time and pitch together
+    ///
+    /// Nothing moves if any note would land before tick 0 or outside 0-127.
+    pub fn move_notes(&mut self, indices: &[usize], delta_ticks: i64, delta_pitch: i16) -> Result<()> {
+        for &i in indices {
+            let note = self.notes.get(i).ok_or_else(|| {
+                MozartError::InvalidEdit(format!("No note at index {}", i))
+            })?;
+            if note.start_tick as i64 + delta_ticks < 0 {
+                return Err(MozartError::InvalidEdit(format!("Note {} would move before the start", i)));
+            }
+            if note.end_tick() as i64 + delta_ticks > u32::MAX as i64 {
+                return Err(MozartError::InvalidEdit(format!("Note {} would move past the last tick", i)));
+            }
+            if !(0..=127).contains(&(note.pitch as i16 + delta_pitch)) {
+                return Err(MozartError::InvalidPitch(format!(
+                    "Note {} would move out of range (0-127)",
+                    i
+                )));
+            }
+        }
+
+        tracing::debug!("Moving {} notes by {} ticks, {} semitones", indices.len(), delta_ticks, delta_pitch);
+        for (i, note) in self.notes.iter_mut().enumerate() {
+            if indices.contains(&i) {
+                note.start_tick = (note.start_tick as i64 + delta_ticks) as u32;
+                note.pitch = (note.pitch as i16 + delta_pitch) as u8;
+            }
+        }
+        self.sort_notes();
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Set (or with `None`, clear) the color label of notes; returns how many changed
+    pub fn set_note_color(&mut self, indices: &[usize], color: Option<ColorLabel>) -> Result<usize> {
+        if let Some(&i) = indices.iter().find(|&&i| i >= self.notes.len()) {
+            return Err(MozartError::InvalidEdit(format!("No note at index {}", i)));
+        }
+
+        let mut changed = 0;
+        for &i in indices {
+            if self.notes[i].color != color {
+                self.notes[i].color = color;
+                changed += 1;
+            }
+        }
+        tracing::debug!("Colored {} notes {}", changed, color.map_or("none", |c| c.name()));
+        if changed > 0 {
+            self.update_modified();
+        }
+        Ok(changed)
+    }
+
     /// Clear all notes
     pub fn clear_notes(&mut self) {
         tracing::debug!("Clearing all notes");
@@ -142,13 +696,964 @@ impl Song {
         self.update_modified();
     }
 
+    /// Delete `count` measures from `first` (1-indexed), pulling later music back
+    ///
+    /// Notes, markers, strums, chord symbols, lyrics, annotations, and repeat marks in the
+    /// deleted measures are removed, and notes held into them (and annotated
+    /// ranges running into them) are cut off where they begin. The
+    /// tempo in effect at the end of the deleted span carries on after it.
+    /// Returns the number of notes removed.
+    pub fn delete_measures(&mut self, first: u32, count: u32) -> Result<usize> {
+        if count == 0 {
+            return Err(MozartError::InvalidEdit("No measures to delete".to_string()));
+        }
+        let (start, end) = self.measures_range(first, count)?;
+        let length = end - start;
+        tracing::debug!("Deleting measures {}-{} (ticks {}-{})", first, first + count - 1, start, end);
+
+        let before = self.notes.len();
+        self.notes.retain(|n| n.start_tick < start || n.start_tick >= end);
+        let removed = before - self.notes.len();
+        for note in &mut self.notes {
+            if note.start_tick >= end {
+                note.start_tick -= length;
+            } else if note.end_tick() > start {
+                note.duration_ticks = start - note.start_tick;
+            }
+        }
+
+        let shift = |tick: &mut u32| {
+            if *tick >= end {
+                *tick -= length;
+            }
+        };
+        let carried = self.settings.tempo_map.iter().rev().find(|c| c.tick >= start && c.tick < end).map(|c| c.tempo);
+        self.settings.tempo_map.retain(|c| c.tick < start || c.tick >= end);
+        self.settings.tempo_map.iter_mut().for_each(|c| shift(&mut c.tick));
+        if let Some(tempo) = carried.filter(|_| !self.settings.tempo_map.iter().any(|c| c.tick == start)) {
+            let at = self.settings.tempo_map.partition_point(|c| c.tick < start);
+            self.settings.tempo_map.insert(at, TempoChange { tick: start, tempo });
+        }
+        self.markers.retain(|m| m.tick < start || m.tick >= end);
+        self.markers.iter_mut().for_each(|m| shift(&mut m.tick));
+        self.strums.retain(|s| s.tick < start || s.tick >= end);
+        self.strums.iter_mut().for_each(|s| shift(&mut s.tick));
+        self.chords.retain(|c| c.tick < start || c.tick >= end);
+        self.chords.iter_mut().for_each(|c| shift(&mut c.tick));
+        self.lyrics.retain(|l| l.tick < start || l.tick >= end);
+        self.lyrics.iter_mut().for_each(|l| shift(&mut l.tick));
+        self.annotations.retain(|a| a.tick < start || a.tick >= end);
+        for annotation in &mut self.annotations {
+            shift(&mut annotation.tick);
+            if let Some(end_tick) = &mut annotation.end_tick {
+                *end_tick = if *end_tick >= end { *end_tick - length } else { (*end_tick).min(start) };
+            }
+        }
+        self.repeats.delete_measures(first, count);
+
+        self.update_modified();
+        Ok(removed)
+    }
+
+    /// Split the note at index into two notes at `at_tick`
+    ///
+    /// Both halves keep the original pitch, velocity, voice, and pan. Returns
+    /// the index of the second half.
+    pub fn split_note(&mut self, index: usize, at_tick: u32) -> Result<usize> {
+        let note = self.notes.get(index).ok_or_else(|| {
+            MozartError::InvalidEdit(format!("No note at index {}", index))
+        })?;
+        if at_tick <= note.start_tick || at_tick >= note.end_tick() {
+            return Err(MozartError::InvalidPosition(format!(
+                "Tick {} is not inside note {} ({}-{})",
+                at_tick, index, note.start_tick, note.end_tick()
+            )));
+        }
+
+        let mut second = note.clone();
+        second.start_tick = at_tick;
+        second.duration_ticks = note.end_tick() - at_tick;
+        self.notes[index].duration_ticks = at_tick - self.notes[index].start_tick;
+
+        tracing::trace!("Splitting note {} at tick {}", index, at_tick);
+        let position = self.notes.partition_point(|n| n.start_tick <= at_tick);
+        self.notes.insert(position, second);
+        self.update_modified();
+        Ok(position)
+    }
+
+    /// Merge adjacent same-pitch notes into one
+    ///
+    /// The notes must share a pitch and each must start no later than the
+    /// previous one ends. The earliest note is extended to cover them all and
+    /// keeps its velocity, voice, and pan. Returns the merged note's index.
+    pub fn join_notes(&mut self, indices: &[usize]) -> Result<usize> {
+        let mut order: Vec<usize> = indices.to_vec();
+        order.sort_unstable();
+        order.dedup();
+        if order.len() < 2 {
+            return Err(MozartError::InvalidEdit("Select at least two notes to join".to_string()));
+        }
+        if let Some(&bad) = order.iter().find(|&&i| i >= self.notes.len()) {
+            return Err(MozartError::InvalidEdit(format!("No note at index {}", bad)));
+        }
+        order.sort_by_key(|&i| (self.notes[i].start_tick, i));
+
+        let first = order[0];
+        let pitch = self.notes[first].pitch;
+        let mut end = self.notes[first].end_tick();
+        for &i in &order[1..] {
+            let note = &self.notes[i];
+            if note.pitch != pitch {
+                return Err(MozartError::InvalidEdit(
+                    "Only notes with the same pitch can be joined".to_string(),
+                ));
+            }
+            if note.start_tick > end {
+                return Err(MozartError::InvalidEdit(format!(
+                    "Notes are not adjacent (gap at tick {})",
+                    end
+                )));
+            }
+            end = end.max(note.end_tick());
+        }
+
+        tracing::trace!("Joining {} notes into note {}", order.len(), first);
+        self.notes[first].duration_ticks = end - self.notes[first].start_tick;
+
+        let mut removed = order[1..].to_vec();
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        for &i in &removed {
+            self.notes.remove(i);
+        }
+        self.update_modified();
+        Ok(first - removed.iter().filter(|&&i| i < first).count())
+    }
+
+    /// Extend each selected note to reach the next selected note's start
+    ///
+    /// `overlap_ticks` lets each note run slightly past the next start. Notes
+    /// sharing a start (chords) move together; the last note is unchanged.
+    /// Returns the number of notes changed.
+    pub fn make_legato(&mut self, indices: &[usize], overlap_ticks: u32) -> usize {
+        let targets = self.next_selected_starts(indices);
+        let mut changed = 0;
+        for (i, next_start) in targets {
+            let note = &mut self.notes[i];
+            // The overlap stops at the last tick
+            let duration = (next_start - note.start_tick).saturating_add(overlap_ticks).min(u32::MAX - note.start_tick);
+            if note.duration_ticks != duration {
+                note.duration_ticks = duration;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            tracing::debug!("Made {} notes legato", changed);
+            self.update_modified();
+        }
+        changed
+    }
+
+    /// Shorten selected notes so each ends `gap_ticks` before the next selected start
+    ///
+    /// Notes already ending earlier are left alone, and no note becomes shorter
+    /// than one tick. Returns the number of notes changed.
+    pub fn add_gap(&mut self, indices: &[usize], gap_ticks: u32) -> usize {
+        let targets = self.next_selected_starts(indices);
+        let mut changed = 0;
+        for (i, next_start) in targets {
+            let note = &mut self.notes[i];
+            let max_duration = (next_start - note.start_tick).saturating_sub(gap_ticks).max(1);
+            if note.duration_ticks > max_duration {
+                note.duration_ticks = max_duration;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            tracing::debug!("Added gaps after {} notes", changed);
+            self.update_modified();
+        }
+        changed
+    }
+
+    /// Snap selected note starts to the nearest multiple of `grid_ticks`
+    ///
+    /// With `durations`, note lengths are rounded to the grid too (never below
+    /// one grid step). Returns the number of notes changed.
+    pub fn quantize(&mut self, indices: &[usize], grid_ticks: u32, durations: bool) -> Result<usize> {
+        if grid_ticks == 0 {
+            return Err(MozartError::InvalidDuration("Quantize grid must be at least one tick".to_string()));
+        }
+        let starts: Vec<u32> = self.notes.iter().map(|n| snap_to_grid(n.start_tick, grid_ticks)).collect();
+        Ok(self.snap_notes(indices, &starts, grid_ticks, durations))
+    }
+
+    /// Quantize selected note starts, pulled toward the accented beats of their voice's meter
+    ///
+    /// See [`TimeSignature::snap_to_accents`]: a gravity of 0 is the same as
+    /// [`Song::quantize`], and around 1-3 notes near a strong or medium beat
+    /// land on it rather than on the closest grid point.
+    pub fn quantize_to_accents(&mut self, indices: &[usize], grid_ticks: u32, gravity: f32, durations: bool) -> Result<usize> {
+        if grid_ticks == 0 {
+            return Err(MozartError::InvalidDuration("Quantize grid must be at least one tick".to_string()));
+        }
+        if !gravity.is_finite() || gravity < 0.0 {
+            return Err(MozartError::InvalidEdit(format!("Accent gravity must be 0 or more, not {}", gravity)));
+        }
+        let starts: Vec<u32> = self
+            .notes
+            .iter()
+            .map(|n| self.voice_time_signature(n.voice).snap_to_accents(n.start_tick, grid_ticks, gravity))
+            .collect();
+        Ok(self.snap_notes(indices, &starts, grid_ticks, durations))
+    }
+
+    // Move selected notes to their snapped starts and optionally round their lengths to the grid
+    fn snap_notes(&mut self, indices: &[usize], starts: &[u32], grid_ticks: u32, durations: bool) -> usize {
+        let selected: HashSet<usize> = indices.iter().copied().collect();
+        let mut changed = 0;
+        for (i, note) in self.notes.iter_mut().enumerate() {
+            if !selected.contains(&i) {
+                continue;
+            }
+            let start = starts[i];
+            let duration = if durations { snap_to_grid(note.duration_ticks, grid_ticks).max(grid_ticks) } else { note.duration_ticks };
+            if (start, duration) != (note.start_tick, note.duration_ticks) {
+                note.start_tick = start;
+                note.duration_ticks = duration;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            tracing::debug!("Quantized {} notes to a {}-tick grid", changed, grid_ticks);
+            self.sort_notes();
+            self.update_modified();
+        }
+        changed
+    }
+
+    /// Pair each valid selected index with the next later start among the selection
+    fn next_selected_starts(&self, indices: &[usize]) -> Vec<(usize, u32)> {
+        let mut starts: Vec<u32> = indices
+            .iter()
+            .filter_map(|&i| self.notes.get(i).map(|n| n.start_tick))
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+
+        let mut targets: Vec<(usize, u32)> = indices
+            .iter()
+            .filter(|&&i| i < self.notes.len())
+            .filter_map(|&i| {
+                let start = self.notes[i].start_tick;
+                let next = starts[starts.partition_point(|&s| s <= start)..].first()?;
+                Some((i, *next))
+            })
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        targets
+    }
+
+    /// Strum the chords among the selected notes by moving their start times
+    ///
+    /// Selected notes sharing a start tick form a chord. Returns the number of
+    /// chords strummed.
+    pub fn strum_chords(&mut self, indices: &[usize], spread_ticks: u32, direction: StrumDirection) -> usize {
+        let selected: HashSet<usize> = indices.iter().copied().collect();
+        let mut chords: BTreeMap<u32, Vec<&mut Note>> = BTreeMap::new();
+        for (i, note) in self.notes.iter_mut().enumerate() {
+            if selected.contains(&i) {
+                chords.entry(note.start_tick).or_default().push(note);
+            }
+        }
+
+        let mut count = 0;
+        for chord in chords.values_mut().filter(|c| c.len() > 1) {
+            strum_chord(chord, spread_ticks, direction);
+            count += 1;
+        }
+
+        if count > 0 {
+            tracing::debug!("Strummed {} chords", count);
+            self.sort_notes();
+            self.update_modified();
+        }
+        count
+    }
+
+    /// Revoice the chords among the selected notes (see [`Voicing`])
+    ///
+    /// Selected notes sharing a start tick form a chord. Returns the number of
+    /// chords changed; chords that would leave the MIDI range are left alone.
+    pub fn voice_chords(&mut self, indices: &[usize], voicing: Voicing) -> usize {
+        let selected: HashSet<usize> = indices.iter().copied().collect();
+        let mut chords: BTreeMap<u32, Vec<&mut Note>> = BTreeMap::new();
+        for (i, note) in self.notes.iter_mut().enumerate() {
+            if selected.contains(&i) {
+                chords.entry(note.start_tick).or_default().push(note);
+            }
+        }
+
+        let mut count = 0;
+        for chord in chords.values_mut() {
+            if voice_chord(chord, voicing) {
+                count += 1;
+            }
+        }
+
+        if count > 0 {
+            tracing::debug!("{} on {} chords", voicing, count);
+            self.update_modified();
+        }
+        count
+    }
+
+    /// Impose a groove template on the selected notes (see [`apply_groove`])
+    ///
+    /// Returns the number of notes changed.
+    pub fn apply_groove(&mut self, indices: &[usize], template: &GrooveTemplate) -> usize {
+        let selected: HashSet<usize> = indices.iter().copied().collect();
+        let mut notes: Vec<&mut Note> = self
+            .notes
+            .iter_mut()
+            .enumerate()
+            .filter(|(i, _)| selected.contains(i))
+            .map(|(_, n)| n)
+            .collect();
+        let changed = apply_groove(template, &mut notes);
+
+        if changed > 0 {
+            tracing::debug!("Applied a groove to {} notes", changed);
+            self.sort_notes();
+            self.update_modified();
+        }
+        changed
+    }
+
+    /// Strum the chord at a tick during playback only (replaces any existing strum there)
+    pub fn set_chord_strum(&mut self, tick: u32, spread_ticks: u32, direction: StrumDirection) {
+        self.strums.retain(|s| s.tick != tick);
+        self.strums.push(Strum { tick, spread_ticks, direction });
+        self.strums.sort_by_key(|s| s.tick);
+        self.update_modified();
+    }
+
+    /// Remove the playback strum at a tick
+    pub fn remove_chord_strum(&mut self, tick: u32) -> Option<Strum> {
+        let index = self.strums.iter().position(|s| s.tick == tick)?;
+        self.update_modified();
+        Some(self.strums.remove(index))
+    }
+
+    /// Put a chord symbol on the chord track (replaces any chord at that tick)
+    pub fn set_chord(&mut self, tick: u32, symbol: &str) -> Result<Chord> {
+        let chord = Chord::parse(symbol)?;
+        tracing::debug!("Setting chord {} at tick {}", symbol.trim(), tick);
+        self.chords.retain(|c| c.tick != tick);
+        let at = self.chords.partition_point(|c| c.tick < tick);
+        self.chords.insert(at, ChordSymbol { tick, symbol: symbol.trim().to_string() });
+        self.update_modified();
+        Ok(chord)
+    }
+
+    /// Remove the chord symbol at a tick
+    pub fn remove_chord(&mut self, tick: u32) -> Option<ChordSymbol> {
+        let index = self.chords.iter().position(|c| c.tick == tick)?;
+        self.update_modified();
+        Some(self.chords.remove(index))
+    }
+
+    /// The pattern with a name
+    pub fn pattern(&self, name: &str) -> Option<&Pattern> {
+        self.patterns.iter().find(|p| p.name == name)
+    }
+
+    fn check_new_pattern_name<'a>(&self, name: &'a str) -> Result<&'a str> {
+        let name = pattern::validate_pattern_name(name)?;
+        if self.pattern(name).is_some() {
+            return Err(MozartError::InvalidEdit(format!("There is already a pattern named {}", name)));
+        }
+        Ok(name)
+    }
+
+    /// Cut `count` measures from `first_measure` (1-indexed) into a new pattern
+    ///
+    /// The notes starting in those measures move into the pattern, leaving
+    /// the measures empty, so the chain can play them back in any order.
+    pub fn create_pattern(&mut self, name: &str, first_measure: u32, count: u32) -> Result<&Pattern> {
+        let name = self.check_new_pattern_name(name)?.to_string();
+        let (start, end) = self.measures_range(first_measure, count)?;
+        let (cut, kept): (Vec<Note>, Vec<Note>) =
+            self.notes.drain(..).partition(|n| (start..end).contains(&n.start_tick));
+        self.notes = kept;
+        let notes = cut.into_iter().map(|note| Note { start_tick: note.start_tick - start, ..note }).collect::<Vec<_>>();
+        tracing::info!("Created pattern {} from {} notes of measures {}-{}", name, notes.len(), first_measure, first_measure + count.max(1) - 1);
+        self.patterns.push(Pattern { name, length_ticks: end - start, notes });
+        self.update_modified();
+        Ok(self.patterns.last().unwrap())
+    }
+
+    /// Copy a pattern under a new name, placed after it (to vary one use of it)
+    pub fn clone_pattern(&mut self, name: &str, new_name: &str) -> Result<&Pattern> {
+        let new_name = self.check_new_pattern_name(new_name)?.to_string();
+        let index = self
+            .patterns
+            .iter()
+            .position(|p| p.name == name)
+            .ok_or_else(|| MozartError::InvalidEdit(format!("Unknown pattern: {}", name)))?;
+        let copy = Pattern { name: new_name, ..self.patterns[index].clone() };
+        self.patterns.insert(index + 1, copy);
+        self.update_modified();
+        Ok(&self.patterns[index + 1])
+    }
+
+    /// Move a pattern to another place in the list of patterns
+    pub fn move_pattern(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.patterns.len() || to >= self.patterns.len() {
+            return Err(MozartError::InvalidEdit(format!("No pattern at {} or {} ({} patterns)", from, to, self.patterns.len())));
+        }
+        let pattern = self.patterns.remove(from);
+        self.patterns.insert(to, pattern);
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Delete a pattern, and its places in the chain
+    pub fn remove_pattern(&mut self, name: &str) -> Option<Pattern> {
+        let index = self.patterns.iter().position(|p| p.name == name)?;
+        self.pattern_chain.retain(|n| n != name);
+        self.update_modified();
+        Some(self.patterns.remove(index))
+    }
+
+    /// Set the order patterns play in, e.g. "A A B A" ('' clears the chain)
+    pub fn set_pattern_chain(&mut self, text: &str) -> Result<()> {
+        self.pattern_chain = pattern::parse_chain(text, &self.patterns)?;
+        tracing::debug!("Pattern chain: {}", self.pattern_chain.join(" "));
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Ticks the pattern chain plays for
+    pub fn pattern_chain_ticks(&self) -> u32 {
+        pattern::expand_chain(&self.patterns, &self.pattern_chain).1
+    }
+
+    /// The chord symbol sounding at a tick (the last one at or before it)
+    pub fn chord_at(&self, tick: u32) -> Option<&ChordSymbol> {
+        self.chords.iter().rev().find(|c| c.tick <= tick)
+    }
+
+    /// Get the notes as they should be played, with the chord track comped,
+    /// playback strums, accent dynamics, and the tracks' mute, solo, and
+    /// volume applied
+    ///
+    /// Notes with a probability or take lane play as on the first pass.
+    pub fn playback_notes(&self) -> Vec<Note> {
+        self.playback_notes_for_pass(0)
+    }
+
+    /// Get the notes a pass through the song plays (see [`crate::chance`]), with playback effects applied
+    ///
+    /// Repeats are unfolded and the pattern chain laid out, so ticks are on
+    /// the played timeline.
+    pub fn playback_notes_for_pass(&self, pass: u32) -> Vec<Note> {
+        if !self.repeats.is_empty() || !self.pattern_chain.is_empty() {
+            return self.as_played().playback_notes_for_pass(pass);
+        }
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let mut notes: Vec<Note> = self
+            .notes
+            .iter()
+            .filter(|note| self.is_voice_audible(note.voice) && chance::plays_on_pass(note, ticks_per_measure, pass))
+            .cloned()
+            .collect();
+        if self.is_voice_audible(CHORD_VOICE) {
+            notes.extend(chord::comp_notes(&self.chords, ticks_per_measure, self.measure_count() * ticks_per_measure));
+        }
+        if self.settings.accent_dynamics {
+            for note in &mut notes {
+                note.velocity = self.accented_velocity(note);
+            }
+        }
+        for note in &mut notes {
+            if let Some(track) = self.track(note.voice).filter(|t| t.volume != FULL_VOLUME) {
+                note.velocity = (note.velocity as u32 * track.volume as u32 / FULL_VOLUME as u32).min(127) as u8;
+            }
+        }
+        for strum in &self.strums {
+            let mut chord: Vec<&mut Note> = notes.iter_mut().filter(|n| n.start_tick == strum.tick).collect();
+            strum_chord(&mut chord, strum.spread_ticks, strum.direction);
+        }
+        notes.sort_by_key(|n| n.start_tick);
+        notes
+    }
+
+    /// Keep the notes a pass plays as plain notes and drop the rest; returns how many were dropped
+    pub fn freeze_arrangement(&mut self, pass: u32) -> usize {
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let before = self.notes.len();
+        self.notes = chance::freeze_arrangement(&self.notes, ticks_per_measure, pass);
+        self.update_modified();
+        tracing::info!("Froze the arrangement of pass {}: {} notes dropped", pass, before - self.notes.len());
+        before - self.notes.len()
+    }
+
+    /// Whether playback changes the notes: strums, accent dynamics, or a track volume
+    pub fn has_playback_effects(&self) -> bool {
+        let comping = !self.chords.is_empty();
+        !self.strums.is_empty()
+            || self.settings.accent_dynamics
+            || self.settings.tracks.iter().any(|t| t.volume != FULL_VOLUME && !(comping && t.voice == CHORD_VOICE))
+    }
+
+    /// Bake the playback strums, accent dynamics, and track volumes into the
+    /// notes, then turn them off, so export writes what playback plays
+    ///
+    /// Works on the written notes (repeats, chance, and mute/solo stay as
+    /// they are), applying the effects in the order playback does. Returns
+    /// how many notes changed.
+    ///
+    /// The chord track's comp is made at playback rather than stored, so
+    /// there is nothing to bake it into: the written notes are strummed as
+    /// they were alongside it, and it keeps its track volume, but it plays
+    /// without accents and strums once they are turned off.
+    pub fn flatten_playback_effects(&mut self) -> usize {
+        let before = self.notes.clone();
+        let comping = !self.chords.is_empty();
+        if self.settings.accent_dynamics {
+            let velocities: Vec<u8> = self.notes.iter().map(|n| self.accented_velocity(n)).collect();
+            self.notes.iter_mut().zip(velocities).for_each(|(note, velocity)| note.velocity = velocity);
+            self.settings.accent_dynamics = false;
+        }
+        for track in &mut self.settings.tracks {
+            if track.volume != FULL_VOLUME && !(comping && track.voice == CHORD_VOICE) {
+                for note in self.notes.iter_mut().filter(|n| n.voice == track.voice) {
+                    note.velocity = (note.velocity as u32 * track.volume as u32 / FULL_VOLUME as u32).min(127) as u8;
+                }
+                track.volume = FULL_VOLUME;
+            }
+        }
+        // Playback spreads a strum over the comp as well, which moves the
+        // written notes to different offsets than strumming them alone
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let mut comp = if comping && self.is_voice_audible(CHORD_VOICE) {
+            chord::comp_notes(&self.chords, ticks_per_measure, self.measure_count() * ticks_per_measure)
+        } else {
+            Vec::new()
+        };
+        for strum in std::mem::take(&mut self.strums) {
+            let mut chord: Vec<&mut Note> =
+                self.notes.iter_mut().chain(comp.iter_mut()).filter(|n| n.start_tick == strum.tick).collect();
+            strum_chord(&mut chord, strum.spread_ticks, strum.direction);
+        }
+
+        let changed = self.notes.iter().zip(&before).filter(|(a, b)| a != b).count();
+        self.sort_notes();
+        self.update_modified();
+        tracing::info!("Flattened playback effects: {} notes changed", changed);
+        changed
+    }
+
+    /// Replace the repeat structure, if it can be played
+    pub fn set_repeats(&mut self, mut repeats: RepeatStructure) -> Result<()> {
+        repeats.validate()?;
+        repeats.sort();
+        tracing::debug!("Setting repeats: {} repeat(s), {} ending(s)", repeats.repeats.len(), repeats.endings.len());
+        self.repeats = repeats;
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Written measures in the order they are played
+    pub fn measure_order(&self) -> Vec<u32> {
+        self.repeats.measure_order(self.measure_count())
+    }
+
+    /// The song as played, with its repeats unfolded into consecutive measures
+    ///
+    /// Each played measure gets a copy of its notes, strums, chord symbols,
+    /// lyrics, and tempo changes, and the tempo and chord are restated wherever
+    /// playback jumps. Markers and
+    /// annotations appear once, the first time their measure is played. The result has
+    /// no repeat structure; the song itself keeps its compact, written form.
+    pub fn flatten_repeats(&self) -> Song {
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let mut flat = Song {
+            notes: Vec::new(),
+            markers: Vec::new(),
+            strums: Vec::new(),
+            chords: Vec::new(),
+            lyrics: Vec::new(),
+            annotations: Vec::new(),
+            repeats: RepeatStructure::default(),
+            ..self.clone()
+        };
+        flat.settings.tempo_map.clear();
+
+        let order = self.measure_order();
+        let mut seen = HashSet::new();
+        let mut tempo = self.settings.tempo;
+        for (played, &measure) in order.iter().enumerate() {
+            let start = (measure - 1) * ticks_per_measure;
+            let range = start..start + ticks_per_measure;
+            let flat_start = played as u32 * ticks_per_measure;
+            let to_flat = |tick: u32| tick - start + flat_start;
+
+            let written_tempo = self.tempo_at_tick(start);
+            if written_tempo != tempo {
+                flat.settings.tempo_map.push(TempoChange { tick: flat_start, tempo: written_tempo });
+                tempo = written_tempo;
+            }
+            for change in self.settings.tempo_map.iter().filter(|c| c.tick > start && range.contains(&c.tick)) {
+                flat.settings.tempo_map.push(TempoChange { tick: to_flat(change.tick), tempo: change.tempo });
+                tempo = change.tempo;
+            }
+            for note in self.notes.iter().filter(|n| range.contains(&n.start_tick)) {
+                flat.notes.push(Note { start_tick: to_flat(note.start_tick), ..note.clone() });
+            }
+            for strum in self.strums.iter().filter(|s| range.contains(&s.tick)) {
+                flat.strums.push(Strum { tick: to_flat(strum.tick), ..*strum });
+            }
+            let sounding = self.chord_at(start).filter(|c| c.tick < start);
+            if let Some(chord) = sounding.filter(|c| flat.chords.last().is_none_or(|last| last.symbol != c.symbol)) {
+                flat.chords.push(ChordSymbol { tick: flat_start, symbol: chord.symbol.clone() });
+            }
+            for chord in self.chords.iter().filter(|c| range.contains(&c.tick)) {
+                flat.chords.push(ChordSymbol { tick: to_flat(chord.tick), symbol: chord.symbol.clone() });
+            }
+            for lyric in self.lyrics.iter().filter(|l| range.contains(&l.tick)) {
+                flat.lyrics.push(Lyric { tick: to_flat(lyric.tick), ..lyric.clone() });
+            }
+            if seen.insert(measure) {
+                for marker in self.markers.iter().filter(|m| range.contains(&m.tick)) {
+                    flat.markers.push(Marker { tick: to_flat(marker.tick), ..marker.clone() });
+                }
+                for annotation in self.annotations.iter().filter(|a| range.contains(&a.tick)) {
+                    let end_tick = annotation.end_tick.map(|end| to_flat(end.max(annotation.tick)));
+                    flat.annotations.push(Annotation { tick: to_flat(annotation.tick), end_tick, ..annotation.clone() });
+                }
+            }
+        }
+        flat.markers.sort_by_key(|m| m.tick);
+        flat.annotations.sort_by_key(|a| a.tick);
+        tracing::debug!("Flattened repeats: {} measures played from {}", order.len(), self.measure_count());
+        flat
+    }
+
+    /// The song as played: itself, or a copy with its repeats flattened and
+    /// its pattern chain laid out among the notes
+    pub fn as_played(&self) -> Cow<'_, Song> {
+        if self.repeats.is_empty() && self.pattern_chain.is_empty() {
+            return Cow::Borrowed(self);
+        }
+        let mut played = if self.repeats.is_empty() { self.clone() } else { self.flatten_repeats() };
+        if !played.pattern_chain.is_empty() {
+            let (notes, _) = pattern::expand_chain(&played.patterns, &played.pattern_chain);
+            played.notes.extend(notes);
+            played.sort_notes();
+            played.pattern_chain.clear();
+        }
+        Cow::Owned(played)
+    }
+
+    /// Written tick of a tick on the played timeline (e.g., for the playhead)
+    pub fn written_tick(&self, played_tick: u32) -> u32 {
+        if self.repeats.is_empty() {
+            return played_tick;
+        }
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let order = self.measure_order();
+        match order.get((played_tick / ticks_per_measure) as usize) {
+            Some(&measure) => (measure - 1) * ticks_per_measure + played_tick % ticks_per_measure,
+            // Past the last played measure (a note held over the end)
+            None => {
+                let last = order.last().copied().unwrap_or(0);
+                played_tick - order.len() as u32 * ticks_per_measure + last * ticks_per_measure
+            }
+        }
+    }
+
+    /// Tick on the played timeline where a written tick is first played (e.g., to seek)
+    pub fn played_tick(&self, written_tick: u32) -> u32 {
+        if self.repeats.is_empty() {
+            return written_tick;
+        }
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        let measure = written_tick / ticks_per_measure + 1;
+        let order = self.measure_order();
+        let played = order.iter().position(|&m| m == measure).unwrap_or(order.len());
+        played as u32 * ticks_per_measure + written_tick % ticks_per_measure
+    }
+
+    /// Velocity of a note scaled by the accent of the beat it starts on, in its voice's meter
+    ///
+    /// Never drops a sounding note to 0, which MIDI treats as note-off.
+    pub fn accented_velocity(&self, note: &Note) -> u8 {
+        if note.velocity == 0 {
+            return 0;
+        }
+        let accent = self.voice_time_signature(note.voice).accent_at_tick(note.start_tick);
+        let scaled = (note.velocity as f32 * accent.velocity_multiplier()).round() as u8;
+        scaled.max(1)
+    }
+
+    /// Add a marker (replaces any existing marker with the same name)
+    pub fn add_marker(&mut self, name: impl Into<String>, tick: u32) {
+        let name = name.into();
+        tracing::debug!("Adding marker '{}' at tick {}", name, tick);
+
+        // Moving a marker keeps its section color
+        let color = self.markers.iter().find(|m| m.name == name).and_then(|m| m.color);
+        self.markers.retain(|m| m.name != name);
+        self.markers.push(Marker { name, tick, color });
+        self.markers.sort_by_key(|m| m.tick);
+        self.update_modified();
+    }
+
+    /// Set (or with `None`, clear) the color label of the section a marker starts
+    pub fn set_section_color(&mut self, name: &str, color: Option<ColorLabel>) -> Result<()> {
+        let marker = self.markers.iter_mut().find(|m| m.name == name).ok_or_else(|| {
+            MozartError::InvalidEdit(format!("No marker named '{}'", name))
+        })?;
+        marker.color = color;
+        self.update_modified();
+        Ok(())
+    }
+
+    /// Color label of the section containing a tick, if its marker has one
+    pub fn section_color_at(&self, tick: u32) -> Option<ColorLabel> {
+        self.markers.iter().rev().find(|m| m.tick <= tick).and_then(|m| m.color)
+    }
+
+    /// Remove a marker by name
+    pub fn remove_marker(&mut self, name: &str) -> Option<Marker> {
+        let index = self.markers.iter().position(|m| m.name == name)?;
+        self.update_modified();
+        Some(self.markers.remove(index))
+    }
+
+    /// Set the lyrics from text, one syllable per melody (voice 0) note onset
+    ///
+    /// Words split into syllables at hyphens ("Hap-py"), a `_` holds the
+    /// previous syllable over another note, and a new line (or `/`) starts a
+    /// new lyric line. Replaces any existing lyrics; syllables past the last
+    /// note are dropped. Returns the number of syllables placed.
+    pub fn set_lyrics(&mut self, text: &str) -> usize {
+        let mut onsets: Vec<u32> = self.notes.iter().filter(|n| n.voice == 0).map(|n| n.start_tick).collect();
+        onsets.dedup();
+
+        let mut lyrics = Vec::new();
+        let mut ticks = onsets.into_iter();
+        let mut dropped = 0;
+        for line in text.split(['\n', '/']) {
+            let mut new_line = true;
+            for word in line.split_whitespace() {
+                let syllables: Vec<&str> = word.split('-').filter(|s| !s.is_empty()).collect();
+                for (i, syllable) in syllables.iter().enumerate() {
+                    let Some(tick) = ticks.next() else {
+                        dropped += 1;
+                        continue;
+                    };
+                    if *syllable == "_" {
+                        continue;
+                    }
+                    let hyphen = if syllables[i + 1..].iter().any(|s| *s != "_") { "-" } else { "" };
+                    lyrics.push(Lyric { tick, text: format!("{}{}", syllable, hyphen), new_line });
+                    new_line = false;
+                }
+            }
+        }
+        if dropped > 0 {
+            tracing::warn!("Dropped {} lyric syllables past the last note", dropped);
+        }
+
+        tracing::debug!("Set {} lyric syllables", lyrics.len());
+        self.lyrics = lyrics;
+        self.update_modified();
+        self.lyrics.len()
+    }
+
+    /// The lyrics as text for editing, the inverse of [`Song::set_lyrics`]
+    pub fn lyrics_text(&self) -> String {
+        let mut onsets: Vec<u32> = self.notes.iter().filter(|n| n.voice == 0).map(|n| n.start_tick).collect();
+        onsets.dedup();
+        // Notes starting strictly between two ticks hold the earlier syllable
+        let held = |from: u32, to: u32| onsets.iter().filter(|&&t| t > from && t < to).count();
+
+        let mut text = String::new();
+        let mut previous: Option<&Lyric> = None;
+        for lyric in &self.lyrics {
+            match previous {
+                Some(prev) if prev.text.ends_with('-') && !lyric.new_line => {
+                    text.push_str(&"_-".repeat(held(prev.tick, lyric.tick)));
+                }
+                Some(prev) => {
+                    text.push_str(&" _".repeat(held(prev.tick, lyric.tick)));
+                    text.push(if lyric.new_line { '\n' } else { ' ' });
+                }
+                None => text.push_str(&"_ ".repeat(onsets.iter().filter(|&&t| t < lyric.tick).count())),
+            }
+            text.push_str(&lyric.text);
+            previous = Some(lyric);
+        }
+        text
+    }
+
+    /// Add an annotation at a tick, or over `tick..end_tick`; returns its index
+    ///
+    /// Annotations at the same tick keep the order they were added in.
+    pub fn add_annotation(&mut self, tick: u32, end_tick: Option<u32>, text: &str) -> Result<usize> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(MozartError::InvalidEdit("An annotation needs some text".to_string()));
+        }
+        if end_tick.is_some_and(|end| end <= tick) {
+            return Err(MozartError::InvalidPosition(format!("An annotated range from tick {} must end after it", tick)));
+        }
+        tracing::debug!("Adding annotation '{}' at tick {}", text, tick);
+
+        let index = self.annotations.partition_point(|a| a.tick <= tick);
+        self.annotations.insert(index, Annotation { tick, end_tick, text: text.to_string() });
+        self.update_modified();
+        Ok(index)
+    }
+
+    /// Remove an annotation by index
+    pub fn remove_annotation(&mut self, index: usize) -> Option<Annotation> {
+        if index >= self.annotations.len() {
+            return None;
+        }
+        self.update_modified();
+        Some(self.annotations.remove(index))
+    }
+
+    /// Get the annotations at or over a tick range, in order
+    pub fn get_annotations(&self, start_tick: u32, end_tick: u32) -> Vec<&Annotation> {
+        self.annotations
+            .iter()
+            .filter(|a| a.tick < end_tick && a.end_tick.unwrap_or(a.tick.saturating_add(1)) > start_tick)
+            .collect()
+    }
+
+    /// Get the tick of a marker by name
+    pub fn marker_tick(&self, name: &str) -> Option<u32> {
+        self.markers.iter().find(|m| m.name == name).map(|m| m.tick)
+    }
+
+    /// Get the first marker strictly after a tick
+    pub fn next_marker(&self, tick: u32) -> Option<&Marker> {
+        self.markers.iter().find(|m| m.tick > tick)
+    }
+
+    /// Get the last marker strictly before a tick
+    pub fn prev_marker(&self, tick: u32) -> Option<&Marker> {
+        self.markers.iter().rev().find(|m| m.tick < tick)
+    }
+
+    /// Get the start tick of a measure (1-indexed)
+    pub fn measure_start_tick(&self, measure: u32) -> Result<u32> {
+        self.mbt_to_tick(measure, 1, 0)
+    }
+
+    /// Ticks from the start of measure `first` (1-indexed) to the end of `count` measures
+    pub fn measures_range(&self, first: u32, count: u32) -> Result<(u32, u32)> {
+        let last = first
+            .checked_add(count.max(1))
+            .ok_or_else(|| MozartError::InvalidPosition(format!("Measure {} is too far into the song", first)))?;
+        Ok((self.measure_start_tick(first)?, self.measure_start_tick(last)?))
+    }
+
+    // First measure and length in measures of the loop region, if there is one
+    fn loop_measures(&self) -> Option<(u32, u32)> {
+        let (start, end) = self.view.loop_region?;
+        let first = self.position_to_mbt(start).measure;
+        let last = self.position_to_mbt(end.saturating_sub(1).max(start)).measure;
+        Some((first, last - first + 1))
+    }
+
+    fn set_loop_measures(&mut self, first: u32, count: u32) -> Result<(u32, u32)> {
+        let region = self.measures_range(first, count)?;
+        tracing::debug!("Looping measures {}-{}", first, first + count - 1);
+        self.view.loop_region = Some(region);
+        Ok(region)
+    }
+
+    /// Loop the measure a tick (e.g., the playhead) falls in; returns the loop region
+    ///
+    /// The loop is view state: saved with the song but not an undoable edit.
+    pub fn loop_current_measure(&mut self, tick: u32) -> Result<(u32, u32)> {
+        self.set_loop_measures(self.position_to_mbt(tick).measure, 1)
+    }
+
+    /// Move the loop forward by its own length (the next bar of a one-bar
+    /// loop); without a loop, loop the measure after the one at `tick`
+    pub fn loop_next_measure(&mut self, tick: u32) -> Result<(u32, u32)> {
+        let (first, count) = self.loop_measures().unwrap_or((self.position_to_mbt(tick).measure, 1));
+        self.set_loop_measures(first + count, count)
+    }
+
+    /// Move the loop back by its own length, stopping at measure 1; without a
+    /// loop, loop the measure before the one at `tick`
+    pub fn loop_previous_measure(&mut self, tick: u32) -> Result<(u32, u32)> {
+        let (first, count) = self.loop_measures().unwrap_or((self.position_to_mbt(tick).measure, 1));
+        self.set_loop_measures(first.saturating_sub(count).max(1), count)
+    }
+
+    /// Grow the loop by the measure after it; without a loop, loop the
+    /// measure at `tick`
+    pub fn extend_loop_by_measure(&mut self, tick: u32) -> Result<(u32, u32)> {
+        match self.loop_measures() {
+            Some((first, count)) => self.set_loop_measures(first, count + 1),
+            None => self.loop_current_measure(tick),
+        }
+    }
+
+    /// Stop looping; returns the region that was looped
+    pub fn clear_loop(&mut self) -> Option<(u32, u32)> {
+        self.view.loop_region.take()
+    }
+
+    /// Get the notes overlapping a tick range, unclipped and with their indices
+    ///
+    /// For fetching only what a viewport shows; the indices are valid for
+    /// editing commands until the notes change.
+    pub fn note_window(&self, start_tick: u32, end_tick: u32) -> Vec<IndexedNote> {
+        // Notes are sorted by start, so everything past this starts too late
+        let end = self.notes.partition_point(|n| n.start_tick < end_tick);
+        self.notes[..end]
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.end_tick() > start_tick)
+            .map(|(index, note)| IndexedNote { index, note: note.clone() })
+            .collect()
+    }
+
+    /// Get the notes sounding within a tick range, clipped to the range
+    ///
+    /// Ticks stay absolute; a note that starts before `start_tick` or runs past
+    /// `end_tick` is shortened to fit.
+    pub fn notes_in_range(&self, start_tick: u32, end_tick: u32) -> Vec<Note> {
+        self.notes
+            .iter()
+            .filter(|n| n.start_tick < end_tick && n.end_tick() > start_tick)
+            .map(|n| {
+                let mut note = n.clone();
+                note.start_tick = n.start_tick.max(start_tick);
+                note.duration_ticks = n.end_tick().min(end_tick) - note.start_tick;
+                note
+            })
+            .collect()
+    }
+
     /// Sort notes by start time
     fn sort_notes(&mut self) {
         self.notes.sort_by_key(|n| n.start_tick);
     }
 
     /// Update the modified timestamp
-    fn update_modified(&mut self) {
+    pub(crate) fn update_modified(&mut self) {
         self.metadata.modified = chrono_lite_now();
     }
 
@@ -169,7 +1674,119 @@ impl Song {
     pub fn measure_count(&self) -> u32 {
         let ticks = self.duration_ticks();
         let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
-        (ticks + ticks_per_measure - 1) / ticks_per_measure
+        ticks.div_ceil(ticks_per_measure)
+    }
+
+    /// Convert a tick position to seconds, honoring the tempo map
+    pub fn tick_to_seconds(&self, tick: u32) -> f64 {
+        let ticks_per_beat = crate::TICKS_PER_QUARTER as f64;
+        let mut seconds = 0.0;
+        let mut segment_start = 0u32;
+        let mut tempo = self.settings.tempo;
+
+        for change in &self.settings.tempo_map {
+            if change.tick >= tick {
+                break;
+            }
+            let beats = (change.tick - segment_start) as f64 / ticks_per_beat;
+            seconds += beats * 60.0 / tempo as f64;
+            segment_start = change.tick;
+            tempo = change.tempo;
+        }
+
+        let beats = (tick - segment_start) as f64 / ticks_per_beat;
+        seconds + beats * 60.0 / tempo as f64
+    }
+
+    /// Convert seconds to a tick position, honoring the tempo map
+    pub fn seconds_to_tick(&self, seconds: f64) -> u32 {
+        let ticks_per_beat = crate::TICKS_PER_QUARTER as f64;
+        let mut remaining = seconds.max(0.0);
+        let mut segment_start = 0u32;
+        let mut tempo = self.settings.tempo;
+
+        for change in &self.settings.tempo_map {
+            let segment_ticks = change.tick - segment_start;
+            let segment_seconds = segment_ticks as f64 / ticks_per_beat * 60.0 / tempo as f64;
+            if remaining < segment_seconds {
+                break;
+            }
+            remaining -= segment_seconds;
+            segment_start = change.tick;
+            tempo = change.tempo;
+        }
+
+        segment_start + (remaining * tempo as f64 / 60.0 * ticks_per_beat).round() as u32
+    }
+
+    /// Convert a tick to a musical position (measure.beat.tick)
+    pub fn position_to_mbt(&self, tick: u32) -> MusicalPosition {
+        self.settings.time_signature.position_at_tick(tick)
+    }
+
+    /// Convert measure, beat, and tick (1-indexed measure and beat) to a tick position
+    pub fn mbt_to_tick(&self, measure: u32, beat: u32, tick: u32) -> Result<u32> {
+        self.settings
+            .time_signature
+            .tick_at_position(MusicalPosition::new(measure, beat, tick))
+    }
+
+    /// Format a tick for the transport display (e.g., "5.3.120 | 0:42")
+    pub fn format_transport_time(&self, tick: u32) -> String {
+        format!(
+            "{} | {}",
+            self.position_to_mbt(tick),
+            format_duration(self.tick_to_seconds(tick))
+        )
+    }
+
+    /// Get the duration in seconds until the last note ends, honoring the tempo map
+    pub fn duration_with_tempo_map(&self) -> f64 {
+        self.tick_to_seconds(self.duration_ticks())
+    }
+
+    /// Get the duration in seconds until the final barline
+    ///
+    /// Unlike `duration_seconds`, this includes any trailing rest that fills
+    /// out the last measure, and honors the tempo map.
+    pub fn duration_to_end_of_last_measure(&self) -> f64 {
+        let ticks_per_measure = self.settings.time_signature.ticks_per_measure();
+        self.tick_to_seconds(self.measure_count() * ticks_per_measure)
+    }
+
+    /// Get the seconds remaining from a tick until the final barline
+    pub fn remaining_seconds(&self, tick: u32) -> f64 {
+        (self.duration_to_end_of_last_measure() - self.tick_to_seconds(tick)).max(0.0)
+    }
+
+    /// Get a display summary of the song
+    pub fn info(&self) -> SongInfo {
+        let duration_seconds = self.duration_with_tempo_map();
+        let total_seconds = self.duration_to_end_of_last_measure();
+        let measure_count = self.measure_count();
+        let pitch_name = |pitch: Option<u8>| pitch.and_then(|p| Pitch::from_midi(p).ok()).map(|p| p.to_string());
+
+        SongInfo {
+            title: self.metadata.title.clone(),
+            tempo: self.settings.tempo,
+            time_signature: self.settings.time_signature.to_string(),
+            key: self.settings.key.to_string(),
+            note_count: self.notes.len(),
+            measure_count,
+            duration_ticks: self.duration_ticks(),
+            duration_seconds,
+            total_seconds,
+            duration_display: format_duration(duration_seconds),
+            total_display: format_duration(total_seconds),
+            lowest_pitch: pitch_name(self.notes.iter().map(|n| n.pitch).min()),
+            highest_pitch: pitch_name(self.notes.iter().map(|n| n.pitch).max()),
+            notes_per_measure: if measure_count == 0 { 0.0 } else { self.notes.len() as f32 / measure_count as f32 },
+            out_of_key_count: self
+                .notes
+                .iter()
+                .filter(|n| !self.settings.key.contains(PitchClass::new(n.pitch % 12)))
+                .count(),
+        }
     }
 
     /// Serialize to JSON string
@@ -181,7 +1798,15 @@ impl Song {
     /// Deserialize from JSON string
     pub fn from_json(json: &str) -> Result<Self> {
         tracing::debug!("Deserializing song from JSON");
-        serde_json::from_str(json).map_err(MozartError::from)
+        let mut song: Song = serde_json::from_str(json)?;
+        // Hand-edited files can hold repeats that set_repeats would refuse,
+        // such as a sign at measure 0, which playback can't place
+        song.repeats.validate().map_err(|e| match e {
+            MozartError::InvalidEdit(msg) => MozartError::ParseError(msg),
+            e => e,
+        })?;
+        song.repeats.sort();
+        Ok(song)
     }
 
     /// Save to file
@@ -221,8 +1846,24 @@ impl Default for Song {
     }
 }
 
+// Nearest multiple of `grid_ticks` (ties going later), or the one below where the next would overflow
+fn snap_to_grid(ticks: u32, grid_ticks: u32) -> u32 {
+    let below = ticks - ticks % grid_ticks;
+    if ticks % grid_ticks >= grid_ticks - grid_ticks / 2 {
+        below.checked_add(grid_ticks).unwrap_or(below)
+    } else {
+        below
+    }
+}
+
+/// Format seconds as m:ss (e.g., 83.4 -> "1:23")
+pub fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).floor() as u64;
+    format!("{}:{:02}", total / 60, total % 60)
+}
+
 /// Simple timestamp generator (no external deps)
-#[cfg(not(feature = "wasm"))]
+#[cfg(not(all(feature = "wasm", target_arch = "wasm32")))]
 fn chrono_lite_now() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -235,7 +1876,7 @@ fn chrono_lite_now() -> String {
 }
 
 /// WASM-compatible timestamp generator using js_sys
-#[cfg(feature = "wasm")]
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 fn chrono_lite_now() -> String {
     let millis = js_sys::Date::now() as u64;
     let secs = millis / 1000;
@@ -246,6 +1887,7 @@ fn chrono_lite_now() -> String {
 mod tests {
     use super::*;
     use crate::note::{NoteDuration, NoteValue};
+    use crate::groove::extract_groove;
     use crate::pitch::{Pitch, PitchClass};
     use pretty_assertions::assert_eq;
 
@@ -280,6 +1922,463 @@ mod tests {
         assert_eq!(song.duration_ticks(), 960);
     }
 
+    #[test]
+    fn test_set_note_pitch() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+
+        song.set_note_pitch(0, 67).unwrap();
+        assert_eq!(song.notes[0].pitch, 67);
+
+        assert!(song.set_note_pitch(0, 128).is_err());
+        assert!(song.set_note_pitch(1, 60).is_err());
+    }
+
+    #[test]
+    fn test_split_note() {
+        let mut song = Song::new();
+        song.add_note(Note::with_velocity(60, 0, 960, 80));
+        song.add_note(Note::new(64, 480, 480));
+
+        let second = song.split_note(0, 240).unwrap();
+        assert_eq!(second, 1);
+        let spans: Vec<(u8, u32, u32)> =
+            song.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(spans, vec![(60, 0, 240), (60, 240, 720), (64, 480, 480)]);
+        assert_eq!(song.notes[1].velocity, 80);
+
+        // The split point must fall strictly inside the note
+        assert!(song.split_note(0, 0).is_err());
+        assert!(song.split_note(0, 240).is_err());
+        assert!(song.split_note(9, 100).is_err());
+    }
+
+    #[test]
+    fn test_join_notes() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(62, 240, 240));
+        song.add_note(Note::new(60, 480, 480));
+        song.add_note(Note::new(60, 960, 240));
+
+        let merged = song.join_notes(&[3, 0, 2]).unwrap();
+        assert_eq!(merged, 0);
+        assert_eq!(song.notes.len(), 2);
+        assert_eq!((song.notes[0].pitch, song.notes[0].duration_ticks), (60, 1200));
+
+        // Mixed pitches, gaps, and single notes are rejected
+        assert!(song.join_notes(&[0, 1]).is_err());
+        assert!(song.join_notes(&[0]).is_err());
+        song.add_note(Note::new(60, 1920, 480));
+        assert!(song.join_notes(&[0, 2]).is_err());
+    }
+
+    #[test]
+    fn test_make_legato() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 240));
+        song.add_note(Note::new(64, 0, 120)); // Chord with the first note
+        song.add_note(Note::new(62, 480, 480)); // Already overlaps the next note
+        song.add_note(Note::new(64, 720, 120));
+
+        assert_eq!(song.make_legato(&[0, 1, 2, 3], 0), 3);
+        let durations: Vec<u32> = song.notes.iter().map(|n| n.duration_ticks).collect();
+        assert_eq!(durations, vec![480, 480, 240, 120]);
+
+        // Overlap runs each note slightly into the next
+        song.make_legato(&[2, 3], 10);
+        assert_eq!(song.notes[2].duration_ticks, 250);
+        assert_eq!(song.notes[0].duration_ticks, 480);
+        song.make_legato(&[2, 3], u32::MAX);
+        assert_eq!(song.notes[2].end_tick(), u32::MAX);
+    }
+
+    #[test]
+    fn test_quantize() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 10, 230));
+        song.add_note(Note::new(62, 470, 130));
+        song.add_note(Note::new(64, 1000, 100));
+
+        // Sixteenth grid; the last note isn't selected
+        assert_eq!(song.quantize(&[0, 1], 120, false).unwrap(), 2);
+        let starts: Vec<u32> = song.notes.iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, vec![0, 480, 1000]);
+        assert_eq!(song.notes[1].duration_ticks, 130);
+
+        assert_eq!(song.quantize(&[0, 1, 2], 120, true).unwrap(), 3);
+        let durations: Vec<u32> = song.notes.iter().map(|n| n.duration_ticks).collect();
+        assert_eq!(durations, vec![240, 120, 120]);
+        assert_eq!(song.notes[2].start_tick, 960);
+        assert!(song.quantize(&[0], 0, false).is_err());
+
+        // At the end of the tick range, rounding up would overflow
+        let mut last = Song::new();
+        last.add_note(Note::new(60, u32::MAX - 1, 1));
+        last.quantize(&[0], 480, true).unwrap();
+        assert_eq!(last.notes[0].start_tick, u32::MAX / 480 * 480);
+    }
+
+    #[test]
+    fn test_quantize_to_accents() {
+        let mut song = Song::new();
+        song.set_time_signature(TimeSignature::parse("7/8").unwrap());
+        // Slightly late for the 3+2+2 group starts at 720 and 1200
+        song.add_note(Note::new(60, 655, 200));
+        song.add_note(Note::new(62, 1135, 200));
+        song.add_note(Note::new(64, 250, 200));
+
+        let mut plain = song.clone();
+        plain.quantize(&[0, 1, 2], 120, false).unwrap();
+        let starts: Vec<u32> = plain.notes.iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, vec![240, 600, 1080]);
+
+        assert_eq!(song.quantize_to_accents(&[0, 1, 2], 120, 1.0, true).unwrap(), 3);
+        let starts: Vec<u32> = song.notes.iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, vec![240, 720, 1200]);
+        assert_eq!(song.notes[0].duration_ticks, 240);
+
+        assert!(song.quantize_to_accents(&[0], 120, -1.0, false).is_err());
+        assert!(song.quantize_to_accents(&[0], 0, 1.0, false).is_err());
+    }
+
+    #[test]
+    fn test_add_gap() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(62, 480, 100));
+        song.add_note(Note::new(64, 960, 480));
+
+        // Only notes that run into the gap are shortened
+        assert_eq!(song.add_gap(&[0, 1, 2], 60), 1);
+        let durations: Vec<u32> = song.notes.iter().map(|n| n.duration_ticks).collect();
+        assert_eq!(durations, vec![420, 100, 480]);
+
+        // A gap larger than the spacing leaves a one-tick note
+        song.add_gap(&[0, 1], 1000);
+        assert_eq!(song.notes[0].duration_ticks, 1);
+    }
+
+    #[test]
+    fn test_strum_chords() {
+        let mut song = Song::new();
+        song.add_note(Note::new(64, 0, 960));
+        song.add_note(Note::new(60, 0, 960));
+        song.add_note(Note::new(72, 960, 480)); // Lone note: not a chord
+
+        assert_eq!(song.strum_chords(&[0, 1, 2], 40, StrumDirection::Up), 1);
+        let starts: Vec<(u8, u32)> = song.notes.iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(starts, vec![(60, 0), (64, 40), (72, 960)]);
+    }
+
+    #[test]
+    fn test_apply_groove() {
+        let mut performance = Song::new();
+        performance.add_notes([Note::new(60, 0, 240), Note::new(62, 290, 190)]);
+        let template = extract_groove(&performance.notes, 480, 2).unwrap();
+
+        let mut song = Song::new();
+        // An early offbeat lands after the next note once grooved
+        song.add_notes([Note::new(60, 230, 100), Note::new(64, 250, 100), Note::new(67, 720, 240)]);
+        assert_eq!(song.apply_groove(&[0, 2], &template), 2);
+        let starts: Vec<(u8, u32)> = song.notes.iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(starts, vec![(64, 250), (60, 290), (67, 770)]);
+    }
+
+    #[test]
+    fn test_voice_chords() {
+        let mut song = Song::new();
+        song.add_notes(vec![Note::new(60, 0, 960), Note::new(64, 0, 960), Note::new(67, 0, 960)]);
+        song.add_notes(vec![Note::new(62, 960, 960), Note::new(65, 960, 960)]);
+
+        // Only the first chord is selected
+        assert_eq!(song.voice_chords(&[0, 1, 2], Voicing::InvertUp), 1);
+        let pitches: Vec<u8> = song.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![72, 64, 67, 62, 65]);
+
+        assert_eq!(song.voice_chords(&[0, 1, 2], Voicing::Close), 0); // Already close
+        assert_eq!(song.voice_chords(&[0, 1, 2, 3, 4], Voicing::Open), 2);
+        let pitches: Vec<u8> = song.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![72, 64, 79, 62, 77]);
+    }
+
+    #[test]
+    fn test_playback_strum() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 960));
+        song.add_note(Note::new(64, 0, 960));
+        song.set_chord_strum(0, 30, StrumDirection::Down);
+
+        // Stored notes are untouched; only playback is offset
+        assert!(song.notes.iter().all(|n| n.start_tick == 0));
+        let played: Vec<(u8, u32)> = song.playback_notes().iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(played, vec![(64, 0), (60, 30)]);
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.strums, song.strums);
+
+        assert!(song.remove_chord_strum(0).is_some());
+        assert!(song.playback_notes().iter().all(|n| n.start_tick == 0));
+    }
+
+    #[test]
+    fn test_update_and_move_notes() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(64, 480, 480));
+
+        // Moving the first note past the second re-sorts it
+        let index = song.update_note(0, Note::with_velocity(62, 960, 240, 90)).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(song.notes[1].velocity, 90);
+        assert!(song.update_note(5, Note::new(60, 0, 480)).is_err());
+
+        song.move_notes(&[0, 1], 480, -2).unwrap();
+        let moved: Vec<(u8, u32)> = song.notes.iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(moved, vec![(62, 960), (60, 1440)]);
+
+        // All or nothing
+        assert!(song.move_notes(&[0, 1], -1000, 0).is_err());
+        assert!(song.move_notes(&[0], u32::MAX as i64, 0).is_err());
+        assert!(song.move_notes(&[0], 0, 100).is_err());
+        assert_eq!(song.notes[0].start_tick, 960);
+    }
+
+    #[test]
+    fn test_accent_dynamics() {
+        let mut song = Song::new();
+        for beat in 0..3 {
+            song.add_note(Note::with_velocity(60, beat * 480, 480, 100));
+        }
+        song.add_note(Note::with_velocity(64, 1500, 120, 1)); // Off the beat
+
+        let velocities = |notes: &[Note]| notes.iter().map(|n| n.velocity).collect::<Vec<_>>();
+        assert_eq!(velocities(&song.playback_notes()), vec![100, 100, 100, 1]);
+
+        // 4/4 is strong, weak, medium, weak; quiet notes never drop to 0
+        song.settings.accent_dynamics = true;
+        assert_eq!(velocities(&song.playback_notes()), vec![100, 70, 85, 1]);
+        assert_eq!(velocities(&song.notes), vec![100, 100, 100, 1]);
+    }
+
+    #[test]
+    fn test_flatten_playback_effects() {
+        let mut song = Song::new();
+        song.add_notes([60, 64, 67].map(|p| Note::with_velocity(p, 0, 960, 100)));
+        song.add_note(Note::with_velocity(72, 480, 480, 100));
+        song.add_note(Note::with_voice(36, 0, 1920, 100, 1));
+        assert!(!song.has_playback_effects());
+        assert_eq!(song.flatten_playback_effects(), 0);
+
+        song.settings.accent_dynamics = true;
+        song.set_chord_strum(0, 60, StrumDirection::Up);
+        song.set_track_volume(1, 50).unwrap();
+        assert!(song.has_playback_effects());
+        let heard = song.playback_notes();
+
+        // The notes become what was heard, and playback adds nothing more
+        assert_eq!(song.flatten_playback_effects(), 5);
+        assert_eq!(song.notes, heard);
+        assert_eq!(song.playback_notes(), heard);
+        assert!(!song.has_playback_effects());
+        assert_eq!(song.track(1).unwrap().volume, FULL_VOLUME);
+        assert_eq!(song.notes.iter().map(|n| n.velocity).collect::<Vec<_>>(), vec![50, 100, 100, 100, 70]);
+    }
+
+    #[test]
+    fn test_flatten_playback_effects_with_chord_track() {
+        let mut song = Song::new();
+        song.add_notes([60, 64, 67].map(|p| Note::with_velocity(p, 0, 960, 100)));
+        song.set_chord(0, "C").unwrap();
+        song.settings.accent_dynamics = true;
+        song.set_chord_strum(0, 60, StrumDirection::Up);
+        song.set_track_volume(CHORD_VOICE, 50).unwrap();
+        let heard = song.playback_notes();
+        let (written, comp): (Vec<Note>, Vec<Note>) = heard.into_iter().partition(|n| n.voice != CHORD_VOICE);
+
+        // The written notes become what was heard
+        song.flatten_playback_effects();
+        assert_eq!(song.notes, written);
+        assert!(!song.has_playback_effects());
+        // The comp keeps its volume, but not the accents and strum
+        assert_eq!(song.track(CHORD_VOICE).unwrap().volume, 50);
+        let replayed: Vec<Note> = song.playback_notes().into_iter().filter(|n| n.voice == CHORD_VOICE).collect();
+        assert_eq!(replayed.len(), comp.len());
+        assert!(replayed.iter().all(|n| n.start_tick == 0));
+        assert!(comp.iter().any(|n| n.start_tick > 0));
+    }
+
+    #[test]
+    fn test_arrangement_passes() {
+        let mut song = Song::new();
+        song.add_note(Note { take: Some(chance::Take::A), ..Note::new(60, 0, 480) });
+        song.add_note(Note { take: Some(chance::Take::B), ..Note::new(67, 0, 480) });
+        song.add_note(Note::new(64, 480, 480));
+
+        // Each pass plays one lane of the measure, plus the plain note
+        for pass in 0..8 {
+            let pitches: Vec<u8> = song.playback_notes_for_pass(pass).iter().map(|n| n.pitch).collect();
+            assert_eq!(pitches.len(), 2);
+            assert_eq!(pitches[1], 64);
+        }
+        assert_eq!(song.playback_notes(), song.playback_notes_for_pass(0));
+
+        let heard = song.playback_notes_for_pass(5);
+        let json = song.to_json().unwrap();
+        assert_eq!(Song::from_json(&json).unwrap().notes, song.notes);
+        assert_eq!(song.freeze_arrangement(5), 1);
+        assert_eq!(song.notes, heard.into_iter().map(|n| Note { take: None, ..n }).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_flatten_repeats() {
+        let mut song = Song::new();
+        for measure in 0..3 {
+            song.add_note(Note::new(60 + measure as u8, measure * 1920, 480));
+        }
+        song.add_tempo_change(1920, 90);
+        song.add_tempo_change(2880, 60);
+        song.add_marker("B", 1920);
+        let mut repeats = RepeatStructure::default();
+        repeats.add_repeat(crate::repeats::Repeat::new(2, 2)).unwrap();
+        song.set_repeats(repeats).unwrap();
+        assert_eq!(song.measure_order(), vec![1, 2, 2, 3]);
+
+        // The repeat restates the tempo its measure starts with
+        let flat = song.flatten_repeats();
+        let starts: Vec<(u8, u32)> = flat.notes.iter().map(|n| (n.pitch, n.start_tick)).collect();
+        assert_eq!(starts, vec![(60, 0), (61, 1920), (61, 3840), (62, 5760)]);
+        let tempos: Vec<(u32, u16)> = flat.settings.tempo_map.iter().map(|c| (c.tick, c.tempo)).collect();
+        assert_eq!(tempos, vec![(1920, 90), (2880, 60), (3840, 90), (4800, 60)]);
+        assert_eq!(flat.markers.len(), 1);
+        assert!(flat.repeats.is_empty());
+        assert_eq!(song.playback_notes().len(), 4);
+
+        assert_eq!(song.written_tick(3840 + 10), 1920 + 10);
+        assert_eq!(song.written_tick(5760 + 10), 3840 + 10);
+        assert_eq!(song.played_tick(3840), 5760);
+
+        // The file keeps the written form
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.repeats, song.repeats);
+        assert_eq!(loaded.notes.len(), 3);
+    }
+
+    #[test]
+    fn test_chord_track() {
+        let mut song = Song::new();
+        song.add_note(Note::new(72, 0, 7680));
+        song.set_chord(0, "C").unwrap();
+        song.set_chord(3840, "G7").unwrap();
+        assert_eq!(song.set_chord(1920, " Am7 ").unwrap().extensions, vec![10]);
+        assert!(song.set_chord(1920, "Xm").is_err());
+        assert_eq!(song.chord_at(2000).unwrap().symbol, "Am7");
+        assert!(song.remove_chord(1920).is_some());
+        assert_eq!(song.chord_at(2000).unwrap().symbol, "C");
+
+        // The chord track comps under the melody, one strike per measure, until muted
+        let comped = |song: &Song| song.playback_notes().iter().filter(|n| n.voice == CHORD_VOICE).count();
+        assert_eq!(comped(&song), 3 * 2 + 4 * 2);
+        song.mute_track(CHORD_VOICE, true).unwrap();
+        assert_eq!(comped(&song), 0);
+        song.mute_track(CHORD_VOICE, false).unwrap();
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.chords, song.chords);
+
+        // A repeat back to a measure restates the chord sounding there
+        song.set_chord(2400, "F").unwrap();
+        let mut repeats = RepeatStructure::default();
+        repeats.add_repeat(crate::repeats::Repeat::new(2, 2)).unwrap();
+        song.set_repeats(repeats).unwrap();
+        let chords: Vec<(u32, String)> = song.flatten_repeats().chords.into_iter().map(|c| (c.tick, c.symbol)).collect();
+        let expected = [(0, "C"), (2400, "F"), (3840, "C"), (4320, "F"), (5760, "G7")];
+        assert_eq!(chords, expected.map(|(tick, symbol)| (tick, symbol.to_string())));
+
+        song.set_repeats(RepeatStructure::default()).unwrap();
+        song.delete_measures(1, 1).unwrap();
+        let chords: Vec<(u32, &str)> = song.chords.iter().map(|c| (c.tick, c.symbol.as_str())).collect();
+        assert_eq!(chords, vec![(480, "F"), (1920, "G7")]);
+    }
+
+    #[test]
+    fn test_rhythm_pitch() {
+        let mut song = Song::new();
+        assert_eq!(song.parse_melody("xq G4q").unwrap()[0].pitch, 60);
+        song.set_rhythm_pitch(Some(39)).unwrap();
+        assert_eq!(song.parse_melody("xq G4q").unwrap().iter().map(|n| n.pitch).collect::<Vec<_>>(), vec![39, 67]);
+        assert!(song.set_rhythm_pitch(Some(128)).is_err());
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.rhythm_pitch(), 39);
+        // The default isn't written to the file
+        song.set_rhythm_pitch(Some(60)).unwrap();
+        assert!(!song.to_json().unwrap().contains("rhythm_pitch"));
+    }
+
+    #[test]
+    fn test_voice_meters() {
+        let mut song = Song::new();
+        let seven_eight = TimeSignature::parse("7/8").unwrap();
+        song.set_voice_time_signature(1, Some(seven_eight.clone()));
+        assert_eq!(song.voice_time_signature(1).to_string(), "7/8");
+        assert_eq!(song.voice_time_signature(0).to_string(), "4/4");
+
+        // Tick 1680 is the downbeat of the ostinato's second measure but a weak offbeat of the melody's
+        song.settings.accent_dynamics = true;
+        song.add_note(Note::with_voice(72, 1680, 240, 100, 0));
+        song.add_note(Note::with_voice(48, 1680, 240, 100, 1));
+        let velocities: Vec<u8> = song.playback_notes().iter().map(|n| n.velocity).collect();
+        assert_eq!(velocities, vec![70, 100]);
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.settings.voice_meters, song.settings.voice_meters);
+        // A meter matching the song's is still the voice's own, and stays when the song's changes
+        song.set_voice_time_signature(1, Some(TimeSignature::common()));
+        assert_eq!(song.settings.voice_meters.len(), 1);
+        song.set_time_signature(seven_eight);
+        assert_eq!(song.voice_time_signature(1).to_string(), "4/4");
+        // Clearing follows the song again
+        song.set_voice_time_signature(1, None);
+        assert!(!song.to_json().unwrap().contains("voice_meters"));
+    }
+
+    #[test]
+    fn test_tracks() {
+        let mut song = Song::new();
+        song.add_note(Note::new(72, 0, 480));
+        assert_eq!(song.add_track("Bassline").unwrap(), 1);
+        assert_eq!(song.add_track("").unwrap(), 2);
+        assert_eq!(song.track(2).unwrap().name, "Track 3");
+        assert!(song.settings.channel_per_voice);
+        song.add_note(Note::with_voice(36, 0, 480, 100, 1));
+        song.add_note(Note::with_voice(64, 0, 480, 100, 2));
+        assert_eq!(song.track_notes(1).len(), 1);
+
+        song.set_track_instrument(1, Some(Instrument::Strings)).unwrap();
+        assert_eq!(song.voice_instrument(1), Instrument::Strings);
+        assert_eq!(song.voice_instrument(0), Instrument::Synth);
+        song.set_track_volume(2, 50).unwrap();
+        let played = |song: &Song| -> Vec<(u8, u8)> { song.playback_notes().iter().map(|n| (n.pitch, n.velocity)).collect() };
+        assert_eq!(played(&song), vec![(72, 100), (36, 100), (64, 50)]);
+
+        // Muting the melody's voice gives it a track; a solo silences everything else
+        song.mute_track(0, true).unwrap();
+        assert_eq!(song.track(0).unwrap().name, "Voice 1");
+        assert_eq!(played(&song), vec![(36, 100), (64, 50)]);
+        song.solo_track(2, true).unwrap();
+        assert_eq!(played(&song), vec![(64, 50)]);
+        song.solo_track(2, false).unwrap();
+        assert!(!song.is_voice_audible(0));
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.settings.tracks, song.settings.tracks);
+        assert_eq!(song.remove_track(1).unwrap(), 1);
+        assert!(song.track(1).is_none());
+        assert!(song.remove_track(1).is_err());
+        assert!(song.mute_track(16, true).is_err());
+    }
+
     #[test]
     fn test_song_serialization() {
         let mut song = Song::with_title("Serialization Test");
@@ -295,6 +2394,41 @@ mod tests {
         assert_eq!(loaded.notes[1].pitch, 64);
     }
 
+    #[test]
+    fn test_from_json_rejects_unplayable_repeats() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        let mut json: serde_json::Value = serde_json::from_str(&song.to_json().unwrap()).unwrap();
+
+        json["repeats"] = serde_json::json!({ "repeats": [{ "start_measure": 0, "end_measure": 1, "times": 2 }] });
+        assert!(matches!(Song::from_json(&json.to_string()), Err(MozartError::ParseError(_))));
+        json["repeats"] = serde_json::json!({ "segno": 0 });
+        assert!(matches!(Song::from_json(&json.to_string()), Err(MozartError::ParseError(_))));
+
+        json["repeats"] = serde_json::json!({ "segno": 1 });
+        assert_eq!(Song::from_json(&json.to_string()).unwrap().repeats.segno, Some(1));
+
+        // Hand-edited out of order: loads, sorted
+        json["repeats"] = serde_json::json!({ "repeats": [
+            { "start_measure": 5, "end_measure": 6, "times": 2 },
+            { "start_measure": 1, "end_measure": 2, "times": 2 },
+        ] });
+        let starts: Vec<u32> =
+            Song::from_json(&json.to_string()).unwrap().repeats.repeats.iter().map(|r| r.start_measure).collect();
+        assert_eq!(starts, vec![1, 5]);
+    }
+
+    #[test]
+    fn test_view_state_persisted() {
+        let mut song = Song::new();
+        assert!(!song.to_json().unwrap().contains("\"view\""));
+
+        song.view.scroll_x = 960.0;
+        song.view.loop_region = Some((1920, 3840));
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.view, song.view);
+    }
+
     #[test]
     fn test_song_duration() {
         let mut song = Song::new();
@@ -309,6 +2443,325 @@ mod tests {
         assert!((duration - 1.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_duration_to_end_of_last_measure() {
+        let mut song = Song::new();
+        // One and a half measures of 4/4 -> rounds up to two full measures
+        song.add_note(Note::new(60, 0, 2880));
+
+        // 2 measures = 8 beats = 4 seconds at 120 BPM
+        assert!((song.duration_to_end_of_last_measure() - 4.0).abs() < 0.001);
+        assert!((song.duration_with_tempo_map() - 3.0).abs() < 0.001);
+        assert!((song.remaining_seconds(1920) - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_duration_with_tempo_map() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 1920));
+        // Halve the tempo after the first two beats
+        song.add_tempo_change(960, 60);
+
+        assert_eq!(song.tempo_at_tick(0), 120);
+        assert_eq!(song.tempo_at_tick(960), 60);
+
+        // 2 beats at 120 BPM (1s) + 2 beats at 60 BPM (2s)
+        assert!((song.duration_with_tempo_map() - 3.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_seconds_to_tick_with_tempo_map() {
+        let mut song = Song::new();
+        song.add_tempo_change(960, 60);
+
+        assert_eq!(song.seconds_to_tick(0.5), 480);
+        assert_eq!(song.seconds_to_tick(1.0), 960);
+        // One extra second at 60 BPM is one beat
+        assert_eq!(song.seconds_to_tick(2.0), 1440);
+        assert!((song.tick_to_seconds(1440) - 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_transport_position() {
+        let song = Song::new();
+        let tick = song.mbt_to_tick(5, 3, 120).unwrap();
+        assert_eq!(song.position_to_mbt(tick).to_string(), "5.3.120");
+        assert_eq!(song.format_transport_time(song.mbt_to_tick(17, 1, 0).unwrap()), "17.1.0 | 0:32");
+    }
+
+    #[test]
+    fn test_measure_loops() {
+        let mut song = Song::new();
+        song.set_time_signature(TimeSignature::new(3, 4).unwrap());
+        assert_eq!(song.measures_range(2, 2).unwrap(), (1440, 4320));
+        assert!(song.measures_range(0, 1).is_err());
+
+        // Bar by bar from the playhead in measure 2
+        assert_eq!(song.loop_current_measure(1500).unwrap(), (1440, 2880));
+        assert_eq!(song.loop_next_measure(0).unwrap(), (2880, 4320));
+        assert_eq!(song.extend_loop_by_measure(0).unwrap(), (2880, 5760));
+        // A two-bar loop moves two bars at a time, stopping at the start
+        assert_eq!(song.loop_next_measure(0).unwrap(), (5760, 8640));
+        assert_eq!(song.loop_previous_measure(0).unwrap(), (2880, 5760));
+        assert_eq!(song.loop_previous_measure(0).unwrap(), (0, 2880));
+        assert_eq!(song.loop_previous_measure(0).unwrap(), (0, 2880));
+
+        // The loop is view state: saved, but the song isn't modified
+        assert_eq!(Song::from_json(&song.to_json().unwrap()).unwrap().view.loop_region, Some((0, 2880)));
+        assert_eq!(song.clear_loop(), Some((0, 2880)));
+        assert_eq!(song.loop_next_measure(1500).unwrap(), (2880, 4320));
+        song.clear_loop();
+        assert_eq!(song.extend_loop_by_measure(1500).unwrap(), (1440, 2880));
+
+        // No measure after the last one a tick can reach
+        song.clear_loop();
+        assert!(song.loop_next_measure(u32::MAX).is_err());
+        assert!(song.measures_range(u32::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn test_pattern_chain() {
+        let mut song = Song::new();
+        song.add_notes(crate::note::parse_melody("C4w G4w E4h E4h").unwrap());
+
+        // Measures cut into patterns leave the song empty there
+        assert_eq!(song.create_pattern("A", 1, 1).unwrap().notes.len(), 1);
+        song.create_pattern("B", 2, 1).unwrap();
+        assert_eq!(song.notes.len(), 2);
+        assert!(song.create_pattern("A", 3, 1).is_err());
+        assert!(song.create_pattern("C", 1, u32::MAX).is_err());
+        song.clone_pattern("A", "A2").unwrap();
+        song.move_pattern(2, 0).unwrap();
+        let names: Vec<&str> = song.patterns.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["B", "A", "A2"]);
+
+        // The chain plays from the start, alongside what's left of the song
+        assert!(song.set_pattern_chain("A A C").is_err());
+        song.set_pattern_chain("A A B A").unwrap();
+        assert_eq!(song.pattern_chain_ticks(), 7680);
+        let played: Vec<(u32, u8)> = song.playback_notes().iter().map(|n| (n.start_tick, n.pitch)).collect();
+        assert_eq!(played, vec![(0, 60), (1920, 60), (3840, 64), (3840, 67), (4800, 64), (5760, 60)]);
+        assert_eq!(song.as_played().pattern_chain, Vec::<String>::new());
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.pattern_chain, song.pattern_chain);
+        assert!(song.remove_pattern("A").is_some());
+        assert_eq!(song.pattern_chain, vec!["B"]);
+    }
+
+    #[test]
+    fn test_notes_in_range() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 960));
+        song.add_note(Note::new(62, 960, 480));
+        song.add_note(Note::new(64, 1920, 480));
+
+        let notes = song.notes_in_range(480, 1920);
+        assert_eq!(notes.len(), 2);
+        // First note is clipped to the start of the range
+        assert_eq!(notes[0].start_tick, 480);
+        assert_eq!(notes[0].duration_ticks, 480);
+        assert_eq!(notes[1].start_tick, 960);
+        assert_eq!(notes[1].duration_ticks, 480);
+
+        assert!(song.notes_in_range(2400, 3000).is_empty());
+    }
+
+    #[test]
+    fn test_note_window() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 1920)); // Long note reaching into the window
+        song.add_note(Note::new(62, 480, 480));
+        song.add_note(Note::new(64, 1440, 480));
+        song.add_note(Note::new(65, 1920, 480));
+
+        let window: Vec<(usize, u32, u32)> = song
+            .note_window(1000, 1920)
+            .iter()
+            .map(|n| (n.index, n.note.start_tick, n.note.duration_ticks))
+            .collect();
+        assert_eq!(window, vec![(0, 0, 1920), (2, 1440, 480)]);
+    }
+
+    #[test]
+    fn test_marker_navigation() {
+        let mut song = Song::new();
+        song.add_marker("Verse", 1920);
+        song.add_marker("Intro", 0);
+        song.add_marker("Chorus", 7680);
+
+        assert_eq!(song.markers[0].name, "Intro");
+        assert_eq!(song.marker_tick("Chorus"), Some(7680));
+        assert_eq!(song.marker_tick("Bridge"), None);
+
+        assert_eq!(song.next_marker(0).unwrap().name, "Verse");
+        assert_eq!(song.next_marker(1920).unwrap().name, "Chorus");
+        assert!(song.next_marker(7680).is_none());
+        assert_eq!(song.prev_marker(1920).unwrap().name, "Intro");
+        assert!(song.prev_marker(0).is_none());
+
+        // Re-adding a name moves the marker
+        song.add_marker("Verse", 3840);
+        assert_eq!(song.markers.len(), 3);
+        assert_eq!(song.marker_tick("Verse"), Some(3840));
+
+        assert!(song.remove_marker("Verse").is_some());
+        assert!(song.remove_marker("Verse").is_none());
+
+        assert_eq!(song.measure_start_tick(3).unwrap(), 3840);
+        assert!(song.measure_start_tick(0).is_err());
+    }
+
+    #[test]
+    fn test_lyrics() {
+        let mut song = Song::new();
+        song.add_notes((0..7).map(|i| Note::new(60 + i, i as u32 * 480, 480)));
+        song.add_note(Note::with_voice(48, 0, 1920, 80, 1)); // Harmony gets no syllables
+
+        assert_eq!(song.set_lyrics("Hap-py _ birth-day / to you and more"), 6);
+        let words: Vec<(u32, &str, bool)> = song.lyrics.iter().map(|l| (l.tick, l.text.as_str(), l.new_line)).collect();
+        assert_eq!(
+            words,
+            vec![
+                (0, "Hap-", true),
+                (480, "py", false),
+                (1440, "birth-", false),
+                (1920, "day", false),
+                (2400, "to", true),
+                (2880, "you", false),
+            ]
+        );
+        assert_eq!(song.lyrics_text(), "Hap-py _ birth-day\nto you");
+
+        // Held syllables inside a word survive the round trip
+        song.set_lyrics("_ A-_-men");
+        assert_eq!(song.lyrics_text(), "_ A-_-men");
+        assert_eq!(song.lyrics[1].tick, 1440);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut song = Song::new();
+        assert_eq!(song.add_annotation(1920, None, " breathe here ").unwrap(), 0);
+        assert_eq!(song.add_annotation(480, Some(1920), "crescendo into chorus").unwrap(), 0);
+        assert_eq!(song.add_annotation(1920, None, "chorus").unwrap(), 2);
+        assert!(song.add_annotation(0, None, "  ").is_err());
+        assert!(song.add_annotation(960, Some(960), "empty range").is_err());
+
+        let texts = |annotations: Vec<&Annotation>| annotations.iter().map(|a| a.text.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(song.get_annotations(0, 960)), vec!["crescendo into chorus"]);
+        assert_eq!(texts(song.get_annotations(1920, 1921)), vec!["breathe here", "chorus"]);
+
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.annotations, song.annotations);
+
+        // Deleting the first measure drops the range starting in it and moves the rest back
+        song.delete_measures(1, 1).unwrap();
+        assert_eq!(song.annotations.len(), 2);
+        assert_eq!((song.annotations[0].tick, song.annotations[0].end_tick), (0, None));
+        assert_eq!(song.remove_annotation(1).unwrap().text, "chorus");
+        assert!(song.remove_annotation(1).is_none());
+    }
+
+    #[test]
+    fn test_color_labels() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(64, 480, 480));
+        song.add_note(Note::new(67, 960, 480));
+        let played = song.playback_notes();
+
+        assert_eq!(song.set_note_color(&[0, 2], Some(ColorLabel::Blue)).unwrap(), 2);
+        assert_eq!(song.set_note_color(&[0, 1], Some(ColorLabel::Blue)).unwrap(), 1);
+        assert!(song.set_note_color(&[1, 3], None).is_err());
+        assert_eq!(song.notes[1].color, Some(ColorLabel::Blue));
+
+        song.add_marker("Verse", 0);
+        song.add_marker("Fill", 1920);
+        song.set_section_color("Fill", Some(ColorLabel::Orange)).unwrap();
+        assert!(song.set_section_color("Bridge", None).is_err());
+        assert_eq!(song.section_color_at(1000), None);
+        assert_eq!(song.section_color_at(2000), Some(ColorLabel::Orange));
+        song.add_marker("Fill", 3840);
+        assert_eq!(song.markers[1].color, Some(ColorLabel::Orange));
+
+        // Saved with the song, but playback only drops the label
+        let loaded = Song::from_json(&song.to_json().unwrap()).unwrap();
+        assert_eq!((loaded.notes.clone(), loaded.markers.clone()), (song.notes.clone(), song.markers.clone()));
+        let stripped: Vec<Note> = song.playback_notes().into_iter().map(|n| Note { color: None, ..n }).collect();
+        assert_eq!(stripped, played);
+    }
+
+    #[test]
+    fn test_song_info_formatting() {
+        assert_eq!(format_duration(0.0), "0:00");
+        assert_eq!(format_duration(83.4), "1:23");
+        assert_eq!(format_duration(600.0), "10:00");
+
+        let mut song = Song::with_title("Info");
+        song.add_note(Note::new(60, 0, 480));
+        let info = song.info();
+        assert_eq!(info.title, "Info");
+        assert_eq!(info.note_count, 1);
+        assert_eq!(info.measure_count, 1);
+        assert_eq!(info.total_display, "0:02");
+
+        // Two measures; F# is outside C major
+        song.add_note(Note::new(66, 480, 480));
+        song.add_note(Note::new(48, 1920, 480));
+        let info = song.info();
+        assert_eq!((info.lowest_pitch.as_deref(), info.highest_pitch.as_deref()), (Some("C3"), Some("F#4")));
+        assert_eq!(info.notes_per_measure, 1.5);
+        assert_eq!(info.out_of_key_count, 1);
+        assert_eq!(Song::new().info().lowest_pitch, None);
+    }
+
+    #[test]
+    fn test_new_with_defaults() {
+        let defaults = SongDefaults {
+            tempo: 90,
+            time_signature: "3/4".to_string(),
+            key: "D minor".to_string(),
+            instrument: "cello".to_string(),
+            pickup_beats: 1,
+        };
+        let song = Song::new_with_defaults(&defaults).unwrap();
+        assert_eq!(song.settings.tempo, 90);
+        assert_eq!(song.settings.time_signature.to_string(), "3/4");
+        assert_eq!(song.settings.key, Scale::parse("D minor").unwrap());
+        assert_eq!(song.settings.pitch_range.as_ref().map(|r| r.name.as_str()), Some("Cello"));
+        // The pickup is the last beat of the first measure
+        assert_eq!(song.markers, vec![Marker { name: PICKUP_MARKER.to_string(), tick: 960, color: None }]);
+
+        let plain = Song::new_with_defaults(&SongDefaults::default()).unwrap();
+        assert_eq!((plain.settings.tempo, plain.markers.len()), (120, 0));
+        assert!(Song::new_with_defaults(&SongDefaults { pickup_beats: 4, ..SongDefaults::default() }).is_err());
+        assert!(Song::new_with_defaults(&SongDefaults { key: "H major".to_string(), ..SongDefaults::default() }).is_err());
+    }
+
+    #[test]
+    fn test_delete_measures() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 2400)); // Held into measure 2
+        song.add_note(Note::new(62, 1920, 480));
+        song.add_note(Note::new(64, 3840, 480));
+        song.add_marker("Bridge", 1920);
+        song.add_marker("Coda", 5760);
+        song.add_tempo_change(2400, 100);
+
+        assert_eq!(song.delete_measures(2, 1).unwrap(), 1);
+        let timing: Vec<(u8, u32, u32)> = song.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(timing, vec![(60, 0, 1920), (64, 1920, 480)]);
+        assert_eq!(song.markers, vec![Marker { name: "Coda".to_string(), tick: 3840, color: None }]);
+        // The deleted measure's tempo change still applies from where it was
+        assert_eq!(song.settings.tempo_map, vec![TempoChange { tick: 1920, tempo: 100 }]);
+
+        assert!(song.delete_measures(1, 0).is_err());
+        assert!(song.delete_measures(0, 1).is_err());
+        assert!(song.delete_measures(1, u32::MAX).is_err());
+        assert!(song.mbt_to_tick(10_000_000, 1, 0).is_err());
+    }
+
     #[test]
     fn test_measure_count() {
         let mut song = Song::new();
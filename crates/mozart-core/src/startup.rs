@@ -0,0 +1,153 @@
+//! Crash detection and safe mode at startup
+//!
+//! The host writes a [`StartupSentinel`] when it launches, moves its `stage`
+//! forward as startup proceeds, and deletes it on a clean exit. A sentinel
+//! still there at the next launch means the previous run crashed (or was
+//! killed); [`startup_diagnostics`] then reports safe mode: start without the
+//! audio engine, without opening the file the app was launched with, and with
+//! macros disabled, so a corrupt file or a bad audio driver can't crash it
+//! again. The stage the sentinel reached says which of those is the likely
+//! culprit.
+
+use serde::{Deserialize, Serialize};
+
+/// How far the previous launch got
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupStage {
+    /// Loading the app
+    Launch,
+    /// Creating the audio engine
+    Audio,
+    /// Opening the song the app was launched with
+    Song,
+    /// Started; the crash happened while in use
+    Running,
+}
+
+impl StartupStage {
+    pub fn description(&self) -> &'static str {
+        match self {
+            StartupStage::Launch => "while loading",
+            StartupStage::Audio => "while starting audio",
+            StartupStage::Song => "while opening a song",
+            StartupStage::Running => "while running",
+        }
+    }
+}
+
+/// Written at launch, deleted on a clean exit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupSentinel {
+    pub stage: StartupStage,
+    /// Wall clock time of the launch (ms since the Unix epoch)
+    pub launched_at_ms: f64,
+    /// Launches in a row that crashed before this one
+    pub crashes: u32,
+}
+
+impl StartupSentinel {
+    /// The sentinel for a launch following `previous` (None after a clean exit)
+    pub fn launch(previous: Option<&StartupSentinel>, now_ms: f64) -> Self {
+        StartupSentinel {
+            stage: StartupStage::Launch,
+            launched_at_ms: now_ms,
+            crashes: previous.map_or(0, |p| p.crashes + 1),
+        }
+    }
+
+    /// Parse a stored sentinel; one that can't be read still marks a crash
+    pub fn parse(json: &str) -> StartupSentinel {
+        serde_json::from_str(json).unwrap_or_else(|e| {
+            tracing::warn!("Unreadable startup sentinel, treating it as a crash: {}", e);
+            StartupSentinel { stage: StartupStage::Launch, launched_at_ms: 0.0, crashes: 0 }
+        })
+    }
+}
+
+/// Whether to start in safe mode, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupDiagnostics {
+    pub safe_mode: bool,
+    /// Stage the previous launch crashed in
+    pub crashed_stage: Option<StartupStage>,
+    /// Launches in a row that crashed, counting the previous one
+    pub crashes: u32,
+    pub message: String,
+    /// What safe mode leaves out
+    pub skipped: Vec<String>,
+    /// How to get back to a normal start
+    pub suggestions: Vec<String>,
+}
+
+/// Diagnose a launch from the sentinel the previous one left (None after a clean exit)
+pub fn startup_diagnostics(previous: Option<&StartupSentinel>) -> StartupDiagnostics {
+    let Some(previous) = previous else {
+        return StartupDiagnostics {
+            safe_mode: false,
+            crashed_stage: None,
+            crashes: 0,
+            message: "Started normally".to_string(),
+            skipped: Vec::new(),
+            suggestions: Vec::new(),
+        };
+    };
+
+    let crashes = previous.crashes + 1;
+    let mut message = format!("Mozart quit unexpectedly {}; started in safe mode", previous.stage.description());
+    if crashes > 1 {
+        message.push_str(&format!(" ({} crashes in a row)", crashes));
+    }
+    let mut suggestions = match previous.stage {
+        StartupStage::Audio => vec![
+            "Check the audio output device, or pick a different sample rate and latency in the audio settings".to_string(),
+        ],
+        StartupStage::Song => vec!["The song the app was opened with may be corrupt; open a copy or a backup".to_string()],
+        StartupStage::Launch | StartupStage::Running => Vec::new(),
+    };
+    suggestions.push("Restart normally once the problem is fixed".to_string());
+    tracing::warn!("{}", message);
+
+    StartupDiagnostics {
+        safe_mode: true,
+        crashed_stage: Some(previous.stage),
+        crashes,
+        message,
+        skipped: vec![
+            "Audio engine (no playback or previews)".to_string(),
+            "Opening the file the app was launched with".to_string(),
+            "Macros".to_string(),
+        ],
+        suggestions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_startup_diagnostics() {
+        assert!(!startup_diagnostics(None).safe_mode);
+
+        let mut sentinel = StartupSentinel::launch(None, 1_000.0);
+        assert_eq!(sentinel.crashes, 0);
+        sentinel.stage = StartupStage::Audio;
+        let diagnostics = startup_diagnostics(Some(&sentinel));
+        assert!(diagnostics.safe_mode);
+        assert_eq!(diagnostics.crashed_stage, Some(StartupStage::Audio));
+        assert!(diagnostics.message.contains("while starting audio"), "{}", diagnostics.message);
+        assert_eq!(diagnostics.suggestions.len(), 2);
+
+        // Crashing again in safe mode counts up
+        let next = StartupSentinel::launch(Some(&sentinel), 2_000.0);
+        let diagnostics = startup_diagnostics(Some(&next));
+        assert_eq!(diagnostics.crashes, 2);
+        assert!(diagnostics.message.contains("2 crashes in a row"));
+
+        let round_trip = StartupSentinel::parse(&serde_json::to_string(&next).unwrap());
+        assert_eq!(round_trip, next);
+        assert_eq!(StartupSentinel::parse("{garbled").stage, StartupStage::Launch);
+    }
+}
@@ -0,0 +1,124 @@
+//! Strum/roll timing for chords
+//!
+//! Offsets the notes of a chord by increasing amounts so they sound one
+//! after another, like a guitar strum or a rolled piano chord.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+/// Order in which a chord's notes are played
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StrumDirection {
+    /// Lowest pitch first
+    #[default]
+    Up,
+    /// Highest pitch first
+    Down,
+}
+
+impl StrumDirection {
+    /// Parse from string
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "up" => Ok(StrumDirection::Up),
+            "down" => Ok(StrumDirection::Down),
+            _ => Err(MozartError::ParseError(format!("Unknown strum direction: {}", s))),
+        }
+    }
+
+    /// Get short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrumDirection::Up => "up",
+            StrumDirection::Down => "down",
+        }
+    }
+}
+
+impl fmt::Display for StrumDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Playback-only strum applied to the chord starting at a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Strum {
+    /// Start tick of the chord
+    pub tick: u32,
+    /// Delay between the first and last note of the chord
+    pub spread_ticks: u32,
+    pub direction: StrumDirection,
+}
+
+/// Offset the notes of one chord in place
+///
+/// The first note keeps its start and the last is delayed by `spread_ticks`,
+/// with the rest evenly in between. Every note keeps its end so the chord
+/// still releases together (never shorter than one tick).
+pub fn strum_chord(chord: &mut [&mut Note], spread_ticks: u32, direction: StrumDirection) {
+    if chord.len() < 2 {
+        return;
+    }
+
+    chord.sort_by_key(|n| n.pitch);
+    if direction == StrumDirection::Down {
+        chord.reverse();
+    }
+
+    let steps = (chord.len() - 1) as u64;
+    for (k, note) in chord.iter_mut().enumerate() {
+        // At most `spread_ticks`, though the product may not fit a u32
+        let offset = (spread_ticks as u64 * k as u64 / steps) as u32;
+        let end = note.end_tick();
+        note.start_tick = note.start_tick.saturating_add(offset.min(note.duration_ticks.saturating_sub(1)));
+        note.duration_ticks = end - note.start_tick;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn chord() -> Vec<Note> {
+        vec![Note::new(67, 0, 960), Note::new(60, 0, 960), Note::new(64, 0, 960)]
+    }
+
+    fn starts(notes: &[Note]) -> Vec<(u8, u32)> {
+        notes.iter().map(|n| (n.pitch, n.start_tick)).collect()
+    }
+
+    #[test]
+    fn test_strum_up_and_down() {
+        let mut notes = chord();
+        strum_chord(&mut notes.iter_mut().collect::<Vec<_>>(), 60, StrumDirection::Up);
+        assert_eq!(starts(&notes), vec![(67, 60), (60, 0), (64, 30)]);
+        assert!(notes.iter().all(|n| n.end_tick() == 960));
+
+        let mut notes = chord();
+        strum_chord(&mut notes.iter_mut().collect::<Vec<_>>(), 60, StrumDirection::Down);
+        assert_eq!(starts(&notes), vec![(67, 0), (60, 60), (64, 30)]);
+    }
+
+    #[test]
+    fn test_strum_never_empties_a_note() {
+        let mut notes = [Note::new(60, 0, 10), Note::new(64, 0, 10)];
+        strum_chord(&mut notes.iter_mut().collect::<Vec<_>>(), 100, StrumDirection::Up);
+        assert_eq!((notes[1].start_tick, notes[1].duration_ticks), (9, 1));
+
+        // A spread too wide to multiply by the note count in a u32
+        let mut notes = chord();
+        strum_chord(&mut notes.iter_mut().collect::<Vec<_>>(), u32::MAX, StrumDirection::Up);
+        assert_eq!(starts(&notes), vec![(67, 959), (60, 0), (64, 959)]);
+    }
+
+    #[test]
+    fn test_direction_parse() {
+        assert_eq!(StrumDirection::parse("Down").unwrap(), StrumDirection::Down);
+        assert!(StrumDirection::parse("sideways").is_err());
+    }
+}
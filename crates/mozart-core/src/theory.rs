@@ -0,0 +1,601 @@
+//! Music theory reference data
+//!
+//! Tables for a Help/Theory panel: intervals, scale formulas, chord formulas,
+//! the circle of fifths, and common cadences. Examples are spelled and chord
+//! qualities derived from the same scale definitions the transposition engine
+//! uses, so the reference cannot disagree with the math.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::pitch::PitchClass;
+use crate::scale::{Scale, ScaleType};
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+
+/// Semitones above C of each letter (also the major scale)
+const NATURALS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+const DEGREE_NAMES: [&str; 6] = ["Tonic", "Supertonic", "Mediant", "Subdominant", "Dominant", "Submediant"];
+
+const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+
+/// Reference topics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TheoryTopic {
+    Intervals,
+    Scales,
+    Chords,
+    CircleOfFifths,
+    Cadences,
+}
+
+impl TheoryTopic {
+    /// Every topic, in panel order
+    pub const ALL: [TheoryTopic; 5] = [
+        TheoryTopic::Intervals,
+        TheoryTopic::Scales,
+        TheoryTopic::Chords,
+        TheoryTopic::CircleOfFifths,
+        TheoryTopic::Cadences,
+    ];
+
+    /// Parse from string (e.g., "scales", "circle-of-fifths")
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace(['-', ' '], "_").as_str() {
+            "intervals" => Ok(TheoryTopic::Intervals),
+            "scales" | "modes" => Ok(TheoryTopic::Scales),
+            "chords" => Ok(TheoryTopic::Chords),
+            "circle_of_fifths" | "circle" | "keys" => Ok(TheoryTopic::CircleOfFifths),
+            "cadences" => Ok(TheoryTopic::Cadences),
+            _ => Err(MozartError::ParseError(format!("Unknown theory topic: {}", s))),
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TheoryTopic::Intervals => "Intervals",
+            TheoryTopic::Scales => "Scales",
+            TheoryTopic::Chords => "Chords",
+            TheoryTopic::CircleOfFifths => "Circle of Fifths",
+            TheoryTopic::Cadences => "Cadences",
+        }
+    }
+}
+
+/// How an interval sounds in common-practice harmony
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Consonance {
+    Perfect,
+    Imperfect,
+    Dissonant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntervalInfo {
+    pub semitones: u8,
+    pub name: String,
+    /// e.g., "m3", "P5"
+    pub short_name: String,
+    pub consonance: Consonance,
+    /// Short name of the interval that completes the octave
+    pub inversion: String,
+    /// Spelled above C (e.g., "C–Eb")
+    pub example: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScaleInfo {
+    pub scale_type: ScaleType,
+    pub name: String,
+    /// Semitones from the root
+    pub intervals: Vec<u8>,
+    /// Degrees against the major scale (e.g., "1", "b3")
+    pub formula: Vec<String>,
+    /// Whole and half steps (e.g., "W W H W W W H")
+    pub steps: String,
+    /// Functional name of each degree (Tonic, Supertonic, ...)
+    pub degree_names: Vec<String>,
+    /// The scale spelled from C
+    pub example: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChordInfo {
+    pub name: String,
+    /// Symbol suffix after the root (e.g., "m7"; "" for a major triad)
+    pub symbol: String,
+    /// Semitones from the root
+    pub intervals: Vec<u8>,
+    /// Chord tones against the major scale (e.g., "1", "b3", "5")
+    pub formula: Vec<String>,
+    /// The chord spelled on C
+    pub example: Vec<String>,
+}
+
+/// A key on the circle of fifths
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyInfo {
+    /// Sharps (positive) or flats (negative) in the key signature
+    pub fifths: i8,
+    /// Major tonic (e.g., "G")
+    pub major: String,
+    /// Relative minor tonic (e.g., "E")
+    pub minor: String,
+    /// Accidentals in signature order (e.g., ["F#", "C#"])
+    pub signature: Vec<String>,
+    /// Major tonic of the same key spelled with the other accidental
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enharmonic: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CadenceInfo {
+    pub name: String,
+    pub description: String,
+    /// Scale degrees of the chords (1-7)
+    pub degrees: Vec<u8>,
+    /// Roman numerals in a major key
+    pub major_numerals: Vec<String>,
+    /// Roman numerals in a (harmonic) minor key
+    pub minor_numerals: Vec<String>,
+    /// Chord symbols in C major
+    pub major_example: Vec<String>,
+    /// Chord symbols in A harmonic minor
+    pub minor_example: Vec<String>,
+}
+
+/// Reference content for one topic
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "topic", content = "entries", rename_all = "snake_case")]
+pub enum TheoryReference {
+    Intervals(Vec<IntervalInfo>),
+    Scales(Vec<ScaleInfo>),
+    Chords(Vec<ChordInfo>),
+    CircleOfFifths(Vec<KeyInfo>),
+    Cadences(Vec<CadenceInfo>),
+}
+
+/// Build the reference tables for a topic
+pub fn get_theory_reference(topic: TheoryTopic) -> TheoryReference {
+    tracing::debug!("Building theory reference for {:?}", topic);
+    match topic {
+        TheoryTopic::Intervals => TheoryReference::Intervals(intervals()),
+        TheoryTopic::Scales => TheoryReference::Scales(scales()),
+        TheoryTopic::Chords => TheoryReference::Chords(chords()),
+        TheoryTopic::CircleOfFifths => TheoryReference::CircleOfFifths(circle_of_fifths()),
+        TheoryTopic::Cadences => TheoryReference::Cadences(cadences()),
+    }
+}
+
+// Spell a pitch class on a letter (0 = C), e.g. Gb rather than F# on G
+fn spell(letter: usize, pitch_class: PitchClass) -> String {
+    let letter = letter % 7;
+    let offset = (pitch_class.semitones() as i8 - NATURALS[letter] as i8 + 6).rem_euclid(12) - 6;
+    format!("{}{}", LETTERS[letter], accidentals(offset))
+}
+
+fn accidentals(offset: i8) -> String {
+    if offset >= 0 {
+        "#".repeat(offset as usize)
+    } else {
+        "b".repeat(offset.unsigned_abs() as usize)
+    }
+}
+
+// Degree (1-based, may pass the octave) relative to the major scale, e.g. "b3"
+fn degree_label(degree: u8, semitones: u8) -> String {
+    let index = (degree - 1) as usize;
+    let major = NATURALS[index % 7] as i8 + 12 * (index / 7) as i8;
+    format!("{}{}", accidentals(semitones as i8 - major), degree)
+}
+
+// (semitones, degree, name, short name)
+const INTERVALS: [(u8, u8, &str, &str); 13] = [
+    (0, 1, "Perfect unison", "P1"),
+    (1, 2, "Minor second", "m2"),
+    (2, 2, "Major second", "M2"),
+    (3, 3, "Minor third", "m3"),
+    (4, 3, "Major third", "M3"),
+    (5, 4, "Perfect fourth", "P4"),
+    (6, 4, "Tritone", "TT"),
+    (7, 5, "Perfect fifth", "P5"),
+    (8, 6, "Minor sixth", "m6"),
+    (9, 6, "Major sixth", "M6"),
+    (10, 7, "Minor seventh", "m7"),
+    (11, 7, "Major seventh", "M7"),
+    (12, 8, "Perfect octave", "P8"),
+];
+
+/// Name and short name of an interval up to an octave (e.g., "Minor third", "m3")
+pub fn interval_name(semitones: u8) -> Option<(&'static str, &'static str)> {
+    INTERVALS.get(semitones as usize).map(|&(_, _, name, short_name)| (name, short_name))
+}
+
+/// The note an interval above `lower`, spelled on the interval's letter
+/// (e.g., a minor second above C is Db, not C#)
+pub fn spell_above(lower: PitchClass, semitones: u8) -> String {
+    let letter = LETTERS.iter().position(|&l| lower.natural_name().starts_with(l)).unwrap_or(0);
+    let degree = INTERVALS[(semitones % 12) as usize].1;
+    spell(letter + (degree - 1) as usize, lower.transpose(semitones as i8))
+}
+
+fn intervals() -> Vec<IntervalInfo> {
+    INTERVALS
+        .iter()
+        .map(|&(semitones, degree, name, short_name)| {
+            let consonance = match semitones {
+                0 | 5 | 7 | 12 => Consonance::Perfect,
+                3 | 4 | 8 | 9 => Consonance::Imperfect,
+                _ => Consonance::Dissonant,
+            };
+            let top = PitchClass::C.transpose(semitones as i8);
+            IntervalInfo {
+                semitones,
+                name: name.to_string(),
+                short_name: short_name.to_string(),
+                consonance,
+                inversion: INTERVALS[12 - semitones as usize].3.to_string(),
+                example: format!("C–{}", spell((degree - 1) as usize, top)),
+            }
+        })
+        .collect()
+}
+
+fn scales() -> Vec<ScaleInfo> {
+    ScaleType::all()
+        .iter()
+        .map(|&scale_type| {
+            let intervals = scale_type.intervals();
+            let steps: Vec<&str> = intervals
+                .iter()
+                .zip(intervals.iter().skip(1).chain(std::iter::once(&12)))
+                .map(|(from, to)| match to - from {
+                    1 => "H",
+                    2 => "W",
+                    _ => "W+H",
+                })
+                .collect();
+            let leading = if intervals[6] == 11 { "Leading tone" } else { "Subtonic" };
+            ScaleInfo {
+                scale_type,
+                name: scale_type.name().to_string(),
+                intervals: intervals.to_vec(),
+                formula: intervals.iter().enumerate().map(|(i, &s)| degree_label(i as u8 + 1, s)).collect(),
+                steps: steps.join(" "),
+                degree_names: DEGREE_NAMES.iter().chain(std::iter::once(&leading)).map(|s| s.to_string()).collect(),
+                example: Scale::new(PitchClass::C, scale_type)
+                    .pitch_classes()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, pc)| spell(i, pc))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+// (name, symbol, degrees, semitones)
+type ChordFormula = (&'static str, &'static str, &'static [u8], &'static [u8]);
+
+const CHORDS: &[ChordFormula] = &[
+    ("Major triad", "", &[1, 3, 5], &[0, 4, 7]),
+    ("Minor triad", "m", &[1, 3, 5], &[0, 3, 7]),
+    ("Diminished triad", "dim", &[1, 3, 5], &[0, 3, 6]),
+    ("Augmented triad", "aug", &[1, 3, 5], &[0, 4, 8]),
+    ("Suspended second", "sus2", &[1, 2, 5], &[0, 2, 7]),
+    ("Suspended fourth", "sus4", &[1, 4, 5], &[0, 5, 7]),
+    ("Major sixth", "6", &[1, 3, 5, 6], &[0, 4, 7, 9]),
+    ("Minor sixth", "m6", &[1, 3, 5, 6], &[0, 3, 7, 9]),
+    ("Dominant seventh", "7", &[1, 3, 5, 7], &[0, 4, 7, 10]),
+    ("Major seventh", "maj7", &[1, 3, 5, 7], &[0, 4, 7, 11]),
+    ("Minor seventh", "m7", &[1, 3, 5, 7], &[0, 3, 7, 10]),
+    ("Minor-major seventh", "m(maj7)", &[1, 3, 5, 7], &[0, 3, 7, 11]),
+    ("Half-diminished seventh", "m7b5", &[1, 3, 5, 7], &[0, 3, 6, 10]),
+    ("Diminished seventh", "dim7", &[1, 3, 5, 7], &[0, 3, 6, 9]),
+    ("Added ninth", "add9", &[1, 3, 5, 9], &[0, 4, 7, 14]),
+    ("Dominant ninth", "9", &[1, 3, 5, 7, 9], &[0, 4, 7, 10, 14]),
+];
+
+/// Semitones above the root for a chord symbol's suffix ("m7"; "" for a
+/// major triad), accepting common spellings such as "min", "-", "M7", "°"
+/// and "ø"
+pub fn chord_intervals(suffix: &str) -> Option<&'static [u8]> {
+    let suffix = match suffix {
+        "maj" | "M" => "",
+        "min" | "mi" | "-" => "m",
+        "°" | "o" => "dim",
+        "+" => "aug",
+        "sus" => "sus4",
+        "M7" | "ma7" | "Δ" | "Δ7" => "maj7",
+        "min7" | "mi7" | "-7" => "m7",
+        "mM7" | "mmaj7" | "-maj7" => "m(maj7)",
+        "ø" | "ø7" | "-7b5" => "m7b5",
+        "°7" | "o7" => "dim7",
+        other => other,
+    };
+    CHORDS.iter().find(|chord| chord.1 == suffix).map(|chord| chord.3)
+}
+
+/// Name a set of pitch classes (0 = C) as a chord symbol, e.g. "Am7" or
+/// "C/E"; the bass is tried as the root first, so an inversion reads as a
+/// slash chord. None if the notes don't spell a chord in the reference
+pub fn chord_symbol(pitch_classes: &[u8], bass: u8) -> Option<String> {
+    let bass = bass % 12;
+    let mut set: Vec<u8> = pitch_classes.iter().map(|pc| pc % 12).collect();
+    set.sort_unstable();
+    set.dedup();
+    let roots = std::iter::once(bass).chain(set.iter().copied().filter(|&pc| pc != bass));
+    for root in roots {
+        for &(_, suffix, _, semitones) in CHORDS {
+            let mut tones: Vec<u8> = semitones.iter().map(|s| (root + s) % 12).collect();
+            tones.sort_unstable();
+            tones.dedup();
+            if tones == set {
+                let symbol = format!("{}{}", PitchClass::new(root), suffix);
+                return Some(if root == bass { symbol } else { format!("{}/{}", symbol, PitchClass::new(bass)) });
+            }
+        }
+    }
+    None
+}
+
+fn chords() -> Vec<ChordInfo> {
+    CHORDS
+        .iter()
+        .map(|&(name, symbol, degrees, semitones)| {
+            let tones = degrees.iter().zip(semitones);
+            ChordInfo {
+                name: name.to_string(),
+                symbol: symbol.to_string(),
+                intervals: semitones.to_vec(),
+                formula: tones.clone().map(|(&d, &s)| degree_label(d, s)).collect(),
+                example: tones
+                    .map(|(&d, &s)| spell((d - 1) as usize, PitchClass::C.transpose(s as i8)))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn circle_of_fifths() -> Vec<KeyInfo> {
+    const SHARPS: [char; 7] = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+    const FLATS: [char; 7] = ['B', 'E', 'A', 'D', 'G', 'C', 'F'];
+
+    let major = |fifths: i8| {
+        let letter = (4 * fifths as i16).rem_euclid(7) as usize;
+        (letter, PitchClass::C.transpose((7 * fifths as i16).rem_euclid(12) as i8))
+    };
+
+    // Clockwise from C: sharp keys up to F#, then flat keys from Db
+    (0..=6)
+        .chain(-5..=-1)
+        .map(|fifths: i8| {
+            let (letter, tonic) = major(fifths);
+            let count = fifths.unsigned_abs() as usize;
+            let signature = if fifths >= 0 {
+                SHARPS[..count].iter().map(|l| format!("{}#", l)).collect()
+            } else {
+                FLATS[..count].iter().map(|l| format!("{}b", l)).collect()
+            };
+            let other = fifths - 12 * fifths.signum();
+            let enharmonic = (fifths != 0 && other.abs() <= 7).then(|| {
+                let (letter, tonic) = major(other);
+                spell(letter, tonic)
+            });
+            KeyInfo {
+                fifths,
+                major: spell(letter, tonic),
+                minor: spell(letter + 5, tonic.transpose(9)),
+                signature,
+                enharmonic,
+            }
+        })
+        .collect()
+}
+
+// Roman numeral and chord symbol of the triad on a scale degree (1-7),
+// with the scale's root written on `root_letter`
+fn triad(scale: &Scale, root_letter: usize, degree: u8) -> (String, String) {
+    let (numeral, symbol, _) = stacked_chord(scale, root_letter, degree, 3);
+    (numeral, symbol)
+}
+
+// Chord of `size` stacked thirds on a scale degree: numeral, symbol, and tones
+fn stacked_chord(scale: &Scale, root_letter: usize, degree: u8, size: u8) -> (String, String, Vec<PitchClass>) {
+    let tones: Vec<PitchClass> = (0..size)
+        .map(|i| scale.degree((degree - 1 + 2 * i) % 7 + 1).unwrap_or(scale.root))
+        .collect();
+    let shape: Vec<u8> = tones.iter().map(|&t| tones[0].interval_to(t)).collect();
+    let symbol = CHORDS
+        .iter()
+        .find(|chord| chord.3 == shape.as_slice())
+        .map_or("?", |chord| chord.1);
+
+    let upper = NUMERALS[(degree - 1) as usize];
+    let lower = upper.to_lowercase();
+    let numeral = match symbol {
+        "m" => lower,
+        "dim" => format!("{}°", lower),
+        "aug" => format!("{}+", upper),
+        "7" => format!("{}7", upper),
+        "maj7" => format!("{}maj7", upper),
+        "m7" => format!("{}7", lower),
+        "m(maj7)" => format!("{}(maj7)", lower),
+        "m7b5" => format!("{}ø7", lower),
+        "dim7" => format!("{}°7", lower),
+        _ => upper.to_string(),
+    };
+    let name = spell(root_letter + (degree - 1) as usize, tones[0]);
+    (numeral, format!("{}{}", name, symbol), tones)
+}
+
+/// Roman numeral, chord symbol, and tones (root first) of the diatonic triad,
+/// or seventh chord, on a degree (1-7) of a key
+///
+/// Symbols are spelled from the key's root, e.g. "Bb" rather than "A#" in F.
+pub fn diatonic_chord(key: &Scale, degree: u8, seventh: bool) -> (String, String, Vec<PitchClass>) {
+    let letter = LETTERS.iter().position(|&l| key.root.natural_name().starts_with(l)).unwrap_or(0);
+    stacked_chord(key, letter, degree.clamp(1, 7), if seventh { 4 } else { 3 })
+}
+
+/// Tone names (root first) of the diatonic triad, or seventh chord, on a
+/// degree (1-7) of a key, spelled from the key's root (e.g., "Bb" in F)
+pub fn diatonic_chord_tone_names(key: &Scale, degree: u8, seventh: bool) -> Vec<String> {
+    let letter = LETTERS.iter().position(|&l| key.root.natural_name().starts_with(l)).unwrap_or(0);
+    let degree = degree.clamp(1, 7);
+    let (_, _, tones) = stacked_chord(key, letter, degree, if seventh { 4 } else { 3 });
+    tones
+        .iter()
+        .enumerate()
+        .map(|(i, &tone)| spell(letter + (degree - 1) as usize + 2 * i, tone))
+        .collect()
+}
+
+// (name, description, degrees)
+const CADENCES: [(&str, &str, &[u8]); 5] = [
+    ("Authentic", "Dominant to tonic; the strongest sense of arrival", &[5, 1]),
+    ("Plagal", "Subdominant to tonic, the \"Amen\" cadence", &[4, 1]),
+    ("Half", "Comes to rest on the dominant, like a question", &[4, 5]),
+    ("Deceptive", "Dominant to submediant instead of the expected tonic", &[5, 6]),
+    ("ii–V–I", "Supertonic through dominant to tonic, the staple of jazz", &[2, 5, 1]),
+];
+
+fn cadences() -> Vec<CadenceInfo> {
+    let major = Scale::c_major();
+    let minor = Scale::new(PitchClass::A, ScaleType::HarmonicMinor);
+    CADENCES
+        .iter()
+        .map(|&(name, description, degrees)| {
+            let (major_numerals, major_example) = degrees.iter().map(|&d| triad(&major, 0, d)).unzip();
+            let (minor_numerals, minor_example) = degrees.iter().map(|&d| triad(&minor, 5, d)).unzip();
+            CadenceInfo {
+                name: name.to_string(),
+                description: description.to_string(),
+                degrees: degrees.to_vec(),
+                major_numerals,
+                minor_numerals,
+                major_example,
+                minor_example,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_intervals_and_chords() {
+        let TheoryReference::Intervals(intervals) = get_theory_reference(TheoryTopic::Intervals) else {
+            panic!("wrong topic");
+        };
+        assert_eq!(intervals.len(), 13);
+        assert_eq!(intervals[3].example, "C–Eb");
+        assert_eq!(intervals[6].example, "C–F#");
+        assert_eq!(intervals[4].inversion, "m6");
+        assert_eq!(intervals[7].consonance, Consonance::Perfect);
+
+        let TheoryReference::Chords(chords) = get_theory_reference(TheoryTopic::Chords) else {
+            panic!("wrong topic");
+        };
+        let half_diminished = chords.iter().find(|c| c.symbol == "m7b5").unwrap();
+        assert_eq!(half_diminished.formula, vec!["1", "b3", "b5", "b7"]);
+        assert_eq!(half_diminished.example, vec!["C", "Eb", "Gb", "Bb"]);
+        let ninth = chords.iter().find(|c| c.symbol == "9").unwrap();
+        assert_eq!(ninth.formula, vec!["1", "3", "5", "b7", "9"]);
+
+        assert_eq!(chord_intervals("m7"), Some(&[0, 3, 7, 10][..]));
+        assert_eq!(chord_intervals("-7"), chord_intervals("m7"));
+        assert_eq!(chord_intervals("ø"), Some(&[0, 3, 6, 10][..]));
+        assert_eq!(chord_intervals(""), Some(&[0, 4, 7][..]));
+        assert_eq!(chord_intervals("13#11"), None);
+
+        assert_eq!(chord_symbol(&[0, 4, 7, 12], 0).as_deref(), Some("C"));
+        assert_eq!(chord_symbol(&[9, 0, 4, 7], 9).as_deref(), Some("Am7"));
+        // The same notes over C are a sixth chord; inversions get a slash bass
+        assert_eq!(chord_symbol(&[9, 0, 4, 7], 0).as_deref(), Some("C6"));
+        assert_eq!(chord_symbol(&[4, 7, 0], 4).as_deref(), Some("C/E"));
+        assert_eq!(chord_symbol(&[7, 11, 2, 5], 7).as_deref(), Some("G7"));
+        assert_eq!(chord_symbol(&[0, 7], 0), None);
+        assert_eq!(chord_symbol(&[0, 1, 2], 0), None);
+    }
+
+    #[test]
+    fn test_scales_are_spelled_by_letter() {
+        let TheoryReference::Scales(scales) = get_theory_reference(TheoryTopic::Scales) else {
+            panic!("wrong topic");
+        };
+        assert_eq!(scales.len(), ScaleType::all().len());
+
+        let harmonic = scales.iter().find(|s| s.scale_type == ScaleType::HarmonicMinor).unwrap();
+        assert_eq!(harmonic.steps, "W H W W H W+H H");
+        assert_eq!(harmonic.formula, vec!["1", "2", "b3", "4", "5", "b6", "7"]);
+        assert_eq!(harmonic.degree_names[6], "Leading tone");
+
+        // Each letter once: Db and Gb, not C# and F#
+        let locrian = scales.iter().find(|s| s.scale_type == ScaleType::Locrian).unwrap();
+        assert_eq!(locrian.example, vec!["C", "Db", "Eb", "F", "Gb", "Ab", "Bb"]);
+        assert_eq!(locrian.degree_names[6], "Subtonic");
+    }
+
+    #[test]
+    fn test_circle_of_fifths() {
+        let TheoryReference::CircleOfFifths(keys) = get_theory_reference(TheoryTopic::CircleOfFifths) else {
+            panic!("wrong topic");
+        };
+        let majors: Vec<&str> = keys.iter().map(|k| k.major.as_str()).collect();
+        assert_eq!(majors, vec!["C", "G", "D", "A", "E", "B", "F#", "Db", "Ab", "Eb", "Bb", "F"]);
+
+        let f_sharp = &keys[6];
+        assert_eq!(f_sharp.minor, "D#");
+        assert_eq!(f_sharp.signature, vec!["F#", "C#", "G#", "D#", "A#", "E#"]);
+        assert_eq!(f_sharp.enharmonic.as_deref(), Some("Gb"));
+        assert_eq!(keys[7].enharmonic.as_deref(), Some("C#"));
+        assert_eq!(keys[11].signature, vec!["Bb"]);
+        assert_eq!(keys[1].enharmonic, None);
+    }
+
+    #[test]
+    fn test_cadences() {
+        let TheoryReference::Cadences(cadences) = get_theory_reference(TheoryTopic::Cadences) else {
+            panic!("wrong topic");
+        };
+        let deceptive = cadences.iter().find(|c| c.name == "Deceptive").unwrap();
+        assert_eq!(deceptive.major_numerals, vec!["V", "vi"]);
+        assert_eq!(deceptive.major_example, vec!["G", "Am"]);
+        // The raised seventh makes the minor-key dominant major
+        assert_eq!(deceptive.minor_numerals, vec!["V", "VI"]);
+        assert_eq!(deceptive.minor_example, vec!["E", "F"]);
+
+        let two_five_one = cadences.iter().find(|c| c.degrees == [2, 5, 1]).unwrap();
+        assert_eq!(two_five_one.minor_numerals, vec!["ii°", "V", "i"]);
+    }
+
+    #[test]
+    fn test_diatonic_chords() {
+        let g_major = Scale::new(PitchClass::G, ScaleType::Major);
+        let (numeral, symbol, tones) = diatonic_chord(&g_major, 5, true);
+        assert_eq!((numeral.as_str(), symbol.as_str()), ("V7", "D7"));
+        assert_eq!(tones, vec![PitchClass::D, PitchClass::F_SHARP, PitchClass::A, PitchClass::C]);
+
+        let f_major = Scale::new(PitchClass::F, ScaleType::Major);
+        assert_eq!(diatonic_chord(&f_major, 4, false).1, "Bb");
+        assert_eq!(diatonic_chord(&f_major, 7, true).0, "viiø7");
+    }
+
+    #[test]
+    fn test_topic_parse() {
+        assert_eq!(TheoryTopic::parse("Circle of Fifths").unwrap(), TheoryTopic::CircleOfFifths);
+        assert_eq!(TheoryTopic::parse("circle-of-fifths").unwrap(), TheoryTopic::CircleOfFifths);
+        assert_eq!(TheoryTopic::parse("scales").unwrap(), TheoryTopic::Scales);
+        assert!(TheoryTopic::parse("counterpoint").is_err());
+
+        let json = serde_json::to_value(get_theory_reference(TheoryTopic::CircleOfFifths)).unwrap();
+        assert_eq!(json["topic"], "circle_of_fifths");
+        assert_eq!(json["entries"][0]["major"], "C");
+    }
+}
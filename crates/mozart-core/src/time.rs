@@ -1,6 +1,6 @@
 //! Time signature and accent patterns
 //!
-//! Supports time signatures from 2-15 with customizable accent patterns
+//! Supports time signatures from 1-32 beats with customizable accent patterns
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -69,11 +69,34 @@ impl AccentPattern {
         }
     }
 
+    /// Create a pattern from beat groups (e.g., [3, 3, 2, 3] for 11/8)
+    ///
+    /// The first beat is strong and each later group starts on a medium accent.
+    pub fn from_grouping(groups: &[u8]) -> Self {
+        let mut accents = Vec::new();
+        for (i, &size) in groups.iter().enumerate() {
+            for beat in 0..size {
+                accents.push(match (i, beat) {
+                    (0, 0) => AccentLevel::Strong,
+                    (_, 0) => AccentLevel::Medium,
+                    _ => AccentLevel::Weak,
+                });
+            }
+        }
+        AccentPattern { accents }
+    }
+
+    /// Get the pattern as numeric values (1=weak, 2=medium, 3=strong)
+    pub fn to_values(&self) -> Vec<u8> {
+        self.accents.iter().map(|&a| a as u8).collect()
+    }
+
     /// Create a default pattern for a given number of beats
     pub fn default_for_beats(beats: u8) -> Self {
         tracing::debug!("Creating default accent pattern for {} beats", beats);
 
         let pattern = match beats {
+            1 => vec![AccentLevel::Strong],
             2 => vec![AccentLevel::Strong, AccentLevel::Weak],
             3 => vec![AccentLevel::Strong, AccentLevel::Weak, AccentLevel::Weak],
             4 => vec![
@@ -214,19 +237,35 @@ impl AccentPattern {
                 AccentLevel::Weak,
                 AccentLevel::Weak,
             ], // 3+3+3+3+3
-            _ => {
-                // Generic pattern: strong on 1, weak everywhere else
-                let mut p = vec![AccentLevel::Weak; beats as usize];
-                if !p.is_empty() {
-                    p[0] = AccentLevel::Strong;
-                }
-                p
-            }
+            _ => return Self::from_grouping(&Self::default_grouping(beats)),
         };
 
         AccentPattern { accents: pattern }
     }
 
+    /// Beat groups for long measures without a hand-written default
+    ///
+    /// Multiples of 4 use groups of 4; anything else uses groups of 3,
+    /// padded with 2s (e.g., 17 = 3+3+3+3+3+2, 19 = 3+3+3+3+3+2+2).
+    fn default_grouping(beats: u8) -> Vec<u8> {
+        if beats == 0 {
+            return Vec::new();
+        }
+        if beats.is_multiple_of(4) {
+            return vec![4; beats as usize / 4];
+        }
+        let mut groups = vec![3; beats as usize / 3];
+        match beats % 3 {
+            1 => {
+                groups.pop();
+                groups.extend([2, 2]);
+            }
+            2 => groups.push(2),
+            _ => {}
+        }
+        groups
+    }
+
     /// Get the number of beats
     pub fn len(&self) -> usize {
         self.accents.len()
@@ -260,6 +299,21 @@ impl AccentPattern {
         }
     }
 
+    /// Get the beat groups implied by the pattern (inverse of [`AccentPattern::from_grouping`])
+    ///
+    /// A new group starts on every beat accented above weak, so the default
+    /// 7/8 pattern gives [3, 2, 2].
+    pub fn groupings(&self) -> Vec<u8> {
+        let mut groups: Vec<u8> = Vec::new();
+        for (beat, &level) in self.accents.iter().enumerate() {
+            match groups.last_mut() {
+                Some(size) if beat > 0 && level == AccentLevel::Weak => *size += 1,
+                _ => groups.push(1),
+            }
+        }
+        groups
+    }
+
     /// Format as visual pattern
     pub fn to_visual(&self) -> String {
         self.accents.iter().map(|a| a.to_string()).collect()
@@ -272,15 +326,63 @@ impl fmt::Display for AccentPattern {
     }
 }
 
+/// A position in musical units: measure, beat, and ticks into the beat
+///
+/// Measures and beats are 1-indexed, matching how positions are displayed
+/// (e.g., "5.3.120" is measure 5, beat 3, 120 ticks past the beat).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MusicalPosition {
+    pub measure: u32,
+    pub beat: u32,
+    pub tick: u32,
+}
+
+impl MusicalPosition {
+    pub fn new(measure: u32, beat: u32, tick: u32) -> Self {
+        MusicalPosition {
+            measure,
+            beat,
+            tick,
+        }
+    }
+
+    /// Parse from string ("17", "5.3", or "5.3.120")
+    pub fn parse(s: &str) -> Result<Self> {
+        let parts: Vec<&str> = s.trim().split('.').collect();
+        if parts.is_empty() || parts.len() > 3 {
+            return Err(MozartError::ParseError(format!("Invalid position: {}", s)));
+        }
+
+        let mut values = [1u32, 1, 0];
+        for (i, part) in parts.iter().enumerate() {
+            values[i] = part
+                .trim()
+                .parse()
+                .map_err(|_| MozartError::ParseError(format!("Invalid position: {}", s)))?;
+        }
+
+        Ok(MusicalPosition::new(values[0], values[1], values[2]))
+    }
+}
+
+impl fmt::Display for MusicalPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.measure, self.beat, self.tick)
+    }
+}
+
 /// Time signature with customizable accents
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TimeSignature {
-    /// Beats per measure (2-15)
+    /// Beats per measure (1-32)
     pub numerator: u8,
-    /// Beat unit (4 = quarter note, 8 = eighth note)
+    /// Beat unit (4 = quarter note, 8 = eighth note, up to 32)
     pub denominator: u8,
     /// Accent pattern for the measure
     pub accents: AccentPattern,
+    /// Beats per felt pulse (e.g., [3, 3] for 6/8 in two); empty means every beat
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pulse_grouping: Vec<u8>,
 }
 
 impl TimeSignature {
@@ -300,6 +402,7 @@ impl TimeSignature {
             numerator,
             denominator,
             accents,
+            pulse_grouping: Vec::new(),
         })
     }
 
@@ -323,18 +426,19 @@ impl TimeSignature {
             numerator,
             denominator,
             accents,
+            pulse_grouping: Vec::new(),
         })
     }
 
     fn validate(numerator: u8, denominator: u8) -> Result<()> {
-        if numerator < 2 || numerator > 15 {
+        if !(1..=32).contains(&numerator) {
             return Err(MozartError::InvalidTimeSignature {
                 numerator,
                 denominator,
             });
         }
 
-        if denominator != 4 && denominator != 8 && denominator != 2 && denominator != 16 {
+        if !matches!(denominator, 2 | 4 | 8 | 16 | 32) {
             return Err(MozartError::InvalidTimeSignature {
                 numerator,
                 denominator,
@@ -372,6 +476,7 @@ impl TimeSignature {
             4 => TICKS_PER_QUARTER,      // Quarter note
             8 => TICKS_PER_QUARTER / 2,  // Eighth note
             16 => TICKS_PER_QUARTER / 4, // Sixteenth note
+            32 => TICKS_PER_QUARTER / 8, // Thirty-second note
             _ => TICKS_PER_QUARTER,
         }
     }
@@ -386,6 +491,48 @@ impl TimeSignature {
         (tick % self.ticks_per_measure()) / self.ticks_per_beat()
     }
 
+    /// Convert a tick to a musical position (measure.beat.tick)
+    pub fn position_at_tick(&self, tick: u32) -> MusicalPosition {
+        let ticks_per_measure = self.ticks_per_measure();
+        let ticks_per_beat = self.ticks_per_beat();
+        let in_measure = tick % ticks_per_measure;
+
+        MusicalPosition {
+            measure: tick / ticks_per_measure + 1,
+            beat: in_measure / ticks_per_beat + 1,
+            tick: in_measure % ticks_per_beat,
+        }
+    }
+
+    /// Convert a musical position to a tick
+    pub fn tick_at_position(&self, position: MusicalPosition) -> Result<u32> {
+        let ticks_per_beat = self.ticks_per_beat();
+
+        if position.measure < 1 {
+            return Err(MozartError::InvalidPosition(format!(
+                "Measure {} must be 1 or greater",
+                position.measure
+            )));
+        }
+        if !(1..=self.numerator as u32).contains(&position.beat) {
+            return Err(MozartError::InvalidPosition(format!(
+                "Beat {} out of range for {} time",
+                position.beat, self
+            )));
+        }
+        if position.tick >= ticks_per_beat {
+            return Err(MozartError::InvalidPosition(format!(
+                "Tick {} exceeds beat length of {} ticks",
+                position.tick, ticks_per_beat
+            )));
+        }
+
+        (position.measure - 1)
+            .checked_mul(self.ticks_per_measure())
+            .and_then(|tick| tick.checked_add((position.beat - 1) * ticks_per_beat + position.tick))
+            .ok_or_else(|| MozartError::InvalidPosition(format!("Measure {} is too far into the song", position.measure)))
+    }
+
     /// Get the accent level at a given tick
     pub fn accent_at_tick(&self, tick: u32) -> AccentLevel {
         let beat = self.beat_at_tick(tick) as usize;
@@ -394,12 +541,102 @@ impl TimeSignature {
 
     /// Check if tick is on a beat boundary
     pub fn is_on_beat(&self, tick: u32) -> bool {
-        tick % self.ticks_per_beat() == 0
+        tick.is_multiple_of(self.ticks_per_beat())
     }
 
     /// Check if tick is on the downbeat
     pub fn is_downbeat(&self, tick: u32) -> bool {
-        tick % self.ticks_per_measure() == 0
+        tick.is_multiple_of(self.ticks_per_measure())
+    }
+
+    /// Get the beat groups of a measure, derived from the accent pattern
+    pub fn groupings(&self) -> Vec<u8> {
+        self.accents.groupings()
+    }
+
+    /// Whether the meter is compound (beats naturally grouped in threes, like 6/8 or 12/8)
+    pub fn is_compound(&self) -> bool {
+        self.denominator >= 8 && self.numerator > 3 && self.numerator.is_multiple_of(3)
+    }
+
+    /// Get the beats per pulse; every beat is its own pulse unless a grouping is set
+    pub fn pulse_grouping(&self) -> Vec<u8> {
+        if self.pulse_grouping.is_empty() {
+            vec![1; self.numerator as usize]
+        } else {
+            self.pulse_grouping.clone()
+        }
+    }
+
+    /// Set how beats are felt as pulses (e.g., [3, 3] for 6/8 in two)
+    ///
+    /// Groups must cover the measure exactly. An empty grouping clears it so
+    /// every beat is a pulse again.
+    pub fn set_pulse_grouping(&mut self, groups: &[u8]) -> Result<()> {
+        let total: u32 = groups.iter().map(|&g| g as u32).sum();
+        if groups.contains(&0) || (!groups.is_empty() && total != self.numerator as u32) {
+            return Err(MozartError::InvalidGrouping(format!(
+                "{:?} does not add up to {} beats",
+                groups, self.numerator
+            )));
+        }
+        tracing::debug!("Setting pulse grouping of {} to {:?}", self, groups);
+        self.pulse_grouping = groups.to_vec();
+        Ok(())
+    }
+
+    /// Get the beat index (0-indexed) where each pulse of a measure starts
+    pub fn pulse_starts(&self) -> Vec<u32> {
+        let mut beat = 0;
+        self.pulse_grouping()
+            .iter()
+            .map(|&size| {
+                let start = beat;
+                beat += size as u32;
+                start
+            })
+            .collect()
+    }
+
+    /// Check if tick is on the start of a pulse
+    pub fn is_on_pulse(&self, tick: u32) -> bool {
+        self.is_on_beat(tick) && self.pulse_starts().contains(&self.beat_at_tick(tick))
+    }
+
+    /// Snap a tick to the nearest pulse start (the quantization grid when felt in pulses)
+    pub fn snap_to_pulse(&self, tick: u32) -> u32 {
+        let measure_ticks = self.ticks_per_measure();
+        let measure_start = tick - tick % measure_ticks;
+        let offset = tick - measure_start;
+
+        // The next downbeat is a candidate too, so late ticks snap forward
+        self.pulse_starts()
+            .iter()
+            .map(|&beat| beat * self.ticks_per_beat())
+            .chain(std::iter::once(measure_ticks))
+            .min_by_key(|&pulse| pulse.abs_diff(offset))
+            .map_or(tick, |pulse| measure_start + pulse)
+    }
+
+    /// Snap a tick to a grid whose beats pull harder the stronger their accent
+    ///
+    /// Every grid point within a beat is a candidate, and its distance is
+    /// divided by `1 + gravity × weight`: 3 for a strong beat, 2 for medium, 1
+    /// for weak, and 0 between beats. With a gravity of 0 this is the plain
+    /// nearest grid point (ties going later); higher gravity lets accented
+    /// beats win from farther away, so an odd meter's groupings shape the result.
+    pub fn snap_to_accents(&self, tick: u32, grid_ticks: u32, gravity: f32) -> u32 {
+        let grid_ticks = grid_ticks.max(1);
+        let reach = self.ticks_per_beat().max(grid_ticks);
+        let first = tick.saturating_sub(reach).div_ceil(grid_ticks) * grid_ticks;
+        let cost = |point: u32| {
+            let weight = if self.is_on_beat(point) { self.accent_at_tick(point) as u8 as f32 } else { 0.0 };
+            point.abs_diff(tick) as f32 / (1.0 + gravity * weight)
+        };
+        (first..=tick.saturating_add(reach))
+            .step_by(grid_ticks as usize)
+            .min_by(|&a, &b| cost(a).total_cmp(&cost(b)).then(b.cmp(&a)))
+            .unwrap_or(tick)
     }
 
     /// Set the accent pattern
@@ -416,6 +653,24 @@ impl TimeSignature {
         }
     }
 
+    /// Get the metronome clicks for one measure at a tempo (quarter notes per minute)
+    ///
+    /// Clicks fall on each pulse, which is every beat unless a pulse grouping is set.
+    pub fn measure_clicks(&self, tempo: u16) -> Vec<Click> {
+        use crate::TICKS_PER_QUARTER;
+        let seconds_per_beat =
+            self.ticks_per_beat() as f64 / TICKS_PER_QUARTER as f64 * 60.0 / tempo.max(1) as f64;
+
+        self.pulse_starts()
+            .into_iter()
+            .map(|beat| Click {
+                beat,
+                seconds: beat as f64 * seconds_per_beat,
+                accent: self.accents.get(beat as usize) as u8,
+            })
+            .collect()
+    }
+
     /// Parse from string (e.g., "4/4", "7/8")
     pub fn parse(s: &str) -> Result<Self> {
         let parts: Vec<&str> = s.split('/').collect();
@@ -452,47 +707,177 @@ impl Default for TimeSignature {
     }
 }
 
+/// A metronome click within a measure
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Click {
+    /// Beat index within the measure (0-indexed)
+    pub beat: u32,
+    /// Offset from the start of the measure in seconds
+    pub seconds: f64,
+    /// Accent level (1=weak, 2=medium, 3=strong)
+    pub accent: u8,
+}
+
+/// A secondary metronome layer: `beats` evenly spaced clicks against `against` main beats
+///
+/// 3:2 clicks three times across every two beats of the main metronome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Polyrhythm {
+    pub beats: u8,
+    pub against: u8,
+}
+
+impl Polyrhythm {
+    /// Largest count allowed on either side
+    pub const MAX: u8 = 16;
+
+    pub fn new(beats: u8, against: u8) -> Result<Self> {
+        if !(1..=Self::MAX).contains(&beats) || !(1..=Self::MAX).contains(&against) {
+            return Err(MozartError::InvalidGrouping(format!(
+                "Polyrhythm {} over {} needs 1-{} on each side",
+                beats,
+                against,
+                Self::MAX
+            )));
+        }
+        Ok(Polyrhythm { beats, against })
+    }
+
+    /// Parse from string (e.g., "3:2", "3/2", "3 over 2", "4 against 3")
+    pub fn parse(s: &str) -> Result<Self> {
+        let lower = s.trim().to_lowercase();
+        let parts: Vec<&str> = lower
+            .split([':', '/'])
+            .flat_map(|p| p.split(" over "))
+            .flat_map(|p| p.split(" against "))
+            .collect();
+        if parts.len() != 2 {
+            return Err(MozartError::ParseError(format!("Invalid polyrhythm: {}", s)));
+        }
+
+        let count = |part: &str| {
+            part.trim()
+                .parse::<u8>()
+                .map_err(|_| MozartError::ParseError(format!("Invalid polyrhythm: {}", s)))
+        };
+        Polyrhythm::new(count(parts[0])?, count(parts[1])?)
+    }
+
+    /// Length in ticks of one cycle (`against` main beats)
+    pub fn cycle_ticks(&self, time_signature: &TimeSignature) -> u32 {
+        self.against as u32 * time_signature.ticks_per_beat()
+    }
+
+    /// Tick offsets of the layer clicks within one cycle (rounded down)
+    pub fn click_ticks(&self, time_signature: &TimeSignature) -> Vec<u32> {
+        let cycle = self.cycle_ticks(time_signature);
+        (0..self.beats as u32).map(|k| cycle * k / self.beats as u32).collect()
+    }
+}
+
+impl fmt::Display for Polyrhythm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.beats, self.against)
+    }
+}
+
+/// Practice speed: scales playback without changing the song's tempo
+///
+/// Only the player and practice audio exports apply it; the saved tempo and
+/// MIDI exports are unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackRate(f64);
+
+impl Default for PlaybackRate {
+    fn default() -> Self {
+        PlaybackRate(1.0)
+    }
+}
+
+impl PlaybackRate {
+    pub const MIN: f64 = 0.25;
+    pub const MAX: f64 = 2.0;
+
+    pub fn new(rate: f64) -> Result<Self> {
+        if !(Self::MIN..=Self::MAX).contains(&rate) {
+            return Err(MozartError::ParseError(format!(
+                "Playback rate {} is outside {}x-{}x",
+                rate,
+                Self::MIN,
+                Self::MAX
+            )));
+        }
+        Ok(PlaybackRate(rate))
+    }
+
+    /// Parse from string (e.g., "0.5", "0.75x", "50%")
+    pub fn parse(s: &str) -> Result<Self> {
+        let trimmed = s.trim().to_lowercase();
+        let invalid = || MozartError::ParseError(format!("Invalid playback rate: {}", s));
+        let rate = match trimmed.strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().map_err(|_| invalid())? / 100.0,
+            None => trimmed.trim_end_matches(['x', '×']).trim().parse::<f64>().map_err(|_| invalid())?,
+        };
+        Self::new(rate)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// The tempo heard when a song at `tempo` BPM plays at this rate
+    pub fn effective_tempo(&self, tempo: u16) -> f64 {
+        tempo as f64 * self.0
+    }
+}
+
+impl fmt::Display for PlaybackRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}×", self.0)
+    }
+}
+
 /// Predefined grouping patterns for odd meters
 pub mod groupings {
     use super::*;
 
     /// 5/8 groupings
     pub fn five_three_two() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 1, 2, 1])
+        AccentPattern::from_grouping(&[3, 2])
     }
 
     pub fn five_two_three() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 2, 1, 1])
+        AccentPattern::from_grouping(&[2, 3])
     }
 
     /// 7/8 groupings
     pub fn seven_three_two_two() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 1, 2, 1, 2, 1])
+        AccentPattern::from_grouping(&[3, 2, 2])
     }
 
     pub fn seven_two_two_three() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 2, 1, 2, 1, 1])
+        AccentPattern::from_grouping(&[2, 2, 3])
     }
 
     pub fn seven_two_three_two() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 2, 1, 1, 2, 1])
+        AccentPattern::from_grouping(&[2, 3, 2])
     }
 
     /// 11/8 groupings
     pub fn eleven_three_three_three_two() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 1, 2, 1, 1, 2, 1, 1, 2, 1])
+        AccentPattern::from_grouping(&[3, 3, 3, 2])
     }
 
     pub fn eleven_three_three_two_three() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 1, 2, 1, 1, 2, 1, 2, 1, 1])
+        AccentPattern::from_grouping(&[3, 3, 2, 3])
     }
 
     pub fn eleven_three_two_three_three() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 1, 2, 1, 2, 1, 1, 2, 1, 1])
+        AccentPattern::from_grouping(&[3, 2, 3, 3])
     }
 
     pub fn eleven_two_three_three_three() -> AccentPattern {
-        AccentPattern::from_values(&[3, 1, 2, 1, 1, 2, 1, 1, 2, 1, 1])
+        AccentPattern::from_grouping(&[2, 3, 3, 3])
     }
 }
 
@@ -511,9 +896,29 @@ mod tests {
 
     #[test]
     fn test_time_signature_validation() {
-        assert!(TimeSignature::new(1, 4).is_err()); // Too few beats
-        assert!(TimeSignature::new(16, 4).is_err()); // Too many beats
+        assert!(TimeSignature::new(0, 4).is_err()); // Too few beats
+        assert!(TimeSignature::new(33, 4).is_err()); // Too many beats
         assert!(TimeSignature::new(4, 3).is_err()); // Invalid denominator
+        assert!(TimeSignature::new(4, 64).is_err()); // Invalid denominator
+
+        assert!(TimeSignature::new(1, 4).is_ok());
+        assert!(TimeSignature::new(32, 32).is_ok());
+    }
+
+    #[test]
+    fn test_thirty_second_notes() {
+        let ts = TimeSignature::new(7, 32).unwrap();
+        assert_eq!(ts.ticks_per_beat(), 60);
+        assert_eq!(ts.ticks_per_measure(), 420);
+    }
+
+    #[test]
+    fn test_long_measure_defaults() {
+        assert_eq!(AccentPattern::default_for_beats(1).accents, vec![AccentLevel::Strong]);
+        assert_eq!(TimeSignature::new(16, 16).unwrap().groupings(), vec![4, 4, 4, 4]);
+        assert_eq!(TimeSignature::new(17, 16).unwrap().groupings(), vec![3, 3, 3, 3, 3, 2]);
+        assert_eq!(TimeSignature::new(19, 16).unwrap().groupings(), vec![3, 3, 3, 3, 3, 2, 2]);
+        assert_eq!(TimeSignature::new(32, 32).unwrap().accents.len(), 32);
     }
 
     #[test]
@@ -535,6 +940,31 @@ mod tests {
         assert_eq!(ts.beat_at_tick(1920), 0); // Next measure
     }
 
+    #[test]
+    fn test_musical_position_round_trip() {
+        let ts = TimeSignature::new(4, 4).unwrap();
+
+        let pos = ts.position_at_tick(4 * 1920 + 2 * 480 + 120);
+        assert_eq!(pos, MusicalPosition::new(5, 3, 120));
+        assert_eq!(pos.to_string(), "5.3.120");
+        assert_eq!(ts.tick_at_position(pos).unwrap(), 4 * 1920 + 2 * 480 + 120);
+
+        assert_eq!(ts.position_at_tick(0), MusicalPosition::new(1, 1, 0));
+        assert!(ts.tick_at_position(MusicalPosition::new(1, 5, 0)).is_err());
+        assert!(ts.tick_at_position(MusicalPosition::new(0, 1, 0)).is_err());
+        // Past the last tick a u32 can hold
+        assert!(ts.tick_at_position(MusicalPosition::new(10_000_000, 1, 0)).is_err());
+    }
+
+    #[test]
+    fn test_musical_position_parse() {
+        assert_eq!(MusicalPosition::parse("17").unwrap(), MusicalPosition::new(17, 1, 0));
+        assert_eq!(MusicalPosition::parse("5.3").unwrap(), MusicalPosition::new(5, 3, 0));
+        assert_eq!(MusicalPosition::parse("5.3.120").unwrap(), MusicalPosition::new(5, 3, 120));
+        assert!(MusicalPosition::parse("5.x").is_err());
+        assert!(MusicalPosition::parse("1.2.3.4").is_err());
+    }
+
     #[test]
     fn test_default_accents() {
         let ts = TimeSignature::common();
@@ -566,6 +996,102 @@ mod tests {
         assert_eq!(ts.denominator, 8);
     }
 
+    #[test]
+    fn test_measure_clicks() {
+        let mut ts = TimeSignature::new(11, 8).unwrap();
+        ts.set_accents(groupings::eleven_three_three_two_three());
+
+        let clicks = ts.measure_clicks(120);
+        assert_eq!(clicks.len(), 11);
+        // Eighth notes at 120 BPM are 0.25s apart
+        assert_eq!(clicks[1].seconds, 0.25);
+        let accents: Vec<u8> = clicks.iter().map(|c| c.accent).collect();
+        assert_eq!(accents, vec![3, 1, 1, 2, 1, 1, 2, 1, 2, 1, 1]);
+    }
+
+    #[test]
+    fn test_groupings() {
+        assert_eq!(TimeSignature::new(7, 8).unwrap().groupings(), vec![3, 2, 2]);
+        assert_eq!(TimeSignature::common().groupings(), vec![2, 2]);
+        assert_eq!(TimeSignature::waltz().groupings(), vec![3]);
+
+        let mut ts = TimeSignature::new(11, 8).unwrap();
+        ts.set_accents(groupings::eleven_two_three_three_three());
+        assert_eq!(ts.groupings(), vec![2, 3, 3, 3]);
+        assert_eq!(AccentPattern::from_values(&[1, 1, 3]).groupings(), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_compound_pulse() {
+        let mut ts = TimeSignature::compound_duple();
+        assert!(ts.is_compound());
+        assert!(!TimeSignature::common().is_compound());
+        assert_eq!(ts.pulse_starts(), vec![0, 1, 2, 3, 4, 5]);
+
+        ts.set_pulse_grouping(&[3, 3]).unwrap();
+        assert_eq!(ts.pulse_starts(), vec![0, 3]);
+        assert!(ts.is_on_pulse(720));
+        assert!(!ts.is_on_pulse(240));
+        assert_eq!(ts.snap_to_pulse(300), 0);
+        assert_eq!(ts.snap_to_pulse(500), 720);
+        assert_eq!(ts.snap_to_pulse(1300), 1440); // Next measure
+
+        let clicks: Vec<(u32, u8)> = ts.measure_clicks(120).iter().map(|c| (c.beat, c.accent)).collect();
+        assert_eq!(clicks, vec![(0, 3), (3, 2)]);
+
+        assert!(ts.set_pulse_grouping(&[3, 2]).is_err());
+        assert!(ts.set_pulse_grouping(&[6, 0]).is_err());
+        ts.set_pulse_grouping(&[]).unwrap();
+        assert_eq!(ts.pulse_grouping(), vec![1; 6]);
+    }
+
+    #[test]
+    fn test_snap_to_accents() {
+        // 7/8 as 3+2+2: beats every 240 ticks, medium accents at 720 and 1200
+        let ts = TimeSignature::parse("7/8").unwrap();
+        assert_eq!(ts.snap_to_accents(650, 120, 0.0), 600); // Plain sixteenths
+        assert_eq!(ts.snap_to_accents(650, 120, 1.0), 720); // Pulled to the group start
+        assert_eq!(ts.snap_to_accents(290, 120, 1.0), 240);
+
+        // Toward the next downbeat only with more gravity
+        assert_eq!(ts.snap_to_accents(1540, 120, 1.0), 1560);
+        assert_eq!(ts.snap_to_accents(1540, 120, 3.0), 1680);
+
+        // Ties go later, as in plain quantizing
+        assert_eq!(ts.snap_to_accents(60, 120, 0.0), 120);
+        assert_eq!(ts.snap_to_accents(0, 120, 2.0), 0);
+
+        // Grid points past the end of the tick range are never candidates
+        assert_eq!(ts.snap_to_accents(u32::MAX, 120, 1.0) % 120, 0);
+    }
+
+    #[test]
+    fn test_playback_rate() {
+        assert_eq!(PlaybackRate::parse("0.5").unwrap().value(), 0.5);
+        assert_eq!(PlaybackRate::parse(" 0.75x ").unwrap().value(), 0.75);
+        assert_eq!(PlaybackRate::parse("150%").unwrap().value(), 1.5);
+        assert_eq!(PlaybackRate::parse("0.5").unwrap().effective_tempo(120), 60.0);
+        assert_eq!(PlaybackRate::default().to_string(), "1×");
+        for bad in ["0.1", "3x", "fast", ""] {
+            assert!(PlaybackRate::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_polyrhythm() {
+        let poly = Polyrhythm::parse("3:2").unwrap();
+        assert_eq!(poly, Polyrhythm { beats: 3, against: 2 });
+        assert_eq!(Polyrhythm::parse("4 over 3").unwrap().to_string(), "4:3");
+        assert_eq!(Polyrhythm::parse(" 2/3 ").unwrap(), Polyrhythm { beats: 2, against: 3 });
+
+        assert_eq!(poly.click_ticks(&TimeSignature::common()), vec![0, 320, 640]);
+        assert_eq!(poly.cycle_ticks(&TimeSignature::compound_duple()), 480);
+
+        assert!(Polyrhythm::parse("3").is_err());
+        assert!(Polyrhythm::parse("0:2").is_err());
+        assert!(Polyrhythm::parse("17:4").is_err());
+    }
+
     #[test]
     fn test_odd_meter_defaults() {
         // 7/8 should have a sensible grouping
@@ -210,7 +210,7 @@ pub fn transpose_pitch_diatonic(
         0
     };
 
-    let new_octave = octave + full_octaves as i8 + boundary_cross + octave_adjustment;
+    let new_octave = octave + full_octaves + boundary_cross + octave_adjustment;
 
     tracing::debug!(
         "Result: degree {} in {} = {}{} (octave {})",
@@ -245,6 +245,10 @@ pub fn transpose_note(note: &Note, mode: &TransposeMode) -> Result<Note> {
         duration_ticks: note.duration_ticks,
         velocity: note.velocity,
         voice: note.voice,
+        pan: note.pan,
+        probability: note.probability,
+        take: note.take,
+        color: note.color,
     })
 }
 
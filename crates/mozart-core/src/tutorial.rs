@@ -0,0 +1,233 @@
+//! Onboarding tutorial
+//!
+//! A fixed sequence of guided steps. Each step is checked against the real
+//! song rather than trusting the UI: the song is snapshotted when a step
+//! starts, and the step is complete once the current song differs from that
+//! snapshot in the way the step asks for.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::song::Song;
+use crate::transpose::{transpose_notes, TransposeMode};
+
+/// Degrees tried when checking for a diatonic transposition (two octaves each way)
+const MAX_TUTORIAL_DEGREES: i8 = 14;
+
+/// One guided step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TutorialStep {
+    AddNote,
+    ChangeMeter,
+    TransposeDiatonic,
+}
+
+impl TutorialStep {
+    /// Every step, in order
+    pub const ALL: [TutorialStep; 3] = [
+        TutorialStep::AddNote,
+        TutorialStep::ChangeMeter,
+        TutorialStep::TransposeDiatonic,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TutorialStep::AddNote => "Add a note",
+            TutorialStep::ChangeMeter => "Change the meter",
+            TutorialStep::TransposeDiatonic => "Transpose within the key",
+        }
+    }
+
+    /// What the user should do
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            TutorialStep::AddNote => "Click anywhere in the piano roll to place a note.",
+            TutorialStep::ChangeMeter => "Pick a different time signature in the transport bar, like 3/4 or 7/8.",
+            TutorialStep::TransposeDiatonic => {
+                "Open the transpose panel, choose Diatonic, and move the melody by a scale degree."
+            }
+        }
+    }
+
+    /// Whether `now` shows the step done, compared with the song when it started
+    fn is_complete(&self, start: &Song, now: &Song) -> bool {
+        match self {
+            TutorialStep::AddNote => now.notes.len() > start.notes.len(),
+            TutorialStep::ChangeMeter => {
+                let (old, new) = (&start.settings.time_signature, &now.settings.time_signature);
+                (old.numerator, old.denominator) != (new.numerator, new.denominator)
+            }
+            TutorialStep::TransposeDiatonic => is_diatonic_transposition(start, now),
+        }
+    }
+}
+
+// `now` holds the notes of `start` moved by a whole number of scale degrees,
+// either replacing the originals or added alongside them
+fn is_diatonic_transposition(start: &Song, now: &Song) -> bool {
+    if start.notes.is_empty() {
+        return false;
+    }
+    let key = start.settings.key;
+    let current = sorted(now.notes.clone());
+    (-MAX_TUTORIAL_DEGREES..=MAX_TUTORIAL_DEGREES)
+        .filter(|&degrees| degrees != 0)
+        .filter_map(|degrees| transpose_notes(&start.notes, &TransposeMode::diatonic(key, degrees)).ok())
+        .any(|moved| {
+            let with_originals = sorted(start.notes.iter().cloned().chain(moved.iter().cloned()).collect());
+            current == sorted(moved) || current == with_originals
+        })
+}
+
+fn sorted(mut notes: Vec<Note>) -> Vec<Note> {
+    notes.sort_by_key(|n| (n.start_tick, n.pitch, n.duration_ticks, n.voice));
+    notes
+}
+
+/// Progress snapshot for the UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TutorialState {
+    /// Current step, or None once the tutorial is finished
+    pub step: Option<TutorialStep>,
+    /// Position of the current step (equals `total` when finished)
+    pub index: usize,
+    pub total: usize,
+    pub title: String,
+    pub instruction: String,
+    /// The current step has been done and `next` will advance
+    pub completed: bool,
+    pub skipped: Vec<TutorialStep>,
+}
+
+/// Tutorial progress through [`TutorialStep::ALL`]
+#[derive(Debug, Clone)]
+pub struct Tutorial {
+    index: usize,
+    /// Song when the current step started
+    start: Song,
+    skipped: Vec<TutorialStep>,
+}
+
+impl Tutorial {
+    /// Start at the first step
+    pub fn new(song: &Song) -> Self {
+        tracing::info!("Starting tutorial");
+        Tutorial { index: 0, start: song.clone(), skipped: Vec::new() }
+    }
+
+    /// Current step (None when finished)
+    pub fn step(&self) -> Option<TutorialStep> {
+        TutorialStep::ALL.get(self.index).copied()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.step().is_none()
+    }
+
+    /// Whether the current step is done in `song`
+    pub fn is_step_complete(&self, song: &Song) -> bool {
+        self.step().is_some_and(|step| step.is_complete(&self.start, song))
+    }
+
+    /// Advance past a completed step
+    pub fn next(&mut self, song: &Song) -> Result<()> {
+        let step = self
+            .step()
+            .ok_or_else(|| MozartError::TutorialError("The tutorial is already finished".to_string()))?;
+        if !step.is_complete(&self.start, song) {
+            return Err(MozartError::TutorialError(format!("Step not done yet: {}", step.title())));
+        }
+        tracing::debug!("Tutorial step {:?} completed", step);
+        self.advance(song);
+        Ok(())
+    }
+
+    /// Move on without doing the current step (no-op when finished)
+    pub fn skip(&mut self, song: &Song) {
+        if let Some(step) = self.step() {
+            tracing::debug!("Tutorial step {:?} skipped", step);
+            self.skipped.push(step);
+            self.advance(song);
+        }
+    }
+
+    fn advance(&mut self, song: &Song) {
+        self.index += 1;
+        self.start = song.clone();
+    }
+
+    /// Progress against the current song
+    pub fn state(&self, song: &Song) -> TutorialState {
+        let step = self.step();
+        TutorialState {
+            step,
+            index: self.index,
+            total: TutorialStep::ALL.len(),
+            title: step.map_or("Tutorial complete", |s| s.title()).to_string(),
+            instruction: step
+                .map_or("You're ready to write music. Reopen the tutorial from the Help menu anytime.", |s| {
+                    s.instruction()
+                })
+                .to_string(),
+            completed: self.is_step_complete(song),
+            skipped: self.skipped.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::TimeSignature;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_steps_validate_song_state() {
+        let mut song = Song::new();
+        let mut tutorial = Tutorial::new(&song);
+        assert_eq!(tutorial.step(), Some(TutorialStep::AddNote));
+        assert!(tutorial.next(&song).is_err());
+
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(64, 480, 480));
+        assert!(tutorial.state(&song).completed);
+        tutorial.next(&song).unwrap();
+
+        // Changing only the accents is not a new meter
+        let mut accented = song.clone();
+        accented.settings.time_signature.accents.accents.reverse();
+        assert!(!tutorial.is_step_complete(&accented));
+        song.set_time_signature(TimeSignature::parse("3/4").unwrap());
+        tutorial.next(&song).unwrap();
+
+        // A chromatic shift off the scale does not count
+        let mut chromatic = song.clone();
+        chromatic.notes = transpose_notes(&song.notes, &TransposeMode::chromatic(1)).unwrap();
+        assert!(!tutorial.is_step_complete(&chromatic));
+
+        let mut harmonized = song.clone();
+        let thirds = transpose_notes(&song.notes, &TransposeMode::diatonic(song.settings.key, 2)).unwrap();
+        harmonized.add_notes(thirds);
+        assert!(tutorial.is_step_complete(&harmonized));
+
+        tutorial.next(&harmonized).unwrap();
+        assert!(tutorial.is_finished());
+        assert_eq!(tutorial.state(&harmonized).index, 3);
+        assert!(tutorial.next(&harmonized).is_err());
+    }
+
+    #[test]
+    fn test_skip() {
+        let song = Song::new();
+        let mut tutorial = Tutorial::new(&song);
+        tutorial.skip(&song);
+        tutorial.skip(&song);
+
+        let state = tutorial.state(&song);
+        assert_eq!(state.step, Some(TutorialStep::TransposeDiatonic));
+        assert_eq!(state.skipped, vec![TutorialStep::AddNote, TutorialStep::ChangeMeter]);
+        // Nothing to transpose yet
+        assert!(!state.completed);
+    }
+}
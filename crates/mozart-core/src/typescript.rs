@@ -0,0 +1,1543 @@
+//! TypeScript declarations for the JSON payloads of the WASM API
+//!
+//! Methods ending in `Json` exchange serde-encoded values whose shapes
+//! wasm-bindgen cannot describe. Their declarations live here, next to the
+//! Rust types, and are emitted into the generated `.d.ts` of the WASM package;
+//! the tests below fail when a type's serde output drifts from them.
+
+/// Declarations for every JSON payload type, in TypeScript syntax
+pub const PAYLOAD_TYPES: &str = r#"export interface Note {
+  pitch: number
+  start_tick: number
+  duration_ticks: number
+  velocity: number
+  voice: number
+  pan: number // 0=left, 64=center, 127=right
+  probability: number // Percent chance of sounding on each pass (experimental)
+  take?: Take // Alternate take lane; absent for none
+  color?: ColorLabel // Piano roll label; playback ignores it
+}
+
+// A note with its index in the song, from `getNoteWindowJson`
+export interface IndexedNote {
+  index: number
+  pitch: number
+  start_tick: number
+  duration_ticks: number
+  velocity: number
+  voice: number
+  pan: number
+  probability: number
+  take?: Take
+  color?: ColorLabel
+}
+
+// Alternate take lane of a note; a pass plays one lane per measure
+export type Take = 'A' | 'B'
+
+// Color label of a note or section, from a fixed palette
+export type ColorLabel = 'red' | 'orange' | 'yellow' | 'green' | 'blue' | 'purple' | 'pink' | 'gray'
+
+export type FollowMode = 'off' | 'page-flip' | 'continuous'
+
+// Chord strum order: 'up' plays the lowest pitch first
+export type StrumDirection = 'up' | 'down'
+
+// How a merged song is timed: 'align' keeps its length in seconds at the
+// first song's tempo; 'keep_first' keeps its ticks
+export type TempoAlignment = 'align' | 'keep_first'
+
+// Sound a song plays with; 'synth' gives each voice its own waveform
+export type Instrument = 'synth' | 'piano' | 'organ' | 'strings' | 'flute'
+
+// A key of a chord captured in chord entry, from `ChordCapture`
+export interface CapturedKey {
+  pitch: number
+  velocity: number
+}
+
+// One metronome click of a measure preview
+export interface Click {
+  beat: number
+  seconds: number // Offset from the start of the measure
+  accent: number // 1=weak, 2=medium, 3=strong
+}
+
+export interface AccentPreset {
+  name: string // e.g., "3+3+2+3"
+  numerator: number
+  accents: number[] // 1=weak, 2=medium, 3=strong
+  description: string
+  builtin: boolean
+}
+
+// One piece of a planned split, from `planSplitJson` or `planSectionsJson`
+export interface SplitPiece {
+  title: string
+  start_tick: number
+  end_tick: number
+  start_position: string // e.g., "5.1.0"
+  notes: number // Notes starting in the piece
+}
+
+// A bundled odd-meter groove, from `groovePresetsJson`
+export interface GroovePreset {
+  name: string
+  time_signature: string // e.g., "7/8"
+  grouping: number[] // Beat groups, e.g., [2, 2, 3]
+  click_groups: boolean // Click once per group instead of every beat
+  tempo: number
+  drums?: string // One measure of rhythm hits, looped in voice 9
+  description: string
+}
+
+// Accent of a beat as saved in the song file
+export type AccentLevel = 'Weak' | 'Medium' | 'Strong'
+
+export interface AccentPattern {
+  accents: AccentLevel[]
+}
+
+// Meter as saved in the song file
+export interface TimeSignature {
+  numerator: number
+  denominator: number
+  accents: AccentPattern
+  pulse_grouping?: number[] // Beats per felt pulse; absent means every beat
+}
+
+// A voice in its own meter, with barlines from the start of the song (polymeter)
+export interface VoiceMeter {
+  voice: number
+  time_signature: TimeSignature
+}
+
+// A named voice with its own sound and mix; its notes are the song's notes in that voice
+export interface Track {
+  voice: number
+  name: string
+  instrument?: Instrument // Absent plays with the song's
+  volume: number // 0-127; 100 plays velocities as written
+  mute: boolean
+  solo: boolean // While any track is soloed, only soloed tracks play
+}
+
+// Secondary metronome layer: `beats` clicks across `against` main beats
+export interface Polyrhythm {
+  beats: number
+  against: number
+}
+
+// A marker also starts a section, which runs to the next marker
+export interface Marker {
+  name: string
+  tick: number
+  color?: ColorLabel // Section color label
+}
+
+// A lyric syllable; words over several notes end in "-" ("Hap-", "py")
+export interface Lyric {
+  tick: number
+  text: string
+  new_line: boolean // First syllable of a lyric line
+}
+
+// A comment on the music at a tick, or over tick..end_tick
+export interface Annotation {
+  tick: number
+  end_tick?: number
+  text: string
+}
+
+// Repeat barlines around measures start..end (1-indexed, inclusive)
+export interface Repeat {
+  start_measure: number
+  end_measure: number
+  times: number // Times the section plays in all; 2 for a plain repeat
+}
+
+// Numbered ending (volta bracket)
+export interface Ending {
+  start_measure: number
+  end_measure: number
+  passes: number[] // Passes through the enclosing repeat that play it, from 1
+}
+
+export type JumpKind = 'da_capo' | 'dal_segno'
+
+// How far the music plays after a jump
+export type JumpEnd = 'end' | 'fine' | 'coda'
+
+// D.C. or D.S., taken at the end of `at_measure`
+export interface Jump {
+  kind: JumpKind
+  until: JumpEnd
+  at_measure: number
+}
+
+// Written repeat structure of a song, from `getRepeatsJson`; signs are measure numbers
+export interface RepeatStructure {
+  repeats?: Repeat[]
+  endings?: Ending[]
+  jump?: Jump
+  segno?: number
+  fine?: number
+  to_coda?: number
+  coda?: number
+}
+
+// One token of text notation, from `parseMelodyToken`
+export interface MelodyToken {
+  pitch: number | null // null for a rest; middle C for a rhythm hit ("xq")
+  duration_ticks: number
+}
+
+export type CompletionKind = 'note' | 'duration'
+
+// A completion for the token being typed, from `suggestNextTokensJson`
+export interface TokenSuggestion {
+  text: string // Replaces the partial token
+  kind: CompletionKind
+  probability: number // 0-1; one call's suggestions add up to 1
+  reason: string
+}
+
+export type IssueKind =
+  | 'overlapping_note'
+  | 'zero_duration'
+  | 'pitch_out_of_range'
+  | 'zero_velocity'
+  | 'stray_note'
+
+export interface ValidationIssue {
+  kind: IssueKind
+  note_index: number
+  message: string
+}
+
+// Issue classes to fix; omitted fields use the defaults (all but stray_notes)
+export interface RepairOptions {
+  overlapping?: boolean
+  zero_duration?: boolean
+  pitch_range?: boolean
+  zero_velocity?: boolean
+  stray_notes?: boolean
+}
+
+export interface RepairReport {
+  removed: number
+  modified: number
+}
+
+export type AudioFault = 'stalled' | 'underrun' | 'device_lost' | 'closed'
+
+// Detail of an `audio:error` event, from `AudioWatchdog`
+export interface AudioDiagnostics {
+  fault: AudioFault
+  message: string
+  state: string // AudioContext state when detected
+  wall_ms: number // Wall and audio clock time over the last window (0 when not measured)
+  audio_ms: number
+  recoveries: number // Restarts in the last minute, counting this one
+  recover: boolean // false once restarts keep failing
+}
+
+export type StartupStage = 'launch' | 'audio' | 'song' | 'running'
+
+// Stored at launch (stage moves forward as startup proceeds), removed on a clean exit
+export interface StartupSentinel {
+  stage: StartupStage
+  launched_at_ms: number
+  crashes: number // Launches in a row that crashed before this one
+}
+
+// Whether this launch is in safe mode because the previous one crashed
+export interface StartupDiagnostics {
+  safe_mode: boolean
+  crashed_stage: StartupStage | null
+  crashes: number
+  message: string
+  skipped: string[] // What safe mode leaves out
+  suggestions: string[]
+}
+
+// Instrument or voice range; name is empty for a custom range
+export interface PitchRange {
+  name: string
+  low: number
+  high: number
+}
+
+export interface RangeWarning {
+  note_index: number
+  pitch: number
+  message: string
+}
+
+// A note moved by octaves to fit a range, from `foldIntoRangeJson`
+export interface FoldedNote {
+  note_index: number
+  from: number
+  to: number
+}
+
+// Indices are into the notes before the cleanup
+export interface CleanupReport {
+  duplicates: number[]
+  merged: NoteMerge[]
+  zero_length: number[]
+  dry_run: boolean
+}
+
+export interface NoteMerge {
+  removed: number
+  kept: number
+}
+
+// What a destructive command would throw away
+export interface LossSummary {
+  action: string // e.g., "Clear notes"
+  notes: number
+  chords: number
+  markers: number
+  lyrics: number
+  annotations: number
+  repeat_marks: number // Repeats, endings, signs, and jumps
+  unsaved_changes: boolean
+  message: string // e.g., "Clear notes removes 12 notes."
+}
+
+// A destructive command ran, or changed nothing until called again with confirm
+export type Outcome<T> =
+  | { status: 'done'; result: T }
+  | { status: 'needs_confirmation'; summary: LossSummary }
+
+// Piano roll pitch rows: the notes' span plus a margin, or a fixed range, moved by octaves
+export type PitchView =
+  | { mode: 'auto'; margin: number; octave_shift: number }
+  | { mode: 'manual'; low: number; high: number; octave_shift: number }
+
+// Editor view state saved in the project file (ignored by core logic)
+export interface ViewState {
+  selected_tab?: string
+  scroll_x: number
+  scroll_y: number
+  pixels_per_tick?: number
+  grid_division?: number
+  loop_region?: [number, number]
+  selected_track?: number
+  pitch_view?: PitchView
+}
+
+export interface SongInfo {
+  title: string
+  tempo: number
+  time_signature: string
+  key: string
+  note_count: number
+  measure_count: number
+  duration_ticks: number
+  duration_seconds: number
+  total_seconds: number
+  duration_display: string
+  total_display: string
+  lowest_pitch: string | null // e.g., "C4"
+  highest_pitch: string | null
+  notes_per_measure: number
+  out_of_key_count: number
+}
+
+// Settings for new songs, saved in the app preferences; validate with `setNewSongDefaults`
+export interface SongDefaults {
+  tempo: number
+  time_signature: string // e.g., "3/4"
+  key: string // e.g., "D minor"
+  instrument: string // Range preset name, or "" for none
+  pickup_beats: number // 0 for no pickup measure
+}
+
+// An invokable action; `shortcut` uses Mod for Ctrl (⌘ on macOS)
+export interface CommandInfo {
+  id: string // e.g., "file.save"
+  name: string
+  category: string
+  description: string
+  shortcut?: string
+}
+
+// A command palette result from `searchCommandsJson`, best first
+export interface CommandMatch {
+  id: string
+  name: string
+  category: string
+  description: string
+  shortcut?: string
+  score: number
+  matched: number[] // Positions in `name` to highlight
+}
+
+// A recorded command and the answers it was given to its prompts
+export interface MacroStep {
+  command: string // Registry id, e.g. "transpose.up"
+  args?: string[]
+}
+
+export interface Macro {
+  name: string
+  shortcut?: string // e.g., "Mod+Alt+1"
+  steps: MacroStep[]
+}
+
+// Saved macros, as persisted in preferences by `saveMacro`
+export interface MacroLibrary {
+  macros: Macro[]
+}
+
+// User accent presets, as saved in preferences
+export interface PresetLibrary {
+  user: AccentPreset[]
+}
+
+// Every preference, for Export/Import Settings; missing fields take their defaults
+export interface AppSettings {
+  version: number
+  song_defaults: SongDefaults
+  macros: MacroLibrary // Including shortcuts
+  accent_presets: PresetLibrary
+  audio: AudioSettings
+  metronome: MetronomeSettings
+  follow_mode: FollowMode
+  prevent_sleep: boolean
+  watch_folder: WatchFolderSettings // Desktop only
+}
+
+export interface AudioSettings {
+  sample_rate: number | null // null = device default
+  latency: 'interactive' | 'balanced' | 'playback' | number // Category, or buffer latency in seconds
+}
+
+export interface MetronomeSettings {
+  polyrhythm_frequency: number // Hz
+  polyrhythm_volume: number // 0 to 1
+}
+
+// MIDI files exported into `folder` are imported into `library` as songs
+export interface WatchFolderSettings {
+  enabled: boolean
+  folder: string
+  library: string
+  poll_ms: number
+}
+
+export type TutorialStep = 'add_note' | 'change_meter' | 'transpose_diatonic'
+
+// Onboarding progress from `tutorialStateJson`, checked against the song
+export interface TutorialState {
+  step: TutorialStep | null // null once finished
+  index: number
+  total: number
+  title: string
+  instruction: string
+  completed: boolean // The step is done; tutorialNext() will advance
+  skipped: TutorialStep[]
+}
+
+export type TheoryTopic = 'intervals' | 'scales' | 'chords' | 'circle_of_fifths' | 'cadences'
+
+export type Consonance = 'perfect' | 'imperfect' | 'dissonant'
+
+export interface IntervalInfo {
+  semitones: number
+  name: string
+  short_name: string // e.g., "m3", "P5"
+  consonance: Consonance
+  inversion: string // Short name of the interval completing the octave
+  example: string // Spelled above C, e.g., "C–Eb"
+}
+
+export interface ScaleInfo {
+  scale_type: string // e.g., "HarmonicMinor"
+  name: string
+  intervals: number[] // Semitones from the root
+  formula: string[] // Against the major scale, e.g., ["1", "2", "b3", ...]
+  steps: string // e.g., "W W H W W W H"
+  degree_names: string[] // Tonic, Supertonic, ...
+  example: string[] // Spelled from C
+}
+
+export type ScaleDirection = 'ascending' | 'descending' | 'both'
+
+// From `scaleAuditionJson` and `chordAuditionJson`, timed from the audition's start
+export interface AuditionNote {
+  pitch: number
+  velocity: number
+  start_seconds: number
+  duration_seconds: number
+}
+
+export interface ChordInfo {
+  name: string
+  symbol: string // Suffix after the root, e.g., "m7" ("" for a major triad)
+  intervals: number[]
+  formula: string[] // e.g., ["1", "b3", "5"]
+  example: string[] // Spelled on C
+}
+
+// A key on the circle of fifths
+export interface KeyInfo {
+  fifths: number // Sharps (positive) or flats (negative)
+  major: string // Major tonic, e.g., "G"
+  minor: string // Relative minor tonic, e.g., "E"
+  signature: string[] // e.g., ["F#", "C#"]
+  enharmonic?: string // Same key spelled with the other accidental
+}
+
+export interface CadenceInfo {
+  name: string
+  description: string
+  degrees: number[] // Scale degrees 1-7
+  major_numerals: string[]
+  minor_numerals: string[] // Harmonic minor
+  major_example: string[] // Chord symbols in C major
+  minor_example: string[] // Chord symbols in A harmonic minor
+}
+
+// Reference tables from `theoryReferenceJson`
+export type TheoryReference =
+  | { topic: 'intervals'; entries: IntervalInfo[] }
+  | { topic: 'scales'; entries: ScaleInfo[] }
+  | { topic: 'chords'; entries: ChordInfo[] }
+  | { topic: 'circle_of_fifths'; entries: KeyInfo[] }
+  | { topic: 'cadences'; entries: CadenceInfo[] }
+
+export type TokenKind = 'note' | 'rest' | 'rhythm_hit'
+
+// One form a melody token takes
+export interface TokenRule {
+  kind: TokenKind
+  syntax: string // e.g., "<pitch class><octave>[duration]"
+  description: string
+  examples: string[] // Tokens the parser accepts
+}
+
+export interface Accidental {
+  semitones: number // +1 sharp, -1 flat
+  symbols: string[]
+}
+
+export interface DurationRule {
+  symbol: string // Short name, e.g., "q"
+  name: string // e.g., "quarter"
+  names: string[] // Usable in any token, the short one first
+  numbers: string[] // Only after a rest or hit (e.g., "R4"); after a note they'd run into the octave
+  ticks: number
+  dotted_ticks: number
+}
+
+export interface OctaveRange {
+  lowest_pitch: string // e.g., "C-1"
+  highest_pitch: string
+  middle_c: string
+}
+
+// The text notation from `notationGrammarJson`, read from the parser's own tables
+export interface NotationGrammar {
+  separator: string
+  case_sensitive: boolean
+  tokens: TokenRule[]
+  pitch_classes: [string, number][] // Spelling and pitch class (0 = C)
+  accidentals: Accidental[]
+  octaves: OctaveRange
+  durations: DurationRule[]
+  dot: string // Makes a duration dotted
+  default_duration: string // For a token written without one
+  ebnf: string
+}
+
+export type Difficulty = 'easy' | 'medium' | 'hard'
+
+// Options for `startCallResponseJson`; omitted fields use the defaults
+export interface CallResponseConfig {
+  generate?: boolean // A generated melody instead of the song
+  start_measure?: number // 1-based
+  measures?: number
+  difficulty?: Difficulty // For a generated melody
+  seed?: number
+  tolerance_ticks?: number
+}
+
+export type CallResponsePhase = 'call' | 'response' | 'finished'
+
+// The phrase to play; seconds passed back are counted from its start
+export interface CallResponseInfo {
+  phrase: Note[] // Starting at tick 0
+  tempo: number
+  duration_ticks: number // Length of the call and of the response window
+  duration_seconds: number
+}
+
+export type NoteVerdict = 'correct' | 'wrong_pitch' | 'early' | 'late' | 'missed'
+
+export interface NoteFeedback {
+  index: number // Into the phrase
+  expected_pitch: number
+  played_pitch?: number
+  timing_offset_ticks?: number // Negative when early
+  verdict: NoteVerdict
+}
+
+export interface CallResponseResult {
+  notes: NoteFeedback[]
+  extra_notes: number
+  pitch_accuracy: number // Percent
+  rhythm_accuracy: number // Percent
+  score: number
+}
+
+// Events from `advanceCallResponseJson`
+export interface PracticePhaseChanged {
+  type: 'practice:phase'
+  phase: CallResponsePhase
+}
+
+export interface PracticeResult {
+  type: 'practice:result'
+  result: CallResponseResult
+}
+
+export type CallResponseEvent =
+  | PracticePhaseChanged
+  | PracticeResult
+
+export type HarmonicFunction = 'tonic' | 'predominant' | 'dominant'
+
+// A diatonic chord of the song's key
+export interface KeyChord {
+  degree: number // 1-7
+  numeral: string // e.g., "V7"
+  symbol: string // e.g., "G7"
+  function: HarmonicFunction
+  pitch_classes: number[] // 0 = C, root first
+}
+
+// A rung of the chord ladder, from `getDiatonicChordsJson`
+export interface DiatonicChord {
+  degree: number // 1-7
+  triad: KeyChord
+  seventh: KeyChord
+  tone_names: string[] // The seventh chord spelled in the key ("Bb", "D", "F", "A"); the triad is the first three
+}
+
+// From `suggestNextChordsJson`, best first
+export interface ChordSuggestion {
+  chord: KeyChord
+  score: number // 0-100
+  voice_movement: number // Semitones from the previous chord
+  common_tones: number
+  reason: string
+}
+
+// Options for `reharmonizeMeasureJson`; omitted fields use the defaults
+export interface ReharmonizeConstraints {
+  chords_per_measure?: number // 1-4
+  previous?: string | null // Chord before the measure
+  next?: string | null // Chord after the measure
+  passing_tones?: boolean // Short off-beat notes may be non-chord tones
+  sevenths?: boolean
+  limit?: number
+}
+
+export interface HarmonizedSpan {
+  start_tick: number
+  end_tick: number
+  chord: KeyChord
+}
+
+// From `reharmonizeMeasureJson`, best first
+export interface Reharmonization {
+  chords: HarmonizedSpan[]
+  score: number // 0-100
+  melody_fit: number // Percent of the melody on chord tones
+}
+
+export type ModulationKind = 'pivot_chord' | 'common_tone' | 'direct'
+
+export interface RouteChord {
+  measure: number // 1-based
+  key: string // Key the chord is heard in
+  chord: KeyChord
+  pivot_numeral?: string // The pivot's numeral in the new key
+}
+
+// From `planModulationJson`, best first; pass one back to `applyModulationJson`
+export interface ModulationPlan {
+  kind: ModulationKind
+  from: string
+  to: string
+  description: string
+  chords: RouteChord[]
+  start_measure: number
+  end_measure: number // Inclusive
+  common_tones: number[] // Pitch classes held across a common-tone change
+}
+
+export type CounterpointRule = 'dissonance' | 'parallel_perfect' | 'unison' | 'opening' | 'cadence' | 'leap'
+
+// Options for `generateCounterpointJson`; omitted fields use the defaults
+export interface CounterpointConfig {
+  species?: number // Only 1 (note against note)
+  above?: boolean // false: below the cantus
+  cantus_voice?: number
+  voice?: number // Replaced by the new line
+}
+
+// A strict rule the line had to break
+export interface Relaxation {
+  rule: CounterpointRule
+  note: number // Index into the cantus firmus
+  message: string
+}
+
+export interface CounterpointReport {
+  voice: number
+  notes: number
+  contrary_motion: number // Percent of moves
+  relaxations: Relaxation[]
+}
+
+// Options for `generateMelodyJson`; omitted fields use the defaults
+export interface MelodyConfig {
+  chart?: string // Chord chart from measure 1, e.g. "| C . . . | Am . F . |"
+  low?: number // MIDI pitch
+  high?: number // At least an octave above low
+  syncopation?: number // 0-1: chance an accented note comes half a beat early
+  voice?: number // Replaced by the melody
+  seed?: number
+}
+
+export interface MelodyReport {
+  voice: number
+  notes: number
+  chord_tones: number // Notes in the chord under them
+  syncopated: number
+}
+
+export type VariationKind = 'rhythmic_displacement' | 'neighbor_tones' | 'octave_displacement'
+
+// Options for `generateVariationsJson`; omitted fields use the defaults
+export interface VariationParams {
+  kinds?: VariationKind[] // Used in rotation, one per variation
+  note_percent?: number // Chance each note is changed
+  displacement_ticks?: number // How early displaced notes start
+  seed?: number
+}
+
+// From `generateVariationsJson`; choose one by index with `applyVariation`
+export interface Variation {
+  kind: VariationKind
+  description: string
+  notes: Note[] // The whole phrase as varied
+}
+
+// Options for `continueMelodyJson`; omitted fields use the defaults
+export interface ContinuationConfig {
+  measures?: number // 1-4, after the measure the line ends in
+  voice?: number
+  seed?: number
+}
+
+// From `continueMelodyJson`; a preview until `acceptContinuation`
+export interface Continuation {
+  voice: number
+  start_tick: number // Where the line ended
+  end_tick: number // A barline
+  notes: Note[]
+}
+
+// From `compareSongJson` / `compareMidi`: how alike the two melodies (voice 0) are
+export interface SimilarityReport {
+  interval_similarity: number // Percent, transposition-invariant
+  rhythm_similarity: number // Percent
+  score: number // Percent, mostly intervals
+  region?: MatchedRegion // Absent when nothing matches for long
+}
+
+// The stretch where the melodies match best; "a" is the open song
+export interface MatchedRegion {
+  a_start_tick: number
+  a_end_tick: number
+  b_start_tick: number
+  b_end_tick: number
+  a_start_position: string // e.g. "5.1.0"
+  b_start_position: string
+  notes: number // Of the open song, in the region
+  transposition: number // Semitones from the open song to the other
+}
+
+// How two versions are heard: at once, or measure by measure (changed
+// measures twice, the open song first)
+export type ComparisonMode = 'together' | 'alternate'
+
+// What a measure of a comparison plays; 'both' is a changed measure heard together
+export type ComparedVersion = 'same' | 'both' | 'current' | 'other'
+
+// From `getComparisonPlaybackJson` / `getComparisonPlaybackMidi`: the open
+// song (panned left) and another version (right) laid out to be played
+export interface Comparison {
+  mode: ComparisonMode
+  notes: Note[] // Playback effects already applied
+  tracks: Track[] // Instruments by voice, the other version's on voices of their own
+  segments: ComparisonSegment[]
+}
+
+export interface ComparisonSegment {
+  measure: number // 1-based, played timeline
+  version: ComparedVersion
+  start_tick: number // In the comparison
+  end_tick: number
+  song_tick: number // Where the measure starts in the song (played timeline)
+}
+
+// From `importChordChartJson`
+export interface ChartChord {
+  measure: number // 1-based
+  start_tick: number
+  end_tick: number
+  symbol: string // As written, e.g. "C/E"
+  pitch_classes: number[] // 0 = C, root first
+  bass?: number // Pitch class under a slash chord
+}
+
+// Triad a chord symbol is built on
+export type ChordQuality = 'major' | 'minor' | 'diminished' | 'augmented' | 'sus2' | 'sus4'
+
+// A chord symbol taken apart, from `parseChordJson`
+export interface Chord {
+  root: number // Pitch class, 0 = C
+  quality: ChordQuality
+  extensions: number[] // Semitones above the root beyond the triad (10 = minor seventh)
+  bass?: number // Pitch class of a slash bass
+}
+
+// A chord of the chord track, sounding until the next one (comped in playback)
+export interface ChordSymbol {
+  tick: number
+  symbol: string // As written, e.g. "Bbmaj7"
+}
+
+// A named clip of notes, from `getPatternsJson`; the chain plays patterns by name
+export interface Pattern {
+  name: string // One word
+  length_ticks: number // Ticks it takes up in the chain
+  notes: Note[] // From tick 0
+}
+
+// From `getPitchHistogramJson`, for a register heatmap
+export interface PitchHistogram {
+  bucket_ticks: number
+  lowest_pitch: number // Pitch of ticks[0] in every bucket
+  highest_pitch: number
+  buckets: HistogramBucket[]
+}
+
+export interface HistogramBucket {
+  start_tick: number
+  ticks: number[] // Ticks each pitch sounds, from lowest_pitch up
+  mean_pitch: number | null // Duration-weighted; null when silent
+}
+
+// From `extractGrooveJson` and `extractGrooveFromMidiJson`; passed back to `applyGrooveJson`
+export interface GrooveTemplate {
+  beat_ticks: number
+  subdivisions: number
+  slots: GrooveSlot[] // One per subdivision, from the beat's start
+}
+
+export interface GrooveSlot {
+  timing_offset_ticks: number // Late (positive) or early of the grid
+  velocity_offset: number // Above (positive) or below the passage's average
+  note_count: number // 0 leaves notes on this slot alone
+}
+
+// Change events from `takeChangesJson`, applied in order to the previous state
+export interface NotesAdded {
+  type: 'notes:added'
+  index: number
+  notes: Note[]
+}
+
+export interface NotesRemoved {
+  type: 'notes:removed'
+  index: number
+  count: number
+}
+
+// Notes from `index` on replaced one for one
+export interface NotesUpdated {
+  type: 'notes:updated'
+  index: number
+  notes: Note[]
+}
+
+// Span of the old and new positions of the changed notes, for windowed views
+export interface NotesDirty {
+  type: 'notes:dirty'
+  start_tick: number
+  end_tick: number
+}
+
+export interface TempoChanged {
+  type: 'settings:tempo'
+  tempo: number
+}
+
+export interface TimeSignatureChanged {
+  type: 'settings:timeSignature'
+  numerator: number
+  denominator: number
+  accents: number[] // 1=weak, 2=medium, 3=strong
+  groupings: number[]
+  pulse_grouping: number[]
+}
+
+export interface VoiceMetersChanged {
+  type: 'settings:voiceMeters'
+  voice_meters: VoiceMeter[]
+}
+
+export interface TracksChanged {
+  type: 'settings:tracks'
+  tracks: Track[]
+}
+
+export interface KeyChanged {
+  type: 'settings:key'
+  key: string
+}
+
+export interface AccentDynamicsChanged {
+  type: 'settings:accentDynamics'
+  enabled: boolean
+}
+
+// Pitch rhythm hits are entered at; null for middle C
+export interface RhythmPitchChanged {
+  type: 'settings:rhythmPitch'
+  pitch: number | null
+}
+
+export interface PitchRangeChanged {
+  type: 'settings:pitchRange'
+  range: PitchRange | null
+}
+
+export interface InstrumentChanged {
+  type: 'settings:instrument'
+  instrument: Instrument
+}
+
+// Notes the edit created or moved outside the pitch range
+export interface NotesOutOfRange {
+  type: 'notes:outOfRange'
+  warnings: RangeWarning[]
+}
+
+export interface MetadataChanged {
+  type: 'song:metadata'
+  title: string
+  composer: string
+}
+
+export interface MarkersChanged {
+  type: 'markers:changed'
+  markers: Marker[]
+}
+
+export interface LyricsChanged {
+  type: 'lyrics:changed'
+  lyrics: Lyric[]
+}
+
+// Chord track changed, and with it the comping
+export interface ChordsChanged {
+  type: 'chords:changed'
+  chords: ChordSymbol[]
+}
+
+// Patterns or the pattern chain changed, and with them playback
+export interface PatternsChanged {
+  type: 'patterns:changed'
+  patterns: Pattern[]
+  pattern_chain: string[]
+}
+
+export interface AnnotationsChanged {
+  type: 'annotations:changed'
+  annotations: Annotation[]
+}
+
+// Repeats, endings, or jumps changed, and so did the played order
+export interface RepeatsChanged {
+  type: 'repeats:changed'
+  repeats: RepeatStructure
+}
+
+// Strums or tempo map changed; the notes did not
+export interface PlaybackChanged {
+  type: 'playback:changed'
+}
+
+// The whole song was swapped out; refetch everything
+export interface SongReplaced {
+  type: 'song:replaced'
+}
+
+export type SongChange =
+  | NotesAdded
+  | NotesRemoved
+  | NotesUpdated
+  | NotesDirty
+  | TempoChanged
+  | TimeSignatureChanged
+  | VoiceMetersChanged
+  | TracksChanged
+  | KeyChanged
+  | AccentDynamicsChanged
+  | RhythmPitchChanged
+  | PitchRangeChanged
+  | InstrumentChanged
+  | NotesOutOfRange
+  | MetadataChanged
+  | MarkersChanged
+  | LyricsChanged
+  | ChordsChanged
+  | PatternsChanged
+  | AnnotationsChanged
+  | RepeatsChanged
+  | PlaybackChanged
+  | SongReplaced
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::SongChange;
+    use crate::commands::{find_command, search_commands};
+    use crate::confirm::{LossSummary, Outcome};
+    use crate::macros::{Macro, MacroLibrary, MacroStep};
+    use crate::tutorial::{Tutorial, TutorialStep};
+    use crate::theory::{get_theory_reference, Consonance, TheoryReference, TheoryTopic};
+    use crate::grammar::{get_notation_grammar, TokenKind};
+    use crate::note::{parse_melody_token, Note};
+    use crate::completion::{suggest_next_tokens, CompletionKind};
+    use crate::practice::{
+        CallResponse, CallResponseConfig, CallResponseEvent, CallResponsePhase, CallResponseResult, NoteFeedback, NoteVerdict,
+    };
+    use crate::worksheet::Difficulty;
+    use crate::harmony::{diatonic_chords, reharmonize_measure, suggest_next_chords, HarmonicFunction, ReharmonizeConstraints};
+    use crate::modulation::{plan_modulation, ModulationKind};
+    use crate::counterpoint::{write_counterpoint, CounterpointConfig, CounterpointRule};
+    use crate::melody::{generate_melody, MelodyConfig};
+    use crate::similarity::{similarity, SimilarityReport};
+    use crate::continuation::{continue_melody, ContinuationConfig, MelodyModel};
+    use crate::chart::parse_chord_chart;
+    use crate::chord::{Chord, ChordQuality, ChordSymbol};
+    use crate::pattern::Pattern;
+    use crate::audition::{scale_audition, ScaleDirection};
+    use crate::register::{pitch_histogram, FoldedNote, PitchRange, RangeWarning};
+    use crate::groove::extract_groove;
+    use crate::variation::{generate_variations, VariationKind, VariationParams};
+    use crate::scale::{Scale, ScaleType};
+    use crate::pitch::PitchClass;
+    use crate::presets::{builtin_presets, groove_presets, PresetLibrary};
+    use crate::song::{Annotation, IndexedNote, Lyric, Marker, Song, SongDefaults, Track, VoiceMeter};
+    use crate::strum::StrumDirection;
+    use crate::merge::{plan_split, TempoAlignment};
+    use crate::comparison::{compare_versions, ComparedVersion, ComparisonMode};
+    use crate::chord_entry::CapturedKey;
+    use crate::instrument::Instrument;
+    use crate::chance::Take;
+    use crate::color::ColorLabel;
+    use crate::repeats::{Ending, Jump, JumpEnd, JumpKind, Repeat, RepeatSign, RepeatStructure};
+    use crate::time::{AccentLevel, AccentPattern, Polyrhythm, TimeSignature};
+    use crate::validate::{CleanupReport, IssueKind, NoteMerge, RepairOptions, RepairReport, ValidationIssue};
+    use crate::view::{FollowMode, PitchView, ViewState};
+    use crate::settings::{AppSettings, AudioSettings, MetronomeSettings, WatchFolderSettings};
+    use crate::startup::{startup_diagnostics, StartupSentinel, StartupStage};
+    use crate::watchdog::{AudioFault, AudioWatchdog};
+    use pretty_assertions::assert_eq;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    // Body of a declaration, without comments
+    fn ts_declaration(head: &str) -> Option<String> {
+        let start = PAYLOAD_TYPES.find(head)? + head.len();
+        let body = &PAYLOAD_TYPES[start..];
+        let body = &body[..body.find("\n\n").unwrap_or(body.len())];
+        let lines = body.lines().map(|line| line.split("//").next().unwrap().trim());
+        Some(lines.filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n"))
+    }
+
+    // Fields of `export interface <name>` as (name, optional, type)
+    fn ts_interface(name: &str) -> Option<Vec<(String, bool, String)>> {
+        let body = ts_declaration(&format!("export interface {} {{", name))?;
+        let body = &body[..body.find('}').unwrap()];
+        Some(body.lines().map(|line| parse_field(line.trim_end_matches(';'))).collect())
+    }
+
+    // "name?: type" as (name, optional, type)
+    fn parse_field(field: &str) -> (String, bool, String) {
+        let (name, ty) = field.split_once(':').unwrap_or_else(|| panic!("Bad TypeScript field {}", field));
+        let optional = name.trim().ends_with('?');
+        (name.trim().trim_end_matches('?').to_string(), optional, ty.trim().to_string())
+    }
+
+    // Members of `export type <name> = ...`, e.g. "'a'", "Note", or "{ mode: 'auto'; ... }"
+    fn ts_alternatives(name: &str) -> Option<Vec<String>> {
+        let body = ts_declaration(&format!("export type {} =", name))?;
+        Some(body.split('|').map(str::trim).filter(|alt| !alt.is_empty()).map(str::to_string).collect())
+    }
+
+    // String members of `export type <name> = 'a' | 'b'`
+    fn ts_union(name: &str) -> Vec<String> {
+        let alternatives = ts_alternatives(name).unwrap_or_else(|| panic!("No TypeScript type {}", name));
+        alternatives.iter().map(|alt| alt.trim_matches('\'').to_string()).collect()
+    }
+
+    fn json(value: &impl Serialize) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    // Why `value` doesn't fit the TypeScript type `ty`, if it doesn't
+    fn type_mismatch(ty: &str, value: &Value) -> Option<String> {
+        let alternatives: Vec<&str> = ty.split(" | ").collect();
+        if alternatives.len() > 1 {
+            return match alternatives.iter().any(|alt| type_mismatch(alt, value).is_none()) {
+                true => None,
+                false => Some(format!("{} is not a {}", value, ty)),
+            };
+        }
+        let fits = match ty {
+            // Outcome<T>'s result
+            "T" => true,
+            "null" => value.is_null(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            _ if ty.starts_with('\'') => value.as_str() == Some(ty.trim_matches('\'')),
+            _ if ty.ends_with("[]") => {
+                let Some(items) = value.as_array() else { return Some(format!("{} is not a {}", value, ty)) };
+                return items.iter().find_map(|item| type_mismatch(&ty[..ty.len() - 2], item));
+            }
+            _ if ty.starts_with('[') => {
+                let types: Vec<&str> = ty[1..ty.len() - 1].split(", ").collect();
+                let Some(items) = value.as_array().filter(|items| items.len() == types.len()) else {
+                    return Some(format!("{} is not a {}", value, ty));
+                };
+                return types.iter().zip(items).find_map(|(ty, item)| type_mismatch(ty, item));
+            }
+            _ if ty.starts_with('{') => {
+                let fields = ty[1..ty.len() - 1].split(';').map(|field| parse_field(field.trim())).collect();
+                return object_mismatch(ty, &fields, value, false);
+            }
+            _ => {
+                if let Some(fields) = ts_interface(ty) {
+                    return object_mismatch(ty, &fields, value, false);
+                }
+                let alternatives = ts_alternatives(ty).unwrap_or_else(|| panic!("No TypeScript type {}", ty));
+                alternatives.iter().any(|alt| type_mismatch(alt, value).is_none())
+            }
+        };
+        (!fits).then(|| format!("{} is not a {}", value, ty))
+    }
+
+    // Why an object doesn't fit an interface: a key it doesn't declare, a
+    // required field missing (any field, for a `complete` example), or a value
+    // of the wrong type
+    fn object_mismatch(name: &str, fields: &Vec<(String, bool, String)>, value: &Value, complete: bool) -> Option<String> {
+        let Some(object) = value.as_object() else { return Some(format!("{} is not a {}", value, name)) };
+        if let Some(key) = object.keys().find(|key| !fields.iter().any(|(field, _, _)| field == *key)) {
+            return Some(format!("{} has no field {}", name, key));
+        }
+        for (field, optional, ty) in fields {
+            match object.get(field) {
+                None if complete => return Some(format!("{}.{} is not serialized", name, field)),
+                None if !optional => return Some(format!("{}.{} is missing but not optional", name, field)),
+                None => {}
+                Some(value) => {
+                    if let Some(mismatch) = type_mismatch(ty, value) {
+                        return Some(format!("{}.{}: {}", name, field, mismatch));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // Check an example against `export interface <name>`; `complete` examples
+    // set every optional field, so a declared field Rust no longer has shows up
+    fn check_interface(name: &str, value: &Value, complete: bool) {
+        let fields = ts_interface(name).unwrap_or_else(|| panic!("No TypeScript interface {}", name));
+        if let Some(mismatch) = object_mismatch(name, &fields, value, complete) {
+            panic!("TypeScript interface {} is out of date: {}", name, mismatch);
+        }
+    }
+
+    // Check that every example fits `export type <name>`, and every member of it has an example
+    fn check_union(name: &str, values: &[Value]) {
+        let alternatives = ts_alternatives(name).unwrap_or_else(|| panic!("No TypeScript type {}", name));
+        for value in values {
+            assert!(
+                alternatives.iter().any(|alt| type_mismatch(alt, value).is_none()),
+                "TypeScript type {} has no member for {}",
+                name,
+                value
+            );
+        }
+        for alt in &alternatives {
+            assert!(
+                values.iter().any(|value| type_mismatch(alt, value).is_none()),
+                "TypeScript type {} declares {}, which Rust no longer produces",
+                name,
+                alt
+            );
+        }
+    }
+
+    #[test]
+    fn test_interfaces_match_serde() {
+        let song = Song::new();
+        let chance_note = Note { take: Some(Take::A), color: Some(ColorLabel::Red), ..Note::new(60, 0, 480) };
+        let view = ViewState {
+            selected_tab: Some("roll".to_string()),
+            pixels_per_tick: Some(0.1),
+            grid_division: Some(4),
+            loop_region: Some((0, 1920)),
+            selected_track: Some(0),
+            pitch_view: Some(PitchView::default()),
+            ..ViewState::default()
+        };
+        let issue = ValidationIssue {
+            kind: IssueKind::ZeroDuration,
+            note_index: 0,
+            message: String::new(),
+        };
+        let macro_step = MacroStep::new("cleanup.normalizeVelocities", &["40-110"]);
+        let saved_macro = Macro { name: "Tidy".to_string(), shortcut: Some("Mod+Alt+T".to_string()), steps: vec![macro_step.clone()] };
+
+        let grammar = get_notation_grammar();
+        // First entry of each theory topic, keyed by its JSON object
+        let theory_entry = |topic: TheoryTopic, at: usize| {
+            let json = serde_json::to_value(get_theory_reference(topic)).unwrap();
+            json["entries"][at].clone()
+        };
+
+        let mut practice_song = Song::new();
+        practice_song.add_note(Note::new(60, 0, 480));
+        let practice = CallResponse::new(&practice_song, &CallResponseConfig::default()).unwrap();
+        let feedback = NoteFeedback {
+            index: 0,
+            expected_pitch: 60,
+            played_pitch: Some(60),
+            timing_offset_ticks: Some(0),
+            verdict: NoteVerdict::Correct,
+        };
+        let suggestion = suggest_next_chords(&[], &song.settings.key).unwrap().remove(0);
+        let mut harmony_song = Song::new();
+        harmony_song.add_note(Note::new(60, 0, 1920));
+        let reharmonization =
+            reharmonize_measure(&harmony_song, 1, &ReharmonizeConstraints::default()).unwrap().remove(0);
+        // A pivot-chord route, whose pivot has a numeral in the new key
+        let g_major = Scale::new(PitchClass::G, ScaleType::Major);
+        let modulation = plan_modulation(&Scale::c_major(), &g_major, 1).unwrap().remove(0);
+        // Two notes a step apart force a relaxation
+        let mut cantus_song = Song::new();
+        cantus_song.add_notes([Note::new(60, 0, 1920), Note::new(62, 1920, 1920)]);
+        let counterpoint = write_counterpoint(&mut cantus_song, &CounterpointConfig::default()).unwrap();
+        let melody_config = MelodyConfig { chart: "| C |".to_string(), ..MelodyConfig::default() };
+        let melody = generate_melody(&mut Song::new(), &melody_config).unwrap();
+        let continuation = continue_melody(&practice_song, &MelodyModel::bundled(), &ContinuationConfig::default()).unwrap();
+        let mut scale_song = Song::new();
+        scale_song.add_notes((0..5u32).map(|i| Note::new(60 + i as u8, i * 480, 480)));
+        let similarity = similarity(&scale_song, &scale_song).unwrap();
+        let comparison = compare_versions(&scale_song, &song, ComparisonMode::Alternate);
+        // A slash chord, which has a bass
+        let chart = parse_chord_chart(&song, "| C/E |").unwrap();
+        let histogram = pitch_histogram(&practice_song, 480).unwrap();
+        let groove = extract_groove(&practice_song.notes, 480, 2).unwrap();
+        let variations = generate_variations(&practice_song.notes, &Scale::c_major(), 1, &VariationParams::default()).unwrap();
+        // Every mark placed, so the optional fields serialize
+        let mut repeats = RepeatStructure::default();
+        repeats.add_repeat(Repeat::parse("1-2").unwrap()).unwrap();
+        repeats.add_ending(Ending::parse("2 1").unwrap()).unwrap();
+        for (sign, measure) in [(RepeatSign::Segno, 1), (RepeatSign::Fine, 2), (RepeatSign::ToCoda, 3), (RepeatSign::Coda, 5)] {
+            repeats.place_sign(sign, measure).unwrap();
+        }
+        repeats.set_jump(Some(Jump::parse("D.S. al Coda 4").unwrap())).unwrap();
+        // With a pulse grouping, so it serializes
+        let mut felt_in_two = TimeSignature::parse("6/8").unwrap();
+        felt_in_two.set_pulse_grouping(&[3, 3]).unwrap();
+
+        let cases: Vec<(&str, Value)> = vec![
+            ("Note", json(&chance_note)),
+            ("IndexedNote", json(&IndexedNote { index: 0, note: chance_note.clone() })),
+            ("Click", json(&TimeSignature::common().measure_clicks(120)[0])),
+            ("CapturedKey", json(&CapturedKey { pitch: 60, velocity: 100 })),
+            ("AccentPreset", json(&builtin_presets()[0])),
+            ("GroovePreset", json(&groove_presets()[0])),
+            ("SplitPiece", json(&plan_split(&practice_song, &[]).unwrap()[0])),
+            ("Polyrhythm", json(&Polyrhythm::new(3, 2).unwrap())),
+            ("AccentPattern", json(&AccentPattern::default_for_beats(4))),
+            ("TimeSignature", json(&felt_in_two)),
+            ("VoiceMeter", json(&VoiceMeter { voice: 1, time_signature: felt_in_two.clone() })),
+            ("Track", json(&Track { instrument: Some(Instrument::Piano), ..Track::new(1, "Bassline") })),
+            ("Marker", json(&Marker { name: "A".to_string(), tick: 0, color: Some(ColorLabel::Blue) })),
+            ("Lyric", json(&Lyric { tick: 0, text: "La".to_string(), new_line: true })),
+            ("Annotation", json(&Annotation { tick: 0, end_tick: Some(480), text: "breathe".to_string() })),
+            ("Repeat", json(&repeats.repeats[0])),
+            ("Ending", json(&repeats.endings[0])),
+            ("Jump", json(&repeats.jump.unwrap())),
+            ("RepeatStructure", json(&repeats)),
+            ("MelodyToken", json(&parse_melody_token("C4q").unwrap())),
+            ("AudioDiagnostics", json(&AudioWatchdog::new().device_lost(0.0, "running"))),
+            ("StartupSentinel", json(&StartupSentinel::launch(None, 0.0))),
+            ("StartupDiagnostics", json(&startup_diagnostics(None))),
+            ("TokenSuggestion", json(&suggest_next_tokens("", &Scale::c_major()).unwrap()[0])),
+            ("ValidationIssue", json(&issue)),
+            ("RepairOptions", json(&RepairOptions::default())),
+            ("RepairReport", json(&RepairReport::default())),
+            ("CleanupReport", json(&CleanupReport::default())),
+            ("PitchRange", json(&PitchRange::preset("Violin").unwrap())),
+            ("RangeWarning", json(&RangeWarning { note_index: 0, pitch: 0, message: String::new() })),
+            ("FoldedNote", json(&FoldedNote { note_index: 0, from: 86, to: 74 })),
+            ("NoteMerge", json(&NoteMerge { removed: 1, kept: 0 })),
+            ("LossSummary", json(&LossSummary::default())),
+            ("ViewState", json(&view)),
+            ("SongInfo", json(&song.info())),
+            ("SongDefaults", json(&SongDefaults::default())),
+            ("CommandInfo", json(find_command("file.save").unwrap())),
+            ("CommandMatch", json(&search_commands("save")[0])),
+            ("MacroStep", json(&macro_step)),
+            ("Macro", json(&saved_macro)),
+            ("MacroLibrary", json(&MacroLibrary { macros: vec![saved_macro.clone()] })),
+            ("PresetLibrary", json(&PresetLibrary::new())),
+            ("AppSettings", json(&AppSettings::default())),
+            ("AudioSettings", json(&AudioSettings::default())),
+            ("MetronomeSettings", json(&MetronomeSettings::default())),
+            ("WatchFolderSettings", json(&WatchFolderSettings::default())),
+            ("TutorialState", json(&Tutorial::new(&song).state(&song))),
+            ("IntervalInfo", json(&theory_entry(TheoryTopic::Intervals, 0))),
+            ("ScaleInfo", json(&theory_entry(TheoryTopic::Scales, 0))),
+            ("ChordInfo", json(&theory_entry(TheoryTopic::Chords, 0))),
+            ("AuditionNote", json(&scale_audition(PitchClass::C, ScaleType::Major, ScaleDirection::Both, 120)[0])),
+            // F#, which has an enharmonic spelling
+            ("KeyInfo", json(&theory_entry(TheoryTopic::CircleOfFifths, 6))),
+            ("CadenceInfo", json(&theory_entry(TheoryTopic::Cadences, 0))),
+            ("NotationGrammar", json(&grammar)),
+            ("TokenRule", json(&grammar.tokens[0])),
+            ("Accidental", json(&grammar.accidentals[0])),
+            ("DurationRule", json(&grammar.durations[0])),
+            ("OctaveRange", json(&grammar.octaves)),
+            ("CallResponseConfig", json(&CallResponseConfig::default())),
+            ("CallResponseInfo", json(&practice.info())),
+            ("NoteFeedback", json(&feedback)),
+            ("CallResponseResult", json(&practice.score())),
+            ("KeyChord", json(&suggestion.chord)),
+            ("DiatonicChord", json(&diatonic_chords(&Scale::c_major())[0])),
+            ("ChordSuggestion", json(&suggestion)),
+            ("ReharmonizeConstraints", json(&ReharmonizeConstraints::default())),
+            ("HarmonizedSpan", json(&reharmonization.chords[0])),
+            ("Reharmonization", json(&reharmonization)),
+            ("RouteChord", json(&modulation.chords[1])),
+            ("ModulationPlan", json(&modulation)),
+            ("CounterpointConfig", json(&CounterpointConfig::default())),
+            ("Relaxation", json(&counterpoint.relaxations[0])),
+            ("CounterpointReport", json(&counterpoint)),
+            ("MelodyConfig", json(&MelodyConfig::default())),
+            ("MelodyReport", json(&melody)),
+            ("ContinuationConfig", json(&ContinuationConfig::default())),
+            ("Continuation", json(&continuation)),
+            ("SimilarityReport", json(&similarity)),
+            ("MatchedRegion", json(similarity.region.as_ref().unwrap())),
+            ("Comparison", json(&comparison)),
+            ("ComparisonSegment", json(&comparison.segments[0])),
+            ("ChartChord", json(&chart[0])),
+            ("Chord", json(&Chord::parse("C7/E").unwrap())),
+            ("ChordSymbol", json(&ChordSymbol { tick: 0, symbol: "C".to_string() })),
+            ("Pattern", json(&Pattern { name: "A".to_string(), length_ticks: 1920, notes: Vec::new() })),
+            ("PitchHistogram", json(&histogram)),
+            ("HistogramBucket", json(&histogram.buckets[0])),
+            ("GrooveTemplate", json(&groove)),
+            ("GrooveSlot", json(&groove.slots[0])),
+            ("VariationParams", json(&VariationParams::default())),
+            ("Variation", json(&variations[0])),
+        ];
+        for (name, value) in cases {
+            check_interface(name, &value, true);
+        }
+
+        // Optional fields left unset, so a field that stops being serialized shows up
+        let sparse: Vec<(&str, Value)> = vec![
+            ("Note", json(&Note::new(60, 0, 480))),
+            ("TimeSignature", json(&TimeSignature::common())),
+            ("Track", json(&Track::new(1, "Bassline"))),
+            ("Marker", json(&Marker { name: "A".to_string(), tick: 0, color: None })),
+            ("Annotation", json(&Annotation { tick: 0, end_tick: None, text: "breathe".to_string() })),
+            ("RepeatStructure", json(&RepeatStructure::default())),
+            ("ViewState", json(&ViewState::default())),
+            ("SongInfo", json(&Song::new().info())),
+            ("SimilarityReport", json(&SimilarityReport { region: None, ..similarity.clone() })),
+        ];
+        for (name, value) in sparse {
+            check_interface(name, &value, false);
+        }
+    }
+
+    #[test]
+    fn test_change_events_match_serde() {
+        let changes = [
+            ("NotesAdded", SongChange::NotesAdded { index: 0, notes: Vec::new() }),
+            ("NotesRemoved", SongChange::NotesRemoved { index: 0, count: 1 }),
+            ("NotesUpdated", SongChange::NotesUpdated { index: 0, notes: Vec::new() }),
+            ("NotesDirty", SongChange::NotesDirty { start_tick: 0, end_tick: 480 }),
+            ("TempoChanged", SongChange::Tempo { tempo: 120 }),
+            (
+                "TimeSignatureChanged",
+                SongChange::TimeSignature {
+                    numerator: 4,
+                    denominator: 4,
+                    accents: Vec::new(),
+                    groupings: Vec::new(),
+                    pulse_grouping: Vec::new(),
+                },
+            ),
+            ("VoiceMetersChanged", SongChange::VoiceMeters { voice_meters: Vec::new() }),
+            ("TracksChanged", SongChange::Tracks { tracks: Vec::new() }),
+            ("KeyChanged", SongChange::Key { key: String::new() }),
+            ("AccentDynamicsChanged", SongChange::AccentDynamics { enabled: true }),
+            ("RhythmPitchChanged", SongChange::RhythmPitch { pitch: None }),
+            ("PitchRangeChanged", SongChange::PitchRange { range: None }),
+            ("InstrumentChanged", SongChange::Instrument { instrument: Instrument::Piano }),
+            ("NotesOutOfRange", SongChange::OutOfRange { warnings: Vec::new() }),
+            ("MetadataChanged", SongChange::Metadata { title: String::new(), composer: String::new() }),
+            ("MarkersChanged", SongChange::Markers { markers: Vec::new() }),
+            ("LyricsChanged", SongChange::Lyrics { lyrics: Vec::new() }),
+            ("ChordsChanged", SongChange::Chords { chords: Vec::new() }),
+            ("PatternsChanged", SongChange::Patterns { patterns: Vec::new(), pattern_chain: Vec::new() }),
+            ("AnnotationsChanged", SongChange::Annotations { annotations: Vec::new() }),
+            ("RepeatsChanged", SongChange::Repeats { repeats: RepeatStructure::default() }),
+            ("PlaybackChanged", SongChange::Playback),
+            ("SongReplaced", SongChange::Replaced),
+        ];
+        for (name, change) in &changes {
+            let value = json(change);
+            check_interface(name, &value, true);
+            assert_eq!(change.name(), value["type"]);
+        }
+        check_union("SongChange", &changes.iter().map(|(_, change)| json(change)).collect::<Vec<_>>());
+    }
+
+    fn tags<T: Serialize>(values: &[T]) -> Vec<Value> {
+        values.iter().map(json).collect()
+    }
+
+    #[test]
+    fn test_unions_match_rust() {
+        let result = CallResponseResult {
+            notes: Vec::new(),
+            extra_notes: 0,
+            pitch_accuracy: 0.0,
+            rhythm_accuracy: 0.0,
+            score: 0.0,
+        };
+        let unions: Vec<(&str, Vec<Value>)> = vec![
+            (
+                "IssueKind",
+                tags(&[
+                    IssueKind::OverlappingNote,
+                    IssueKind::ZeroDuration,
+                    IssueKind::PitchOutOfRange,
+                    IssueKind::ZeroVelocity,
+                    IssueKind::StrayNote,
+                ]),
+            ),
+            ("StrumDirection", tags(&[StrumDirection::Up, StrumDirection::Down])),
+            (
+                "ChordQuality",
+                tags(&[
+                    ChordQuality::Major,
+                    ChordQuality::Minor,
+                    ChordQuality::Diminished,
+                    ChordQuality::Augmented,
+                    ChordQuality::Sus2,
+                    ChordQuality::Sus4,
+                ]),
+            ),
+            ("TempoAlignment", tags(&[TempoAlignment::Align, TempoAlignment::KeepFirst])),
+            ("ComparisonMode", tags(&[ComparisonMode::Together, ComparisonMode::Alternate])),
+            (
+                "ComparedVersion",
+                tags(&[ComparedVersion::Same, ComparedVersion::Both, ComparedVersion::Current, ComparedVersion::Other]),
+            ),
+            ("Instrument", tags(Instrument::all())),
+            ("Take", tags(&[Take::A, Take::B])),
+            ("ColorLabel", tags(&ColorLabel::ALL)),
+            ("JumpKind", tags(&[JumpKind::DaCapo, JumpKind::DalSegno])),
+            ("JumpEnd", tags(&[JumpEnd::End, JumpEnd::Fine, JumpEnd::Coda])),
+            ("CompletionKind", tags(&[CompletionKind::Note, CompletionKind::Duration])),
+            ("AudioFault", tags(&[AudioFault::Stalled, AudioFault::Underrun, AudioFault::DeviceLost, AudioFault::Closed])),
+            ("StartupStage", tags(&[StartupStage::Launch, StartupStage::Audio, StartupStage::Song, StartupStage::Running])),
+            ("TutorialStep", tags(&TutorialStep::ALL)),
+            ("TheoryTopic", tags(&TheoryTopic::ALL)),
+            ("TheoryReference", TheoryTopic::ALL.iter().map(|&topic| json(&get_theory_reference(topic))).collect()),
+            ("TokenKind", tags(&[TokenKind::Note, TokenKind::Rest, TokenKind::RhythmHit])),
+            ("Consonance", tags(&[Consonance::Perfect, Consonance::Imperfect, Consonance::Dissonant])),
+            ("Difficulty", tags(&[Difficulty::Easy, Difficulty::Medium, Difficulty::Hard])),
+            ("CallResponsePhase", tags(&[CallResponsePhase::Call, CallResponsePhase::Response, CallResponsePhase::Finished])),
+            (
+                "NoteVerdict",
+                tags(&[NoteVerdict::Correct, NoteVerdict::WrongPitch, NoteVerdict::Early, NoteVerdict::Late, NoteVerdict::Missed]),
+            ),
+            ("HarmonicFunction", tags(&[HarmonicFunction::Tonic, HarmonicFunction::Predominant, HarmonicFunction::Dominant])),
+            ("ModulationKind", tags(&[ModulationKind::PivotChord, ModulationKind::CommonTone, ModulationKind::Direct])),
+            (
+                "CounterpointRule",
+                tags(&[
+                    CounterpointRule::Dissonance,
+                    CounterpointRule::ParallelPerfect,
+                    CounterpointRule::Unison,
+                    CounterpointRule::Opening,
+                    CounterpointRule::Cadence,
+                    CounterpointRule::Leap,
+                ]),
+            ),
+            ("VariationKind", tags(&VariationKind::ALL)),
+            ("ScaleDirection", tags(&[ScaleDirection::Ascending, ScaleDirection::Descending, ScaleDirection::Both])),
+            ("AccentLevel", tags(&[AccentLevel::Weak, AccentLevel::Medium, AccentLevel::Strong])),
+            (
+                "CallResponseEvent",
+                vec![
+                    json(&CallResponseEvent::Phase { phase: CallResponsePhase::Call }),
+                    json(&CallResponseEvent::Result { result }),
+                ],
+            ),
+            (
+                "Outcome<T>",
+                vec![
+                    json(&Outcome::Done { result: () }),
+                    json(&Outcome::<()>::NeedsConfirmation { summary: LossSummary::default() }),
+                ],
+            ),
+            ("PitchView", vec![json(&PitchView::default()), json(&PitchView::parse("C1-C7").unwrap())]),
+        ];
+        for (name, values) in unions {
+            check_union(name, &values);
+        }
+        assert!(matches!(get_theory_reference(TheoryTopic::Scales), TheoryReference::Scales(_)));
+
+        // Follow modes are passed as strings and parsed, not serialized
+        for mode in ts_union("FollowMode") {
+            assert!(FollowMode::parse(&mode).is_ok(), "FollowMode::parse rejects {}", mode);
+        }
+    }
+}
@@ -0,0 +1,561 @@
+//! Song validation and repair
+//!
+//! Finds note data that plays badly or not at all (stacked duplicates,
+//! silent notes, out-of-range pitches, stray notes far past the music) and
+//! fixes selected classes of problems. Also tames dynamics that are too wild
+//! to work with, as imported MIDI often has.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::song::Song;
+
+/// Class of problem found by [`validate_song`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Same pitch overlapping another note (only one can sound)
+    OverlappingNote,
+    /// Note with no length
+    ZeroDuration,
+    /// Pitch outside the MIDI range 0-127
+    PitchOutOfRange,
+    /// Velocity 0 (treated as note-off by MIDI devices)
+    ZeroVelocity,
+    /// Note separated from the rest of the song by a long empty stretch
+    StrayNote,
+}
+
+impl IssueKind {
+    /// Get short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            IssueKind::OverlappingNote => "overlapping note",
+            IssueKind::ZeroDuration => "zero duration",
+            IssueKind::PitchOutOfRange => "pitch out of range",
+            IssueKind::ZeroVelocity => "zero velocity",
+            IssueKind::StrayNote => "stray note",
+        }
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A single problem with a note
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub kind: IssueKind,
+    /// Index of the offending note in `song.notes`
+    pub note_index: usize,
+    pub message: String,
+}
+
+/// Which issue classes [`repair_song`] should fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepairOptions {
+    /// Remove stacked duplicates and trim same-pitch overlaps
+    pub overlapping: bool,
+    /// Remove zero-length notes
+    pub zero_duration: bool,
+    /// Clamp pitches into 0-127
+    pub pitch_range: bool,
+    /// Raise velocity 0 to [`RepairOptions::MIN_VELOCITY`]
+    pub zero_velocity: bool,
+    /// Remove stray notes
+    pub stray_notes: bool,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        RepairOptions {
+            overlapping: true,
+            zero_duration: true,
+            pitch_range: true,
+            zero_velocity: true,
+            stray_notes: false, // Destructive; opt in
+        }
+    }
+}
+
+impl RepairOptions {
+    /// Velocity given to notes repaired from velocity 0
+    pub const MIN_VELOCITY: u8 = 1;
+
+    fn fixes(&self, kind: IssueKind) -> bool {
+        match kind {
+            IssueKind::OverlappingNote => self.overlapping,
+            IssueKind::ZeroDuration => self.zero_duration,
+            IssueKind::PitchOutOfRange => self.pitch_range,
+            IssueKind::ZeroVelocity => self.zero_velocity,
+            IssueKind::StrayNote => self.stray_notes,
+        }
+    }
+}
+
+/// Summary of what [`repair_song`] changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairReport {
+    /// Notes deleted
+    pub removed: usize,
+    /// Notes modified in place
+    pub modified: usize,
+}
+
+/// Empty measures after which a note counts as stray
+pub const STRAY_GAP_MEASURES: u32 = 32;
+
+/// Check a song for note problems, ordered by note index
+pub fn validate_song(song: &Song) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let measure_ticks = song.settings.time_signature.ticks_per_measure();
+    let stray_gap = measure_ticks * STRAY_GAP_MEASURES;
+
+    // Indices ordered by start so overlap and gap checks are a single pass
+    let mut order: Vec<usize> = (0..song.notes.len()).collect();
+    order.sort_by_key(|&i| (song.notes[i].start_tick, i));
+    let mut stray = vec![false; song.notes.len()];
+    let mut overlaps: Vec<Option<usize>> = vec![None; song.notes.len()];
+    let mut music_end: Option<u32> = None;
+    // Latest-ending earlier note per pitch: (end tick, index)
+    let mut sounding: HashMap<u8, (u32, usize)> = HashMap::new();
+
+    for &i in &order {
+        let note = &song.notes[i];
+
+        if let Some(&(end, j)) = sounding.get(&note.pitch) {
+            if end > note.start_tick {
+                overlaps[i] = Some(j);
+            }
+        }
+        let latest = sounding.entry(note.pitch).or_insert((note.end_tick(), i));
+        if note.end_tick() > latest.0 {
+            *latest = (note.end_tick(), i);
+        }
+
+        if let Some(end) = music_end {
+            stray[i] = note.start_tick > end.saturating_add(stray_gap);
+        }
+        music_end = Some(music_end.map_or(note.end_tick(), |end| end.max(note.end_tick())));
+    }
+
+    for (i, note) in song.notes.iter().enumerate() {
+        if let Some(j) = overlaps[i] {
+            issues.push(ValidationIssue {
+                kind: IssueKind::OverlappingNote,
+                note_index: i,
+                message: format!("Note {} overlaps note {} with the same pitch ({})", i, j, note.pitch),
+            });
+        }
+        if note.duration_ticks == 0 {
+            issues.push(ValidationIssue {
+                kind: IssueKind::ZeroDuration,
+                note_index: i,
+                message: format!("Note {} has zero duration", i),
+            });
+        }
+        if note.pitch > 127 {
+            issues.push(ValidationIssue {
+                kind: IssueKind::PitchOutOfRange,
+                note_index: i,
+                message: format!("Note {} has pitch {} (MIDI range is 0-127)", i, note.pitch),
+            });
+        }
+        if note.velocity == 0 {
+            issues.push(ValidationIssue {
+                kind: IssueKind::ZeroVelocity,
+                note_index: i,
+                message: format!("Note {} has velocity 0 and will not sound", i),
+            });
+        }
+        if stray[i] {
+            issues.push(ValidationIssue {
+                kind: IssueKind::StrayNote,
+                note_index: i,
+                message: format!(
+                    "Note {} starts more than {} empty measures after the previous music",
+                    i, STRAY_GAP_MEASURES
+                ),
+            });
+        }
+    }
+
+    if !issues.is_empty() {
+        tracing::debug!("Validation found {} issues", issues.len());
+    }
+    issues
+}
+
+/// Fix the issue classes selected in `options`
+pub fn repair_song(song: &mut Song, options: &RepairOptions) -> RepairReport {
+    let mut report = RepairReport::default();
+    let issues = validate_song(song);
+    let mut remove = vec![false; song.notes.len()];
+
+    for issue in issues.iter().filter(|issue| options.fixes(issue.kind)) {
+        let i = issue.note_index;
+        match issue.kind {
+            IssueKind::ZeroDuration | IssueKind::StrayNote => remove[i] = true,
+            IssueKind::PitchOutOfRange => {
+                song.notes[i].pitch = song.notes[i].pitch.min(127);
+                report.modified += 1;
+            }
+            IssueKind::ZeroVelocity => {
+                song.notes[i].velocity = RepairOptions::MIN_VELOCITY;
+                report.modified += 1;
+            }
+            IssueKind::OverlappingNote => {}
+        }
+    }
+
+    if options.overlapping {
+        // Re-scan: fixing one overlap can resolve others on the same pitch
+        let mut order: Vec<usize> = (0..song.notes.len()).filter(|&i| !remove[i]).collect();
+        order.sort_by_key(|&i| (song.notes[i].start_tick, i));
+        let mut last_by_pitch: HashMap<u8, usize> = HashMap::new();
+
+        for i in order {
+            let (pitch, start) = (song.notes[i].pitch, song.notes[i].start_tick);
+            if let Some(&prev) = last_by_pitch.get(&pitch) {
+                if song.notes[prev].end_tick() > start {
+                    if song.notes[prev].start_tick == start {
+                        // Stacked duplicate: keep the longer of the two
+                        if song.notes[i].duration_ticks > song.notes[prev].duration_ticks {
+                            remove[prev] = true;
+                            last_by_pitch.insert(pitch, i);
+                        } else {
+                            remove[i] = true;
+                        }
+                        continue;
+                    }
+                    song.notes[prev].duration_ticks = start - song.notes[prev].start_tick;
+                    report.modified += 1;
+                }
+            }
+            last_by_pitch.insert(pitch, i);
+        }
+    }
+
+    let before = song.notes.len();
+    let mut index = 0;
+    song.notes.retain(|_| {
+        let keep = !remove[index];
+        index += 1;
+        keep
+    });
+    report.removed = before - song.notes.len();
+
+    if report.removed > 0 || report.modified > 0 {
+        tracing::info!("Repaired song: {} notes removed, {} modified", report.removed, report.modified);
+        song.update_modified();
+    }
+    report
+}
+
+/// Starts this close together count as the same note struck twice (a 64th note)
+pub const MERGE_TOLERANCE_TICKS: u32 = 30;
+
+/// A near-identical note folded into the one it overlapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteMerge {
+    pub removed: usize,
+    /// Extended to cover the removed note, at the louder velocity
+    pub kept: usize,
+}
+
+/// What [`cleanup_song`] removed, or would remove on a dry run
+///
+/// Indices are into `song.notes` as it was before the cleanup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CleanupReport {
+    /// Notes identical to an earlier one
+    pub duplicates: Vec<usize>,
+    pub merged: Vec<NoteMerge>,
+    pub zero_length: Vec<usize>,
+    pub dry_run: bool,
+}
+
+impl CleanupReport {
+    /// Notes removed in total
+    pub fn removed(&self) -> usize {
+        self.duplicates.len() + self.merged.len() + self.zero_length.len()
+    }
+}
+
+/// Remove exact duplicates and zero-length notes, and merge near-identical overlaps
+///
+/// Same-voice notes of the same pitch that start within
+/// [`MERGE_TOLERANCE_TICKS`] of each other and overlap become one note
+/// spanning both. With `dry_run` the song is left unchanged and the report
+/// says what would happen.
+pub fn cleanup_song(song: &mut Song, dry_run: bool) -> CleanupReport {
+    let mut report = CleanupReport { dry_run, ..CleanupReport::default() };
+    let mut notes = song.notes.clone();
+    let mut remove = vec![false; notes.len()];
+
+    let mut order: Vec<usize> = (0..notes.len()).collect();
+    order.sort_by_key(|&i| (notes[i].voice, notes[i].pitch, notes[i].start_tick, i));
+    let mut kept: Option<usize> = None;
+    for i in order {
+        if notes[i].duration_ticks == 0 {
+            report.zero_length.push(i);
+            remove[i] = true;
+            continue;
+        }
+        let Some(k) = kept.filter(|&k| (notes[k].voice, notes[k].pitch) == (notes[i].voice, notes[i].pitch)) else {
+            kept = Some(i);
+            continue;
+        };
+
+        let near = notes[i].start_tick - notes[k].start_tick <= MERGE_TOLERANCE_TICKS;
+        if notes[i] == notes[k] {
+            report.duplicates.push(i);
+        } else if near && notes[i].start_tick < notes[k].end_tick() {
+            let end = notes[k].end_tick().max(notes[i].end_tick());
+            notes[k].duration_ticks = end - notes[k].start_tick;
+            notes[k].velocity = notes[k].velocity.max(notes[i].velocity);
+            report.merged.push(NoteMerge { removed: i, kept: k });
+        } else {
+            kept = Some(i);
+            continue;
+        }
+        remove[i] = true;
+    }
+    report.duplicates.sort_unstable();
+    report.merged.sort_by_key(|m| m.removed);
+    report.zero_length.sort_unstable();
+
+    if !dry_run && report.removed() > 0 {
+        let mut index = 0;
+        notes.retain(|_| {
+            let keep = !remove[index];
+            index += 1;
+            keep
+        });
+        song.notes = notes;
+        song.update_modified();
+        tracing::info!(
+            "Cleaned up song: {} duplicates, {} merged, {} zero-length",
+            report.duplicates.len(),
+            report.merged.len(),
+            report.zero_length.len()
+        );
+    }
+    report
+}
+
+/// Rescale velocities so the quietest note plays at `target_min` and the loudest at `target_max`
+///
+/// Relative dynamics in between are kept; if every note has the same
+/// velocity, all land on the middle of the range. Returns the number of
+/// notes changed.
+pub fn normalize_velocities(song: &mut Song, target_min: u8, target_max: u8) -> Result<usize> {
+    if target_min == 0 || target_min > target_max || target_max > 127 {
+        return Err(MozartError::InvalidEdit(format!(
+            "Velocity range must be within 1-127, got {}-{}",
+            target_min, target_max
+        )));
+    }
+    let (Some(low), Some(high)) = (
+        song.notes.iter().map(|n| n.velocity).min(),
+        song.notes.iter().map(|n| n.velocity).max(),
+    ) else {
+        return Ok(0);
+    };
+
+    let target = (target_max - target_min) as f64;
+    let changed = set_velocities(song, |v| {
+        if high == low {
+            (target_min as f64 + target / 2.0).round() as u8
+        } else {
+            (target_min as f64 + (v - low) as f64 * target / (high - low) as f64).round() as u8
+        }
+    });
+    tracing::info!("Normalized {} velocities to {}-{}", changed, target_min, target_max);
+    Ok(changed)
+}
+
+/// Reduce the amount velocities exceed `threshold` by `ratio`
+///
+/// With a ratio of 2, a note 20 above the threshold ends up 10 above it.
+/// Notes at or below the threshold are left alone. Returns the number of
+/// notes changed.
+pub fn compress_velocities(song: &mut Song, ratio: f32, threshold: u8) -> Result<usize> {
+    if ratio.is_nan() || ratio < 1.0 {
+        return Err(MozartError::InvalidEdit(format!("Compression ratio must be at least 1, got {}", ratio)));
+    }
+    let changed = set_velocities(song, |v| {
+        if v <= threshold {
+            v
+        } else {
+            (threshold as f32 + (v - threshold) as f32 / ratio).round() as u8
+        }
+    });
+    tracing::info!("Compressed {} velocities above {} at {}:1", changed, threshold, ratio);
+    Ok(changed)
+}
+
+// Map every note's velocity, returning how many changed
+fn set_velocities(song: &mut Song, map: impl Fn(u8) -> u8) -> usize {
+    let mut changed = 0;
+    for note in &mut song.notes {
+        let velocity = map(note.velocity);
+        if velocity != note.velocity {
+            note.velocity = velocity;
+            changed += 1;
+        }
+    }
+    if changed > 0 {
+        song.update_modified();
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::Note;
+    use pretty_assertions::assert_eq;
+
+    fn kinds(song: &Song) -> Vec<(IssueKind, usize)> {
+        validate_song(song).into_iter().map(|i| (i.kind, i.note_index)).collect()
+    }
+
+    #[test]
+    fn test_clean_song_has_no_issues() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(60, 480, 480));
+        song.add_note(Note::new(64, 0, 960));
+        assert!(validate_song(&song).is_empty());
+    }
+
+    #[test]
+    fn test_validate_finds_issues() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(60, 240, 480)); // Overlaps note 0
+        song.add_note(Note::new(62, 960, 0));
+        song.add_note(Note::with_velocity(64, 1440, 480, 0));
+        song.add_note(Note::new(65, 1920 * 40, 480)); // Far past everything
+        song.notes.push(Note::new(200, 1920, 480));
+
+        assert_eq!(
+            kinds(&song),
+            vec![
+                (IssueKind::OverlappingNote, 1),
+                (IssueKind::ZeroDuration, 2),
+                (IssueKind::ZeroVelocity, 3),
+                (IssueKind::StrayNote, 4),
+                (IssueKind::PitchOutOfRange, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repair_overlaps() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 480));
+        song.add_note(Note::new(60, 0, 960)); // Stacked duplicate, longer
+        song.add_note(Note::new(62, 0, 960));
+        song.add_note(Note::new(62, 480, 480)); // Overlaps the tail
+
+        let report = repair_song(&mut song, &RepairOptions::default());
+        assert_eq!(report, RepairReport { removed: 1, modified: 1 });
+        assert!(validate_song(&song).is_empty());
+
+        let durations: Vec<(u8, u32, u32)> =
+            song.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(durations, vec![(60, 0, 960), (62, 0, 480), (62, 480, 480)]);
+    }
+
+    #[test]
+    fn test_repair_respects_options() {
+        let mut song = Song::new();
+        song.add_note(Note::new(60, 0, 0));
+        song.add_note(Note::with_velocity(62, 480, 480, 0));
+        song.add_note(Note::new(64, 1920 * 40, 480));
+
+        // Stray notes are only removed when asked for
+        let report = repair_song(&mut song, &RepairOptions::default());
+        assert_eq!(report, RepairReport { removed: 1, modified: 1 });
+        assert_eq!(song.notes[0].velocity, RepairOptions::MIN_VELOCITY);
+        assert_eq!(kinds(&song), vec![(IssueKind::StrayNote, 1)]);
+
+        let options = RepairOptions { stray_notes: true, ..RepairOptions::default() };
+        repair_song(&mut song, &options);
+        assert_eq!(song.notes.len(), 1);
+    }
+
+    fn velocities(song: &Song) -> Vec<u8> {
+        song.notes.iter().map(|n| n.velocity).collect()
+    }
+
+    #[test]
+    fn test_normalize_velocities() {
+        let mut song = Song::new();
+        for (i, velocity) in [5, 65, 125].into_iter().enumerate() {
+            song.add_note(Note::with_velocity(60, i as u32 * 480, 480, velocity));
+        }
+
+        assert_eq!(normalize_velocities(&mut song, 40, 100).unwrap(), 3);
+        assert_eq!(velocities(&song), vec![40, 70, 100]);
+        // Already in range
+        assert_eq!(normalize_velocities(&mut song, 40, 100).unwrap(), 0);
+
+        assert!(normalize_velocities(&mut song, 0, 100).is_err());
+        assert!(normalize_velocities(&mut song, 100, 40).is_err());
+
+        let mut flat = Song::new();
+        flat.add_note(Note::with_velocity(60, 0, 480, 10));
+        normalize_velocities(&mut flat, 60, 80).unwrap();
+        assert_eq!(velocities(&flat), vec![70]);
+    }
+
+    #[test]
+    fn test_compress_velocities() {
+        let mut song = Song::new();
+        for (i, velocity) in [50, 90, 110, 127].into_iter().enumerate() {
+            song.add_note(Note::with_velocity(60, i as u32 * 480, 480, velocity));
+        }
+
+        assert_eq!(compress_velocities(&mut song, 2.0, 90).unwrap(), 2);
+        assert_eq!(velocities(&song), vec![50, 90, 100, 109]);
+
+        assert!(compress_velocities(&mut song, 0.5, 90).is_err());
+        assert!(compress_velocities(&mut song, f32::NAN, 90).is_err());
+    }
+
+    #[test]
+    fn test_cleanup_song() {
+        let mut harmony = Note::new(60, 0, 480);
+        harmony.voice = 1;
+        let mut song = Song::new();
+        song.notes = vec![
+            Note::new(60, 0, 480),
+            Note::new(60, 0, 480), // Exact duplicate
+            Note::with_velocity(60, 10, 600, 110), // Struck again 10 ticks later
+            Note::new(62, 480, 0),
+            Note::new(60, 480, 480), // Overlaps, but a separate note
+            harmony,
+        ];
+
+        let preview = cleanup_song(&mut song, true);
+        assert_eq!(preview.duplicates, vec![1]);
+        assert_eq!(preview.merged, vec![NoteMerge { removed: 2, kept: 0 }]);
+        assert_eq!(preview.zero_length, vec![3]);
+        assert_eq!(song.notes.len(), 6);
+
+        let report = cleanup_song(&mut song, false);
+        assert_eq!(report, CleanupReport { dry_run: false, ..preview });
+        let notes: Vec<(u8, u32, u32, u8)> =
+            song.notes.iter().map(|n| (n.pitch, n.start_tick, n.duration_ticks, n.velocity)).collect();
+        assert_eq!(notes, vec![(60, 0, 610, 110), (60, 480, 480, 100), (60, 0, 480, 100)]);
+        assert_eq!(cleanup_song(&mut song, false).removed(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+//! Phrase variations
+//!
+//! Candidate rewrites of a phrase for brainstorming: each variation changes
+//! some of the phrase's notes in one way (anticipating them off the beat,
+//! ornamenting them with a neighbor tone, or moving them an octave), and
+//! nothing is written until one is chosen with [`apply_variation`]. The
+//! candidates are seeded, so the same phrase and parameters always give the
+//! same set.
+
+use serde::{Deserialize, Serialize};
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::transpose::{transpose_note, TransposeMode};
+use crate::worksheet::Rng;
+
+/// Shortest note that is split to fit a neighbor tone (an eighth)
+const MIN_NEIGHBOR_TICKS: u32 = 240;
+
+/// How a variation rewrites the notes it picks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariationKind {
+    /// Start early by `displacement_ticks`, held to the old end
+    RhythmicDisplacement,
+    /// Step to the scale tone above or below and back, within the note
+    NeighborTones,
+    /// Up or down an octave
+    OctaveDisplacement,
+}
+
+impl VariationKind {
+    pub const ALL: [VariationKind; 3] = [
+        VariationKind::RhythmicDisplacement,
+        VariationKind::NeighborTones,
+        VariationKind::OctaveDisplacement,
+    ];
+
+    /// Parse "rhythm", "neighbor", or "octave" (or the full snake_case names)
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "rhythm" | "rhythmic_displacement" => Ok(VariationKind::RhythmicDisplacement),
+            "neighbor" | "neighbour" | "neighbor_tones" => Ok(VariationKind::NeighborTones),
+            "octave" | "octave_displacement" => Ok(VariationKind::OctaveDisplacement),
+            _ => Err(MozartError::ParseError(format!("Unknown variation kind: {}", s))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariationKind::RhythmicDisplacement => "Rhythmic displacement",
+            VariationKind::NeighborTones => "Neighbor tones",
+            VariationKind::OctaveDisplacement => "Octave displacement",
+        }
+    }
+}
+
+/// What to vary and how much
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VariationParams {
+    /// Used in rotation, one per variation
+    pub kinds: Vec<VariationKind>,
+    /// Chance that each note is changed, in percent (at least one always is)
+    pub note_percent: u8,
+    /// How early displaced notes start
+    pub displacement_ticks: u32,
+    /// Same seed, same variations
+    pub seed: u64,
+}
+
+impl Default for VariationParams {
+    fn default() -> Self {
+        VariationParams {
+            kinds: VariationKind::ALL.to_vec(),
+            note_percent: 40,
+            displacement_ticks: 240,
+            seed: 1,
+        }
+    }
+}
+
+/// One candidate rewrite of a phrase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variation {
+    pub kind: VariationKind,
+    /// e.g., "Octave displacement: 2 notes"
+    pub description: String,
+    /// The whole phrase as varied, ordered by start
+    pub notes: Vec<Note>,
+}
+
+/// `count` candidate variations of a phrase in `key`
+pub fn generate_variations(notes: &[Note], key: &Scale, count: usize, params: &VariationParams) -> Result<Vec<Variation>> {
+    if notes.is_empty() {
+        return Err(MozartError::InvalidEdit("Select a phrase to vary".to_string()));
+    }
+    if params.kinds.is_empty() {
+        return Err(MozartError::InvalidEdit("No variation kinds to use".to_string()));
+    }
+
+    let mut phrase = notes.to_vec();
+    phrase.sort_by_key(|n| (n.start_tick, n.pitch));
+    let mut rng = Rng(params.seed);
+    let variations = (0..count)
+        .map(|i| {
+            let kind = params.kinds[i % params.kinds.len()];
+            vary(&phrase, key, kind, params, &mut rng)
+        })
+        .collect();
+
+    tracing::debug!("Generated {} variations of {} notes", count, phrase.len());
+    Ok(variations)
+}
+
+fn vary(phrase: &[Note], key: &Scale, kind: VariationKind, params: &VariationParams, rng: &mut Rng) -> Variation {
+    let eligible: Vec<usize> = (0..phrase.len())
+        .filter(|&i| match kind {
+            // Not past the start of the song or the note before
+            VariationKind::RhythmicDisplacement => {
+                let earliest = if i == 0 { 0 } else { phrase[i - 1].start_tick + 1 };
+                params.displacement_ticks > 0 && phrase[i].start_tick >= earliest + params.displacement_ticks
+            }
+            VariationKind::NeighborTones => phrase[i].duration_ticks >= MIN_NEIGHBOR_TICKS,
+            VariationKind::OctaveDisplacement => true,
+        })
+        .collect();
+    let mut picked: Vec<usize> = eligible.iter().copied().filter(|_| rng.below(100) < params.note_percent as usize).collect();
+    if picked.is_empty() && !eligible.is_empty() {
+        picked.push(rng.pick(&eligible));
+    }
+
+    let mut notes = phrase.to_vec();
+    let mut added = Vec::new();
+    for &i in &picked {
+        match kind {
+            VariationKind::RhythmicDisplacement => {
+                let start = notes[i].start_tick - params.displacement_ticks;
+                notes[i].duration_ticks += params.displacement_ticks;
+                notes[i].start_tick = start;
+                // Notes still sounding are cut off where the anticipation starts
+                for earlier in notes[..i].iter_mut().filter(|n| n.end_tick() > start) {
+                    earlier.duration_ticks = start - earlier.start_tick;
+                }
+            }
+            VariationKind::NeighborTones => {
+                let degrees = if rng.chance(2) { 1 } else { -1 };
+                let Ok(neighbor) = transpose_note(&notes[i], &TransposeMode::diatonic(*key, degrees)) else {
+                    continue;
+                };
+                // Main note for half, neighbor for a quarter, back for the rest
+                let note = notes[i].clone();
+                let half = note.duration_ticks / 2;
+                let quarter = note.duration_ticks / 4;
+                notes[i].duration_ticks = half;
+                added.push(Note { start_tick: note.start_tick + half, duration_ticks: quarter, ..neighbor });
+                added.push(Note {
+                    start_tick: note.start_tick + half + quarter,
+                    duration_ticks: note.duration_ticks - half - quarter,
+                    ..note.clone()
+                });
+            }
+            VariationKind::OctaveDisplacement => {
+                let pitch = notes[i].pitch;
+                let up = rng.chance(2);
+                notes[i].pitch = match (pitch.checked_add(12).filter(|&p| p <= 127), pitch.checked_sub(12)) {
+                    (Some(higher), _) if up => higher,
+                    (_, Some(lower)) => lower,
+                    (Some(higher), None) => higher,
+                    (None, None) => pitch,
+                };
+            }
+        }
+    }
+    notes.extend(added);
+    notes.sort_by_key(|n| (n.start_tick, n.pitch));
+
+    let changed = picked.len();
+    Variation {
+        kind,
+        description: format!("{}: {} note{}", kind.name(), changed, if changed == 1 { "" } else { "s" }),
+        notes,
+    }
+}
+
+/// Replace a phrase in the song with one of its variations
+///
+/// `phrase` is the notes the variation was made from; each must still be in
+/// the song. Returns the number of notes written.
+pub fn apply_variation(song: &mut Song, phrase: &[Note], variation: &Variation) -> Result<usize> {
+    let mut remaining = song.notes.clone();
+    for note in phrase {
+        let index = remaining
+            .iter()
+            .position(|n| n == note)
+            .ok_or_else(|| MozartError::InvalidEdit("The phrase changed since the variations were made".to_string()))?;
+        remaining.remove(index);
+    }
+
+    song.notes = remaining;
+    song.add_notes(variation.notes.iter().cloned());
+    tracing::info!("Applied variation: {}", variation.description);
+    Ok(variation.notes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // C D E F in quarters
+    fn phrase() -> Vec<Note> {
+        [60, 62, 64, 65].iter().enumerate().map(|(i, &p)| Note::new(p, i as u32 * 480, 480)).collect()
+    }
+
+    fn params(kind: VariationKind) -> VariationParams {
+        VariationParams { kinds: vec![kind], note_percent: 100, ..VariationParams::default() }
+    }
+
+    #[test]
+    fn test_rhythmic_displacement() {
+        let variations =
+            generate_variations(&phrase(), &Scale::c_major(), 1, &params(VariationKind::RhythmicDisplacement)).unwrap();
+        let timing: Vec<(u32, u32)> = variations[0].notes.iter().map(|n| (n.start_tick, n.duration_ticks)).collect();
+        // The first note has nothing before it to push into
+        assert_eq!(timing, vec![(0, 240), (240, 480), (720, 480), (1200, 720)]);
+        assert_eq!(variations[0].description, "Rhythmic displacement: 3 notes");
+    }
+
+    #[test]
+    fn test_neighbor_and_octave_variations() {
+        let key = Scale::c_major();
+        let neighbor = &generate_variations(&phrase()[..1], &key, 1, &params(VariationKind::NeighborTones)).unwrap()[0];
+        let timing: Vec<(u32, u32)> = neighbor.notes.iter().map(|n| (n.start_tick, n.duration_ticks)).collect();
+        assert_eq!(timing, vec![(0, 240), (240, 120), (360, 120)]);
+        // B or D around the C
+        let pitches: Vec<u8> = neighbor.notes.iter().map(|n| n.pitch).collect();
+        assert!(pitches == [60, 59, 60] || pitches == [60, 62, 60], "{:?}", pitches);
+
+        let octave = &generate_variations(&phrase(), &key, 1, &params(VariationKind::OctaveDisplacement)).unwrap()[0];
+        for (varied, original) in octave.notes.iter().zip(&phrase()) {
+            assert_eq!(varied.pitch.abs_diff(original.pitch), 12);
+            assert_eq!(varied.start_tick, original.start_tick);
+        }
+    }
+
+    #[test]
+    fn test_variations_are_seeded() {
+        let key = Scale::c_major();
+        let params = VariationParams::default();
+        let first = generate_variations(&phrase(), &key, 6, &params).unwrap();
+        assert_eq!(first, generate_variations(&phrase(), &key, 6, &params).unwrap());
+        let kinds: Vec<VariationKind> = first.iter().map(|v| v.kind).collect();
+        assert_eq!(kinds[..3], VariationKind::ALL);
+        // Every candidate changes something
+        assert!(first.iter().all(|v| v.notes != phrase()));
+
+        assert!(generate_variations(&[], &key, 3, &params).is_err());
+    }
+
+    #[test]
+    fn test_apply_variation() {
+        let mut song = Song::new();
+        song.add_notes(phrase());
+        song.add_note(Note::new(48, 0, 1920)); // Not part of the phrase
+        let variation = generate_variations(&phrase(), &Scale::c_major(), 1, &params(VariationKind::OctaveDisplacement))
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(apply_variation(&mut song, &phrase(), &variation).unwrap(), 4);
+        assert_eq!(song.notes.len(), 5);
+        assert!(song.notes.iter().any(|n| n.pitch == 48));
+        assert!(apply_variation(&mut song, &phrase(), &variation).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+//! Piano roll view geometry
+//!
+//! Maps ticks to horizontal pixel positions, computes playhead-follow
+//! scrolling, picks the pitch rows to show, and turns where a piano key is
+//! pressed into a velocity, so the UI logic can be tested outside the browser
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+use crate::pitch::Pitch;
+
+/// How the view follows the playhead during playback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FollowMode {
+    /// Never scroll automatically
+    Off,
+    /// Jump a full page when the playhead leaves the visible area
+    #[default]
+    PageFlip,
+    /// Scroll smoothly, keeping the playhead at a fixed position
+    Continuous,
+}
+
+impl FollowMode {
+    /// Parse from string
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "off" | "none" => Ok(FollowMode::Off),
+            "page" | "page-flip" | "pageflip" => Ok(FollowMode::PageFlip),
+            "continuous" | "scroll" => Ok(FollowMode::Continuous),
+            _ => Err(MozartError::ParseError(format!("Unknown follow mode: {}", s))),
+        }
+    }
+
+    /// Get short name
+    pub fn name(&self) -> &'static str {
+        match self {
+            FollowMode::Off => "off",
+            FollowMode::PageFlip => "page-flip",
+            FollowMode::Continuous => "continuous",
+        }
+    }
+}
+
+impl fmt::Display for FollowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Editor view state saved with a song so it reopens where it was left
+///
+/// Purely presentational: core logic never reads it, and edits to it are not
+/// undoable and do not mark the song as modified.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// Selected editor tab (e.g., "piano-roll")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_tab: Option<String>,
+    /// Piano roll horizontal scroll in pixels
+    pub scroll_x: f64,
+    /// Piano roll vertical scroll in pixels
+    pub scroll_y: f64,
+    /// Piano roll zoom (pixels per tick)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pixels_per_tick: Option<f64>,
+    /// Snap grid division (4 = quarter notes, 8 = eighths, ...)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid_division: Option<u32>,
+    /// Loop region as (start tick, end tick)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loop_region: Option<(u32, u32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_track: Option<u32>,
+    /// Piano roll pitch rows (fitted to the notes when unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pitch_view: Option<PitchView>,
+}
+
+impl ViewState {
+    /// Whether nothing differs from the defaults (and the section can be omitted)
+    pub fn is_default(&self) -> bool {
+        *self == ViewState::default()
+    }
+}
+
+/// Which pitches the piano roll shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum PitchRangeMode {
+    /// The notes' lowest to highest pitch, plus `margin` semitones each side
+    Auto { margin: u8 },
+    Manual { low: u8, high: u8 },
+}
+
+/// Piano roll pitch rows: a range mode moved by whole octaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PitchView {
+    #[serde(flatten)]
+    pub range: PitchRangeMode,
+    /// Octaves to move the range by (e.g., to look above a fitted melody)
+    #[serde(default)]
+    pub octave_shift: i8,
+}
+
+impl Default for PitchView {
+    fn default() -> Self {
+        PitchView { range: PitchRangeMode::Auto { margin: Self::DEFAULT_MARGIN }, octave_shift: 0 }
+    }
+}
+
+impl PitchView {
+    /// Rows shown for an empty auto-fitted song (C2-C6)
+    pub const EMPTY_RANGE: (u8, u8) = (36, 84);
+    /// Fewest rows shown, so a one-note song isn't a sliver
+    pub const MIN_SPAN: u8 = 24;
+    pub const DEFAULT_MARGIN: u8 = 5;
+
+    /// Parse "auto", "auto 7" (margin), or "low-high" as note names or MIDI numbers ("C1-C7", "24-96")
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+        if let Some(margin) = lower.strip_prefix("auto") {
+            let margin = match margin.trim() {
+                "" => Self::DEFAULT_MARGIN,
+                m => m.parse().map_err(|_| MozartError::ParseError(format!("Invalid margin: {}", m)))?,
+            };
+            return Ok(PitchView { range: PitchRangeMode::Auto { margin }, octave_shift: 0 });
+        }
+
+        // Split on the hyphen between the two ends, not a negative octave's ("C-1-G9")
+        let split = s
+            .char_indices()
+            .skip(1)
+            .find(|&(i, c)| c == '-' && s[..i].trim_end().ends_with(|p: char| p.is_ascii_digit()))
+            .map(|(i, _)| i)
+            .ok_or_else(|| MozartError::ParseError(format!("Expected 'auto' or low-high, got '{}'", s)))?;
+        let pitch = |end: &str| -> Result<u8> {
+            let end = end.trim();
+            match end.parse::<u8>() {
+                Ok(midi) => Ok(midi),
+                Err(_) => Ok(Pitch::parse(end)?.midi()),
+            }
+        };
+        let (low, high) = (pitch(&s[..split])?, pitch(&s[split + 1..])?);
+        if low >= high || high > 127 {
+            return Err(MozartError::InvalidPitch(format!("Pitch range {}-{} is not within MIDI 0-127", low, high)));
+        }
+        Ok(PitchView { range: PitchRangeMode::Manual { low, high }, octave_shift: 0 })
+    }
+
+    /// The same range moved by `octaves` more
+    pub fn shifted(self, octaves: i8) -> Self {
+        PitchView { octave_shift: self.octave_shift.saturating_add(octaves), ..self }
+    }
+
+    /// Lowest and highest pitch row to show for these notes
+    ///
+    /// The octave shift stops at the ends of MIDI 0-127 instead of cutting
+    /// rows off.
+    pub fn visible_range(&self, notes: &[Note]) -> (u8, u8) {
+        let (low, high) = match self.range {
+            PitchRangeMode::Manual { low, high } => (low, high),
+            PitchRangeMode::Auto { margin } => {
+                match (notes.iter().map(|n| n.pitch).min(), notes.iter().map(|n| n.pitch).max()) {
+                    (Some(lowest), Some(highest)) => {
+                        let (low, high) = (lowest.saturating_sub(margin), highest.saturating_add(margin).min(127));
+                        // Widen around the middle to the minimum span, staying below 127
+                        let span = (high - low).max(Self::MIN_SPAN);
+                        let low = low.saturating_sub((span - (high - low)).div_ceil(2)).min(127 - span);
+                        (low, low + span)
+                    }
+                    _ => Self::EMPTY_RANGE,
+                }
+            }
+        };
+        let shift = (self.octave_shift as i16 * 12).clamp(-(low as i16), 127 - high as i16);
+        ((low as i16 + shift) as u8, (high as i16 + shift) as u8)
+    }
+}
+
+/// Softest and loudest velocity of a piano key preview
+pub const KEY_PRESS_VELOCITY: (u8, u8) = (20, 127);
+
+/// Preview velocity for a press `offset` pixels below the top of a key `key_height` tall
+///
+/// The top edge plays softest and the bottom edge loudest, like striking a
+/// key further out on a real keyboard.
+pub fn key_press_velocity(offset: f64, key_height: f64) -> u8 {
+    let (soft, loud) = KEY_PRESS_VELOCITY;
+    if key_height <= 0.0 {
+        return loud;
+    }
+    let depth = (offset / key_height).clamp(0.0, 1.0);
+    (soft as f64 + depth * (loud - soft) as f64).round() as u8
+}
+
+/// Horizontal layout of the piano roll timeline
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimelineView {
+    /// Width of the piano keys column in pixels (ticks start after it)
+    pub key_width: f64,
+    /// Horizontal zoom in pixels per tick
+    pub pixels_per_tick: f64,
+}
+
+impl Default for TimelineView {
+    fn default() -> Self {
+        TimelineView {
+            key_width: 60.0,
+            pixels_per_tick: 0.1,
+        }
+    }
+}
+
+impl TimelineView {
+    /// Fraction of the visible width where continuous mode keeps the playhead
+    pub const CONTINUOUS_ANCHOR: f64 = 0.33;
+    /// Zoom limits in pixels per tick
+    pub const MIN_PIXELS_PER_TICK: f64 = 0.01;
+    pub const MAX_PIXELS_PER_TICK: f64 = 1.0;
+
+    pub fn new(key_width: f64, pixels_per_tick: f64) -> Self {
+        TimelineView {
+            key_width,
+            pixels_per_tick,
+        }
+    }
+
+    /// Get the x coordinate of a tick
+    pub fn tick_to_x(&self, tick: u32) -> f64 {
+        self.key_width + tick as f64 * self.pixels_per_tick
+    }
+
+    /// Get the tick at an x coordinate (0 for positions over the keys)
+    pub fn x_to_tick(&self, x: f64) -> u32 {
+        if self.pixels_per_tick <= 0.0 {
+            return 0;
+        }
+        ((x - self.key_width) / self.pixels_per_tick).max(0.0).floor() as u32
+    }
+
+    /// Zoom by `factor`, keeping the timeline under `anchor_x` in place
+    ///
+    /// `anchor_x` is relative to the visible area (e.g., the center of a pinch).
+    /// Returns the zoomed view and the scroll offset that holds the anchor.
+    pub fn zoom_about(&self, factor: f64, anchor_x: f64, scroll_left: f64) -> (TimelineView, f64) {
+        let pixels_per_tick = (self.pixels_per_tick * factor)
+            .clamp(Self::MIN_PIXELS_PER_TICK, Self::MAX_PIXELS_PER_TICK);
+        // Over the keys, hold tick 0 where it is instead
+        let anchor_x = anchor_x.max(self.key_width - scroll_left);
+        let tick = ((scroll_left + anchor_x - self.key_width) / self.pixels_per_tick).max(0.0);
+
+        let zoomed = TimelineView::new(self.key_width, pixels_per_tick);
+        let scroll_left = (self.key_width + tick * pixels_per_tick - anchor_x).max(0.0);
+        (zoomed, scroll_left)
+    }
+
+    /// Compute the scroll offset that keeps the playhead in view
+    ///
+    /// `scroll_left` and `visible_width` describe the currently visible span
+    /// in pixels. Returns the new scroll offset (unchanged if no scroll is needed).
+    pub fn follow_scroll(
+        &self,
+        mode: FollowMode,
+        scroll_left: f64,
+        visible_width: f64,
+        playhead_tick: u32,
+    ) -> f64 {
+        let playhead_x = self.tick_to_x(playhead_tick);
+
+        match mode {
+            FollowMode::Off => scroll_left,
+            FollowMode::PageFlip => {
+                let visible_end = scroll_left + visible_width;
+                if playhead_x < scroll_left + self.key_width || playhead_x >= visible_end {
+                    // Start the new page with the playhead just right of the keys
+                    (playhead_x - self.key_width).max(0.0)
+                } else {
+                    scroll_left
+                }
+            }
+            FollowMode::Continuous => {
+                (playhead_x - visible_width * Self::CONTINUOUS_ANCHOR).max(0.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_key_press_velocity() {
+        assert_eq!(key_press_velocity(0.0, 12.0), 20);
+        assert_eq!(key_press_velocity(12.0, 12.0), 127);
+        assert_eq!(key_press_velocity(6.0, 12.0), 74);
+        // Presses past the edges (and degenerate keys) stay in range
+        assert_eq!(key_press_velocity(-3.0, 12.0), 20);
+        assert_eq!(key_press_velocity(5.0, 0.0), 127);
+    }
+
+    #[test]
+    fn test_tick_x_round_trip() {
+        let view = TimelineView::default();
+        assert_eq!(view.tick_to_x(0), 60.0);
+        assert_eq!(view.tick_to_x(480), 108.0);
+        assert_eq!(view.x_to_tick(108.0), 480);
+        assert_eq!(view.x_to_tick(10.0), 0);
+    }
+
+    #[test]
+    fn test_follow_page_flip() {
+        let view = TimelineView::default();
+
+        // Playhead inside the visible page: no scroll
+        assert_eq!(view.follow_scroll(FollowMode::PageFlip, 0.0, 800.0, 1920), 0.0);
+
+        // Playhead past the right edge: flip so it sits just after the keys
+        let tick = 8000; // x = 860
+        assert_eq!(view.follow_scroll(FollowMode::PageFlip, 0.0, 800.0, tick), 800.0);
+
+        // Playhead behind the page (e.g., after a loop): flip back
+        assert_eq!(view.follow_scroll(FollowMode::PageFlip, 800.0, 800.0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_follow_continuous_and_off() {
+        let view = TimelineView::default();
+
+        // Near the start the view can't scroll left of zero
+        assert_eq!(view.follow_scroll(FollowMode::Continuous, 0.0, 900.0, 0), 0.0);
+
+        let scroll = view.follow_scroll(FollowMode::Continuous, 0.0, 900.0, 10000);
+        assert!((view.tick_to_x(10000) - scroll - 297.0).abs() < 0.001);
+
+        assert_eq!(view.follow_scroll(FollowMode::Off, 123.0, 900.0, 10000), 123.0);
+    }
+
+    #[test]
+    fn test_zoom_about_keeps_anchor() {
+        let view = TimelineView::new(60.0, 0.125);
+        let (zoomed, scroll) = view.zoom_about(2.0, 300.0, 1000.0);
+        assert_eq!(zoomed.pixels_per_tick, 0.25);
+        // Tick 9920 was under the anchor before and still is
+        assert_eq!(view.x_to_tick(1300.0), 9920);
+        assert_eq!(zoomed.x_to_tick(scroll + 300.0), 9920);
+
+        let (zoomed, scroll) = view.zoom_about(1000.0, 0.0, 0.0);
+        assert_eq!((zoomed.pixels_per_tick, scroll), (TimelineView::MAX_PIXELS_PER_TICK, 0.0));
+    }
+
+    #[test]
+    fn test_view_state_round_trip() {
+        assert!(ViewState::default().is_default());
+
+        let state = ViewState {
+            selected_tab: Some("piano-roll".to_string()),
+            scroll_x: 480.0,
+            loop_region: Some((0, 1920)),
+            ..ViewState::default()
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<ViewState>(&json).unwrap(), state);
+
+        // Missing fields fall back to defaults
+        assert!(serde_json::from_str::<ViewState>("{}").unwrap().is_default());
+    }
+
+    #[test]
+    fn test_pitch_view_auto_fit() {
+        let auto = PitchView::default();
+        assert_eq!(auto.visible_range(&[]), PitchView::EMPTY_RANGE);
+
+        // A bass line well below C2, with the default margin
+        let bass = [Note::new(28, 0, 480), Note::new(52, 480, 480)];
+        assert_eq!(auto.visible_range(&bass), (23, 57));
+        // A single note widens to the minimum span
+        assert_eq!(auto.visible_range(&[Note::new(100, 0, 480)]), (88, 112));
+        assert_eq!(auto.visible_range(&[Note::new(126, 0, 480)]), (103, 127));
+
+        // Shifts stop at the top of the MIDI range
+        assert_eq!(auto.shifted(1).visible_range(&bass), (35, 69));
+        assert_eq!(auto.shifted(9).visible_range(&bass), (93, 127));
+    }
+
+    #[test]
+    fn test_pitch_view_parse() {
+        let manual = PitchView::parse("C1-C7").unwrap();
+        assert_eq!(manual.range, PitchRangeMode::Manual { low: 24, high: 96 });
+        assert_eq!(manual.visible_range(&[]), (24, 96));
+        assert_eq!(PitchView::parse("24 - 96").unwrap(), manual);
+        assert_eq!(PitchView::parse("C-1-G9").unwrap().range, PitchRangeMode::Manual { low: 0, high: 127 });
+        assert_eq!(PitchView::parse("Auto 2").unwrap().range, PitchRangeMode::Auto { margin: 2 });
+        assert!(PitchView::parse("C7-C1").is_err());
+        assert!(PitchView::parse("wide").is_err());
+
+        // Saved flattened with the view state
+        let json = serde_json::to_string(&manual.shifted(-1)).unwrap();
+        assert_eq!(json, r#"{"mode":"manual","low":24,"high":96,"octave_shift":-1}"#);
+        assert_eq!(serde_json::from_str::<PitchView>(&json).unwrap(), manual.shifted(-1));
+    }
+
+    #[test]
+    fn test_follow_mode_parse() {
+        assert_eq!(FollowMode::parse("page-flip").unwrap(), FollowMode::PageFlip);
+        assert_eq!(FollowMode::parse("Continuous").unwrap(), FollowMode::Continuous);
+        assert_eq!(FollowMode::parse("off").unwrap(), FollowMode::Off);
+        assert!(FollowMode::parse("sideways").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Chord inversions and voicings
+//!
+//! Rearranges the pitches of a chord by octaves without changing which
+//! pitch classes it holds, so a texture can be inverted, spread, or packed
+//! tightly without moving notes one by one.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::error::{MozartError, Result};
+use crate::note::Note;
+
+const OCTAVE: u8 = 12;
+
+/// How to rearrange a chord
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Voicing {
+    /// Move the lowest note up by octaves until it is the highest
+    InvertUp,
+    /// Move the highest note down by octaves until it is the lowest
+    InvertDown,
+    /// Close the chord, then lift every second voice above the bass an
+    /// octave (C E G becomes C G E)
+    Open,
+    /// Keep the bass and pack the other notes into the octave above it
+    Close,
+}
+
+impl Voicing {
+    /// Parse from string
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('-', "_").as_str() {
+            "up" | "invert_up" => Ok(Voicing::InvertUp),
+            "down" | "invert_down" => Ok(Voicing::InvertDown),
+            "open" => Ok(Voicing::Open),
+            "close" | "closed" => Ok(Voicing::Close),
+            _ => Err(MozartError::ParseError(format!("Unknown voicing: {}", s))),
+        }
+    }
+
+    /// Undo history label
+    pub fn label(&self) -> &'static str {
+        match self {
+            Voicing::InvertUp => "Invert chord up",
+            Voicing::InvertDown => "Invert chord down",
+            Voicing::Open => "Open voicing",
+            Voicing::Close => "Close voicing",
+        }
+    }
+}
+
+impl fmt::Display for Voicing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+/// New pitch for each note of a chord, in the order given; None if a note
+/// would leave the MIDI range
+///
+/// Every note's pitch moves by whole octaves, so a note keeps its pitch
+/// class (and, in note form, its velocity and voice) through any voicing.
+pub fn voice_pitches(pitches: &[u8], voicing: Voicing) -> Option<Vec<u8>> {
+    // Positions from lowest to highest pitch
+    let mut order: Vec<usize> = (0..pitches.len()).collect();
+    order.sort_by_key(|&i| pitches[i]);
+    let (&lowest, &highest) = (order.first()?, order.last()?);
+
+    let mut voiced = pitches.to_vec();
+    match voicing {
+        Voicing::InvertUp => {
+            while voiced[lowest] <= pitches[highest] {
+                voiced[lowest] = raise(voiced[lowest])?;
+            }
+        }
+        Voicing::InvertDown => {
+            while voiced[highest] >= pitches[lowest] {
+                voiced[highest] = voiced[highest].checked_sub(OCTAVE)?;
+            }
+        }
+        Voicing::Close => close(&mut voiced, &order)?,
+        Voicing::Open => {
+            close(&mut voiced, &order)?;
+            let mut closed = order.clone();
+            closed.sort_by_key(|&i| voiced[i]);
+            for &i in closed.iter().skip(1).step_by(2) {
+                voiced[i] = raise(voiced[i])?;
+            }
+        }
+    }
+    Some(voiced)
+}
+
+fn raise(pitch: u8) -> Option<u8> {
+    pitch.checked_add(OCTAVE).filter(|&p| p <= 127)
+}
+
+// Keep the bass and move each other note to the nearest free pitch above it
+fn close(pitches: &mut [u8], order: &[usize]) -> Option<()> {
+    let bass = pitches[order[0]];
+    let mut taken = vec![bass];
+    for &i in &order[1..] {
+        let mut placed = bass + match (pitches[i] - bass) % OCTAVE {
+            0 => OCTAVE,
+            interval => interval,
+        };
+        // Doubled pitch classes stack up by octaves
+        while taken.contains(&placed) {
+            placed = raise(placed)?;
+        }
+        if placed > 127 {
+            return None;
+        }
+        taken.push(placed);
+        pitches[i] = placed;
+    }
+    Some(())
+}
+
+/// Revoice one chord in place; returns whether any pitch changed
+pub fn voice_chord(chord: &mut [&mut Note], voicing: Voicing) -> bool {
+    if chord.len() < 2 {
+        return false;
+    }
+
+    let pitches: Vec<u8> = chord.iter().map(|n| n.pitch).collect();
+    let Some(voiced) = voice_pitches(&pitches, voicing) else {
+        tracing::debug!("Cannot apply {} to {:?}: out of range", voicing, pitches);
+        return false;
+    };
+    if voiced == pitches {
+        return false;
+    }
+
+    for (note, pitch) in chord.iter_mut().zip(voiced) {
+        note.pitch = pitch;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // C4 E4 G4
+    const C_MAJOR: [u8; 3] = [60, 64, 67];
+
+    #[test]
+    fn test_inversions() {
+        assert_eq!(voice_pitches(&C_MAJOR, Voicing::InvertUp), Some(vec![72, 64, 67]));
+        assert_eq!(voice_pitches(&[72, 64, 67], Voicing::InvertUp), Some(vec![72, 76, 67]));
+        assert_eq!(voice_pitches(&C_MAJOR, Voicing::InvertDown), Some(vec![60, 64, 55]));
+
+        // A wide chord: the bass has to climb two octaves to reach the top
+        assert_eq!(voice_pitches(&[48, 64, 67], Voicing::InvertUp), Some(vec![72, 64, 67]));
+        assert_eq!(voice_pitches(&[120, 124, 127], Voicing::InvertUp), None);
+    }
+
+    #[test]
+    fn test_open_and_close() {
+        assert_eq!(voice_pitches(&C_MAJOR, Voicing::Open), Some(vec![60, 76, 67]));
+        // C7: C G E' Bb'
+        assert_eq!(voice_pitches(&[60, 64, 67, 70], Voicing::Open), Some(vec![60, 76, 67, 82]));
+
+        assert_eq!(voice_pitches(&[60, 76, 67], Voicing::Close), Some(C_MAJOR.to_vec()));
+        // The doubled root stays an octave above the bass
+        assert_eq!(voice_pitches(&[48, 60, 64, 67], Voicing::Close), Some(vec![48, 60, 52, 55]));
+        assert_eq!(voice_pitches(&C_MAJOR, Voicing::Close), Some(C_MAJOR.to_vec()));
+    }
+
+    #[test]
+    fn test_voice_chord_keeps_timing() {
+        let mut notes = [Note::new(67, 0, 960), Note::new(60, 0, 960), Note::new(64, 0, 960)];
+        notes[1].velocity = 80;
+        assert!(voice_chord(&mut notes.iter_mut().collect::<Vec<_>>(), Voicing::InvertUp));
+
+        // Only the old bass moves
+        let pitches: Vec<(u8, u8)> = notes.iter().map(|n| (n.pitch, n.velocity)).collect();
+        assert_eq!(pitches, vec![(67, 100), (72, 80), (64, 100)]);
+        assert!(notes.iter().all(|n| n.start_tick == 0 && n.duration_ticks == 960));
+
+        assert!(!voice_chord(&mut notes[..1].iter_mut().collect::<Vec<_>>(), Voicing::Open));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Voicing::parse("invert-up").unwrap(), Voicing::InvertUp);
+        assert_eq!(Voicing::parse("Close").unwrap(), Voicing::Close);
+        assert!(Voicing::parse("drop2").is_err());
+    }
+}
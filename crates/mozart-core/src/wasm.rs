@@ -1,15 +1,61 @@
 //! WebAssembly bindings for Mozart Core
 //!
 //! This module provides JavaScript-friendly wrappers around the core music engine.
+//! It is the canonical embedding API: every edit goes through the wrapper's
+//! undo history, and web hosts should need nothing beyond these bindings.
 
 use wasm_bindgen::prelude::*;
-use crate::note::{Note, parse_melody, format_melody};
+use crate::note::{Note, pitched_notes, parse_melody_token, format_melody};
+use crate::completion::suggest_next_tokens;
 use crate::pitch::{Pitch, PitchClass};
 use crate::scale::{Scale, ScaleType};
-use crate::time::{TimeSignature, AccentPattern};
+use crate::time::{TimeSignature, AccentPattern, MusicalPosition, PlaybackRate, Polyrhythm};
 use crate::transpose::{TransposeMode, transpose_notes};
-use crate::song::Song;
-use crate::midi::export_to_midi;
+use crate::song::{Song, SongDefaults, format_duration};
+use crate::midi::{export_to_midi, import_from_midi, MidiExporter};
+use crate::merge::{merge_into, plan_sections, plan_split, split_song, SplitPiece, TempoAlignment};
+use crate::view::{FollowMode, PitchView, TimelineView, ViewState};
+use crate::settings::AppSettings;
+use crate::startup::{startup_diagnostics, StartupSentinel};
+use crate::watchdog::AudioWatchdog;
+use crate::history::History;
+use crate::changes::{diff_songs, SongChange};
+use crate::commands::search_commands;
+use crate::theory::{get_theory_reference, TheoryTopic};
+use crate::grammar::get_notation_grammar;
+use crate::tutorial::Tutorial;
+use crate::practice::{CallResponse, CallResponseConfig};
+use crate::strum::StrumDirection;
+use crate::chord_entry::{chord_notes, CapturedKey, ChordCapture};
+use crate::chance::{self, Take};
+use crate::color::ColorLabel;
+use crate::repeats::{Ending, Jump, Repeat, RepeatSign, RepeatStructure};
+use crate::instrument::Instrument;
+use crate::render::{render_practice_wav, DEFAULT_SAMPLE_RATE};
+use crate::groove::{extract_groove, GrooveTemplate};
+use crate::voicing::Voicing;
+use crate::harmony::{diatonic_chords, reharmonize_measure, suggest_next_chords, ReharmonizeConstraints};
+use crate::modulation::{apply_modulation, plan_modulation, ModulationPlan};
+use crate::counterpoint::{write_counterpoint, CounterpointConfig};
+use crate::melody::{generate_melody, MelodyConfig};
+use crate::similarity::similarity;
+use crate::comparison::{compare_versions, ComparisonMode};
+use crate::continuation::{apply_continuation, continue_melody, Continuation, ContinuationConfig, MelodyModel};
+use crate::variation::{apply_variation, generate_variations, Variation, VariationParams};
+use crate::chart::import_chord_chart;
+use crate::chord::Chord;
+use crate::lrc::LrcExporter;
+use crate::chordpro::chordpro_text;
+use crate::audition::{chord_audition, diatonic_chord_audition, phrase_audition, scale_audition, ScaleDirection};
+use crate::register::{fold_into_range, out_of_range_notes, pitch_histogram, PitchRange};
+use crate::presets::{groove_presets, load_groove_preset, PresetLibrary};
+use crate::macros::{self, MacroLibrary, MacroStep};
+use crate::validate::{self, RepairOptions};
+use crate::confirm::{clear_notes_loss, confirm_or_run, delete_measures_loss, new_song_loss};
+
+/// Declarations for the JSON payloads, emitted into the package's `.d.ts`
+#[wasm_bindgen(typescript_custom_section)]
+const PAYLOAD_TYPES: &'static str = crate::typescript::PAYLOAD_TYPES;
 
 /// Initialize panic hook for better error messages in the browser console
 #[wasm_bindgen(start)]
@@ -21,6 +67,136 @@ pub fn init() {
 #[wasm_bindgen]
 pub struct Mozart {
     song: Song,
+    history: History,
+    /// Changes not yet taken by the host, oldest first
+    changes: Vec<SongChange>,
+    /// Song as of the last save, for dirty tracking
+    saved: Song,
+    /// Onboarding progress, while the tutorial is open
+    tutorial: Option<Tutorial>,
+    /// Call-and-response round in progress
+    practice: Option<CallResponse>,
+    /// Candidate variations and the phrase they were made from, until one is applied
+    variations: Option<(Vec<Note>, Vec<Variation>)>,
+    /// Continuation previewed by "continue my melody", until kept or discarded
+    continuation: Option<Continuation>,
+}
+
+/// Pending changes after which the host is just told to refetch the song
+const MAX_PENDING_CHANGES: usize = 256;
+
+impl Mozart {
+    fn from_song(song: Song) -> Mozart {
+        Mozart {
+            saved: song.clone(),
+            song,
+            history: History::new(),
+            changes: Vec::new(),
+            tutorial: None,
+            practice: None,
+            variations: None,
+            continuation: None,
+        }
+    }
+
+    fn push_metadata(&mut self) {
+        let metadata = &self.song.metadata;
+        let change = SongChange::Metadata {
+            title: metadata.title.clone(),
+            composer: metadata.composer.clone(),
+        };
+        self.push_changes([change]);
+    }
+
+    fn push_changes(&mut self, changes: impl IntoIterator<Item = SongChange>) {
+        // Nothing before a replacement matters to the host
+        if self.changes.last() != Some(&SongChange::Replaced) {
+            self.changes.extend(changes);
+        }
+        if self.changes.len() > MAX_PENDING_CHANGES || self.changes.last() == Some(&SongChange::Replaced) {
+            self.changes = vec![SongChange::Replaced];
+        }
+    }
+
+    fn note_at(&self, index: usize) -> Result<Note, JsValue> {
+        self.song.notes.get(index).cloned()
+            .ok_or_else(|| JsValue::from_str(&format!("No note at index {}", index)))
+    }
+
+    fn voice_chords(&mut self, indices: &[u32], voicing: Voicing) -> usize {
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.edit(voicing.label(), |song| song.voice_chords(&indices, voicing))
+    }
+
+    fn variation(&self, index: usize) -> Result<&Variation, JsValue> {
+        self.variations
+            .as_ref()
+            .and_then(|(_, variations)| variations.get(index))
+            .ok_or_else(|| JsValue::from_str(&format!("No variation {}", index)))
+    }
+
+    /// Apply an edit to the song, recording the prior state for undo
+    fn edit<T>(&mut self, label: &str, f: impl FnOnce(&mut Song) -> T) -> T {
+        let before = self.song.clone();
+        let result = f(&mut self.song);
+        self.record_edit(label, before);
+        result
+    }
+
+    // Report and record an edit that changed something; an edit that changed
+    // nothing leaves the history (and its redo steps) alone
+    fn record_edit(&mut self, label: &str, before: Song) {
+        let changes = diff_songs(&before, &self.song);
+        if changes.is_empty() {
+            return;
+        }
+        self.push_changes(changes);
+        self.history.record(label, before, now_ms());
+    }
+
+    /// Edit a copy of the repeat structure and, if it can be played, apply it as one edit
+    fn edit_repeats(
+        &mut self,
+        label: &str,
+        f: impl FnOnce(&mut RepeatStructure) -> crate::error::Result<()>,
+    ) -> Result<(), JsValue> {
+        let mut repeats = self.song.repeats.clone();
+        f(&mut repeats).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.try_edit(label, |song| song.set_repeats(repeats).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Apply a fallible edit; on error the song is restored and nothing is recorded
+    fn try_edit<T>(
+        &mut self,
+        label: &str,
+        f: impl FnOnce(&mut Song) -> Result<T, JsValue>,
+    ) -> Result<T, JsValue> {
+        let before = self.song.clone();
+        match f(&mut self.song) {
+            Ok(result) => {
+                self.record_edit(label, before);
+                Ok(result)
+            }
+            Err(e) => {
+                self.song = before;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Milliseconds since the epoch, used to coalesce rapid edits in the history
+fn now_ms() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now() as u64
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
 }
 
 #[wasm_bindgen]
@@ -28,15 +204,23 @@ impl Mozart {
     /// Create a new Mozart instance with an empty song
     #[wasm_bindgen(constructor)]
     pub fn new() -> Mozart {
-        Mozart { song: Song::new() }
+        Mozart::from_song(Song::new())
     }
 
     /// Create with a title
     #[wasm_bindgen(js_name = withTitle)]
     pub fn with_title(title: &str) -> Mozart {
-        Mozart {
-            song: Song::with_title(title),
-        }
+        Mozart::from_song(Song::with_title(title))
+    }
+
+    /// Create with a title from the user's new-song defaults (JSON `SongDefaults`)
+    #[wasm_bindgen(js_name = withDefaults)]
+    pub fn with_defaults(title: &str, defaults_json: &str) -> Result<Mozart, JsValue> {
+        let defaults: SongDefaults = serde_json::from_str(defaults_json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mut song = Song::new_with_defaults(&defaults).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        song.metadata.title = title.to_string();
+        Ok(Mozart::from_song(song))
     }
 
     // ==================== Song Metadata ====================
@@ -51,6 +235,7 @@ impl Mozart {
     #[wasm_bindgen(setter)]
     pub fn set_title(&mut self, title: String) {
         self.song.metadata.title = title;
+        self.push_metadata();
     }
 
     /// Get the composer name
@@ -63,6 +248,7 @@ impl Mozart {
     #[wasm_bindgen(setter)]
     pub fn set_composer(&mut self, composer: String) {
         self.song.metadata.composer = composer;
+        self.push_metadata();
     }
 
     // ==================== Song Settings ====================
@@ -76,7 +262,47 @@ impl Mozart {
     /// Set the tempo in BPM (20-300)
     #[wasm_bindgen(setter)]
     pub fn set_tempo(&mut self, tempo: u16) {
-        self.song.set_tempo(tempo);
+        self.edit("Set tempo", |song| song.set_tempo(tempo));
+    }
+
+    /// Whether playback velocities follow the accent pattern
+    #[wasm_bindgen(getter, js_name = accentDynamics)]
+    pub fn accent_dynamics(&self) -> bool {
+        self.song.settings.accent_dynamics
+    }
+
+    /// Enable or disable accent-shaped playback dynamics
+    #[wasm_bindgen(setter, js_name = accentDynamics)]
+    pub fn set_accent_dynamics(&mut self, enabled: bool) {
+        self.edit("Toggle accent dynamics", |song| song.settings.accent_dynamics = enabled);
+    }
+
+    /// Instrument the song plays with (see `getInstruments`)
+    #[wasm_bindgen(getter)]
+    pub fn instrument(&self) -> String {
+        self.song.settings.instrument.name().to_string()
+    }
+
+    /// Set the instrument the song plays with (e.g., "piano")
+    #[wasm_bindgen(js_name = setInstrument)]
+    pub fn set_instrument(&mut self, name: &str) -> Result<(), JsValue> {
+        let instrument = Instrument::parse(name).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Change instrument", |song| song.set_instrument(instrument));
+        Ok(())
+    }
+
+    /// MIDI pitch rhythm hits ("xq") are entered at
+    #[wasm_bindgen(getter, js_name = rhythmPitch)]
+    pub fn rhythm_pitch(&self) -> u8 {
+        self.song.rhythm_pitch()
+    }
+
+    /// Set the MIDI pitch rhythm hits are entered at, or the default (middle C) without one
+    #[wasm_bindgen(js_name = setRhythmPitch)]
+    pub fn set_rhythm_pitch(&mut self, pitch: Option<u8>) -> Result<(), JsValue> {
+        self.try_edit("Set rhythm pitch", |song| {
+            song.set_rhythm_pitch(pitch).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
     /// Get time signature as "numerator/denominator"
@@ -90,10 +316,104 @@ impl Mozart {
     pub fn set_time_signature(&mut self, ts: &str) -> Result<(), JsValue> {
         let time_sig = TimeSignature::parse(ts)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.song.set_time_signature(time_sig);
+        self.edit("Set time signature", |song| song.set_time_signature(time_sig));
+        Ok(())
+    }
+
+    /// Get a voice's time signature as "numerator/denominator" (the song's unless it has its own)
+    #[wasm_bindgen(js_name = getVoiceTimeSignature)]
+    pub fn get_voice_time_signature(&self, voice: u8) -> String {
+        self.song.voice_time_signature(voice).to_string()
+    }
+
+    /// Give a voice its own time signature (e.g., "7/8"), or "" to follow the song's
+    #[wasm_bindgen(js_name = setVoiceTimeSignature)]
+    pub fn set_voice_time_signature(&mut self, voice: u8, ts: &str) -> Result<(), JsValue> {
+        let time_sig = match ts.trim() {
+            "" => None,
+            ts => Some(TimeSignature::parse(ts).map_err(|e| JsValue::from_str(&e.to_string()))?),
+        };
+        self.edit("Set voice time signature", |song| song.set_voice_time_signature(voice, time_sig));
         Ok(())
     }
 
+    /// Voices in their own meter as JSON `VoiceMeter[]`
+    #[wasm_bindgen(js_name = getVoiceMetersJson)]
+    pub fn get_voice_meters_json(&self) -> String {
+        serde_json::to_string(&self.song.settings.voice_meters).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// The song's tracks as JSON `Track[]`, sorted by voice
+    #[wasm_bindgen(js_name = getTracksJson)]
+    pub fn get_tracks_json(&self) -> String {
+        serde_json::to_string(&self.song.settings.tracks).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Add an empty track ("" names it after its number); returns its voice
+    #[wasm_bindgen(js_name = addTrack)]
+    pub fn add_track(&mut self, name: &str) -> Result<u8, JsValue> {
+        self.try_edit("Add track", |song| song.add_track(name).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Remove a voice's track and its notes; returns how many notes went
+    #[wasm_bindgen(js_name = removeTrack)]
+    pub fn remove_track(&mut self, voice: u8) -> Result<usize, JsValue> {
+        self.try_edit("Remove track", |song| song.remove_track(voice).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    #[wasm_bindgen(js_name = renameTrack)]
+    pub fn rename_track(&mut self, voice: u8, name: &str) -> Result<(), JsValue> {
+        self.try_edit("Rename track", |song| song.rename_track(voice, name).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Give a track its own instrument (e.g., "strings"), or "" for the song's
+    #[wasm_bindgen(js_name = setTrackInstrument)]
+    pub fn set_track_instrument(&mut self, voice: u8, name: &str) -> Result<(), JsValue> {
+        let instrument = match name.trim() {
+            "" => None,
+            name => Some(Instrument::parse(name).map_err(|e| JsValue::from_str(&e.to_string()))?),
+        };
+        self.try_edit("Change track instrument", |song| {
+            song.set_track_instrument(voice, instrument).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Set a track's volume (0-127; 100 plays velocities as written)
+    #[wasm_bindgen(js_name = setTrackVolume)]
+    pub fn set_track_volume(&mut self, voice: u8, volume: u8) -> Result<(), JsValue> {
+        self.try_edit("Change track volume", |song| {
+            song.set_track_volume(voice, volume).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    #[wasm_bindgen(js_name = muteTrack)]
+    pub fn mute_track(&mut self, voice: u8, mute: bool) -> Result<(), JsValue> {
+        self.try_edit("Mute track", |song| song.mute_track(voice, mute).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    #[wasm_bindgen(js_name = soloTrack)]
+    pub fn solo_track(&mut self, voice: u8, solo: bool) -> Result<(), JsValue> {
+        self.try_edit("Solo track", |song| song.solo_track(voice, solo).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Add a tempo change at a tick (BPM, 20-300)
+    #[wasm_bindgen(js_name = addTempoChange)]
+    pub fn add_tempo_change(&mut self, tick: u32, tempo: u16) {
+        self.edit("Add tempo change", |song| song.add_tempo_change(tick, tempo));
+    }
+
+    /// Remove all tempo changes
+    #[wasm_bindgen(js_name = clearTempoChanges)]
+    pub fn clear_tempo_changes(&mut self) {
+        self.edit("Clear tempo changes", |song| song.clear_tempo_changes());
+    }
+
+    /// Get the tempo in effect at a tick
+    #[wasm_bindgen(js_name = tempoAtTick)]
+    pub fn tempo_at_tick(&self, tick: u32) -> u16 {
+        self.song.tempo_at_tick(tick)
+    }
+
     /// Get the time signature numerator
     #[wasm_bindgen(js_name = getTimeSignatureNumerator)]
     pub fn get_time_signature_numerator(&self) -> u8 {
@@ -117,10 +437,54 @@ impl Mozart {
     pub fn set_key(&mut self, key: &str) -> Result<(), JsValue> {
         let scale = Scale::parse(key)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.song.set_key(scale);
+        self.edit("Set key", |song| song.set_key(scale));
+        Ok(())
+    }
+
+    /// Get the instrument range as JSON `PitchRange`, or "null" without one
+    #[wasm_bindgen(js_name = getPitchRangeJson)]
+    pub fn get_pitch_range_json(&self) -> String {
+        serde_json::to_string(&self.song.settings.pitch_range).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Check notes against a custom range of MIDI pitches, inclusive
+    #[wasm_bindgen(js_name = setPitchRange)]
+    pub fn set_pitch_range(&mut self, low: u8, high: u8) -> Result<(), JsValue> {
+        let range = PitchRange::new("", low, high).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Set pitch range", |song| song.set_pitch_range(Some(range)));
+        Ok(())
+    }
+
+    /// Check notes against a preset instrument or voice range (e.g., "Violin")
+    #[wasm_bindgen(js_name = setPitchRangePreset)]
+    pub fn set_pitch_range_preset(&mut self, name: &str) -> Result<(), JsValue> {
+        let range = PitchRange::preset(name).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Set pitch range", |song| song.set_pitch_range(Some(range)));
         Ok(())
     }
 
+    /// Stop checking notes against a range
+    #[wasm_bindgen(js_name = clearPitchRange)]
+    pub fn clear_pitch_range(&mut self) {
+        self.edit("Clear pitch range", |song| song.set_pitch_range(None));
+    }
+
+    /// Indices of notes outside the pitch range, as a JSON array
+    #[wasm_bindgen(js_name = getOutOfRangeNotesJson)]
+    pub fn get_out_of_range_notes_json(&self) -> String {
+        serde_json::to_string(&out_of_range_notes(&self.song)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Move notes outside low-high (MIDI, inclusive) in by octaves, keeping
+    /// the melody's shape where possible; returns JSON `FoldedNote[]`
+    #[wasm_bindgen(js_name = foldIntoRangeJson)]
+    pub fn fold_into_range_json(&mut self, low: u8, high: u8) -> Result<String, JsValue> {
+        let folded = self.try_edit("Fold into range", |song| {
+            fold_into_range(&mut song.notes, low, high).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&folded).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     // ==================== Note Management ====================
 
     /// Get the number of notes
@@ -132,280 +496,2023 @@ impl Mozart {
     /// Add a note by MIDI pitch, start tick, and duration ticks
     #[wasm_bindgen(js_name = addNote)]
     pub fn add_note(&mut self, pitch: u8, start_tick: u32, duration_ticks: u32) {
-        self.song.add_note(Note::new(pitch, start_tick, duration_ticks));
+        self.edit("Add note", |song| song.add_note(Note::new(pitch, start_tick, duration_ticks)));
     }
 
     /// Add a note with velocity
     #[wasm_bindgen(js_name = addNoteWithVelocity)]
     pub fn add_note_with_velocity(&mut self, pitch: u8, start_tick: u32, duration_ticks: u32, velocity: u8) {
-        self.song.add_note(Note::with_velocity(pitch, start_tick, duration_ticks, velocity));
+        let note = Note::with_velocity(pitch, start_tick, duration_ticks, velocity);
+        self.edit("Add note", |song| song.add_note(note));
     }
 
     /// Add a note with velocity and voice
     #[wasm_bindgen(js_name = addNoteWithVoice)]
     pub fn add_note_with_voice(&mut self, pitch: u8, start_tick: u32, duration_ticks: u32, velocity: u8, voice: u8) {
-        self.song.add_note(Note::with_voice(pitch, start_tick, duration_ticks, velocity, voice));
+        let note = Note::with_voice(pitch, start_tick, duration_ticks, velocity, voice);
+        self.edit("Add note", |song| song.add_note(note));
     }
 
-    /// Remove a note at index
-    #[wasm_bindgen(js_name = removeNote)]
-    pub fn remove_note(&mut self, index: usize) -> bool {
-        self.song.remove_note(index).is_some()
+    /// Change the pitch of the note at index
+    #[wasm_bindgen(js_name = setNotePitch)]
+    pub fn set_note_pitch(&mut self, index: usize, pitch: u8) -> Result<(), JsValue> {
+        self.try_edit("Move note", |song| {
+            song.set_note_pitch(index, pitch)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Clear all notes
-    #[wasm_bindgen(js_name = clearNotes)]
-    pub fn clear_notes(&mut self) {
-        self.song.clear_notes();
+    /// Replace the note at index from JSON; returns its new index after re-sorting
+    #[wasm_bindgen(js_name = updateNoteJson)]
+    pub fn update_note_json(&mut self, index: usize, json: &str) -> Result<usize, JsValue> {
+        let note: Note = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.try_edit("Edit note", |song| {
+            song.update_note(index, note)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Get all notes as JSON array
-    #[wasm_bindgen(js_name = getNotesJson)]
-    pub fn get_notes_json(&self) -> String {
-        serde_json::to_string(&self.song.notes).unwrap_or_else(|_| "[]".to_string())
+    /// Set the velocity of the note at index (1-127)
+    #[wasm_bindgen(js_name = setNoteVelocity)]
+    pub fn set_note_velocity(&mut self, index: usize, velocity: u8) -> Result<(), JsValue> {
+        let mut note = self.note_at(index)?;
+        note.velocity = velocity.clamp(1, 127);
+        self.try_edit("Set velocity", |song| {
+            song.update_note(index, note)
+                .map(|_| ())
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Get a single note as JSON
-    #[wasm_bindgen(js_name = getNoteJson)]
-    pub fn get_note_json(&self, index: usize) -> Option<String> {
-        self.song.notes.get(index).map(|n| serde_json::to_string(n).unwrap_or_default())
+    /// Change the length of the note at index
+    #[wasm_bindgen(js_name = setNoteDuration)]
+    pub fn set_note_duration(&mut self, index: usize, duration_ticks: u32) -> Result<(), JsValue> {
+        let mut note = self.note_at(index)?;
+        note.duration_ticks = duration_ticks.max(1);
+        self.try_edit("Resize note", |song| {
+            song.update_note(index, note)
+                .map(|_| ())
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    // ==================== Melody Parsing ====================
+    /// Shift notes in time and pitch together; nothing moves if any would leave the valid range
+    #[wasm_bindgen(js_name = moveNotes)]
+    pub fn move_notes(&mut self, indices: &[u32], delta_ticks: i32, delta_pitch: i16) -> Result<(), JsValue> {
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.try_edit("Move notes", |song| {
+            song.move_notes(&indices, delta_ticks as i64, delta_pitch)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
 
-    /// Parse a melody string and add the notes
-    /// Format: "C4q D4q E4q" (pitch + duration, space-separated)
-    /// Durations: w=whole, h=half, q=quarter, e=eighth, s=sixteenth
-    /// Add . for dotted (e.g., "q." for dotted quarter)
-    /// Use "R" for rests (e.g., "Rq" for quarter rest)
-    #[wasm_bindgen(js_name = parseMelody)]
-    pub fn parse_melody_str(&mut self, melody: &str) -> Result<usize, JsValue> {
-        let notes = parse_melody(melody)
+    /// Add notes from a JSON array (e.g., pasted or imported); returns how many were added
+    #[wasm_bindgen(js_name = addNotesJson)]
+    pub fn add_notes_json(&mut self, json: &str) -> Result<usize, JsValue> {
+        let notes: Vec<Note> = serde_json::from_str(json)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
         let count = notes.len();
+        self.edit("Add notes", |song| song.add_notes(notes));
+        Ok(count)
+    }
 
-        // Auto-detect key from the melody
-        if let Some(detected_scale) = crate::transpose::detect_scale(&notes) {
-            self.song.set_key(detected_scale);
+    /// Add a captured chord (JSON `CapturedKey[]`) at a tick as one edit; returns how many notes were added
+    #[wasm_bindgen(js_name = addChordJson)]
+    pub fn add_chord_json(&mut self, json: &str, start_tick: u32, duration_ticks: u32) -> Result<usize, JsValue> {
+        let keys: Vec<CapturedKey> = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let notes = chord_notes(&keys, start_tick, duration_ticks);
+        let count = notes.len();
+        if count > 0 {
+            self.edit("Add chord", |song| song.add_notes(notes));
         }
-
-        self.song.add_notes(notes);
         Ok(count)
     }
 
-    /// Format all notes as a melody string
-    #[wasm_bindgen(js_name = formatMelody)]
-    pub fn format_melody_str(&self) -> String {
-        format_melody(&self.song.notes)
+    /// Set the stereo pan of a note at index (0=left, 64=center, 127=right)
+    #[wasm_bindgen(js_name = setNotePan)]
+    pub fn set_note_pan(&mut self, index: usize, pan: u8) -> bool {
+        if index >= self.song.notes.len() {
+            return false;
+        }
+        self.edit("Set pan", |song| song.notes[index].set_pan(pan));
+        true
     }
 
-    // ==================== Transposition ====================
+    /// Set the chance the note at index sounds on each pass (0-100%, experimental)
+    #[wasm_bindgen(js_name = setNoteProbability)]
+    pub fn set_note_probability(&mut self, index: usize, probability: u8) -> Result<(), JsValue> {
+        let mut note = self.note_at(index)?;
+        note.set_probability(probability);
+        self.try_edit("Set probability", |song| {
+            song.update_note(index, note)
+                .map(|_| ())
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
 
-    /// Transpose all notes chromatically by semitones
-    /// Positive = up, negative = down
-    #[wasm_bindgen(js_name = transposeChromatic)]
-    pub fn transpose_chromatic(&mut self, semitones: i8) -> Result<(), JsValue> {
-        let mode = TransposeMode::chromatic(semitones);
-        let transposed = transpose_notes(&self.song.notes, &mode)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.song.notes = transposed;
-        Ok(())
+    /// Put the note at index in take lane "A" or "B", or in none for "" (experimental)
+    #[wasm_bindgen(js_name = setNoteTake)]
+    pub fn set_note_take(&mut self, index: usize, take: &str) -> Result<(), JsValue> {
+        let mut note = self.note_at(index)?;
+        note.take = Take::parse(take).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.try_edit("Set take", |song| {
+            song.update_note(index, note)
+                .map(|_| ())
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Transpose all notes diatonically within the current key
-    /// Positive = up, negative = down (in scale degrees)
-    #[wasm_bindgen(js_name = transposeDiatonic)]
-    pub fn transpose_diatonic(&mut self, degrees: i8) -> Result<(), JsValue> {
-        let scale = self.song.settings.key;
-        let mode = TransposeMode::diatonic(scale, degrees);
-        let transposed = transpose_notes(&self.song.notes, &mode)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.song.notes = transposed;
-        Ok(())
+    /// Label notes at indices with a color ("blue"), or clear it with ""; returns how many changed
+    #[wasm_bindgen(js_name = setNoteColor)]
+    pub fn set_note_color(&mut self, indices: &[u32], color: &str) -> Result<usize, JsValue> {
+        let color = ColorLabel::parse(color).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.try_edit("Set color", |song| {
+            song.set_note_color(&indices, color)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Transpose diatonically with a key change
-    #[wasm_bindgen(js_name = transposeDiatonicWithKeyChange)]
-    pub fn transpose_diatonic_with_key_change(
-        &mut self,
-        target_key: &str,
-        degrees: i8,
-    ) -> Result<(), JsValue> {
-        let source_scale = self.song.settings.key;
-        let target_scale = Scale::parse(target_key)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        let mode = TransposeMode::diatonic_with_key_change(source_scale, target_scale, degrees);
-        let transposed = transpose_notes(&self.song.notes, &mode)
-            .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        self.song.notes = transposed;
-        self.song.set_key(target_scale);
-        Ok(())
+    /// Whether any note has a probability or take lane
+    #[wasm_bindgen(js_name = hasChanceNotes)]
+    pub fn has_chance_notes(&self) -> bool {
+        chance::has_chance_notes(&self.song.notes)
     }
 
-    /// Invert all notes around a pivot pitch (mirror reflection)
-    /// new_pitch = 2 * pivot - old_pitch
-    #[wasm_bindgen(js_name = invert)]
-    pub fn invert(&mut self, pivot: u8) -> Result<(), JsValue> {
-        for note in &mut self.song.notes {
-            let new_pitch = (2 * pivot as i16) - (note.pitch as i16);
-            if new_pitch < 0 || new_pitch > 127 {
-                return Err(JsValue::from_str(&format!(
-                    "Inversion would put note out of MIDI range (pivot: {}, original: {}, result: {})",
-                    pivot, note.pitch, new_pitch
-                )));
-            }
-            note.pitch = new_pitch as u8;
+    /// Keep the notes a pass played and drop the chance settings, as one edit; returns how many notes were dropped
+    #[wasm_bindgen(js_name = freezeArrangement)]
+    pub fn freeze_arrangement(&mut self, pass: u32) -> usize {
+        if !self.has_chance_notes() {
+            return 0;
         }
-        Ok(())
+        self.edit("Freeze arrangement", |song| song.freeze_arrangement(pass))
     }
 
-    // ==================== Accents ====================
-
-    /// Get the accent pattern as an array of levels (1=weak, 2=medium, 3=strong)
-    #[wasm_bindgen(js_name = getAccents)]
-    pub fn get_accents(&self) -> Vec<u8> {
-        self.song.settings.time_signature.accents.accents
-            .iter()
-            .map(|a| *a as u8)
-            .collect()
+    /// Whether playback strums, accents, or track volumes change what the notes say
+    #[wasm_bindgen(js_name = hasPlaybackEffects)]
+    pub fn has_playback_effects(&self) -> bool {
+        self.song.has_playback_effects()
     }
 
-    /// Set the accent pattern from an array of levels (1=weak, 2=medium, 3=strong)
-    #[wasm_bindgen(js_name = setAccents)]
-    pub fn set_accents(&mut self, accents: &[u8]) -> Result<(), JsValue> {
-        if accents.len() != self.song.settings.time_signature.numerator as usize {
-            return Err(JsValue::from_str(&format!(
-                "Accent pattern length ({}) must match time signature numerator ({})",
-                accents.len(),
-                self.song.settings.time_signature.numerator
-            )));
+    /// Bake strums, accent dynamics, and track volumes into the notes, as one edit; returns how many notes changed
+    #[wasm_bindgen(js_name = flattenPlaybackEffects)]
+    pub fn flatten_playback_effects(&mut self) -> usize {
+        if !self.has_playback_effects() {
+            return 0;
         }
-        let pattern = AccentPattern::from_values(accents);
-        self.song.settings.time_signature.set_accents(pattern);
-        Ok(())
+        self.edit("Flatten playback effects", |song| song.flatten_playback_effects())
     }
 
-    /// Cycle accent at beat index (weak -> medium -> strong -> weak)
-    #[wasm_bindgen(js_name = cycleAccent)]
-    pub fn cycle_accent(&mut self, beat: usize) {
-        self.song.settings.time_signature.accents.cycle(beat);
+    /// Split a note in two at a tick; returns the index of the second half
+    #[wasm_bindgen(js_name = splitNote)]
+    pub fn split_note(&mut self, index: usize, at_tick: u32) -> Result<usize, JsValue> {
+        self.try_edit("Split note", |song| {
+            song.split_note(index, at_tick)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    /// Get accent pattern as visual string (e.g., ">.-.")
-    #[wasm_bindgen(js_name = getAccentVisual)]
-    pub fn get_accent_visual(&self) -> String {
-        self.song.settings.time_signature.accents.to_visual()
+    /// Merge adjacent same-pitch notes; returns the index of the merged note
+    #[wasm_bindgen(js_name = joinNotes)]
+    pub fn join_notes(&mut self, indices: &[u32]) -> Result<usize, JsValue> {
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.try_edit("Join notes", |song| {
+            song.join_notes(&indices)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
     }
 
-    // ==================== Duration Info ====================
+    /// Extend selected notes to reach the next selected note (plus optional overlap)
+    #[wasm_bindgen(js_name = makeLegato)]
+    pub fn make_legato(&mut self, indices: &[u32], overlap_ticks: u32) -> usize {
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.edit("Legato", |song| song.make_legato(&indices, overlap_ticks))
+    }
 
-    /// Get total duration in ticks
-    #[wasm_bindgen(js_name = durationTicks)]
-    pub fn duration_ticks(&self) -> u32 {
-        self.song.duration_ticks()
+    /// Shorten selected notes to leave a gap before the next selected note
+    #[wasm_bindgen(js_name = addGap)]
+    pub fn add_gap(&mut self, indices: &[u32], gap_ticks: u32) -> usize {
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        self.edit("Add gap", |song| song.add_gap(&indices, gap_ticks))
     }
 
-    /// Get total duration in seconds
-    #[wasm_bindgen(js_name = durationSeconds)]
-    pub fn duration_seconds(&self) -> f64 {
-        self.song.duration_seconds()
+    /// Strum chords among the selected notes (direction: "up" or "down"); returns chords changed
+    #[wasm_bindgen(js_name = strumChords)]
+    pub fn strum_chords(&mut self, indices: &[u32], spread_ticks: u32, direction: &str) -> Result<usize, JsValue> {
+        let direction = StrumDirection::parse(direction)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        Ok(self.edit("Strum", |song| song.strum_chords(&indices, spread_ticks, direction)))
     }
 
-    /// Get number of measures
-    #[wasm_bindgen(js_name = measureCount)]
-    pub fn measure_count(&self) -> u32 {
-        self.song.measure_count()
+    /// Move the lowest note of each selected chord up an octave (or more) to the top; returns chords changed
+    #[wasm_bindgen(js_name = invertChordUp)]
+    pub fn invert_chord_up(&mut self, indices: &[u32]) -> usize {
+        self.voice_chords(indices, Voicing::InvertUp)
     }
 
-    /// Get ticks per beat
-    #[wasm_bindgen(js_name = ticksPerBeat)]
-    pub fn ticks_per_beat(&self) -> u32 {
-        self.song.settings.time_signature.ticks_per_beat()
+    /// Move the highest note of each selected chord down to the bottom; returns chords changed
+    #[wasm_bindgen(js_name = invertChordDown)]
+    pub fn invert_chord_down(&mut self, indices: &[u32]) -> usize {
+        self.voice_chords(indices, Voicing::InvertDown)
     }
 
-    /// Get ticks per measure
-    #[wasm_bindgen(js_name = ticksPerMeasure)]
-    pub fn ticks_per_measure(&self) -> u32 {
-        self.song.settings.time_signature.ticks_per_measure()
+    /// Spread each selected chord by lifting every second voice above the bass an octave
+    #[wasm_bindgen(js_name = openVoicing)]
+    pub fn open_voicing(&mut self, indices: &[u32]) -> usize {
+        self.voice_chords(indices, Voicing::Open)
     }
 
-    // ==================== Serialization ====================
+    /// Pack each selected chord into the octave above its bass
+    #[wasm_bindgen(js_name = closeVoicing)]
+    pub fn close_voicing(&mut self, indices: &[u32]) -> usize {
+        self.voice_chords(indices, Voicing::Close)
+    }
 
-    /// Export the song to JSON
-    #[wasm_bindgen(js_name = toJson)]
-    pub fn to_json(&self) -> Result<String, JsValue> {
-        self.song.to_json()
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    /// Groove of the selected notes against `subdivisions` per beat of their
+    /// meter (their voice's, if they share one), as JSON `GrooveTemplate`
+    #[wasm_bindgen(js_name = extractGrooveJson)]
+    pub fn extract_groove_json(&self, indices: &[u32], subdivisions: u8) -> Result<String, JsValue> {
+        let notes: Vec<&Note> = indices.iter().filter_map(|&i| self.song.notes.get(i as usize)).collect();
+        let meter = match notes.first() {
+            Some(first) if notes.iter().all(|n| n.voice == first.voice) => self.song.voice_time_signature(first.voice),
+            _ => &self.song.settings.time_signature,
+        };
+        let template = extract_groove(notes, meter.ticks_per_beat(), subdivisions)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&template).map_err(|e| JsValue::from_str(&e.to_string()))
     }
 
-    /// Load a song from JSON
-    #[wasm_bindgen(js_name = fromJson)]
-    pub fn from_json(json: &str) -> Result<Mozart, JsValue> {
-        let song = Song::from_json(json)
+    /// Impose a JSON `GrooveTemplate` on the selected notes; returns notes changed
+    #[wasm_bindgen(js_name = applyGrooveJson)]
+    pub fn apply_groove_json(&mut self, template_json: &str, indices: &[u32]) -> Result<usize, JsValue> {
+        let template: GrooveTemplate = serde_json::from_str(template_json)
             .map_err(|e| JsValue::from_str(&e.to_string()))?;
-        Ok(Mozart { song })
+        if template.subdivisions == 0 || template.slots.len() != template.subdivisions as usize {
+            return Err(JsValue::from_str("Groove template needs one slot per subdivision"));
+        }
+        let indices: Vec<usize> = indices.iter().map(|&i| i as usize).collect();
+        Ok(self.edit("Apply groove", |song| song.apply_groove(&indices, &template)))
     }
 
-    /// Export to MIDI bytes
-    #[wasm_bindgen(js_name = toMidi)]
-    pub fn to_midi(&self) -> Result<Vec<u8>, JsValue> {
-        export_to_midi(&self.song)
-            .map_err(|e| JsValue::from_str(&e.to_string()))
+    /// Candidate variations of the selected phrase as JSON `Variation[]`,
+    /// kept until one is applied
+    #[wasm_bindgen(js_name = generateVariationsJson)]
+    pub fn generate_variations_json(&mut self, indices: &[u32], count: usize, params_json: &str) -> Result<String, JsValue> {
+        let params: VariationParams = if params_json.trim().is_empty() {
+            VariationParams::default()
+        } else {
+            serde_json::from_str(params_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let phrase: Vec<Note> = indices.iter().filter_map(|&i| self.song.notes.get(i as usize).cloned()).collect();
+        let variations = generate_variations(&phrase, &self.song.settings.key, count, &params)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let json = serde_json::to_string(&variations).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.variations = Some((phrase, variations));
+        Ok(json)
     }
 
-    // ==================== Pitch Utilities ====================
+    /// Notes of a candidate variation as JSON `AuditionNote[]`, from the phrase's start
+    #[wasm_bindgen(js_name = variationAuditionJson)]
+    pub fn variation_audition_json(&self, index: usize) -> Result<String, JsValue> {
+        let variation = self.variation(index)?;
+        serde_json::to_string(&phrase_audition(&self.song, &variation.notes)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
 
-    /// Get the frequency in Hz for a MIDI note number
-    #[wasm_bindgen(js_name = midiToFrequency)]
-    pub fn midi_to_frequency(midi: u8) -> f64 {
-        Pitch::from_midi(midi)
-            .map(|p| p.frequency())
-            .unwrap_or(0.0)
+    /// Replace the phrase with a candidate variation; returns notes written
+    #[wasm_bindgen(js_name = applyVariation)]
+    pub fn apply_variation(&mut self, index: usize) -> Result<usize, JsValue> {
+        let variation = self.variation(index)?.clone();
+        let phrase = self.variations.as_ref().map(|(phrase, _)| phrase.clone()).unwrap_or_default();
+        let written = self.try_edit("Apply variation", |song| {
+            apply_variation(song, &phrase, &variation).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        self.variations = None;
+        Ok(written)
     }
 
-    /// Get the note name for a MIDI note number (e.g., "C4", "F#5")
-    #[wasm_bindgen(js_name = midiToNoteName)]
-    pub fn midi_to_note_name(midi: u8) -> String {
-        Pitch::from_midi(midi)
-            .map(|p| p.to_string())
-            .unwrap_or_else(|_| "?".to_string())
+    /// Drop the candidate variations without applying one
+    #[wasm_bindgen(js_name = clearVariations)]
+    pub fn clear_variations(&mut self) {
+        self.variations = None;
     }
 
-    /// Parse a note name to MIDI number
-    #[wasm_bindgen(js_name = noteNameToMidi)]
-    pub fn note_name_to_midi(name: &str) -> Result<u8, JsValue> {
+    /// Continue a voice's line (JSON `ContinuationConfig`, empty for the
+    /// defaults) from a model of the bundled tunes and this song; returns JSON
+    /// `Continuation`, kept as a preview until accepted
+    #[wasm_bindgen(js_name = continueMelodyJson)]
+    pub fn continue_melody_json(&mut self, config_json: &str) -> Result<String, JsValue> {
+        let config: ContinuationConfig = if config_json.trim().is_empty() {
+            ContinuationConfig::default()
+        } else {
+            serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let mut model = MelodyModel::bundled();
+        model.learn(&self.song);
+        let continuation = continue_melody(&self.song, &model, &config).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let json = serde_json::to_string(&continuation).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.continuation = Some(continuation);
+        Ok(json)
+    }
+
+    /// Notes of the previewed continuation as JSON `AuditionNote[]`, from its start
+    #[wasm_bindgen(js_name = continuationAuditionJson)]
+    pub fn continuation_audition_json(&self) -> Result<String, JsValue> {
+        let continuation = self.continuation.as_ref().ok_or_else(|| JsValue::from_str("No continuation to play"))?;
+        serde_json::to_string(&phrase_audition(&self.song, &continuation.notes)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Add the previewed continuation to the song; returns notes written
+    #[wasm_bindgen(js_name = acceptContinuation)]
+    pub fn accept_continuation(&mut self) -> Result<usize, JsValue> {
+        let continuation = self.continuation.clone().ok_or_else(|| JsValue::from_str("No continuation to keep"))?;
+        let written = self.try_edit("Continue melody", |song| {
+            apply_continuation(song, &continuation).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        self.continuation = None;
+        Ok(written)
+    }
+
+    /// Drop the previewed continuation
+    #[wasm_bindgen(js_name = rejectContinuation)]
+    pub fn reject_continuation(&mut self) {
+        self.continuation = None;
+    }
+
+    /// The triads and seventh chords on each degree of the song's key as JSON
+    /// `DiatonicChord[]` (for a chord ladder)
+    #[wasm_bindgen(js_name = getDiatonicChordsJson)]
+    pub fn get_diatonic_chords_json(&self) -> String {
+        serde_json::to_string(&diatonic_chords(&self.song.settings.key)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Rank chords of the song's key to follow a progression (JSON array of
+    /// numerals or symbols); returns JSON `ChordSuggestion[]`, best first
+    #[wasm_bindgen(js_name = suggestNextChordsJson)]
+    pub fn suggest_next_chords_json(&self, progression_json: &str) -> Result<String, JsValue> {
+        let progression: Vec<String> = serde_json::from_str(progression_json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let suggestions = suggest_next_chords(&progression, &self.song.settings.key)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&suggestions).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Propose chords for a measure (1-based) that hold its melody; constraints
+    /// are JSON `ReharmonizeConstraints` (empty for the defaults); returns
+    /// JSON `Reharmonization[]`, best first
+    #[wasm_bindgen(js_name = reharmonizeMeasureJson)]
+    pub fn reharmonize_measure_json(&self, measure: u32, constraints_json: &str) -> Result<String, JsValue> {
+        let constraints: ReharmonizeConstraints = if constraints_json.trim().is_empty() {
+            ReharmonizeConstraints::default()
+        } else {
+            serde_json::from_str(constraints_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let options = reharmonize_measure(&self.song, measure, &constraints)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&options).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Ways to modulate from the song's key to another (e.g., "G major"),
+    /// starting at a measure; returns JSON `ModulationPlan[]`, best first
+    #[wasm_bindgen(js_name = planModulationJson)]
+    pub fn plan_modulation_json(&self, to_key: &str, start_measure: u32) -> Result<String, JsValue> {
+        let to = Scale::parse(to_key).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let plans = plan_modulation(&self.song.settings.key, &to, start_measure)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&plans).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Write a JSON `ModulationPlan`'s chords into the song and mark the new
+    /// key's arrival; returns how many notes were added
+    #[wasm_bindgen(js_name = applyModulationJson)]
+    pub fn apply_modulation_json(&mut self, plan_json: &str) -> Result<usize, JsValue> {
+        let plan: ModulationPlan = serde_json::from_str(plan_json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.try_edit("Apply modulation", |song| {
+            apply_modulation(song, &plan).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Write a first-species counterpoint against a voice (JSON
+    /// `CounterpointConfig`, empty for the defaults), replacing the target
+    /// voice; returns JSON `CounterpointReport`
+    #[wasm_bindgen(js_name = generateCounterpointJson)]
+    pub fn generate_counterpoint_json(&mut self, config_json: &str) -> Result<String, JsValue> {
+        let config: CounterpointConfig = if config_json.trim().is_empty() {
+            CounterpointConfig::default()
+        } else {
+            serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let report = self.try_edit("Generate counterpoint", |song| {
+            write_counterpoint(song, &config).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Write a melody over a chord chart (JSON `MelodyConfig`), replacing the
+    /// target voice; returns JSON `MelodyReport`
+    #[wasm_bindgen(js_name = generateMelodyJson)]
+    pub fn generate_melody_json(&mut self, config_json: &str) -> Result<String, JsValue> {
+        let config: MelodyConfig = serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let report = self.try_edit("Generate melody", |song| {
+            generate_melody(song, &config).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Load a bundled odd-meter groove (meter, accents, tempo, and drums) by
+    /// name; returns JSON `GroovePreset`
+    #[wasm_bindgen(js_name = loadGroovePreset)]
+    pub fn load_groove_preset(&mut self, name: &str) -> Result<String, JsValue> {
+        let preset = self.try_edit("Load groove preset", |song| {
+            load_groove_preset(song, name).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&preset).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Write a plain-text chord chart ("| C . . . | Am . F . |") into the song
+    /// from measure 1; returns JSON `ChartChord[]`
+    #[wasm_bindgen(js_name = importChordChartJson)]
+    pub fn import_chord_chart_json(&mut self, text: &str) -> Result<String, JsValue> {
+        let chords = self.try_edit("Import chord chart", |song| {
+            import_chord_chart(song, text).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&chords).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Strum the chord at a tick during playback only
+    #[wasm_bindgen(js_name = setChordStrum)]
+    pub fn set_chord_strum(&mut self, tick: u32, spread_ticks: u32, direction: &str) -> Result<(), JsValue> {
+        let direction = StrumDirection::parse(direction)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Set strum", |song| song.set_chord_strum(tick, spread_ticks, direction));
+        Ok(())
+    }
+
+    /// Remove the playback strum at a tick
+    #[wasm_bindgen(js_name = removeChordStrum)]
+    pub fn remove_chord_strum(&mut self, tick: u32) -> bool {
+        if !self.song.strums.iter().any(|s| s.tick == tick) {
+            return false;
+        }
+        self.edit("Remove strum", |song| song.remove_chord_strum(tick).is_some())
+    }
+
+    /// Take a chord symbol ("F#m7b5", "G/B") apart; returns JSON `Chord`
+    #[wasm_bindgen(js_name = parseChordJson)]
+    pub fn parse_chord_json(&self, symbol: &str) -> Result<String, JsValue> {
+        let chord = Chord::parse(symbol).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&chord).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Get the chord track as JSON `ChordSymbol[]`
+    #[wasm_bindgen(js_name = getChordsJson)]
+    pub fn get_chords_json(&self) -> String {
+        serde_json::to_string(&self.song.chords).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Put a chord symbol on the chord track at a tick, or change the one there
+    #[wasm_bindgen(js_name = setChord)]
+    pub fn set_chord(&mut self, tick: u32, symbol: &str) -> Result<(), JsValue> {
+        self.try_edit("Set chord", |song| {
+            song.set_chord(tick, symbol).map(|_| ()).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Remove the chord symbol at a tick
+    #[wasm_bindgen(js_name = removeChord)]
+    pub fn remove_chord(&mut self, tick: u32) -> bool {
+        if !self.song.chords.iter().any(|c| c.tick == tick) {
+            return false;
+        }
+        self.edit("Remove chord", |song| song.remove_chord(tick).is_some())
+    }
+
+    /// Get the song's patterns as JSON `Pattern[]`
+    #[wasm_bindgen(js_name = getPatternsJson)]
+    pub fn get_patterns_json(&self) -> String {
+        serde_json::to_string(&self.song.patterns).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the pattern chain as written, e.g. "A A B A" ('' without one)
+    #[wasm_bindgen(js_name = getPatternChain)]
+    pub fn get_pattern_chain(&self) -> String {
+        self.song.pattern_chain.join(" ")
+    }
+
+    /// Cut measures (1-indexed) into a new pattern
+    #[wasm_bindgen(js_name = createPattern)]
+    pub fn create_pattern(&mut self, name: &str, first_measure: u32, count: u32) -> Result<(), JsValue> {
+        self.try_edit("Create pattern", |song| {
+            song.create_pattern(name, first_measure, count).map(|_| ()).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Copy a pattern under a new name
+    #[wasm_bindgen(js_name = clonePattern)]
+    pub fn clone_pattern(&mut self, name: &str, new_name: &str) -> Result<(), JsValue> {
+        self.try_edit("Clone pattern", |song| {
+            song.clone_pattern(name, new_name).map(|_| ()).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Move a pattern to another place in the list of patterns
+    #[wasm_bindgen(js_name = movePattern)]
+    pub fn move_pattern(&mut self, from: usize, to: usize) -> Result<(), JsValue> {
+        self.try_edit("Move pattern", |song| song.move_pattern(from, to).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Delete a pattern and its places in the chain
+    #[wasm_bindgen(js_name = removePattern)]
+    pub fn remove_pattern(&mut self, name: &str) -> bool {
+        if self.song.pattern(name).is_none() {
+            return false;
+        }
+        self.edit("Remove pattern", |song| song.remove_pattern(name).is_some())
+    }
+
+    /// Set the order patterns play in, e.g. "A A B A" ('' clears it)
+    #[wasm_bindgen(js_name = setPatternChain)]
+    pub fn set_pattern_chain(&mut self, text: &str) -> Result<(), JsValue> {
+        self.try_edit("Set pattern chain", |song| song.set_pattern_chain(text).map_err(|e| JsValue::from_str(&e.to_string())))
+    }
+
+    /// Get the notes as played (playback strums applied) as JSON
+    #[wasm_bindgen(js_name = getPlaybackNotesJson)]
+    pub fn get_playback_notes_json(&self) -> String {
+        serde_json::to_string(&self.song.playback_notes()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the notes a pass through the song plays (0 is the first) as JSON, playback effects applied
+    #[wasm_bindgen(js_name = getPlaybackNotesForPassJson)]
+    pub fn get_playback_notes_for_pass_json(&self, pass: u32) -> String {
+        serde_json::to_string(&self.song.playback_notes_for_pass(pass)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Remove a note at index
+    #[wasm_bindgen(js_name = removeNote)]
+    pub fn remove_note(&mut self, index: usize) -> bool {
+        if index >= self.song.notes.len() {
+            return false;
+        }
+        self.edit("Remove note", |song| song.remove_note(index).is_some())
+    }
+
+    /// Clear all notes, asking first unless `confirm` (JSON `Outcome<null>`)
+    #[wasm_bindgen(js_name = clearNotes)]
+    pub fn clear_notes(&mut self, confirm: bool) -> String {
+        let loss = clear_notes_loss(&self.song);
+        let outcome = confirm_or_run(loss, confirm, || self.edit("Clear notes", |song| song.clear_notes()));
+        serde_json::to_string(&outcome).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Delete `count` measures from `first` (1-indexed), asking first unless
+    /// `confirm` if they hold anything (JSON `Outcome<number>`, the notes removed)
+    #[wasm_bindgen(js_name = deleteMeasures)]
+    pub fn delete_measures(&mut self, first: u32, count: u32, confirm: bool) -> Result<String, JsValue> {
+        let loss = delete_measures_loss(&self.song, first, count);
+        let outcome = confirm_or_run(loss, confirm, || {
+            self.try_edit("Delete measures", |song| {
+                song.delete_measures(first, count).map_err(|e| JsValue::from_str(&e.to_string()))
+            })
+        })
+        .transpose()?;
+        serde_json::to_string(&outcome).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Whether a new song may replace this one: JSON `Outcome<null>`, asking
+    /// first while there are unsaved changes unless `confirm`
+    #[wasm_bindgen(js_name = checkNewSong)]
+    pub fn check_new_song(&self, confirm: bool) -> String {
+        let outcome = confirm_or_run(new_song_loss(&self.song, self.is_dirty()), confirm, || ());
+        serde_json::to_string(&outcome).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Get all notes as JSON array
+    #[wasm_bindgen(js_name = getNotesJson)]
+    pub fn get_notes_json(&self) -> String {
+        serde_json::to_string(&self.song.notes).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the notes sounding within a tick range, clipped to it, as JSON array
+    #[wasm_bindgen(js_name = getNotesInRangeJson)]
+    pub fn get_notes_in_range_json(&self, start_tick: u32, end_tick: u32) -> String {
+        serde_json::to_string(&self.song.notes_in_range(start_tick, end_tick))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the notes overlapping a tick range as JSON, unclipped and with their indices
+    ///
+    /// Lets a viewport fetch only what it shows; refetch when a `notes:dirty`
+    /// change overlaps the range.
+    #[wasm_bindgen(js_name = getNoteWindowJson)]
+    pub fn get_note_window_json(&self, start_tick: u32, end_tick: u32) -> String {
+        serde_json::to_string(&self.song.note_window(start_tick, end_tick))
+            .unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get a single note as JSON
+    #[wasm_bindgen(js_name = getNoteJson)]
+    pub fn get_note_json(&self, index: usize) -> Option<String> {
+        self.song.notes.get(index).map(|n| serde_json::to_string(n).unwrap_or_default())
+    }
+
+    // ==================== Melody Parsing ====================
+
+    /// Parse a melody string and add the notes
+    /// Format: "C4q D4q E4q" (pitch + duration, space-separated)
+    /// Durations: w=whole, h=half, q=quarter, e=eighth, s=sixteenth
+    /// Add . for dotted (e.g., "q." for dotted quarter)
+    /// Use "R" for rests (e.g., "Rq" for quarter rest), and "x" for rhythm
+    /// hits at the song's rhythm pitch (e.g., "xq xe xe")
+    #[wasm_bindgen(js_name = parseMelody)]
+    pub fn parse_melody_str(&mut self, melody: &str) -> Result<usize, JsValue> {
+        let notes = self.song.parse_melody(melody)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let count = notes.len();
+
+        // Auto-detect key from the melody (the pitch of rhythm hits says nothing about it)
+        let pitched = pitched_notes(melody).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        self.edit("Parse melody", |song| {
+            if let Some(detected_scale) = crate::transpose::detect_scale(&pitched) {
+                song.set_key(detected_scale);
+            }
+
+            song.add_notes(notes);
+        });
+        Ok(count)
+    }
+
+    /// Format all notes as a melody string
+    #[wasm_bindgen(js_name = formatMelody)]
+    pub fn format_melody_str(&self) -> String {
+        format_melody(&self.song.notes)
+    }
+
+    /// Completions for a melody being typed (possibly ending in a half-typed
+    /// token) in the song's key; returns JSON `TokenSuggestion[]`, most likely first
+    #[wasm_bindgen(js_name = suggestNextTokensJson)]
+    pub fn suggest_next_tokens_json(&self, partial_text: &str) -> Result<String, JsValue> {
+        let suggestions = suggest_next_tokens(partial_text, &self.song.settings.key)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&suggestions).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // ==================== Transposition ====================
+
+    /// Transpose all notes chromatically by semitones
+    /// Positive = up, negative = down
+    #[wasm_bindgen(js_name = transposeChromatic)]
+    pub fn transpose_chromatic(&mut self, semitones: i8) -> Result<(), JsValue> {
+        let mode = TransposeMode::chromatic(semitones);
+        let transposed = transpose_notes(&self.song.notes, &mode)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Transpose", |song| song.notes = transposed);
+        Ok(())
+    }
+
+    /// Transpose all notes diatonically within the current key
+    /// Positive = up, negative = down (in scale degrees)
+    #[wasm_bindgen(js_name = transposeDiatonic)]
+    pub fn transpose_diatonic(&mut self, degrees: i8) -> Result<(), JsValue> {
+        let scale = self.song.settings.key;
+        let mode = TransposeMode::diatonic(scale, degrees);
+        let transposed = transpose_notes(&self.song.notes, &mode)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Transpose", |song| song.notes = transposed);
+        Ok(())
+    }
+
+    /// Transpose diatonically with a key change
+    #[wasm_bindgen(js_name = transposeDiatonicWithKeyChange)]
+    pub fn transpose_diatonic_with_key_change(
+        &mut self,
+        target_key: &str,
+        degrees: i8,
+    ) -> Result<(), JsValue> {
+        let source_scale = self.song.settings.key;
+        let target_scale = Scale::parse(target_key)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mode = TransposeMode::diatonic_with_key_change(source_scale, target_scale, degrees);
+        let transposed = transpose_notes(&self.song.notes, &mode)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit("Transpose", |song| {
+            song.notes = transposed;
+            song.set_key(target_scale);
+        });
+        Ok(())
+    }
+
+    /// Invert all notes around a pivot pitch (mirror reflection)
+    /// new_pitch = 2 * pivot - old_pitch
+    #[wasm_bindgen(js_name = invert)]
+    pub fn invert(&mut self, pivot: u8) -> Result<(), JsValue> {
+        self.try_edit("Invert", |song| {
+            for note in &mut song.notes {
+                let new_pitch = (2 * pivot as i16) - (note.pitch as i16);
+                if new_pitch < 0 || new_pitch > 127 {
+                    return Err(JsValue::from_str(&format!(
+                        "Inversion would put note out of MIDI range (pivot: {}, original: {}, result: {})",
+                        pivot, note.pitch, new_pitch
+                    )));
+                }
+                note.pitch = new_pitch as u8;
+            }
+            Ok(())
+        })
+    }
+
+    // ==================== Validation ====================
+
+    /// Check the song for note problems; returns a JSON array of issues
+    #[wasm_bindgen(js_name = validateSongJson)]
+    pub fn validate_song_json(&self) -> String {
+        serde_json::to_string(&validate::validate_song(&self.song)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Fix the issue classes selected in the options JSON (missing fields use defaults)
+    ///
+    /// Returns a JSON report of how many notes were removed and modified.
+    #[wasm_bindgen(js_name = repairSong)]
+    pub fn repair_song(&mut self, options_json: &str) -> Result<String, JsValue> {
+        let options: RepairOptions = serde_json::from_str(options_json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let report = self.edit("Repair song", |song| validate::repair_song(song, &options));
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Remove duplicate and zero-length notes and merge near-identical overlaps
+    ///
+    /// With dryRun the song is unchanged; the JSON report says what would be
+    /// removed either way.
+    #[wasm_bindgen(js_name = cleanupSongJson)]
+    pub fn cleanup_song_json(&mut self, dry_run: bool) -> String {
+        let report = if dry_run {
+            validate::cleanup_song(&mut self.song.clone(), true)
+        } else {
+            self.edit("Clean up notes", |song| validate::cleanup_song(song, false))
+        };
+        serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Rescale every note's velocity onto min..=max, keeping relative dynamics
+    ///
+    /// Returns the number of notes changed.
+    #[wasm_bindgen(js_name = normalizeVelocities)]
+    pub fn normalize_velocities(&mut self, target_min: u8, target_max: u8) -> Result<usize, JsValue> {
+        self.try_edit("Normalize velocities", |song| {
+            validate::normalize_velocities(song, target_min, target_max).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Divide how far velocities exceed the threshold by ratio
+    ///
+    /// Returns the number of notes changed.
+    #[wasm_bindgen(js_name = compressVelocities)]
+    pub fn compress_velocities(&mut self, ratio: f32, threshold: u8) -> Result<usize, JsValue> {
+        self.try_edit("Compress velocities", |song| {
+            validate::compress_velocities(song, ratio, threshold).map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    // ==================== View State ====================
+
+    /// Get the saved editor view state as JSON
+    #[wasm_bindgen(js_name = getViewStateJson)]
+    pub fn get_view_state_json(&self) -> String {
+        serde_json::to_string(&self.song.view).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Store editor view state (saved with the song, not part of undo)
+    #[wasm_bindgen(js_name = setViewStateJson)]
+    pub fn set_view_state_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let view: ViewState = serde_json::from_str(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.song.view = view;
+        Ok(())
+    }
+
+    /// Lowest and highest piano roll row for a `PitchView` JSON (empty for the default fit)
+    ///
+    /// Returns `[low, high]`.
+    #[wasm_bindgen(js_name = getVisiblePitchRange)]
+    pub fn get_visible_pitch_range(&self, pitch_view_json: &str) -> Result<Vec<u8>, JsValue> {
+        let view: PitchView = if pitch_view_json.trim().is_empty() {
+            PitchView::default()
+        } else {
+            serde_json::from_str(pitch_view_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let (low, high) = view.visible_range(&self.song.notes);
+        Ok(vec![low, high])
+    }
+
+    /// Loop region as `[start, end]` written ticks (empty when not looping)
+    #[wasm_bindgen(js_name = getLoopRegion)]
+    pub fn get_loop_region(&self) -> Vec<u32> {
+        self.song.view.loop_region.map(|(start, end)| vec![start, end]).unwrap_or_default()
+    }
+
+    /// Loop the measure containing `tick`; returns `[start, end]`
+    #[wasm_bindgen(js_name = loopCurrentMeasure)]
+    pub fn loop_current_measure(&mut self, tick: u32) -> Result<Vec<u32>, JsValue> {
+        let (start, end) = self.song.loop_current_measure(tick).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(vec![start, end])
+    }
+
+    /// Move the loop forward by its length (or loop the measure after
+    /// `tick`'s); returns `[start, end]`
+    #[wasm_bindgen(js_name = loopNextMeasure)]
+    pub fn loop_next_measure(&mut self, tick: u32) -> Result<Vec<u32>, JsValue> {
+        let (start, end) = self.song.loop_next_measure(tick).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(vec![start, end])
+    }
+
+    /// Move the loop back by its length; returns `[start, end]`
+    #[wasm_bindgen(js_name = loopPreviousMeasure)]
+    pub fn loop_previous_measure(&mut self, tick: u32) -> Result<Vec<u32>, JsValue> {
+        let (start, end) = self.song.loop_previous_measure(tick).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(vec![start, end])
+    }
+
+    /// Add the next measure to the loop (or loop `tick`'s measure); returns `[start, end]`
+    #[wasm_bindgen(js_name = extendLoopByMeasure)]
+    pub fn extend_loop_by_measure(&mut self, tick: u32) -> Result<Vec<u32>, JsValue> {
+        let (start, end) = self.song.extend_loop_by_measure(tick).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(vec![start, end])
+    }
+
+    /// Stop looping; returns whether there was a loop
+    #[wasm_bindgen(js_name = clearLoop)]
+    pub fn clear_loop(&mut self) -> bool {
+        self.song.clear_loop().is_some()
+    }
+
+    // ==================== Change Events ====================
+
+    /// Take the changes made since the last call as a JSON array of `SongChange`
+    ///
+    /// Applying them in order to the previously fetched state reproduces the
+    /// current song, so hosts need not refetch everything after each edit.
+    #[wasm_bindgen(js_name = takeChangesJson)]
+    pub fn take_changes_json(&mut self) -> String {
+        let changes = std::mem::take(&mut self.changes);
+        serde_json::to_string(&changes).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // ==================== Undo/Redo ====================
+
+    /// Undo the last edit (returns false if there is nothing to undo)
+    #[wasm_bindgen(js_name = undo)]
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(&self.song) {
+            Some(mut song) => {
+                // View state is not part of undo
+                song.view = std::mem::take(&mut self.song.view);
+                self.push_changes(diff_songs(&self.song, &song));
+                self.song = song;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone edit (returns false if there is nothing to redo)
+    #[wasm_bindgen(js_name = redo)]
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(&self.song) {
+            Some(mut song) => {
+                song.view = std::mem::take(&mut self.song.view);
+                self.push_changes(diff_songs(&self.song, &song));
+                self.song = song;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[wasm_bindgen(js_name = canUndo)]
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    #[wasm_bindgen(js_name = canRedo)]
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Label of the edit that undo would revert (e.g., "Add note")
+    #[wasm_bindgen(js_name = undoLabel)]
+    pub fn undo_label(&self) -> Option<String> {
+        self.history.undo_label().map(String::from)
+    }
+
+    /// Label of the edit that redo would reapply
+    #[wasm_bindgen(js_name = redoLabel)]
+    pub fn redo_label(&self) -> Option<String> {
+        self.history.redo_label().map(String::from)
+    }
+
+    /// Record the current song as saved (clears the dirty state)
+    #[wasm_bindgen(js_name = markSaved)]
+    pub fn mark_saved(&mut self) {
+        self.saved = self.song.clone();
+    }
+
+    /// Whether the song differs from the last save (undoing back to it counts as clean)
+    #[wasm_bindgen(js_name = isDirty)]
+    pub fn is_dirty(&self) -> bool {
+        !diff_songs(&self.saved, &self.song).is_empty()
+    }
+
+    /// Set the maximum number of undo steps
+    #[wasm_bindgen(js_name = setUndoLimit)]
+    pub fn set_undo_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Set the approximate memory budget for undo snapshots, in bytes
+    #[wasm_bindgen(js_name = setUndoMemoryLimit)]
+    pub fn set_undo_memory_limit(&mut self, bytes: usize) {
+        self.history.set_memory_limit(bytes);
+    }
+
+    /// Merge repeated edits with the same label within this window (ms); 0 disables
+    #[wasm_bindgen(js_name = setUndoCoalesceWindow)]
+    pub fn set_undo_coalesce_window(&mut self, window_ms: u32) {
+        self.history.set_coalesce_window(window_ms as u64);
+    }
+
+    /// Approximate memory used by undo history, in bytes
+    #[wasm_bindgen(js_name = undoMemoryUsage)]
+    pub fn undo_memory_usage(&self) -> usize {
+        self.history.memory_usage()
+    }
+
+    // ==================== Tutorial ====================
+
+    /// Start (or restart) the onboarding tutorial from the first step
+    #[wasm_bindgen(js_name = tutorialStart)]
+    pub fn tutorial_start(&mut self) {
+        self.tutorial = Some(Tutorial::new(&self.song));
+    }
+
+    /// Close the tutorial
+    #[wasm_bindgen(js_name = tutorialStop)]
+    pub fn tutorial_stop(&mut self) {
+        self.tutorial = None;
+    }
+
+    /// Tutorial progress as JSON `TutorialState`, checked against the current song ("null" when closed)
+    #[wasm_bindgen(js_name = tutorialStateJson)]
+    pub fn tutorial_state_json(&self) -> String {
+        let state = self.tutorial.as_ref().map(|t| t.state(&self.song));
+        serde_json::to_string(&state).unwrap_or_else(|_| "null".to_string())
+    }
+
+    /// Advance past the current step; fails if it has not been done yet
+    #[wasm_bindgen(js_name = tutorialNext)]
+    pub fn tutorial_next(&mut self) -> Result<(), JsValue> {
+        let tutorial = self.tutorial.as_mut().ok_or_else(|| JsValue::from_str("The tutorial is not running"))?;
+        tutorial.next(&self.song).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Move on without doing the current step
+    #[wasm_bindgen(js_name = tutorialSkip)]
+    pub fn tutorial_skip(&mut self) {
+        if let Some(tutorial) = self.tutorial.as_mut() {
+            tutorial.skip(&self.song);
+        }
+    }
+
+    // ==================== Practice ====================
+
+    /// Start a call-and-response round from a JSON `CallResponseConfig` ("" for
+    /// the defaults); returns the phrase to play as JSON `CallResponseInfo`
+    ///
+    /// Times passed to the other practice methods are seconds since the
+    /// host started playing the phrase.
+    #[wasm_bindgen(js_name = startCallResponseJson)]
+    pub fn start_call_response_json(&mut self, config_json: &str) -> Result<String, JsValue> {
+        let config: CallResponseConfig = if config_json.trim().is_empty() {
+            CallResponseConfig::default()
+        } else {
+            serde_json::from_str(config_json).map_err(|e| JsValue::from_str(&e.to_string()))?
+        };
+        let round = CallResponse::new(&self.song, &config).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let info = serde_json::to_string(&round.info()).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.practice = Some(round);
+        Ok(info)
+    }
+
+    /// Record a key press from the user's MIDI keyboard
+    #[wasm_bindgen(js_name = callResponseNoteOn)]
+    pub fn call_response_note_on(&mut self, pitch: u8, velocity: u8, seconds: f64) {
+        if let Some(round) = self.practice.as_mut() {
+            round.note_on(pitch, velocity, seconds);
+        }
+    }
+
+    /// Move the round's clock; returns JSON `CallResponseEvent[]`
+    #[wasm_bindgen(js_name = advanceCallResponseJson)]
+    pub fn advance_call_response_json(&mut self, seconds: f64) -> String {
+        let events = self.practice.as_mut().map(|round| round.advance(seconds)).unwrap_or_default();
+        serde_json::to_string(&events).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// End the round early; returns JSON `CallResponseResult` ("null" if none was running)
+    #[wasm_bindgen(js_name = stopCallResponseJson)]
+    pub fn stop_call_response_json(&mut self) -> String {
+        let result = self.practice.take().map(|mut round| round.stop());
+        serde_json::to_string(&result).unwrap_or_else(|_| "null".to_string())
+    }
+
+    // ==================== Accents ====================
+
+    /// Get one measure of metronome clicks with the current accents and tempo as JSON
+    #[wasm_bindgen(js_name = getAccentPreviewJson)]
+    pub fn get_accent_preview_json(&self) -> String {
+        let clicks = self.song.settings.time_signature.measure_clicks(self.song.settings.tempo);
+        serde_json::to_string(&clicks).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get one measure of metronome clicks in a voice's meter as JSON
+    #[wasm_bindgen(js_name = getVoiceAccentPreviewJson)]
+    pub fn get_voice_accent_preview_json(&self, voice: u8) -> String {
+        let clicks = self.song.voice_time_signature(voice).measure_clicks(self.song.settings.tempo);
+        serde_json::to_string(&clicks).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Notes of a scale audition at the song's tempo as JSON `AuditionNote[]`
+    /// (direction "up", "down", or "both")
+    #[wasm_bindgen(js_name = scaleAuditionJson)]
+    pub fn scale_audition_json(&self, root: &str, scale_type: &str, direction: &str) -> Result<String, JsValue> {
+        let root = PitchClass::parse(root).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let scale_type = ScaleType::parse(scale_type).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let direction = ScaleDirection::parse(direction).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let notes = scale_audition(root, scale_type, direction, self.song.settings.tempo);
+        serde_json::to_string(&notes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Notes of a chord audition at the song's tempo as JSON `AuditionNote[]`
+    /// (quality is a symbol suffix, e.g., "m7")
+    #[wasm_bindgen(js_name = chordAuditionJson)]
+    pub fn chord_audition_json(&self, root: &str, quality: &str, inversion: u8) -> Result<String, JsValue> {
+        let root = PitchClass::parse(root).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let notes = chord_audition(root, quality, inversion, self.song.settings.tempo)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&notes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Notes auditioning the chord on a degree (1-7) of the song's key at its
+    /// tempo as JSON `AuditionNote[]`
+    #[wasm_bindgen(js_name = diatonicChordAuditionJson)]
+    pub fn diatonic_chord_audition_json(&self, degree: u8, seventh: bool) -> Result<String, JsValue> {
+        let notes = diatonic_chord_audition(&self.song.settings.key, degree, seventh, self.song.settings.tempo)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&notes).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Pitch usage per `bucketTicks` of the song as JSON `PitchHistogram`
+    /// (for a register heatmap)
+    #[wasm_bindgen(js_name = getPitchHistogramJson)]
+    pub fn get_pitch_histogram_json(&self, bucket_ticks: u32) -> Result<String, JsValue> {
+        let histogram = pitch_histogram(&self.song, bucket_ticks).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&histogram).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Get the accent pattern as an array of levels (1=weak, 2=medium, 3=strong)
+    #[wasm_bindgen(js_name = getAccents)]
+    pub fn get_accents(&self) -> Vec<u8> {
+        self.song.settings.time_signature.accents.accents
+            .iter()
+            .map(|a| *a as u8)
+            .collect()
+    }
+
+    /// Set the accent pattern from an array of levels (1=weak, 2=medium, 3=strong)
+    #[wasm_bindgen(js_name = setAccents)]
+    pub fn set_accents(&mut self, accents: &[u8]) -> Result<(), JsValue> {
+        if accents.len() != self.song.settings.time_signature.numerator as usize {
+            return Err(JsValue::from_str(&format!(
+                "Accent pattern length ({}) must match time signature numerator ({})",
+                accents.len(),
+                self.song.settings.time_signature.numerator
+            )));
+        }
+        let pattern = AccentPattern::from_values(accents);
+        self.edit("Set accents", |song| song.settings.time_signature.set_accents(pattern));
+        Ok(())
+    }
+
+    /// Get the beat groups of a measure (e.g., [3, 2, 2] for 7/8), derived from the accents
+    #[wasm_bindgen(js_name = getGroupings)]
+    pub fn get_groupings(&self) -> Vec<u8> {
+        self.song.settings.time_signature.groupings()
+    }
+
+    /// Get the beats per felt pulse (all 1s unless set, e.g., [3, 3] for 6/8 in two)
+    #[wasm_bindgen(js_name = getPulseGrouping)]
+    pub fn get_pulse_grouping(&self) -> Vec<u8> {
+        self.song.settings.time_signature.pulse_grouping()
+    }
+
+    /// Set the pulse grouping; an empty array makes every beat a pulse again
+    #[wasm_bindgen(js_name = setPulseGrouping)]
+    pub fn set_pulse_grouping(&mut self, groups: &[u8]) -> Result<(), JsValue> {
+        self.try_edit("Set pulse grouping", |song| {
+            song.settings.time_signature.set_pulse_grouping(groups)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Snap a tick to the nearest pulse start
+    #[wasm_bindgen(js_name = snapToPulse)]
+    pub fn snap_to_pulse(&self, tick: u32) -> u32 {
+        self.song.settings.time_signature.snap_to_pulse(tick)
+    }
+
+    /// Cycle accent at beat index (weak -> medium -> strong -> weak)
+    #[wasm_bindgen(js_name = cycleAccent)]
+    pub fn cycle_accent(&mut self, beat: usize) {
+        self.edit("Cycle accent", |song| song.settings.time_signature.accents.cycle(beat));
+    }
+
+    /// Get accent pattern as visual string (e.g., ">.-.")
+    #[wasm_bindgen(js_name = getAccentVisual)]
+    pub fn get_accent_visual(&self) -> String {
+        self.song.settings.time_signature.accents.to_visual()
+    }
+
+    // ==================== Duration Info ====================
+
+    /// Get total duration in ticks
+    #[wasm_bindgen(js_name = durationTicks)]
+    pub fn duration_ticks(&self) -> u32 {
+        self.song.duration_ticks()
+    }
+
+    /// Get total duration in seconds
+    #[wasm_bindgen(js_name = durationSeconds)]
+    pub fn duration_seconds(&self) -> f64 {
+        self.song.duration_seconds()
+    }
+
+    /// Get duration in seconds until the last note ends, honoring tempo changes
+    #[wasm_bindgen(js_name = durationWithTempoMap)]
+    pub fn duration_with_tempo_map(&self) -> f64 {
+        self.song.duration_with_tempo_map()
+    }
+
+    /// Get duration in seconds until the final barline
+    #[wasm_bindgen(js_name = durationToEndOfLastMeasure)]
+    pub fn duration_to_end_of_last_measure(&self) -> f64 {
+        self.song.duration_to_end_of_last_measure()
+    }
+
+    /// Get seconds remaining from a tick until the final barline
+    #[wasm_bindgen(js_name = remainingSeconds)]
+    pub fn remaining_seconds(&self, tick: u32) -> f64 {
+        self.song.remaining_seconds(tick)
+    }
+
+    /// Get the song summary (title, durations, m:ss strings, ...) as JSON
+    #[wasm_bindgen(js_name = getSongInfoJson)]
+    pub fn get_song_info_json(&self) -> String {
+        serde_json::to_string(&self.song.info()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Format seconds as m:ss
+    #[wasm_bindgen(js_name = formatDuration)]
+    pub fn format_duration(seconds: f64) -> String {
+        format_duration(seconds)
+    }
+
+    /// Get number of measures
+    #[wasm_bindgen(js_name = measureCount)]
+    pub fn measure_count(&self) -> u32 {
+        self.song.measure_count()
+    }
+
+    // ==================== Position Conversion ====================
+
+    /// Convert a tick to seconds, honoring tempo changes
+    #[wasm_bindgen(js_name = tickToSeconds)]
+    pub fn tick_to_seconds(&self, tick: u32) -> f64 {
+        self.song.tick_to_seconds(tick)
+    }
+
+    /// Convert seconds to a tick, honoring tempo changes
+    #[wasm_bindgen(js_name = secondsToTick)]
+    pub fn seconds_to_tick(&self, seconds: f64) -> u32 {
+        self.song.seconds_to_tick(seconds)
+    }
+
+    /// Convert a tick to "measure.beat.tick" (e.g., "5.3.120")
+    #[wasm_bindgen(js_name = positionToMbt)]
+    pub fn position_to_mbt(&self, tick: u32) -> String {
+        self.song.position_to_mbt(tick).to_string()
+    }
+
+    /// Convert measure, beat (both 1-indexed) and tick to a tick position
+    #[wasm_bindgen(js_name = mbtToTick)]
+    pub fn mbt_to_tick(&self, measure: u32, beat: u32, tick: u32) -> Result<u32, JsValue> {
+        self.song
+            .mbt_to_tick(measure, beat, tick)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Parse a position like "17" or "5.3.120" into a tick
+    #[wasm_bindgen(js_name = parsePosition)]
+    pub fn parse_position(&self, position: &str) -> Result<u32, JsValue> {
+        MusicalPosition::parse(position)
+            .and_then(|p| self.song.mbt_to_tick(p.measure, p.beat, p.tick))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Get the start tick of a measure (1-indexed)
+    #[wasm_bindgen(js_name = measureStartTick)]
+    pub fn measure_start_tick(&self, measure: u32) -> Result<u32, JsValue> {
+        self.song
+            .measure_start_tick(measure)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // ==================== Markers ====================
+
+    /// Add a named marker at a tick (replaces a marker with the same name)
+    #[wasm_bindgen(js_name = addMarker)]
+    pub fn add_marker(&mut self, name: &str, tick: u32) {
+        self.edit("Add marker", |song| song.add_marker(name, tick));
+    }
+
+    /// Remove a marker by name
+    #[wasm_bindgen(js_name = removeMarker)]
+    pub fn remove_marker(&mut self, name: &str) -> bool {
+        if self.song.marker_tick(name).is_none() {
+            return false;
+        }
+        self.edit("Remove marker", |song| song.remove_marker(name).is_some())
+    }
+
+    /// Label the section a marker starts with a color ("orange"), or clear it with ""
+    #[wasm_bindgen(js_name = setSectionColor)]
+    pub fn set_section_color(&mut self, name: &str, color: &str) -> Result<(), JsValue> {
+        let color = ColorLabel::parse(color).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.try_edit("Set section color", |song| {
+            song.set_section_color(name, color)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Get all markers as JSON array
+    #[wasm_bindgen(js_name = getMarkersJson)]
+    pub fn get_markers_json(&self) -> String {
+        serde_json::to_string(&self.song.markers).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the tick of a marker by name
+    #[wasm_bindgen(js_name = markerTick)]
+    pub fn marker_tick(&self, name: &str) -> Option<u32> {
+        self.song.marker_tick(name)
+    }
+
+    /// Get the tick of the first marker after a tick
+    #[wasm_bindgen(js_name = nextMarkerTick)]
+    pub fn next_marker_tick(&self, tick: u32) -> Option<u32> {
+        self.song.next_marker(tick).map(|m| m.tick)
+    }
+
+    /// Get the tick of the last marker before a tick
+    #[wasm_bindgen(js_name = prevMarkerTick)]
+    pub fn prev_marker_tick(&self, tick: u32) -> Option<u32> {
+        self.song.prev_marker(tick).map(|m| m.tick)
+    }
+
+    // ==================== Lyrics ====================
+
+    /// Set the lyrics from text, one syllable per melody note ("Hap-py
+    /// birth-day", `_` to hold, `/` or a new line for a new line); returns
+    /// the number of syllables placed
+    #[wasm_bindgen(js_name = setLyrics)]
+    pub fn set_lyrics(&mut self, text: &str) -> usize {
+        self.edit("Set lyrics", |song| song.set_lyrics(text))
+    }
+
+    /// Get the lyrics as editable text
+    #[wasm_bindgen(js_name = getLyricsText)]
+    pub fn get_lyrics_text(&self) -> String {
+        self.song.lyrics_text()
+    }
+
+    /// Export the lyrics as LRC text, enhanced with per-syllable stamps if asked
+    #[wasm_bindgen(js_name = exportLrc)]
+    pub fn export_lrc(&self, syllable_timing: bool) -> Result<String, JsValue> {
+        LrcExporter::new()
+            .with_syllable_timing(syllable_timing)
+            .export(&self.song)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Export a ChordPro chord-and-lyrics sheet
+    #[wasm_bindgen(js_name = exportChordPro)]
+    pub fn export_chordpro(&self) -> Result<String, JsValue> {
+        chordpro_text(&self.song).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Get the annotations as JSON (`Annotation[]`), sorted by tick
+    #[wasm_bindgen(js_name = getAnnotationsJson)]
+    pub fn get_annotations_json(&self) -> String {
+        serde_json::to_string(&self.song.annotations).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Annotate a tick, or the range up to `end_tick`; returns the annotation's index
+    #[wasm_bindgen(js_name = addAnnotation)]
+    pub fn add_annotation(&mut self, tick: u32, end_tick: Option<u32>, text: &str) -> Result<usize, JsValue> {
+        self.try_edit("Add annotation", |song| {
+            song.add_annotation(tick, end_tick, text)
+                .map_err(|e| JsValue::from_str(&e.to_string()))
+        })
+    }
+
+    /// Remove an annotation by index
+    #[wasm_bindgen(js_name = removeAnnotation)]
+    pub fn remove_annotation(&mut self, index: usize) -> bool {
+        if index >= self.song.annotations.len() {
+            return false;
+        }
+        self.edit("Remove annotation", |song| song.remove_annotation(index).is_some())
+    }
+
+    /// Get the written repeat structure as JSON (`RepeatStructure`)
+    #[wasm_bindgen(js_name = getRepeatsJson)]
+    pub fn get_repeats_json(&self) -> String {
+        serde_json::to_string(&self.song.repeats).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Add repeat barlines, e.g. "5-8" or "5-8 x3", replacing any repeat they overlap
+    #[wasm_bindgen(js_name = addRepeat)]
+    pub fn add_repeat(&mut self, spec: &str) -> Result<(), JsValue> {
+        let repeat = Repeat::parse(spec).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit_repeats("Add repeat", |repeats| repeats.add_repeat(repeat))
+    }
+
+    /// Add a numbered ending, e.g. "8 1" or "9-10 2", replacing any ending it overlaps
+    #[wasm_bindgen(js_name = addEnding)]
+    pub fn add_ending(&mut self, spec: &str) -> Result<(), JsValue> {
+        let ending = Ending::parse(spec).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit_repeats("Add ending", |repeats| repeats.add_ending(ending))
+    }
+
+    /// Set the jump, e.g. "D.C. al Fine 16" or "D.S. al Coda 24"; "" removes it
+    #[wasm_bindgen(js_name = setJump)]
+    pub fn set_jump(&mut self, spec: &str) -> Result<(), JsValue> {
+        let jump = match spec.trim() {
+            "" => None,
+            spec => Some(Jump::parse(spec).map_err(|e| JsValue::from_str(&e.to_string()))?),
+        };
+        self.edit_repeats("Set jump", |repeats| repeats.set_jump(jump))
+    }
+
+    /// Place a sign at a measure, e.g. "segno 3", "fine 12", "to coda 16", or "coda 20"
+    #[wasm_bindgen(js_name = placeRepeatSign)]
+    pub fn place_repeat_sign(&mut self, spec: &str) -> Result<(), JsValue> {
+        let (sign, measure) = RepeatSign::parse(spec).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.edit_repeats("Place sign", |repeats| repeats.place_sign(sign, measure))
+    }
+
+    /// Remove the repeats, endings, signs, and jump at a measure; returns whether any were there
+    #[wasm_bindgen(js_name = removeRepeatsAt)]
+    pub fn remove_repeats_at(&mut self, measure: u32) -> bool {
+        let mut repeats = self.song.repeats.clone();
+        if !repeats.remove_at(measure) {
+            return false;
+        }
+        self.edit("Remove repeat marks", |song| song.repeats = repeats);
+        true
+    }
+
+    /// Remove the whole repeat structure; returns whether there was one
+    #[wasm_bindgen(js_name = clearRepeats)]
+    pub fn clear_repeats(&mut self) -> bool {
+        if self.song.repeats.is_empty() {
+            return false;
+        }
+        self.edit("Clear repeats", |song| song.repeats = RepeatStructure::default());
+        true
+    }
+
+    /// Written measures in the order they are played
+    #[wasm_bindgen(js_name = getMeasureOrder)]
+    pub fn get_measure_order(&self) -> Vec<u32> {
+        self.song.measure_order()
+    }
+
+    /// Written tick of a tick on the played timeline of `getPlaybackNotesJson` (for the playhead)
+    #[wasm_bindgen(js_name = writtenTick)]
+    pub fn written_tick(&self, played_tick: u32) -> u32 {
+        self.song.written_tick(played_tick)
+    }
+
+    /// Tick on the played timeline where a written tick is first played (for seeking)
+    #[wasm_bindgen(js_name = playedTick)]
+    pub fn played_tick(&self, written_tick: u32) -> u32 {
+        self.song.played_tick(written_tick)
+    }
+
+    /// Format a tick for the transport (e.g., "5.3.120 | 0:42")
+    #[wasm_bindgen(js_name = formatTransportTime)]
+    pub fn format_transport_time(&self, tick: u32) -> String {
+        self.song.format_transport_time(tick)
+    }
+
+    /// Get ticks per beat
+    #[wasm_bindgen(js_name = ticksPerBeat)]
+    pub fn ticks_per_beat(&self) -> u32 {
+        self.song.settings.time_signature.ticks_per_beat()
+    }
+
+    /// Get ticks per measure
+    #[wasm_bindgen(js_name = ticksPerMeasure)]
+    pub fn ticks_per_measure(&self) -> u32 {
+        self.song.settings.time_signature.ticks_per_measure()
+    }
+
+    // ==================== Serialization ====================
+
+    /// Export the song to JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        self.song.to_json()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Load a song from JSON
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<Mozart, JsValue> {
+        let song = Song::from_json(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Mozart::from_song(song))
+    }
+
+    /// Replace the song with one loaded from JSON, as an undoable edit
+    #[wasm_bindgen(js_name = importJson)]
+    pub fn import_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let imported = Song::from_json(json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let before = std::mem::replace(&mut self.song, imported);
+        self.history.record("Import song", before, now_ms());
+        self.push_changes([SongChange::Replaced]);
+        Ok(())
+    }
+
+    /// Load a song from Standard MIDI File bytes
+    #[wasm_bindgen(js_name = fromMidi)]
+    pub fn from_midi(bytes: &[u8]) -> Result<Mozart, JsValue> {
+        let song = import_from_midi(bytes)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Mozart::from_song(song))
+    }
+
+    /// Layer a song from JSON onto voices of its own, timed by `alignment`
+    /// ("align" or "keep"); returns the voices it landed on as a JSON array
+    #[wasm_bindgen(js_name = mergeSongJson)]
+    pub fn merge_song_json(&mut self, json: &str, alignment: &str) -> Result<String, JsValue> {
+        let source = Song::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.merge_song(&source, alignment)
+    }
+
+    /// Layer a song from Standard MIDI File bytes, as `mergeSongJson`
+    #[wasm_bindgen(js_name = mergeMidi)]
+    pub fn merge_midi(&mut self, bytes: &[u8], alignment: &str) -> Result<String, JsValue> {
+        let source = import_from_midi(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.merge_song(&source, alignment)
+    }
+
+    fn merge_song(&mut self, source: &Song, alignment: &str) -> Result<String, JsValue> {
+        let alignment = TempoAlignment::parse(alignment).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let voices = self.try_edit("Merge song", |song| {
+            merge_into(song, source, alignment).map_err(|e| JsValue::from_str(&e.to_string()))
+        })?;
+        serde_json::to_string(&voices).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Compare the song's melody with another song's (JSON) as JSON
+    /// `SimilarityReport`; the region's `a` ticks are this song's
+    #[wasm_bindgen(js_name = compareSongJson)]
+    pub fn compare_song_json(&self, json: &str) -> Result<String, JsValue> {
+        let other = Song::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.compare_song(&other)
+    }
+
+    /// Compare with a song from Standard MIDI File bytes, as `compareSongJson`
+    #[wasm_bindgen(js_name = compareMidi)]
+    pub fn compare_midi(&self, bytes: &[u8]) -> Result<String, JsValue> {
+        let other = import_from_midi(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.compare_song(&other)
+    }
+
+    fn compare_song(&self, other: &Song) -> Result<String, JsValue> {
+        let report = similarity(&self.song, other).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// The song and another version of it (JSON) laid out to be played
+    /// against each other, as JSON `Comparison`; `mode` is "together" or
+    /// "alternate"
+    #[wasm_bindgen(js_name = getComparisonPlaybackJson)]
+    pub fn get_comparison_playback_json(&self, json: &str, mode: &str) -> Result<String, JsValue> {
+        let other = Song::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.comparison_playback(&other, mode)
+    }
+
+    /// Compare with a version from Standard MIDI File bytes, as `getComparisonPlaybackJson`
+    #[wasm_bindgen(js_name = getComparisonPlaybackMidi)]
+    pub fn get_comparison_playback_midi(&self, bytes: &[u8], mode: &str) -> Result<String, JsValue> {
+        let other = import_from_midi(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.comparison_playback(&other, mode)
+    }
+
+    fn comparison_playback(&self, other: &Song, mode: &str) -> Result<String, JsValue> {
+        let mode = ComparisonMode::parse(mode).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let comparison = compare_versions(&self.song, other, mode);
+        serde_json::to_string(&comparison).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Preview cutting the song at ticks (on the played timeline) as JSON
+    /// `SplitPiece[]`
+    #[wasm_bindgen(js_name = planSplitJson)]
+    pub fn plan_split_json(&self, ticks: &[u32]) -> Result<String, JsValue> {
+        let plan = plan_split(&self.song, ticks).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&plan).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Preview cutting the song at its section markers as JSON `SplitPiece[]`
+    #[wasm_bindgen(js_name = planSectionsJson)]
+    pub fn plan_sections_json(&self) -> Result<String, JsValue> {
+        let plan = plan_sections(&self.song).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&plan).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Cut the song into a planned split's pieces, returned as a JSON array
+    /// of songs; the open song is left as it is
+    #[wasm_bindgen(js_name = splitSongJson)]
+    pub fn split_song_json(&self, plan_json: &str) -> Result<String, JsValue> {
+        let plan: Vec<SplitPiece> = serde_json::from_str(plan_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&split_song(&self.song, &plan)).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Export to MIDI bytes
+    #[wasm_bindgen(js_name = toMidi)]
+    pub fn to_midi(&self) -> Result<Vec<u8>, JsValue> {
+        export_to_midi(&self.song)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Export to MIDI bytes, optionally baking accent dynamics into velocities
+    #[wasm_bindgen(js_name = toMidiWithAccentDynamics)]
+    pub fn to_midi_with_accent_dynamics(&self, accent_dynamics: bool) -> Result<Vec<u8>, JsValue> {
+        MidiExporter::new()
+            .with_accent_dynamics(accent_dynamics)
+            .export(&self.song)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Render a practice track as WAV bytes, slowed (or sped up) to `rate` with pitch kept
+    #[wasm_bindgen(js_name = toPracticeWav)]
+    pub fn to_practice_wav(&self, rate: f64) -> Result<Vec<u8>, JsValue> {
+        let rate = PlaybackRate::new(rate).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        render_practice_wav(&self.song, rate, DEFAULT_SAMPLE_RATE).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // ==================== Pitch Utilities ====================
+
+    /// Get the frequency in Hz for a MIDI note number
+    #[wasm_bindgen(js_name = midiToFrequency)]
+    pub fn midi_to_frequency(midi: u8) -> f64 {
+        Pitch::from_midi(midi)
+            .map(|p| p.frequency())
+            .unwrap_or(0.0)
+    }
+
+    /// Get the note name for a MIDI note number (e.g., "C4", "F#5")
+    #[wasm_bindgen(js_name = midiToNoteName)]
+    pub fn midi_to_note_name(midi: u8) -> String {
+        Pitch::from_midi(midi)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|_| "?".to_string())
+    }
+
+    /// Parse a note name to MIDI number
+    #[wasm_bindgen(js_name = noteNameToMidi)]
+    pub fn note_name_to_midi(name: &str) -> Result<u8, JsValue> {
         Pitch::parse(name)
             .map(|p| p.midi())
             .map_err(|e| JsValue::from_str(&e.to_string()))
     }
-}
+}
+
+/// Binary note transfer for large songs
+///
+/// Faster than the JSON getters above [`crate::packed::PACKED_MIN_NOTES`]
+/// notes; decode with `decodePackedNotes` in `web/src/wasm/packed.ts`.
+#[cfg(feature = "packed-notes")]
+#[wasm_bindgen]
+impl Mozart {
+    /// Get all notes in the packed binary encoding
+    #[wasm_bindgen(js_name = getNotesPacked)]
+    pub fn get_notes_packed(&self) -> Vec<u8> {
+        crate::packed::encode_notes(&self.song.notes)
+    }
+
+    /// Get the notes overlapping a tick range, with indices, in the packed binary encoding
+    #[wasm_bindgen(js_name = getNoteWindowPacked)]
+    pub fn get_note_window_packed(&self, start_tick: u32, end_tick: u32) -> Vec<u8> {
+        crate::packed::encode_note_window(&self.song.note_window(start_tick, end_tick))
+    }
+}
+
+/// Note count below which hosts should keep using JSON
+#[cfg(feature = "packed-notes")]
+#[wasm_bindgen(js_name = packedMinNotes)]
+pub fn packed_min_notes() -> usize {
+    crate::packed::PACKED_MIN_NOTES
+}
+
+// ==================== Constants ====================
+
+/// Get ticks per quarter note (480)
+#[wasm_bindgen(js_name = TICKS_PER_QUARTER)]
+pub fn ticks_per_quarter() -> u32 {
+    crate::TICKS_PER_QUARTER
+}
+
+/// Get all scale types as a JSON array
+#[wasm_bindgen(js_name = getScaleTypes)]
+pub fn get_scale_types() -> String {
+    let types: Vec<&str> = ScaleType::all().iter().map(|t| t.name()).collect();
+    serde_json::to_string(&types).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Get all instrument names as a JSON array
+#[wasm_bindgen(js_name = getInstruments)]
+pub fn get_instruments() -> String {
+    let names: Vec<&str> = Instrument::all().iter().map(|i| i.name()).collect();
+    serde_json::to_string(&names).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Get all pitch class names
+#[wasm_bindgen(js_name = getPitchClasses)]
+pub fn get_pitch_classes() -> String {
+    let names: Vec<&str> = PitchClass::all().iter().map(|pc| pc.natural_name()).collect();
+    serde_json::to_string(&names).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Get the x coordinate of a tick in the piano roll
+#[wasm_bindgen(js_name = tickToX)]
+pub fn tick_to_x(tick: u32, key_width: f64, pixels_per_tick: f64) -> f64 {
+    TimelineView::new(key_width, pixels_per_tick).tick_to_x(tick)
+}
+
+/// Preview velocity for a press `offset` pixels below the top of a piano key (top soft, bottom loud)
+#[wasm_bindgen(js_name = keyPressVelocity)]
+pub fn key_press_velocity(offset: f64, key_height: f64) -> u8 {
+    crate::view::key_press_velocity(offset, key_height)
+}
+
+/// Get the tick at an x coordinate in the piano roll
+#[wasm_bindgen(js_name = xToTick)]
+pub fn x_to_tick(x: f64, key_width: f64, pixels_per_tick: f64) -> u32 {
+    TimelineView::new(key_width, pixels_per_tick).x_to_tick(x)
+}
+
+/// Zoom the piano roll about a point of the visible area (e.g., a pinch center)
+/// Returns `[pixelsPerTick, scrollLeft]`
+#[wasm_bindgen(js_name = zoomAbout)]
+pub fn zoom_about(factor: f64, anchor_x: f64, scroll_left: f64, key_width: f64, pixels_per_tick: f64) -> Vec<f64> {
+    let (view, scroll_left) = TimelineView::new(key_width, pixels_per_tick).zoom_about(factor, anchor_x, scroll_left);
+    vec![view.pixels_per_tick, scroll_left]
+}
+
+/// Compute the scroll offset that keeps the playhead visible
+/// Mode: "off", "page-flip", or "continuous"
+#[wasm_bindgen(js_name = followScroll)]
+pub fn follow_scroll(
+    mode: &str,
+    scroll_left: f64,
+    visible_width: f64,
+    playhead_tick: u32,
+    key_width: f64,
+    pixels_per_tick: f64,
+) -> Result<f64, JsValue> {
+    let mode = FollowMode::parse(mode).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Ok(TimelineView::new(key_width, pixels_per_tick)
+        .follow_scroll(mode, scroll_left, visible_width, playhead_tick))
+}
+
+/// Parse one token of text notation ("C4q", "Rq") into `MelodyToken` JSON
+#[wasm_bindgen(js_name = parseMelodyToken)]
+pub fn parse_melody_token_json(token: &str) -> Result<String, JsValue> {
+    let token = parse_melody_token(token).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&token).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// `StartupDiagnostics` JSON for a launch, given the sentinel the previous
+/// one left (null after a clean exit)
+#[wasm_bindgen(js_name = startupDiagnosticsJson)]
+pub fn startup_diagnostics_json(previous_sentinel: Option<String>) -> Result<String, JsValue> {
+    let previous = previous_sentinel.as_deref().map(StartupSentinel::parse);
+    serde_json::to_string(&startup_diagnostics(previous.as_ref())).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// `StartupSentinel` JSON to store for this launch
+#[wasm_bindgen(js_name = launchSentinelJson)]
+pub fn launch_sentinel_json(previous_sentinel: Option<String>, now_ms: f64) -> Result<String, JsValue> {
+    let previous = previous_sentinel.as_deref().map(StartupSentinel::parse);
+    serde_json::to_string(&StartupSentinel::launch(previous.as_ref(), now_ms)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Parse a piano roll pitch range ("auto", "auto 7", "C1-C7") into `PitchView` JSON
+#[wasm_bindgen(js_name = parsePitchView)]
+pub fn parse_pitch_view(text: &str) -> Result<String, JsValue> {
+    let view = PitchView::parse(text).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&view).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// List accent presets for a beat count as JSON (built-ins plus saved user presets)
+///
+/// `user_presets_json` is the string previously returned by `saveAccentPreset`
+/// (or empty for none).
+#[wasm_bindgen(js_name = listAccentPresets)]
+pub fn list_accent_presets(numerator: u8, user_presets_json: &str) -> Result<String, JsValue> {
+    let library = load_preset_library(user_presets_json)?;
+    serde_json::to_string(&library.list(numerator)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Save a user accent preset; returns the updated user presets JSON to persist
+#[wasm_bindgen(js_name = saveAccentPreset)]
+pub fn save_accent_preset(
+    user_presets_json: &str,
+    name: &str,
+    accents: &[u8],
+    description: &str,
+) -> Result<String, JsValue> {
+    let mut library = load_preset_library(user_presets_json)?;
+    library
+        .save(name, &AccentPattern::from_values(accents), description)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    library.to_json().map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Parse a polyrhythm (e.g., "3:2", "4 over 3") into JSON `{ beats, against }`
+#[wasm_bindgen(js_name = parsePolyrhythm)]
+pub fn parse_polyrhythm(spec: &str) -> Result<String, JsValue> {
+    let poly = Polyrhythm::parse(spec).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&poly).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Parse a practice playback rate (e.g., "0.5", "75%") into a multiplier, 0.25-2
+#[wasm_bindgen(js_name = parsePlaybackRate)]
+pub fn parse_playback_rate(spec: &str) -> Result<f64, JsValue> {
+    PlaybackRate::parse(spec).map(|rate| rate.value()).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Commands matching a palette query as JSON `CommandMatch[]`, best first (all for "")
+#[wasm_bindgen(js_name = searchCommandsJson)]
+pub fn search_commands_json(query: &str) -> String {
+    serde_json::to_string(&search_commands(query)).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Check new-song defaults (JSON `SongDefaults`, missing fields use the
+/// built-in defaults), returning the complete JSON to save in preferences
+#[wasm_bindgen(js_name = setNewSongDefaults)]
+pub fn set_new_song_defaults(defaults_json: &str) -> Result<String, JsValue> {
+    let defaults: SongDefaults = serde_json::from_str(defaults_json)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    Song::new_with_defaults(&defaults).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&defaults).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Check exported settings (JSON `AppSettings`, missing fields use the
+/// defaults), returning the complete JSON to write or apply
+#[wasm_bindgen(js_name = checkSettings)]
+pub fn check_settings(settings_json: &str) -> Result<String, JsValue> {
+    let settings = AppSettings::from_json(settings_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    settings.to_json().map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Preset instrument and voice ranges as JSON `PitchRange[]`
+#[wasm_bindgen(js_name = pitchRangePresetsJson)]
+pub fn pitch_range_presets_json() -> String {
+    serde_json::to_string(&PitchRange::presets()).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// An instrument or voice range preset as JSON `PitchRange` (e.g., "flute",
+/// "alto voice")
+#[wasm_bindgen(js_name = instrumentRangeJson)]
+pub fn instrument_range_json(name: &str) -> Result<String, JsValue> {
+    let range = PitchRange::preset(name).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&range).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Bundled odd-meter grooves as JSON `GroovePreset[]`
+#[wasm_bindgen(js_name = groovePresetsJson)]
+pub fn groove_presets_json() -> String {
+    serde_json::to_string(&groove_presets()).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Theory reference tables for a topic as JSON `TheoryReference`
+#[wasm_bindgen(js_name = theoryReferenceJson)]
+pub fn theory_reference_json(topic: &str) -> Result<String, JsValue> {
+    let topic = TheoryTopic::parse(topic).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&get_theory_reference(topic)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// The text notation the melody parser reads, as JSON `NotationGrammar`
+#[wasm_bindgen(js_name = notationGrammarJson)]
+pub fn notation_grammar_json() -> String {
+    serde_json::to_string(&get_notation_grammar()).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Groove of every note in Standard MIDI File bytes, against `subdivisions`
+/// per beat of the file's meter, as JSON `GrooveTemplate`
+#[wasm_bindgen(js_name = extractGrooveFromMidiJson)]
+pub fn extract_groove_from_midi_json(bytes: &[u8], subdivisions: u8) -> Result<String, JsValue> {
+    let song = import_from_midi(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let template = extract_groove(&song.notes, song.settings.time_signature.ticks_per_beat(), subdivisions)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    serde_json::to_string(&template).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Whether a registry command can be recorded into a macro
+#[wasm_bindgen(js_name = isRecordableCommand)]
+pub fn is_recordable_command(id: &str) -> bool {
+    macros::is_recordable(id)
+}
+
+/// Save a macro (JSON `MacroStep[]`); returns the updated macro library JSON
+/// to persist
+///
+/// `library_json` is the string previously returned by `saveMacro` or
+/// `removeMacro` (or empty for none). Throws on an empty name or recording,
+/// or a shortcut already in use.
+#[wasm_bindgen(js_name = saveMacro)]
+pub fn save_macro(library_json: &str, name: &str, shortcut: Option<String>, steps_json: &str) -> Result<String, JsValue> {
+    let mut library = MacroLibrary::from_json(library_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let steps: Vec<MacroStep> = serde_json::from_str(steps_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    library.save(name, shortcut.as_deref(), steps).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    library.to_json().map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Remove a macro by name; returns the updated macro library JSON
+#[wasm_bindgen(js_name = removeMacro)]
+pub fn remove_macro(library_json: &str, name: &str) -> Result<String, JsValue> {
+    let mut library = MacroLibrary::from_json(library_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    library.remove(name);
+    library.to_json().map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn load_preset_library(json: &str) -> Result<PresetLibrary, JsValue> {
+    if json.trim().is_empty() {
+        return Ok(PresetLibrary::new());
+    }
+    PresetLibrary::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Health checks for the host's audio engine (see [`crate::watchdog`])
+#[wasm_bindgen(js_name = AudioWatchdog)]
+#[derive(Default)]
+pub struct WasmAudioWatchdog {
+    watchdog: AudioWatchdog,
+}
+
+#[wasm_bindgen(js_class = AudioWatchdog)]
+impl WasmAudioWatchdog {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sample the audio clock (`AudioContext.currentTime` and `state`);
+    /// returns JSON `AudioDiagnostics` when the engine is unhealthy, or ""
+    pub fn observe(&mut self, now_ms: f64, audio_time: f64, state: &str) -> String {
+        self.watchdog
+            .observe(now_ms, audio_time, state)
+            .map(|diagnostics| serde_json::to_string(&diagnostics).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// The output device is gone; returns JSON `AudioDiagnostics`
+    #[wasm_bindgen(js_name = deviceLost)]
+    pub fn device_lost(&mut self, now_ms: f64, state: &str) -> String {
+        serde_json::to_string(&self.watchdog.device_lost(now_ms, state)).unwrap_or_default()
+    }
+
+    /// Start measuring afresh, after the engine was re-created
+    pub fn restart(&mut self) {
+        self.watchdog.restart();
+    }
+}
+
+/// Groups key presses into chords for chord entry (see [`crate::chord_entry`])
+#[wasm_bindgen(js_name = ChordCapture)]
+#[derive(Default)]
+pub struct WasmChordCapture {
+    capture: ChordCapture,
+}
+
+#[wasm_bindgen(js_class = ChordCapture)]
+impl WasmChordCapture {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-// ==================== Constants ====================
+    /// How long after the first key later keys still join the chord
+    #[wasm_bindgen(getter, js_name = windowMs)]
+    pub fn window_ms(&self) -> f64 {
+        self.capture.window_ms()
+    }
 
-/// Get ticks per quarter note (480)
-#[wasm_bindgen(js_name = TICKS_PER_QUARTER)]
-pub fn ticks_per_quarter() -> u32 {
-    crate::TICKS_PER_QUARTER
-}
+    /// Record a key press; returns the previous chord as JSON `CapturedKey[]` if its window had closed, or ""
+    pub fn press(&mut self, pitch: u8, velocity: u8, now_ms: f64) -> String {
+        Self::chord_json(self.capture.press(pitch, velocity, now_ms))
+    }
 
-/// Get all scale types as a JSON array
-#[wasm_bindgen(js_name = getScaleTypes)]
-pub fn get_scale_types() -> String {
-    let types: Vec<&str> = ScaleType::all().iter().map(|t| t.name()).collect();
-    serde_json::to_string(&types).unwrap_or_else(|_| "[]".to_string())
-}
+    /// The chord as JSON `CapturedKey[]` once its window has passed, or ""
+    pub fn poll(&mut self, now_ms: f64) -> String {
+        Self::chord_json(self.capture.poll(now_ms))
+    }
 
-/// Get all pitch class names
-#[wasm_bindgen(js_name = getPitchClasses)]
-pub fn get_pitch_classes() -> String {
-    let names: Vec<&str> = PitchClass::all().iter().map(|pc| pc.natural_name()).collect();
-    serde_json::to_string(&names).unwrap_or_else(|_| "[]".to_string())
+    /// Close the window now; the chord as JSON `CapturedKey[]`, or "" without keys
+    pub fn flush(&mut self) -> String {
+        Self::chord_json(self.capture.flush())
+    }
+
+    fn chord_json(chord: Option<Vec<CapturedKey>>) -> String {
+        chord.map(|keys| serde_json::to_string(&keys).unwrap_or_default()).unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_chord_entry() {
+        let mut mozart = Mozart::new();
+        let mut capture = WasmChordCapture::new();
+        assert_eq!(capture.press(64, 90, 0.0), "");
+        assert_eq!(capture.press(60, 90, 20.0), "");
+        let chord = capture.poll(capture.window_ms());
+        assert_eq!(mozart.add_chord_json(&chord, 480, 240).unwrap(), 2);
+        assert_eq!(mozart.undo_label().as_deref(), Some("Add chord"));
+        let starts: Vec<u32> = mozart.song.notes.iter().map(|n| n.start_tick).collect();
+        assert_eq!(starts, vec![480, 480]);
+
+        // One undo removes the whole chord
+        mozart.undo();
+        assert_eq!(mozart.note_count(), 0);
+        assert_eq!(capture.flush(), "");
+    }
+
+    #[test]
+    fn test_check_settings() {
+        let settings: serde_json::Value = serde_json::from_str(&check_settings(r#"{"prevent_sleep": false}"#).unwrap()).unwrap();
+        assert_eq!(settings["prevent_sleep"], false);
+        assert_eq!(settings["song_defaults"]["tempo"], 120);
+    }
+
+    #[test]
+    fn test_notation_grammar() {
+        let grammar: serde_json::Value = serde_json::from_str(&notation_grammar_json()).unwrap();
+        assert_eq!(grammar["default_duration"], "q");
+        assert_eq!(grammar["tokens"][1]["kind"], "rest");
+    }
+
+    #[test]
+    fn test_startup_sentinel() {
+        let sentinel = launch_sentinel_json(None, 0.0).unwrap();
+        let clean: serde_json::Value = serde_json::from_str(&startup_diagnostics_json(None).unwrap()).unwrap();
+        assert_eq!(clean["safe_mode"], false);
+        let crashed: serde_json::Value = serde_json::from_str(&startup_diagnostics_json(Some(sentinel)).unwrap()).unwrap();
+        assert_eq!(crashed["safe_mode"], true);
+        assert_eq!(crashed["crashed_stage"], "launch");
+    }
+
+    #[test]
+    fn test_audio_watchdog() {
+        let mut watchdog = WasmAudioWatchdog::new();
+        assert_eq!(watchdog.observe(0.0, 0.0, "running"), "");
+        let diagnostics: serde_json::Value = serde_json::from_str(&watchdog.observe(1_000.0, 0.0, "running")).unwrap();
+        assert_eq!(diagnostics["fault"], "stalled");
+        assert_eq!(diagnostics["recover"], true);
+        watchdog.restart();
+        assert!(watchdog.device_lost(2_000.0, "running").contains("device_lost"));
+    }
+
     #[test]
     fn test_mozart_wasm_basic() {
         let mut mozart = Mozart::new();
@@ -424,6 +2531,60 @@ mod tests {
         assert_eq!(mozart.note_count(), 3);
     }
 
+    #[test]
+    fn test_rhythm_entry() {
+        let mut mozart = Mozart::new();
+        mozart.set_key("A minor").unwrap();
+        mozart.set_rhythm_pitch(Some(39)).unwrap();
+        assert_eq!(mozart.parse_melody_str("xq xe xe Rq xh").unwrap(), 4);
+        assert!(mozart.song.notes.iter().all(|n| n.pitch == 39));
+        // A rhythm says nothing about the key
+        assert_eq!(mozart.get_key(), "A Natural Minor");
+        // Nor do the hits among pitched notes: F# minor, though the hits are at C
+        mozart.set_rhythm_pitch(None).unwrap();
+        mozart.parse_melody_str("xq A4q B4q xq C#5q D5q E5q F#5q").unwrap();
+        assert_eq!(mozart.get_key(), "F# Natural Minor");
+
+        mozart.set_rhythm_pitch(None).unwrap();
+        assert_eq!(mozart.rhythm_pitch(), 60);
+        assert_eq!(mozart.song.settings.rhythm_pitch, None);
+    }
+
+    #[test]
+    fn test_voice_meters() {
+        let mut mozart = Mozart::new();
+        mozart.set_voice_time_signature(1, "7/8").unwrap();
+        assert_eq!(mozart.get_voice_time_signature(1), "7/8");
+        assert_eq!(mozart.get_voice_time_signature(0), "4/4");
+        assert!(mozart.take_changes_json().contains("settings:voiceMeters"));
+        let clicks: Vec<serde_json::Value> = serde_json::from_str(&mozart.get_voice_accent_preview_json(1)).unwrap();
+        assert_eq!(clicks.len(), 7);
+
+        mozart.set_voice_time_signature(1, "").unwrap();
+        assert_eq!(mozart.get_voice_meters_json(), "[]");
+    }
+
+    #[test]
+    fn test_tracks() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(72, 0, 480);
+        assert_eq!(mozart.add_track("Bassline").unwrap(), 1);
+        mozart.set_track_instrument(1, "strings").unwrap();
+        mozart.solo_track(1, true).unwrap();
+        assert!(mozart.take_changes_json().contains("settings:tracks"));
+        let tracks: Vec<serde_json::Value> = serde_json::from_str(&mozart.get_tracks_json()).unwrap();
+        assert_eq!(tracks[0]["instrument"], "strings");
+        assert_eq!(mozart.get_playback_notes_json(), "[]");
+
+        mozart.undo();
+        mozart.mute_track(0, true).unwrap();
+        mozart.set_track_volume(0, 80).unwrap();
+        mozart.rename_track(0, "Melody").unwrap();
+        assert_eq!(mozart.song.track(0).unwrap().name, "Melody");
+        assert_eq!(mozart.remove_track(0).unwrap(), 1);
+        assert_eq!(mozart.undo_label(), Some("Remove track".to_string()));
+    }
+
     #[test]
     fn test_transpose() {
         let mut mozart = Mozart::new();
@@ -433,4 +2594,724 @@ mod tests {
         let json = mozart.get_notes_json();
         assert!(json.contains("62")); // D4
     }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+        mozart.set_tempo(90);
+        assert_eq!(mozart.undo_label(), Some("Set tempo".to_string()));
+
+        assert!(mozart.undo());
+        assert_eq!(mozart.tempo(), 120);
+        assert_eq!(mozart.note_count(), 1);
+        assert!(mozart.redo());
+        assert_eq!(mozart.tempo(), 90);
+
+        assert!(mozart.undo());
+        assert!(mozart.undo());
+        assert_eq!(mozart.note_count(), 0);
+        assert!(!mozart.can_undo());
+    }
+
+    #[test]
+    fn test_no_op_edit_keeps_redo() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+        mozart.set_tempo(90);
+        assert!(mozart.undo());
+        mozart.take_changes_json();
+
+        // Nothing to join: no undo step, no change events, and redo survives
+        assert_eq!(mozart.make_legato(&[0], 0), 0);
+        assert_eq!(mozart.take_changes_json(), "[]");
+        assert_eq!(mozart.undo_label(), Some("Add note".to_string()));
+        assert!(mozart.can_redo());
+        assert!(mozart.redo());
+        assert_eq!(mozart.tempo(), 90);
+    }
+
+    #[test]
+    fn test_note_editing() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+        mozart.add_note(64, 480, 480);
+
+        mozart.move_notes(&[0, 1], 960, 2).unwrap();
+        mozart.set_note_velocity(0, 0).unwrap();
+        mozart.set_note_duration(1, 240).unwrap();
+        let note = serde_json::to_string(&Note::new(55, 2400, 120)).unwrap();
+        let index = mozart.update_note_json(0, &note).unwrap();
+        assert_eq!(index, 1);
+
+        let notes = &mozart.song.notes;
+        assert_eq!((notes[0].pitch, notes[0].start_tick, notes[0].duration_ticks), (66, 1440, 240));
+        assert_eq!((notes[1].pitch, notes[1].start_tick), (55, 2400));
+
+        assert!(mozart.undo());
+        assert_eq!(mozart.song.notes[0].velocity, 1); // Clamped, and restored with the undo
+    }
+
+    #[test]
+    fn test_freeze_arrangement() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q E4q G4q").unwrap();
+        assert_eq!(mozart.freeze_arrangement(0), 0);
+        mozart.set_note_take(0, "a").unwrap();
+        mozart.set_note_take(1, "B").unwrap();
+        mozart.set_note_probability(2, 0).unwrap();
+        assert!(mozart.has_chance_notes());
+        // Both lanes are in the same measure, so one of them plays
+        let played: Vec<Note> = serde_json::from_str(&mozart.get_playback_notes_for_pass_json(1)).unwrap();
+        assert_eq!(played.len(), 1);
+
+        assert_eq!(mozart.freeze_arrangement(1), 2);
+        assert_eq!(mozart.song.notes, vec![Note { take: None, ..played[0].clone() }]);
+        assert!(!mozart.has_chance_notes());
+        assert!(mozart.undo());
+        assert_eq!(mozart.song.notes.len(), 3);
+    }
+
+    #[test]
+    fn test_flatten_playback_effects() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q E4q G4q C5q").unwrap();
+        assert_eq!(mozart.flatten_playback_effects(), 0);
+        mozart.song.settings.accent_dynamics = true;
+        let played: Vec<Note> = serde_json::from_str(&mozart.get_playback_notes_json()).unwrap();
+
+        // One undo step brings the accents back as a playback effect
+        assert_eq!(mozart.flatten_playback_effects(), 3);
+        assert_eq!(mozart.song.notes, played);
+        assert!(!mozart.has_playback_effects());
+        assert!(mozart.undo());
+        assert!(mozart.has_playback_effects());
+        assert_eq!(mozart.song.notes[1].velocity, 100);
+    }
+
+    #[test]
+    fn test_color_labels() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q E4q G4q").unwrap();
+        assert_eq!(mozart.set_note_color(&[0, 1], "Blue").unwrap(), 2);
+        assert_eq!(mozart.song.notes[1].color, Some(ColorLabel::Blue));
+        assert_eq!(mozart.set_note_color(&[1], "").unwrap(), 1);
+        assert_eq!(mozart.song.notes[1].color, None);
+
+        mozart.add_marker("Fill", 960);
+        mozart.set_section_color("Fill", "orange").unwrap();
+        assert_eq!(mozart.song.markers[0].color, Some(ColorLabel::Orange));
+        assert!(mozart.undo());
+        assert_eq!(mozart.song.markers[0].color, None);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let mut mozart = Mozart::new();
+        assert_eq!(mozart.add_annotation(960, None, "breathe here").unwrap(), 0);
+        assert_eq!(mozart.add_annotation(0, Some(1920), "crescendo").unwrap(), 0);
+        let annotations: Vec<crate::song::Annotation> = serde_json::from_str(&mozart.get_annotations_json()).unwrap();
+        assert_eq!(annotations, mozart.song.annotations);
+        assert_eq!(annotations[0].end_tick, Some(1920));
+
+        assert!(mozart.remove_annotation(1));
+        assert!(!mozart.remove_annotation(1));
+        assert!(mozart.undo());
+        assert_eq!(mozart.song.annotations.len(), 2);
+    }
+
+    #[test]
+    fn test_repeats() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4w D4w E4w").unwrap();
+        mozart.add_repeat("2 x3").unwrap();
+        mozart.place_repeat_sign("fine 1").unwrap();
+        mozart.set_jump("D.C. al Fine 3").unwrap();
+        assert_eq!(mozart.get_measure_order(), vec![1, 2, 2, 2, 3, 1]);
+        let played: Vec<Note> = serde_json::from_str(&mozart.get_playback_notes_json()).unwrap();
+        assert_eq!(played.len(), 6);
+        assert_eq!(mozart.written_tick(3 * 1920 + 5), 1920 + 5);
+        assert_eq!(mozart.played_tick(2 * 1920), 4 * 1920);
+
+        let repeats: RepeatStructure = serde_json::from_str(&mozart.get_repeats_json()).unwrap();
+        assert_eq!(repeats, mozart.song.repeats);
+        assert!(mozart.remove_repeats_at(1));
+        assert_eq!(mozart.song.repeats.jump, None);
+        assert!(mozart.clear_repeats());
+        assert!(!mozart.clear_repeats());
+        assert!(mozart.undo());
+        assert_eq!(mozart.song.repeats.repeats.len(), 1);
+    }
+
+    #[test]
+    fn test_dirty_tracking() {
+        let mut mozart = Mozart::new();
+        assert!(!mozart.is_dirty());
+
+        mozart.add_note(60, 0, 480);
+        assert!(mozart.is_dirty());
+        mozart.mark_saved();
+        assert!(!mozart.is_dirty());
+
+        mozart.set_tempo(90);
+        assert!(mozart.is_dirty());
+        assert!(mozart.undo());
+        assert!(!mozart.is_dirty());
+    }
+
+    #[test]
+    fn test_macro_library() {
+        assert!(is_recordable_command("cleanup.normalizeVelocities"));
+        assert!(!is_recordable_command("macro.play"));
+
+        let steps = r#"[{"command": "cleanup.normalizeVelocities", "args": ["40-110"]}, {"command": "transpose.up"}]"#;
+        let saved = save_macro("", "Tidy", Some("ctrl+alt+t".to_string()), steps).unwrap();
+        let library: MacroLibrary = serde_json::from_str(&saved).unwrap();
+        assert_eq!(library.macros[0].shortcut.as_deref(), Some("Mod+Alt+T"));
+        assert_eq!(library.macros[0].steps[1], MacroStep::new("transpose.up", &[]));
+
+        let saved = remove_macro(&saved, "Tidy").unwrap();
+        assert_eq!(saved, MacroLibrary::new().to_json().unwrap());
+    }
+
+    #[test]
+    fn test_destructive_commands_confirm() {
+        let mut mozart = Mozart::new();
+        let status = |json: String| serde_json::from_str::<serde_json::Value>(&json).unwrap()["status"].clone();
+        assert_eq!(status(mozart.check_new_song(false)), "done");
+
+        mozart.add_note(60, 0, 480);
+        mozart.add_note(62, 1920, 480);
+        let outcome: serde_json::Value = serde_json::from_str(&mozart.clear_notes(false)).unwrap();
+        assert_eq!(outcome["status"], "needs_confirmation");
+        assert_eq!(outcome["summary"]["notes"], 2);
+        assert_eq!(mozart.song.notes.len(), 2);
+        assert_eq!(status(mozart.check_new_song(false)), "needs_confirmation");
+        assert_eq!(status(mozart.check_new_song(true)), "done");
+
+        assert_eq!(status(mozart.delete_measures(1, 1, false).unwrap()), "needs_confirmation");
+        let outcome: serde_json::Value = serde_json::from_str(&mozart.delete_measures(1, 1, true).unwrap()).unwrap();
+        assert_eq!(outcome["result"], 1);
+        assert_eq!(mozart.song.notes[0].start_tick, 0);
+        assert_eq!(mozart.undo_label().as_deref(), Some("Delete measures"));
+
+        assert_eq!(status(mozart.clear_notes(true)), "done");
+        assert!(mozart.song.notes.is_empty());
+    }
+
+    #[test]
+    fn test_tutorial() {
+        let mut mozart = Mozart::new();
+        assert_eq!(mozart.tutorial_state_json(), "null");
+
+        mozart.tutorial_start();
+        let state: serde_json::Value = serde_json::from_str(&mozart.tutorial_state_json()).unwrap();
+        assert_eq!(state["step"], "add_note");
+        assert_eq!(state["completed"], false);
+
+        mozart.add_note(60, 0, 480);
+        mozart.tutorial_next().unwrap();
+        mozart.tutorial_skip();
+        let state: serde_json::Value = serde_json::from_str(&mozart.tutorial_state_json()).unwrap();
+        assert_eq!(state["step"], "transpose_diatonic");
+
+        mozart.transpose_diatonic(1).unwrap();
+        mozart.tutorial_next().unwrap();
+        let state: serde_json::Value = serde_json::from_str(&mozart.tutorial_state_json()).unwrap();
+        assert_eq!(state["step"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_patterns() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 1920);
+        mozart.add_note(67, 1920, 1920);
+        mozart.create_pattern("A", 1, 1).unwrap();
+        mozart.create_pattern("B", 2, 1).unwrap();
+        mozart.clone_pattern("B", "C").unwrap();
+        mozart.set_pattern_chain("A B A C").unwrap();
+        assert_eq!(mozart.get_pattern_chain(), "A B A C");
+        assert_eq!(mozart.undo_label().as_deref(), Some("Set pattern chain"));
+        let patterns: serde_json::Value = serde_json::from_str(&mozart.get_patterns_json()).unwrap();
+        assert_eq!(patterns[2]["name"], "C");
+
+        assert!(mozart.remove_pattern("C"));
+        assert!(!mozart.remove_pattern("C"));
+        assert_eq!(mozart.get_pattern_chain(), "A B A");
+        mozart.undo();
+        assert_eq!(mozart.get_pattern_chain(), "A B A C");
+    }
+
+    #[test]
+    fn test_chord_voicing() {
+        let mut mozart = Mozart::new();
+        for pitch in [60, 64, 67] {
+            mozart.add_note(pitch, 0, 960);
+        }
+
+        assert_eq!(mozart.invert_chord_up(&[0, 1, 2]), 1);
+        assert_eq!(mozart.undo_label(), Some("Invert chord up".to_string()));
+        assert_eq!(mozart.close_voicing(&[0, 1, 2]), 0);
+        assert_eq!(mozart.invert_chord_down(&[0, 1, 2]), 1);
+        let pitches: Vec<u8> = mozart.song.notes.iter().map(|n| n.pitch).collect();
+        assert_eq!(pitches, vec![60, 64, 67]);
+    }
+
+    #[test]
+    fn test_chord_suggestions() {
+        let mut mozart = Mozart::new();
+        let suggestions: serde_json::Value =
+            serde_json::from_str(&mozart.suggest_next_chords_json(r#"["ii"]"#).unwrap()).unwrap();
+        assert_eq!(suggestions[0]["chord"]["symbol"], "G");
+        assert_eq!(suggestions[0]["chord"]["function"], "dominant");
+
+        mozart.parse_melody_str("C4h E4h").unwrap();
+        let options: serde_json::Value =
+            serde_json::from_str(&mozart.reharmonize_measure_json(1, "").unwrap()).unwrap();
+        let symbols: Vec<&str> = options
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|o| o["chords"][0]["chord"]["symbol"].as_str().unwrap())
+            .collect();
+        assert_eq!(symbols, vec!["C", "Am"]);
+    }
+
+    #[test]
+    fn test_modulation() {
+        let mut mozart = Mozart::new();
+        let plans = mozart.plan_modulation_json("G major", 1).unwrap();
+        let first = serde_json::to_string(&serde_json::from_str::<Vec<serde_json::Value>>(&plans).unwrap()[0]).unwrap();
+
+        assert_eq!(mozart.apply_modulation_json(&first).unwrap(), 13);
+        assert_eq!(mozart.undo_label(), Some("Apply modulation".to_string()));
+        assert_eq!(mozart.song.marker_tick("G Major"), Some(1920 * 3));
+    }
+
+    #[test]
+    fn test_counterpoint() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4w D4w E4w D4w C4w").unwrap();
+
+        let report: serde_json::Value = serde_json::from_str(&mozart.generate_counterpoint_json("").unwrap()).unwrap();
+        assert_eq!(report["notes"], 5);
+        assert_eq!(report["relaxations"], serde_json::json!([]));
+        assert_eq!(mozart.song.notes.iter().filter(|n| n.voice == 1).count(), 5);
+        assert_eq!(mozart.undo_label(), Some("Generate counterpoint".to_string()));
+    }
+
+    #[test]
+    fn test_generate_melody() {
+        let mut mozart = Mozart::new();
+        let config = r#"{"chart": "| C . . . | G . C . |", "syncopation": 0}"#;
+        let report: serde_json::Value = serde_json::from_str(&mozart.generate_melody_json(config).unwrap()).unwrap();
+        assert_eq!(report["notes"], 8);
+        assert_eq!(mozart.song.notes.iter().filter(|n| n.voice == 1).count(), 8);
+        assert_eq!(mozart.undo_label(), Some("Generate melody".to_string()));
+    }
+
+    #[test]
+    fn test_merge_song() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+        let mut other = Song::new();
+        other.set_tempo(60);
+        other.add_note(Note::new(67, 480, 480));
+
+        // At 60 BPM the note comes twice as many ticks in at the song's 120
+        assert_eq!(mozart.merge_song_json(&other.to_json().unwrap(), "align").unwrap(), "[1]");
+        let midi = export_to_midi(&other).unwrap();
+        assert_eq!(mozart.merge_midi(&midi, "keep").unwrap(), "[2]");
+        let starts: Vec<(u8, u32)> = mozart.song.notes.iter().map(|n| (n.voice, n.start_tick)).collect();
+        assert_eq!(starts, vec![(0, 0), (2, 480), (1, 960)]);
+        assert!(mozart.song.settings.channel_per_voice);
+        assert_eq!(mozart.undo_label(), Some("Merge song".to_string()));
+    }
+
+    #[test]
+    fn test_compare_song() {
+        let mut mozart = Mozart::new();
+        for (i, pitch) in [60, 62, 64, 65, 67].into_iter().enumerate() {
+            mozart.add_note(pitch, i as u32 * 480, 480);
+        }
+        let mut other = Song::new();
+        other.add_notes([65, 67, 69, 70, 72].into_iter().enumerate().map(|(i, p)| Note::new(p, i as u32 * 480, 480)));
+
+        let report: serde_json::Value = serde_json::from_str(&mozart.compare_song_json(&other.to_json().unwrap()).unwrap()).unwrap();
+        assert_eq!(report["score"], 100.0);
+        assert_eq!(report["region"]["transposition"], 5);
+        let midi = export_to_midi(&other).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&mozart.compare_midi(&midi).unwrap()).unwrap();
+        assert_eq!(report["region"]["b_end_tick"], 2400);
+    }
+
+    #[test]
+    fn test_comparison_playback() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4h D4h E4w").unwrap();
+        let mut other = Song::new();
+        other.add_notes(crate::note::parse_melody("C4h D4h F4w").unwrap());
+
+        let comparison: serde_json::Value =
+            serde_json::from_str(&mozart.get_comparison_playback_json(&other.to_json().unwrap(), "alternate").unwrap()).unwrap();
+        let versions: Vec<&str> = comparison["segments"].as_array().unwrap().iter().map(|s| s["version"].as_str().unwrap()).collect();
+        assert_eq!(versions, vec!["same", "current", "other"]);
+        assert_eq!(comparison["tracks"][0]["voice"], 1);
+    }
+
+    #[test]
+    fn test_split_song() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 1920);
+        mozart.add_note(62, 1920, 1920);
+        mozart.add_marker("Verse", 1920);
+
+        let plan = mozart.plan_split_json(&[1920]).unwrap();
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&plan).unwrap()[1]["start_position"], "2.1.0");
+        let sections: serde_json::Value = serde_json::from_str(&mozart.plan_sections_json().unwrap()).unwrap();
+        assert_eq!(sections[1]["title"], "Untitled - Verse");
+        let pieces: Vec<Song> = serde_json::from_str(&mozart.split_song_json(&plan).unwrap()).unwrap();
+        assert_eq!(pieces[1].notes[0].start_tick, 0);
+        assert_eq!(mozart.song.notes.len(), 2);
+    }
+
+    #[test]
+    fn test_load_groove_preset() {
+        let mut mozart = Mozart::new();
+        assert!(groove_presets_json().contains("\"Kopanitsa\""));
+        let preset: serde_json::Value = serde_json::from_str(&mozart.load_groove_preset("Kopanitsa").unwrap()).unwrap();
+        assert_eq!(preset["time_signature"], "11/8");
+        assert_eq!(mozart.song.settings.tempo, 140);
+        assert_eq!(mozart.song.notes.iter().filter(|n| n.voice == crate::presets::DRUM_VOICE).count(), 24);
+        assert_eq!(mozart.undo_label(), Some("Load groove preset".to_string()));
+    }
+
+    #[test]
+    fn test_auditions() {
+        let mut mozart = Mozart::new();
+        mozart.set_tempo(60);
+
+        let scale: serde_json::Value =
+            serde_json::from_str(&mozart.scale_audition_json("G", "Mixolydian", "down").unwrap()).unwrap();
+        assert_eq!(scale[0]["pitch"], 79);
+        assert_eq!(scale[1]["start_seconds"], 0.5);
+        let chord: serde_json::Value =
+            serde_json::from_str(&mozart.chord_audition_json("F", "maj7", 0).unwrap()).unwrap();
+        assert_eq!(chord.as_array().unwrap().len(), 8);
+
+        // The ladder and its auditions follow the song's key
+        mozart.set_key("G major").unwrap();
+        let ladder: Vec<crate::harmony::DiatonicChord> = serde_json::from_str(&mozart.get_diatonic_chords_json()).unwrap();
+        assert_eq!(ladder.len(), 7);
+        assert_eq!(ladder[4].seventh.symbol, "D7");
+        let dominant: serde_json::Value =
+            serde_json::from_str(&mozart.diatonic_chord_audition_json(5, false).unwrap()).unwrap();
+        assert_eq!(dominant[0]["pitch"], 62);
+    }
+
+    #[test]
+    fn test_variations() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q D4q E4q F4q").unwrap();
+        let label = mozart.undo_label();
+
+        let variations: serde_json::Value =
+            serde_json::from_str(&mozart.generate_variations_json(&[0, 1, 2, 3], 3, "").unwrap()).unwrap();
+        assert_eq!(variations.as_array().unwrap().len(), 3);
+        assert_eq!(variations[2]["kind"], "octave_displacement");
+        // Generating changes nothing until one is applied
+        assert_eq!(mozart.undo_label(), label);
+
+        let audition: serde_json::Value = serde_json::from_str(&mozart.variation_audition_json(2).unwrap()).unwrap();
+        assert_eq!(audition[1]["start_seconds"], 0.5);
+        assert_eq!(mozart.apply_variation(2).unwrap(), 4);
+        assert_eq!(mozart.undo_label(), Some("Apply variation".to_string()));
+        let notes: serde_json::Value = serde_json::from_str(&mozart.get_notes_json()).unwrap();
+        assert_eq!(notes, variations[2]["notes"]);
+    }
+
+    #[test]
+    fn test_continue_melody() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q D4q E4q F4q").unwrap();
+        let label = mozart.undo_label();
+
+        let continuation: serde_json::Value =
+            serde_json::from_str(&mozart.continue_melody_json(r#"{"measures": 1}"#).unwrap()).unwrap();
+        assert_eq!(continuation["start_tick"], 1920);
+        assert_eq!(continuation["end_tick"], 3840);
+        // A preview until it is kept
+        assert_eq!(mozart.undo_label(), label);
+        let audition: serde_json::Value = serde_json::from_str(&mozart.continuation_audition_json().unwrap()).unwrap();
+        assert_eq!(audition[0]["start_seconds"], 0.0);
+
+        let added = continuation["notes"].as_array().unwrap().len();
+        assert_eq!(mozart.accept_continuation().unwrap(), added);
+        assert_eq!(mozart.song.notes.len(), 4 + added);
+        assert_eq!(mozart.undo_label(), Some("Continue melody".to_string()));
+    }
+
+    #[test]
+    fn test_groove() {
+        let mut performance = Mozart::new();
+        performance.add_note_with_velocity(60, 0, 240, 110);
+        performance.add_note_with_velocity(62, 300, 180, 70);
+        let midi = performance.to_midi().unwrap();
+        let template = extract_groove_from_midi_json(&midi, 2).unwrap();
+        assert_eq!(template, performance.extract_groove_json(&[0, 1], 2).unwrap());
+
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4e D4e E4e F4e").unwrap();
+        assert_eq!(mozart.apply_groove_json(&template, &[0, 1, 2, 3]).unwrap(), 4);
+        assert_eq!(mozart.undo_label(), Some("Apply groove".to_string()));
+        let notes: serde_json::Value = serde_json::from_str(&mozart.get_notes_json()).unwrap();
+        assert_eq!(notes[1]["start_tick"], 300);
+        assert_eq!(notes[1]["velocity"], 80);
+    }
+
+    #[test]
+    fn test_new_song_defaults() {
+        let saved = set_new_song_defaults(r#"{"tempo": 96, "time_signature": "6/8", "pickup_beats": 1}"#).unwrap();
+        let defaults: SongDefaults = serde_json::from_str(&saved).unwrap();
+        assert_eq!(defaults, SongDefaults { tempo: 96, time_signature: "6/8".to_string(), pickup_beats: 1, ..SongDefaults::default() });
+
+        let mozart = Mozart::with_defaults("Jig", &saved).unwrap();
+        assert_eq!((mozart.title(), mozart.tempo()), ("Jig".to_string(), 96));
+        assert_eq!(mozart.get_time_signature_numerator(), 6);
+        assert!(!mozart.can_undo());
+    }
+
+    #[test]
+    fn test_visible_pitch_range() {
+        let mut mozart = Mozart::new();
+        assert_eq!(mozart.get_visible_pitch_range("").unwrap(), vec![36, 84]);
+        mozart.parse_melody_str("E1q C3q").unwrap();
+        assert_eq!(mozart.get_visible_pitch_range("").unwrap(), vec![23, 53]);
+
+        let manual = parse_pitch_view("C1-C7").unwrap();
+        assert_eq!(mozart.get_visible_pitch_range(&manual).unwrap(), vec![24, 96]);
+    }
+
+    #[test]
+    fn test_measure_loops() {
+        let mut mozart = Mozart::new();
+        assert!(mozart.get_loop_region().is_empty());
+        assert_eq!(mozart.loop_current_measure(2000).unwrap(), vec![1920, 3840]);
+        assert_eq!(mozart.extend_loop_by_measure(0).unwrap(), vec![1920, 5760]);
+        assert_eq!(mozart.loop_previous_measure(0).unwrap(), vec![0, 3840]);
+        assert_eq!(mozart.loop_next_measure(0).unwrap(), vec![3840, 7680]);
+        assert_eq!(mozart.get_loop_region(), vec![3840, 7680]);
+        // Looping is not an edit
+        assert!(!mozart.can_undo());
+        assert!(mozart.clear_loop());
+        assert!(!mozart.clear_loop());
+    }
+
+    #[test]
+    fn test_instrument() {
+        let mut mozart = Mozart::new();
+        assert_eq!(mozart.instrument(), "synth");
+        mozart.set_instrument("Organ").unwrap();
+        let changes: serde_json::Value = serde_json::from_str(&mozart.take_changes_json()).unwrap();
+        assert_eq!(changes[0], serde_json::json!({ "type": "settings:instrument", "instrument": "organ" }));
+
+        let reopened = Mozart::from_json(&mozart.to_json().unwrap()).unwrap();
+        assert_eq!(reopened.instrument(), "organ");
+        mozart.undo();
+        assert_eq!(mozart.instrument(), "synth");
+        assert!(get_instruments().contains("\"flute\""));
+    }
+
+    #[test]
+    fn test_practice_wav() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q E4q").unwrap();
+        let wav = mozart.to_practice_wav(0.5).unwrap();
+        assert_eq!(&wav[8..12], b"WAVE");
+        // Two quarter notes at 120 BPM, at half speed: two seconds of 16-bit stereo
+        assert_eq!(wav.len(), 44 + 2 * DEFAULT_SAMPLE_RATE as usize * 4);
+    }
+
+    #[test]
+    fn test_pitch_range() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q A5q").unwrap();
+        mozart.take_changes_json();
+        mozart.set_pitch_range_preset("alto").unwrap();
+        assert_eq!(mozart.get_out_of_range_notes_json(), "[1]");
+        let changes: serde_json::Value = serde_json::from_str(&mozart.take_changes_json()).unwrap();
+        assert_eq!(changes[0]["type"], "settings:pitchRange");
+        assert_eq!(changes[0]["range"]["name"], "Alto");
+
+        // Both notes are moved, so both warn, not just the newly out-of-range one
+        mozart.transpose_chromatic(16).unwrap();
+        let changes: serde_json::Value = serde_json::from_str(&mozart.take_changes_json()).unwrap();
+        let warnings = &changes.as_array().unwrap().last().unwrap()["warnings"];
+        assert_eq!(warnings.as_array().unwrap().len(), 2);
+
+        let folded: serde_json::Value = serde_json::from_str(&mozart.fold_into_range_json(53, 74).unwrap()).unwrap();
+        assert_eq!(folded.as_array().unwrap().len(), 2);
+        assert_eq!(mozart.get_out_of_range_notes_json(), "[]");
+        assert_eq!(mozart.undo_label(), Some("Fold into range".to_string()));
+
+        mozart.clear_pitch_range();
+        assert_eq!(mozart.get_pitch_range_json(), "null");
+        assert_eq!(mozart.get_out_of_range_notes_json(), "[]");
+        assert!(pitch_range_presets_json().contains("\"Violin\""));
+        assert_eq!(instrument_range_json("alto voice").unwrap(), r#"{"name":"Alto","low":53,"high":74}"#);
+    }
+
+    #[test]
+    fn test_cleanup_song() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q D4q").unwrap();
+        mozart.add_note(60, 0, 480);
+        let label = mozart.undo_label();
+
+        let preview: serde_json::Value = serde_json::from_str(&mozart.cleanup_song_json(true)).unwrap();
+        assert_eq!(preview["duplicates"], serde_json::json!([1]));
+        assert_eq!((mozart.note_count(), mozart.undo_label()), (3, label));
+
+        mozart.cleanup_song_json(false);
+        assert_eq!(mozart.note_count(), 2);
+        assert_eq!(mozart.undo_label(), Some("Clean up notes".to_string()));
+    }
+
+    #[test]
+    fn test_velocity_dynamics() {
+        let mut mozart = Mozart::new();
+        mozart.add_note_with_velocity(60, 0, 480, 20);
+        mozart.add_note_with_velocity(62, 480, 480, 127);
+        assert_eq!(mozart.compress_velocities(4.0, 100).unwrap(), 1);
+        assert_eq!(mozart.normalize_velocities(50, 90).unwrap(), 2);
+        assert_eq!(mozart.undo_label(), Some("Normalize velocities".to_string()));
+        let notes: serde_json::Value = serde_json::from_str(&mozart.get_notes_json()).unwrap();
+        assert_eq!((notes[0]["velocity"].clone(), notes[1]["velocity"].clone()), (50.into(), 90.into()));
+    }
+
+    #[test]
+    fn test_pitch_histogram() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4h E4h G4w").unwrap();
+
+        let histogram: serde_json::Value =
+            serde_json::from_str(&mozart.get_pitch_histogram_json(1920).unwrap()).unwrap();
+        assert_eq!(histogram["lowest_pitch"], 60);
+        assert_eq!(histogram["highest_pitch"], 67);
+        assert_eq!(histogram["buckets"][0]["mean_pitch"], 62.0);
+        assert_eq!(histogram["buckets"][1]["ticks"][7], 1920);
+    }
+
+    #[test]
+    fn test_lyrics() {
+        let mut mozart = Mozart::new();
+        mozart.parse_melody_str("C4q D4q E4h").unwrap();
+
+        assert_eq!(mozart.set_lyrics("Hel-lo there"), 3);
+        assert_eq!(mozart.undo_label(), Some("Set lyrics".to_string()));
+        assert_eq!(mozart.get_lyrics_text(), "Hel-lo there");
+        assert!(mozart.export_lrc(false).unwrap().ends_with("[00:00.00]Hello there\n"));
+        assert!(mozart.export_chordpro().unwrap().ends_with("\nHello there\n"));
+    }
+
+    #[test]
+    fn test_chord_chart() {
+        let mut mozart = Mozart::new();
+        let chords: serde_json::Value =
+            serde_json::from_str(&mozart.import_chord_chart_json("| C . . . | G7 |").unwrap()).unwrap();
+        assert_eq!(chords[1]["start_tick"], 1920);
+        assert_eq!(chords[1]["pitch_classes"], serde_json::json!([7, 11, 2, 5]));
+        assert_eq!(mozart.song.notes.len(), 7);
+        assert_eq!(mozart.undo_label(), Some("Import chord chart".to_string()));
+    }
+
+    #[test]
+    fn test_chord_track() {
+        let mut mozart = Mozart::new();
+        let chord: serde_json::Value = serde_json::from_str(&mozart.parse_chord_json("F#m7b5").unwrap()).unwrap();
+        assert_eq!(chord["quality"], "diminished");
+        assert_eq!(chord["extensions"], serde_json::json!([10]));
+
+        mozart.set_chord(0, "Cmaj7").unwrap();
+        mozart.set_chord(1920, "G/B").unwrap();
+        assert_eq!(mozart.undo_label(), Some("Set chord".to_string()));
+        let chords: serde_json::Value = serde_json::from_str(&mozart.get_chords_json()).unwrap();
+        assert_eq!(chords[1], serde_json::json!({"tick": 1920, "symbol": "G/B"}));
+        let played: Vec<Note> = serde_json::from_str(&mozart.get_playback_notes_json()).unwrap();
+        assert_eq!(played.len(), 8);
+
+        assert!(mozart.remove_chord(0));
+        assert!(!mozart.remove_chord(0));
+        assert_eq!(mozart.song.chords.len(), 1);
+    }
+
+    #[test]
+    fn test_call_response() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+
+        let info: serde_json::Value =
+            serde_json::from_str(&mozart.start_call_response_json(r#"{"measures": 1}"#).unwrap()).unwrap();
+        assert_eq!(info["duration_seconds"], 2.0);
+
+        mozart.call_response_note_on(60, 100, 2.0);
+        let events: serde_json::Value = serde_json::from_str(&mozart.advance_call_response_json(4.5)).unwrap();
+        assert_eq!(events[0]["type"], "practice:phase");
+        assert_eq!(events[2]["result"]["notes"][0]["verdict"], "correct");
+        assert_eq!(mozart.stop_call_response_json(), "{\"notes\":[{\"index\":0,\"expected_pitch\":60,\"played_pitch\":60,\"timing_offset_ticks\":0,\"verdict\":\"correct\"}],\"extra_notes\":0,\"pitch_accuracy\":100.0,\"rhythm_accuracy\":100.0,\"score\":100.0}");
+        assert_eq!(mozart.stop_call_response_json(), "null");
+    }
+
+    #[test]
+    fn test_import_is_undoable() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+
+        let mut other = Song::with_title("Imported");
+        other.add_note(Note::new(72, 0, 960));
+        other.add_note(Note::new(74, 960, 960));
+        mozart.import_json(&other.to_json().unwrap()).unwrap();
+        assert_eq!(mozart.title(), "Imported");
+        assert_eq!(mozart.add_notes_json(&mozart.get_notes_json()).unwrap(), 2);
+        assert_eq!(mozart.note_count(), 4);
+
+        assert!(mozart.undo());
+        assert!(mozart.undo());
+        assert_eq!(mozart.note_count(), 1);
+    }
+
+    #[test]
+    fn test_change_events() {
+        let mut mozart = Mozart::new();
+        mozart.add_note(60, 0, 480);
+        mozart.set_tempo(90);
+        let changes: Vec<SongChange> = serde_json::from_str(&mozart.take_changes_json()).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                SongChange::NotesAdded { index: 0, notes: vec![Note::new(60, 0, 480)] },
+                SongChange::NotesDirty { start_tick: 0, end_tick: 480 },
+                SongChange::Tempo { tempo: 90 },
+            ]
+        );
+        assert_eq!(mozart.take_changes_json(), "[]");
+
+        mozart.undo();
+        mozart.undo();
+        let changes: Vec<SongChange> = serde_json::from_str(&mozart.take_changes_json()).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                SongChange::Tempo { tempo: 120 },
+                SongChange::NotesRemoved { index: 0, count: 1 },
+                SongChange::NotesDirty { start_tick: 0, end_tick: 480 },
+            ]
+        );
+
+        // A replacement supersedes everything pending before it
+        mozart.add_note(62, 0, 480);
+        mozart.import_json(&Song::new().to_json().unwrap()).unwrap();
+        mozart.add_note(64, 0, 480);
+        assert_eq!(mozart.take_changes_json(), r#"[{"type":"song:replaced"}]"#);
+    }
 }
@@ -0,0 +1,358 @@
+//! Watch-folder import (native only)
+//!
+//! While a piece is being written in notation software it gets exported to
+//! the same folder again and again. A [`FolderWatcher`] polls that folder
+//! and picks up every `.mid` file that appears or changes there, once its
+//! size and modification time have held still for a poll (so a file that is
+//! still being written isn't read halfway). [`import_into_library`] turns
+//! each one into a `.mozart.json` song in the library directory, replacing
+//! the earlier import of the same file.
+//!
+//! [`watch_folder`] runs this on a background thread and reports every
+//! import as a [`WatchEvent`]; the host shows it as a notification that
+//! offers to open the song. Files already in the folder when watching starts
+//! are left alone. Polling rather than OS change notifications keeps the
+//! watcher dependency-free and also works on network drives, where change
+//! notifications are unreliable.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use crate::error::{MozartError, Result};
+use crate::midi::import_from_midi_file;
+use crate::settings::WatchFolderSettings;
+use crate::song::SongMetadata;
+
+/// How often [`watch_folder`] checks for stop requests while waiting out a poll interval
+const STOP_CHECK_MS: u64 = 50;
+
+/// Size and modification time, to tell when a file changed or is still being written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Whether a path names a MIDI file (by extension)
+pub fn is_midi_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("mid") || ext.eq_ignore_ascii_case("midi"))
+}
+
+/// Finds MIDI files that are new or changed in a folder since the last poll
+#[derive(Debug)]
+pub struct FolderWatcher {
+    folder: PathBuf,
+    /// Last stamp seen per file, and whether the file has been reported in that state
+    seen: HashMap<PathBuf, (FileStamp, bool)>,
+}
+
+impl FolderWatcher {
+    /// Start watching a folder; the MIDI files already in it are not reported
+    pub fn new(folder: impl Into<PathBuf>) -> Result<Self> {
+        let folder = folder.into();
+        if !folder.is_dir() {
+            return Err(MozartError::FileError(format!("Not a folder: {:?}", folder)));
+        }
+        let mut watcher = FolderWatcher { folder, seen: HashMap::new() };
+        for (path, stamp) in watcher.list()? {
+            watcher.seen.insert(path, (stamp, true));
+        }
+        Ok(watcher)
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    fn list(&self) -> Result<Vec<(PathBuf, FileStamp)>> {
+        let entries = fs::read_dir(&self.folder)
+            .map_err(|e| MozartError::FileError(format!("Failed to read {:?}: {}", self.folder, e)))?;
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| is_midi_file(path))
+            .filter_map(|path| {
+                let meta = fs::metadata(&path).ok().filter(|meta| meta.is_file())?;
+                Some((path, FileStamp { len: meta.len(), modified: meta.modified().ok() }))
+            })
+            .collect())
+    }
+
+    /// MIDI files that appeared or changed and have stayed the same since the previous poll
+    ///
+    /// A new file is reported on the second poll that sees it unchanged, and
+    /// again whenever it is overwritten.
+    pub fn poll(&mut self) -> Result<Vec<PathBuf>> {
+        let current = self.list()?;
+        let present: HashSet<PathBuf> = current.iter().map(|(path, _)| path.clone()).collect();
+        let mut ready = Vec::new();
+        for (path, stamp) in current {
+            match self.seen.get(&path) {
+                Some(&(last, reported)) if last == stamp => {
+                    if !reported {
+                        self.seen.insert(path.clone(), (stamp, true));
+                        ready.push(path);
+                    }
+                }
+                _ => {
+                    self.seen.insert(path, (stamp, false));
+                }
+            }
+        }
+        // A file deleted and dropped in again counts as new
+        self.seen.retain(|path, _| present.contains(path));
+        ready.sort();
+        Ok(ready)
+    }
+}
+
+/// Where a song imported from the watch folder went
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WatchEvent {
+    Imported { source: PathBuf, song: PathBuf, title: String, notes: usize },
+    Failed { source: PathBuf, error: String },
+}
+
+impl WatchEvent {
+    /// Notification text for the host
+    pub fn message(&self) -> String {
+        match self {
+            WatchEvent::Imported { source, title, notes, .. } => {
+                format!("Imported '{}' ({} notes) from {}. Open it?", title, notes, file_name(source))
+            }
+            WatchEvent::Failed { source, error } => format!("Could not import {}: {}", file_name(source), error),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Import a MIDI file into the library as `<file stem>.mozart.json`, replacing an earlier import
+///
+/// Songs the MIDI file doesn't name are titled after the file.
+pub fn import_into_library(midi: impl AsRef<Path>, library: impl AsRef<Path>) -> Result<WatchEvent> {
+    let (midi, library) = (midi.as_ref(), library.as_ref());
+    let stem = midi
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| MozartError::FileError(format!("Not a file: {:?}", midi)))?;
+    let mut song = import_from_midi_file(midi)?;
+    if song.metadata.title.trim().is_empty() || song.metadata.title == SongMetadata::default().title {
+        song.metadata.title = stem.clone();
+    }
+    fs::create_dir_all(library)
+        .map_err(|e| MozartError::FileError(format!("Failed to create {:?}: {}", library, e)))?;
+    let path = library.join(format!("{}.mozart.json", stem));
+    song.save(&path)?;
+    tracing::info!("Watch folder imported {:?} to {:?}", midi, path);
+    Ok(WatchEvent::Imported {
+        source: midi.to_path_buf(),
+        song: path,
+        title: song.metadata.title,
+        notes: song.notes.len(),
+    })
+}
+
+/// A running [`watch_folder`] thread; stopped when dropped
+pub struct WatchHandle {
+    folder: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Stop watching and wait for the thread to finish
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Import every MIDI file dropped into `folder` into `library`, on a background thread
+///
+/// `listener` is called from the watcher thread for each import or failure.
+pub fn watch_folder(
+    folder: impl Into<PathBuf>,
+    library: impl Into<PathBuf>,
+    poll_ms: u64,
+    listener: impl Fn(&WatchEvent) + Send + 'static,
+) -> Result<WatchHandle> {
+    let mut watcher = FolderWatcher::new(folder)?;
+    let library = library.into();
+    let folder = watcher.folder().to_path_buf();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = Arc::clone(&stop);
+    tracing::info!("Watching {:?} for MIDI files (library {:?})", folder, library);
+
+    let thread = thread::Builder::new()
+        .name("mozart-watch".to_string())
+        .spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match watcher.poll() {
+                    Ok(ready) => {
+                        for midi in ready {
+                            let event = import_into_library(&midi, &library).unwrap_or_else(|e| {
+                                tracing::warn!("Watch folder import of {:?} failed: {}", midi, e);
+                                WatchEvent::Failed { source: midi, error: e.to_string() }
+                            });
+                            listener(&event);
+                        }
+                    }
+                    // The folder may be on a drive that comes and goes; keep trying
+                    Err(e) => tracing::warn!("{}", e),
+                }
+                let mut waited = 0;
+                while waited < poll_ms && !stopped.load(Ordering::Relaxed) {
+                    let step = STOP_CHECK_MS.min(poll_ms - waited);
+                    thread::sleep(Duration::from_millis(step));
+                    waited += step;
+                }
+            }
+        })
+        .map_err(|e| MozartError::FileError(format!("Failed to start watcher: {}", e)))?;
+
+    Ok(WatchHandle { folder, stop, thread: Some(thread) })
+}
+
+/// Start the watcher the settings describe, or None when it is turned off
+pub fn watch_from_settings(
+    settings: &WatchFolderSettings,
+    listener: impl Fn(&WatchEvent) + Send + 'static,
+) -> Result<Option<WatchHandle>> {
+    if !settings.enabled {
+        return Ok(None);
+    }
+    settings.validate()?;
+    watch_folder(&settings.folder, &settings.library, settings.poll_ms, listener).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::export_to_midi_file;
+    use crate::note::Note;
+    use crate::song::Song;
+    use pretty_assertions::assert_eq;
+    use std::sync::mpsc;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mozart-watch-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_midi(path: &Path, title: &str, notes: usize) {
+        let mut song = Song::with_title(title);
+        for i in 0..notes {
+            song.add_note(Note::new(60 + i as u8, i as u32 * 480, 480));
+        }
+        export_to_midi_file(&song, path).unwrap();
+    }
+
+    #[test]
+    fn test_poll_reports_new_and_changed_files() {
+        let dir = temp_dir("poll");
+        write_midi(&dir.join("old.mid"), "Old", 1);
+        fs::write(dir.join("notes.txt"), "not midi").unwrap();
+        let mut watcher = FolderWatcher::new(&dir).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+
+        // Reported once it has held still for a poll
+        let new = dir.join("new.MID");
+        write_midi(&new, "New", 2);
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), vec![new.clone()]);
+        assert!(watcher.poll().unwrap().is_empty());
+
+        // Re-exported over the same name
+        write_midi(&new, "New", 5);
+        assert!(watcher.poll().unwrap().is_empty());
+        assert_eq!(watcher.poll().unwrap(), vec![new]);
+
+        assert!(FolderWatcher::new(dir.join("missing")).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_import_into_library() {
+        let dir = temp_dir("import");
+        let midi = dir.join("Sketch.mid");
+        write_midi(&midi, "", 3);
+        let library = dir.join("library");
+
+        let event = import_into_library(&midi, &library).unwrap();
+        let song_path = library.join("Sketch.mozart.json");
+        assert_eq!(
+            event,
+            WatchEvent::Imported { source: midi.clone(), song: song_path.clone(), title: "Sketch".to_string(), notes: 3 }
+        );
+        assert_eq!(event.message(), "Imported 'Sketch' (3 notes) from Sketch.mid. Open it?");
+        assert_eq!(Song::load(&song_path).unwrap().notes.len(), 3);
+
+        // A new export replaces the earlier import
+        write_midi(&midi, "Sketch v2", 4);
+        import_into_library(&midi, &library).unwrap();
+        let song = Song::load(&song_path).unwrap();
+        assert_eq!((song.metadata.title.as_str(), song.notes.len()), ("Sketch v2", 4));
+
+        fs::write(dir.join("broken.mid"), "not midi").unwrap();
+        assert!(import_into_library(dir.join("broken.mid"), &library).is_err());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_watch_folder() {
+        let dir = temp_dir("thread");
+        let (folder, library) = (dir.join("exports"), dir.join("library"));
+        fs::create_dir_all(&folder).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let handle = watch_folder(&folder, &library, 20, move |event| {
+            sender.send(event.clone()).ok();
+        })
+        .unwrap();
+
+        write_midi(&folder.join("Etude.mid"), "Etude", 2);
+        let event = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            event,
+            WatchEvent::Imported {
+                source: folder.join("Etude.mid"),
+                song: library.join("Etude.mozart.json"),
+                title: "Etude".to_string(),
+                notes: 2,
+            }
+        );
+        handle.stop();
+
+        let off = WatchFolderSettings::default();
+        assert!(watch_from_settings(&off, |_| {}).unwrap().is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
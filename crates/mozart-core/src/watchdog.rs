@@ -0,0 +1,210 @@
+//! Audio engine health checks
+//!
+//! A stalled or underrunning audio stream, or an output device that went
+//! away, otherwise leaves the app silently mute. The host samples its audio
+//! clock a few times a second and hands it to [`AudioWatchdog::observe`]
+//! along with the stream's state; the watchdog compares the audio clock to
+//! wall time and reports an [`AudioDiagnostics`] when they part ways. The
+//! host then re-creates its engine, keeping the transport position, unless
+//! that has already happened [`MAX_RECOVERIES`] times in the last
+//! [`RECOVERY_PERIOD_MS`].
+//!
+//! Times are milliseconds on any steady wall clock (e.g., `performance.now()`).
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Wall time each clock comparison covers
+pub const WINDOW_MS: f64 = 1_000.0;
+
+/// Audio clock progress, as a share of wall time, below which the stream has stalled
+pub const STALL_RATIO: f64 = 0.1;
+
+/// Progress below which a window counts as underrunning (the stream keeps dropping buffers)
+pub const UNDERRUN_RATIO: f64 = 0.9;
+
+/// Underrunning windows in a row before it's reported
+pub const UNDERRUN_WINDOWS: u32 = 3;
+
+/// Recoveries allowed within [`RECOVERY_PERIOD_MS`] before the host should stop trying
+pub const MAX_RECOVERIES: usize = 3;
+pub const RECOVERY_PERIOD_MS: f64 = 60_000.0;
+
+/// What went wrong with the audio stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFault {
+    /// The audio clock stopped while the stream claims to be running
+    Stalled,
+    /// The audio clock keeps falling behind wall time
+    Underrun,
+    /// The output device disappeared
+    DeviceLost,
+    /// The stream was closed out from under the app
+    Closed,
+}
+
+impl AudioFault {
+    pub fn description(&self) -> &'static str {
+        match self {
+            AudioFault::Stalled => "Audio output stalled",
+            AudioFault::Underrun => "Audio output keeps dropping out",
+            AudioFault::DeviceLost => "Audio output device disconnected",
+            AudioFault::Closed => "Audio output was closed",
+        }
+    }
+}
+
+/// What the host reports as an `audio:error`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioDiagnostics {
+    pub fault: AudioFault,
+    pub message: String,
+    /// Stream state the host reported (e.g., "running", "closed")
+    pub state: String,
+    /// Wall and audio clock time over the last window (both 0 when not measured)
+    pub wall_ms: f64,
+    pub audio_ms: f64,
+    /// Recoveries within the last [`RECOVERY_PERIOD_MS`], counting this one
+    pub recoveries: usize,
+    /// Whether to re-create the engine (false once recoveries keep failing)
+    pub recover: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Mark {
+    wall_ms: f64,
+    audio_ms: f64,
+}
+
+/// Watches one audio stream's clock
+#[derive(Debug, Clone, Default)]
+pub struct AudioWatchdog {
+    window: Option<Mark>,
+    lagging: u32,
+    recoveries: VecDeque<f64>,
+}
+
+impl AudioWatchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a clock sample; returns diagnostics when the stream is unhealthy
+    ///
+    /// `audio_time` is the stream's clock in seconds. A stream that isn't
+    /// running (suspended until a user gesture, or interrupted by a call) is
+    /// not a fault, and restarts the measurement.
+    pub fn observe(&mut self, now_ms: f64, audio_time: f64, state: &str) -> Option<AudioDiagnostics> {
+        match state {
+            "running" => {}
+            "closed" => return Some(self.fault(AudioFault::Closed, now_ms, state, 0.0, 0.0)),
+            _ => {
+                self.restart();
+                return None;
+            }
+        }
+
+        let mark = Mark { wall_ms: now_ms, audio_ms: audio_time * 1000.0 };
+        let Some(start) = self.window else {
+            self.window = Some(mark);
+            return None;
+        };
+        let wall_ms = mark.wall_ms - start.wall_ms;
+        if wall_ms < WINDOW_MS {
+            return None;
+        }
+        let audio_ms = mark.audio_ms - start.audio_ms;
+        self.window = Some(mark);
+
+        let ratio = audio_ms / wall_ms;
+        if ratio < STALL_RATIO {
+            return Some(self.fault(AudioFault::Stalled, now_ms, state, wall_ms, audio_ms));
+        }
+        if ratio < UNDERRUN_RATIO {
+            self.lagging += 1;
+            tracing::debug!("Audio clock lagging: {:.0} of {:.0} ms", audio_ms, wall_ms);
+            if self.lagging >= UNDERRUN_WINDOWS {
+                return Some(self.fault(AudioFault::Underrun, now_ms, state, wall_ms, audio_ms));
+            }
+        } else {
+            self.lagging = 0;
+        }
+        None
+    }
+
+    /// The host noticed its output device is gone
+    pub fn device_lost(&mut self, now_ms: f64, state: &str) -> AudioDiagnostics {
+        self.fault(AudioFault::DeviceLost, now_ms, state, 0.0, 0.0)
+    }
+
+    /// Start measuring afresh (e.g., after the engine was re-created)
+    pub fn restart(&mut self) {
+        self.window = None;
+        self.lagging = 0;
+    }
+
+    fn fault(&mut self, fault: AudioFault, now_ms: f64, state: &str, wall_ms: f64, audio_ms: f64) -> AudioDiagnostics {
+        self.restart();
+        self.recoveries.retain(|&at| now_ms - at < RECOVERY_PERIOD_MS);
+        self.recoveries.push_back(now_ms);
+        let recoveries = self.recoveries.len();
+        let recover = recoveries <= MAX_RECOVERIES;
+        let message = match recover {
+            true => format!("{}; restarting audio", fault.description()),
+            false => format!("{}; gave up after {} restarts", fault.description(), recoveries - 1),
+        };
+        tracing::warn!("{} (state {}, {:.0} ms audio in {:.0} ms)", message, state, audio_ms, wall_ms);
+        AudioDiagnostics { fault, message, state: state.to_string(), wall_ms, audio_ms, recoveries, recover }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // Sample every 250 ms with the audio clock advancing at `rate` of wall time
+    fn run(watchdog: &mut AudioWatchdog, from_ms: f64, to_ms: f64, rate: f64) -> Vec<AudioDiagnostics> {
+        let mut faults = Vec::new();
+        let mut now = from_ms;
+        while now <= to_ms {
+            faults.extend(watchdog.observe(now, now * rate / 1000.0, "running"));
+            now += 250.0;
+        }
+        faults
+    }
+
+    #[test]
+    fn test_stall_and_underrun() {
+        let mut watchdog = AudioWatchdog::new();
+        assert!(run(&mut watchdog, 0.0, 5_000.0, 1.0).is_empty());
+
+        // Suspended is a choice, not a fault
+        assert!(watchdog.observe(5_250.0, 5.25, "suspended").is_none());
+
+        let stalled = run(&mut watchdog, 6_000.0, 7_000.0, 0.0);
+        assert_eq!(stalled.len(), 1);
+        assert_eq!(stalled[0].fault, AudioFault::Stalled);
+        assert!(stalled[0].recover);
+
+        // Three lagging windows in a row before an underrun is reported
+        let mut watchdog = AudioWatchdog::new();
+        let faults = run(&mut watchdog, 0.0, 4_000.0, 0.5);
+        let kinds: Vec<AudioFault> = faults.iter().map(|d| d.fault).collect();
+        assert_eq!(kinds, vec![AudioFault::Underrun]);
+    }
+
+    #[test]
+    fn test_gives_up_after_repeated_recoveries() {
+        let mut watchdog = AudioWatchdog::new();
+        let faults: Vec<AudioDiagnostics> = (0..4).map(|i| watchdog.device_lost(i as f64 * 1_000.0, "running")).collect();
+        let recover: Vec<bool> = faults.iter().map(|d| d.recover).collect();
+        assert_eq!(recover, vec![true, true, true, false]);
+        assert!(faults[3].message.contains("gave up"));
+
+        // Once the period has passed, restarts are allowed again
+        let closed = watchdog.observe(120_000.0, 0.0, "closed").unwrap();
+        assert_eq!((closed.fault, closed.recoveries, closed.recover), (AudioFault::Closed, 1, true));
+    }
+}
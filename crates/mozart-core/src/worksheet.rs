@@ -0,0 +1,583 @@
+//! Ear-training worksheets
+//!
+//! Generates short exercises (interval identification, rhythm dictation, and
+//! melodic dictation) as songs with an answer key, and writes a set to a
+//! directory as one MIDI file per exercise plus a printable PDF. Generation
+//! is seeded, so the same config always produces the same worksheet.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::error::{MozartError, Result};
+use crate::midi::MidiExporter;
+use crate::note::{format_melody, Note, NoteDuration, NoteValue};
+use crate::pitch::{Pitch, PitchClass};
+use crate::scale::Scale;
+use crate::song::Song;
+use crate::theory::{interval_name, spell_above};
+use crate::time::TimeSignature;
+
+/// Kind of exercise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExerciseKind {
+    /// Name the interval between two notes
+    Interval,
+    /// Write down a rhythm played on one pitch
+    Rhythm,
+    /// Write down a short melody in the key
+    Melody,
+}
+
+impl ExerciseKind {
+    pub const ALL: [ExerciseKind; 3] = [ExerciseKind::Interval, ExerciseKind::Rhythm, ExerciseKind::Melody];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "interval" | "intervals" => Ok(ExerciseKind::Interval),
+            "rhythm" | "rhythms" => Ok(ExerciseKind::Rhythm),
+            "melody" | "melodies" | "melodic" => Ok(ExerciseKind::Melody),
+            _ => Err(MozartError::ParseError(format!("Unknown exercise kind: {}", s))),
+        }
+    }
+
+    fn file_stem(&self) -> &'static str {
+        match self {
+            ExerciseKind::Interval => "interval",
+            ExerciseKind::Rhythm => "rhythm",
+            ExerciseKind::Melody => "melody",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "easy" | "1" => Ok(Difficulty::Easy),
+            "medium" | "2" => Ok(Difficulty::Medium),
+            "hard" | "3" => Ok(Difficulty::Hard),
+            _ => Err(MozartError::ParseError(format!("Unknown difficulty: {}", s))),
+        }
+    }
+
+    // Intervals asked about, in semitones
+    fn intervals(&self) -> &'static [u8] {
+        match self {
+            Difficulty::Easy => &[3, 4, 5, 7, 12],
+            Difficulty::Medium | Difficulty::Hard => &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12],
+        }
+    }
+
+    // Note lengths used in rhythms, besides the meter's beat
+    fn durations(&self) -> Vec<NoteDuration> {
+        use NoteValue::*;
+        match self {
+            Difficulty::Easy => vec![NoteDuration::new(Half), NoteDuration::new(Quarter)],
+            Difficulty::Medium => vec![
+                NoteDuration::new(Half),
+                NoteDuration::new(Quarter),
+                NoteDuration::new(Eighth),
+                NoteDuration::dotted(Quarter),
+            ],
+            Difficulty::Hard => vec![
+                NoteDuration::new(Quarter),
+                NoteDuration::new(Eighth),
+                NoteDuration::new(Sixteenth),
+                NoteDuration::dotted(Quarter),
+                NoteDuration::dotted(Eighth),
+            ],
+        }
+    }
+
+    fn measures(&self) -> u32 {
+        match self {
+            Difficulty::Easy => 2,
+            Difficulty::Medium | Difficulty::Hard => 4,
+        }
+    }
+
+    // Largest melodic leap, in scale steps
+    fn max_leap(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => 3,
+            Difficulty::Hard => 7,
+        }
+    }
+}
+
+/// What to generate; omitted fields use the defaults
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorksheetConfig {
+    pub title: String,
+    /// Number of exercises
+    pub count: usize,
+    /// Exercise kinds, used in rotation
+    pub kinds: Vec<ExerciseKind>,
+    pub difficulty: Difficulty,
+    /// Meter for rhythm and melodic dictation (e.g., "3/4")
+    pub time_signature: String,
+    /// Key for melodic dictation (e.g., "G major")
+    pub key: String,
+    pub tempo: u16,
+    /// Same seed, same worksheet
+    pub seed: u64,
+}
+
+impl Default for WorksheetConfig {
+    fn default() -> Self {
+        WorksheetConfig {
+            title: "Ear Training".to_string(),
+            count: 9,
+            kinds: ExerciseKind::ALL.to_vec(),
+            difficulty: Difficulty::Easy,
+            time_signature: "4/4".to_string(),
+            key: "C major".to_string(),
+            tempo: 80,
+            seed: 1,
+        }
+    }
+}
+
+/// One exercise with its answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exercise {
+    /// 1-based position on the sheet
+    pub number: usize,
+    pub kind: ExerciseKind,
+    pub prompt: String,
+    pub answer: String,
+    pub song: Song,
+}
+
+impl Exercise {
+    /// MIDI file name (e.g., "03-melody.mid")
+    pub fn file_name(&self) -> String {
+        format!("{:02}-{}.mid", self.number, self.kind.file_stem())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Worksheet {
+    pub title: String,
+    pub exercises: Vec<Exercise>,
+}
+
+/// Files written by [`generate_worksheet`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorksheetReport {
+    /// One MIDI file per exercise, in order
+    pub midi_files: Vec<PathBuf>,
+    /// Questions followed by the answer key
+    pub pdf: PathBuf,
+}
+
+// SplitMix64: small, seedable, and identical on every platform
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub(crate) fn range(&mut self, low: i32, high: i32) -> i32 {
+        low + self.below((high - low + 1) as usize) as i32
+    }
+
+    pub(crate) fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    pub(crate) fn chance(&mut self, one_in: usize) -> bool {
+        self.below(one_in) == 0
+    }
+}
+
+/// Generate the exercises and answer key
+pub fn build_worksheet(config: &WorksheetConfig) -> Result<Worksheet> {
+    if config.count == 0 || config.kinds.is_empty() {
+        return Err(MozartError::WorksheetError("Nothing to generate".to_string()));
+    }
+    let time_signature = TimeSignature::parse(&config.time_signature)?;
+    let key = Scale::parse(&config.key)?;
+    tracing::info!("Building worksheet: {} {:?} exercises (seed {})", config.count, config.difficulty, config.seed);
+
+    let mut rng = Rng(config.seed);
+    let exercises = (0..config.count)
+        .map(|i| {
+            let kind = config.kinds[i % config.kinds.len()];
+            let mut song = Song::with_title(format!("{} {}", config.title, i + 1));
+            song.set_tempo(config.tempo);
+            song.set_time_signature(time_signature.clone());
+            song.set_key(key);
+
+            let (prompt, answer) = match kind {
+                ExerciseKind::Interval => interval_exercise(&mut rng, config.difficulty, &mut song),
+                ExerciseKind::Rhythm => rhythm_exercise(&mut rng, config.difficulty, &mut song),
+                ExerciseKind::Melody => melody_exercise(&mut rng, config.difficulty, &mut song),
+            };
+            Exercise { number: i + 1, kind, prompt, answer, song }
+        })
+        .collect();
+
+    Ok(Worksheet { title: config.title.clone(), exercises })
+}
+
+/// Generate a worksheet and write its MIDI files and PDF to `out_dir`
+pub fn generate_worksheet(config: &WorksheetConfig, out_dir: impl AsRef<Path>) -> Result<WorksheetReport> {
+    let out_dir = out_dir.as_ref();
+    let worksheet = build_worksheet(config)?;
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| MozartError::FileError(format!("Failed to create {:?}: {}", out_dir, e)))?;
+
+    let exporter = MidiExporter::new();
+    let midi_files = worksheet
+        .exercises
+        .iter()
+        .map(|exercise| {
+            let path = out_dir.join(exercise.file_name());
+            exporter.export_to_file(&exercise.song, &path)?;
+            Ok(path)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let pdf = out_dir.join("worksheet.pdf");
+    std::fs::write(&pdf, worksheet_pdf(&worksheet))
+        .map_err(|e| MozartError::FileError(format!("Failed to write {:?}: {}", pdf, e)))?;
+
+    tracing::info!("Worksheet written to {:?}", out_dir);
+    Ok(WorksheetReport { midi_files, pdf })
+}
+
+// MIDI note of a scale degree counted from the tonic in octave 4 (0 = tonic)
+fn degree_pitch(key: &Scale, degree: i32) -> u8 {
+    let intervals = key.scale_type.intervals();
+    let octave = degree.div_euclid(7);
+    let step = intervals[degree.rem_euclid(7) as usize] as i32;
+    (60 + key.root.semitones() as i32 + 12 * octave + step) as u8
+}
+
+fn pitch_name(midi: u8) -> String {
+    Pitch::from_midi(midi).map(|p| p.to_string()).unwrap_or_default()
+}
+
+fn interval_exercise(rng: &mut Rng, difficulty: Difficulty, song: &mut Song) -> (String, String) {
+    let key = song.settings.key;
+    let semitones = rng.pick(difficulty.intervals());
+    // A scale tone between G3 and G4 keeps both notes in a comfortable range
+    let lower = degree_pitch(&key, rng.range(-3, 3));
+    let upper = lower + semitones;
+
+    let harmonic = difficulty == Difficulty::Hard && rng.chance(3);
+    let descending = !harmonic && difficulty != Difficulty::Easy && rng.chance(2);
+    let whole = NoteValue::Whole.ticks();
+    let (first, second) = if descending { (upper, lower) } else { (lower, upper) };
+    if harmonic {
+        song.add_notes([Note::new(lower, 0, whole), Note::new(upper, 0, whole)]);
+    } else {
+        song.add_notes([Note::new(first, 0, whole / 2), Note::new(second, whole / 2, whole / 2)]);
+    }
+
+    let (name, short_name) = interval_name(semitones).unwrap_or(("Interval", "?"));
+    let direction = if harmonic { "harmonic" } else if descending { "descending" } else { "ascending" };
+    let lower_pc = PitchClass::new(lower % 12);
+    let upper_name = format!("{}{}", spell_above(lower_pc, semitones), Pitch::from_midi(upper).map_or(4, |p| p.octave()));
+    let notes = if descending {
+        format!("{}–{}", upper_name, pitch_name(lower))
+    } else {
+        format!("{}–{}", pitch_name(lower), upper_name)
+    };
+    (
+        format!("Name the interval ({})", direction),
+        format!("{} ({}), {}", name, short_name, notes),
+    )
+}
+
+// Fill the difficulty's measures with note lengths from `vocabulary` (and
+// always the beat, so any meter can be completed), each flagged true if it
+// is a rest
+fn rhythm(
+    rng: &mut Rng,
+    difficulty: Difficulty,
+    vocabulary: Difficulty,
+    time_signature: &TimeSignature,
+    rests: bool,
+) -> Vec<(NoteDuration, bool)> {
+    let beat = NoteDuration::from_ticks(time_signature.ticks_per_beat());
+    let mut durations = vocabulary.durations();
+    if !durations.contains(&beat) {
+        durations.push(beat);
+    }
+    let measure = time_signature.ticks_per_measure();
+
+    let mut events = Vec::new();
+    for _ in 0..difficulty.measures() {
+        let mut left = measure;
+        while left > 0 {
+            let fits: Vec<NoteDuration> = durations.iter().copied().filter(|d| d.ticks() <= left).collect();
+            // Only meters with beats shorter than a sixteenth (e.g., 5/32) can run out
+            let duration = if fits.is_empty() { NoteDuration::from_ticks(left) } else { rng.pick(&fits) };
+            let rest = rests && !events.is_empty() && rng.chance(6);
+            left -= duration.ticks().min(left);
+            events.push((duration, rest));
+        }
+    }
+    events
+}
+
+fn rhythm_answer(events: &[(NoteDuration, bool)], measure: u32) -> String {
+    let mut answer = String::new();
+    let mut tick = 0;
+    for (duration, rest) in events {
+        if tick > 0 && tick % measure == 0 {
+            answer.push_str(" |");
+        }
+        if !answer.is_empty() {
+            answer.push(' ');
+        }
+        if *rest {
+            answer.push('r');
+        }
+        answer.push_str(&duration.to_string());
+        tick += duration.ticks();
+    }
+    answer
+}
+
+fn rhythm_exercise(rng: &mut Rng, difficulty: Difficulty, song: &mut Song) -> (String, String) {
+    let time_signature = song.settings.time_signature.clone();
+    let events = rhythm(rng, difficulty, difficulty, &time_signature, difficulty != Difficulty::Easy);
+    let pitch = degree_pitch(&song.settings.key, 0);
+
+    let mut tick = 0;
+    for (duration, rest) in &events {
+        if !rest {
+            song.add_note(Note::new(pitch, tick, duration.ticks()));
+        }
+        tick += duration.ticks();
+    }
+
+    (
+        format!(
+            "Write the rhythm ({} measures of {}/{})",
+            difficulty.measures(),
+            time_signature.numerator,
+            time_signature.denominator
+        ),
+        rhythm_answer(&events, time_signature.ticks_per_measure()),
+    )
+}
+
+fn melody_exercise(rng: &mut Rng, difficulty: Difficulty, song: &mut Song) -> (String, String) {
+    let key = song.settings.key;
+    let time_signature = song.settings.time_signature.clone();
+    // Easier melodies keep to halves and beats; hard ones use every rhythm
+    let vocabulary = if difficulty == Difficulty::Hard { difficulty } else { Difficulty::Easy };
+    let events = rhythm(rng, difficulty, vocabulary, &time_signature, false);
+
+    let mut degree = 0;
+    let mut tick = 0;
+    let last = events.len() - 1;
+    for (i, (duration, _)) in events.iter().enumerate() {
+        if i == last {
+            // Resolve to the nearest tonic
+            degree = if degree > 3 { 7 } else { 0 };
+        } else if i > 0 {
+            let leap = difficulty.max_leap();
+            degree = (degree + rng.range(-leap, leap)).clamp(-3, 9);
+        }
+        song.add_note(Note::new(degree_pitch(&key, degree), tick, duration.ticks()));
+        tick += duration.ticks();
+    }
+
+    (
+        format!("Write the melody in {} (it starts on {})", key, pitch_name(degree_pitch(&key, 0))),
+        format_melody(&song.notes),
+    )
+}
+
+// Lines of the question pages and the answer key pages
+fn worksheet_pdf(worksheet: &Worksheet) -> Vec<u8> {
+    let mut questions = vec![
+        worksheet.title.clone(),
+        "Name: ______________________    Date: ____________".to_string(),
+        String::new(),
+    ];
+    for exercise in &worksheet.exercises {
+        questions.push(format!("{}. {}  [{}]", exercise.number, exercise.prompt, exercise.file_name()));
+        questions.push("    ________________________________________________".to_string());
+        questions.push(String::new());
+    }
+
+    let mut answers = vec![format!("{}: Answer Key", worksheet.title), String::new()];
+    for exercise in &worksheet.exercises {
+        answers.push(format!("{}. {}", exercise.number, exercise.answer));
+    }
+
+    let mut pages = paginate(questions);
+    pages.extend(paginate(answers));
+    text_pdf(&pages)
+}
+
+const PDF_LINES_PER_PAGE: usize = 48;
+
+fn paginate(lines: Vec<String>) -> Vec<Vec<String>> {
+    lines.chunks(PDF_LINES_PER_PAGE).map(|page| page.to_vec()).collect()
+}
+
+// A text-only PDF (US Letter, Helvetica): the first line of each page is a heading
+fn text_pdf(pages: &[Vec<String>]) -> Vec<u8> {
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|i| format!("{} 0 R", 4 + 2 * i)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+    ];
+    for (i, lines) in pages.iter().enumerate() {
+        let mut content = String::from("BT\n/F1 16 Tf\n56 736 Td\n");
+        for (n, line) in lines.iter().enumerate() {
+            if n == 1 {
+                content.push_str("/F1 11 Tf\n0 -28 Td\n");
+            } else if n > 1 {
+                content.push_str("0 -14 Td\n");
+            }
+            content.push_str(&format!("({}) Tj\n", pdf_escape(line)));
+        }
+        content.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 612 792] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + 2 * i
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+    pdf.into_bytes()
+}
+
+// PDF string literal contents; non-ASCII as WinAnsi octal escapes
+fn pdf_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '(' | ')' | '\\' => format!("\\{}", c),
+            '–' => "\\226".to_string(),
+            '—' => "\\227".to_string(),
+            c if c.is_ascii() => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_worksheet_is_seeded() {
+        let config = WorksheetConfig { count: 6, ..WorksheetConfig::default() };
+        let answers = |config: &WorksheetConfig| -> Vec<String> {
+            build_worksheet(config).unwrap().exercises.into_iter().map(|e| e.answer).collect()
+        };
+        assert_eq!(answers(&config), answers(&config));
+        assert_ne!(answers(&config), answers(&WorksheetConfig { seed: 2, ..config.clone() }));
+
+        let first = build_worksheet(&config).unwrap();
+
+        let kinds: Vec<ExerciseKind> = first.exercises.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds[..3], ExerciseKind::ALL);
+        assert_eq!(first.exercises[2].file_name(), "03-melody.mid");
+        assert!(build_worksheet(&WorksheetConfig { count: 0, ..config }).is_err());
+    }
+
+    #[test]
+    fn test_exercises_fill_the_meter() {
+        for (meter, difficulty) in [("4/4", Difficulty::Medium), ("7/8", Difficulty::Easy), ("6/8", Difficulty::Hard)] {
+            let config = WorksheetConfig {
+                count: 12,
+                kinds: vec![ExerciseKind::Rhythm, ExerciseKind::Melody],
+                difficulty,
+                time_signature: meter.to_string(),
+                ..WorksheetConfig::default()
+            };
+            for exercise in build_worksheet(&config).unwrap().exercises {
+                let song = &exercise.song;
+                let measures = song.settings.time_signature.ticks_per_measure() * difficulty.measures();
+                if exercise.kind == ExerciseKind::Rhythm {
+                    // A trailing rest leaves the song short, but the bar lines still count
+                    assert!(song.duration_ticks() <= measures, "{} {}", meter, exercise.answer);
+                    assert_eq!(exercise.answer.matches('|').count() as u32, difficulty.measures() - 1);
+                } else {
+                    assert_eq!(song.duration_ticks(), measures, "{} {}", meter, exercise.answer);
+                    assert_eq!(song.notes.last().unwrap().pitch % 12, 0); // Ends on C
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_interval_answers() {
+        let config = WorksheetConfig {
+            count: 20,
+            kinds: vec![ExerciseKind::Interval],
+            key: "Bb major".to_string(),
+            ..WorksheetConfig::default()
+        };
+        for exercise in build_worksheet(&config).unwrap().exercises {
+            let notes = &exercise.song.notes;
+            let semitones = (notes[1].pitch as i16 - notes[0].pitch as i16).unsigned_abs() as u8;
+            let (name, _) = interval_name(semitones).unwrap();
+            assert!(exercise.answer.starts_with(name), "{}", exercise.answer);
+            assert!(Difficulty::Easy.intervals().contains(&semitones));
+        }
+    }
+
+    #[test]
+    fn test_generate_worksheet_files() {
+        let dir = std::env::temp_dir().join(format!("mozart-worksheet-{}", std::process::id()));
+        let config = WorksheetConfig { count: 3, ..WorksheetConfig::default() };
+        let report = generate_worksheet(&config, &dir).unwrap();
+
+        assert_eq!(report.midi_files.len(), 3);
+        assert!(report.midi_files.iter().all(|path| path.exists()));
+        let song = crate::midi::import_from_midi_file(&report.midi_files[0]).unwrap();
+        assert_eq!(song.notes.len(), 2);
+
+        let pdf = std::fs::read(&report.pdf).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Ear Training: Answer Key) Tj"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -7,10 +7,36 @@ import {
   TextInput,
   TransposePanel,
   AccentEditor,
+  CommandPalette,
+  TutorialPanel,
+  TheoryPanel,
+  PracticePanel,
+  HarmonyPanel,
+  ModulationPanel,
+  VariationPanel,
+  ContinuationPanel,
+  SongDefaultsPanel,
+  TracksPanel,
+  RegisterHeatmap,
 } from './components'
+import { connectMediaSession } from './integration/mediaSession'
+import { connectWakeLock } from './integration/wakeLock'
+import { connectFileLaunch } from './integration/fileOpen'
 
 export default function App() {
-  const { init, isWasmLoaded } = useMozartStore()
+  const {
+    init,
+    isWasmLoaded,
+    startupDiagnostics,
+    seekTo,
+    seekToMeasure,
+    nextMarker,
+    prevMarker,
+    loopCurrentMeasure,
+    loopNextMeasure,
+    loopPreviousMeasure,
+    extendLoopByMeasure,
+  } = useMozartStore()
   const [error, setError] = useState<string | null>(null)
 
   useEffect(() => {
@@ -20,6 +46,54 @@ export default function App() {
     })
   }, [init])
 
+  // Media keys and the OS media controls
+  useEffect(() => {
+    if (!isWasmLoaded) return
+    return connectMediaSession()
+  }, [isWasmLoaded])
+
+  // Files and share links the app was opened with (not in safe mode: one may be what crashed)
+  useEffect(() => {
+    if (isWasmLoaded && !startupDiagnostics?.safe_mode) connectFileLaunch()
+  }, [isWasmLoaded, startupDiagnostics])
+
+  // Keep the machine awake during playback
+  useEffect(() => {
+    if (!isWasmLoaded) return
+    return connectWakeLock()
+  }, [isWasmLoaded])
+
+  // Navigation shortcuts: [ / ] jump between markers, Home to start, Ctrl+G go to measure,
+  // L loop the measure (Shift+L add a measure), Shift+Left / Shift+Right step the loop
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      const target = e.target as HTMLElement
+      if (target.tagName === 'INPUT' || target.tagName === 'TEXTAREA' || target.tagName === 'SELECT') return
+
+      if (e.key === '[') {
+        prevMarker()
+      } else if (e.key === ']') {
+        nextMarker()
+      } else if (e.key === 'Home') {
+        seekTo(0)
+      } else if (e.key.toLowerCase() === 'l' && !e.ctrlKey && !e.metaKey && !e.altKey) {
+        if (e.shiftKey) extendLoopByMeasure()
+        else loopCurrentMeasure()
+      } else if (e.shiftKey && (e.key === 'ArrowRight' || e.key === 'ArrowLeft')) {
+        e.preventDefault()
+        if (e.key === 'ArrowRight') loopNextMeasure()
+        else loopPreviousMeasure()
+      } else if (e.key === 'g' && (e.ctrlKey || e.metaKey)) {
+        e.preventDefault()
+        const measure = parseInt(window.prompt('Go to measure:') ?? '', 10)
+        if (!isNaN(measure)) seekToMeasure(measure)
+      }
+    }
+
+    window.addEventListener('keydown', handleKeyDown)
+    return () => window.removeEventListener('keydown', handleKeyDown)
+  }, [seekTo, seekToMeasure, nextMarker, prevMarker, loopCurrentMeasure, loopNextMeasure, loopPreviousMeasure, extendLoopByMeasure])
+
   if (error) {
     return (
       <div style={styles.error}>
@@ -43,11 +117,35 @@ export default function App() {
   return (
     <div style={styles.app}>
       <Header />
+      {startupDiagnostics?.safe_mode && (
+        <div style={styles.safeMode}>
+          <strong>{startupDiagnostics.message}</strong>
+          <span>Skipped: {startupDiagnostics.skipped.join(', ')}.</span>
+          {startupDiagnostics.suggestions.map((suggestion) => (
+            <span key={suggestion}>{suggestion}.</span>
+          ))}
+          {/* Reloading is a clean exit, so the next start is normal */}
+          <button style={styles.safeModeButton} onClick={() => window.location.reload()}>
+            Restart normally
+          </button>
+        </div>
+      )}
       <Transport />
+      <RegisterHeatmap />
       <PianoRoll />
       <TransposePanel />
       <AccentEditor />
       <TextInput />
+      <CommandPalette />
+      <TutorialPanel />
+      <TheoryPanel />
+      <PracticePanel />
+      <HarmonyPanel />
+      <ModulationPanel />
+      <VariationPanel />
+      <ContinuationPanel />
+      <SongDefaultsPanel />
+      <TracksPanel />
     </div>
   )
 }
@@ -58,6 +156,26 @@ const styles: Record<string, React.CSSProperties> = {
     flexDirection: 'column',
     height: '100vh',
   },
+  safeMode: {
+    display: 'flex',
+    alignItems: 'center',
+    flexWrap: 'wrap',
+    gap: '12px',
+    padding: '8px 16px',
+    background: '#3a1020',
+    borderBottom: '1px solid #e94560',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  safeModeButton: {
+    marginLeft: 'auto',
+    padding: '4px 12px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+  },
   loading: {
     display: 'flex',
     flexDirection: 'column',
@@ -1,20 +1,115 @@
 // Web Audio API wrapper for Mozart
 // Provides oscillator-based synthesis for note playback
 
+import type { Instrument, Track } from '../wasm/types'
+
+// Audio stream configuration (latency vs stability trade-off)
+export interface AudioConfig {
+  sampleRate: number | null // null = device default
+  latency: AudioContextLatencyCategory | number // category or buffer latency in seconds
+}
+
+export const DEFAULT_AUDIO_CONFIG: AudioConfig = {
+  sampleRate: null,
+  latency: 'interactive',
+}
+
+// Web Audio cannot query device capabilities, so offer the common rates;
+// init() falls back to the device default if the browser rejects one
+export const SUPPORTED_SAMPLE_RATES = [22050, 44100, 48000, 88200, 96000]
+export const SUPPORTED_LATENCIES: AudioContextLatencyCategory[] = ['interactive', 'balanced', 'playback']
+
+// Waveform and envelope (seconds, sustain as a share of the peak) of each instrument;
+// 'synth' keeps the per-voice waveforms instead
+interface Timbre {
+  type: OscillatorType
+  attack: number
+  decay: number
+  sustain: number
+  release: number
+}
+
+const TIMBRES: Record<Exclude<Instrument, 'synth'>, Timbre> = {
+  piano: { type: 'triangle', attack: 0.005, decay: 0.4, sustain: 0.25, release: 0.2 },
+  organ: { type: 'square', attack: 0.01, decay: 0.02, sustain: 0.9, release: 0.05 },
+  strings: { type: 'sawtooth', attack: 0.12, decay: 0.2, sustain: 0.8, release: 0.3 },
+  flute: { type: 'sine', attack: 0.06, decay: 0.1, sustain: 0.85, release: 0.12 },
+}
+
 export class AudioEngine {
   private ctx: AudioContext | null = null
+  private _config: AudioConfig = DEFAULT_AUDIO_CONFIG
   private masterGain: GainNode | null = null
   private activeOscillators: Map<string, { osc: OscillatorNode; gain: GainNode }> = new Map()
-
-  async init(): Promise<void> {
+  // Persistent voice for low-latency pitch previews (e.g., while dragging notes)
+  private previewVoice: { osc: OscillatorNode; gain: GainNode } | null = null
+  // Kept across reinit(), so a restarted engine sounds the same
+  private instrument: Instrument = 'synth'
+  // Voices whose track has an instrument of its own
+  private voiceInstruments: Map<number, Instrument> = new Map()
+
+  async init(config: AudioConfig = this._config): Promise<void> {
     if (this.ctx) return
 
-    this.ctx = new AudioContext()
+    this._config = config
+    // Older iOS Safari only has the prefixed constructor
+    const Context: typeof AudioContext =
+      window.AudioContext ?? (window as unknown as { webkitAudioContext: typeof AudioContext }).webkitAudioContext
+    try {
+      this.ctx = new Context({
+        sampleRate: config.sampleRate ?? undefined,
+        latencyHint: config.latency,
+      })
+    } catch (err) {
+      console.warn('Audio config rejected, using device defaults:', err)
+      this._config = DEFAULT_AUDIO_CONFIG
+      this.ctx = new Context()
+    }
+    this.unlockOnGesture()
+    // Master stereo bus: everything is panned into this node before the destination
     this.masterGain = this.ctx.createGain()
+    this.masterGain.channelCount = 2
+    this.masterGain.channelCountMode = 'explicit'
     this.masterGain.gain.value = 0.3
     this.masterGain.connect(this.ctx.destination)
   }
 
+  // Recreate the audio context with a new configuration
+  async reinit(config: AudioConfig): Promise<void> {
+    this.dispose()
+    await this.init(config)
+  }
+
+  // Configuration the context was created with: the one asked for, or the
+  // defaults if the browser rejected it
+  get config(): AudioConfig {
+    return this._config
+  }
+
+  // Configuration actually in effect (the browser may not honor the request exactly)
+  get activeConfig(): { sampleRate: number; baseLatency: number; outputLatency: number } | null {
+    if (!this.ctx) return null
+    return {
+      sampleRate: this.ctx.sampleRate,
+      baseLatency: this.ctx.baseLatency ?? 0,
+      outputLatency: this.ctx.outputLatency ?? 0,
+    }
+  }
+
+  // Mobile browsers start the context suspended and only let a user gesture resume it
+  private unlockOnGesture(): void {
+    if (this.ctx?.state !== 'suspended') return
+
+    const events = ['touchend', 'pointerdown', 'keydown'] as const
+    const unlock = () => {
+      this.resume().then(() => {
+        if (this.ctx?.state !== 'running') return
+        events.forEach((event) => document.removeEventListener(event, unlock))
+      })
+    }
+    events.forEach((event) => document.addEventListener(event, unlock))
+  }
+
   async resume(): Promise<void> {
     if (this.ctx?.state === 'suspended') {
       await this.ctx.resume()
@@ -73,19 +168,35 @@ export class AudioEngine {
     velocity: number = 100,
     duration: number = 0.5,
     startTime?: number,
-    voice: number = 0
+    voice: number = 0,
+    pan: number = 64,
+    instrument: Instrument = this.voiceInstruments.get(voice) ?? this.instrument
   ): void {
     const frequency = this.midiToFrequency(midi)
-    this.playNote(frequency, velocity, duration, startTime, voice)
+    this.playNote(frequency, velocity, duration, startTime, voice, pan, instrument)
+  }
+
+  // Sound notes are played with from now on
+  setInstrument(instrument: Instrument): void {
+    this.instrument = instrument
   }
 
-  // Play a note with voice-specific timbre
+  // Sounds of the tracks with their own instrument; other voices play the song's
+  setTrackInstruments(tracks: Track[]): void {
+    this.voiceInstruments = new Map(
+      tracks.flatMap((track) => (track.instrument ? [[track.voice, track.instrument] as const] : []))
+    )
+  }
+
+  // Play a note with the instrument's timbre (voice-specific for the synth)
   playNote(
     frequency: number,
     velocity: number = 100,
     duration: number = 0.5,
     startTime?: number,
-    voice: number = 0
+    voice: number = 0,
+    pan: number = 64,
+    instrument: Instrument = this.voiceInstruments.get(voice) ?? this.instrument
   ): void {
     if (!this.ctx || !this.masterGain) return
 
@@ -98,7 +209,8 @@ export class AudioEngine {
 
     // Different oscillator types for different voices
     const oscillatorTypes: OscillatorType[] = ['sine', 'triangle', 'square', 'sawtooth']
-    osc.type = oscillatorTypes[voice % oscillatorTypes.length]
+    const timbre = instrument === 'synth' ? null : TIMBRES[instrument]
+    osc.type = timbre?.type ?? oscillatorTypes[voice % oscillatorTypes.length]
 
     // Slightly detune harmony voices for richer sound
     if (voice > 0) {
@@ -108,10 +220,10 @@ export class AudioEngine {
     osc.frequency.value = frequency
 
     // ADSR envelope (slightly different per voice)
-    const attack = voice === 0 ? 0.01 : 0.02
-    const decay = 0.1
-    const sustain = voice === 0 ? 0.7 : 0.5
-    const release = 0.15
+    const attack = timbre?.attack ?? (voice === 0 ? 0.01 : 0.02)
+    const decay = timbre?.decay ?? 0.1
+    const sustain = timbre?.sustain ?? (voice === 0 ? 0.7 : 0.5)
+    const release = timbre?.release ?? 0.15
 
     gain.gain.setValueAtTime(0, start)
     gain.gain.linearRampToValueAtTime(amp, start + attack)
@@ -120,7 +232,7 @@ export class AudioEngine {
     gain.gain.linearRampToValueAtTime(0, start + duration)
 
     osc.connect(gain)
-    gain.connect(this.masterGain)
+    gain.connect(this.createPanner(this.midiPanToStereo(pan)))
 
     osc.start(start)
     osc.stop(start + duration + 0.01)
@@ -166,6 +278,33 @@ export class AudioEngine {
     this.activeOscillators.delete(id)
   }
 
+  // Preview a pitch on the persistent preview voice
+  // Retunes and retriggers the same oscillator instead of creating new nodes per call
+  previewPitch(midi: number, velocity: number = 90, duration: number = 0.15): void {
+    if (!this.ctx || !this.masterGain) return
+
+    if (!this.previewVoice) {
+      const osc = this.ctx.createOscillator()
+      const gain = this.ctx.createGain()
+      osc.type = 'triangle'
+      gain.gain.value = 0
+      osc.connect(gain)
+      gain.connect(this.masterGain)
+      osc.start()
+      this.previewVoice = { osc, gain }
+    }
+
+    const now = this.ctx.currentTime
+    const amp = (velocity / 127) * 0.5
+    const { osc, gain } = this.previewVoice
+
+    osc.frequency.setValueAtTime(this.midiToFrequency(midi), now)
+    gain.gain.cancelScheduledValues(now)
+    gain.gain.setValueAtTime(gain.gain.value, now)
+    gain.gain.linearRampToValueAtTime(amp, now + 0.005)
+    gain.gain.setTargetAtTime(0, now + duration, 0.03)
+  }
+
   // Play metronome click
   playClick(isDownbeat: boolean = false): void {
     if (!this.ctx || !this.masterGain) return
@@ -189,8 +328,8 @@ export class AudioEngine {
     osc.stop(this.ctx.currentTime + duration)
   }
 
-  // Play click with custom frequency and velocity
-  playClickWithParams(frequency: number, velocity: number): void {
+  // Play click with custom frequency, velocity and stereo position (-1 to 1)
+  playClickWithParams(frequency: number, velocity: number, pan: number = 0): void {
     if (!this.ctx || !this.masterGain) return
 
     const osc = this.ctx.createOscillator()
@@ -206,12 +345,25 @@ export class AudioEngine {
     gain.gain.exponentialRampToValueAtTime(0.001, this.ctx.currentTime + duration)
 
     osc.connect(gain)
-    gain.connect(this.masterGain)
+    gain.connect(this.createPanner(pan))
 
     osc.start()
     osc.stop(this.ctx.currentTime + duration)
   }
 
+  // Create a constant-power panner (-1 = left, 1 = right) feeding the master bus
+  private createPanner(pan: number): AudioNode {
+    const panner = this.ctx!.createStereoPanner()
+    panner.pan.value = Math.max(-1, Math.min(1, pan))
+    panner.connect(this.masterGain!)
+    return panner
+  }
+
+  // Convert MIDI pan (0-127, 64 = center) to stereo position (-1 to 1)
+  midiPanToStereo(pan: number): number {
+    return pan <= 64 ? (pan - 64) / 64 : (pan - 64) / 63
+  }
+
   // Convert MIDI note number to frequency
   midiToFrequency(midi: number): number {
     return 440 * Math.pow(2, (midi - 69) / 12)
@@ -229,6 +381,8 @@ export class AudioEngine {
     for (const [id] of this.activeOscillators) {
       this.stopNote(id)
     }
+    this.previewVoice?.osc.stop()
+    this.previewVoice = null
 
     if (this.ctx) {
       this.ctx.close()
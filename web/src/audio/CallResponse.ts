@@ -0,0 +1,91 @@
+// Call-and-response practice round
+//
+// The core engine picks the phrase and scores the answer; this class owns
+// the clock. The call is scheduled on the audio context after a short
+// lead-in, and every animation frame reports the seconds since the call
+// started so the engine can open the response window and finish the round.
+
+import { AudioEngine } from './AudioEngine'
+import type { CallResponseConfig, CallResponseEvent, CallResponseInfo, Mozart } from '../wasm/types'
+
+const LEAD_IN_SECONDS = 0.5
+
+export class CallResponseSession {
+  private mozart: Mozart
+  private audioEngine: AudioEngine
+  private startTime: number = 0
+  private frameId: number | null = null
+  private onEvent: (event: CallResponseEvent) => void
+
+  readonly info: CallResponseInfo
+
+  // Throws if the config selects no notes
+  constructor(
+    mozart: Mozart,
+    audioEngine: AudioEngine,
+    config: CallResponseConfig,
+    onEvent: (event: CallResponseEvent) => void
+  ) {
+    this.mozart = mozart
+    this.audioEngine = audioEngine
+    this.onEvent = onEvent
+    this.info = JSON.parse(mozart.startCallResponseJson(JSON.stringify(config)))
+  }
+
+  start(): void {
+    this.audioEngine.resume()
+    this.startTime = this.audioEngine.currentTime + LEAD_IN_SECONDS
+
+    const { phrase, duration_ticks, duration_seconds } = this.info
+    const secondsPerTick = duration_seconds / duration_ticks
+    for (const note of phrase) {
+      this.audioEngine.playMidiNote(
+        note.pitch,
+        note.velocity,
+        note.duration_ticks * secondsPerTick,
+        this.startTime + note.start_tick * secondsPerTick,
+        note.voice,
+        note.pan
+      )
+    }
+
+    this.frameId = requestAnimationFrame(this.tick)
+  }
+
+  // A key pressed on the user's MIDI keyboard
+  noteOn(pitch: number, velocity: number): void {
+    this.mozart.callResponseNoteOn(pitch, velocity, this.elapsed())
+  }
+
+  // End the round early, scoring what was played so far
+  stop(): void {
+    this.cancel()
+    const result = JSON.parse(this.mozart.stopCallResponseJson())
+    if (result) this.onEvent({ type: 'practice:result', result })
+  }
+
+  private elapsed(): number {
+    return this.audioEngine.currentTime - this.startTime
+  }
+
+  private cancel(): void {
+    if (this.frameId !== null) cancelAnimationFrame(this.frameId)
+    this.frameId = null
+  }
+
+  private tick = () => {
+    const events: CallResponseEvent[] = JSON.parse(this.mozart.advanceCallResponseJson(this.elapsed()))
+    let finished = false
+    for (const event of events) {
+      if (event.type === 'practice:result') finished = true
+      this.onEvent(event)
+    }
+
+    if (finished) {
+      this.frameId = null
+      this.mozart.stopCallResponseJson() // Drop the finished round
+    } else {
+      this.frameId = requestAnimationFrame(this.tick)
+    }
+  }
+}
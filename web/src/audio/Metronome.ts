@@ -6,17 +6,39 @@ export interface MetronomeOptions {
   tempo: number
   beatsPerMeasure: number
   accents?: number[] // 1=weak, 2=medium, 3=strong
+  clickPan?: number // -1 (left) to 1 (right), offsets clicks from the music
   onBeat?: (beat: number, isDownbeat: boolean) => void
 }
 
+// Secondary click layer: `beats` evenly spaced clicks across `against` main beats (e.g., 3 over 2)
+export interface PolyrhythmLayer {
+  beats: number
+  against: number
+  sound: PolyrhythmSound
+}
+
+export interface PolyrhythmSound {
+  frequency: number // Hz; keep apart from the main clicks (800-1000 Hz)
+  volume: number // 0 to 1
+}
+
+export const DEFAULT_POLYRHYTHM_SOUND: PolyrhythmSound = { frequency: 1500, volume: 0.6 }
+
 export class Metronome {
   private audioEngine: AudioEngine
   private tempo: number
+  private rate: number = 1 // Practice speed, matching the sequencer's
   private beatsPerMeasure: number
   private accents: number[]
+  private clickPan: number
+  private pulseStarts: Set<number> | null = null // Beats that click; null = every beat
   private isPlaying: boolean = false
   private currentBeat: number = 0
   private nextBeatTime: number = 0
+  private beatCount: number = 0 // Beats since start, for polyrhythm cycles across barlines
+  private polyrhythm: PolyrhythmLayer | null = null
+  private layerCycleStart: number = 0
+  private layerClick: number = 0 // Next layer click within the current cycle
   private timerId: number | null = null
   private lookahead: number = 25 // ms
   private scheduleAhead: number = 0.1 // seconds
@@ -28,6 +50,7 @@ export class Metronome {
     this.tempo = options.tempo
     this.beatsPerMeasure = options.beatsPerMeasure
     this.accents = options.accents ?? this.defaultAccents(options.beatsPerMeasure)
+    this.clickPan = options.clickPan ?? 0
     this.onBeat = options.onBeat
   }
 
@@ -42,6 +65,10 @@ export class Metronome {
     this.tempo = Math.max(20, Math.min(300, tempo))
   }
 
+  setRate(rate: number): void {
+    this.rate = Math.max(0.25, Math.min(2, rate))
+  }
+
   setBeatsPerMeasure(beats: number): void {
     this.beatsPerMeasure = beats
     if (this.accents.length !== beats) {
@@ -53,14 +80,42 @@ export class Metronome {
     this.accents = accents
   }
 
-  start(): void {
+  // Click only on the first beat of each group (e.g., [3, 3] feels 6/8 in two)
+  setPulseGrouping(groups: number[]): void {
+    if (groups.every((size) => size === 1)) {
+      this.pulseStarts = null
+      return
+    }
+    const starts = new Set<number>()
+    let beat = 0
+    for (const size of groups) {
+      starts.add(beat)
+      beat += size
+    }
+    this.pulseStarts = starts
+  }
+
+  // Set or clear (null) the polyrhythm layer; a new layer starts with the next cycle
+  setPolyrhythm(layer: PolyrhythmLayer | null): void {
+    this.polyrhythm = layer
+    this.layerClick = layer?.beats ?? 0
+  }
+
+  setClickPan(pan: number): void {
+    this.clickPan = Math.max(-1, Math.min(1, pan))
+  }
+
+  // Start clicking with `beat` of the measure (0-based) at audio clock time `at`,
+  // e.g., the next beat the playhead reaches; by default a downbeat, now
+  start(beat: number = 0, at: number = this.audioEngine.currentTime): void {
     if (this.isPlaying) return
 
     this.audioEngine.resume()
 
     this.isPlaying = true
-    this.currentBeat = 0
-    this.nextBeatTime = this.audioEngine.currentTime
+    this.currentBeat = beat % this.beatsPerMeasure
+    this.beatCount = 0
+    this.nextBeatTime = Math.max(at, this.audioEngine.currentTime)
 
     this.schedule()
   }
@@ -88,22 +143,65 @@ export class Metronome {
     if (!ctx) return
 
     // Schedule beats that fall within the schedule window
-    while (this.nextBeatTime < ctx.currentTime + this.scheduleAhead) {
-      this.scheduleBeat(this.currentBeat, this.nextBeatTime)
+    const horizon = ctx.currentTime + this.scheduleAhead
+    while (this.nextBeatTime < horizon) {
+      if (this.polyrhythm && this.beatCount % this.polyrhythm.against === 0) {
+        // Finish the previous cycle, then start a new one on this beat
+        this.scheduleLayer(this.nextBeatTime)
+        this.layerCycleStart = this.nextBeatTime
+        this.layerClick = 0
+      }
+
+      if (!this.pulseStarts || this.pulseStarts.has(this.currentBeat)) {
+        this.scheduleBeat(this.currentBeat, this.nextBeatTime)
+      }
 
       // Advance beat
-      const secondsPerBeat = 60 / this.tempo
+      const secondsPerBeat = 60 / (this.tempo * this.rate)
       this.nextBeatTime += secondsPerBeat
       this.currentBeat = (this.currentBeat + 1) % this.beatsPerMeasure
+      this.beatCount++
     }
+    this.scheduleLayer(horizon)
 
     // Schedule next check
     this.timerId = window.setTimeout(this.schedule, this.lookahead)
   }
 
+  // Schedule the polyrhythm clicks of the current cycle that fall before `until`
+  private scheduleLayer(until: number): void {
+    const layer = this.polyrhythm
+    if (!layer) return
+
+    const interval = (layer.against * 60) / (this.tempo * this.rate) / layer.beats
+    while (this.layerClick < layer.beats) {
+      const time = this.layerCycleStart + this.layerClick * interval
+      if (time >= until) break
+
+      const delay = Math.max(0, (time - this.audioEngine.currentTime) * 1000)
+      setTimeout(() => {
+        if (!this.isPlaying) return
+        this.audioEngine.playClickWithParams(layer.sound.frequency, layer.sound.volume * 127, this.clickPan)
+      }, delay)
+      this.layerClick++
+    }
+  }
+
+  // Play a single measure of clicks (offsets in seconds) without starting the metronome
+  previewMeasure(clicks: { beat: number; seconds: number; accent: number }[]): void {
+    this.audioEngine.resume()
+    const start = this.audioEngine.currentTime + 0.05
+    for (const click of clicks) {
+      this.scheduleClick(click.beat, click.accent, start + click.seconds)
+    }
+  }
+
   private scheduleBeat(beat: number, time: number): void {
+    this.scheduleClick(beat, this.accents[beat] ?? 1, time)
+  }
+
+  private scheduleClick(beat: number, accent: number, time: number): void {
     const isDownbeat = beat === 0
-    const accent = this.accents[beat] ?? 1
 
     // Map accent level to frequency and velocity
     let frequency: number
@@ -129,7 +227,7 @@ export class Metronome {
     const delay = Math.max(0, (time - this.audioEngine.currentTime) * 1000)
 
     setTimeout(() => {
-      this.audioEngine.playClickWithParams(frequency, velocity)
+      this.audioEngine.playClickWithParams(frequency, velocity, this.clickPan)
       this.onBeat?.(beat, isDownbeat)
     }, delay)
   }
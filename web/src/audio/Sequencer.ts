@@ -13,12 +13,15 @@ export interface SequencerOptions {
   onBeat?: (beat: number, isDownbeat: boolean) => void
   onNotePlay?: (note: Note) => void
   onStateChange?: (state: SequencerState) => void
+  onLoopWrap?: () => void // A loop pass ended and the next one began
+  getNextPassNotes?: () => Note[] | null // Notes the next loop pass plays (chance notes picked again); null for the same notes
 }
 
 export class Sequencer {
   private audioEngine: AudioEngine
   private notes: Note[] = []
   private tempo: number
+  private rate: number = 1 // Practice speed; scales playback without touching the song tempo
   private ticksPerBeat: number
   private _ticksPerMeasure: number = 1920
   private currentTick: number = 0
@@ -26,6 +29,9 @@ export class Sequencer {
   private startTime: number = 0
   private pausedTick: number = 0
   private scheduledNotes: Set<number> = new Set()
+  private nextPassNotes: Set<number> = new Set() // Scheduled for the loop pass after this one
+  private upcomingNotes: Note[] | null = null // What the next loop pass plays, once asked for
+  private loop: { start: number; end: number } | null = null
   private animationFrameId: number | null = null
   private lookaheadTime: number = 0.1 // seconds to look ahead for scheduling
 
@@ -34,6 +40,8 @@ export class Sequencer {
   private _onBeat?: (beat: number, isDownbeat: boolean) => void
   private onNotePlay?: (note: Note) => void
   private onStateChange?: (state: SequencerState) => void
+  private onLoopWrap?: () => void
+  private getNextPassNotes?: () => Note[] | null
 
   constructor(audioEngine: AudioEngine, options: SequencerOptions) {
     this.audioEngine = audioEngine
@@ -43,16 +51,27 @@ export class Sequencer {
     this._onBeat = options.onBeat
     this.onNotePlay = options.onNotePlay
     this.onStateChange = options.onStateChange
+    this.onLoopWrap = options.onLoopWrap
+    this.getNextPassNotes = options.getNextPassNotes
   }
 
   setNotes(notes: Note[]): void {
     this.notes = [...notes]
+    if (this.nextPassNotes.size === 0) this.upcomingNotes = null
   }
 
   setTempo(tempo: number): void {
     this.tempo = Math.max(20, Math.min(300, tempo))
   }
 
+  // Keeps the playhead where it is when changed mid-playback
+  setRate(rate: number): void {
+    this.rate = Math.max(0.25, Math.min(2, rate))
+    if (this.state === 'playing') {
+      this.startTime = this.audioEngine.currentTime - this.tickToSeconds(this.currentTick)
+    }
+  }
+
   setTicksPerBeat(ticks: number): void {
     this.ticksPerBeat = ticks
   }
@@ -61,6 +80,21 @@ export class Sequencer {
     this._ticksPerMeasure = ticks
   }
 
+  // Repeat [start, end) until cleared; a playhead outside the loop jumps to its start
+  setLoop(start: number, end: number): void {
+    if (end <= start) return
+    this.loop = { start, end }
+    this.nextPassNotes.clear()
+    this.upcomingNotes = null
+    if (this.currentTick < start || this.currentTick >= end) this.seekTo(start)
+  }
+
+  clearLoop(): void {
+    this.loop = null
+    this.nextPassNotes.clear()
+    this.upcomingNotes = null
+  }
+
   getState(): SequencerState {
     return this.state
   }
@@ -69,6 +103,11 @@ export class Sequencer {
     return this.currentTick
   }
 
+  // Audio clock time a tick plays at on the current pass (while playing)
+  timeOfTick(tick: number): number {
+    return this.startTime + this.tickToSeconds(tick)
+  }
+
   play(): void {
     if (this.state === 'playing') return
 
@@ -80,6 +119,7 @@ export class Sequencer {
 
     if (this.state === 'stopped') {
       this.scheduledNotes.clear()
+      this.nextPassNotes.clear()
     }
 
     this.state = 'playing'
@@ -105,6 +145,7 @@ export class Sequencer {
     this.currentTick = 0
     this.pausedTick = 0
     this.scheduledNotes.clear()
+    this.nextPassNotes.clear()
     this.onStateChange?.(this.state)
 
     if (this.animationFrameId !== null) {
@@ -117,6 +158,7 @@ export class Sequencer {
     this.currentTick = Math.max(0, tick)
     this.pausedTick = this.currentTick
     this.scheduledNotes.clear()
+    this.nextPassNotes.clear()
 
     if (this.state === 'playing') {
       this.startTime = this.audioEngine.currentTime - this.tickToSeconds(this.currentTick)
@@ -129,9 +171,21 @@ export class Sequencer {
     const ctx = this.audioEngine.context
     if (!ctx) return
 
-    const elapsedTime = ctx.currentTime - this.startTime
+    let elapsedTime = ctx.currentTime - this.startTime
     this.currentTick = this.secondsToTick(elapsedTime)
 
+    // Wrap back to the loop start; the pass's first notes were scheduled ahead
+    if (this.loop && this.currentTick >= this.loop.end) {
+      this.startTime += this.tickToSeconds(this.loop.end - this.loop.start)
+      this.notes = this.upcomingNotes ?? this.getNextPassNotes?.() ?? this.notes
+      this.upcomingNotes = null
+      this.scheduledNotes = this.nextPassNotes
+      this.nextPassNotes = new Set()
+      elapsedTime = ctx.currentTime - this.startTime
+      this.currentTick = this.secondsToTick(elapsedTime)
+      this.onLoopWrap?.()
+    }
+
     // Report current tick
     this.onTick?.(this.currentTick)
 
@@ -147,26 +201,31 @@ export class Sequencer {
       // Skip if note starts after lookahead
       if (note.start_tick > lookaheadTick) continue
 
+      // Notes from the loop end on don't sound while looping
+      if (this.loop && note.start_tick >= this.loop.end) continue
+
       // Skip if note already passed
       if (note.start_tick + note.duration_ticks < this.currentTick) {
         this.scheduledNotes.add(i)
         continue
       }
 
-      // Schedule the note
-      const noteStartTime = this.startTime + this.tickToSeconds(note.start_tick)
-      const noteDuration = this.tickToSeconds(note.duration_ticks)
-
-      this.audioEngine.playMidiNote(
-        note.pitch,
-        note.velocity,
-        noteDuration,
-        noteStartTime,
-        note.voice ?? 0
-      )
-
+      this.scheduleNote(note, this.startTime)
       this.scheduledNotes.add(i)
-      this.onNotePlay?.(note)
+    }
+
+    // A lookahead past the loop end reaches into the next pass: schedule its
+    // first notes now, so they sound on time rather than when the wrap is seen
+    if (this.loop && lookaheadTick >= this.loop.end) {
+      const passStartTime = this.startTime + this.tickToSeconds(this.loop.end - this.loop.start)
+      const until = this.loop.start + lookaheadTick - this.loop.end
+      this.upcomingNotes ??= this.getNextPassNotes?.() ?? this.notes
+      for (let i = 0; i < this.upcomingNotes.length; i++) {
+        const note = this.upcomingNotes[i]
+        if (this.nextPassNotes.has(i) || note.start_tick < this.loop.start || note.start_tick > until) continue
+        this.scheduleNote(note, passStartTime)
+        this.nextPassNotes.add(i)
+      }
     }
 
     // Check if we've passed all notes
@@ -175,7 +234,7 @@ export class Sequencer {
       0
     )
 
-    if (this.currentTick > maxTick + this.ticksPerBeat) {
+    if (!this.loop && this.currentTick > maxTick + this.ticksPerBeat) {
       this.stop()
       return
     }
@@ -183,14 +242,28 @@ export class Sequencer {
     this.animationFrameId = requestAnimationFrame(this.scheduleLoop)
   }
 
+  // Play a note on the pass whose tick 0 is at passStartTime, cut off at the loop end
+  private scheduleNote(note: Note, passStartTime: number): void {
+    const endTick = Math.min(note.start_tick + note.duration_ticks, this.loop?.end ?? Infinity)
+    this.audioEngine.playMidiNote(
+      note.pitch,
+      note.velocity,
+      this.tickToSeconds(endTick - note.start_tick),
+      passStartTime + this.tickToSeconds(note.start_tick),
+      note.voice ?? 0,
+      note.pan ?? 64
+    )
+    this.onNotePlay?.(note)
+  }
+
   private tickToSeconds(tick: number): number {
-    const beatsPerSecond = this.tempo / 60
+    const beatsPerSecond = (this.tempo * this.rate) / 60
     const ticksPerSecond = beatsPerSecond * this.ticksPerBeat
     return tick / ticksPerSecond
   }
 
   private secondsToTick(seconds: number): number {
-    const beatsPerSecond = this.tempo / 60
+    const beatsPerSecond = (this.tempo * this.rate) / 60
     const ticksPerSecond = beatsPerSecond * this.ticksPerBeat
     return Math.floor(seconds * ticksPerSecond)
   }
@@ -1,5 +1,7 @@
-export { AudioEngine, getAudioEngine } from './AudioEngine'
+export { AudioEngine, getAudioEngine, DEFAULT_AUDIO_CONFIG, SUPPORTED_SAMPLE_RATES, SUPPORTED_LATENCIES } from './AudioEngine'
+export type { AudioConfig } from './AudioEngine'
 export { Sequencer } from './Sequencer'
 export type { SequencerState, SequencerOptions } from './Sequencer'
-export { Metronome } from './Metronome'
-export type { MetronomeOptions } from './Metronome'
+export { Metronome, DEFAULT_POLYRHYTHM_SOUND } from './Metronome'
+export type { MetronomeOptions, PolyrhythmLayer, PolyrhythmSound } from './Metronome'
+export { CallResponseSession } from './CallResponse'
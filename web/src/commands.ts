@@ -0,0 +1,1052 @@
+// Handlers for the command registry (mozart-core src/commands.rs)
+//
+// The menu bar and the command palette both run commands by id, so every
+// action is wired up once, here.
+
+import { useMozartStore } from './store'
+import { SUPPORTED_LATENCIES, SUPPORTED_SAMPLE_RATES } from './audio'
+import type { AudioConfig } from './audio'
+import { compareFile, mergeFile, openFile, playComparisonFile } from './integration/fileOpen'
+import { getGroovePresets, getInstrumentRange, getNotationGrammar, getPitchRangePresets, midiToNoteName, noteNameToMidi, parsePitchView, searchCommands, zoomAbout } from './wasm'
+import type { ColorLabel, CommandInfo, Jump, LossSummary, Macro, Take } from './wasm/types'
+
+const ZOOM_STEP = 1.25
+const COLOR_LABELS: ColorLabel[] = ['red', 'orange', 'yellow', 'green', 'blue', 'purple', 'pink', 'gray']
+export const OPEN_PALETTE_EVENT = 'mozart:open-palette'
+export const OPEN_THEORY_EVENT = 'mozart:open-theory'
+export const OPEN_PRACTICE_EVENT = 'mozart:open-practice'
+export const OPEN_HARMONY_EVENT = 'mozart:open-harmony'
+export const OPEN_MODULATION_EVENT = 'mozart:open-modulation'
+export const OPEN_VARIATIONS_EVENT = 'mozart:open-variations'
+export const OPEN_CONTINUATION_EVENT = 'mozart:open-continuation'
+export const OPEN_SONG_DEFAULTS_EVENT = 'mozart:open-song-defaults'
+export const TOGGLE_REGISTER_EVENT = 'mozart:toggle-register'
+export const TOGGLE_TRACKS_EVENT = 'mozart:toggle-tracks'
+export const IS_MAC = /Mac|iPhone|iPad/.test(navigator.platform)
+
+// "Mod+Shift+Z" as Ctrl+Shift+Z, or ⌘⇧Z on macOS
+export function formatShortcut(shortcut: string): string {
+  if (!IS_MAC) return shortcut.replace('Mod', 'Ctrl')
+  return shortcut.replace('Mod+', '⌘').replace('Alt+', '⌥').replace('Shift+', '⇧')
+}
+
+// A key press as "Mod+Alt+Shift+K", the form macro shortcuts are saved in
+export function shortcutFromEvent(e: KeyboardEvent): string {
+  // The physical key, since Alt changes e.key on macOS
+  const key = e.code.match(/^(?:Key|Digit)(.)$/)?.[1] ?? e.key
+  return [IS_MAC ? e.metaKey && 'Mod' : e.ctrlKey && 'Mod', e.altKey && 'Alt', e.shiftKey && 'Shift', key]
+    .filter(Boolean)
+    .join('+')
+}
+
+// Prompt answers for the command a macro is replaying, and those given to
+// the command now running, for recording
+let replayAnswers: string[] | null = null
+let givenAnswers: string[] = []
+
+// window.prompt, answered from the macro when one is replaying
+function ask(message: string, defaultValue?: string): string | null {
+  const answer = replayAnswers ? (replayAnswers.shift() ?? null) : window.prompt(message, defaultValue)
+  if (answer !== null) givenAnswers.push(answer)
+  return answer
+}
+
+// Save a file: the share sheet on touch devices (where download links are
+// unreliable, e.g. iOS Safari), a download everywhere else. Resolves to
+// false if the user dismissed the share sheet.
+async function saveFile(blob: Blob, filename: string): Promise<boolean> {
+  const file = new File([blob], filename, { type: blob.type })
+  if (matchMedia('(pointer: coarse)').matches && navigator.canShare?.({ files: [file] })) {
+    try {
+      await navigator.share({ files: [file] })
+      return true
+    } catch (err) {
+      if ((err as DOMException).name === 'AbortError') return false // User closed the sheet
+    }
+  }
+
+  const url = URL.createObjectURL(blob)
+  const a = document.createElement('a')
+  a.href = url
+  a.download = filename
+  a.click()
+  URL.revokeObjectURL(url)
+  return true
+}
+
+// Run a destructive action, asking first if the core says something would be lost
+export function withConfirmation(run: (confirm: boolean) => LossSummary | null) {
+  const loss = run(false)
+  if (loss && window.confirm(`${loss.message} Continue?`)) run(true)
+}
+
+function confirmDiscard(): boolean {
+  return !useMozartStore.getState().editState.isDirty || window.confirm('Discard unsaved changes?')
+}
+
+function pickFile() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  // Mobile pickers match on MIME type rather than the double extension
+  input.accept = '.mozart.json,.json,application/json,.mid,.midi,audio/midi'
+  input.onchange = () => {
+    const file = input.files?.[0]
+    if (file) openFile(file).catch((err) => console.error('Failed to open file:', err))
+  }
+  input.click()
+}
+
+// Layer other songs into this one; each lands on its own voices and MIDI channel
+function pickMergeFiles() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  input.multiple = true
+  input.accept = '.mozart.json,.json,application/json,.mid,.midi,audio/midi'
+  input.onchange = async () => {
+    const files = Array.from(input.files ?? [])
+    if (files.length === 0) return
+    const answer = ask('Timing: "align" keeps each song\'s own speed, "keep" plays it at this song\'s tempo:', 'align')
+    if (!answer) return
+    const alignment = answer.trim().toLowerCase().startsWith('keep') ? 'keep_first' : 'align'
+    try {
+      for (const file of files) await mergeFile(file, alignment)
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+  input.click()
+}
+
+// Score how alike another song's melody is, and show where they match best
+function pickCompareFile() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  input.accept = '.mozart.json,.json,application/json,.mid,.midi,audio/midi'
+  input.onchange = async () => {
+    const file = input.files?.[0]
+    if (!file) return
+    try {
+      const { score, interval_similarity, rhythm_similarity, region } = await compareFile(file)
+      const summary = `${score.toFixed(0)}% similar to ${file.name} (intervals ${interval_similarity.toFixed(0)}%, rhythm ${rhythm_similarity.toFixed(0)}%)`
+      if (!region) {
+        window.alert(`${summary}
+No passage in common.`)
+        return
+      }
+      const shift = region.transposition === 0 ? 'in the same key' : `${region.transposition > 0 ? '+' : ''}${region.transposition} semitones`
+      window.alert(
+        `${summary}
+Best match: ${region.notes} notes from ${region.a_start_position} here and ${region.b_start_position} there (${shift}).`
+      )
+      // Play from the matching passage
+      useMozartStore.getState().seekTo(region.a_start_tick)
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+  input.click()
+}
+
+// Hear what changed against another version: both at once, or measure by measure
+function pickComparisonFile() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  input.accept = '.mozart.json,.json,application/json,.mid,.midi,audio/midi'
+  input.onchange = async () => {
+    const file = input.files?.[0]
+    if (!file) return
+    const answer = ask('Play "together" (this song left, the other right) or "alternate" measure by measure:', 'together')
+    if (!answer) return
+    const mode = answer.trim().toLowerCase().startsWith('alt') ? 'alternate' : 'together'
+    try {
+      const comparison = await playComparisonFile(file, mode)
+      if (comparison.segments.every((s) => s.version === 'same')) window.alert(`No measures differ from ${file.name}.`)
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+  input.click()
+}
+
+// Cut the song into practice chunks, previewed before each is saved as a numbered file
+async function splitSong() {
+  const answer = ask('Start a piece at each measure (e.g. 5 9 13), or "sections" for one per marker:', 'sections')?.trim()
+  if (!answer) return
+  const { mozart, planSplit, splitSong } = useMozartStore.getState()
+  try {
+    const measures = answer.toLowerCase() === 'sections' ? 'sections' : answer.split(/[\s,]+/).map(Number).filter((m) => m > 0)
+    const plan = planSplit(measures)
+    const preview = plan.map((piece, i) => `${i + 1}. ${piece.title} from ${piece.start_position} (${piece.notes} notes)`)
+    if (!window.confirm(`Save ${plan.length} files?\n${preview.join('\n')}`)) return
+
+    const stem = (mozart?.title || 'song').toLowerCase().replace(/\s+/g, '-')
+    const pieces = splitSong(plan)
+    for (const [i, json] of pieces.entries()) {
+      const number = String(i + 1).padStart(2, '0')
+      if (!(await saveFile(new Blob([json], { type: 'application/json' }), `${stem}-${number}.mozart.json`))) break
+    }
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+async function save() {
+  const { mozart, saveToJson, markSaved } = useMozartStore.getState()
+  const json = saveToJson()
+  if (!json) return
+
+  const saved = await saveFile(new Blob([json], { type: 'application/json' }), `${mozart?.title || 'song'}.mozart.json`)
+  if (saved) markSaved()
+}
+
+function exportMidi() {
+  const { mozart, exportToMidi } = useMozartStore.getState()
+  const midi = exportToMidi()
+  if (!midi) return
+
+  saveFile(new Blob([midi], { type: 'audio/midi' }), `${mozart?.title || 'song'}.mid`)
+}
+
+async function exportSettings() {
+  const json = useMozartStore.getState().exportSettings()
+  await saveFile(new Blob([json], { type: 'application/json' }), 'mozart-settings.json')
+}
+
+function importSettings() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  input.accept = '.json,application/json'
+  input.onchange = async () => {
+    const file = input.files?.[0]
+    if (!file) return
+    try {
+      await useMozartStore.getState().importSettings(await file.text())
+    } catch (err) {
+      window.alert(`Could not import settings: ${err}`)
+    }
+  }
+  input.click()
+}
+
+function importChordChart() {
+  const text = ask('Chord chart, one bar per measure:', '| C . . . | Am . . . | F . G . | C . . . |')
+  if (!text) return
+
+  try {
+    useMozartStore.getState().importChordChart(text)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function exportChordPro() {
+  const { mozart, exportToChordPro } = useMozartStore.getState()
+  try {
+    saveFile(new Blob([exportToChordPro()], { type: 'text/plain' }), `${mozart?.title || 'song'}.cho`)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function exportLyrics() {
+  const { mozart, exportToLrc } = useMozartStore.getState()
+  try {
+    saveFile(new Blob([exportToLrc()], { type: 'text/plain' }), `${mozart?.title || 'song'}.lrc`)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Slower by default; the practice speed when one is set
+function exportPracticeAudio() {
+  const { mozart, playbackRate, exportPracticeAudio } = useMozartStore.getState()
+  const rate = ask('Practice track speed (0.25-2, e.g. 0.5 or 75%):', String(playbackRate === 1 ? 0.5 : playbackRate))
+  if (!rate) return
+  try {
+    const wav = exportPracticeAudio(rate)
+    saveFile(new Blob([wav], { type: 'audio/wav' }), `${mozart?.title || 'song'} (practice).wav`)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// "50%" (or "50") sets the selected note's probability, "A"/"B" its take lane, "-" clears both
+function editNoteChance() {
+  const { notes, selectedNoteIndex, setNoteProbability, setNoteTake } = useMozartStore.getState()
+  if (selectedNoteIndex === null || !notes[selectedNoteIndex]) return
+  const note = notes[selectedNoteIndex]
+  const current = note.take ?? `${note.probability}%`
+  const answer = ask('Chance of playing each pass (e.g. 50%), a take lane (A or B), or - to always play:', current)?.trim()
+  if (!answer) return
+
+  if (answer === '-') {
+    setNoteTake(selectedNoteIndex, null)
+    setNoteProbability(selectedNoteIndex, 100)
+  } else if (/^[ab]$/i.test(answer)) {
+    setNoteTake(selectedNoteIndex, answer.toUpperCase() as Take)
+  } else {
+    const probability = parseFloat(answer)
+    if (isNaN(probability)) window.alert(`Not a probability or take lane: ${answer}`)
+    else setNoteProbability(selectedNoteIndex, probability)
+  }
+}
+
+// A palette name, null for "-" (no label), or undefined when cancelled or unknown
+function askColorLabel(message: string, current?: ColorLabel): ColorLabel | null | undefined {
+  const answer = ask(`${message} (${COLOR_LABELS.join(', ')}, or - for none):`, current ?? '')?.trim().toLowerCase()
+  if (!answer) return undefined
+  if (answer === '-') return null
+  const label = COLOR_LABELS.find((name) => name === (answer === 'grey' ? 'gray' : answer))
+  if (!label) window.alert(`Not a color label: ${answer}`)
+  return label
+}
+
+// The selected note's voice, or every note (e.g., melody in blue, fill in orange)
+function editNoteColor() {
+  const { notes, selectedNoteIndex, setNoteColor } = useMozartStore.getState()
+  const selected = selectedNoteIndex !== null ? notes[selectedNoteIndex] : undefined
+  const label = askColorLabel(selected ? `Color label for voice ${selected.voice + 1}` : 'Color label for every note', selected?.color)
+  if (label !== undefined) setNoteColor(label)
+}
+
+// The section under the playhead, from its marker to the next
+function editSectionColor() {
+  const { markers, currentTick, setSectionColor } = useMozartStore.getState()
+  const marker = [...markers].reverse().find((m) => m.tick <= currentTick)
+  if (!marker) {
+    window.alert('Move the playhead past a marker to color its section')
+    return
+  }
+  const label = askColorLabel(`Color label for section "${marker.name}"`, marker.color)
+  if (label !== undefined) setSectionColor(marker.name, label)
+}
+
+// The selected note's voice in its own meter, with barlines of its own (polymeter)
+function editVoiceMeter() {
+  const { mozart, notes, selectedNoteIndex, timeSignature, voiceMeters, setVoiceTimeSignature } = useMozartStore.getState()
+  const selected = selectedNoteIndex !== null ? notes[selectedNoteIndex] : undefined
+  if (!mozart || !selected) return
+  const songMeter = `${timeSignature.numerator}/${timeSignature.denominator}`
+  // Blank when it follows the song, since a meter typed in is kept even if it matches
+  const ownMeter = voiceMeters.some((meter) => meter.voice === selected.voice)
+  const answer = ask(
+    `Time signature for voice ${selected.voice + 1} (blank to follow the song's ${songMeter}):`,
+    ownMeter ? mozart.getVoiceTimeSignature(selected.voice) : '',
+  )
+  if (answer === null) return
+  try {
+    setVoiceTimeSignature(selected.voice, answer.trim())
+  } catch (e) {
+    window.alert(String(e))
+  }
+}
+
+// A new layer on a voice of its own; notes drawn afterwards go into it
+function addTrack() {
+  const name = ask('Track name (e.g. Bassline):', '')
+  if (name === null) return
+  try {
+    const voice = useMozartStore.getState().addTrack(name)
+    window.alert(`Added ${useMozartStore.getState().tracks.find((t) => t.voice === voice)?.name}; notes you draw now go into it.`)
+  } catch (e) {
+    window.alert(String(e))
+  }
+}
+
+// At the playhead, or through the end of a measure typed after the text
+function addAnnotation() {
+  const { mozart, currentTick, addAnnotation } = useMozartStore.getState()
+  if (!mozart) return
+  const text = ask('Annotation at the playhead (e.g., breathe here):')?.trim()
+  if (!text) return
+  const until = ask('Through measure (leave blank for a single point):', '')?.trim()
+
+  try {
+    const endTick = until ? mozart.measureStartTick(parseInt(until, 10) + 1) : undefined
+    addAnnotation(text, currentTick, endTick)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function removeAnnotation() {
+  const { mozart, annotations, removeAnnotation } = useMozartStore.getState()
+  if (!mozart || annotations.length === 0) return
+  const measure = (tick: number) => Math.floor(tick / mozart.ticksPerMeasure()) + 1
+  const list = annotations.map((a, i) => `${i + 1}. m${measure(a.tick)}: ${a.text}`).join('\n')
+  const index = parseInt(ask(`Remove which annotation?\n${list}`, '1') ?? '', 10)
+  if (!isNaN(index)) removeAnnotation(index - 1)
+}
+
+// A chord symbol on the beat under the playhead; blank removes the one there
+function editChordSymbol() {
+  const { mozart, currentTick, chords, setChord, removeChord } = useMozartStore.getState()
+  if (!mozart) return
+  const tick = currentTick - (currentTick % mozart.ticksPerBeat())
+  const existing = chords.find((c) => c.tick === tick)
+  const answer = ask(`Chord at ${mozart.positionToMbt(tick)} (e.g. Cmaj7, F#m7b5, G/B; blank to remove):`, existing?.symbol ?? '')
+  if (answer === null) return
+  try {
+    if (answer.trim()) setChord(tick, answer.trim())
+    else removeChord(tick)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// The measure under the playhead, 1-indexed
+function currentMeasure(): number {
+  const { mozart, currentTick } = useMozartStore.getState()
+  return mozart ? Math.floor(currentTick / mozart.ticksPerMeasure()) + 1 : 1
+}
+
+// Repeat marks are typed as specs (e.g. "5-8 x3"); the core explains one it can't use
+function editRepeats(message: string, defaultValue: string, apply: (spec: string) => void) {
+  const spec = ask(message, defaultValue)?.trim()
+  if (!spec) return
+  try {
+    apply(spec)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function addRepeat() {
+  const measure = currentMeasure()
+  editRepeats('Repeat measures, with how many times they play (e.g. 5-8 or 5-8 x3):', `${measure}-${measure + 3}`, (spec) =>
+    useMozartStore.getState().addRepeat(spec),
+  )
+}
+
+function addEnding() {
+  editRepeats('Ending measures and the passes that play them (e.g. 8 1, or 9-10 2):', `${currentMeasure()} 1`, (spec) =>
+    useMozartStore.getState().addEnding(spec),
+  )
+}
+
+function placeRepeatSign() {
+  editRepeats('Sign and measure (segno 3, fine 12, to coda 16, or coda 20):', `segno ${currentMeasure()}`, (spec) =>
+    useMozartStore.getState().placeRepeatSign(spec),
+  )
+}
+
+// A jump as typed, e.g. "D.S. al Coda 24" (mozart-core Jump's Display)
+function jumpSpec({ kind, until, at_measure }: Jump): string {
+  const name = kind === 'da_capo' ? 'D.C.' : 'D.S.'
+  const end = { end: '', fine: ' al Fine', coda: ' al Coda' }[until]
+  return `${name}${end} ${at_measure}`
+}
+
+// "-" removes the jump
+function setJump() {
+  const { repeats, setJump } = useMozartStore.getState()
+  const current = repeats.jump ? jumpSpec(repeats.jump) : `D.C. al Fine ${currentMeasure()}`
+  editRepeats('Jump and the measure it is at (e.g. D.C. al Fine 16, D.S. al Coda 24), or - to remove:', current, (spec) =>
+    setJump(spec === '-' ? '' : spec),
+  )
+}
+
+function removeRepeatsAt() {
+  const measure = parseInt(ask('Remove the repeat marks at measure:', String(currentMeasure())) ?? '', 10)
+  if (!isNaN(measure) && !useMozartStore.getState().removeRepeatsAt(measure)) {
+    window.alert(`No repeat marks at measure ${measure}`)
+  }
+}
+
+// What the audio context runs at, as the browser granted it
+function describeAudioOutput(): string {
+  const active = useMozartStore.getState().audioEngine?.activeConfig
+  if (!active) return 'Audio is off'
+  const ms = (seconds: number) => `${Math.round(seconds * 1000)} ms`
+  return `${active.sampleRate} Hz, ${ms(active.baseLatency)} buffer + ${ms(active.outputLatency)} output latency`
+}
+
+// "48000 interactive", "default 0.02": a sample rate (or default) and a latency category or seconds
+function setAudioOutput() {
+  const { audioConfig, setAudioConfig } = useMozartStore.getState()
+  const current = `${audioConfig.sampleRate ?? 'default'} ${audioConfig.latency}`
+  const answer = ask(
+    `Sample rate (default, ${SUPPORTED_SAMPLE_RATES.join(', ')}) and latency (${SUPPORTED_LATENCIES.join(', ')}, or seconds such as 0.02). Now: ${describeAudioOutput()}`,
+    current,
+  )?.trim()
+  if (!answer) return
+
+  const [rate, latency = String(audioConfig.latency)] = answer.split(/\s+/)
+  const sampleRate = rate === 'default' ? null : parseInt(rate, 10)
+  const category = SUPPORTED_LATENCIES.find((c) => c === latency)
+  const config: AudioConfig = { sampleRate, latency: category ?? parseFloat(latency) }
+  if ((sampleRate !== null && !SUPPORTED_SAMPLE_RATES.includes(sampleRate)) || Number.isNaN(config.latency)) {
+    window.alert(`Not an audio setting: ${answer}`)
+    return
+  }
+  setAudioConfig(config).then((granted) =>
+    window.alert(`${granted ? 'Audio output' : 'The browser rejected that setting; using the defaults'}: ${describeAudioOutput()}`),
+  )
+}
+
+// Patterns are named in prompts; the core explains a name it can't use
+function editPatterns(message: string, defaultValue: string, apply: (words: string[]) => void) {
+  const words = ask(message, defaultValue)?.trim().split(/\s+/).filter(Boolean)
+  if (!words?.length) return
+  try {
+    apply(words)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// The first letter not yet taken by a pattern
+function nextPatternName(): string {
+  const taken = new Set(useMozartStore.getState().patterns.map((pattern) => pattern.name))
+  return [...'ABCDEFGHIJKLMNOPQRSTUVWXYZ'].find((name) => !taken.has(name)) ?? ''
+}
+
+// "A 5-8" cuts measures 5 to 8 into pattern A
+function createPattern() {
+  const measure = currentMeasure()
+  editPatterns('Pattern name and the measures cut into it (e.g. A 1-4):', `${nextPatternName()} ${measure}-${measure + 3}`, ([name, measures = '']) => {
+    const [first, last = first] = measures.split('-').map((m) => parseInt(m, 10))
+    if (isNaN(first) || isNaN(last) || last < first) throw new Error(`Not a measure range: ${measures}`)
+    useMozartStore.getState().createPattern(name, first, last - first + 1)
+  })
+}
+
+function clonePattern() {
+  const last = useMozartStore.getState().patterns.at(-1)?.name ?? ''
+  editPatterns('Pattern to clone and the new name (e.g. A A2):', `${last} ${nextPatternName()}`, ([name, newName = '']) =>
+    useMozartStore.getState().clonePattern(name, newName),
+  )
+}
+
+// Positions in the list start at 1
+function movePattern() {
+  const { patterns } = useMozartStore.getState()
+  const list = patterns.map((pattern, i) => `${i + 1}. ${pattern.name}`).join(', ')
+  editPatterns(`Move pattern (${list}) to position:`, `${patterns.at(-1)?.name ?? ''} 1`, ([name, to = '']) => {
+    const from = patterns.findIndex((pattern) => pattern.name === name)
+    if (from < 0) throw new Error(`Unknown pattern: ${name}`)
+    useMozartStore.getState().movePattern(from, parseInt(to, 10) - 1)
+  })
+}
+
+function removePattern() {
+  const { patterns } = useMozartStore.getState()
+  editPatterns(`Delete pattern (${patterns.map((pattern) => pattern.name).join(', ')}):`, '', ([name]) => {
+    if (!useMozartStore.getState().removePattern(name)) window.alert(`No pattern named ${name}`)
+  })
+}
+
+// "-" clears the chain
+function setPatternChain() {
+  const { patterns, patternChain } = useMozartStore.getState()
+  const current = patternChain.join(' ') || patterns.map((pattern) => pattern.name).join(' ')
+  editPatterns('Patterns in the order they play from the start (e.g. A A B A), or - to clear:', current, (names) =>
+    useMozartStore.getState().setPatternChain(names[0] === '-' ? '' : names.join(' ')),
+  )
+}
+
+// A prompt has one line, so lyric lines are separated by " / " while editing
+function editLyrics() {
+  const { getLyricsText, setLyrics } = useMozartStore.getState()
+  const text = ask(
+    'Lyrics, one syllable per note (Hap-py birth-day; _ holds; / starts a line):',
+    getLyricsText().replaceAll('\n', ' / '),
+  )
+  if (text === null) return
+
+  const placed = setLyrics(text)
+  const syllables = text.split(/[\s/-]+/).filter((s) => s && s !== '_').length
+  if (placed < syllables) window.alert(`Only ${placed} of ${syllables} syllables fit the melody`)
+}
+
+// Beat subdivisions for a groove: 4 = sixteenths in 4/4
+function askSubdivisions(): number | null {
+  const subdivisions = parseInt(ask('Subdivisions per beat:', '4') ?? '', 10)
+  return subdivisions > 0 ? subdivisions : null
+}
+
+function extractGroove() {
+  const subdivisions = askSubdivisions()
+  if (subdivisions === null) return
+
+  try {
+    useMozartStore.getState().extractGroove(subdivisions)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function grooveFromMidi() {
+  const input = document.createElement('input')
+  input.type = 'file'
+  input.accept = '.mid,.midi,audio/midi'
+  input.onchange = async () => {
+    const file = input.files?.[0]
+    const subdivisions = file ? askSubdivisions() : null
+    if (!file || subdivisions === null) return
+
+    try {
+      useMozartStore.getState().loadGrooveFromMidi(new Uint8Array(await file.arrayBuffer()), subdivisions)
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+  input.click()
+}
+
+function repairSong() {
+  const { removed, modified } = useMozartStore.getState().repairSong()
+  window.alert(removed || modified ? `Removed ${removed} notes, modified ${modified} notes` : 'No problems found')
+}
+
+// Show what a cleanup would remove, and remove it if confirmed
+function cleanupNotes() {
+  const { cleanupSong } = useMozartStore.getState()
+  const preview = cleanupSong(true)
+  if (!preview) return
+
+  const { duplicates, merged, zero_length } = preview
+  if (duplicates.length + merged.length + zero_length.length === 0) {
+    window.alert('No duplicate, overlapping, or zero-length notes')
+    return
+  }
+  const summary = [
+    `${duplicates.length} duplicate notes`,
+    `${merged.length} near-identical notes merged into their neighbors`,
+    `${zero_length.length} zero-length notes`,
+  ]
+  if (window.confirm(`Remove:\n${summary.join('\n')}`)) cleanupSong(false)
+}
+
+// Measures as "3" or "3-4", defaulting to the playhead's measure
+function deleteMeasures() {
+  const { mozart, currentTick, deleteMeasures } = useMozartStore.getState()
+  if (!mozart) return
+  const current = Math.floor(currentTick / mozart.ticksPerMeasure()) + 1
+  const range = ask('Delete measures (e.g., 3 or 3-4):', String(current))?.match(/^\s*(\d+)\s*(?:-\s*(\d+))?\s*$/)
+  if (!range) return
+
+  const first = Number(range[1])
+  const last = range[2] ? Number(range[2]) : first
+  try {
+    withConfirmation((confirm) => deleteMeasures(first, Math.max(0, last - first + 1), confirm))
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Velocities as "min-max", e.g. "40-110"
+function normalizeVelocities() {
+  const range = ask('Velocity range (min-max):', '40-110')?.match(/^\s*(\d+)\s*-\s*(\d+)\s*$/)
+  if (!range) return
+
+  try {
+    useMozartStore.getState().normalizeVelocities(Number(range[1]), Number(range[2]))
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function compressVelocities() {
+  const threshold = parseInt(ask('Compress velocities above:', '90') ?? '', 10)
+  const ratio = Number.isNaN(threshold) ? NaN : parseFloat(ask('Ratio (2 halves the excess):', '2') ?? '')
+  if (Number.isNaN(ratio)) return
+
+  try {
+    useMozartStore.getState().compressVelocities(ratio, threshold)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Write a melody into voice 2 that outlines a chord chart
+function generateMelody() {
+  const chart = ask('Chord chart to outline, one bar per measure:', '| C . . . | Am . . . | F . G . | C . . . |')
+  if (!chart) return
+  const syncopation = parseFloat(ask('Syncopation (0-1):', '0.2') ?? '')
+  if (Number.isNaN(syncopation)) return
+
+  try {
+    useMozartStore.getState().generateMelody({ chart, syncopation })
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Meter, accents, tempo, and a drum loop in one step
+function loadGroovePreset() {
+  const presets = getGroovePresets()
+  const list = presets.map((p) => `${p.name} (${p.time_signature}, ${p.grouping.join('+')}) — ${p.description}`)
+  const name = ask(`Groove preset:\n${list.join('\n')}`, presets[0]?.name)
+  if (!name) return
+
+  try {
+    useMozartStore.getState().loadGroovePreset(name)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function zoom(factor: number) {
+  const { pixelsPerTick, setPixelsPerTick } = useMozartStore.getState()
+  setPixelsPerTick(zoomAbout(factor, 0, 0, 0, pixelsPerTick).pixelsPerTick)
+}
+
+// Write a first-species line into voice 2 and say which rules it had to bend
+function writeCounterpoint(above: boolean) {
+  try {
+    const report = useMozartStore.getState().generateCounterpoint({ above })
+    if (report.relaxations.length > 0) {
+      const lines = report.relaxations.map((r) => r.message)
+      window.alert(`Counterpoint written with ${lines.length} rule(s) relaxed:\n${lines.join('\n')}`)
+    }
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// The target instrument whose range the piano roll shades; "-" checks no range
+function setInstrumentRange() {
+  const { pitchRange, setPitchRange } = useMozartStore.getState()
+  const names = getPitchRangePresets().map((r) => r.name).join(', ')
+  const text = ask(`Instrument or voice to write for (${names}), or - for none:`, pitchRange?.name ?? '')?.trim()
+  if (!text) return
+  if (text === '-') return setPitchRange(null)
+
+  try {
+    setPitchRange(getInstrumentRange(text).name)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Into the song's range, an instrument preset, or two notes such as G3 C6
+function foldIntoRange() {
+  const { pitchRange, foldIntoRange } = useMozartStore.getState()
+  const current = pitchRange ? pitchRange.name || `${midiToNoteName(pitchRange.low)} ${midiToNoteName(pitchRange.high)}` : 'C4 C6'
+  const text = ask('Fold notes into (an instrument, or lowest and highest note):', current)?.trim()
+  if (!text) return
+
+  try {
+    const preset = getPitchRangePresets().find((r) => r.name.toLowerCase() === text.toLowerCase())
+    const [low, high] = preset ? [preset.low, preset.high] : text.split(/[\s,-]+/).map(noteNameToMidi)
+    const folded = foldIntoRange(low, high)
+    window.alert(`Folded ${folded.length} ${folded.length === 1 ? 'note' : 'notes'}`)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// "auto" fits the rows to the notes; a range such as C1-C7 stays put
+function setPitchRange() {
+  const { pitchView, setPitchView } = useMozartStore.getState()
+  const current = pitchView.mode === 'auto' ? `auto ${pitchView.margin}` : `${pitchView.low}-${pitchView.high}`
+  const text = ask('Piano roll rows ("auto", "auto 7" for a wider margin, or e.g. C1-C7):', current)
+  if (!text) return
+  try {
+    setPitchView(parsePitchView(text))
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+function showShortcuts() {
+  const lines = searchCommands('')
+    .filter((command) => command.shortcut)
+    .map((command) => `${formatShortcut(command.shortcut!)} — ${command.name}`)
+  const macros = useMozartStore
+    .getState()
+    .macros.filter((m) => m.shortcut)
+    .map((m) => `${formatShortcut(m.shortcut!)} — ${m.name} (macro)`)
+  window.alert([...lines, ...macros].join('\n'))
+}
+
+// The EBNF to read, and the full JSON description for editor integrations
+function showNotationGrammar() {
+  const grammar = getNotationGrammar()
+  if (window.confirm(`${grammar.ebnf}\n\nSave the grammar as JSON for an editor?`)) {
+    saveFile(new Blob([JSON.stringify(grammar, null, 2)], { type: 'application/json' }), 'mozart-notation.json')
+  }
+}
+
+// Asks for the name; the shortcut is optional
+function stopMacroRecording() {
+  const { macroSteps, stopMacroRecording, cancelMacroRecording } = useMozartStore.getState()
+  const name = window.prompt(`Name for these ${macroSteps?.length ?? 0} commands:`)
+  if (name === null) {
+    if (window.confirm('Discard the recording?')) cancelMacroRecording()
+    return
+  }
+  const shortcut = window.prompt('Shortcut (e.g., Alt+1), or leave empty:', '') ?? ''
+  try {
+    stopMacroRecording(name, shortcut)
+  } catch (err) {
+    window.alert(String(err))
+  }
+}
+
+// Pick a saved macro by name (or number in the list)
+function pickMacro(verb: string): Macro | undefined {
+  const { macros } = useMozartStore.getState()
+  const list = macros.map((m, i) => `${i + 1}. ${m.name}`).join('\n')
+  const answer = window.prompt(`${verb} which macro?\n${list}`, macros[0]?.name)?.trim()
+  if (!answer) return
+  return macros.find((m) => m.name === answer) ?? macros[parseInt(answer, 10) - 1]
+}
+
+// Run a macro's commands in order, answering their prompts as recorded
+export function playMacro(macro: Macro) {
+  if (useMozartStore.getState().startupDiagnostics?.safe_mode) {
+    window.alert('Macros are disabled in safe mode')
+    return
+  }
+  for (const step of macro.steps) {
+    if (!HANDLERS[step.command]) {
+      window.alert(`${macro.name}: no command ${step.command}`)
+      return
+    }
+    runCommand(step.command, step.args ?? [])
+  }
+}
+
+const HANDLERS: Record<string, () => void> = {
+  'file.new': () => withConfirmation((confirm) => useMozartStore.getState().newSong(undefined, confirm)),
+  'file.newSongDefaults': () => window.dispatchEvent(new Event(OPEN_SONG_DEFAULTS_EVENT)),
+  'file.open': () => confirmDiscard() && pickFile(),
+  'file.save': () => void save(),
+  'file.exportMidi': exportMidi,
+  'file.mergeSongs': pickMergeFiles,
+  'file.splitSong': () => void splitSong(),
+  'file.compareSong': pickCompareFile,
+  'file.playComparison': pickComparisonFile,
+  'file.importChordChart': importChordChart,
+  'file.exportPracticeAudio': exportPracticeAudio,
+  'file.exportLrc': exportLyrics,
+  'file.exportChordPro': exportChordPro,
+  'file.exportSettings': () => void exportSettings(),
+  'file.importSettings': importSettings,
+  'edit.undo': () => useMozartStore.getState().undo(),
+  'edit.redo': () => useMozartStore.getState().redo(),
+  'edit.deleteNote': () => {
+    const { selectedNoteIndex, removeNote } = useMozartStore.getState()
+    if (selectedNoteIndex !== null) removeNote(selectedNoteIndex)
+  },
+  'edit.clearNotes': () => withConfirmation((confirm) => useMozartStore.getState().clearNotes(confirm)),
+  'edit.deleteMeasures': deleteMeasures,
+  'edit.legato': () => useMozartStore.getState().makeLegato(),
+  'edit.lyrics': editLyrics,
+  'edit.addAnnotation': addAnnotation,
+  'edit.removeAnnotation': removeAnnotation,
+  'edit.chordSymbol': editChordSymbol,
+  'edit.invertUp': () => useMozartStore.getState().voiceChords('invert_up'),
+  'edit.invertDown': () => useMozartStore.getState().voiceChords('invert_down'),
+  'edit.openVoicing': () => useMozartStore.getState().voiceChords('open'),
+  'edit.closeVoicing': () => useMozartStore.getState().voiceChords('close'),
+  'edit.chordSuggestions': () => window.dispatchEvent(new Event(OPEN_HARMONY_EVENT)),
+  'edit.modulation': () => window.dispatchEvent(new Event(OPEN_MODULATION_EVENT)),
+  'edit.counterpointAbove': () => writeCounterpoint(true),
+  'edit.counterpointBelow': () => writeCounterpoint(false),
+  'edit.generateMelody': generateMelody,
+  'edit.variations': () => window.dispatchEvent(new Event(OPEN_VARIATIONS_EVENT)),
+  'edit.continueMelody': () => window.dispatchEvent(new Event(OPEN_CONTINUATION_EVENT)),
+  'edit.extractGroove': extractGroove,
+  'edit.grooveFromMidi': grooveFromMidi,
+  'edit.applyGroove': () => useMozartStore.getState().applyGroove(),
+  'edit.noteChance': editNoteChance,
+  'edit.freezeArrangement': () => useMozartStore.getState().freezeArrangement(),
+  'edit.flattenEffects': () => useMozartStore.getState().flattenPlaybackEffects(),
+  'edit.noteColor': editNoteColor,
+  'edit.sectionColor': editSectionColor,
+  'edit.voiceMeter': editVoiceMeter,
+  'edit.addTrack': addTrack,
+  'repeat.add': addRepeat,
+  'repeat.ending': addEnding,
+  'repeat.sign': placeRepeatSign,
+  'repeat.jump': setJump,
+  'repeat.remove': removeRepeatsAt,
+  'repeat.clear': () => useMozartStore.getState().clearRepeats(),
+  'pattern.create': createPattern,
+  'pattern.clone': clonePattern,
+  'pattern.move': movePattern,
+  'pattern.remove': removePattern,
+  'pattern.chain': setPatternChain,
+  'cleanup.repair': repairSong,
+  'cleanup.removeDuplicates': cleanupNotes,
+  'cleanup.normalizeVelocities': normalizeVelocities,
+  'cleanup.compressVelocities': compressVelocities,
+  'tool.draw': () => useMozartStore.getState().setEditTool('draw'),
+  'tool.split': () => useMozartStore.getState().setEditTool('split'),
+  'tool.join': () => useMozartStore.getState().setEditTool('join'),
+  'tool.chordEntry': () => {
+    const { chordEntry, setChordEntry } = useMozartStore.getState()
+    setChordEntry(!chordEntry)
+  },
+  'transpose.up': () => useMozartStore.getState().transposeChromatic(1),
+  'transpose.down': () => useMozartStore.getState().transposeChromatic(-1),
+  'transpose.octaveUp': () => useMozartStore.getState().transposeChromatic(12),
+  'transpose.octaveDown': () => useMozartStore.getState().transposeChromatic(-12),
+  'transpose.stepUp': () => useMozartStore.getState().transposeDiatonic(1),
+  'transpose.stepDown': () => useMozartStore.getState().transposeDiatonic(-1),
+  'transpose.instrumentRange': setInstrumentRange,
+  'transpose.foldIntoRange': foldIntoRange,
+  'view.zoomIn': () => zoom(ZOOM_STEP),
+  'view.zoomOut': () => zoom(1 / ZOOM_STEP),
+  'view.pitchRange': setPitchRange,
+  'view.octaveUp': () => useMozartStore.getState().shiftPitchView(1),
+  'view.octaveDown': () => useMozartStore.getState().shiftPitchView(-1),
+  'view.followPage': () => useMozartStore.getState().setFollowMode('page-flip'),
+  'view.followContinuous': () => useMozartStore.getState().setFollowMode('continuous'),
+  'view.followOff': () => useMozartStore.getState().setFollowMode('off'),
+  'view.registerHeatmap': () => window.dispatchEvent(new Event(TOGGLE_REGISTER_EVENT)),
+  'view.tracks': () => window.dispatchEvent(new Event(TOGGLE_TRACKS_EVENT)),
+  'playback.playPause': () => {
+    const { playbackState, play, pause } = useMozartStore.getState()
+    if (playbackState === 'playing') pause()
+    else play()
+  },
+  'playback.stop': () => useMozartStore.getState().stop(),
+  'playback.metronome': () => useMozartStore.getState().toggleMetronome(),
+  'playback.start': () => useMozartStore.getState().seekTo(0),
+  'playback.measure': () => {
+    const measure = parseInt(ask('Go to measure:') ?? '', 10)
+    if (!isNaN(measure)) useMozartStore.getState().seekToMeasure(measure)
+  },
+  'playback.groovePreset': loadGroovePreset,
+  'playback.audioOutput': setAudioOutput,
+  'playback.rate': () => {
+    const rate = ask('Practice speed (0.25-2, e.g. 0.5 or 75%):', String(useMozartStore.getState().playbackRate))
+    if (rate) useMozartStore.getState().setPlaybackRate(rate)
+  },
+  'playback.prevMarker': () => useMozartStore.getState().prevMarker(),
+  'playback.nextMarker': () => useMozartStore.getState().nextMarker(),
+  'playback.loopMeasure': () => useMozartStore.getState().loopCurrentMeasure(),
+  'playback.loopNext': () => useMozartStore.getState().loopNextMeasure(),
+  'playback.loopPrevious': () => useMozartStore.getState().loopPreviousMeasure(),
+  'playback.extendLoop': () => useMozartStore.getState().extendLoopByMeasure(),
+  'playback.clearLoop': () => useMozartStore.getState().clearLoop(),
+  'playback.callResponse': () => window.dispatchEvent(new Event(OPEN_PRACTICE_EVENT)),
+  'help.palette': () => window.dispatchEvent(new Event(OPEN_PALETTE_EVENT)),
+  'help.tutorial': () => useMozartStore.getState().startTutorial(),
+  'help.theory': () => window.dispatchEvent(new Event(OPEN_THEORY_EVENT)),
+  'help.shortcuts': showShortcuts,
+  'help.notationGrammar': showNotationGrammar,
+  'help.about': () => window.alert('Mozart\nMelody editor with transposition, accents, and MIDI export'),
+  'macro.record': () => useMozartStore.getState().startMacroRecording(),
+  'macro.stop': stopMacroRecording,
+  'macro.play': () => {
+    const macro = pickMacro('Play')
+    if (macro) playMacro(macro)
+  },
+  'macro.delete': () => {
+    const macro = pickMacro('Delete')
+    if (macro) useMozartStore.getState().deleteMacro(macro.name)
+  },
+}
+
+// Run a registry command by id; `args` answer its prompts (from a macro)
+export function runCommand(id: string, args?: string[]): void {
+  const handler = HANDLERS[id]
+  if (!handler) {
+    console.warn(`No handler for command ${id}`)
+    return
+  }
+
+  replayAnswers = args ? [...args] : null
+  givenAnswers = []
+  try {
+    handler()
+  } finally {
+    replayAnswers = null
+  }
+  useMozartStore.getState().recordMacroStep(id, givenAnswers)
+}
+
+// Whether a command can run in the current state
+export function isCommandEnabled(id: string): boolean {
+  const {
+    mozart,
+    editState,
+    selectedNoteIndex,
+    playbackState,
+    notes,
+    annotations,
+    markers,
+    repeats,
+    patterns,
+    loopRegion,
+    grooveTemplate,
+    macros,
+    macroSteps,
+    startupDiagnostics,
+  } = useMozartStore.getState()
+  switch (id) {
+    case 'file.save':
+      return editState.isDirty
+    case 'edit.undo':
+      return editState.canUndo
+    case 'edit.redo':
+      return editState.canRedo
+    case 'edit.deleteNote':
+    case 'edit.noteChance':
+    case 'edit.voiceMeter':
+      return selectedNoteIndex !== null
+    case 'edit.freezeArrangement':
+      return notes.some((note) => note.probability < 100 || note.take !== undefined)
+    case 'edit.flattenEffects':
+      return mozart?.hasPlaybackEffects() ?? false
+    case 'edit.removeAnnotation':
+      return annotations.length > 0
+    case 'playback.clearLoop':
+      return loopRegion !== null
+    case 'edit.noteColor':
+      return notes.length > 0
+    case 'edit.sectionColor':
+      return markers.length > 0
+    case 'repeat.remove':
+    case 'repeat.clear':
+      return Object.keys(repeats).length > 0
+    case 'pattern.clone':
+    case 'pattern.remove':
+    case 'pattern.chain':
+      return patterns.length > 0
+    case 'pattern.move':
+      return patterns.length > 1
+    case 'edit.clearNotes':
+    case 'edit.deleteMeasures':
+    case 'edit.legato':
+    case 'edit.lyrics':
+    case 'edit.invertUp':
+    case 'edit.invertDown':
+    case 'edit.openVoicing':
+    case 'edit.closeVoicing':
+    case 'edit.counterpointAbove':
+    case 'edit.counterpointBelow':
+    case 'edit.variations':
+    case 'edit.continueMelody':
+    case 'edit.extractGroove':
+    case 'cleanup.repair':
+    case 'cleanup.removeDuplicates':
+    case 'cleanup.normalizeVelocities':
+    case 'cleanup.compressVelocities':
+      return notes.length > 0
+    case 'edit.applyGroove':
+      return notes.length > 0 && grooveTemplate !== null
+    case 'playback.stop':
+      return playbackState !== 'stopped'
+    case 'macro.record':
+      return macroSteps === null
+    case 'macro.stop':
+      return macroSteps !== null
+    case 'macro.play':
+      return macros.length > 0 && !startupDiagnostics?.safe_mode
+    case 'macro.delete':
+      return macros.length > 0
+    default:
+      return true
+  }
+}
+
+// Registry entries by id
+export function commandsById(): Map<string, CommandInfo> {
+  return new Map(searchCommands('').map((command) => [command.id, command]))
+}
@@ -1,8 +1,33 @@
-import React from 'react'
+import React, { useMemo, useState } from 'react'
 import { useMozartStore } from '../store'
 
 export function AccentEditor() {
-  const { accents, cycleAccent } = useMozartStore()
+  const {
+    accents,
+    timeSignature,
+    isWasmLoaded,
+    cycleAccent,
+    setAccents,
+    previewAccentPattern,
+    accentDynamics,
+    setAccentDynamics,
+    groupings,
+    pulseGrouping,
+    setPulseGrouping,
+    listAccentPresets,
+    saveAccentPreset,
+  } = useMozartStore()
+  const [presetVersion, setPresetVersion] = useState(0)
+
+  const presets = useMemo(() => listAccentPresets(), [timeSignature.numerator, isWasmLoaded, presetVersion])
+  const currentPreset = presets.find((p) => p.accents.join() === accents.join())
+
+  const handleSavePreset = () => {
+    const name = window.prompt('Preset name:')
+    if (!name) return
+    saveAccentPreset(name)
+    setPresetVersion((v) => v + 1)
+  }
 
   const getAccentLabel = (level: number): string => {
     switch (level) {
@@ -46,6 +71,46 @@ export function AccentEditor() {
           </button>
         ))}
       </div>
+      <select
+        style={styles.preview}
+        value={currentPreset?.name ?? ''}
+        onChange={(e) => {
+          const preset = presets.find((p) => p.name === e.target.value)
+          if (preset) setAccents(preset.accents)
+        }}
+      >
+        <option value="" disabled>
+          Presets
+        </option>
+        {presets.map((p) => (
+          <option key={p.name} value={p.name} title={p.description}>
+            {p.builtin ? p.name : `★ ${p.name}`}
+          </option>
+        ))}
+      </select>
+      <button style={styles.preview} onClick={handleSavePreset} title="Save the current accents as a preset">
+        Save…
+      </button>
+      <button style={styles.preview} onClick={previewAccentPattern} title="Play one measure of clicks">
+        ▶ Preview
+      </button>
+      <label style={styles.toggle} title="Click and snap on the first beat of each group (e.g., 6/8 in two)">
+        <input
+          type="checkbox"
+          checked={pulseGrouping.some((size) => size > 1)}
+          disabled={groupings.length === accents.length}
+          onChange={(e) => setPulseGrouping(e.target.checked ? groupings : [])}
+        />
+        Pulse by group
+      </label>
+      <label style={styles.toggle} title="Scale note velocities by the accent of the beat they start on">
+        <input
+          type="checkbox"
+          checked={accentDynamics}
+          onChange={(e) => setAccentDynamics(e.target.checked)}
+        />
+        Shape dynamics
+      </label>
       <span style={styles.hint}>Click to cycle accent level</span>
     </div>
   )
@@ -89,6 +154,23 @@ const styles: Record<string, React.CSSProperties> = {
     fontSize: '20px',
     fontWeight: 'bold',
   },
+  preview: {
+    padding: '6px 12px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '4px',
+    color: '#ccc',
+    fontSize: '12px',
+    cursor: 'pointer',
+  },
+  toggle: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '4px',
+    color: '#ccc',
+    fontSize: '12px',
+    cursor: 'pointer',
+  },
   hint: {
     marginLeft: 'auto',
     color: '#555',
@@ -0,0 +1,202 @@
+import React, { useEffect, useMemo, useRef, useState } from 'react'
+import { IS_MAC, OPEN_PALETTE_EVENT, formatShortcut, isCommandEnabled, runCommand } from '../commands'
+import { searchCommands } from '../wasm'
+
+// Name with the fuzzy-matched characters emphasized
+function Highlighted({ name, matched }: { name: string; matched: number[] }) {
+  const positions = new Set(matched)
+  return (
+    <>
+      {[...name].map((char, i) =>
+        positions.has(i) ? (
+          <strong key={i} style={styles.match}>
+            {char}
+          </strong>
+        ) : (
+          char
+        )
+      )}
+    </>
+  )
+}
+
+// Ctrl+K (⌘K) command palette over the command registry
+export function CommandPalette() {
+  const [open, setOpen] = useState(false)
+  const [query, setQuery] = useState('')
+  const [selected, setSelected] = useState(0)
+  const inputRef = useRef<HTMLInputElement>(null)
+
+  const results = useMemo(() => (open ? searchCommands(query) : []), [open, query])
+
+  useEffect(() => {
+    const show = () => {
+      setQuery('')
+      setSelected(0)
+      setOpen(true)
+    }
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key.toLowerCase() === 'k' && (IS_MAC ? e.metaKey : e.ctrlKey)) {
+        e.preventDefault()
+        show()
+      }
+    }
+    window.addEventListener('keydown', handleKeyDown)
+    window.addEventListener(OPEN_PALETTE_EVENT, show)
+    return () => {
+      window.removeEventListener('keydown', handleKeyDown)
+      window.removeEventListener(OPEN_PALETTE_EVENT, show)
+    }
+  }, [])
+
+  useEffect(() => {
+    if (open) inputRef.current?.focus()
+  }, [open])
+
+  if (!open) return null
+
+  const run = (id: string) => {
+    if (!isCommandEnabled(id)) return
+    setOpen(false)
+    runCommand(id)
+  }
+
+  const handleKeyDown = (e: React.KeyboardEvent) => {
+    if (e.key === 'Escape') {
+      setOpen(false)
+    } else if (e.key === 'ArrowDown') {
+      e.preventDefault()
+      setSelected((i) => Math.min(i + 1, results.length - 1))
+    } else if (e.key === 'ArrowUp') {
+      e.preventDefault()
+      setSelected((i) => Math.max(i - 1, 0))
+    } else if (e.key === 'Enter' && results[selected]) {
+      e.preventDefault()
+      run(results[selected].id)
+    }
+  }
+
+  return (
+    <div style={styles.backdrop} onPointerDown={() => setOpen(false)}>
+      <div style={styles.palette} onPointerDown={(e) => e.stopPropagation()} role="dialog" aria-label="Command palette">
+        <input
+          ref={inputRef}
+          value={query}
+          onChange={(e) => {
+            setQuery(e.target.value)
+            setSelected(0)
+          }}
+          onKeyDown={handleKeyDown}
+          placeholder="Type a command…"
+          style={styles.input}
+        />
+        <div style={styles.results} role="listbox">
+          {results.length === 0 && <div style={styles.empty}>No matching commands</div>}
+          {results.map((result, i) => {
+            const enabled = isCommandEnabled(result.id)
+            return (
+              <div
+                key={result.id}
+                role="option"
+                aria-selected={i === selected}
+                aria-disabled={!enabled}
+                style={{
+                  ...styles.result,
+                  ...(i === selected ? styles.resultSelected : {}),
+                  ...(enabled ? {} : styles.resultDisabled),
+                }}
+                onPointerEnter={() => setSelected(i)}
+                onClick={() => run(result.id)}
+              >
+                <span style={styles.category}>{result.category}</span>
+                <span style={styles.name}>
+                  <Highlighted name={result.name} matched={result.matched} />
+                  <span style={styles.description}>{result.description}</span>
+                </span>
+                {result.shortcut && <span style={styles.shortcut}>{formatShortcut(result.shortcut)}</span>}
+              </div>
+            )
+          })}
+        </div>
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  backdrop: {
+    position: 'fixed',
+    inset: 0,
+    display: 'flex',
+    justifyContent: 'center',
+    alignItems: 'flex-start',
+    paddingTop: '12vh',
+    background: 'rgba(0, 0, 0, 0.4)',
+    zIndex: 200,
+  },
+  palette: {
+    width: 'min(560px, 90vw)',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    overflow: 'hidden',
+  },
+  input: {
+    width: '100%',
+    padding: '12px 16px',
+    background: '#1a1a2e',
+    border: 'none',
+    borderBottom: '1px solid #0f3460',
+    color: '#eee',
+    fontSize: '16px',
+    outline: 'none',
+  },
+  results: {
+    maxHeight: '50vh',
+    overflowY: 'auto',
+    padding: '4px 0',
+  },
+  empty: {
+    padding: '12px 16px',
+    color: '#888',
+    fontSize: '13px',
+  },
+  result: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '12px',
+    padding: '8px 16px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '14px',
+  },
+  resultSelected: {
+    background: '#0f3460',
+  },
+  resultDisabled: {
+    color: '#666',
+    cursor: 'default',
+  },
+  category: {
+    width: '72px',
+    color: '#888',
+    fontSize: '12px',
+  },
+  name: {
+    flex: 1,
+    display: 'flex',
+    flexDirection: 'column',
+  },
+  match: {
+    color: '#e94560',
+  },
+  description: {
+    color: '#888',
+    fontSize: '12px',
+  },
+  shortcut: {
+    color: '#888',
+    fontSize: '12px',
+  },
+}
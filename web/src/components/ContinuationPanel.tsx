@@ -0,0 +1,162 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_CONTINUATION_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { midiToNoteName } from '../wasm'
+import type { Continuation } from '../wasm/types'
+
+const MEASURE_CHOICES = [1, 2, 3, 4]
+
+// Edit → Continue Melody: preview a few more measures of the selected voice, hear them, keep or discard
+export function ContinuationPanel() {
+  const { mozart, notes, selectedNoteIndex, continueMelody, auditionContinuation, acceptContinuation, rejectContinuation } =
+    useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [measures, setMeasures] = useState(2)
+  const [seed, setSeed] = useState(1)
+  const [continuation, setContinuation] = useState<Continuation | null>(null)
+  const [error, setError] = useState<string | null>(null)
+  const voice = selectedNoteIndex !== null ? (notes[selectedNoteIndex]?.voice ?? 0) : 0
+
+  useEffect(() => {
+    const show = () => setOpen(true)
+    window.addEventListener(OPEN_CONTINUATION_EVENT, show)
+    return () => window.removeEventListener(OPEN_CONTINUATION_EVENT, show)
+  }, [])
+
+  useEffect(() => {
+    if (!open) return
+    try {
+      setContinuation(continueMelody({ measures, voice, seed }))
+      setError(null)
+    } catch (err) {
+      setContinuation(null)
+      setError(String(err))
+    }
+  }, [open, measures, voice, seed, continueMelody])
+
+  if (!open || !mozart) return null
+
+  const discard = () => {
+    rejectContinuation()
+    setOpen(false)
+  }
+
+  const keep = () => {
+    try {
+      acceptContinuation()
+      setOpen(false)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Continue Melody">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Continue Melody</h3>
+        <button style={styles.close} onClick={discard} title="Discard">
+          ×
+        </button>
+      </div>
+
+      <div style={styles.row}>
+        <span style={styles.count}>Measures</span>
+        {MEASURE_CHOICES.map((n) => (
+          <button key={n} style={n === measures ? styles.primary : styles.button} onClick={() => setMeasures(n)}>
+            {n}
+          </button>
+        ))}
+      </div>
+
+      {error && <p style={styles.error}>{error}</p>}
+      {continuation && (
+        <>
+          <div style={styles.notes}>{continuation.notes.map((n) => midiToNoteName(n.pitch)).join(' ')}</div>
+          <div style={styles.row}>
+            <button style={styles.button} onClick={auditionContinuation} title="Play the continuation">
+              ▶ Play
+            </button>
+            <button style={styles.button} onClick={() => setSeed((s) => s + 1)} title="Try another continuation">
+              Another
+            </button>
+            <button style={styles.primary} onClick={keep}>
+              Keep
+            </button>
+          </div>
+        </>
+      )}
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '340px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  count: {
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  notes: {
+    marginTop: '4px',
+    color: '#aaa',
+    fontSize: '12px',
+    fontFamily: 'monospace',
+    overflowWrap: 'anywhere',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  button: {
+    padding: '4px 10px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
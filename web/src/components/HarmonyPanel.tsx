@@ -0,0 +1,284 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_HARMONY_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import type { ChordSuggestion, KeyChord, Reharmonization } from '../wasm/types'
+
+// Edit → Chord Suggestions: what could come next, and chords for a measure's melody
+export function HarmonyPanel() {
+  const { mozart, key, suggestNextChords, reharmonizeMeasure, insertChords, playChord } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [progression, setProgression] = useState('I')
+  const [suggestions, setSuggestions] = useState<ChordSuggestion[]>([])
+  const [measure, setMeasure] = useState(1)
+  const [chordsPerMeasure, setChordsPerMeasure] = useState(1)
+  const [passingTones, setPassingTones] = useState(true)
+  const [sevenths, setSevenths] = useState(false)
+  const [options, setOptions] = useState<Reharmonization[] | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  // Start from the playhead's measure each time the panel opens
+  useEffect(() => {
+    const show = () => {
+      const { mozart, currentTick } = useMozartStore.getState()
+      if (mozart) setMeasure(Math.floor(currentTick / mozart.ticksPerMeasure()) + 1)
+      setOpen(true)
+    }
+    window.addEventListener(OPEN_HARMONY_EVENT, show)
+    return () => window.removeEventListener(OPEN_HARMONY_EVENT, show)
+  }, [])
+
+  // Suggestions follow the typed progression and the song's key
+  useEffect(() => {
+    if (!open) return
+    try {
+      setSuggestions(suggestNextChords(progression.split(/\s+/).filter(Boolean)))
+      setError(null)
+    } catch (err) {
+      setSuggestions([])
+      setError(String(err))
+    }
+  }, [open, progression, key, suggestNextChords])
+
+  if (!open || !mozart) return null
+
+  // "F#m7" is played as "F#" and "m7"
+  const audition = (chord: KeyChord) => {
+    const [, root, quality] = chord.symbol.match(/^([A-G][#b]?)(.*)$/) ?? []
+    if (root) playChord(root, quality)
+  }
+
+  const reharmonize = () => {
+    const chords = progression.split(/\s+/).filter(Boolean)
+    try {
+      setOptions(
+        reharmonizeMeasure(measure, {
+          chords_per_measure: chordsPerMeasure,
+          previous: chords[chords.length - 1],
+          passing_tones: passingTones,
+          sevenths,
+        })
+      )
+      setError(null)
+    } catch (err) {
+      setOptions(null)
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Chord suggestions">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Chord Suggestions</h3>
+        <button style={styles.close} onClick={() => setOpen(false)} title="Close">
+          ×
+        </button>
+      </div>
+
+      <label style={styles.label}>
+        Progression
+        <input
+          value={progression}
+          onChange={(e) => setProgression(e.target.value)}
+          placeholder="e.g., I vi IV"
+          style={styles.text}
+        />
+      </label>
+      <div style={styles.chips}>
+        {suggestions.map((s) => (
+          <button
+            key={s.chord.numeral}
+            style={styles.chip}
+            onClick={() => {
+              setProgression(`${progression} ${s.chord.numeral}`.trim())
+              audition(s.chord)
+            }}
+            title={`${s.reason}; ${s.common_tones} common tone(s), voices move ${s.voice_movement} semitone(s)`}
+          >
+            <strong>{s.chord.numeral}</strong> {s.chord.symbol}
+            <span style={styles.score}>{Math.round(s.score)}</span>
+          </button>
+        ))}
+      </div>
+
+      <div style={styles.row}>
+        <label style={styles.label}>
+          Measure
+          <input
+            type="number"
+            min={1}
+            max={mozart.measureCount()}
+            value={measure}
+            onChange={(e) => setMeasure(Math.max(1, parseInt(e.target.value, 10) || 1))}
+            style={styles.input}
+          />
+        </label>
+        <select
+          style={styles.select}
+          value={chordsPerMeasure}
+          onChange={(e) => setChordsPerMeasure(Number(e.target.value))}
+        >
+          <option value={1}>1 chord</option>
+          <option value={2}>2 chords</option>
+          <option value={4}>4 chords</option>
+        </select>
+      </div>
+      <div style={styles.row}>
+        <label style={styles.label}>
+          <input type="checkbox" checked={passingTones} onChange={(e) => setPassingTones(e.target.checked)} />
+          Passing tones
+        </label>
+        <label style={styles.label}>
+          <input type="checkbox" checked={sevenths} onChange={(e) => setSevenths(e.target.checked)} />
+          Sevenths
+        </label>
+        <button style={styles.secondary} onClick={reharmonize}>
+          Reharmonize
+        </button>
+      </div>
+
+      {error && <p style={styles.error}>{error}</p>}
+      {options?.length === 0 && <p style={styles.status}>No chords of the key hold this melody</p>}
+      {options?.map((option, i) => (
+        <div key={i} style={styles.option}>
+          <span>{option.chords.map((span) => span.chord.symbol).join(' – ')}</span>
+          <span style={styles.score}>
+            {Math.round(option.score)} · fit {Math.round(option.melody_fit)}%
+          </span>
+          <button style={styles.primary} onClick={() => insertChords(option.chords)}>
+            Insert
+          </button>
+        </div>
+      ))}
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '360px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+    marginBottom: '12px',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '10px',
+    marginTop: '12px',
+  },
+  label: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '6px',
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  text: {
+    flex: 1,
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+  },
+  input: {
+    width: '48px',
+    padding: '4px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+  },
+  select: {
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  chips: {
+    display: 'flex',
+    flexWrap: 'wrap',
+    gap: '6px',
+    marginTop: '8px',
+  },
+  chip: {
+    display: 'flex',
+    gap: '4px',
+    alignItems: 'baseline',
+    padding: '4px 8px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+  score: {
+    color: '#888',
+    fontSize: '11px',
+  },
+  option: {
+    display: 'flex',
+    alignItems: 'center',
+    justifyContent: 'space-between',
+    gap: '8px',
+    marginTop: '8px',
+    fontSize: '13px',
+    color: '#eee',
+  },
+  status: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#aaa',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+  secondary: {
+    marginLeft: 'auto',
+    padding: '6px 14px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+}
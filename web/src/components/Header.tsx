@@ -1,81 +1,32 @@
-import React, { useRef } from 'react'
+import React, { useEffect } from 'react'
 import { useMozartStore } from '../store'
+import { MenuBar } from './MenuBar'
 
 export function Header() {
-  const { mozart, saveToJson, loadFromJson, exportToMidi, newSong } = useMozartStore()
-  const fileInputRef = useRef<HTMLInputElement>(null)
-
-  const handleSave = () => {
-    const json = saveToJson()
-    if (!json) return
-
-    const blob = new Blob([json], { type: 'application/json' })
-    const url = URL.createObjectURL(blob)
-    const a = document.createElement('a')
-    a.href = url
-    a.download = `${mozart?.title || 'song'}.mozart.json`
-    a.click()
-    URL.revokeObjectURL(url)
-  }
-
-  const handleLoad = () => {
-    fileInputRef.current?.click()
-  }
-
-  const handleFileChange = (e: React.ChangeEvent<HTMLInputElement>) => {
-    const file = e.target.files?.[0]
-    if (!file) return
-
-    const reader = new FileReader()
-    reader.onload = () => {
-      const json = reader.result as string
-      loadFromJson(json)
-    }
-    reader.readAsText(file)
-
-    // Reset input
-    e.target.value = ''
-  }
-
-  const handleExportMidi = () => {
-    const midi = exportToMidi()
-    if (!midi) return
-
-    const blob = new Blob([midi], { type: 'audio/midi' })
-    const url = URL.createObjectURL(blob)
-    const a = document.createElement('a')
-    a.href = url
-    a.download = `${mozart?.title || 'song'}.mid`
-    a.click()
-    URL.revokeObjectURL(url)
-  }
+  const { mozart, editState } = useMozartStore()
+  const title = mozart?.title || 'Untitled'
+
+  // Mirror the song and its unsaved state in the window title
+  useEffect(() => {
+    document.title = `${editState.isDirty ? '• ' : ''}${title} — Mozart`
+  }, [title, editState.isDirty])
+
+  // Browsers only show their own wording, but still ask before discarding edits
+  useEffect(() => {
+    if (!editState.isDirty) return
+    const handleBeforeUnload = (e: BeforeUnloadEvent) => e.preventDefault()
+    window.addEventListener('beforeunload', handleBeforeUnload)
+    return () => window.removeEventListener('beforeunload', handleBeforeUnload)
+  }, [editState.isDirty])
 
   return (
     <header style={styles.header}>
       <h1 style={styles.title}>Mozart</h1>
-
-      <div style={styles.actions}>
-        <button style={styles.button} onClick={() => newSong()}>
-          New
-        </button>
-        <button style={styles.button} onClick={handleLoad}>
-          Open
-        </button>
-        <button style={styles.button} onClick={handleSave}>
-          Save
-        </button>
-        <button style={styles.button} onClick={handleExportMidi}>
-          Export MIDI
-        </button>
-      </div>
-
-      <input
-        ref={fileInputRef}
-        type="file"
-        accept=".mozart.json,.json"
-        style={{ display: 'none' }}
-        onChange={handleFileChange}
-      />
+      <MenuBar />
+      <span style={styles.song} title={editState.isDirty ? 'Unsaved changes' : undefined}>
+        {title}
+        {editState.isDirty && <span style={styles.dirty}> •</span>}
+      </span>
     </header>
   )
 }
@@ -84,7 +35,7 @@ const styles: Record<string, React.CSSProperties> = {
   header: {
     display: 'flex',
     alignItems: 'center',
-    justifyContent: 'space-between',
+    gap: '20px',
     padding: '12px 20px',
     background: '#16213e',
     borderBottom: '1px solid #0f3460',
@@ -95,18 +46,12 @@ const styles: Record<string, React.CSSProperties> = {
     color: '#e94560',
     margin: 0,
   },
-  actions: {
-    display: 'flex',
-    gap: '8px',
-  },
-  button: {
-    padding: '8px 16px',
-    background: '#0f3460',
-    border: 'none',
-    borderRadius: '4px',
-    color: '#eee',
-    cursor: 'pointer',
+  song: {
+    marginLeft: 'auto',
+    color: '#aaa',
     fontSize: '14px',
-    transition: 'background 0.2s',
+  },
+  dirty: {
+    color: '#e94560',
   },
 }
@@ -0,0 +1,312 @@
+import React, { useEffect, useRef, useState } from 'react'
+import { useMozartStore } from '../store'
+import { IS_MAC, commandsById, formatShortcut, isCommandEnabled, playMacro, runCommand, shortcutFromEvent } from '../commands'
+
+// Menus by registry command id (mozart-core src/commands.rs)
+const MENUS: Record<string, (string | 'separator')[]> = {
+  File: [
+    'file.new',
+    'file.newSongDefaults',
+    'file.open',
+    'file.mergeSongs',
+    'file.splitSong',
+    'file.compareSong',
+    'file.playComparison',
+    'file.importChordChart',
+    'file.save',
+    'separator',
+    'file.exportMidi',
+    'file.exportPracticeAudio',
+    'file.exportLrc',
+    'file.exportChordPro',
+    'separator',
+    'file.exportSettings',
+    'file.importSettings',
+  ],
+  Edit: [
+    'edit.undo',
+    'edit.redo',
+    'separator',
+    'edit.deleteNote',
+    'edit.clearNotes',
+    'edit.deleteMeasures',
+    'edit.legato',
+    'edit.lyrics',
+    'edit.addAnnotation',
+    'edit.removeAnnotation',
+    'edit.chordSymbol',
+    'separator',
+    'edit.invertUp',
+    'edit.invertDown',
+    'edit.openVoicing',
+    'edit.closeVoicing',
+    'edit.chordSuggestions',
+    'edit.modulation',
+    'edit.counterpointAbove',
+    'edit.counterpointBelow',
+    'edit.generateMelody',
+    'edit.variations',
+    'edit.continueMelody',
+    'separator',
+    'edit.extractGroove',
+    'edit.grooveFromMidi',
+    'edit.applyGroove',
+    'separator',
+    'edit.noteChance',
+    'edit.freezeArrangement',
+    'edit.flattenEffects',
+    'separator',
+    'edit.noteColor',
+    'edit.sectionColor',
+    'edit.voiceMeter',
+    'edit.addTrack',
+    'separator',
+    'repeat.add',
+    'repeat.ending',
+    'repeat.sign',
+    'repeat.jump',
+    'repeat.remove',
+    'repeat.clear',
+    'separator',
+    'pattern.create',
+    'pattern.clone',
+    'pattern.move',
+    'pattern.remove',
+    'pattern.chain',
+    'separator',
+    'cleanup.repair',
+    'cleanup.removeDuplicates',
+    'cleanup.normalizeVelocities',
+    'cleanup.compressVelocities',
+    'separator',
+    'macro.record',
+    'macro.stop',
+    'macro.play',
+    'macro.delete',
+    'separator',
+    'tool.draw',
+    'tool.split',
+    'tool.join',
+    'tool.chordEntry',
+  ],
+  View: ['view.zoomIn', 'view.zoomOut', 'separator', 'view.pitchRange', 'view.octaveUp', 'view.octaveDown', 'separator', 'view.followPage', 'view.followContinuous', 'view.followOff', 'separator', 'view.registerHeatmap', 'view.tracks'],
+  Playback: [
+    'playback.playPause',
+    'playback.stop',
+    'separator',
+    'playback.metronome',
+    'playback.rate',
+    'playback.groovePreset',
+    'separator',
+    'playback.start',
+    'playback.measure',
+    'playback.prevMarker',
+    'playback.nextMarker',
+    'separator',
+    'playback.loopMeasure',
+    'playback.loopPrevious',
+    'playback.loopNext',
+    'playback.extendLoop',
+    'playback.clearLoop',
+    'separator',
+    'playback.audioOutput',
+    'playback.callResponse',
+  ],
+  Help: ['help.palette', 'help.tutorial', 'help.theory', 'help.notationGrammar', 'help.shortcuts', 'help.about'],
+}
+
+type MenuItem = { id: string; label: string; shortcut?: string; disabled: boolean; checked: boolean } | 'separator'
+
+// Application menu (File/Edit/View/Playback/Help) built from the command registry
+export function MenuBar() {
+  // Subscribing keeps labels and enabled states current
+  const { editState, playbackState, isMetronomeEnabled, editTool, chordEntry, followMode, macroSteps } = useMozartStore()
+  const [openMenu, setOpenMenu] = useState<string | null>(null)
+  const barRef = useRef<HTMLDivElement>(null)
+  const commands = useRef(commandsById()).current
+
+  const checked: Record<string, boolean> = {
+    'tool.draw': editTool === 'draw',
+    'tool.split': editTool === 'split',
+    'tool.join': editTool === 'join',
+    'tool.chordEntry': chordEntry,
+    'view.followPage': followMode === 'page-flip',
+    'view.followContinuous': followMode === 'continuous',
+    'view.followOff': followMode === 'off',
+    'playback.metronome': isMetronomeEnabled,
+  }
+  const labels: Record<string, string | null> = {
+    'edit.undo': editState.undoLabel && `Undo ${editState.undoLabel}`,
+    'edit.redo': editState.redoLabel && `Redo ${editState.redoLabel}`,
+    'playback.playPause': playbackState === 'playing' ? 'Pause' : 'Play',
+    'playback.metronome': 'Metronome',
+    'macro.stop': macroSteps && `Stop Recording (${macroSteps.length} commands)…`,
+  }
+  const menuItems = (ids: (string | 'separator')[]): MenuItem[] =>
+    ids.map((id) => {
+      if (id === 'separator') return id
+      const command = commands.get(id)
+      return {
+        id,
+        label: labels[id] ?? command?.name ?? id,
+        shortcut: command?.shortcut && formatShortcut(command.shortcut),
+        disabled: !isCommandEnabled(id),
+        checked: checked[id] ?? false,
+      }
+    })
+  const menus = Object.fromEntries(Object.entries(MENUS).map(([name, ids]) => [name, menuItems(ids)]))
+
+  // Close on outside click or Escape
+  useEffect(() => {
+    if (!openMenu) return
+    const handlePointer = (e: PointerEvent) => {
+      if (!barRef.current?.contains(e.target as Node)) setOpenMenu(null)
+    }
+    const handleKey = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') setOpenMenu(null)
+    }
+    window.addEventListener('pointerdown', handlePointer)
+    window.addEventListener('keydown', handleKey)
+    return () => {
+      window.removeEventListener('pointerdown', handlePointer)
+      window.removeEventListener('keydown', handleKey)
+    }
+  }, [openMenu])
+
+  // Menu accelerators and macro shortcuts; text fields keep their own undo
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      const target = e.target as HTMLElement
+      if (target.tagName === 'INPUT' || target.tagName === 'TEXTAREA') return
+
+      const shortcut = shortcutFromEvent(e)
+      const macro = useMozartStore.getState().macros.find((m) => m.shortcut === shortcut)
+      if (macro) {
+        e.preventDefault()
+        playMacro(macro)
+        return
+      }
+      if (!(IS_MAC ? e.metaKey : e.ctrlKey)) return
+
+      const key = e.key.toLowerCase()
+      const id =
+        (key === 'z' && e.shiftKey) || (key === 'y' && !IS_MAC)
+          ? 'edit.redo'
+          : ({ z: 'edit.undo', s: 'file.save', o: 'file.open' } as Record<string, string>)[key]
+      if (!id) return
+      e.preventDefault()
+      // Saving a clean song is harmless, so the shortcut always works
+      if (id === 'file.save' || isCommandEnabled(id)) runCommand(id)
+    }
+
+    window.addEventListener('keydown', handleKeyDown)
+    return () => window.removeEventListener('keydown', handleKeyDown)
+  }, [])
+
+  return (
+    <div ref={barRef} style={styles.bar}>
+      {Object.entries(menus).map(([name, items]) => (
+        <div key={name} style={styles.menu}>
+          <button
+            style={{ ...styles.menuButton, ...(openMenu === name ? styles.menuButtonOpen : {}) }}
+            onClick={() => setOpenMenu(openMenu === name ? null : name)}
+            onPointerEnter={() => openMenu && setOpenMenu(name)}
+          >
+            {name}
+          </button>
+          {openMenu === name && (
+            <div style={styles.dropdown} role="menu">
+              {items.map((item, i) =>
+                item === 'separator' ? (
+                  <div key={i} style={styles.separator} />
+                ) : (
+                  <button
+                    key={item.id}
+                    role="menuitem"
+                    style={{ ...styles.item, ...(item.disabled ? styles.itemDisabled : {}) }}
+                    disabled={item.disabled}
+                    onClick={() => {
+                      setOpenMenu(null)
+                      runCommand(item.id)
+                    }}
+                  >
+                    <span style={styles.check}>{item.checked ? '✓' : ''}</span>
+                    <span style={styles.itemLabel}>{item.label}</span>
+                    {item.shortcut && <span style={styles.shortcut}>{item.shortcut}</span>}
+                  </button>
+                )
+              )}
+            </div>
+          )}
+        </div>
+      ))}
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  bar: {
+    display: 'flex',
+    gap: '2px',
+  },
+  menu: {
+    position: 'relative',
+  },
+  menuButton: {
+    padding: '6px 12px',
+    background: 'transparent',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '14px',
+  },
+  menuButtonOpen: {
+    background: '#0f3460',
+  },
+  dropdown: {
+    position: 'absolute',
+    top: '100%',
+    left: 0,
+    minWidth: '220px',
+    padding: '4px 0',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '4px',
+    boxShadow: '0 4px 12px rgba(0, 0, 0, 0.4)',
+    zIndex: 100,
+  },
+  item: {
+    display: 'flex',
+    alignItems: 'center',
+    width: '100%',
+    padding: '6px 12px 6px 4px',
+    background: 'transparent',
+    border: 'none',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '13px',
+    textAlign: 'left',
+  },
+  itemDisabled: {
+    color: '#666',
+    cursor: 'default',
+  },
+  check: {
+    width: '20px',
+    textAlign: 'center',
+  },
+  itemLabel: {
+    flex: 1,
+  },
+  shortcut: {
+    marginLeft: '24px',
+    color: '#888',
+    fontSize: '12px',
+  },
+  separator: {
+    height: '1px',
+    margin: '4px 0',
+    background: '#0f3460',
+  },
+}
@@ -0,0 +1,205 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_MODULATION_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import type { ModulationKind, ModulationPlan, RouteChord } from '../wasm/types'
+
+const ROOTS = ['C', 'C#', 'D', 'Eb', 'E', 'F', 'F#', 'G', 'Ab', 'A', 'Bb', 'B']
+const KINDS: Record<ModulationKind, string> = {
+  pivot_chord: 'Pivot chord',
+  common_tone: 'Common tone',
+  direct: 'Direct',
+}
+
+function chordLabel(route: RouteChord): string {
+  return route.pivot_numeral ? `${route.chord.symbol} (${route.chord.numeral} = ${route.pivot_numeral})` : route.chord.symbol
+}
+
+// Edit → Plan Modulation: routes from the song's key to a new one
+export function ModulationPanel() {
+  const { mozart, key, planModulation, applyModulation } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [root, setRoot] = useState('G')
+  const [mode, setMode] = useState('Major')
+  const [startMeasure, setStartMeasure] = useState(1)
+  const [plans, setPlans] = useState<ModulationPlan[]>([])
+  const [error, setError] = useState<string | null>(null)
+
+  // Start from the playhead's measure each time the panel opens
+  useEffect(() => {
+    const show = () => {
+      const { mozart, currentTick } = useMozartStore.getState()
+      if (mozart) setStartMeasure(Math.floor(currentTick / mozart.ticksPerMeasure()) + 1)
+      setOpen(true)
+    }
+    window.addEventListener(OPEN_MODULATION_EVENT, show)
+    return () => window.removeEventListener(OPEN_MODULATION_EVENT, show)
+  }, [])
+
+  useEffect(() => {
+    if (!open) return
+    try {
+      setPlans(planModulation(`${root} ${mode}`, startMeasure))
+      setError(null)
+    } catch (err) {
+      setPlans([])
+      setError(String(err))
+    }
+  }, [open, root, mode, startMeasure, key, planModulation])
+
+  if (!open || !mozart) return null
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Plan modulation">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Modulate from {key}</h3>
+        <button style={styles.close} onClick={() => setOpen(false)} title="Close">
+          ×
+        </button>
+      </div>
+
+      <div style={styles.row}>
+        <label style={styles.label}>
+          To
+          <select style={styles.select} value={root} onChange={(e) => setRoot(e.target.value)}>
+            {ROOTS.map((r) => (
+              <option key={r} value={r}>
+                {r}
+              </option>
+            ))}
+          </select>
+        </label>
+        <select style={styles.select} value={mode} onChange={(e) => setMode(e.target.value)}>
+          <option value="Major">Major</option>
+          <option value="Natural Minor">Minor</option>
+        </select>
+        <label style={styles.label}>
+          From measure
+          <input
+            type="number"
+            min={1}
+            value={startMeasure}
+            onChange={(e) => setStartMeasure(Math.max(1, parseInt(e.target.value, 10) || 1))}
+            style={styles.input}
+          />
+        </label>
+      </div>
+
+      {error && <p style={styles.error}>{error}</p>}
+      {plans.map((plan, i) => (
+        <div key={i} style={styles.plan}>
+          <div style={styles.planHeader}>
+            <span style={styles.kind}>
+              {KINDS[plan.kind]} · m{plan.start_measure}–{plan.end_measure}
+            </span>
+            <button style={styles.primary} onClick={() => applyModulation(plan)}>
+              Apply
+            </button>
+          </div>
+          <div style={styles.chords}>{plan.chords.map(chordLabel).join(' → ')}</div>
+          <div style={styles.description}>{plan.description}</div>
+        </div>
+      ))}
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '380px',
+    maxHeight: '70vh',
+    overflowY: 'auto',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  label: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '6px',
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  select: {
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  input: {
+    width: '48px',
+    padding: '4px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  plan: {
+    marginTop: '12px',
+    paddingTop: '10px',
+    borderTop: '1px solid #0f3460',
+  },
+  planHeader: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  kind: {
+    color: '#888',
+    fontSize: '12px',
+  },
+  chords: {
+    marginTop: '4px',
+    color: '#eee',
+    fontSize: '14px',
+  },
+  description: {
+    marginTop: '2px',
+    color: '#aaa',
+    fontSize: '12px',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
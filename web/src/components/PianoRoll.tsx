@@ -1,36 +1,242 @@
-import React, { useRef, useEffect, useCallback } from 'react'
+import React, { useRef, useEffect, useCallback, useMemo } from 'react'
 import { useMozartStore } from '../store'
-import { midiToNoteName } from '../wasm'
+import type { EditTool } from '../store'
+import type { Annotation, ChordSymbol, ColorLabel, Marker, Pattern, RepeatStructure } from '../wasm/types'
+import { followScroll, keyPressVelocity, midiToNoteName, zoomAbout } from '../wasm'
 
 const PIANO_KEY_WIDTH = 60
 const NOTE_HEIGHT = 12
-const TICK_WIDTH = 0.1 // pixels per tick
-const MIN_PITCH = 36 // C2
-const MAX_PITCH = 84 // C6
-const TOTAL_KEYS = MAX_PITCH - MIN_PITCH + 1
+const DRAG_PREVIEW_INTERVAL_MS = 50 // Throttle for pitch previews while dragging
+const RULER_HEIGHT = 20
+const GROUP_SHADE = 'rgba(255, 255, 255, 0.035)' // Overlay on every other beat group
+const OUT_OF_RANGE_SHADE = 'rgba(0, 0, 0, 0.35)' // Overlay on rows outside the instrument range
+const LONG_PRESS_MS = 500 // Touch and hold on a note to delete it
+const LONG_PRESS_SLOP = 8 // Pixels a finger may drift before a long press is cancelled
+const REPEAT_MARK_COLOR = '#e0c060'
+const ANNOTATION_COLOR = '#7fb8e0'
+const CHORD_SYMBOL_COLOR = '#f0f0a0'
+const PATTERN_CHAIN_COLOR = '#c090e0'
+const LOOP_REGION_SHADE = 'rgba(96, 192, 255, 0.25)' // Overlay on the ruler over the loop
+const VOICE_METER_COLOR = '#60c0a0' // Barlines of a voice in its own meter
+// Note fills for color labels; sections are tinted with the same colors
+const LABEL_COLORS: Record<ColorLabel, string> = {
+  red: '#d9534f',
+  orange: '#e8873a',
+  yellow: '#d4b83a',
+  green: '#4caf6a',
+  blue: '#4a90d9',
+  purple: '#9b6ad9',
+  pink: '#d96aa7',
+  gray: '#8a8a9a',
+}
+
+// Visit each beat group up to totalTicks; every other group in a measure is shaded
+function forEachBeatGroup(
+  groupings: number[],
+  ticksPerBeat: number,
+  ticksPerMeasure: number,
+  totalTicks: number,
+  visit: (startTick: number, endTick: number, shaded: boolean) => void
+) {
+  for (let measureStart = 0; measureStart < totalTicks; measureStart += ticksPerMeasure) {
+    let tick = measureStart
+    groupings.forEach((size, i) => {
+      const end = tick + size * ticksPerBeat
+      visit(tick, end, i % 2 === 1)
+      tick = end
+    })
+  }
+}
+
+// Colored sections: each runs from its marker to the next one (or the end)
+function forEachColoredSection(
+  markers: Marker[],
+  totalTicks: number,
+  visit: (startTick: number, endTick: number, color: string) => void
+) {
+  markers.forEach((marker, i) => {
+    const end = markers[i + 1]?.tick ?? totalTicks
+    if (marker.color && end > marker.tick) visit(marker.tick, end, LABEL_COLORS[marker.color])
+  })
+}
+
+// Annotations along the top of the ruler: a flag at the tick, a bar over a range
+function drawAnnotations(ctx: CanvasRenderingContext2D, annotations: Annotation[], tickX: (tick: number) => number) {
+  ctx.fillStyle = ANNOTATION_COLOR
+  ctx.font = '9px sans-serif'
+  for (const { tick, end_tick, text } of annotations) {
+    const x = tickX(tick)
+    if (end_tick !== undefined) {
+      ctx.globalAlpha = 0.5
+      ctx.fillRect(x, 0, tickX(end_tick) - x, 2)
+      ctx.globalAlpha = 1
+    }
+    ctx.beginPath()
+    ctx.moveTo(x, 0)
+    ctx.lineTo(x + 6, 0)
+    ctx.lineTo(x, 7)
+    ctx.fill()
+    // Right of the measure number when both sit at a barline
+    ctx.fillText(text, x + 18, 8)
+  }
+}
+
+// Chord track symbols along the bottom of the ruler, each where it starts
+function drawChordSymbols(ctx: CanvasRenderingContext2D, chords: ChordSymbol[], tickX: (tick: number) => number) {
+  ctx.fillStyle = CHORD_SYMBOL_COLOR
+  ctx.font = 'bold 9px sans-serif'
+  for (const { tick, symbol } of chords) {
+    ctx.fillText(symbol, tickX(tick) + 3, RULER_HEIGHT - 4)
+  }
+}
+
+// The pattern chain along the top of the ruler: each pattern's name over the ticks it plays
+function drawPatternChain(ctx: CanvasRenderingContext2D, patterns: Pattern[], chain: string[], tickX: (tick: number) => number) {
+  ctx.fillStyle = PATTERN_CHAIN_COLOR
+  ctx.font = '9px sans-serif'
+  let tick = 0
+  for (const name of chain) {
+    const pattern = patterns.find((p) => p.name === name)
+    if (!pattern) continue
+    const x = tickX(tick)
+    ctx.fillRect(x + 1, 0, tickX(tick + pattern.length_ticks) - x - 2, 2)
+    ctx.fillText(name, x + 3, 11)
+    tick += pattern.length_ticks
+  }
+}
+
+// Repeat barlines, ending brackets, signs, and the jump along the bottom of the ruler
+function drawRepeatMarks(ctx: CanvasRenderingContext2D, repeats: RepeatStructure, measureX: (measure: number) => number) {
+  ctx.fillStyle = REPEAT_MARK_COLOR
+  ctx.strokeStyle = REPEAT_MARK_COLOR
+  ctx.lineWidth = 1
+  ctx.font = '9px sans-serif'
+  const label = (text: string, x: number, alignRight = false) => {
+    ctx.textAlign = alignRight ? 'right' : 'left'
+    ctx.fillText(text, x, RULER_HEIGHT - 3)
+    ctx.textAlign = 'left'
+  }
+
+  for (const repeat of repeats.repeats ?? []) {
+    label('𝄆', measureX(repeat.start_measure) + 2)
+    label(repeat.times === 2 ? '𝄇' : `×${repeat.times} 𝄇`, measureX(repeat.end_measure + 1) - 2, true)
+  }
+  for (const ending of repeats.endings ?? []) {
+    const x = measureX(ending.start_measure) + 1
+    const end = measureX(ending.end_measure + 1) - 3
+    ctx.beginPath()
+    ctx.moveTo(x, RULER_HEIGHT)
+    ctx.lineTo(x, 14)
+    ctx.lineTo(end, 14)
+    ctx.stroke()
+    label(`${ending.passes.join(',')}.`, x + 14)
+  }
+  if (repeats.segno) label('𝄋', measureX(repeats.segno) + 14)
+  if (repeats.coda) label('𝄌', measureX(repeats.coda) + 14)
+  if (repeats.fine) label('Fine', measureX(repeats.fine + 1) - 10, true)
+  if (repeats.to_coda) label('To 𝄌', measureX(repeats.to_coda + 1) - 10, true)
+  if (repeats.jump) {
+    const { kind, until, at_measure } = repeats.jump
+    const end = { end: '', fine: ' al Fine', coda: ' al Coda' }[until]
+    label(`${kind === 'da_capo' ? 'D.C.' : 'D.S.'}${end}`, measureX(at_measure + 1) - 10, true)
+  }
+}
+
+// Tick offsets within a measure where each pulse starts
+function pulseOffsets(pulseGrouping: number[], ticksPerBeat: number): number[] {
+  const offsets: number[] = []
+  let tick = 0
+  for (const size of pulseGrouping) {
+    offsets.push(tick)
+    tick += size * ticksPerBeat
+  }
+  return offsets
+}
 
 export function PianoRoll() {
   const canvasRef = useRef<HTMLCanvasElement>(null)
+  const rulerRef = useRef<HTMLCanvasElement>(null)
   const containerRef = useRef<HTMLDivElement>(null)
+  const dragRef = useRef<{ index: number; pitch: number; lastPreview: number; moved: boolean } | null>(null)
+  const touchRef = useRef<{
+    x: number
+    y: number
+    pinchDistance: number | null
+    longPress: number | null
+    deleted: boolean // A long press removed a note; swallow the click that follows
+  }>({ x: 0, y: 0, pinchDistance: null, longPress: null, deleted: false })
+  const pendingScrollRef = useRef<number | null>(null) // Applied once a zoomed canvas is drawn
 
   const {
     notes,
+    visibleNotes,
+    viewportStart,
+    viewportEnd,
+    setViewport,
     currentTick,
     playbackState,
     timeSignature,
+    groupings,
+    pulseGrouping,
     selectedNoteIndex,
+    pitchRange,
+    outOfRangeNotes,
+    annotations,
+    chords,
+    markers,
+    repeats,
+    patterns,
+    patternChain,
+    loopRegion,
+    voiceMeters,
+    visiblePitchRange,
     isWasmLoaded,
+    followMode,
+    mozart,
+    pixelsPerTick: tickWidth,
+    setPixelsPerTick,
+    setViewScroll,
+    editTool,
+    setEditTool,
+    splitNote,
+    joinNotes,
     addNote,
     removeNote,
     selectNote,
     playNotePreview,
+    previewPitch,
+    chordEntry,
+    chordEntryKey,
+    setNotePitch,
+    auditionRange,
     seekTo,
     play,
     pause,
   } = useMozartStore()
 
-  const ticksPerBeat = 480
+  const outOfRange = useMemo(() => new Set(outOfRangeNotes), [outOfRangeNotes])
+  // Rows shown, top to bottom from maxPitch (View > Pitch Range)
+  const { low: minPitch, high: maxPitch } = visiblePitchRange
+  const totalKeys = maxPitch - minPitch + 1
+
+  const ticksPerBeat = mozart ? mozart.ticksPerBeat() : 480
   const ticksPerMeasure = ticksPerBeat * timeSignature.numerator
+  // Beat lines and snapping follow the felt pulse (e.g., dotted quarters in 6/8 felt in two)
+  const pulses = pulseOffsets(pulseGrouping, ticksPerBeat)
+
+  // The selected note's voice, when it has a meter of its own (polymeter)
+  const selectedVoice = selectedNoteIndex !== null ? notes[selectedNoteIndex]?.voice : undefined
+  const voiceMeter = voiceMeters.find((meter) => meter.voice === selectedVoice)?.time_signature
+  const voiceTicksPerMeasure = voiceMeter
+    ? ((ticksPerBeat * timeSignature.denominator) / voiceMeter.denominator) * voiceMeter.numerator
+    : null
+
+  // Snap down to the pulse at or before a tick
+  const snapToPulse = (tick: number): number => {
+    const measureStart = tick - (tick % ticksPerMeasure)
+    const offset = tick - measureStart
+    return measureStart + Math.max(0, ...pulses.filter((p) => p <= offset))
+  }
 
   // Draw the piano roll
   const draw = useCallback(() => {
@@ -43,17 +249,15 @@ export function PianoRoll() {
 
     // Calculate content width based on notes (minimum 8 measures, expand as needed)
     const minMeasures = 8
-    const maxNoteTick = notes.length > 0
-      ? Math.max(...notes.map((n) => n.start_tick + n.duration_ticks))
-      : 0
+    const maxNoteTick = mozart ? mozart.durationTicks() : 0
     const contentMeasures = Math.ceil(maxNoteTick / ticksPerMeasure) + 2 // Add 2 measures of padding
     const totalMeasures = Math.max(minMeasures, contentMeasures)
     const totalTicks = totalMeasures * ticksPerMeasure
 
     // Set canvas size - width based on content, not container
-    const contentWidth = PIANO_KEY_WIDTH + totalTicks * TICK_WIDTH
+    const contentWidth = PIANO_KEY_WIDTH + totalTicks * tickWidth
     const width = Math.max(container.clientWidth, contentWidth)
-    const height = TOTAL_KEYS * NOTE_HEIGHT
+    const height = totalKeys * NOTE_HEIGHT
     canvas.width = width
     canvas.height = height
 
@@ -64,14 +268,18 @@ export function PianoRoll() {
     const gridWidth = width - PIANO_KEY_WIDTH
 
     // Draw horizontal lines (pitch grid)
-    for (let i = 0; i <= TOTAL_KEYS; i++) {
+    for (let i = 0; i <= totalKeys; i++) {
       const y = i * NOTE_HEIGHT
-      const pitch = MAX_PITCH - i
+      const pitch = maxPitch - i
       const isBlackKey = [1, 3, 6, 8, 10].includes(pitch % 12)
 
       // Row background
       ctx.fillStyle = isBlackKey ? '#151525' : '#1a1a2e'
       ctx.fillRect(PIANO_KEY_WIDTH, y, gridWidth, NOTE_HEIGHT)
+      if (pitchRange && (pitch < pitchRange.low || pitch > pitchRange.high)) {
+        ctx.fillStyle = OUT_OF_RANGE_SHADE
+        ctx.fillRect(PIANO_KEY_WIDTH, y, gridWidth, NOTE_HEIGHT)
+      }
 
       // Grid line
       ctx.strokeStyle = '#252540'
@@ -81,12 +289,28 @@ export function PianoRoll() {
       ctx.stroke()
     }
 
+    // Shade alternating beat groups so odd meters read as their groupings (e.g., 3+2+2)
+    ctx.fillStyle = GROUP_SHADE
+    forEachBeatGroup(groupings, ticksPerBeat, ticksPerMeasure, totalTicks, (start, end, shaded) => {
+      if (shaded) {
+        ctx.fillRect(PIANO_KEY_WIDTH + start * tickWidth, 0, (end - start) * tickWidth, height)
+      }
+    })
+
+    // Tint colored sections behind the grid
+    ctx.globalAlpha = 0.08
+    forEachColoredSection(markers, totalTicks, (start, end, color) => {
+      ctx.fillStyle = color
+      ctx.fillRect(PIANO_KEY_WIDTH + start * tickWidth, 0, (end - start) * tickWidth, height)
+    })
+    ctx.globalAlpha = 1
+
     // Draw vertical lines (beat grid)
     for (let tick = 0; tick <= totalTicks; tick += ticksPerBeat / 4) {
-      const x = PIANO_KEY_WIDTH + tick * TICK_WIDTH
+      const x = PIANO_KEY_WIDTH + tick * tickWidth
 
       const isMeasure = tick % ticksPerMeasure === 0
-      const isBeat = tick % ticksPerBeat === 0
+      const isBeat = pulses.includes(tick % ticksPerMeasure)
 
       ctx.strokeStyle = isMeasure ? '#404060' : isBeat ? '#303050' : '#252540'
       ctx.lineWidth = isMeasure ? 2 : 1
@@ -97,29 +321,54 @@ export function PianoRoll() {
       ctx.lineWidth = 1
     }
 
-    // Draw notes
-    notes.forEach((note, index) => {
-      const x = PIANO_KEY_WIDTH + note.start_tick * TICK_WIDTH
-      const y = (MAX_PITCH - note.pitch) * NOTE_HEIGHT
-      const noteWidth = note.duration_ticks * TICK_WIDTH
+    // The selected voice's own barlines, drifting against the song's
+    if (voiceTicksPerMeasure) {
+      ctx.strokeStyle = VOICE_METER_COLOR
+      ctx.setLineDash([4, 4])
+      for (let tick = 0; tick <= totalTicks; tick += voiceTicksPerMeasure) {
+        const x = PIANO_KEY_WIDTH + tick * tickWidth
+        ctx.beginPath()
+        ctx.moveTo(x, 0)
+        ctx.lineTo(x, height)
+        ctx.stroke()
+      }
+      ctx.setLineDash([])
+    }
+
+    // Draw notes (only the viewport's are fetched)
+    visibleNotes.forEach(({ index, ...note }) => {
+      const x = PIANO_KEY_WIDTH + note.start_tick * tickWidth
+      const y = (maxPitch - note.pitch) * NOTE_HEIGHT
+      const noteWidth = note.duration_ticks * tickWidth
 
       // Skip if off screen
       if (x + noteWidth < PIANO_KEY_WIDTH || x > width) return
-      if (note.pitch < MIN_PITCH || note.pitch > MAX_PITCH) return
+      if (note.pitch < minPitch || note.pitch > maxPitch) return
 
       const isSelected = index === selectedNoteIndex
+      const isOutOfRange = outOfRange.has(index)
 
-      // Note rectangle
-      ctx.fillStyle = isSelected ? '#e94560' : '#4a90d9'
+      // Note rectangle, fainter the less likely it is to play
+      ctx.globalAlpha = 0.35 + 0.65 * ((note.probability ?? 100) / 100)
+      const fill = note.color ? LABEL_COLORS[note.color] : '#4a90d9'
+      ctx.fillStyle = isSelected ? '#e94560' : isOutOfRange ? '#d98c20' : fill
       ctx.fillRect(x, y + 1, noteWidth - 1, NOTE_HEIGHT - 2)
+      ctx.globalAlpha = 1
 
       // Note border
-      ctx.strokeStyle = isSelected ? '#ff6b8a' : '#6ab0ff'
+      ctx.strokeStyle = isSelected ? '#ff6b8a' : isOutOfRange ? '#f5a623' : '#6ab0ff'
       ctx.strokeRect(x, y + 1, noteWidth - 1, NOTE_HEIGHT - 2)
+
+      // Alternate take lane
+      if (note.take && noteWidth > 10) {
+        ctx.fillStyle = '#fff'
+        ctx.font = '9px sans-serif'
+        ctx.fillText(note.take, x + 2, y + NOTE_HEIGHT - 3)
+      }
     })
 
     // Draw playhead (always visible, brighter when playing)
-    const playheadX = PIANO_KEY_WIDTH + currentTick * TICK_WIDTH
+    const playheadX = PIANO_KEY_WIDTH + currentTick * tickWidth
     ctx.strokeStyle = playbackState === 'playing' ? '#e94560' : '#994040'
     ctx.lineWidth = 2
     ctx.beginPath()
@@ -129,9 +378,9 @@ export function PianoRoll() {
     ctx.lineWidth = 1
 
     // Draw piano keys
-    for (let i = 0; i < TOTAL_KEYS; i++) {
+    for (let i = 0; i < totalKeys; i++) {
       const y = i * NOTE_HEIGHT
-      const pitch = MAX_PITCH - i
+      const pitch = maxPitch - i
       const isBlackKey = [1, 3, 6, 8, 10].includes(pitch % 12)
 
       // Key background
@@ -149,19 +398,107 @@ export function PianoRoll() {
         ctx.fillText(midiToNoteName(pitch), 4, y + NOTE_HEIGHT - 3)
       }
     }
-  }, [notes, currentTick, playbackState, timeSignature, selectedNoteIndex, isWasmLoaded])
+
+    // Draw the ruler: measure numbers over the same beat-group shading
+    const ruler = rulerRef.current
+    const rulerCtx = ruler?.getContext('2d')
+    if (ruler && rulerCtx) {
+      ruler.width = width
+      ruler.height = RULER_HEIGHT
+
+      rulerCtx.fillStyle = '#1a1a2e'
+      rulerCtx.fillRect(0, 0, width, RULER_HEIGHT)
+
+      forEachBeatGroup(groupings, ticksPerBeat, ticksPerMeasure, totalTicks, (start, end, shaded) => {
+        const x = PIANO_KEY_WIDTH + start * tickWidth
+        const isMeasure = start % ticksPerMeasure === 0
+
+        rulerCtx.fillStyle = shaded ? '#1f2b4d' : '#16213e'
+        rulerCtx.fillRect(x, 0, (end - start) * tickWidth, RULER_HEIGHT)
+
+        rulerCtx.strokeStyle = '#404060'
+        rulerCtx.beginPath()
+        rulerCtx.moveTo(x, isMeasure ? 0 : RULER_HEIGHT / 2)
+        rulerCtx.lineTo(x, RULER_HEIGHT)
+        rulerCtx.stroke()
+
+        if (isMeasure) {
+          rulerCtx.fillStyle = '#888'
+          rulerCtx.font = '10px sans-serif'
+          rulerCtx.fillText(String(start / ticksPerMeasure + 1), x + 3, 12)
+        }
+      })
+
+      if (loopRegion) {
+        rulerCtx.fillStyle = LOOP_REGION_SHADE
+        rulerCtx.fillRect(PIANO_KEY_WIDTH + loopRegion[0] * tickWidth, 0, (loopRegion[1] - loopRegion[0]) * tickWidth, RULER_HEIGHT)
+      }
+
+      // A strip along the bottom of the ruler under each colored section
+      forEachColoredSection(markers, totalTicks, (start, end, color) => {
+        rulerCtx.fillStyle = color
+        rulerCtx.fillRect(PIANO_KEY_WIDTH + start * tickWidth, RULER_HEIGHT - 3, (end - start) * tickWidth, 3)
+      })
+
+      if (voiceMeter && voiceTicksPerMeasure) {
+        rulerCtx.fillStyle = VOICE_METER_COLOR
+        for (let tick = 0; tick <= totalTicks; tick += voiceTicksPerMeasure) {
+          rulerCtx.fillRect(PIANO_KEY_WIDTH + tick * tickWidth - 1, RULER_HEIGHT - 6, 2, 6)
+        }
+        rulerCtx.font = '9px sans-serif'
+        rulerCtx.fillText(`${voiceMeter.numerator}/${voiceMeter.denominator}`, PIANO_KEY_WIDTH + 16, RULER_HEIGHT - 7)
+      }
+
+      drawRepeatMarks(rulerCtx, repeats, (measure) => PIANO_KEY_WIDTH + (measure - 1) * ticksPerMeasure * tickWidth)
+      drawAnnotations(rulerCtx, annotations, (tick) => PIANO_KEY_WIDTH + tick * tickWidth)
+      drawPatternChain(rulerCtx, patterns, patternChain, (tick) => PIANO_KEY_WIDTH + tick * tickWidth)
+      drawChordSymbols(rulerCtx, chords, (tick) => PIANO_KEY_WIDTH + tick * tickWidth)
+
+      rulerCtx.strokeStyle = playbackState === 'playing' ? '#e94560' : '#994040'
+      rulerCtx.lineWidth = 2
+      rulerCtx.beginPath()
+      rulerCtx.moveTo(playheadX, 0)
+      rulerCtx.lineTo(playheadX, RULER_HEIGHT)
+      rulerCtx.stroke()
+    }
+  }, [notes, visibleNotes, currentTick, playbackState, timeSignature, groupings, pulseGrouping, selectedNoteIndex, pitchRange, outOfRange, annotations, chords, markers, repeats, patterns, patternChain, loopRegion, voiceMeter, voiceTicksPerMeasure, isWasmLoaded, tickWidth, minPitch, maxPitch])
 
   // Redraw on state changes
   useEffect(() => {
     draw()
+    const container = containerRef.current
+    if (container && pendingScrollRef.current !== null) {
+      container.scrollLeft = pendingScrollRef.current
+      pendingScrollRef.current = null
+    }
   }, [draw])
 
+  // Fetch a wider window once the visible ticks leave the fetched one
+  const updateViewport = useCallback(() => {
+    const container = containerRef.current
+    if (!container) return
+
+    const start = Math.max(0, (container.scrollLeft - PIANO_KEY_WIDTH) / tickWidth)
+    const end = (container.scrollLeft + container.clientWidth) / tickWidth
+    if (start < viewportStart || end > viewportEnd) {
+      const margin = end - start
+      setViewport(Math.floor(Math.max(0, start - margin)), Math.ceil(end + margin))
+    }
+  }, [viewportStart, viewportEnd, setViewport, tickWidth])
+
+  useEffect(() => {
+    updateViewport()
+  }, [updateViewport, mozart])
+
   // Redraw on resize
   useEffect(() => {
-    const handleResize = () => draw()
+    const handleResize = () => {
+      updateViewport()
+      draw()
+    }
     window.addEventListener('resize', handleResize)
     return () => window.removeEventListener('resize', handleResize)
-  }, [draw])
+  }, [draw, updateViewport])
 
   // Animation loop for playback
   useEffect(() => {
@@ -177,6 +514,42 @@ export function PianoRoll() {
     return () => cancelAnimationFrame(animationId)
   }, [playbackState, draw])
 
+  // Keep the same pitch at the top of the view when the rows change (a load
+  // restores its own scroll below, after this)
+  const lastMaxPitch = useRef(maxPitch)
+  useEffect(() => {
+    const container = containerRef.current
+    if (container) container.scrollTop += (maxPitch - lastMaxPitch.current) * NOTE_HEIGHT
+    lastMaxPitch.current = maxPitch
+  }, [maxPitch])
+
+  // Restore the saved scroll position when a song is loaded
+  useEffect(() => {
+    const container = containerRef.current
+    if (!container) return
+    const { viewScroll } = useMozartStore.getState()
+    container.scrollLeft = viewScroll.x
+    container.scrollTop = viewScroll.y
+  }, [mozart])
+
+  // Keep the playhead in view while playing
+  useEffect(() => {
+    const container = containerRef.current
+    if (!container || playbackState !== 'playing' || followMode === 'off') return
+
+    const scrollLeft = followScroll(
+      followMode,
+      container.scrollLeft,
+      container.clientWidth,
+      currentTick,
+      PIANO_KEY_WIDTH,
+      tickWidth
+    )
+    if (scrollLeft !== container.scrollLeft) {
+      container.scrollLeft = scrollLeft
+    }
+  }, [currentTick, playbackState, followMode, tickWidth])
+
   // Handle keyboard events
   useEffect(() => {
     const handleKeyDown = (e: KeyboardEvent) => {
@@ -195,52 +568,220 @@ export function PianoRoll() {
       if (e.key === 'Backspace' && selectedNoteIndex !== null) {
         e.preventDefault()
         removeNote(selectedNoteIndex)
+        return
+      }
+
+      // Tool shortcuts: D = draw, X = scissors (split), G = glue (join)
+      if (e.target === document.body && !e.ctrlKey && !e.metaKey && !e.altKey) {
+        const tool = ({ d: 'draw', x: 'split', g: 'join' } as const)[e.key.toLowerCase()]
+        if (tool) setEditTool(tool)
       }
     }
 
     window.addEventListener('keydown', handleKeyDown)
     return () => window.removeEventListener('keydown', handleKeyDown)
-  }, [selectedNoteIndex, removeNote, playbackState, play, pause])
+  }, [selectedNoteIndex, removeNote, playbackState, play, pause, setEditTool])
+
+  // Pitch under the cursor, or null over the keys / outside the grid
+  const pitchAt = (e: React.MouseEvent<HTMLCanvasElement>): number | null => {
+    const rect = canvasRef.current!.getBoundingClientRect()
+    const pitch = maxPitch - Math.floor((e.clientY - rect.top) / NOTE_HEIGHT)
+    return pitch >= minPitch && pitch <= maxPitch ? pitch : null
+  }
+
+  // Note under a point in client coordinates
+  const noteAt = (clientX: number, clientY: number) => {
+    const rect = canvasRef.current!.getBoundingClientRect()
+    const x = clientX - rect.left
+    const pitch = maxPitch - Math.floor((clientY - rect.top) / NOTE_HEIGHT)
+    if (x < PIANO_KEY_WIDTH || pitch < minPitch || pitch > maxPitch) return undefined
+
+    const tick = Math.floor((x - PIANO_KEY_WIDTH) / tickWidth)
+    return visibleNotes.find(
+      (n) => n.pitch === pitch && tick >= n.start_tick && tick < n.start_tick + n.duration_ticks
+    )
+  }
+
+  // Start a vertical drag when pressing on an existing note
+  const handleMouseDown = (e: React.MouseEvent<HTMLCanvasElement>) => {
+    if (!canvasRef.current || e.shiftKey || e.altKey || e.ctrlKey || e.metaKey) return
+
+    const hit = noteAt(e.clientX, e.clientY)
+    if (hit) {
+      dragRef.current = { index: hit.index, pitch: hit.pitch, lastPreview: 0, moved: false }
+    }
+  }
+
+  const cancelLongPress = () => {
+    const touch = touchRef.current
+    if (touch.longPress !== null) {
+      window.clearTimeout(touch.longPress)
+      touch.longPress = null
+    }
+  }
+
+  const pinchDistance = (touches: React.TouchList) =>
+    Math.hypot(touches[0].clientX - touches[1].clientX, touches[0].clientY - touches[1].clientY)
+
+  // One finger: hold on a note to delete it. Two fingers: pinch to zoom.
+  const handleTouchStart = (e: React.TouchEvent<HTMLCanvasElement>) => {
+    const touch = touchRef.current
+    cancelLongPress()
+
+    if (e.touches.length === 2) {
+      touch.pinchDistance = pinchDistance(e.touches)
+      return
+    }
+    if (e.touches.length !== 1) return
+
+    const { clientX, clientY } = e.touches[0]
+    touch.x = clientX
+    touch.y = clientY
+    const hit = noteAt(clientX, clientY)
+    if (hit) {
+      touch.longPress = window.setTimeout(() => {
+        touch.longPress = null
+        touch.deleted = true
+        removeNote(hit.index)
+        navigator.vibrate?.(20)
+      }, LONG_PRESS_MS)
+    }
+  }
+
+  const handleTouchMove = (e: React.TouchEvent<HTMLCanvasElement>) => {
+    const touch = touchRef.current
+    const container = containerRef.current
+
+    if (e.touches.length === 2 && touch.pinchDistance && container) {
+      const distance = pinchDistance(e.touches)
+      const centerX = (e.touches[0].clientX + e.touches[1].clientX) / 2
+      const anchorX = centerX - container.getBoundingClientRect().left
+      const zoomed = zoomAbout(distance / touch.pinchDistance, anchorX, container.scrollLeft, PIANO_KEY_WIDTH, tickWidth)
+      touch.pinchDistance = distance
+      pendingScrollRef.current = zoomed.scrollLeft
+      setPixelsPerTick(zoomed.pixelsPerTick)
+      return
+    }
+
+    const { clientX, clientY } = e.touches[0]
+    if (Math.hypot(clientX - touch.x, clientY - touch.y) > LONG_PRESS_SLOP) {
+      cancelLongPress()
+    }
+  }
+
+  const handleTouchEnd = (e: React.TouchEvent<HTMLCanvasElement>) => {
+    cancelLongPress()
+    if (e.touches.length < 2) {
+      touchRef.current.pinchDistance = null
+    }
+  }
+
+  // While dragging, move the note and audition the pitch it would land on (throttled)
+  const handleMouseMove = (e: React.MouseEvent<HTMLCanvasElement>) => {
+    const drag = dragRef.current
+    if (!drag) return
+
+    const pitch = pitchAt(e)
+    if (pitch === null || pitch === drag.pitch) return
+
+    drag.pitch = pitch
+    drag.moved = true
+    setNotePitch(drag.index, pitch)
+
+    const now = performance.now()
+    if (now - drag.lastPreview >= DRAG_PREVIEW_INTERVAL_MS) {
+      drag.lastPreview = now
+      previewPitch(pitch)
+    }
+  }
+
+  const handleMouseUp = () => {
+    const drag = dragRef.current
+    if (drag && !drag.moved) {
+      dragRef.current = null
+    }
+  }
 
   // Handle click to add/select notes
   const handleClick = (e: React.MouseEvent<HTMLCanvasElement>) => {
     const canvas = canvasRef.current
     if (!canvas) return
 
+    // A long press already deleted the note under the finger
+    if (touchRef.current.deleted) {
+      touchRef.current.deleted = false
+      return
+    }
+
+    // A drag ends with a click event; don't treat it as select/add
+    if (dragRef.current?.moved) {
+      dragRef.current = null
+      return
+    }
+    dragRef.current = null
+
     const rect = canvas.getBoundingClientRect()
     const x = e.clientX - rect.left
     const y = e.clientY - rect.top
 
     // Ignore clicks on piano keys
     if (x < PIANO_KEY_WIDTH) {
-      // Play the note preview
-      const pitch = MAX_PITCH - Math.floor(y / NOTE_HEIGHT)
-      if (pitch >= MIN_PITCH && pitch <= MAX_PITCH) {
-        playNotePreview(pitch)
+      // Play the note preview, louder the lower on the key it was clicked
+      const pitch = maxPitch - Math.floor(y / NOTE_HEIGHT)
+      if (pitch >= minPitch && pitch <= maxPitch) {
+        const velocity = keyPressVelocity(y % NOTE_HEIGHT, NOTE_HEIGHT)
+        playNotePreview(pitch, velocity)
+        if (chordEntry) chordEntryKey(pitch, velocity)
       }
       return
     }
 
-    const tick = Math.floor((x - PIANO_KEY_WIDTH) / TICK_WIDTH)
-    const pitch = MAX_PITCH - Math.floor(y / NOTE_HEIGHT)
+    const tick = Math.floor((x - PIANO_KEY_WIDTH) / tickWidth)
+    const pitch = maxPitch - Math.floor(y / NOTE_HEIGHT)
+
+    // Ctrl/Cmd+click to audition the measure under the cursor
+    if (e.ctrlKey || e.metaKey) {
+      const measureStart = Math.floor(tick / ticksPerMeasure) * ticksPerMeasure
+      auditionRange(measureStart, measureStart + ticksPerMeasure)
+      return
+    }
 
     // Alt+click to set playback position
     if (e.altKey) {
-      const snapTicks = ticksPerBeat
-      const snappedTick = Math.floor(tick / snapTicks) * snapTicks
-      seekTo(snappedTick)
+      seekTo(snapToPulse(tick))
       return
     }
 
-    if (pitch < MIN_PITCH || pitch > MAX_PITCH) return
+    if (pitch < minPitch || pitch > maxPitch) return
 
     // Check if clicking on existing note
-    const clickedIndex = notes.findIndex(
+    const clicked = visibleNotes.find(
       (n) =>
         n.pitch === pitch &&
         tick >= n.start_tick &&
         tick < n.start_tick + n.duration_ticks
     )
+    const clickedIndex = clicked ? clicked.index : -1
+
+    if (clicked && editTool === 'split') {
+      // Scissors: cut at the nearest beat inside the note, or exactly at the cursor
+      const note = clicked
+      const beat = Math.round(tick / ticksPerBeat) * ticksPerBeat
+      const end = note.start_tick + note.duration_ticks
+      splitNote(clickedIndex, beat > note.start_tick && beat < end ? beat : tick)
+      return
+    }
+
+    if (clicked && editTool === 'join') {
+      // Glue: merge with the next note of the same pitch that starts where this one ends
+      const note = clicked
+      const end = note.start_tick + note.duration_ticks
+      const next = visibleNotes.find(
+        (n) => n.index !== clickedIndex && n.pitch === note.pitch && n.start_tick >= note.start_tick && n.start_tick <= end
+      )
+      if (next) joinNotes([clickedIndex, next.index])
+      return
+    }
 
     if (clickedIndex !== -1) {
       if (e.shiftKey) {
@@ -252,28 +793,63 @@ export function PianoRoll() {
       }
     } else {
       // Click to add new note
-      // Snap to grid (quarter note grid by default)
-      const snapTicks = ticksPerBeat
-      const snappedTick = Math.floor(tick / snapTicks) * snapTicks
-      const defaultDuration = ticksPerBeat // Quarter note
+      // Snap to the pulse grid (every beat unless the meter is felt in groups)
+      const defaultDuration = ticksPerBeat // One beat
 
-      addNote(pitch, snappedTick, defaultDuration)
+      addNote(pitch, snapToPulse(tick), defaultDuration)
       playNotePreview(pitch)
     }
   }
 
   return (
-    <div ref={containerRef} style={styles.container}>
-      <canvas ref={canvasRef} style={styles.canvas} onClick={handleClick} />
+    <div
+      ref={containerRef}
+      style={styles.container}
+      onScroll={(e) => {
+        setViewScroll(e.currentTarget.scrollLeft, e.currentTarget.scrollTop)
+        updateViewport()
+      }}
+    >
+      <canvas ref={rulerRef} style={styles.ruler} />
+      <canvas
+        ref={canvasRef}
+        style={{ ...styles.canvas, cursor: TOOL_CURSORS[editTool] }}
+        onClick={handleClick}
+        onMouseDown={handleMouseDown}
+        onMouseMove={handleMouseMove}
+        onMouseUp={handleMouseUp}
+        onMouseLeave={() => (dragRef.current = null)}
+        onTouchStart={handleTouchStart}
+        onTouchMove={handleTouchMove}
+        onTouchEnd={handleTouchEnd}
+        onTouchCancel={handleTouchEnd}
+        onContextMenu={(e) => {
+          // Holding a finger down opens the context menu on mobile; it is a delete gesture here
+          if ((e.nativeEvent as PointerEvent).pointerType === 'touch') e.preventDefault()
+        }}
+      />
     </div>
   )
 }
 
+const TOOL_CURSORS: Record<EditTool, string> = {
+  draw: 'crosshair',
+  split: 'col-resize',
+  join: 'copy',
+}
+
 const styles: Record<string, React.CSSProperties> = {
   container: {
     flex: 1,
     overflow: 'auto',
     background: '#1a1a2e',
+    touchAction: 'pan-x pan-y', // Pinch zooms the timeline, not the page
+  },
+  ruler: {
+    display: 'block',
+    position: 'sticky',
+    top: 0,
+    zIndex: 1,
   },
   canvas: {
     display: 'block',
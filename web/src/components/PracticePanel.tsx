@@ -0,0 +1,274 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_PRACTICE_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { connectMidiInput, connectMidiPreview } from '../integration/midiInput'
+import { midiToNoteName } from '../wasm'
+import type { Difficulty, NoteFeedback, NoteVerdict } from '../wasm/types'
+
+const VERDICTS: Record<NoteVerdict, { label: string; color: string }> = {
+  correct: { label: 'Correct', color: '#4ecca3' },
+  wrong_pitch: { label: 'Wrong note', color: '#e94560' },
+  early: { label: 'Early', color: '#f0a500' },
+  late: { label: 'Late', color: '#f0a500' },
+  missed: { label: 'Missed', color: '#888' },
+}
+
+function FeedbackRow({ feedback }: { feedback: NoteFeedback }) {
+  const verdict = VERDICTS[feedback.verdict]
+  const offset = feedback.timing_offset_ticks
+  return (
+    <tr>
+      <td style={styles.td}>{feedback.index + 1}</td>
+      <td style={styles.td}>{midiToNoteName(feedback.expected_pitch)}</td>
+      <td style={styles.td}>{feedback.played_pitch !== undefined ? midiToNoteName(feedback.played_pitch) : '—'}</td>
+      <td style={styles.td}>{offset !== undefined ? `${offset > 0 ? '+' : ''}${offset}` : ''}</td>
+      <td style={{ ...styles.td, color: verdict.color }}>{verdict.label}</td>
+    </tr>
+  )
+}
+
+// Playback → Call and Response: hear a phrase, play it back on a MIDI keyboard
+export function PracticePanel() {
+  const { practice, mozart, currentTick, startCallResponse, callResponseNoteOn, stopCallResponse, closeCallResponse } =
+    useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [generate, setGenerate] = useState(false)
+  const [measures, setMeasures] = useState(2)
+  const [difficulty, setDifficulty] = useState<Difficulty>('easy')
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    const show = () => setOpen(true)
+    window.addEventListener(OPEN_PRACTICE_EVENT, show)
+    return () => window.removeEventListener(OPEN_PRACTICE_EVENT, show)
+  }, [])
+
+  // Only ask for MIDI access while the panel is open; otherwise keys preview
+  // notes, if access was granted before
+  useEffect(() => {
+    if (!open) return connectMidiPreview()
+    return connectMidiInput(callResponseNoteOn)
+  }, [open, callResponseNoteOn])
+
+  if (!open || !mozart) return null
+
+  const close = () => {
+    closeCallResponse()
+    setError(null)
+    setOpen(false)
+  }
+
+  const start = () => {
+    const startMeasure = Math.floor(currentTick / mozart.ticksPerMeasure()) + 1
+    setError(
+      startCallResponse({
+        generate,
+        start_measure: startMeasure,
+        measures,
+        difficulty,
+        seed: Date.now() % 1_000_000,
+      })
+    )
+  }
+
+  const running = practice !== null && practice.phase !== 'finished'
+  const result = practice?.result ?? null
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Call and response">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Call and Response</h3>
+        <button style={styles.close} onClick={close} title="Close">
+          ×
+        </button>
+      </div>
+
+      <div style={styles.row}>
+        <select
+          style={styles.select}
+          value={generate ? 'generate' : 'song'}
+          onChange={(e) => setGenerate(e.target.value === 'generate')}
+          disabled={running}
+        >
+          <option value="song">From the playhead</option>
+          <option value="generate">Generated melody</option>
+        </select>
+        {generate ? (
+          <select
+            style={styles.select}
+            value={difficulty}
+            onChange={(e) => setDifficulty(e.target.value as Difficulty)}
+            disabled={running}
+          >
+            <option value="easy">Easy</option>
+            <option value="medium">Medium</option>
+            <option value="hard">Hard</option>
+          </select>
+        ) : (
+          <label style={styles.label}>
+            Measures
+            <input
+              type="number"
+              min={1}
+              max={8}
+              value={measures}
+              onChange={(e) => setMeasures(Math.max(1, parseInt(e.target.value, 10) || 1))}
+              style={styles.input}
+              disabled={running}
+            />
+          </label>
+        )}
+      </div>
+
+      <p style={styles.status}>
+        {practice?.phase === 'call' && 'Listen…'}
+        {practice?.phase === 'response' && 'Your turn: play it back'}
+        {!running && !result && 'Connect a MIDI keyboard, then press Start'}
+        {result && `Score ${Math.round(result.score)} · pitch ${Math.round(result.pitch_accuracy)}% · rhythm ${Math.round(result.rhythm_accuracy)}%`}
+      </p>
+      {error && <p style={styles.error}>{error}</p>}
+
+      {result && (
+        <table style={styles.table}>
+          <thead>
+            <tr>
+              {['#', 'Expected', 'Played', 'Ticks off', ''].map((h) => (
+                <th key={h} style={styles.th}>
+                  {h}
+                </th>
+              ))}
+            </tr>
+          </thead>
+          <tbody>
+            {result.notes.map((feedback) => (
+              <FeedbackRow key={feedback.index} feedback={feedback} />
+            ))}
+          </tbody>
+        </table>
+      )}
+      {result && result.extra_notes > 0 && <p style={styles.status}>{result.extra_notes} extra note(s)</p>}
+
+      <div style={styles.actions}>
+        {running ? (
+          <button style={styles.secondary} onClick={stopCallResponse}>
+            Stop
+          </button>
+        ) : (
+          <button style={styles.primary} onClick={start}>
+            {result ? 'Again' : 'Start'}
+          </button>
+        )}
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    left: '20px',
+    bottom: '20px',
+    width: '340px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  select: {
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  label: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '6px',
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  input: {
+    width: '48px',
+    padding: '4px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+  },
+  status: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#aaa',
+  },
+  error: {
+    margin: '6px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  table: {
+    width: '100%',
+    marginTop: '8px',
+    borderCollapse: 'collapse',
+    fontSize: '13px',
+    color: '#eee',
+  },
+  th: {
+    padding: '4px 6px',
+    color: '#888',
+    fontWeight: 'normal',
+    textAlign: 'left',
+    borderBottom: '1px solid #0f3460',
+  },
+  td: {
+    padding: '4px 6px',
+  },
+  actions: {
+    display: 'flex',
+    justifyContent: 'flex-end',
+    marginTop: '14px',
+  },
+  primary: {
+    padding: '6px 14px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+  secondary: {
+    padding: '6px 14px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+}
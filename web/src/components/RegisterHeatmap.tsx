@@ -0,0 +1,133 @@
+import React, { useEffect, useRef, useState } from 'react'
+import { TOGGLE_REGISTER_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { midiToNoteName } from '../wasm'
+import type { PitchHistogram } from '../wasm/types'
+
+const STRIP_HEIGHT = 72
+const LABEL_WIDTH = 40
+
+// View → Register Heatmap: where the music sits in register, one column per measure
+export function RegisterHeatmap() {
+  const { mozart, notes, timeSignature, currentTick, seekTo, getPitchHistogram } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [histogram, setHistogram] = useState<PitchHistogram | null>(null)
+  const canvasRef = useRef<HTMLCanvasElement>(null)
+
+  useEffect(() => {
+    const toggle = () => setOpen((o) => !o)
+    window.addEventListener(TOGGLE_REGISTER_EVENT, toggle)
+    return () => window.removeEventListener(TOGGLE_REGISTER_EVENT, toggle)
+  }, [])
+
+  useEffect(() => {
+    if (!open || !mozart) return
+    setHistogram(getPitchHistogram(mozart.ticksPerMeasure()))
+  }, [open, mozart, notes, timeSignature, getPitchHistogram])
+
+  useEffect(() => {
+    const canvas = canvasRef.current
+    const ctx = canvas?.getContext('2d')
+    if (!canvas || !ctx || !histogram) return
+
+    const width = canvas.clientWidth
+    canvas.width = width
+    canvas.height = STRIP_HEIGHT
+    ctx.fillStyle = '#1a1a2e'
+    ctx.fillRect(0, 0, width, STRIP_HEIGHT)
+
+    const { buckets, bucket_ticks, lowest_pitch, highest_pitch } = histogram
+    if (buckets.length === 0) return
+    const rows = highest_pitch - lowest_pitch + 1
+    const columnWidth = (width - LABEL_WIDTH) / buckets.length
+    const rowHeight = STRIP_HEIGHT / rows
+    const rowTop = (pitch: number) => (highest_pitch - pitch) * rowHeight
+
+    // Brighter where a pitch sounds for more of the measure
+    buckets.forEach((bucket, i) => {
+      const x = LABEL_WIDTH + i * columnWidth
+      bucket.ticks.forEach((ticks, row) => {
+        if (ticks === 0) return
+        ctx.fillStyle = `rgba(233, 69, 96, ${Math.min(1, 0.15 + ticks / bucket_ticks)})`
+        ctx.fillRect(x, rowTop(lowest_pitch + row), Math.ceil(columnWidth), Math.ceil(rowHeight))
+      })
+    })
+
+    // Mean pitch contour
+    ctx.strokeStyle = '#eee'
+    ctx.lineWidth = 1.5
+    ctx.beginPath()
+    let drawing = false
+    buckets.forEach((bucket, i) => {
+      if (bucket.mean_pitch === null) {
+        drawing = false
+        return
+      }
+      const x = LABEL_WIDTH + (i + 0.5) * columnWidth
+      const y = rowTop(bucket.mean_pitch) + rowHeight / 2
+      if (drawing) ctx.lineTo(x, y)
+      else ctx.moveTo(x, y)
+      drawing = true
+    })
+    ctx.stroke()
+
+    const playheadX = LABEL_WIDTH + (currentTick / bucket_ticks) * columnWidth
+    ctx.fillStyle = '#4a90d9'
+    ctx.fillRect(playheadX, 0, 2, STRIP_HEIGHT)
+
+    ctx.fillStyle = '#888'
+    ctx.font = '10px sans-serif'
+    ctx.fillText(midiToNoteName(highest_pitch), 4, 10)
+    ctx.fillText(midiToNoteName(lowest_pitch), 4, STRIP_HEIGHT - 4)
+  }, [histogram, currentTick])
+
+  if (!open || !mozart) return null
+
+  const handleClick = (e: React.MouseEvent<HTMLCanvasElement>) => {
+    if (!histogram || histogram.buckets.length === 0) return
+    const rect = e.currentTarget.getBoundingClientRect()
+    const column = Math.floor(((e.clientX - rect.left - LABEL_WIDTH) / (rect.width - LABEL_WIDTH)) * histogram.buckets.length)
+    const bucket = histogram.buckets[column]
+    if (bucket) seekTo(bucket.start_tick)
+  }
+
+  return (
+    <div style={styles.strip} aria-label="Register heatmap">
+      <canvas ref={canvasRef} style={styles.canvas} onClick={handleClick} title="Click a measure to move the playhead" />
+      {histogram?.buckets.length === 0 && <span style={styles.empty}>No notes yet</span>}
+      <button style={styles.close} onClick={() => setOpen(false)} title="Close">
+        ×
+      </button>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  strip: {
+    position: 'relative',
+    display: 'flex',
+    background: '#1a1a2e',
+    borderBottom: '1px solid #0f3460',
+  },
+  canvas: {
+    flex: 1,
+    height: `${STRIP_HEIGHT}px`,
+    cursor: 'pointer',
+  },
+  empty: {
+    position: 'absolute',
+    left: `${LABEL_WIDTH}px`,
+    top: '50%',
+    transform: 'translateY(-50%)',
+    color: '#888',
+    fontSize: '12px',
+  },
+  close: {
+    alignSelf: 'flex-start',
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    fontSize: '18px',
+    cursor: 'pointer',
+  },
+}
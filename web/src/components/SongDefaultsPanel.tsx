@@ -0,0 +1,197 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_SONG_DEFAULTS_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { getPitchRangePresets } from '../wasm'
+import type { SongDefaults } from '../wasm/types'
+
+const ROOTS = ['C', 'C#', 'D', 'Eb', 'E', 'F', 'F#', 'G', 'Ab', 'A', 'Bb', 'B']
+const METERS = ['2/4', '3/4', '4/4', '5/4', '6/8', '7/8', '9/8', '12/8']
+
+// File → New Song Defaults: what File → New Song starts with
+export function SongDefaultsPanel() {
+  const { isWasmLoaded, newSongDefaults, setNewSongDefaults } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [draft, setDraft] = useState<SongDefaults>(newSongDefaults)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    const show = () => {
+      setDraft(useMozartStore.getState().newSongDefaults)
+      setError(null)
+      setOpen(true)
+    }
+    window.addEventListener(OPEN_SONG_DEFAULTS_EVENT, show)
+    return () => window.removeEventListener(OPEN_SONG_DEFAULTS_EVENT, show)
+  }, [])
+
+  if (!open || !isWasmLoaded) return null
+
+  const [root, ...mode] = draft.key.split(' ')
+  const update = (change: Partial<SongDefaults>) => setDraft({ ...draft, ...change })
+  const save = () => {
+    try {
+      setNewSongDefaults(draft)
+      setOpen(false)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="New song defaults">
+      <div style={styles.header}>
+        <h3 style={styles.title}>New Song Defaults</h3>
+        <button style={styles.close} onClick={() => setOpen(false)} title="Close">
+          ×
+        </button>
+      </div>
+
+      <div style={styles.row}>
+        <label style={styles.label}>
+          Tempo
+          <input
+            type="number"
+            min={20}
+            max={300}
+            value={draft.tempo}
+            onChange={(e) => update({ tempo: parseInt(e.target.value, 10) || 120 })}
+            style={styles.input}
+          />
+        </label>
+        <label style={styles.label}>
+          Meter
+          <select style={styles.select} value={draft.time_signature} onChange={(e) => update({ time_signature: e.target.value })}>
+            {(METERS.includes(draft.time_signature) ? METERS : [draft.time_signature, ...METERS]).map((m) => (
+              <option key={m} value={m}>
+                {m}
+              </option>
+            ))}
+          </select>
+        </label>
+      </div>
+
+      <div style={styles.row}>
+        <label style={styles.label}>
+          Key
+          <select style={styles.select} value={root} onChange={(e) => update({ key: `${e.target.value} ${mode.join(' ')}` })}>
+            {(ROOTS.includes(root) ? ROOTS : [root, ...ROOTS]).map((r) => (
+              <option key={r} value={r}>
+                {r}
+              </option>
+            ))}
+          </select>
+        </label>
+        <select style={styles.select} value={mode.join(' ').toLowerCase()} onChange={(e) => update({ key: `${root} ${e.target.value}` })}>
+          <option value="major">Major</option>
+          <option value="minor">Minor</option>
+        </select>
+      </div>
+
+      <div style={styles.row}>
+        <label style={styles.label}>
+          Instrument
+          <select style={styles.select} value={draft.instrument} onChange={(e) => update({ instrument: e.target.value })}>
+            <option value="">Any</option>
+            {getPitchRangePresets().map((preset) => (
+              <option key={preset.name} value={preset.name}>
+                {preset.name}
+              </option>
+            ))}
+          </select>
+        </label>
+        <label style={styles.label}>
+          Pickup beats
+          <input
+            type="number"
+            min={0}
+            value={draft.pickup_beats}
+            onChange={(e) => update({ pickup_beats: Math.max(0, parseInt(e.target.value, 10) || 0) })}
+            style={styles.input}
+          />
+        </label>
+      </div>
+
+      {error && <p style={styles.error}>{error}</p>}
+      <div style={styles.row}>
+        <button style={styles.primary} onClick={save}>
+          Save
+        </button>
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '340px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  label: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '6px',
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  select: {
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  input: {
+    width: '48px',
+    padding: '4px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
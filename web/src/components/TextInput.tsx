@@ -1,20 +1,56 @@
-import React, { useState } from 'react'
+import React, { useMemo, useState } from 'react'
 import { useMozartStore } from '../store'
+import { getNotationGrammar, parseMelodyToken } from '../wasm'
+import type { TokenSuggestion } from '../wasm/types'
+
+const SHOWN_COMPLETIONS = 5
 
 export function TextInput() {
   const [input, setInput] = useState('')
   const [error, setError] = useState('')
-  const { parseMelody, formatMelody, clearNotes } = useMozartStore()
+  // Live entry: each token is auditioned and staged as it's typed
+  const [live, setLive] = useState(false)
+  const [staged, setStaged] = useState<string[]>([])
+  const [focused, setFocused] = useState(false)
+  const { mozart, key, parseMelody, formatMelody, suggestNextTokens, clearNotes, playNotePreview } = useMozartStore()
+
+  // The help below is read from the parser's tables, so it can't drift from what it accepts
+  const grammar = useMemo(() => {
+    try {
+      return mozart ? getNotationGrammar() : null
+    } catch {
+      return null
+    }
+  }, [mozart])
+  const tokenRule = (kind: string) => grammar?.tokens.find((t) => t.kind === kind)
+
+  // Completions for the token being typed, following the melody so far and the song's key
+  const completions = useMemo(() => {
+    if (!focused) return []
+    const text = live && staged.length > 0 ? `${staged.join(' ')} ${input}` : input
+    try {
+      return suggestNextTokens(text).slice(0, SHOWN_COMPLETIONS)
+    } catch {
+      // An earlier token is invalid; submitting reports it
+      return []
+    }
+  }, [focused, live, staged, input, key, suggestNextTokens])
 
   const handleSubmit = (e: React.FormEvent) => {
     e.preventDefault()
     setError('')
 
-    if (!input.trim()) return
+    const melody = [...(live ? staged : []), input.trim()].join(' ').trim()
+    if (!melody) return
 
     try {
-      clearNotes()
-      parseMelody(input.trim())
+      // Typing a melody replaces the notes, so no confirmation
+      clearNotes(true)
+      parseMelody(melody)
+      if (live) {
+        setStaged([])
+        setInput('')
+      }
     } catch (err) {
       setError(String(err))
     }
@@ -24,12 +60,73 @@ export function TextInput() {
     setError('')
     try {
       const text = formatMelody()
-      setInput(text)
+      if (live) {
+        setStaged(text.split(' ').filter(Boolean))
+        setInput('')
+      } else {
+        setInput(text)
+      }
     } catch (err) {
       setError(String(err))
     }
   }
 
+  // In live entry, a space finishes a token: play it and stage it, or keep it to fix
+  const handleChange = (value: string) => {
+    if (!live || !/\s$/.test(value)) {
+      setInput(value)
+      return
+    }
+    const token = value.trim()
+    if (!token) return
+    try {
+      const parsed = parseMelodyToken(token)
+      if (parsed.pitch !== null) {
+        const seconds = mozart ? mozart.tickToSeconds(parsed.duration_ticks) : 0.3
+        // Rhythm hits sound at the song's rhythm pitch
+        const pitch = /^x/i.test(token) && mozart ? mozart.rhythmPitch : parsed.pitch
+        playNotePreview(pitch, 100, seconds)
+      }
+      setStaged([...staged, token])
+      setInput('')
+      setError('')
+    } catch (err) {
+      setInput(token)
+      setError(String(err))
+    }
+  }
+
+  // A completion replaces the partial token and finishes it (so live entry plays it)
+  const accept = (suggestion: TokenSuggestion) => {
+    handleChange(input.replace(/\S*$/, suggestion.text) + ' ')
+  }
+
+  // Tab takes the first completion; Backspace in an empty field takes back the last staged token
+  const handleKeyDown = (e: React.KeyboardEvent<HTMLInputElement>) => {
+    if (e.key === 'Tab' && !e.shiftKey && completions.length > 0) {
+      e.preventDefault()
+      accept(completions[0])
+      return
+    }
+    if (live && e.key === 'Backspace' && input === '' && staged.length > 0) {
+      e.preventDefault()
+      setStaged(staged.slice(0, -1))
+    }
+  }
+
+  const toggleLive = (enabled: boolean) => {
+    setLive(enabled)
+    setError('')
+    // Carry what's there over to the other mode
+    if (enabled) {
+      setStaged(input.split(/\s+/).filter(Boolean))
+      setInput('')
+    } else {
+      setInput([...staged, input.trim()].join(' ').trim())
+      setStaged([])
+    }
+  }
+
   return (
     <div style={styles.container}>
       <form onSubmit={handleSubmit} style={styles.form}>
@@ -38,11 +135,18 @@ export function TextInput() {
           <input
             type="text"
             value={input}
-            onChange={(e) => setInput(e.target.value)}
-            placeholder="e.g., C4q D4q E4q F4q G4h"
+            onChange={(e) => handleChange(e.target.value)}
+            onKeyDown={handleKeyDown}
+            onFocus={() => setFocused(true)}
+            onBlur={() => setFocused(false)}
+            placeholder={live ? 'Type a note and press space, e.g., C4q' : 'e.g., C4q D4q E4q F4q G4h'}
             style={styles.input}
           />
         </label>
+        <label style={styles.toggle} title="Hear each note as you type it">
+          <input type="checkbox" checked={live} onChange={(e) => toggleLive(e.target.checked)} />
+          Live
+        </label>
         <button type="button" onClick={handleToText} style={styles.secondaryButton}>
           To Text
         </button>
@@ -51,23 +155,70 @@ export function TextInput() {
         </button>
       </form>
 
+      {completions.length > 0 && (
+        <div style={styles.completions} role="listbox" aria-label="Completions">
+          {completions.map((suggestion, i) => (
+            <button
+              key={suggestion.text}
+              type="button"
+              role="option"
+              aria-selected={i === 0}
+              title={suggestion.reason}
+              // Keep focus in the field
+              onMouseDown={(e) => e.preventDefault()}
+              onClick={() => accept(suggestion)}
+              style={i === 0 ? { ...styles.completion, ...styles.firstCompletion } : styles.completion}
+            >
+              {suggestion.text} <span style={styles.probability}>{Math.round(suggestion.probability * 100)}%</span>
+            </button>
+          ))}
+          <span style={styles.completionHint}>Tab to accept</span>
+        </div>
+      )}
+
+      {live && staged.length > 0 && (
+        <div style={styles.chips}>
+          {staged.map((token, i) => (
+            <span key={i} style={/^r/i.test(token) ? { ...styles.chip, ...styles.restChip } : styles.chip}>
+              {token}
+            </span>
+          ))}
+        </div>
+      )}
+
       {error && <p style={styles.error}>{error}</p>}
 
       <div style={styles.help}>
+        {grammar && (
+          <>
+            <p>
+              <strong>Format:</strong> {tokenRule('note')?.syntax} separated by spaces, e.g.,{' '}
+              {tokenRule('note')?.examples.join(' ')}
+            </p>
+            <p>
+              <strong>Notes:</strong> {grammar.pitch_classes.filter(([s]) => /^[A-G][#b]?$/.test(s)).map(([s]) => s).join(', ')}
+              {' '}({grammar.accidentals.map((a) => `${a.semitones > 0 ? 'sharps' : 'flats'} ${a.symbols.join(' ')}`).join(', ')}; octaves{' '}
+              {grammar.octaves.lowest_pitch} to {grammar.octaves.highest_pitch}, middle C = {grammar.octaves.middle_c})
+            </p>
+            <p>
+              <strong>Durations:</strong> {grammar.durations.map((d) => `${d.symbol}=${d.name}`).join(', ')} (default{' '}
+              {grammar.default_duration})
+            </p>
+            <p>
+              <strong>Dotted:</strong> Add {grammar.dot} after duration (e.g., q{grammar.dot} for dotted quarter)
+            </p>
+            <p>
+              <strong>Rests:</strong> {tokenRule('rest')?.syntax} (e.g., {tokenRule('rest')?.examples.join(' ')})
+            </p>
+            <p>
+              <strong>Rhythm:</strong> {tokenRule('rhythm_hit')?.syntax}, {tokenRule('rhythm_hit')?.description.toLowerCase()}{' '}
+              (e.g., {tokenRule('rhythm_hit')?.examples.join(' ')})
+            </p>
+          </>
+        )}
         <p>
-          <strong>Format:</strong> [Note][Octave][Duration] separated by spaces
-        </p>
-        <p>
-          <strong>Notes:</strong> C, C#, D, Eb, E, F, F#, G, Ab, A, Bb, B
-        </p>
-        <p>
-          <strong>Durations:</strong> w=whole, h=half, q=quarter, e=eighth, s=sixteenth
-        </p>
-        <p>
-          <strong>Dotted:</strong> Add . after duration (e.g., q. for dotted quarter)
-        </p>
-        <p>
-          <strong>Rests:</strong> R followed by duration (e.g., Rq for quarter rest)
+          <strong>Live:</strong> Each note plays as you finish it with a space; Backspace takes the last one back, Enter
+          writes them all
         </p>
       </div>
     </div>
@@ -122,6 +273,60 @@ const styles: Record<string, React.CSSProperties> = {
     cursor: 'pointer',
     fontSize: '14px',
   },
+  completions: {
+    display: 'flex',
+    flexWrap: 'wrap',
+    alignItems: 'center',
+    gap: '6px',
+    marginTop: '8px',
+  },
+  completion: {
+    padding: '2px 8px',
+    background: '#1a1a2e',
+    border: '1px solid #0f3460',
+    borderRadius: '4px',
+    color: '#ddd',
+    cursor: 'pointer',
+    fontSize: '13px',
+    fontFamily: 'monospace',
+  },
+  firstCompletion: {
+    borderColor: '#e94560',
+  },
+  probability: {
+    color: '#888',
+    fontSize: '11px',
+  },
+  completionHint: {
+    color: '#666',
+    fontSize: '12px',
+  },
+  toggle: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '4px',
+    color: '#aaa',
+    fontSize: '14px',
+    cursor: 'pointer',
+  },
+  chips: {
+    display: 'flex',
+    flexWrap: 'wrap',
+    gap: '6px',
+    marginTop: '8px',
+  },
+  chip: {
+    padding: '2px 8px',
+    background: '#0f3460',
+    borderRadius: '10px',
+    color: '#fff',
+    fontSize: '13px',
+    fontFamily: 'monospace',
+  },
+  restChip: {
+    background: '#1a1a2e',
+    color: '#888',
+  },
   error: {
     marginTop: '8px',
     color: '#ff6b6b',
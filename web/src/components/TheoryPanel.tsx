@@ -0,0 +1,262 @@
+import React, { useEffect, useMemo, useState } from 'react'
+import { OPEN_THEORY_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { getTheoryReference } from '../wasm'
+import type { TheoryReference, TheoryTopic } from '../wasm/types'
+
+const TOPICS: { topic: TheoryTopic; title: string }[] = [
+  { topic: 'intervals', title: 'Intervals' },
+  { topic: 'scales', title: 'Scales' },
+  { topic: 'chords', title: 'Chords' },
+  { topic: 'circle_of_fifths', title: 'Circle of Fifths' },
+  { topic: 'cadences', title: 'Cadences' },
+]
+const ROOTS = ['C', 'C#', 'D', 'Eb', 'E', 'F', 'F#', 'G', 'Ab', 'A', 'Bb', 'B']
+
+// Audition button for a scale or chord row
+function Play({ onPlay, title }: { onPlay: () => void; title: string }) {
+  return (
+    <button style={styles.play} onClick={onPlay} title={title}>
+      ▶
+    </button>
+  )
+}
+
+function Table({ head, rows }: { head: string[]; rows: React.ReactNode[][] }) {
+  return (
+    <table style={styles.table}>
+      <thead>
+        <tr>
+          {head.map((h) => (
+            <th key={h} style={styles.th}>
+              {h}
+            </th>
+          ))}
+        </tr>
+      </thead>
+      <tbody>
+        {rows.map((row, i) => (
+          <tr key={i}>
+            {row.map((cell, j) => (
+              <td key={j} style={styles.td}>
+                {cell}
+              </td>
+            ))}
+          </tr>
+        ))}
+      </tbody>
+    </table>
+  )
+}
+
+function ReferenceTable({ reference, root }: { reference: TheoryReference; root: string }) {
+  const { playScale, playChord } = useMozartStore.getState()
+  switch (reference.topic) {
+    case 'intervals':
+      return (
+        <Table
+          head={['Semitones', 'Interval', '', 'Example', 'Inverts to', 'Quality']}
+          rows={reference.entries.map((i) => [i.semitones, i.name, i.short_name, i.example, i.inversion, i.consonance])}
+        />
+      )
+    case 'scales':
+      return (
+        <Table
+          head={['Scale', 'Formula', 'Steps', 'From C', '']}
+          rows={reference.entries.map((s) => [
+            s.name,
+            s.formula.join(' '),
+            s.steps,
+            s.example.join(' '),
+            <Play onPlay={() => playScale(root, s.name)} title={`Play ${root} ${s.name.toLowerCase()}`} />,
+          ])}
+        />
+      )
+    case 'chords':
+      return (
+        <Table
+          head={['Chord', 'Symbol', 'Formula', 'On C', '']}
+          rows={reference.entries.map((c) => [
+            c.name,
+            `C${c.symbol}`,
+            c.formula.join(' '),
+            c.example.join(' '),
+            <Play onPlay={() => playChord(root, c.symbol)} title={`Play ${root}${c.symbol}`} />,
+          ])}
+        />
+      )
+    case 'circle_of_fifths':
+      return (
+        <Table
+          head={['Major', 'Relative minor', 'Signature', 'Also written']}
+          rows={reference.entries.map((k) => [
+            k.major,
+            k.minor.toLowerCase(),
+            k.signature.length ? k.signature.join(' ') : '—',
+            k.enharmonic ?? '',
+          ])}
+        />
+      )
+    case 'cadences':
+      return (
+        <Table
+          head={['Cadence', 'Major', 'C major', 'Minor', 'A minor', '']}
+          rows={reference.entries.map((c) => [
+            c.name,
+            c.major_numerals.join('–'),
+            c.major_example.join(' '),
+            c.minor_numerals.join('–'),
+            c.minor_example.join(' '),
+            c.description,
+          ])}
+        />
+      )
+  }
+}
+
+// Help → Theory Reference: tables generated by the core theory engine
+export function TheoryPanel() {
+  const [open, setOpen] = useState(false)
+  const [topic, setTopic] = useState<TheoryTopic>('intervals')
+  const [root, setRoot] = useState('C')
+
+  const reference = useMemo(() => (open ? getTheoryReference(topic) : null), [open, topic])
+
+  useEffect(() => {
+    const show = () => setOpen(true)
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') setOpen(false)
+    }
+    window.addEventListener(OPEN_THEORY_EVENT, show)
+    window.addEventListener('keydown', handleKeyDown)
+    return () => {
+      window.removeEventListener(OPEN_THEORY_EVENT, show)
+      window.removeEventListener('keydown', handleKeyDown)
+    }
+  }, [])
+
+  if (!open || !reference) return null
+
+  return (
+    <div style={styles.backdrop} onPointerDown={() => setOpen(false)}>
+      <div style={styles.panel} onPointerDown={(e) => e.stopPropagation()} role="dialog" aria-label="Theory reference">
+        <div style={styles.tabs}>
+          {TOPICS.map((t) => (
+            <button
+              key={t.topic}
+              style={{ ...styles.tab, ...(t.topic === topic ? styles.tabActive : {}) }}
+              onClick={() => setTopic(t.topic)}
+            >
+              {t.title}
+            </button>
+          ))}
+          {(topic === 'scales' || topic === 'chords') && (
+            <select style={styles.root} value={root} onChange={(e) => setRoot(e.target.value)} title="Audition root">
+              {ROOTS.map((r) => (
+                <option key={r} value={r}>
+                  {r}
+                </option>
+              ))}
+            </select>
+          )}
+          <button
+            style={{ ...styles.close, ...(topic === 'scales' || topic === 'chords' ? { marginLeft: '8px' } : {}) }}
+            onClick={() => setOpen(false)}
+            title="Close"
+          >
+            ×
+          </button>
+        </div>
+        <div style={styles.body}>
+          <ReferenceTable reference={reference} root={root} />
+        </div>
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  backdrop: {
+    position: 'fixed',
+    inset: 0,
+    display: 'flex',
+    justifyContent: 'center',
+    alignItems: 'flex-start',
+    paddingTop: '8vh',
+    background: 'rgba(0, 0, 0, 0.4)',
+    zIndex: 200,
+  },
+  panel: {
+    width: 'min(820px, 94vw)',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    overflow: 'hidden',
+  },
+  tabs: {
+    display: 'flex',
+    gap: '2px',
+    padding: '6px',
+    borderBottom: '1px solid #0f3460',
+  },
+  tab: {
+    padding: '6px 12px',
+    background: 'transparent',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#aaa',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+  tabActive: {
+    background: '#0f3460',
+    color: '#eee',
+  },
+  root: {
+    marginLeft: 'auto',
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  close: {
+    marginLeft: 'auto',
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  body: {
+    maxHeight: '70vh',
+    overflowY: 'auto',
+    padding: '8px 12px 12px',
+  },
+  table: {
+    width: '100%',
+    borderCollapse: 'collapse',
+    fontSize: '13px',
+    color: '#eee',
+  },
+  th: {
+    padding: '6px 8px',
+    color: '#888',
+    fontWeight: 'normal',
+    textAlign: 'left',
+    borderBottom: '1px solid #0f3460',
+  },
+  td: {
+    padding: '5px 8px',
+    borderBottom: '1px solid #1a1a2e',
+  },
+  play: {
+    background: 'none',
+    border: 'none',
+    color: '#e94560',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
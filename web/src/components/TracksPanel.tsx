@@ -0,0 +1,229 @@
+import React, { useEffect, useState } from 'react'
+import { TOGGLE_TRACKS_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import type { Instrument } from '../wasm/types'
+
+const INSTRUMENTS: Instrument[] = ['synth', 'piano', 'organ', 'strings', 'flute']
+
+// View → Tracks: each voice as a layer with its own sound, volume, mute, and solo
+export function TracksPanel() {
+  const {
+    isWasmLoaded,
+    notes,
+    tracks,
+    activeTrack,
+    instrument,
+    addTrack,
+    setActiveTrack,
+    removeTrack,
+    renameTrack,
+    setTrackInstrument,
+    setTrackVolume,
+    muteTrack,
+    soloTrack,
+  } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    const toggle = () => setOpen((o) => !o)
+    window.addEventListener(TOGGLE_TRACKS_EVENT, toggle)
+    return () => window.removeEventListener(TOGGLE_TRACKS_EVENT, toggle)
+  }, [])
+
+  if (!open || !isWasmLoaded) return null
+
+  // Voices with notes but no track yet get one the first time they are changed
+  const voices = [...new Set([...tracks.map((t) => t.voice), ...notes.map((n) => n.voice ?? 0)])].sort((a, b) => a - b)
+  const soloing = tracks.some((t) => t.solo)
+  const attempt = (action: () => void) => {
+    try {
+      action()
+      setError(null)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Tracks">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Tracks</h3>
+        <button style={styles.close} onClick={() => setOpen(false)} title="Close">
+          ×
+        </button>
+      </div>
+
+      {voices.length === 0 && <p style={styles.empty}>No notes yet. Add a track to write a layer of its own.</p>}
+      {voices.map((voice) => {
+        const track = tracks.find((t) => t.voice === voice)
+        const count = notes.filter((n) => (n.voice ?? 0) === voice).length
+        const audible = soloing ? track?.solo : !track?.mute
+        return (
+          <div key={voice} style={{ ...styles.row, opacity: audible ? 1 : 0.5 }}>
+            <button
+              style={{ ...styles.toggle, ...(voice === activeTrack ? styles.active : {}) }}
+              onClick={() => setActiveTrack(voice)}
+              title="Draw notes into this track"
+            >
+              ✎
+            </button>
+            <input
+              key={track?.name}
+              defaultValue={track?.name ?? `Voice ${voice + 1}`}
+              onBlur={(e) => {
+                const name = e.target.value.trim()
+                if (name && name !== (track?.name ?? `Voice ${voice + 1}`)) attempt(() => renameTrack(voice, name))
+              }}
+              style={styles.name}
+              title={`Voice ${voice + 1}, ${count} notes`}
+            />
+            <select
+              value={track?.instrument ?? ''}
+              onChange={(e) => setTrackInstrument(voice, (e.target.value || null) as Instrument | null)}
+              style={styles.select}
+              title="Sound this track plays with"
+            >
+              <option value="">Song ({instrument})</option>
+              {INSTRUMENTS.map((name) => (
+                <option key={name} value={name}>
+                  {name[0].toUpperCase() + name.slice(1)}
+                </option>
+              ))}
+            </select>
+            <input
+              type="range"
+              min={0}
+              max={127}
+              value={track?.volume ?? 100}
+              onChange={(e) => setTrackVolume(voice, Number(e.target.value))}
+              style={styles.volume}
+              title={`Volume ${track?.volume ?? 100}`}
+            />
+            <button
+              style={{ ...styles.toggle, ...(track?.mute ? styles.active : {}) }}
+              onClick={() => muteTrack(voice, !track?.mute)}
+              title="Mute"
+            >
+              M
+            </button>
+            <button
+              style={{ ...styles.toggle, ...(track?.solo ? styles.active : {}) }}
+              onClick={() => soloTrack(voice, !track?.solo)}
+              title="Solo"
+            >
+              S
+            </button>
+            <button
+              style={styles.close}
+              onClick={() => {
+                if (count === 0 || window.confirm(`Remove this track and its ${count} notes?`)) attempt(() => removeTrack(voice))
+              }}
+              title="Remove track"
+            >
+              ×
+            </button>
+          </div>
+        )
+      })}
+
+      {error && <p style={styles.error}>{error}</p>}
+      <div style={styles.row}>
+        <button style={styles.primary} onClick={() => attempt(() => addTrack(''))}>
+          Add Track
+        </button>
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '440px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  name: {
+    width: '110px',
+    padding: '4px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  select: {
+    padding: '4px 6px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    fontSize: '13px',
+  },
+  volume: {
+    width: '80px',
+  },
+  toggle: {
+    width: '24px',
+    padding: '2px 0',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#aaa',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+  active: {
+    background: '#e94560',
+    color: '#fff',
+  },
+  empty: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#888',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
@@ -1,8 +1,13 @@
-import React from 'react'
+import React, { useMemo } from 'react'
 import { useMozartStore } from '../store'
+import { runCommand } from '../commands'
+import type { FollowMode, Instrument, SongInfo } from '../wasm/types'
 
 export function Transport() {
   const {
+    mozart,
+    notes,
+    key,
     playbackState,
     tempo,
     timeSignature,
@@ -13,8 +18,21 @@ export function Transport() {
     stop,
     setTempo,
     setTimeSignature,
+    instrument,
+    setInstrument,
     toggleMetronome,
-    clearNotes,
+    polyrhythm,
+    polyrhythmSound,
+    setPolyrhythm,
+    setPolyrhythmSound,
+    followMode,
+    setFollowMode,
+    playbackRate,
+    setPlaybackRate,
+    preventSleep,
+    setPreventSleep,
+    audioError,
+    dismissAudioError,
   } = useMozartStore()
 
   const handleTempoChange = (e: React.ChangeEvent<HTMLInputElement>) => {
@@ -25,12 +43,23 @@ export function Transport() {
     setTimeSignature(e.target.value)
   }
 
-  // Calculate beat position for display
-  const ticksPerBeat = 480
-  const beat = Math.floor(currentTick / ticksPerBeat) + 1
-  const ticksPerMeasure = ticksPerBeat * timeSignature.numerator
-  const measure = Math.floor(currentTick / ticksPerMeasure) + 1
-  const beatInMeasure = ((beat - 1) % timeSignature.numerator) + 1
+  // Position as "measure.beat.tick | m:ss", computed by the core (honors tempo changes)
+  const positionDisplay = mozart?.formatTransportTime(currentTick) ?? '1.1.0 | 0:00'
+
+  // Range, density, and chromaticism from SongInfo; recomputed when the notes or key change
+  const info = useMemo<SongInfo | null>(
+    () => (mozart ? JSON.parse(mozart.getSongInfoJson()) : null),
+    [mozart, notes, key],
+  )
+  const stats =
+    info?.lowest_pitch && info.highest_pitch
+      ? `${info.lowest_pitch}–${info.highest_pitch} · ${info.notes_per_measure.toFixed(1)} notes/measure · ${info.out_of_key_count} out of key`
+      : null
+
+  // A speed typed in through Playback → Practice Speed… joins the presets
+  const rates = PLAYBACK_RATES.includes(playbackRate)
+    ? PLAYBACK_RATES
+    : [...PLAYBACK_RATES, playbackRate].sort((a, b) => a - b)
 
   return (
     <div style={styles.transport}>
@@ -65,9 +94,45 @@ export function Transport() {
           Metro
         </button>
 
+        <select
+          value={polyrhythm ? `${polyrhythm.beats}:${polyrhythm.against}` : ''}
+          onChange={(e) => setPolyrhythm(e.target.value || null)}
+          style={styles.select}
+          title="Polyrhythm click layer (clicks over main beats)"
+        >
+          <option value="">Poly off</option>
+          {POLYRHYTHMS.map((p) => (
+            <option key={p} value={p}>
+              {p.replace(':', ' over ')}
+            </option>
+          ))}
+        </select>
+        {polyrhythm && (
+          <>
+            <select
+              value={polyrhythmSound.frequency}
+              onChange={(e) => setPolyrhythmSound({ frequency: Number(e.target.value) })}
+              style={styles.select}
+              title="Polyrhythm click sound"
+            >
+              <option value={1500}>High</option>
+              <option value={600}>Low</option>
+            </select>
+            <input
+              type="range"
+              min="0"
+              max="1"
+              step="0.05"
+              value={polyrhythmSound.volume}
+              onChange={(e) => setPolyrhythmSound({ volume: Number(e.target.value) })}
+              title="Polyrhythm volume"
+            />
+          </>
+        )}
+
         <button
           style={styles.clearButton}
-          onClick={clearNotes}
+          onClick={() => runCommand('edit.clearNotes')}
         >
           Clear
         </button>
@@ -76,10 +141,20 @@ export function Transport() {
       <div style={styles.position}>
         <span style={styles.positionLabel}>Position:</span>
         <span style={styles.positionValue}>
-          {measure}:{beatInMeasure}
+          {positionDisplay}
         </span>
+        {stats && <span style={styles.stats}>{stats}</span>}
       </div>
 
+      {audioError && (
+        <div style={styles.audioError} title={`Audio context ${audioError.state}`}>
+          <span>{audioError.message}</span>
+          <button style={styles.dismissButton} onClick={dismissAudioError} title="Dismiss">
+            ×
+          </button>
+        </div>
+      )}
+
       <div style={styles.settings}>
         <label style={styles.label}>
           <span>Tempo:</span>
@@ -94,6 +169,58 @@ export function Transport() {
           <span>BPM</span>
         </label>
 
+        <label style={styles.label} title="Practice speed; the song's tempo stays as it is">
+          <span>Speed:</span>
+          <select
+            value={playbackRate}
+            onChange={(e) => setPlaybackRate(Number(e.target.value))}
+            style={styles.select}
+          >
+            {rates.map((rate) => (
+              <option key={rate} value={rate}>
+                {rate}×
+              </option>
+            ))}
+          </select>
+        </label>
+
+        <label style={styles.label} title="Sound the song plays with, saved with it">
+          <span>Sound:</span>
+          <select
+            value={instrument}
+            onChange={(e) => setInstrument(e.target.value as Instrument)}
+            style={styles.select}
+          >
+            {INSTRUMENTS.map((name) => (
+              <option key={name} value={name}>
+                {name[0].toUpperCase() + name.slice(1)}
+              </option>
+            ))}
+          </select>
+        </label>
+
+        <label style={styles.label}>
+          <span>Follow:</span>
+          <select
+            value={followMode}
+            onChange={(e) => setFollowMode(e.target.value as FollowMode)}
+            style={styles.select}
+          >
+            <option value="page-flip">Page</option>
+            <option value="continuous">Scroll</option>
+            <option value="off">Off</option>
+          </select>
+        </label>
+
+        <label style={styles.label} title="Keep the screen and machine awake while playing">
+          <input
+            type="checkbox"
+            checked={preventSleep}
+            onChange={(e) => setPreventSleep(e.target.checked)}
+          />
+          <span>Stay awake</span>
+        </label>
+
         <label style={styles.label}>
           <span>Time:</span>
           <select
@@ -101,6 +228,7 @@ export function Transport() {
             onChange={handleTimeSignatureChange}
             style={styles.select}
           >
+            <option value="1/4">1/4</option>
             <option value="2/4">2/4</option>
             <option value="3/4">3/4</option>
             <option value="4/4">4/4</option>
@@ -113,6 +241,9 @@ export function Transport() {
             <option value="9/8">9/8</option>
             <option value="11/8">11/8</option>
             <option value="12/8">12/8</option>
+            <option value="7/16">7/16</option>
+            <option value="15/16">15/16</option>
+            <option value="21/32">21/32</option>
           </select>
         </label>
       </div>
@@ -120,6 +251,10 @@ export function Transport() {
   )
 }
 
+const POLYRHYTHMS = ['3:2', '2:3', '4:3', '3:4', '5:4']
+const PLAYBACK_RATES = [0.25, 0.5, 0.75, 1, 1.25, 1.5, 2]
+const INSTRUMENTS: Instrument[] = ['synth', 'piano', 'organ', 'strings', 'flute']
+
 const styles: Record<string, React.CSSProperties> = {
   transport: {
     display: 'flex',
@@ -184,6 +319,28 @@ const styles: Record<string, React.CSSProperties> = {
     fontFamily: 'monospace',
     fontWeight: 'bold',
   },
+  stats: {
+    color: '#888',
+    fontSize: '12px',
+  },
+  audioError: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    padding: '6px 12px',
+    background: '#3a1020',
+    border: '1px solid #e94560',
+    borderRadius: '4px',
+    color: '#e94560',
+    fontSize: '12px',
+  },
+  dismissButton: {
+    background: 'none',
+    border: 'none',
+    color: '#e94560',
+    cursor: 'pointer',
+    fontSize: '14px',
+  },
   settings: {
     display: 'flex',
     gap: '16px',
@@ -1,9 +1,14 @@
-import React, { useState } from 'react'
+import React, { useMemo, useState } from 'react'
 import { useMozartStore } from '../store'
+import { getPitchRangePresets, midiToNoteName, noteNameToMidi } from '../wasm'
+
+const CUSTOM_RANGE = '__custom'
 
 export function TransposePanel() {
-  const { key, setKey, transposeChromatic, transposeDiatonic, invert, notes } = useMozartStore()
+  const { key, setKey, transposeChromatic, transposeDiatonic, invert, notes, isWasmLoaded } = useMozartStore()
+  const { pitchRange, setPitchRange, outOfRangeNotes, rangeWarnings, foldIntoRange } = useMozartStore()
   const [keepOriginal, setKeepOriginal] = useState(false)
+  const rangePresets = useMemo(() => (isWasmLoaded ? getPitchRangePresets() : []), [isWasmLoaded])
 
   // Parse current key into root and scale type
   const keyParts = key.split(' ')
@@ -51,6 +56,29 @@ export function TransposePanel() {
     setKey(`${currentRoot} ${e.target.value}`)
   }
 
+  const handleRangeChange = (e: React.ChangeEvent<HTMLSelectElement>) => {
+    if (e.target.value !== CUSTOM_RANGE) {
+      setPitchRange(e.target.value || null)
+      return
+    }
+    const text = window.prompt('Lowest and highest note (e.g., G3 C6):', 'C3 C6')
+    const [low, high] = (text ?? '').split(/[\s,-]+/).filter(Boolean)
+    try {
+      if (low && high) setPitchRange({ low: noteNameToMidi(low), high: noteNameToMidi(high) })
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+
+  const handleFold = () => {
+    if (!pitchRange) return
+    try {
+      foldIntoRange(pitchRange.low, pitchRange.high)
+    } catch (err) {
+      window.alert(String(err))
+    }
+  }
+
   const handleChromatic = (e: React.ChangeEvent<HTMLSelectElement>) => {
     const value = parseInt(e.target.value)
     if (!isNaN(value)) {
@@ -94,6 +122,41 @@ export function TransposePanel() {
         </select>
       </div>
 
+      <div style={styles.section}>
+        <h3 style={styles.title}>Range</h3>
+        <select
+          value={pitchRange ? pitchRange.name || CUSTOM_RANGE : ''}
+          onChange={handleRangeChange}
+          style={styles.selectWide}
+        >
+          <option value="">Any</option>
+          {rangePresets.map((r) => (
+            <option key={r.name} value={r.name}>
+              {r.name} ({midiToNoteName(r.low)}–{midiToNoteName(r.high)})
+            </option>
+          ))}
+          <option value={CUSTOM_RANGE}>
+            {pitchRange && !pitchRange.name
+              ? `Custom (${midiToNoteName(pitchRange.low)}–${midiToNoteName(pitchRange.high)})`
+              : 'Custom…'}
+          </option>
+        </select>
+        {outOfRangeNotes.length > 0 && (
+          <span style={styles.warning} title={rangeWarnings.map((w) => w.message).join('\n') || undefined}>
+            {outOfRangeNotes.length} out of range
+          </span>
+        )}
+        {pitchRange && outOfRangeNotes.length > 0 && (
+          <button
+            style={styles.button}
+            onClick={handleFold}
+            title="Move the out-of-range notes in by octaves"
+          >
+            Fold In
+          </button>
+        )}
+      </div>
+
       <div style={styles.section}>
         <h3 style={styles.title}>Chromatic</h3>
         <select onChange={handleChromatic} style={styles.select} defaultValue="">
@@ -190,6 +253,10 @@ const styles: Record<string, React.CSSProperties> = {
     cursor: 'pointer',
     fontSize: '13px',
   },
+  warning: {
+    color: '#f5a623',
+    fontSize: '13px',
+  },
   checkbox: {
     display: 'flex',
     alignItems: 'center',
@@ -0,0 +1,139 @@
+import React from 'react'
+import { useMozartStore } from '../store'
+
+// Coaching card for the onboarding tutorial; steps are validated by the core
+export function TutorialPanel() {
+  const { tutorial, tutorialNext, tutorialSkip, stopTutorial } = useMozartStore()
+  if (!tutorial) return null
+
+  const finished = tutorial.step === null
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Tutorial">
+      <div style={styles.header}>
+        <span style={styles.progress}>
+          {Array.from({ length: tutorial.total }, (_, i) => (
+            <span
+              key={i}
+              style={{
+                ...styles.dot,
+                ...(i < tutorial.index ? styles.dotDone : {}),
+                ...(i === tutorial.index ? styles.dotCurrent : {}),
+              }}
+            />
+          ))}
+        </span>
+        <button style={styles.close} onClick={stopTutorial} title="Close the tutorial">
+          ×
+        </button>
+      </div>
+
+      <h3 style={styles.title}>{tutorial.title}</h3>
+      <p style={styles.instruction}>{tutorial.instruction}</p>
+
+      <div style={styles.actions}>
+        {finished ? (
+          <button style={styles.primary} onClick={stopTutorial}>
+            Done
+          </button>
+        ) : (
+          <>
+            <button style={styles.secondary} onClick={tutorialSkip}>
+              Skip
+            </button>
+            <button
+              style={{ ...styles.primary, ...(tutorial.completed ? {} : styles.disabled) }}
+              disabled={!tutorial.completed}
+              onClick={tutorialNext}
+              title={tutorial.completed ? undefined : 'Do the step first'}
+            >
+              {tutorial.completed ? 'Nice! Next' : 'Next'}
+            </button>
+          </>
+        )}
+      </div>
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '300px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #e94560',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  progress: {
+    display: 'flex',
+    gap: '6px',
+  },
+  dot: {
+    width: '8px',
+    height: '8px',
+    borderRadius: '50%',
+    background: '#0f3460',
+  },
+  dotDone: {
+    background: '#4ecca3',
+  },
+  dotCurrent: {
+    background: '#e94560',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  title: {
+    margin: '12px 0 6px',
+    fontSize: '16px',
+    color: '#eee',
+  },
+  instruction: {
+    margin: 0,
+    fontSize: '13px',
+    color: '#aaa',
+    lineHeight: 1.4,
+  },
+  actions: {
+    display: 'flex',
+    justifyContent: 'flex-end',
+    gap: '8px',
+    marginTop: '14px',
+  },
+  primary: {
+    padding: '6px 14px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+  secondary: {
+    padding: '6px 14px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '13px',
+  },
+  disabled: {
+    opacity: 0.5,
+    cursor: 'default',
+  },
+}
@@ -0,0 +1,172 @@
+import React, { useEffect, useState } from 'react'
+import { OPEN_VARIATIONS_EVENT } from '../commands'
+import { useMozartStore } from '../store'
+import { midiToNoteName } from '../wasm'
+import type { Variation } from '../wasm/types'
+
+const VARIATION_COUNT = 6
+
+// Edit → Variations: flip through candidate rewrites of the selected voice, hear them, keep one
+export function VariationPanel() {
+  const { mozart, generateVariations, auditionVariation, applyVariation, clearVariations } = useMozartStore()
+  const [open, setOpen] = useState(false)
+  const [seed, setSeed] = useState(1)
+  const [variations, setVariations] = useState<Variation[]>([])
+  const [index, setIndex] = useState(0)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    const show = () => setOpen(true)
+    window.addEventListener(OPEN_VARIATIONS_EVENT, show)
+    return () => window.removeEventListener(OPEN_VARIATIONS_EVENT, show)
+  }, [])
+
+  useEffect(() => {
+    if (!open) return
+    try {
+      setVariations(generateVariations(VARIATION_COUNT, { seed }))
+      setIndex(0)
+      setError(null)
+    } catch (err) {
+      setVariations([])
+      setError(String(err))
+    }
+  }, [open, seed, generateVariations])
+
+  if (!open || !mozart) return null
+
+  const close = () => {
+    clearVariations()
+    setOpen(false)
+  }
+
+  const apply = () => {
+    try {
+      applyVariation(index)
+      setOpen(false)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  const variation = variations[index]
+  const step = (delta: number) => setIndex((i) => (i + delta + variations.length) % variations.length)
+
+  return (
+    <div style={styles.panel} role="dialog" aria-label="Variations">
+      <div style={styles.header}>
+        <h3 style={styles.title}>Variations</h3>
+        <button style={styles.close} onClick={close} title="Close">
+          ×
+        </button>
+      </div>
+
+      {error && <p style={styles.error}>{error}</p>}
+      {variation && (
+        <>
+          <div style={styles.row}>
+            <button style={styles.button} onClick={() => step(-1)} title="Previous">
+              ‹
+            </button>
+            <span style={styles.count}>
+              {index + 1} of {variations.length}
+            </span>
+            <button style={styles.button} onClick={() => step(1)} title="Next">
+              ›
+            </button>
+          </div>
+          <div style={styles.description}>{variation.description}</div>
+          <div style={styles.notes}>{variation.notes.map((n) => midiToNoteName(n.pitch)).join(' ')}</div>
+          <div style={styles.row}>
+            <button style={styles.button} onClick={() => auditionVariation(index)} title="Play this variation">
+              ▶ Play
+            </button>
+            <button style={styles.button} onClick={() => setSeed((s) => s + 1)} title="Make a new set">
+              Shuffle
+            </button>
+            <button style={styles.primary} onClick={apply}>
+              Apply
+            </button>
+          </div>
+        </>
+      )}
+    </div>
+  )
+}
+
+const styles: Record<string, React.CSSProperties> = {
+  panel: {
+    position: 'fixed',
+    right: '20px',
+    bottom: '20px',
+    width: '340px',
+    padding: '16px',
+    background: '#16213e',
+    border: '1px solid #0f3460',
+    borderRadius: '6px',
+    boxShadow: '0 8px 24px rgba(0, 0, 0, 0.5)',
+    zIndex: 150,
+  },
+  header: {
+    display: 'flex',
+    justifyContent: 'space-between',
+    alignItems: 'center',
+  },
+  title: {
+    margin: 0,
+    fontSize: '16px',
+    color: '#eee',
+  },
+  close: {
+    background: 'none',
+    border: 'none',
+    color: '#888',
+    cursor: 'pointer',
+    fontSize: '18px',
+  },
+  row: {
+    display: 'flex',
+    alignItems: 'center',
+    gap: '8px',
+    marginTop: '12px',
+  },
+  count: {
+    color: '#aaa',
+    fontSize: '13px',
+  },
+  description: {
+    marginTop: '10px',
+    color: '#eee',
+    fontSize: '14px',
+  },
+  notes: {
+    marginTop: '4px',
+    color: '#aaa',
+    fontSize: '12px',
+    fontFamily: 'monospace',
+    overflowWrap: 'anywhere',
+  },
+  error: {
+    margin: '12px 0 0',
+    fontSize: '13px',
+    color: '#e94560',
+  },
+  button: {
+    padding: '4px 10px',
+    background: '#0f3460',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#eee',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+  primary: {
+    padding: '4px 10px',
+    background: '#e94560',
+    border: 'none',
+    borderRadius: '4px',
+    color: '#fff',
+    cursor: 'pointer',
+    fontSize: '12px',
+  },
+}
@@ -4,3 +4,15 @@ export { PianoRoll } from './PianoRoll'
 export { TextInput } from './TextInput'
 export { TransposePanel } from './TransposePanel'
 export { AccentEditor } from './AccentEditor'
+export { MenuBar } from './MenuBar'
+export { CommandPalette } from './CommandPalette'
+export { TutorialPanel } from './TutorialPanel'
+export { TheoryPanel } from './TheoryPanel'
+export { PracticePanel } from './PracticePanel'
+export { HarmonyPanel } from './HarmonyPanel'
+export { ModulationPanel } from './ModulationPanel'
+export { VariationPanel } from './VariationPanel'
+export { ContinuationPanel } from './ContinuationPanel'
+export { SongDefaultsPanel } from './SongDefaultsPanel'
+export { RegisterHeatmap } from './RegisterHeatmap'
+export { TracksPanel } from './TracksPanel'
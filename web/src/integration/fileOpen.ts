@@ -0,0 +1,110 @@
+// "Open with Mozart": file associations and share links
+//
+// When Mozart is installed as an app, the manifest registers it for
+// .mozart.json and .mid files (delivered through the launch queue) and for
+// web+mozart: links, which arrive as a `link` query parameter. Browsers only
+// allow custom schemes with the web+ prefix, so share links look like
+// web+mozart://open?url=https://example.com/song.mozart.json
+
+import { useMozartStore } from '../store'
+import type { Comparison, ComparisonMode, SimilarityReport, TempoAlignment } from '../wasm/types'
+
+const MIDI_EXTENSIONS = ['.mid', '.midi']
+
+// Minimal File Handling API surface (not yet in lib.dom)
+interface LaunchParams {
+  files: FileSystemFileHandle[]
+}
+declare global {
+  interface Window {
+    launchQueue?: { setConsumer(consumer: (params: LaunchParams) => void): void }
+  }
+}
+
+function isMidi(name: string): boolean {
+  const lower = name.toLowerCase()
+  return MIDI_EXTENSIONS.some((ext) => lower.endsWith(ext))
+}
+
+function baseName(name: string): string {
+  return name.replace(/\.(mozart\.json|json|midi?)$/i, '')
+}
+
+// Load a song file (.mozart.json or MIDI) into the editor, replacing the current song
+export async function openFile(file: File): Promise<void> {
+  const { loadFromJson, loadFromMidi } = useMozartStore.getState()
+  if (isMidi(file.name) || file.type === 'audio/midi') {
+    loadFromMidi(new Uint8Array(await file.arrayBuffer()), baseName(file.name))
+  } else {
+    loadFromJson(await file.text())
+  }
+}
+
+// Layer a song file onto the current song, on voices of its own; returns those voices
+export async function mergeFile(file: File, alignment: TempoAlignment): Promise<number[]> {
+  const { mergeSong } = useMozartStore.getState()
+  if (isMidi(file.name) || file.type === 'audio/midi') {
+    return mergeSong(new Uint8Array(await file.arrayBuffer()), alignment)
+  }
+  return mergeSong(await file.text(), alignment)
+}
+
+// Compare the current song's melody with a song file's
+export async function compareFile(file: File): Promise<SimilarityReport> {
+  const { compareSong } = useMozartStore.getState()
+  if (isMidi(file.name) || file.type === 'audio/midi') {
+    return compareSong(new Uint8Array(await file.arrayBuffer()))
+  }
+  return compareSong(await file.text())
+}
+
+// Play the current song against another version of it from a file
+export async function playComparisonFile(file: File, mode: ComparisonMode): Promise<Comparison> {
+  const { playComparison } = useMozartStore.getState()
+  if (isMidi(file.name) || file.type === 'audio/midi') {
+    return playComparison(new Uint8Array(await file.arrayBuffer()), mode)
+  }
+  return playComparison(await file.text(), mode)
+}
+
+// Load the file a share link points at
+async function openLink(link: string): Promise<void> {
+  const target = new URL(link.replace(/^web\+mozart:(\/\/)?/, 'https://mozart.invalid/')).searchParams.get('url')
+  if (!target) throw new Error(`Share link has no url: ${link}`)
+
+  const response = await fetch(target)
+  if (!response.ok) throw new Error(`Failed to fetch ${target}: ${response.status}`)
+  const name = new URL(target).pathname.split('/').pop() || 'song.mozart.json'
+  await openFile(new File([await response.blob()], name, { type: response.headers.get('content-type') ?? '' }))
+}
+
+// Open whatever the app was launched with (files or a share link)
+export function connectFileLaunch(): void {
+  const params = new URLSearchParams(window.location.search)
+  const link = params.get('link')
+  const { setStartupStage } = useMozartStore.getState()
+  if (link) {
+    setStartupStage('song')
+    openLink(link)
+      .catch((err) => console.error('Failed to open share link:', err))
+      .finally(() => setStartupStage('running'))
+    // Drop the parameter so a reload doesn't reopen the link over later edits
+    params.delete('link')
+    const query = params.toString()
+    history.replaceState(null, '', window.location.pathname + (query ? `?${query}` : ''))
+  }
+
+  window.launchQueue?.setConsumer(async ({ files }) => {
+    // A single song is open at a time; the last file wins
+    const handle = files[files.length - 1]
+    if (!handle) return
+    setStartupStage('song')
+    try {
+      await openFile(await handle.getFile())
+    } catch (err) {
+      console.error('Failed to open file:', err)
+    } finally {
+      setStartupStage('running')
+    }
+  })
+}
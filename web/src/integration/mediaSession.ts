@@ -0,0 +1,133 @@
+// OS media integration through the Media Session API
+//
+// Browsers forward hardware media keys and the system media controls
+// (MPRIS on Linux, SMTC on Windows, Now Playing on macOS, the lock screen on
+// mobile) to the handlers registered here, and show the song title and
+// playback state there.
+
+import { useMozartStore } from '../store'
+import type { PlaybackState } from '../store'
+
+const SEEK_STEP_SECONDS = 5
+
+// Browsers only hand the system controls to a page that is playing media
+// through an element, so a silent loop plays alongside the Web Audio output
+function createKeepAlive(): HTMLAudioElement {
+  const sampleRate = 8000
+  const samples = sampleRate // One second
+  const buffer = new ArrayBuffer(44 + samples)
+  const view = new DataView(buffer)
+  const writeString = (offset: number, text: string) =>
+    [...text].forEach((c, i) => view.setUint8(offset + i, c.charCodeAt(0)))
+
+  // 8-bit mono PCM WAV, where 128 is silence
+  writeString(0, 'RIFF')
+  view.setUint32(4, 36 + samples, true)
+  writeString(8, 'WAVEfmt ')
+  view.setUint32(16, 16, true)
+  view.setUint16(20, 1, true)
+  view.setUint16(22, 1, true)
+  view.setUint32(24, sampleRate, true)
+  view.setUint32(28, sampleRate, true)
+  view.setUint16(32, 1, true)
+  view.setUint16(34, 8, true)
+  writeString(36, 'data')
+  view.setUint32(40, samples, true)
+  new Uint8Array(buffer, 44).fill(128)
+
+  const audio = new Audio(URL.createObjectURL(new Blob([buffer], { type: 'audio/wav' })))
+  audio.loop = true
+  return audio
+}
+
+const SESSION_STATES: Record<PlaybackState, MediaSessionPlaybackState> = {
+  playing: 'playing',
+  paused: 'paused',
+  stopped: 'none',
+}
+
+// Register media key handlers and mirror the transport; returns a disconnect function
+export function connectMediaSession(): () => void {
+  if (!('mediaSession' in navigator)) return () => {}
+
+  const session = navigator.mediaSession
+  const store = useMozartStore.getState
+  const keepAlive = createKeepAlive()
+
+  const seekBy = (seconds: number) => {
+    const { mozart, currentTick, seekTo } = store()
+    if (!mozart) return
+    const target = Math.max(0, mozart.tickToSeconds(currentTick) + seconds)
+    seekTo(mozart.secondsToTick(target))
+  }
+
+  const handlers: [MediaSessionAction, MediaSessionActionHandler][] = [
+    ['play', () => store().play()],
+    ['pause', () => store().pause()],
+    ['stop', () => store().stop()],
+    ['seekbackward', (details) => seekBy(-(details.seekOffset ?? SEEK_STEP_SECONDS))],
+    ['seekforward', (details) => seekBy(details.seekOffset ?? SEEK_STEP_SECONDS)],
+    ['previoustrack', () => store().prevMarker()],
+    ['nexttrack', () => store().nextMarker()],
+    [
+      'seekto',
+      (details) => {
+        const { mozart, seekTo } = store()
+        if (mozart && details.seekTime !== undefined) seekTo(mozart.secondsToTick(details.seekTime))
+      },
+    ],
+  ]
+  for (const [action, handler] of handlers) {
+    try {
+      session.setActionHandler(action, handler)
+    } catch {
+      // Not every browser supports every action
+    }
+  }
+
+  let title: string | null = null
+  const updatePosition = () => {
+    const { mozart, currentTick } = store()
+    if (!mozart || !session.setPositionState) return
+    const duration = mozart.durationWithTempoMap()
+    session.setPositionState({
+      duration,
+      position: Math.min(mozart.tickToSeconds(currentTick), duration),
+      playbackRate: 1,
+    })
+  }
+
+  const unsubscribe = useMozartStore.subscribe((state, previous) => {
+    const songTitle = state.mozart?.title || 'Untitled'
+    if (songTitle !== title) {
+      title = songTitle
+      session.metadata = new MediaMetadata({ title: songTitle, artist: state.mozart?.composer ?? '', album: 'Mozart' })
+    }
+
+    if (state.playbackState !== previous.playbackState) {
+      session.playbackState = SESSION_STATES[state.playbackState]
+      if (state.playbackState === 'playing') {
+        keepAlive.play().catch(() => {})
+      } else {
+        keepAlive.pause()
+      }
+      updatePosition()
+    } else if (state.currentTick !== previous.currentTick && state.playbackState !== 'playing') {
+      // Seeks; while playing the OS extrapolates from the last position
+      updatePosition()
+    }
+  })
+
+  return () => {
+    unsubscribe()
+    keepAlive.pause()
+    URL.revokeObjectURL(keepAlive.src)
+    for (const [action] of handlers) {
+      try {
+        session.setActionHandler(action, null)
+      } catch {
+        // Unsupported action
+      }
+    }
+  }
+}
@@ -0,0 +1,94 @@
+// MIDI keyboard input through the Web MIDI API
+//
+// Note-ons from every connected input (including ones plugged in later) go
+// to the handler, and each held key sounds through the audio engine so the
+// player hears what they play. Outside practice, keys preview like clicked
+// piano keys, at the velocity they were played.
+
+import { useMozartStore } from '../store'
+
+type NoteOnHandler = (pitch: number, velocity: number) => void
+
+// Listen to all MIDI inputs; returns a disconnect function
+export function connectMidiInput(onNoteOn: NoteOnHandler, soundHeldKeys: boolean = true): () => void {
+  if (!('requestMIDIAccess' in navigator)) {
+    console.warn('Web MIDI is not supported in this browser')
+    return () => {}
+  }
+
+  let access: MIDIAccess | null = null
+  let disconnected = false
+  const held = new Set<number>()
+
+  const release = (pitch: number) => {
+    held.delete(pitch)
+    useMozartStore.getState().audioEngine?.stopNote(`midi-in-${pitch}`)
+  }
+
+  const handleMessage = (event: MIDIMessageEvent) => {
+    const data = event.data
+    if (!data || data.length < 3) return
+    const [status, pitch, velocity] = data
+    const command = status & 0xf0
+
+    if (command === 0x90 && velocity > 0) {
+      const audioEngine = soundHeldKeys ? useMozartStore.getState().audioEngine : null
+      if (audioEngine) {
+        audioEngine.resume()
+        audioEngine.startNote(`midi-in-${pitch}`, audioEngine.midiToFrequency(pitch), velocity)
+      }
+      held.add(pitch)
+      onNoteOn(pitch, velocity)
+    } else if (command === 0x80 || command === 0x90) {
+      // Note-off, or note-on with velocity 0
+      release(pitch)
+    }
+  }
+
+  const listen = () => {
+    access?.inputs.forEach((input) => {
+      input.onmidimessage = handleMessage
+    })
+  }
+
+  navigator
+    .requestMIDIAccess()
+    .then((granted) => {
+      if (disconnected) return
+      access = granted
+      access.onstatechange = listen
+      listen()
+    })
+    .catch((err) => console.warn('MIDI input unavailable:', err))
+
+  return () => {
+    disconnected = true
+    access?.inputs.forEach((input) => {
+      input.onmidimessage = null
+    })
+    if (access) access.onstatechange = null
+    held.forEach(release)
+  }
+}
+
+// Preview each key played through playNotePreview at its velocity. Only
+// connects when MIDI access was already granted (e.g. by the practice
+// panel), so it never prompts on its own.
+export function connectMidiPreview(): () => void {
+  if (!('requestMIDIAccess' in navigator) || !navigator.permissions) return () => {}
+
+  let disconnect: (() => void) | null = null
+  let cancelled = false
+  navigator.permissions
+    .query({ name: 'midi' as PermissionName })
+    .then((status) => {
+      if (cancelled || status.state !== 'granted') return
+      disconnect = connectMidiInput((pitch, velocity) => useMozartStore.getState().playNotePreview(pitch, velocity), false)
+    })
+    .catch(() => {})
+
+  return () => {
+    cancelled = true
+    disconnect?.()
+  }
+}
@@ -0,0 +1,59 @@
+// Sleep prevention through the Screen Wake Lock API
+//
+// While playing (and the preventSleep preference is on) a screen wake lock
+// keeps the OS from dimming or sleeping during long practice loops. The
+// browser drops the lock when the page is hidden, so it is requested again
+// when the page becomes visible.
+
+import { useMozartStore } from '../store'
+
+// Hold a wake lock whenever the store is playing; returns a disconnect function
+export function connectWakeLock(): () => void {
+  if (!('wakeLock' in navigator)) return () => {}
+
+  let sentinel: WakeLockSentinel | null = null
+  let pending = false
+
+  const wanted = () => {
+    const { playbackState, preventSleep } = useMozartStore.getState()
+    return preventSleep && playbackState === 'playing' && document.visibilityState === 'visible'
+  }
+
+  const update = async () => {
+    if (wanted() && !sentinel && !pending) {
+      pending = true
+      try {
+        sentinel = await navigator.wakeLock.request('screen')
+        sentinel.addEventListener('release', () => {
+          sentinel = null
+        })
+      } catch (err) {
+        // Denied (e.g., battery saver); playback carries on without it
+        console.warn('Wake lock unavailable:', err)
+      } finally {
+        pending = false
+      }
+      // State may have moved on while the request was in flight
+      if (!wanted()) update()
+    } else if (!wanted() && sentinel) {
+      const held = sentinel
+      sentinel = null
+      await held.release()
+    }
+  }
+
+  const unsubscribe = useMozartStore.subscribe((state, previous) => {
+    if (state.playbackState !== previous.playbackState || state.preventSleep !== previous.preventSleep) {
+      update()
+    }
+  })
+  const handleVisibility = () => update()
+  document.addEventListener('visibilitychange', handleVisibility)
+
+  return () => {
+    unsubscribe()
+    document.removeEventListener('visibilitychange', handleVisibility)
+    sentinel?.release()
+    sentinel = null
+  }
+}
@@ -1,30 +1,167 @@
 import { create } from 'zustand'
-import type { Mozart, Note } from '../wasm/types'
-import { createMozart, initWasm, loadMozartFromJson } from '../wasm'
-import { AudioEngine, Sequencer, Metronome } from '../audio'
+import type {
+  AccentPreset,
+  Annotation,
+  AppSettings,
+  AudioDiagnostics,
+  AudioWatchdog,
+  ChordCapture,
+  ChordSymbol,
+  AuditionNote,
+  CallResponseConfig,
+  CallResponseInfo,
+  CallResponsePhase,
+  CallResponseResult,
+  ChartChord,
+  ChordSuggestion,
+  CleanupReport,
+  Click,
+  ColorLabel,
+  Comparison,
+  ComparisonMode,
+  Continuation,
+  ContinuationConfig,
+  CounterpointConfig,
+  CounterpointReport,
+  DiatonicChord,
+  FoldedNote,
+  FollowMode,
+  GroovePreset,
+  GrooveTemplate,
+  HarmonizedSpan,
+  IndexedNote,
+  Instrument,
+  LossSummary,
+  Macro,
+  MacroLibrary,
+  MacroStep,
+  Marker,
+  MelodyConfig,
+  MelodyReport,
+  ModulationPlan,
+  Mozart,
+  Note,
+  Outcome,
+  Pattern,
+  PitchHistogram,
+  PitchRange,
+  PitchView,
+  Polyrhythm,
+  RangeWarning,
+  Reharmonization,
+  ReharmonizeConstraints,
+  RepairOptions,
+  RepeatStructure,
+  RepairReport,
+  ScaleDirection,
+  SimilarityReport,
+  SongChange,
+  SongDefaults,
+  SplitPiece,
+  StartupDiagnostics,
+  StartupSentinel,
+  StartupStage,
+  StrumDirection,
+  Take,
+  TempoAlignment,
+  TokenSuggestion,
+  Track,
+  TutorialState,
+  Variation,
+  VariationParams,
+  ViewState,
+  VoiceMeter,
+} from '../wasm/types'
+import {
+  checkSettings,
+  createAudioWatchdog,
+  createChordCapture,
+  createMozart,
+  createMozartWithDefaults,
+  createMozartWithTitle,
+  extractGrooveFromMidi,
+  getStartupDiagnostics,
+  initWasm,
+  isRecordableCommand,
+  launchSentinel,
+  listAccentPresets,
+  loadMozartFromJson,
+  loadMozartFromMidi,
+  parsePlaybackRate,
+  parsePolyrhythm,
+  removeMacro,
+  saveAccentPreset,
+  saveMacro,
+  setNewSongDefaults,
+} from '../wasm'
+import { readNoteWindow, readNotes } from '../wasm/packed'
+import { AudioEngine, Sequencer, Metronome, CallResponseSession, DEFAULT_AUDIO_CONFIG, DEFAULT_POLYRHYTHM_SOUND } from '../audio'
+import type { AudioConfig, PolyrhythmSound } from '../audio'
 
 export type PlaybackState = 'stopped' | 'playing' | 'paused'
 
+// Piano roll click behavior: draw/select, scissors (split), or glue (join)
+export type EditTool = 'draw' | 'split' | 'join'
+
+// Chord rearrangements (mozart-core src/voicing.rs)
+export type ChordVoicing = 'invert_up' | 'invert_down' | 'open' | 'close'
+
+// Undo/redo availability and unsaved changes, for menus and the title bar
+export interface EditState {
+  canUndo: boolean
+  canRedo: boolean
+  undoLabel: string | null
+  redoLabel: string | null
+  isDirty: boolean
+}
+
+// A call-and-response round, for the practice panel
+export interface PracticeState {
+  phase: CallResponsePhase
+  info: CallResponseInfo
+  result: CallResponseResult | null // Set once the round is finished
+}
+
 interface MozartState {
   // WASM instance
   mozart: Mozart | null
   isWasmLoaded: boolean
+  startupDiagnostics: StartupDiagnostics | null // Safe mode after a crash, and why
 
   // Audio
   audioEngine: AudioEngine | null
   sequencer: Sequencer | null
   metronome: Metronome | null
+  audioConfig: AudioConfig // Requested sample rate / latency (persisted)
+  audioError: AudioDiagnostics | null // Last stall, dropout, or lost device, until dismissed
+  newSongDefaults: SongDefaults // Tempo, meter, key, instrument, and pickup for New Song (persisted)
 
   // Playback state
   playbackState: PlaybackState
   currentTick: number
   isMetronomeEnabled: boolean
+  playbackRate: number // Practice speed, 0.25-2; the song's tempo (and exports) stay as they are
+  arrangementPass: number // Pass playing (or last played); picks which chance notes and take lanes sound
+  loopRegion: [number, number] | null // Written ticks playback repeats, saved with the song
+  comparison: Comparison | null // Two versions playing against each other, until playback stops
+  polyrhythm: Polyrhythm | null // Secondary click layer (e.g., 3 over 2)
+  polyrhythmSound: PolyrhythmSound
+  preventSleep: boolean // Hold a wake lock while playing (persisted)
 
   // UI state
   selectedNoteIndex: number | null
   gridDivision: number // 4 = quarter notes, 8 = eighth notes, etc.
   viewportStart: number // Start tick of the visible area
   viewportEnd: number // End tick of the visible area
+  visibleNotes: IndexedNote[] // Notes overlapping the viewport, fetched on their own
+  followMode: FollowMode // Piano roll playhead follow during playback
+  viewScroll: { x: number; y: number } // Piano roll scroll, saved with the song
+  pixelsPerTick: number // Piano roll zoom, saved with the song
+  pitchView: PitchView // Piano roll rows: fit to the notes or a fixed range, saved with the song
+  visiblePitchRange: { low: number; high: number } // Rows pitchView gives for the current notes
+  editTool: EditTool
+  chordEntry: boolean // Piano keys pressed together are added as one chord at the playhead
+  grooveTemplate: GrooveTemplate | null // Feel to impose with Apply Groove
 
   // Derived state (cached for performance)
   notes: Note[]
@@ -32,30 +169,159 @@ interface MozartState {
   timeSignature: { numerator: number; denominator: number }
   key: string
   accents: number[]
+  groupings: number[] // Beat groups of a measure, derived from the accents
+  pulseGrouping: number[] // Beats per felt pulse (all 1s = every beat)
+  voiceMeters: VoiceMeter[] // Voices in their own time signature, sorted by voice
+  tracks: Track[] // Named voices with their own instrument and mix, sorted by voice
+  activeTrack: number // Voice that notes drawn in the piano roll go into
+  accentDynamics: boolean // Shape playback velocities by beat accent
+  pitchRange: PitchRange | null // Instrument range notes are checked against
+  instrument: Instrument // Sound the song plays with, saved with it
+  annotations: Annotation[] // Comments on the music, sorted by tick
+  chords: ChordSymbol[] // Chord track, sorted by tick (comped in playback)
+  markers: Marker[] // Sorted by tick; each starts a section running to the next
+  repeats: RepeatStructure // Written repeat structure; playback unfolds it
+  patterns: Pattern[] // Named clips of notes
+  patternChain: string[] // Pattern names in the order they play from the start (alongside the notes)
+  outOfRangeNotes: number[] // Indices of notes outside pitchRange
+  rangeWarnings: RangeWarning[] // Notes the last edit put outside pitchRange
+  editState: EditState
+  tutorial: TutorialState | null // Onboarding progress while the tutorial is open
+  practice: PracticeState | null // Call-and-response round, kept after it ends to show the result
+  macros: Macro[] // Saved macros (persisted)
+  macroSteps: MacroStep[] | null // Commands recorded so far, while recording a macro
 
   // Actions
   init: () => Promise<void>
+  getStartupDiagnostics: () => StartupDiagnostics | null
+  setStartupStage: (stage: StartupStage) => void // Recorded so a crash at this point is diagnosed
 
   // Song actions
-  newSong: (title?: string) => void
+  // Destructive actions return what would be lost, changing nothing, unless `confirm`; null once done
+  newSong: (title?: string, confirm?: boolean) => LossSummary | null
   loadFromJson: (json: string) => void
+  loadFromMidi: (bytes: Uint8Array, fallbackTitle?: string) => void
+  mergeSong: (source: string | Uint8Array, alignment: TempoAlignment) => number[] // JSON or MIDI bytes; throws past 16 voices
+  compareSong: (source: string | Uint8Array) => SimilarityReport // JSON or MIDI bytes; the region's a ticks are this song's
+  playComparison: (source: string | Uint8Array, mode: ComparisonMode) => Comparison // Plays this song (left) against another version (right)
+  planSplit: (measures: number[] | 'sections') => SplitPiece[] // Pieces starting at these measures or at each marker; throws without notes
+  splitSong: (plan: SplitPiece[]) => string[] // Each piece as .mozart.json text; the open song is unchanged
   saveToJson: () => string | null
-  exportToMidi: () => Uint8Array | null
+  markSaved: () => void
+  exportToMidi: (bakeAccentDynamics?: boolean) => Uint8Array | null
+  exportToLrc: (syllableTiming?: boolean) => string // Throws if the song has no lyrics
+  exportToChordPro: () => string // Throws if the song has no lyrics or chords
+  exportPracticeAudio: (rate: number | string) => Uint8Array // WAV at a practice speed; throws on a bad rate
+  setLyrics: (text: string) => number
+  getLyricsText: () => string
+  addAnnotation: (text: string, tick: number, endTick?: number) => number // Index; throws on blank text or an empty range
+  removeAnnotation: (index: number) => boolean
+  setChord: (tick: number, symbol: string) => void // Throws on an unknown chord
+  removeChord: (tick: number) => boolean
+  // Repeat structure; the spec actions throw on a spec that can't be parsed or played
+  addRepeat: (spec: string) => void // e.g. "5-8 x3"
+  addEnding: (spec: string) => void // e.g. "9-10 2"
+  placeRepeatSign: (spec: string) => void // e.g. "segno 3" or "to coda 16"
+  setJump: (spec: string) => void // e.g. "D.S. al Coda 24"; '' removes it
+  removeRepeatsAt: (measure: number) => boolean
+  clearRepeats: () => boolean
+  // Patterns; the actions throw on a name that is taken, unknown, or not one word
+  createPattern: (name: string, firstMeasure: number, count: number) => void // Cuts the measures' notes into it
+  clonePattern: (name: string, newName: string) => void
+  movePattern: (from: number, to: number) => void
+  removePattern: (name: string) => boolean
+  setPatternChain: (text: string) => void // e.g. "A A B A"; '' clears it
+
+  // History actions
+  undo: () => void
+  redo: () => void
+
+  // Tutorial actions
+  startTutorial: () => void
+  stopTutorial: () => void
+  tutorialNext: () => void
+  tutorialSkip: () => void
+
+  // Practice actions
+  startCallResponse: (config: CallResponseConfig) => string | null // Error message, if it could not start
+  callResponseNoteOn: (pitch: number, velocity: number) => void
+  stopCallResponse: () => void // Score what was played so far
+  closeCallResponse: () => void
 
   // Note actions
-  addNote: (pitch: number, startTick: number, durationTicks: number, velocity?: number) => void
+  addNote: (pitch: number, startTick: number, durationTicks: number, velocity?: number) => void // Into the active track
   removeNote: (index: number) => void
-  clearNotes: () => void
+  setNotePitch: (index: number, pitch: number) => void
+  splitNote: (index: number, atTick: number) => void
+  joinNotes: (indices: number[]) => void
+  makeLegato: (overlapTicks?: number) => void
+  addGap: (gapTicks: number) => void
+  strumChordAt: (tick: number, spreadTicks: number, direction: StrumDirection, playbackOnly?: boolean) => void
+  voiceChords: (voicing: ChordVoicing) => void // The selected note's chord, or every chord
+  // Experimental: notes that play only on some passes
+  setNoteProbability: (index: number, probability: number) => void // 0-100%
+  setNoteTake: (index: number, take: Take | null) => void
+  freezeArrangement: () => number // Keeps what the last pass played; returns the notes dropped
+  flattenPlaybackEffects: () => number // Bakes strums, accents, and track volumes into the notes; returns the notes changed
+  // Color labels are shown in the piano roll only; playback ignores them
+  setNoteColor: (color: ColorLabel | null) => void // The selected note's voice, or every note
+  setSectionColor: (marker: string, color: ColorLabel | null) => void
+  setVoiceTimeSignature: (voice: number, timeSignature: string) => void // '' follows the song's; throws on a bad meter
+  addTrack: (name: string) => number // Its voice, now the active track; throws past 16 tracks
+  setActiveTrack: (voice: number) => void
+  removeTrack: (voice: number) => number // Notes removed with it
+  renameTrack: (voice: number, name: string) => void
+  setTrackInstrument: (voice: number, instrument: Instrument | null) => void // null plays the song's
+  setTrackVolume: (voice: number, volume: number) => void // 0-127
+  muteTrack: (voice: number, mute: boolean) => void
+  soloTrack: (voice: number, solo: boolean) => void
+  // Grooves and variations are taken from the selected note's voice, or every note
+  extractGroove: (subdivisions: number) => void // Throws without notes
+  loadGrooveFromMidi: (bytes: Uint8Array, subdivisions: number) => void // Throws on an unreadable file
+  applyGroove: () => number
+  generateVariations: (count: number, params?: VariationParams) => Variation[] // Throws without notes
+  auditionVariation: (index: number) => void
+  applyVariation: (index: number) => void // Throws if the phrase changed since
+  clearVariations: () => void
+  continueMelody: (config: ContinuationConfig) => Continuation // A preview; throws without a melody
+  auditionContinuation: () => void
+  acceptContinuation: () => void // Throws if the melody changed since
+  rejectContinuation: () => void
+  suggestNextChords: (progression: string[]) => ChordSuggestion[] // Throws on a chord outside the key
+  reharmonizeMeasure: (measure: number, constraints: ReharmonizeConstraints) => Reharmonization[]
+  insertChords: (spans: HarmonizedSpan[]) => void // Root position, from the octave below middle C
+  planModulation: (toKey: string, startMeasure: number) => ModulationPlan[] // Throws if already in that key
+  applyModulation: (plan: ModulationPlan) => void
+  generateCounterpoint: (config: CounterpointConfig) => CounterpointReport // Throws without a cantus firmus
+  generateMelody: (config: MelodyConfig) => MelodyReport // Throws on an unknown chord
+  loadGroovePreset: (name: string) => GroovePreset // Meter, tempo, and drums; throws on an unknown name
+  importChordChart: (text: string) => ChartChord[] // Throws on an unknown chord
+  getPitchHistogram: (bucketTicks: number) => PitchHistogram | null
+  repairSong: (options?: Partial<RepairOptions>) => RepairReport
+  cleanupSong: (dryRun: boolean) => CleanupReport | null
+  normalizeVelocities: (min: number, max: number) => number // Throws on a range outside 1-127
+  compressVelocities: (ratio: number, threshold: number) => number // Throws on a ratio below 1
+  clearNotes: (confirm?: boolean) => LossSummary | null
+  deleteMeasures: (first: number, count: number, confirm?: boolean) => LossSummary | null // Throws on measure 0 or no measures
   parseMelody: (melody: string) => number
   formatMelody: () => string
+  suggestNextTokens: (partialText: string) => TokenSuggestion[] // Throws on an invalid finished token
   selectNote: (index: number | null) => void
 
   // Settings actions
   setTempo: (tempo: number) => void
   setTimeSignature: (ts: string) => void
   setKey: (key: string) => void
+  setPitchRange: (range: string | { low: number; high: number } | null) => void // A preset name, or custom pitches
+  foldIntoRange: (low: number, high: number) => FoldedNote[] // Throws on a range narrower than an octave
+  setInstrument: (instrument: Instrument) => void
   setAccents: (accents: number[]) => void
   cycleAccent: (beat: number) => void
+  previewAccentPattern: () => void
+  setAccentDynamics: (enabled: boolean) => void
+  setPulseGrouping: (groups: number[]) => void
+  listAccentPresets: () => AccentPreset[]
+  saveAccentPreset: (name: string, description?: string) => void
 
   // Transposition
   transposeChromatic: (semitones: number, keepOriginal?: boolean) => void
@@ -67,145 +333,1270 @@ interface MozartState {
   pause: () => void
   stop: () => void
   seekTo: (tick: number) => void
+  seekToMeasure: (measure: number) => void
+  seekToMarker: (name: string) => void
+  nextMarker: () => void
+  prevMarker: () => void
+  // Measure loops, from the playhead's measure; the loop steps by its own length
+  loopCurrentMeasure: () => void
+  loopNextMeasure: () => void
+  loopPreviousMeasure: () => void
+  extendLoopByMeasure: () => void
+  clearLoop: () => void
   toggleMetronome: () => void
+  setPolyrhythm: (spec: string | null) => void
+  setPlaybackRate: (rate: number | string) => void // A multiplier, or text like "75%"
+  setPolyrhythmSound: (sound: Partial<PolyrhythmSound>) => void
+  setAudioConfig: (config: AudioConfig) => Promise<boolean> // false if the browser rejected it and the defaults are used
+  reportAudioError: (diagnostics: AudioDiagnostics) => Promise<void>
+  dismissAudioError: () => void
 
   // Preview
-  playNotePreview: (pitch: number, velocity?: number, duration?: number) => void
+  // Velocity 1-127 (a click's depth on the key, or a MIDI key's); the song's instrument unless overridden
+  playNotePreview: (pitch: number, velocity?: number, duration?: number, instrument?: Instrument) => void
+  previewPitch: (pitch: number) => void
+  auditionRange: (startTick: number, endTick: number) => void
+  playScale: (root: string, scaleType: string, direction?: ScaleDirection) => void
+  playChord: (root: string, quality: string, inversion?: number) => void
+  diatonicChords: () => DiatonicChord[] // The chord ladder of the song's key
+  playDiatonicChord: (degree: number, seventh?: boolean) => void
 
   // UI actions
   setGridDivision: (division: number) => void
   setViewport: (start: number, end: number) => void
+  setFollowMode: (mode: FollowMode) => void
+  setViewScroll: (x: number, y: number) => void
+  setPixelsPerTick: (pixelsPerTick: number) => void
+  setPitchView: (pitchView: PitchView) => void
+  shiftPitchView: (octaves: number) => void // Keeps the octave shift when the range is changed
+  setEditTool: (tool: EditTool) => void
+  setChordEntry: (enabled: boolean) => void
+  chordEntryKey: (pitch: number, velocity: number) => void // A piano key pressed while chord entry is on
+  setPreventSleep: (enabled: boolean) => void
+  // Throws on an unknown meter, key, or instrument, or a pickup as long as a measure
+  setNewSongDefaults: (defaults: Partial<SongDefaults>) => void
+  exportSettings: () => string // Every preference as one JSON file
+  importSettings: (json: string) => Promise<void> // Throws on invalid settings, changing nothing
+
+  // Macros (replayed by runCommand in commands.ts)
+  startMacroRecording: () => void
+  recordMacroStep: (command: string, args: string[]) => void // Ignored unless recording, or if a replay can't run it
+  // Throws on an empty name or recording, or a shortcut already in use (recording continues)
+  stopMacroRecording: (name: string, shortcut?: string) => void
+  cancelMacroRecording: () => void
+  deleteMacro: (name: string) => void
 
   // Sync state from WASM
   syncFromWasm: () => void
+  applyChanges: () => void
+  fetchVisibleNotes: () => void
+}
+
+const FOLLOW_MODE_KEY = 'mozart.followMode'
+const PREVENT_SLEEP_KEY = 'mozart.preventSleep'
+const TUTORIAL_SEEN_KEY = 'mozart.tutorialSeen'
+const NEW_SONG_DEFAULTS_KEY = 'mozart.newSongDefaults'
+const MACROS_KEY = 'mozart.macros'
+
+// Matches mozart-core's SongDefaults::default()
+const DEFAULT_SONG_DEFAULTS: SongDefaults = { tempo: 120, time_signature: '4/4', key: 'C major', instrument: '', pickup_beats: 0 }
+
+// The running call-and-response round; its state is mirrored in `practice`
+let practiceSession: CallResponseSession | null = null
+
+// Samples the audio clock for the watchdog; often enough to catch a stall within a second or two
+const AUDIO_WATCHDOG_INTERVAL_MS = 250
+let audioWatchdog: AudioWatchdog | null = null
+
+// Keys of the chord being entered; it is added once the capture window closes
+let chordCapture: ChordCapture | null = null
+let chordCaptureTimer: ReturnType<typeof setTimeout> | null = null
+
+// Plays started from the top and loop passes this session; the next one is that pass
+let passesStarted = 0
+
+// Present while the app runs; left behind when it crashes, which puts the next launch in safe mode
+const STARTUP_SENTINEL_KEY = 'mozart.startupSentinel'
+let startupSentinel: StartupSentinel | null = null
+const DEFAULT_PIXELS_PER_TICK = 0.1
+// Matches mozart-core's PitchView::default()
+const DEFAULT_PITCH_VIEW: PitchView = { mode: 'auto', margin: 5, octave_shift: 0 }
+const CHORD_ROOT_PITCH = 48 // Lowest root for inserted chords (C3)
+const CHORD_VELOCITY = 80
+
+// The piano roll rows for the song's notes under a pitch view
+function readVisiblePitchRange(mozart: Mozart, pitchView: PitchView): { low: number; high: number } {
+  const [low, high] = mozart.getVisiblePitchRange(JSON.stringify(pitchView))
+  return { low, high }
+}
+
+// The selected note's voice, or every note
+function selectedVoiceIndices({ notes, selectedNoteIndex }: MozartState): Uint32Array {
+  const voice = selectedNoteIndex !== null ? notes[selectedNoteIndex]?.voice : undefined
+  return Uint32Array.from([...notes.keys()].filter((i) => voice === undefined || notes[i].voice === voice))
+}
+
+// Schedule a scale or chord audition from now, outside the sequencer
+// Hand the loop to the sequencer on the played timeline; a playhead outside it moves to its start
+function applyLoopRegion(mozart: Mozart, sequencer: Sequencer | null, region: [number, number] | null) {
+  if (!region) {
+    sequencer?.clearLoop()
+    return
+  }
+  const start = mozart.playedTick(region[0])
+  sequencer?.setLoop(start, start + region[1] - region[0])
+}
+
+// Click from the next beat the playhead reaches, in step with the sequencer's clock
+function startMetronome() {
+  const { mozart, sequencer, metronome } = useMozartStore.getState()
+  if (!mozart || !sequencer || !metronome) return
+  const ticksPerBeat = mozart.ticksPerBeat()
+  const nextBeat = Math.ceil(sequencer.getCurrentTick() / ticksPerBeat) * ticksPerBeat
+  metronome.start((nextBeat % mozart.ticksPerMeasure()) / ticksPerBeat, sequencer.timeOfTick(nextBeat))
+}
+
+// Bring a running click back in step after the playhead jumps
+function resyncMetronome() {
+  const { metronome, isMetronomeEnabled, playbackState } = useMozartStore.getState()
+  if (!metronome || !isMetronomeEnabled || playbackState !== 'playing') return
+  metronome.stop()
+  startMetronome()
+}
+
+// Where a comparison tick is in the song (played timeline), for the playhead
+function comparisonSongTick(comparison: Comparison, tick: number): number {
+  const segment = comparison.segments.find((s) => tick >= s.start_tick && tick < s.end_tick)
+  return segment ? segment.song_tick + tick - segment.start_tick : tick
+}
+
+// Back to the song's own instruments and loop once a comparison stops
+function endComparison() {
+  const { mozart, sequencer, audioEngine, tracks, loopRegion } = useMozartStore.getState()
+  audioEngine?.setTrackInstruments(tracks)
+  if (mozart) applyLoopRegion(mozart, sequencer, loopRegion)
+  useMozartStore.setState({ comparison: null, currentTick: 0 })
+}
+
+// Play the loop a measure command returned (written [start, end]); the playhead follows it in
+function setLoopRegion(range: Uint32Array) {
+  const { mozart, sequencer, currentTick } = useMozartStore.getState()
+  if (!mozart) return
+  const loopRegion: [number, number] = [range[0], range[1]]
+  applyLoopRegion(mozart, sequencer, loopRegion)
+  const inLoop = currentTick >= loopRegion[0] && currentTick < loopRegion[1]
+  useMozartStore.setState(inLoop ? { loopRegion } : { loopRegion, currentTick: loopRegion[0] })
+  if (!inLoop) resyncMetronome()
+}
+
+function playAudition(audioEngine: AudioEngine, notes: AuditionNote[]) {
+  audioEngine.resume()
+  const now = audioEngine.currentTime
+  for (const note of notes) {
+    audioEngine.playMidiNote(note.pitch, note.velocity, note.duration_seconds, now + note.start_seconds)
+  }
+}
+
+// Add a chord from the capture (JSON CapturedKey[], or '') at the playhead, one beat long
+function addCapturedChord(chordJson: string) {
+  const { mozart, currentTick, applyChanges } = useMozartStore.getState()
+  if (!mozart || !chordJson) return
+  try {
+    mozart.addChordJson(chordJson, currentTick, mozart.ticksPerBeat())
+    applyChanges()
+  } catch (err) {
+    console.error('Failed to add chord:', err)
+  }
+}
+
+// Changes that alter what the sequencer plays (strums, accent dynamics, chord track, repeats, and patterns included)
+const PLAYBACK_CHANGES = new Set<SongChange['type']>([
+  'notes:added',
+  'notes:removed',
+  'notes:updated',
+  'settings:timeSignature',
+  'settings:accentDynamics',
+  'settings:tracks',
+  'repeats:changed',
+  'chords:changed',
+  'patterns:changed',
+  'playback:changed',
+])
+
+const AUDIO_CONFIG_KEY = 'mozart.audioConfig'
+const ACCENT_PRESETS_KEY = 'mozart.accentPresets'
+const POLYRHYTHM_SOUND_KEY = 'mozart.polyrhythmSound'
+// Only the desktop app watches folders; kept so exported settings carry it along
+const WATCH_FOLDER_KEY = 'mozart.watchFolder'
+
+function loadAudioConfig(): AudioConfig {
+  try {
+    const saved = localStorage.getItem(AUDIO_CONFIG_KEY)
+    return saved ? { ...DEFAULT_AUDIO_CONFIG, ...JSON.parse(saved) } : DEFAULT_AUDIO_CONFIG
+  } catch {
+    return DEFAULT_AUDIO_CONFIG
+  }
+}
+
+// Remember the config the engine runs with, so a rejected one isn't asked for again
+function keepAudioConfig(config: AudioConfig) {
+  localStorage.setItem(AUDIO_CONFIG_KEY, JSON.stringify(config))
+  useMozartStore.setState({ audioConfig: config })
+}
+
+function loadPolyrhythmSound(): PolyrhythmSound {
+  try {
+    const saved = localStorage.getItem(POLYRHYTHM_SOUND_KEY)
+    return saved ? { ...DEFAULT_POLYRHYTHM_SOUND, ...JSON.parse(saved) } : DEFAULT_POLYRHYTHM_SOUND
+  } catch {
+    return DEFAULT_POLYRHYTHM_SOUND
+  }
+}
+
+function loadFollowMode(): FollowMode {
+  const saved = localStorage.getItem(FOLLOW_MODE_KEY)
+  return saved === 'off' || saved === 'continuous' ? saved : 'page-flip'
+}
+
+function readEditState(mozart: Mozart): EditState {
+  return {
+    canUndo: mozart.canUndo(),
+    canRedo: mozart.canRedo(),
+    undoLabel: mozart.undoLabel() ?? null,
+    redoLabel: mozart.redoLabel() ?? null,
+    isDirty: mozart.isDirty(),
+  }
+}
+
+function readTutorial(mozart: Mozart): TutorialState | null {
+  return JSON.parse(mozart.tutorialStateJson())
+}
+
+function loadPreventSleep(): boolean {
+  return localStorage.getItem(PREVENT_SLEEP_KEY) !== 'false'
+}
+
+function loadMacros(): Macro[] {
+  try {
+    const saved = localStorage.getItem(MACROS_KEY)
+    return saved ? (JSON.parse(saved) as MacroLibrary).macros : []
+  } catch {
+    return []
+  }
+}
+
+function loadNewSongDefaults(): SongDefaults {
+  try {
+    const saved = localStorage.getItem(NEW_SONG_DEFAULTS_KEY)
+    return saved ? { ...DEFAULT_SONG_DEFAULTS, ...JSON.parse(saved) } : DEFAULT_SONG_DEFAULTS
+  } catch {
+    return DEFAULT_SONG_DEFAULTS
+  }
 }
 
 export const useMozartStore = create<MozartState>((set, get) => ({
   // Initial state
   mozart: null,
   isWasmLoaded: false,
+  startupDiagnostics: null,
   audioEngine: null,
   sequencer: null,
   metronome: null,
+  audioConfig: loadAudioConfig(),
+  audioError: null,
+  newSongDefaults: loadNewSongDefaults(),
   playbackState: 'stopped',
   currentTick: 0,
   isMetronomeEnabled: false,
+  playbackRate: 1,
+  arrangementPass: 0,
+  loopRegion: null,
+  comparison: null,
+  polyrhythm: null,
+  polyrhythmSound: loadPolyrhythmSound(),
+  preventSleep: loadPreventSleep(),
   selectedNoteIndex: null,
   gridDivision: 4,
   viewportStart: 0,
   viewportEnd: 1920 * 4, // 4 measures at 4/4
+  visibleNotes: [],
+  followMode: loadFollowMode(),
+  viewScroll: { x: 0, y: 0 },
+  pixelsPerTick: DEFAULT_PIXELS_PER_TICK,
+  pitchView: DEFAULT_PITCH_VIEW,
+  visiblePitchRange: { low: 36, high: 84 },
+  editTool: 'draw',
+  chordEntry: false,
+  grooveTemplate: null,
 
   notes: [],
   tempo: 120,
   timeSignature: { numerator: 4, denominator: 4 },
   key: 'C Major',
   accents: [3, 1, 2, 1],
+  groupings: [2, 2],
+  pulseGrouping: [1, 1, 1, 1],
+  voiceMeters: [],
+  tracks: [],
+  activeTrack: 0,
+  accentDynamics: false,
+  pitchRange: null,
+  instrument: 'synth',
+  annotations: [],
+  chords: [],
+  markers: [],
+  repeats: {},
+  patterns: [],
+  patternChain: [],
+  outOfRangeNotes: [],
+  rangeWarnings: [],
+  editState: { canUndo: false, canRedo: false, undoLabel: null, redoLabel: null, isDirty: false },
+  tutorial: null,
+  practice: null,
+  macros: loadMacros(),
+  macroSteps: null,
 
   // Initialize WASM and audio
   init: async () => {
     try {
+      const previousSentinel = localStorage.getItem(STARTUP_SENTINEL_KEY)
       await initWasm()
+      const startupDiagnostics = getStartupDiagnostics(previousSentinel)
+      startupSentinel = launchSentinel(previousSentinel, Date.now())
+      get().setStartupStage('launch')
+      // A clean exit; written back if the page returns from the back/forward cache
+      window.addEventListener('pagehide', () => localStorage.removeItem(STARTUP_SENTINEL_KEY))
+      window.addEventListener('pageshow', (e) => e.persisted && get().setStartupStage('running'))
+      if (startupDiagnostics.safe_mode) console.warn(startupDiagnostics.message)
+      set({ startupDiagnostics })
+
       const mozart = createMozart()
-      const audioEngine = new AudioEngine()
-      await audioEngine.init()
-
-      const sequencer = new Sequencer(audioEngine, {
-        tempo: 120,
-        ticksPerBeat: 480,
-        onTick: (tick) => set({ currentTick: tick }),
-        onStateChange: (playbackState) => set({ playbackState }),
-      })
+      set({ mozart })
+
+      // Safe mode starts without audio, in case the driver is what crashed
+      if (!startupDiagnostics.safe_mode) {
+        get().setStartupStage('audio')
+        const audioEngine = new AudioEngine()
+        await audioEngine.init(get().audioConfig)
+        keepAudioConfig(audioEngine.config)
+
+        const sequencer = new Sequencer(audioEngine, {
+          tempo: 120,
+          ticksPerBeat: 480,
+          // The sequencer plays the unfolded repeats; the playhead shows the written measure
+          onTick: (tick) => {
+            const { mozart, comparison } = get()
+            const played = comparison ? comparisonSongTick(comparison, tick) : tick
+            set({ currentTick: mozart?.writtenTick(played) ?? played })
+          },
+          onStateChange: (playbackState) => {
+            set({ playbackState })
+            // Playing to the end stops the click too
+            if (playbackState === 'stopped') get().metronome?.stop()
+            if (playbackState === 'stopped' && get().comparison) endComparison()
+          },
+          // Every loop pass is a new pass, so chance notes and take lanes pick again
+          getNextPassNotes: () => {
+            const { mozart, comparison } = get()
+            if (!mozart || comparison) return null
+            return JSON.parse(mozart.getPlaybackNotesForPassJson(passesStarted))
+          },
+          onLoopWrap: () => {
+            if (!get().comparison) set({ arrangementPass: passesStarted++ })
+          },
+        })
+
+        const metronome = new Metronome(audioEngine, {
+          tempo: 120,
+          beatsPerMeasure: 4,
+        })
+
+        set({ audioEngine, sequencer, metronome })
+
+        // Watch for a stalled stream or a disconnected output device
+        audioWatchdog = createAudioWatchdog()
+        setInterval(() => {
+          const ctx = audioEngine.context
+          if (!audioWatchdog || !ctx) return
+          const fault = audioWatchdog.observe(performance.now(), ctx.currentTime, ctx.state)
+          if (fault) get().reportAudioError(JSON.parse(fault))
+        }, AUDIO_WATCHDOG_INTERVAL_MS)
+        navigator.mediaDevices?.addEventListener('devicechange', async () => {
+          const devices = await navigator.mediaDevices.enumerateDevices()
+          // Browsers that hide device labels still list one output per device
+          const outputs = devices.filter((d) => d.kind === 'audiooutput')
+          const ctx = audioEngine.context
+          if (outputs.length === 0 && audioWatchdog && ctx) {
+            get().reportAudioError(JSON.parse(audioWatchdog.deviceLost(performance.now(), ctx.state)))
+          }
+        })
+      }
 
-      const metronome = new Metronome(audioEngine, {
-        tempo: 120,
-        beatsPerMeasure: 4,
-      })
+      set({ isWasmLoaded: true })
+
+      // Sync initial state
+      get().syncFromWasm()
+      get().setStartupStage('running')
+
+      // Coach first-time users
+      if (!localStorage.getItem(TUTORIAL_SEEN_KEY)) get().startTutorial()
+    } catch (err) {
+      console.error('Failed to initialize:', err)
+    }
+  },
+
+  getStartupDiagnostics: () => get().startupDiagnostics,
 
+  setStartupStage: (stage) => {
+    if (!startupSentinel) return
+    startupSentinel = { ...startupSentinel, stage }
+    localStorage.setItem(STARTUP_SENTINEL_KEY, JSON.stringify(startupSentinel))
+  },
+
+  // Song actions
+  newSong: (title = 'Untitled', confirm = false) => {
+    const { mozart } = get()
+    if (!mozart) return null
+
+    const outcome: Outcome<null> = JSON.parse(mozart.checkNewSong(confirm))
+    if (outcome.status === 'needs_confirmation') return outcome.summary
+
+    let newMozart: Mozart
+    try {
+      newMozart = createMozartWithDefaults(title, get().newSongDefaults)
+    } catch (err) {
+      // Saved by an older version that accepted something this one doesn't
+      console.warn('Ignoring invalid new song defaults:', err)
+      newMozart = createMozartWithTitle(title)
+    }
+    set({ mozart: newMozart, selectedNoteIndex: null })
+    get().syncFromWasm()
+    return null
+  },
+
+  loadFromJson: (json: string) => {
+    try {
+      const mozart = loadMozartFromJson(json)
+      const view: ViewState = JSON.parse(mozart.getViewStateJson())
       set({
         mozart,
-        isWasmLoaded: true,
-        audioEngine,
-        sequencer,
-        metronome,
+        selectedNoteIndex: null,
+        viewScroll: { x: view.scroll_x, y: view.scroll_y },
+        pixelsPerTick: view.pixels_per_tick ?? DEFAULT_PIXELS_PER_TICK,
+        gridDivision: view.grid_division ?? get().gridDivision,
+        pitchView: view.pitch_view ?? DEFAULT_PITCH_VIEW,
       })
+      get().syncFromWasm()
+    } catch (err) {
+      console.error('Failed to load song:', err)
+    }
+  },
 
-      // Sync initial state
+  loadFromMidi: (bytes, fallbackTitle) => {
+    try {
+      const mozart = loadMozartFromMidi(bytes)
+      // Files without a track name import as "Untitled"
+      if (fallbackTitle && mozart.title === 'Untitled') mozart.title = fallbackTitle
+      set({ mozart, selectedNoteIndex: null, viewScroll: { x: 0, y: 0 } })
       get().syncFromWasm()
     } catch (err) {
-      console.error('Failed to initialize:', err)
+      console.error('Failed to import MIDI:', err)
+    }
+  },
+
+  mergeSong: (source, alignment) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const voices =
+      typeof source === 'string' ? mozart.mergeSongJson(source, alignment) : mozart.mergeMidi(source, alignment)
+    get().applyChanges()
+    return JSON.parse(voices)
+  },
+
+  compareSong: (source) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    return JSON.parse(typeof source === 'string' ? mozart.compareSongJson(source) : mozart.compareMidi(source))
+  },
+
+  playComparison: (source, mode) => {
+    const { mozart, sequencer, metronome, audioEngine } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const comparison: Comparison = JSON.parse(
+      typeof source === 'string'
+        ? mozart.getComparisonPlaybackJson(source, mode)
+        : mozart.getComparisonPlaybackMidi(source, mode)
+    )
+    if (!sequencer) return comparison
+    sequencer.stop()
+    metronome?.stop()
+    // The comparison has a timeline of its own
+    sequencer.clearLoop()
+    sequencer.setNotes(comparison.notes)
+    audioEngine?.setTrackInstruments(comparison.tracks)
+    set({ comparison })
+    sequencer.play()
+    return comparison
+  },
+
+  planSplit: (measures) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    if (measures === 'sections') return JSON.parse(mozart.planSectionsJson())
+    const ticks = Uint32Array.from(measures.map((measure) => mozart.measureStartTick(measure)))
+    return JSON.parse(mozart.planSplitJson(ticks))
+  },
+
+  splitSong: (plan) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const pieces: unknown[] = JSON.parse(mozart.splitSongJson(JSON.stringify(plan)))
+    return pieces.map((piece) => JSON.stringify(piece, null, 2))
+  },
+
+  saveToJson: () => {
+    const { mozart } = get()
+    if (!mozart) return null
+    try {
+      const { viewScroll, pixelsPerTick, gridDivision, pitchView } = get()
+      const view: ViewState = {
+        ...JSON.parse(mozart.getViewStateJson()),
+        scroll_x: viewScroll.x,
+        scroll_y: viewScroll.y,
+        pixels_per_tick: pixelsPerTick,
+        grid_division: gridDivision,
+        pitch_view: pitchView,
+      }
+      mozart.setViewStateJson(JSON.stringify(view))
+      return mozart.toJson()
+    } catch (err) {
+      console.error('Failed to save song:', err)
+      return null
+    }
+  },
+
+  // Called once the saved file has actually been written or shared
+  markSaved: () => {
+    const { mozart } = get()
+    if (!mozart) return
+    mozart.markSaved()
+    set({ editState: readEditState(mozart) })
+  },
+
+  undo: () => {
+    const { mozart } = get()
+    if (mozart?.undo()) get().applyChanges()
+  },
+
+  redo: () => {
+    const { mozart } = get()
+    if (mozart?.redo()) get().applyChanges()
+  },
+
+  startTutorial: () => {
+    const { mozart } = get()
+    if (!mozart) return
+    mozart.tutorialStart()
+    localStorage.setItem(TUTORIAL_SEEN_KEY, 'true')
+    set({ tutorial: readTutorial(mozart) })
+  },
+
+  stopTutorial: () => {
+    const { mozart } = get()
+    if (!mozart) return
+    mozart.tutorialStop()
+    set({ tutorial: null })
+  },
+
+  tutorialNext: () => {
+    const { mozart } = get()
+    if (!mozart) return
+    try {
+      mozart.tutorialNext()
+    } catch (err) {
+      console.warn('Tutorial step not done:', err)
+    }
+    set({ tutorial: readTutorial(mozart) })
+  },
+
+  tutorialSkip: () => {
+    const { mozart } = get()
+    if (!mozart) return
+    mozart.tutorialSkip()
+    set({ tutorial: readTutorial(mozart) })
+  },
+
+  startCallResponse: (config) => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return 'Not ready'
+
+    get().closeCallResponse()
+    get().stop()
+    try {
+      practiceSession = new CallResponseSession(mozart, audioEngine, config, (event) => {
+        const { practice } = get()
+        if (!practice) return
+        if (event.type === 'practice:phase') {
+          set({ practice: { ...practice, phase: event.phase } })
+        } else {
+          practiceSession = null
+          set({ practice: { ...practice, phase: 'finished', result: event.result } })
+        }
+      })
+    } catch (err) {
+      return String(err)
+    }
+
+    set({ practice: { phase: 'call', info: practiceSession.info, result: null } })
+    practiceSession.start()
+    return null
+  },
+
+  callResponseNoteOn: (pitch, velocity) => {
+    practiceSession?.noteOn(pitch, velocity)
+  },
+
+  stopCallResponse: () => {
+    practiceSession?.stop()
+    practiceSession = null
+  },
+
+  closeCallResponse: () => {
+    if (practiceSession) {
+      const session = practiceSession
+      practiceSession = null
+      session.stop()
+    }
+    set({ practice: null })
+  },
+
+  exportToMidi: (bakeAccentDynamics) => {
+    const { mozart, accentDynamics } = get()
+    if (!mozart) return null
+    try {
+      // Defaults to exporting what playback sounds like
+      return mozart.toMidiWithAccentDynamics(bakeAccentDynamics ?? accentDynamics)
+    } catch (err) {
+      console.error('Failed to export MIDI:', err)
+      return null
+    }
+  },
+
+  exportToLrc: (syllableTiming = true) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    return mozart.exportLrc(syllableTiming)
+  },
+
+  exportToChordPro: () => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    return mozart.exportChordPro()
+  },
+
+  exportPracticeAudio: (rate) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    return mozart.toPracticeWav(parsePlaybackRate(String(rate)))
+  },
+
+  setLyrics: (text) => {
+    const { mozart } = get()
+    if (!mozart) return 0
+
+    const placed = mozart.setLyrics(text)
+    get().applyChanges()
+    return placed
+  },
+
+  getLyricsText: () => get().mozart?.getLyricsText() ?? '',
+
+  addAnnotation: (text, tick, endTick) => {
+    const { mozart } = get()
+    if (!mozart) return -1
+
+    const index = mozart.addAnnotation(tick, endTick, text)
+    get().applyChanges()
+    return index
+  },
+
+  removeAnnotation: (index) => {
+    const { mozart } = get()
+    if (!mozart) return false
+
+    const removed = mozart.removeAnnotation(index)
+    get().applyChanges()
+    return removed
+  },
+
+  setChord: (tick, symbol) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setChord(tick, symbol)
+    get().applyChanges()
+  },
+
+  removeChord: (tick) => {
+    const { mozart } = get()
+    if (!mozart) return false
+
+    const removed = mozart.removeChord(tick)
+    get().applyChanges()
+    return removed
+  },
+
+  addRepeat: (spec) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.addRepeat(spec)
+    get().applyChanges()
+  },
+
+  addEnding: (spec) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.addEnding(spec)
+    get().applyChanges()
+  },
+
+  placeRepeatSign: (spec) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.placeRepeatSign(spec)
+    get().applyChanges()
+  },
+
+  setJump: (spec) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setJump(spec)
+    get().applyChanges()
+  },
+
+  removeRepeatsAt: (measure) => {
+    const { mozart } = get()
+    if (!mozart) return false
+
+    const removed = mozart.removeRepeatsAt(measure)
+    get().applyChanges()
+    return removed
+  },
+
+  clearRepeats: () => {
+    const { mozart } = get()
+    if (!mozart) return false
+
+    const cleared = mozart.clearRepeats()
+    get().applyChanges()
+    return cleared
+  },
+
+  createPattern: (name, firstMeasure, count) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.createPattern(name, firstMeasure, count)
+    get().applyChanges()
+  },
+
+  clonePattern: (name, newName) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.clonePattern(name, newName)
+    get().applyChanges()
+  },
+
+  movePattern: (from, to) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.movePattern(from, to)
+    get().applyChanges()
+  },
+
+  removePattern: (name) => {
+    const { mozart } = get()
+    if (!mozart) return false
+
+    const removed = mozart.removePattern(name)
+    get().applyChanges()
+    return removed
+  },
+
+  setPatternChain: (text) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setPatternChain(text)
+    get().applyChanges()
+  },
+
+  // Note actions
+  setNoteProbability: (index, probability) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setNoteProbability(index, Math.max(0, Math.min(100, Math.round(probability))))
+    get().applyChanges()
+  },
+
+  setNoteTake: (index, take) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setNoteTake(index, take ?? '')
+    get().applyChanges()
+  },
+
+  freezeArrangement: () => {
+    const { mozart, arrangementPass } = get()
+    if (!mozart) return 0
+
+    const dropped = mozart.freezeArrangement(arrangementPass)
+    get().applyChanges()
+    return dropped
+  },
+
+  flattenPlaybackEffects: () => {
+    const { mozart } = get()
+    if (!mozart) return 0
+
+    const changed = mozart.flattenPlaybackEffects()
+    get().applyChanges()
+    return changed
+  },
+
+  setNoteColor: (color) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setNoteColor(selectedVoiceIndices(get()), color ?? '')
+    get().applyChanges()
+  },
+
+  setSectionColor: (marker, color) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setSectionColor(marker, color ?? '')
+    get().applyChanges()
+  },
+
+  setVoiceTimeSignature: (voice, timeSignature) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setVoiceTimeSignature(voice, timeSignature)
+    get().applyChanges()
+  },
+
+  addTrack: (name) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const voice = mozart.addTrack(name)
+    get().applyChanges()
+    set({ activeTrack: voice })
+    return voice
+  },
+
+  setActiveTrack: (voice) => {
+    set({ activeTrack: voice })
+  },
+
+  removeTrack: (voice) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const removed = mozart.removeTrack(voice)
+    get().applyChanges()
+    if (get().activeTrack === voice) set({ activeTrack: 0 })
+    return removed
+  },
+
+  renameTrack: (voice, name) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    mozart.renameTrack(voice, name)
+    get().applyChanges()
+  },
+
+  setTrackInstrument: (voice, instrument) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setTrackInstrument(voice, instrument ?? '')
+    get().applyChanges()
+  },
+
+  setTrackVolume: (voice, volume) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.setTrackVolume(voice, Math.round(Math.min(Math.max(volume, 0), 127)))
+    get().applyChanges()
+  },
+
+  muteTrack: (voice, mute) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.muteTrack(voice, mute)
+    get().applyChanges()
+  },
+
+  soloTrack: (voice, solo) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.soloTrack(voice, solo)
+    get().applyChanges()
+  },
+
+  makeLegato: (overlapTicks = 0) => {
+    const { mozart, notes } = get()
+    if (!mozart) return
+
+    mozart.makeLegato(Uint32Array.from(notes.keys()), overlapTicks)
+    get().applyChanges()
+  },
+
+  addGap: (gapTicks) => {
+    const { mozart, notes } = get()
+    if (!mozart) return
+
+    mozart.addGap(Uint32Array.from(notes.keys()), gapTicks)
+    get().applyChanges()
+  },
+
+  strumChordAt: (tick, spreadTicks, direction, playbackOnly = false) => {
+    const { mozart, notes } = get()
+    if (!mozart) return
+
+    try {
+      if (playbackOnly) {
+        mozart.setChordStrum(tick, spreadTicks, direction)
+      } else {
+        const chord = [...notes.keys()].filter((i) => notes[i].start_tick === tick)
+        mozart.strumChords(Uint32Array.from(chord), spreadTicks, direction)
+      }
+      get().applyChanges()
+    } catch (err) {
+      console.error('Failed to strum chord:', err)
+    }
+  },
+
+  voiceChords: (voicing) => {
+    const { mozart, notes, selectedNoteIndex } = get()
+    if (!mozart) return
+
+    const selected = selectedNoteIndex !== null ? notes[selectedNoteIndex] : undefined
+    const chord = [...notes.keys()].filter((i) => !selected || notes[i].start_tick === selected.start_tick)
+    const indices = Uint32Array.from(chord)
+    switch (voicing) {
+      case 'invert_up':
+        mozart.invertChordUp(indices)
+        break
+      case 'invert_down':
+        mozart.invertChordDown(indices)
+        break
+      case 'open':
+        mozart.openVoicing(indices)
+        break
+      case 'close':
+        mozart.closeVoicing(indices)
+        break
+    }
+    get().applyChanges()
+  },
+
+  extractGroove: (subdivisions) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    set({ grooveTemplate: JSON.parse(mozart.extractGrooveJson(selectedVoiceIndices(get()), subdivisions)) })
+  },
+
+  loadGrooveFromMidi: (bytes, subdivisions) => {
+    set({ grooveTemplate: extractGrooveFromMidi(bytes, subdivisions) })
+  },
+
+  applyGroove: () => {
+    const { mozart, grooveTemplate } = get()
+    if (!mozart || !grooveTemplate) return 0
+
+    const changed = mozart.applyGrooveJson(JSON.stringify(grooveTemplate), selectedVoiceIndices(get()))
+    get().applyChanges()
+    return changed
+  },
+
+  generateVariations: (count, params = {}) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    return JSON.parse(mozart.generateVariationsJson(selectedVoiceIndices(get()), count, JSON.stringify(params)))
+  },
+
+  auditionVariation: (index) => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+    playAudition(audioEngine, JSON.parse(mozart.variationAuditionJson(index)))
+  },
+
+  applyVariation: (index) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    mozart.applyVariation(index)
+    get().applyChanges()
+  },
+
+  clearVariations: () => {
+    get().mozart?.clearVariations()
+  },
+
+  continueMelody: (config) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+    return JSON.parse(mozart.continueMelodyJson(JSON.stringify(config)))
+  },
+
+  auditionContinuation: () => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+    playAudition(audioEngine, JSON.parse(mozart.continuationAuditionJson()))
+  },
+
+  acceptContinuation: () => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    mozart.acceptContinuation()
+    get().applyChanges()
+  },
+
+  rejectContinuation: () => {
+    get().mozart?.rejectContinuation()
+  },
+
+  suggestNextChords: (progression) => {
+    const { mozart } = get()
+    if (!mozart) return []
+    return JSON.parse(mozart.suggestNextChordsJson(JSON.stringify(progression)))
+  },
+
+  reharmonizeMeasure: (measure, constraints) => {
+    const { mozart } = get()
+    if (!mozart) return []
+    return JSON.parse(mozart.reharmonizeMeasureJson(measure, JSON.stringify(constraints)))
+  },
+
+  insertChords: (spans) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    const notes = spans.flatMap((span) => {
+      // Stack the tones upward from the root
+      let pitch = CHORD_ROOT_PITCH - 1
+      return span.chord.pitch_classes.map((pitchClass) => {
+        pitch += (pitchClass - pitch + 120) % 12 || 12
+        return {
+          pitch,
+          start_tick: span.start_tick,
+          duration_ticks: span.end_tick - span.start_tick,
+          velocity: CHORD_VELOCITY,
+          voice: 0,
+          pan: 64,
+        }
+      })
+    })
+    mozart.addNotesJson(JSON.stringify(notes))
+    get().applyChanges()
+  },
+
+  planModulation: (toKey, startMeasure) => {
+    const { mozart } = get()
+    if (!mozart) return []
+    return JSON.parse(mozart.planModulationJson(toKey, startMeasure))
+  },
+
+  applyModulation: (plan) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    try {
+      mozart.applyModulationJson(JSON.stringify(plan))
+    } catch (err) {
+      console.error('Failed to apply modulation:', err)
+    }
+    get().applyChanges()
+  },
+
+  generateCounterpoint: (config) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const report = JSON.parse(mozart.generateCounterpointJson(JSON.stringify(config)))
+    get().applyChanges()
+    return report
+  },
+
+  generateMelody: (config) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const report = JSON.parse(mozart.generateMelodyJson(JSON.stringify(config)))
+    get().applyChanges()
+    return report
+  },
+
+  loadGroovePreset: (name) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const preset = JSON.parse(mozart.loadGroovePreset(name))
+    get().applyChanges()
+    return preset
+  },
+
+  importChordChart: (text) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const chords = JSON.parse(mozart.importChordChartJson(text))
+    get().applyChanges()
+    return chords
+  },
+
+  getPitchHistogram: (bucketTicks) => {
+    const { mozart } = get()
+    if (!mozart || bucketTicks <= 0) return null
+    return JSON.parse(mozart.getPitchHistogramJson(bucketTicks))
+  },
+
+  repairSong: (options = {}) => {
+    const { mozart } = get()
+    if (!mozart) return { removed: 0, modified: 0 }
+
+    const report = JSON.parse(mozart.repairSong(JSON.stringify(options)))
+    get().applyChanges()
+    return report
+  },
+
+  cleanupSong: (dryRun) => {
+    const { mozart } = get()
+    if (!mozart) return null
+
+    const report = JSON.parse(mozart.cleanupSongJson(dryRun))
+    if (!dryRun) get().applyChanges()
+    return report
+  },
+
+  normalizeVelocities: (min, max) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const changed = mozart.normalizeVelocities(min, max)
+    get().applyChanges()
+    return changed
+  },
+
+  compressVelocities: (ratio, threshold) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const changed = mozart.compressVelocities(ratio, threshold)
+    get().applyChanges()
+    return changed
+  },
+
+  addNote: (pitch, startTick, durationTicks, velocity = 100) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.addNoteWithVoice(pitch, startTick, durationTicks, velocity, get().activeTrack)
+    get().applyChanges()
+  },
+
+  removeNote: (index) => {
+    const { mozart, selectedNoteIndex } = get()
+    if (!mozart) return
+
+    mozart.removeNote(index)
+    if (selectedNoteIndex === index) {
+      set({ selectedNoteIndex: null })
     }
+    get().applyChanges()
   },
 
-  // Song actions
-  newSong: (title = 'Untitled') => {
+  setNotePitch: (index, pitch) => {
     const { mozart } = get()
     if (!mozart) return
 
-    const newMozart = createMozart()
-    newMozart.title = title
-    set({ mozart: newMozart, selectedNoteIndex: null })
-    get().syncFromWasm()
-  },
-
-  loadFromJson: (json: string) => {
     try {
-      const mozart = loadMozartFromJson(json)
-      set({ mozart, selectedNoteIndex: null })
-      get().syncFromWasm()
+      mozart.setNotePitch(index, pitch)
+      get().applyChanges()
     } catch (err) {
-      console.error('Failed to load song:', err)
+      console.error('Failed to set note pitch:', err)
     }
   },
 
-  saveToJson: () => {
+  splitNote: (index, atTick) => {
     const { mozart } = get()
-    if (!mozart) return null
+    if (!mozart) return
+
     try {
-      return mozart.toJson()
+      const second = mozart.splitNote(index, atTick)
+      set({ selectedNoteIndex: second })
+      get().applyChanges()
     } catch (err) {
-      console.error('Failed to save song:', err)
-      return null
+      console.error('Failed to split note:', err)
     }
   },
 
-  exportToMidi: () => {
+  joinNotes: (indices) => {
     const { mozart } = get()
-    if (!mozart) return null
+    if (!mozart) return
+
     try {
-      return mozart.toMidi()
+      const merged = mozart.joinNotes(new Uint32Array(indices))
+      set({ selectedNoteIndex: merged })
+      get().applyChanges()
     } catch (err) {
-      console.error('Failed to export MIDI:', err)
-      return null
+      console.error('Failed to join notes:', err)
     }
   },
 
-  // Note actions
-  addNote: (pitch, startTick, durationTicks, velocity = 100) => {
+  clearNotes: (confirm = false) => {
     const { mozart } = get()
-    if (!mozart) return
-
-    mozart.addNoteWithVelocity(pitch, startTick, durationTicks, velocity)
-    get().syncFromWasm()
-  },
-
-  removeNote: (index) => {
-    const { mozart, selectedNoteIndex } = get()
-    if (!mozart) return
+    if (!mozart) return null
 
-    mozart.removeNote(index)
-    if (selectedNoteIndex === index) {
-      set({ selectedNoteIndex: null })
-    }
-    get().syncFromWasm()
+    const outcome: Outcome<null> = JSON.parse(mozart.clearNotes(confirm))
+    if (outcome.status === 'needs_confirmation') return outcome.summary
+    set({ selectedNoteIndex: null })
+    get().applyChanges()
+    return null
   },
 
-  clearNotes: () => {
+  deleteMeasures: (first, count, confirm = false) => {
     const { mozart } = get()
-    if (!mozart) return
+    if (!mozart) return null
 
-    mozart.clearNotes()
+    const outcome: Outcome<number> = JSON.parse(mozart.deleteMeasures(first, count, confirm))
+    if (outcome.status === 'needs_confirmation') return outcome.summary
     set({ selectedNoteIndex: null })
-    get().syncFromWasm()
+    get().applyChanges()
+    return null
   },
 
   parseMelody: (melody) => {
@@ -214,7 +1605,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
 
     try {
       const count = mozart.parseMelody(melody)
-      get().syncFromWasm()
+      get().applyChanges()
       return count
     } catch (err) {
       console.error('Failed to parse melody:', err)
@@ -234,32 +1625,32 @@ export const useMozartStore = create<MozartState>((set, get) => ({
     }
   },
 
+  suggestNextTokens: (partialText) => {
+    const { mozart } = get()
+    if (!mozart) return []
+    return JSON.parse(mozart.suggestNextTokensJson(partialText))
+  },
+
   selectNote: (index) => {
     set({ selectedNoteIndex: index })
   },
 
   // Settings actions
   setTempo: (tempo) => {
-    const { mozart, sequencer, metronome } = get()
+    const { mozart } = get()
     if (!mozart) return
 
     mozart.tempo = tempo
-    sequencer?.setTempo(tempo)
-    metronome?.setTempo(tempo)
-    set({ tempo })
+    get().applyChanges()
   },
 
   setTimeSignature: (ts) => {
-    const { mozart, sequencer, metronome } = get()
+    const { mozart } = get()
     if (!mozart) return
 
     try {
       mozart.setTimeSignature(ts)
-      get().syncFromWasm()
-
-      const numerator = mozart.getTimeSignatureNumerator()
-      sequencer?.setTicksPerMeasure(mozart.ticksPerMeasure())
-      metronome?.setBeatsPerMeasure(numerator)
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to set time signature:', err)
     }
@@ -271,33 +1662,114 @@ export const useMozartStore = create<MozartState>((set, get) => ({
 
     try {
       mozart.setKey(key)
-      set({ key: mozart.getKey() })
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to set key:', err)
     }
   },
 
+  setPitchRange: (range) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    try {
+      if (range === null) mozart.clearPitchRange()
+      else if (typeof range === 'string') mozart.setPitchRangePreset(range)
+      else mozart.setPitchRange(range.low, range.high)
+      get().applyChanges()
+    } catch (err) {
+      console.error('Failed to set pitch range:', err)
+    }
+  },
+
+  foldIntoRange: (low, high) => {
+    const { mozart } = get()
+    if (!mozart) throw new Error('Not ready')
+
+    const folded = JSON.parse(mozart.foldIntoRangeJson(low, high))
+    get().applyChanges()
+    return folded
+  },
+
+  setInstrument: (instrument) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    try {
+      mozart.setInstrument(instrument)
+      get().applyChanges()
+    } catch (err) {
+      console.error('Failed to set instrument:', err)
+    }
+  },
+
   setAccents: (accents) => {
-    const { mozart, metronome } = get()
+    const { mozart } = get()
     if (!mozart) return
 
     try {
       mozart.setAccents(new Uint8Array(accents))
-      metronome?.setAccents(accents)
-      set({ accents })
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to set accents:', err)
     }
   },
 
   cycleAccent: (beat) => {
-    const { mozart, metronome } = get()
+    const { mozart } = get()
     if (!mozart) return
 
     mozart.cycleAccent(beat)
-    const newAccents = Array.from(mozart.getAccents())
-    metronome?.setAccents(newAccents)
-    set({ accents: newAccents })
+    get().applyChanges()
+  },
+
+  previewAccentPattern: () => {
+    const { mozart, metronome } = get()
+    if (!mozart || !metronome) return
+
+    const clicks: Click[] = JSON.parse(mozart.getAccentPreviewJson())
+    metronome.previewMeasure(clicks)
+  },
+
+  setPulseGrouping: (groups) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    try {
+      mozart.setPulseGrouping(new Uint8Array(groups))
+      get().applyChanges()
+    } catch (err) {
+      console.error('Failed to set pulse grouping:', err)
+    }
+  },
+
+  setAccentDynamics: (enabled) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    mozart.accentDynamics = enabled
+    get().applyChanges()
+  },
+
+  listAccentPresets: () => {
+    const { isWasmLoaded, timeSignature } = get()
+    if (!isWasmLoaded) return []
+    try {
+      return listAccentPresets(timeSignature.numerator, localStorage.getItem(ACCENT_PRESETS_KEY) ?? '')
+    } catch (err) {
+      console.error('Failed to list accent presets:', err)
+      return []
+    }
+  },
+
+  saveAccentPreset: (name, description = '') => {
+    const { accents } = get()
+    try {
+      const saved = saveAccentPreset(localStorage.getItem(ACCENT_PRESETS_KEY) ?? '', name, accents, description)
+      localStorage.setItem(ACCENT_PRESETS_KEY, saved)
+    } catch (err) {
+      console.error('Failed to save accent preset:', err)
+    }
   },
 
   // Transposition
@@ -317,7 +1789,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         const transposedNotes: Note[] = JSON.parse(mozart.getNotesJson())
 
         // Clear and rebuild with voices
-        mozart.clearNotes()
+        mozart.clearNotes(true)
 
         // Add original notes as voice 0 (main melody)
         for (const note of originalNotes) {
@@ -332,7 +1804,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         mozart.transposeChromatic(semitones)
       }
 
-      get().syncFromWasm()
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to transpose:', err)
     }
@@ -354,7 +1826,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         const transposedNotes: Note[] = JSON.parse(mozart.getNotesJson())
 
         // Clear and rebuild with voices
-        mozart.clearNotes()
+        mozart.clearNotes(true)
 
         // Add original notes as voice 0 (main melody)
         for (const note of originalNotes) {
@@ -369,7 +1841,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         mozart.transposeDiatonic(degrees)
       }
 
-      get().syncFromWasm()
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to transpose:', err)
     }
@@ -391,7 +1863,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         const invertedNotes: Note[] = JSON.parse(mozart.getNotesJson())
 
         // Clear and rebuild with voices
-        mozart.clearNotes()
+        mozart.clearNotes(true)
 
         // Add original notes as voice 0 (main melody)
         for (const note of originalNotes) {
@@ -406,7 +1878,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         mozart.invert(pivot)
       }
 
-      get().syncFromWasm()
+      get().applyChanges()
     } catch (err) {
       console.error('Failed to invert:', err)
     }
@@ -414,15 +1886,18 @@ export const useMozartStore = create<MozartState>((set, get) => ({
 
   // Playback actions
   play: () => {
-    const { sequencer, metronome, isMetronomeEnabled, notes } = get()
-    if (!sequencer) return
-
-    sequencer.setNotes(notes)
+    const { mozart, sequencer, isMetronomeEnabled } = get()
+    if (!sequencer || !mozart) return
+
+    // Each play from the start is a new pass, so chance notes and take lanes pick again
+    if (sequencer.getState() === 'stopped') {
+      const arrangementPass = passesStarted++
+      sequencer.setNotes(JSON.parse(mozart.getPlaybackNotesForPassJson(arrangementPass)))
+      set({ arrangementPass })
+    }
     sequencer.play()
 
-    if (isMetronomeEnabled) {
-      metronome?.start()
-    }
+    if (isMetronomeEnabled) startMetronome()
   },
 
   pause: () => {
@@ -439,9 +1914,65 @@ export const useMozartStore = create<MozartState>((set, get) => ({
   },
 
   seekTo: (tick) => {
-    const { sequencer } = get()
-    sequencer?.seekTo(tick)
+    const { mozart, sequencer } = get()
+    sequencer?.seekTo(mozart?.playedTick(tick) ?? tick)
     set({ currentTick: tick })
+    resyncMetronome()
+  },
+
+  seekToMeasure: (measure) => {
+    const { mozart } = get()
+    if (!mozart) return
+
+    try {
+      get().seekTo(mozart.measureStartTick(measure))
+    } catch (err) {
+      console.error('Failed to seek to measure:', err)
+    }
+  },
+
+  seekToMarker: (name) => {
+    const tick = get().mozart?.markerTick(name)
+    if (tick !== undefined) get().seekTo(tick)
+  },
+
+  nextMarker: () => {
+    const { mozart, currentTick } = get()
+    const tick = mozart?.nextMarkerTick(currentTick)
+    if (tick !== undefined) get().seekTo(tick)
+  },
+
+  prevMarker: () => {
+    const { mozart, currentTick } = get()
+    const tick = mozart?.prevMarkerTick(currentTick)
+    if (tick !== undefined) get().seekTo(tick)
+  },
+
+  loopCurrentMeasure: () => {
+    const { mozart, currentTick } = get()
+    if (mozart) setLoopRegion(mozart.loopCurrentMeasure(currentTick))
+  },
+
+  loopNextMeasure: () => {
+    const { mozart, currentTick } = get()
+    if (mozart) setLoopRegion(mozart.loopNextMeasure(currentTick))
+  },
+
+  loopPreviousMeasure: () => {
+    const { mozart, currentTick } = get()
+    if (mozart) setLoopRegion(mozart.loopPreviousMeasure(currentTick))
+  },
+
+  extendLoopByMeasure: () => {
+    const { mozart, currentTick } = get()
+    if (mozart) setLoopRegion(mozart.extendLoopByMeasure(currentTick))
+  },
+
+  clearLoop: () => {
+    const { mozart, sequencer } = get()
+    if (!mozart?.clearLoop()) return
+    sequencer?.clearLoop()
+    set({ loopRegion: null })
   },
 
   toggleMetronome: () => {
@@ -450,7 +1981,7 @@ export const useMozartStore = create<MozartState>((set, get) => ({
 
     if (playbackState === 'playing') {
       if (newEnabled) {
-        metronome?.start()
+        startMetronome()
       } else {
         metronome?.stop()
       }
@@ -459,12 +1990,141 @@ export const useMozartStore = create<MozartState>((set, get) => ({
     set({ isMetronomeEnabled: newEnabled })
   },
 
+  setPolyrhythm: (spec) => {
+    const { metronome, polyrhythmSound } = get()
+    try {
+      const polyrhythm = spec ? parsePolyrhythm(spec) : null
+      metronome?.setPolyrhythm(polyrhythm && { ...polyrhythm, sound: polyrhythmSound })
+      set({ polyrhythm })
+    } catch (err) {
+      console.error('Failed to set polyrhythm:', err)
+    }
+  },
+
+  setPlaybackRate: (rate) => {
+    const { sequencer, metronome } = get()
+    try {
+      const playbackRate = parsePlaybackRate(String(rate))
+      sequencer?.setRate(playbackRate)
+      metronome?.setRate(playbackRate)
+      set({ playbackRate })
+      resyncMetronome()
+    } catch (err) {
+      console.error('Failed to set playback rate:', err)
+    }
+  },
+
+  setPolyrhythmSound: (sound) => {
+    const { metronome, polyrhythm, polyrhythmSound } = get()
+    const newSound = { ...polyrhythmSound, ...sound }
+    metronome?.setPolyrhythm(polyrhythm && { ...polyrhythm, sound: newSound })
+    localStorage.setItem(POLYRHYTHM_SOUND_KEY, JSON.stringify(newSound))
+    set({ polyrhythmSound: newSound })
+  },
+
+  setAudioConfig: async (config) => {
+    const { audioEngine } = get()
+    if (!audioEngine) {
+      keepAudioConfig(config)
+      return true
+    }
+
+    // The context is recreated, so anything scheduled on the old one is lost
+    get().stop()
+    try {
+      await audioEngine.reinit(config)
+    } catch (err) {
+      console.error('Failed to apply audio config, reverting to defaults:', err)
+      await audioEngine.reinit(DEFAULT_AUDIO_CONFIG)
+    }
+    keepAudioConfig(audioEngine.config)
+    return audioEngine.config === config
+  },
+
+  reportAudioError: async (diagnostics) => {
+    console.warn('Audio error:', diagnostics)
+    set({ audioError: diagnostics })
+    window.dispatchEvent(new CustomEvent('audio:error', { detail: diagnostics }))
+
+    const { audioEngine, audioConfig, playbackState, currentTick } = get()
+    if (!diagnostics.recover || !audioEngine) return
+
+    // Re-create the engine and pick up where the transport was
+    get().stop()
+    try {
+      await audioEngine.reinit(audioConfig)
+    } catch (err) {
+      console.error('Failed to restart audio:', err)
+      return
+    } finally {
+      audioWatchdog?.restart()
+    }
+    get().seekTo(currentTick)
+    if (playbackState === 'playing') get().play()
+  },
+
+  dismissAudioError: () => set({ audioError: null }),
+
   // Preview
-  playNotePreview: (pitch, velocity = 100, duration = 0.3) => {
+  playNotePreview: (pitch, velocity = 100, duration = 0.3, instrument) => {
     const { audioEngine } = get()
     if (!audioEngine) return
 
-    audioEngine.playMidiNote(pitch, velocity, duration)
+    audioEngine.resume()
+    const clamped = Math.max(1, Math.min(127, Math.round(velocity)))
+    audioEngine.playMidiNote(pitch, clamped, duration, undefined, 0, 64, instrument)
+  },
+
+  previewPitch: (pitch) => {
+    get().audioEngine?.previewPitch(pitch)
+  },
+
+  // Play a span once, independent of the sequencer (transport position is untouched)
+  auditionRange: (startTick, endTick) => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+
+    audioEngine.resume()
+    const now = audioEngine.currentTime
+    const offset = mozart.tickToSeconds(startTick)
+    const notes: Note[] = JSON.parse(mozart.getNotesInRangeJson(startTick, endTick))
+
+    for (const note of notes) {
+      const start = mozart.tickToSeconds(note.start_tick) - offset
+      const duration = mozart.tickToSeconds(note.start_tick + note.duration_ticks) - offset - start
+      audioEngine.playMidiNote(note.pitch, note.velocity, duration, now + start, note.voice, note.pan)
+    }
+  },
+
+  playScale: (root, scaleType, direction = 'both') => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+    playAudition(audioEngine, JSON.parse(mozart.scaleAuditionJson(root, scaleType, direction)))
+  },
+
+  playChord: (root, quality, inversion = 0) => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+    try {
+      playAudition(audioEngine, JSON.parse(mozart.chordAuditionJson(root, quality, inversion)))
+    } catch (err) {
+      console.error('Failed to play chord:', err)
+    }
+  },
+
+  diatonicChords: () => {
+    const { mozart } = get()
+    return mozart ? JSON.parse(mozart.getDiatonicChordsJson()) : []
+  },
+
+  playDiatonicChord: (degree, seventh = false) => {
+    const { mozart, audioEngine } = get()
+    if (!mozart || !audioEngine) return
+    try {
+      playAudition(audioEngine, JSON.parse(mozart.diatonicChordAuditionJson(degree, seventh)))
+    } catch (err) {
+      console.error('Failed to play chord:', err)
+    }
   },
 
   // UI actions
@@ -474,24 +2134,170 @@ export const useMozartStore = create<MozartState>((set, get) => ({
 
   setViewport: (start, end) => {
     set({ viewportStart: start, viewportEnd: end })
+    get().fetchVisibleNotes()
+  },
+
+  setFollowMode: (mode) => {
+    localStorage.setItem(FOLLOW_MODE_KEY, mode)
+    set({ followMode: mode })
+  },
+
+  setViewScroll: (x, y) => {
+    set({ viewScroll: { x, y } })
+  },
+
+  setPixelsPerTick: (pixelsPerTick) => {
+    set({ pixelsPerTick })
+  },
+
+  setPitchView: (pitchView) => {
+    const { mozart } = get()
+    const octave_shift = get().pitchView.octave_shift
+    const next = { ...pitchView, octave_shift }
+    set({ pitchView: next, ...(mozart && { visiblePitchRange: readVisiblePitchRange(mozart, next) }) })
+  },
+
+  shiftPitchView: (octaves) => {
+    const { mozart, pitchView, visiblePitchRange } = get()
+    if (!mozart) return
+    const next = { ...pitchView, octave_shift: pitchView.octave_shift + octaves }
+    const range = readVisiblePitchRange(mozart, next)
+    // Already against the top or bottom of MIDI
+    if (range.low === visiblePitchRange.low && range.high === visiblePitchRange.high) return
+    set({ pitchView: next, visiblePitchRange: range })
+  },
+
+  setEditTool: (tool) => {
+    set({ editTool: tool })
+  },
+
+  setChordEntry: (enabled) => {
+    if (!enabled && chordCapture) {
+      // Keys pressed just before switching off still count
+      if (chordCaptureTimer) clearTimeout(chordCaptureTimer)
+      chordCaptureTimer = null
+      addCapturedChord(chordCapture.flush())
+    }
+    set({ chordEntry: enabled })
+  },
+
+  chordEntryKey: (pitch, velocity) => {
+    if (!get().mozart) return
+    chordCapture ??= createChordCapture()
+    const capture = chordCapture
+    addCapturedChord(capture.press(pitch, velocity, performance.now()))
+    chordCaptureTimer ??= setTimeout(() => {
+      chordCaptureTimer = null
+      addCapturedChord(capture.flush())
+    }, capture.windowMs)
+  },
+
+  setPreventSleep: (enabled) => {
+    localStorage.setItem(PREVENT_SLEEP_KEY, String(enabled))
+    set({ preventSleep: enabled })
+  },
+
+  setNewSongDefaults: (defaults) => {
+    const checked = setNewSongDefaults({ ...get().newSongDefaults, ...defaults })
+    localStorage.setItem(NEW_SONG_DEFAULTS_KEY, JSON.stringify(checked))
+    set({ newSongDefaults: checked })
+  },
+
+  exportSettings: () => {
+    const { newSongDefaults, macros, audioConfig, polyrhythmSound, followMode, preventSleep } = get()
+    const presets = localStorage.getItem(ACCENT_PRESETS_KEY)
+    const watchFolder = localStorage.getItem(WATCH_FOLDER_KEY)
+    return checkSettings({
+      song_defaults: newSongDefaults,
+      macros: { macros },
+      accent_presets: presets ? JSON.parse(presets) : { user: [] },
+      audio: { sample_rate: audioConfig.sampleRate, latency: audioConfig.latency },
+      metronome: { polyrhythm_frequency: polyrhythmSound.frequency, polyrhythm_volume: polyrhythmSound.volume },
+      follow_mode: followMode,
+      prevent_sleep: preventSleep,
+      ...(watchFolder ? { watch_folder: JSON.parse(watchFolder) } : {}),
+    })
+  },
+
+  importSettings: async (json) => {
+    // Checked as a whole first, so a bad file changes nothing
+    const settings: AppSettings = JSON.parse(checkSettings(json))
+    localStorage.setItem(MACROS_KEY, JSON.stringify(settings.macros))
+    localStorage.setItem(ACCENT_PRESETS_KEY, JSON.stringify(settings.accent_presets))
+    localStorage.setItem(NEW_SONG_DEFAULTS_KEY, JSON.stringify(settings.song_defaults))
+    localStorage.setItem(WATCH_FOLDER_KEY, JSON.stringify(settings.watch_folder))
+    set({ macros: settings.macros.macros, newSongDefaults: settings.song_defaults })
+    get().setFollowMode(settings.follow_mode)
+    get().setPreventSleep(settings.prevent_sleep)
+    get().setPolyrhythmSound({ frequency: settings.metronome.polyrhythm_frequency, volume: settings.metronome.polyrhythm_volume })
+
+    // Re-creating the audio context stops playback, so only do it for a different config
+    const audioConfig: AudioConfig = { sampleRate: settings.audio.sample_rate, latency: settings.audio.latency }
+    const current = get().audioConfig
+    if (audioConfig.sampleRate !== current.sampleRate || audioConfig.latency !== current.latency) {
+      await get().setAudioConfig(audioConfig)
+    }
+  },
+
+  // Macros
+  startMacroRecording: () => {
+    set({ macroSteps: [] })
+  },
+
+  recordMacroStep: (command, args) => {
+    const { macroSteps, isWasmLoaded } = get()
+    if (!macroSteps || !isWasmLoaded || !isRecordableCommand(command)) return
+    set({ macroSteps: [...macroSteps, args.length > 0 ? { command, args } : { command }] })
+  },
+
+  stopMacroRecording: (name, shortcut) => {
+    const saved = saveMacro(localStorage.getItem(MACROS_KEY) ?? '', name, shortcut || null, get().macroSteps ?? [])
+    localStorage.setItem(MACROS_KEY, saved)
+    set({ macros: (JSON.parse(saved) as MacroLibrary).macros, macroSteps: null })
+  },
+
+  cancelMacroRecording: () => {
+    set({ macroSteps: null })
+  },
+
+  deleteMacro: (name) => {
+    const saved = removeMacro(localStorage.getItem(MACROS_KEY) ?? '', name)
+    localStorage.setItem(MACROS_KEY, saved)
+    set({ macros: (JSON.parse(saved) as MacroLibrary).macros })
   },
 
   // Sync state from WASM
   syncFromWasm: () => {
-    const { mozart, sequencer } = get()
+    const { mozart, sequencer, metronome } = get()
     if (!mozart) return
 
     try {
-      const notesJson = mozart.getNotesJson()
-      const notes: Note[] = JSON.parse(notesJson)
+      // Pending change events are covered by the full refetch
+      mozart.takeChangesJson()
+
+      const notes = readNotes(mozart)
 
       const numerator = mozart.getTimeSignatureNumerator()
       const denominator = mozart.getTimeSignatureDenominator()
       const accents = Array.from(mozart.getAccents())
 
-      sequencer?.setNotes(notes)
+      // The sequencer plays notes with playback-only effects (strums, accent dynamics) applied
+      sequencer?.setNotes(JSON.parse(mozart.getPlaybackNotesForPassJson(get().arrangementPass)))
       sequencer?.setTicksPerBeat(mozart.ticksPerBeat())
       sequencer?.setTicksPerMeasure(mozart.ticksPerMeasure())
+      sequencer?.setTempo(mozart.tempo)
+      const pulseGrouping = Array.from(mozart.getPulseGrouping())
+      metronome?.setTempo(mozart.tempo)
+      metronome?.setBeatsPerMeasure(numerator)
+      metronome?.setAccents(accents)
+      metronome?.setPulseGrouping(pulseGrouping)
+      const instrument = mozart.instrument as Instrument
+      get().audioEngine?.setInstrument(instrument)
+      const tracks: Track[] = JSON.parse(mozart.getTracksJson())
+      get().audioEngine?.setTrackInstruments(tracks)
+      const region = Array.from(mozart.getLoopRegion())
+      const loopRegion: [number, number] | null = region.length === 2 ? [region[0], region[1]] : null
+      applyLoopRegion(mozart, get().sequencer, loopRegion)
 
       set({
         notes,
@@ -499,9 +2305,167 @@ export const useMozartStore = create<MozartState>((set, get) => ({
         timeSignature: { numerator, denominator },
         key: mozart.getKey(),
         accents,
+        groupings: Array.from(mozart.getGroupings()),
+        pulseGrouping,
+        voiceMeters: JSON.parse(mozart.getVoiceMetersJson()),
+        tracks,
+        activeTrack: 0,
+        accentDynamics: mozart.accentDynamics,
+        pitchRange: JSON.parse(mozart.getPitchRangeJson()),
+        instrument,
+        annotations: JSON.parse(mozart.getAnnotationsJson()),
+        chords: JSON.parse(mozart.getChordsJson()),
+        markers: JSON.parse(mozart.getMarkersJson()),
+        loopRegion,
+        repeats: JSON.parse(mozart.getRepeatsJson()),
+        patterns: JSON.parse(mozart.getPatternsJson()),
+        patternChain: mozart.getPatternChain().split(' ').filter(Boolean),
+        outOfRangeNotes: JSON.parse(mozart.getOutOfRangeNotesJson()),
+        rangeWarnings: [],
+        visiblePitchRange: readVisiblePitchRange(mozart, get().pitchView),
+        editState: readEditState(mozart),
+        tutorial: readTutorial(mozart),
       })
+      get().fetchVisibleNotes()
     } catch (err) {
       console.error('Failed to sync from WASM:', err)
     }
   },
+
+  // Apply the deltas of the last commands instead of refetching everything
+  applyChanges: () => {
+    const { mozart, sequencer, metronome } = get()
+    if (!mozart) return
+
+    try {
+      const changes: SongChange[] = JSON.parse(mozart.takeChangesJson())
+      if (changes.some((change) => change.type === 'song:replaced')) {
+        get().syncFromWasm()
+        return
+      }
+
+      const { viewportStart, viewportEnd } = get()
+      const update: Partial<MozartState> = {}
+      let notes = get().notes
+      let visibleNotes = get().visibleNotes
+      let windowDirty = false
+      for (const change of changes) {
+        switch (change.type) {
+          case 'notes:added': {
+            notes = [...notes.slice(0, change.index), ...change.notes, ...notes.slice(change.index)]
+            update.notes = notes
+            const shift = change.notes.length
+            visibleNotes = visibleNotes.map((n) => (n.index >= change.index ? { ...n, index: n.index + shift } : n))
+            break
+          }
+          case 'notes:removed': {
+            notes = [...notes.slice(0, change.index), ...notes.slice(change.index + change.count)]
+            update.notes = notes
+            const end = change.index + change.count
+            visibleNotes = visibleNotes.map((n) => (n.index >= end ? { ...n, index: n.index - change.count } : n))
+            break
+          }
+          case 'notes:updated':
+            notes = [
+              ...notes.slice(0, change.index),
+              ...change.notes,
+              ...notes.slice(change.index + change.notes.length),
+            ]
+            update.notes = notes
+            break
+          case 'notes:dirty':
+            windowDirty ||= change.start_tick < viewportEnd && change.end_tick > viewportStart
+            break
+          case 'settings:tempo':
+            sequencer?.setTempo(change.tempo)
+            metronome?.setTempo(change.tempo)
+            update.tempo = change.tempo
+            break
+          case 'settings:timeSignature':
+            sequencer?.setTicksPerBeat(mozart.ticksPerBeat())
+            sequencer?.setTicksPerMeasure(mozart.ticksPerMeasure())
+            metronome?.setBeatsPerMeasure(change.numerator)
+            metronome?.setAccents(change.accents)
+            metronome?.setPulseGrouping(change.pulse_grouping)
+            update.timeSignature = { numerator: change.numerator, denominator: change.denominator }
+            update.accents = change.accents
+            update.groupings = change.groupings
+            update.pulseGrouping = change.pulse_grouping
+            break
+          case 'settings:voiceMeters':
+            update.voiceMeters = change.voice_meters
+            break
+          case 'settings:tracks':
+            get().audioEngine?.setTrackInstruments(change.tracks)
+            update.tracks = change.tracks
+            break
+          case 'settings:key':
+            update.key = change.key
+            break
+          case 'settings:accentDynamics':
+            update.accentDynamics = change.enabled
+            break
+          case 'settings:pitchRange':
+            update.pitchRange = change.range
+            break
+          case 'settings:instrument':
+            get().audioEngine?.setInstrument(change.instrument)
+            update.instrument = change.instrument
+            break
+          case 'notes:outOfRange':
+            update.rangeWarnings = change.warnings
+            break
+          case 'annotations:changed':
+            update.annotations = change.annotations
+            break
+          case 'chords:changed':
+            update.chords = change.chords
+            break
+          case 'markers:changed':
+            update.markers = change.markers
+            break
+          case 'repeats:changed':
+            update.repeats = change.repeats
+            break
+          case 'patterns:changed':
+            update.patterns = change.patterns
+            update.patternChain = change.pattern_chain
+            break
+          default:
+            // Metadata and lyrics are read from the instance where they are shown
+            break
+        }
+      }
+
+      if (changes.some((change) => PLAYBACK_CHANGES.has(change.type))) {
+        sequencer?.setNotes(JSON.parse(mozart.getPlaybackNotesForPassJson(get().arrangementPass)))
+      }
+      // Indices shift with every splice, so the offenders are refetched
+      if (changes.some((change) => change.type === 'notes:dirty' || change.type === 'settings:pitchRange')) {
+        update.outOfRangeNotes = JSON.parse(mozart.getOutOfRangeNotesJson())
+        update.rangeWarnings ??= []
+      }
+      // An auto-fit piano roll follows the notes
+      if (update.notes && get().pitchView.mode === 'auto') {
+        update.visiblePitchRange = readVisiblePitchRange(mozart, get().pitchView)
+      }
+      // Tutorial steps are checked against the song, so every edit can complete one
+      set({ ...update, visibleNotes, editState: readEditState(mozart), tutorial: readTutorial(mozart) })
+      // Edits outside the viewport only shift indices; inside it, refetch the window
+      if (windowDirty) get().fetchVisibleNotes()
+    } catch (err) {
+      console.error('Failed to apply changes from WASM:', err)
+    }
+  },
+
+  fetchVisibleNotes: () => {
+    const { mozart, viewportStart, viewportEnd } = get()
+    if (!mozart) return
+
+    try {
+      set({ visibleNotes: readNoteWindow(mozart, viewportStart, viewportEnd) })
+    } catch (err) {
+      console.error('Failed to fetch visible notes:', err)
+    }
+  },
 }))
@@ -1,7 +1,29 @@
 // WASM loader for Mozart Core
 // This module loads and initializes the WASM package
 
-import type { Mozart } from './types'
+import type {
+  AccentPreset,
+  AppSettings,
+  AudioWatchdog,
+  ChordCapture,
+  CommandMatch,
+  FollowMode,
+  GroovePreset,
+  GrooveTemplate,
+  Instrument,
+  MacroStep,
+  MelodyToken,
+  Mozart,
+  NotationGrammar,
+  PitchRange,
+  PitchView,
+  Polyrhythm,
+  SongDefaults,
+  StartupDiagnostics,
+  StartupSentinel,
+  TheoryReference,
+  TheoryTopic,
+} from './types'
 
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 let wasmModule: any = null
@@ -44,6 +66,31 @@ export function createMozartWithTitle(title: string): Mozart {
   return wasmModule.Mozart.withTitle(title)
 }
 
+// Throws if a default is invalid (see setNewSongDefaults)
+export function createMozartWithDefaults(title: string, defaults: SongDefaults): Mozart {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.Mozart.withDefaults(title, JSON.stringify(defaults))
+}
+
+// Check new-song defaults, returning them complete; throws on an unknown meter, key, or instrument
+export function setNewSongDefaults(defaults: Partial<SongDefaults>): SongDefaults {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.setNewSongDefaults(JSON.stringify(defaults)))
+}
+
+// Check exported settings (missing fields take their defaults); returns the complete JSON
+// text, or throws naming the first invalid value
+export function checkSettings(settings: Partial<AppSettings> | string): string {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.checkSettings(typeof settings === 'string' ? settings : JSON.stringify(settings))
+}
+
 export function loadMozartFromJson(json: string): Mozart {
   if (!initialized) {
     throw new Error('WASM not initialized. Call initWasm() first.')
@@ -51,6 +98,13 @@ export function loadMozartFromJson(json: string): Mozart {
   return wasmModule.Mozart.fromJson(json)
 }
 
+export function loadMozartFromMidi(bytes: Uint8Array): Mozart {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.Mozart.fromMidi(bytes)
+}
+
 // Re-export utility functions
 export function getTicksPerQuarter(): number {
   if (!initialized) {
@@ -66,6 +120,13 @@ export function getScaleTypes(): string[] {
   return JSON.parse(wasmModule.getScaleTypes())
 }
 
+export function getInstruments(): Instrument[] {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.getInstruments())
+}
+
 export function getPitchClasses(): string[] {
   if (!initialized) {
     throw new Error('WASM not initialized. Call initWasm() first.')
@@ -94,4 +155,205 @@ export function noteNameToMidi(name: string): number {
   return wasmModule.Mozart.noteNameToMidi(name)
 }
 
-export type { Mozart } from './types'
+export function followScroll(
+  mode: FollowMode,
+  scrollLeft: number,
+  visibleWidth: number,
+  playheadTick: number,
+  keyWidth: number,
+  pixelsPerTick: number
+): number {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.followScroll(mode, scrollLeft, visibleWidth, playheadTick, keyWidth, pixelsPerTick)
+}
+
+// Practice playback rate (e.g., "0.5", "75%") as a multiplier; throws outside 0.25-2
+export function parsePlaybackRate(spec: string): number {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.parsePlaybackRate(spec)
+}
+
+// Preview velocity for a press `offset` pixels below the top of a piano key (top soft, bottom loud)
+export function keyPressVelocity(offset: number, keyHeight: number): number {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.keyPressVelocity(offset, keyHeight)
+}
+
+// Zoom about a point of the visible area (e.g., a pinch center), keeping it in place
+export function zoomAbout(
+  factor: number,
+  anchorX: number,
+  scrollLeft: number,
+  keyWidth: number,
+  pixelsPerTick: number
+): { pixelsPerTick: number; scrollLeft: number } {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  const [zoomed, scroll] = wasmModule.zoomAbout(factor, anchorX, scrollLeft, keyWidth, pixelsPerTick)
+  return { pixelsPerTick: zoomed, scrollLeft: scroll }
+}
+
+export function listAccentPresets(numerator: number, userPresetsJson: string): AccentPreset[] {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.listAccentPresets(numerator, userPresetsJson))
+}
+
+// Returns the updated user presets JSON to persist
+export function saveAccentPreset(
+  userPresetsJson: string,
+  name: string,
+  accents: number[],
+  description: string = ''
+): string {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.saveAccentPreset(userPresetsJson, name, new Uint8Array(accents), description)
+}
+
+// Registry commands a macro can record (not help, macro, or panel commands)
+export function isRecordableCommand(id: string): boolean {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.isRecordableCommand(id)
+}
+
+// Returns the updated macro library JSON to persist; throws on an empty name
+// or recording, or a shortcut already in use
+export function saveMacro(libraryJson: string, name: string, shortcut: string | null, steps: MacroStep[]): string {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.saveMacro(libraryJson, name, shortcut ?? undefined, JSON.stringify(steps))
+}
+
+// Returns the updated macro library JSON to persist
+export function removeMacro(libraryJson: string, name: string): string {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return wasmModule.removeMacro(libraryJson, name)
+}
+
+// Parse one token of text notation ("C4q", "Rq"); throws on invalid or half-typed input
+export function parseMelodyToken(token: string): MelodyToken {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.parseMelodyToken(token))
+}
+
+// Parse "auto", "auto 7" (margin), or a range such as "C1-C7"; throws on invalid input
+export function parsePitchView(text: string): PitchView {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.parsePitchView(text))
+}
+
+// Parse "3:2", "4 over 3", etc.; throws on invalid input
+export function parsePolyrhythm(spec: string): Polyrhythm {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.parsePolyrhythm(spec))
+}
+
+// Command palette search, best first; every command for an empty query
+export function searchCommands(query: string): CommandMatch[] {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.searchCommandsJson(query))
+}
+
+// Theory reference tables for a Help/Theory panel
+export function getTheoryReference(topic: TheoryTopic): TheoryReference {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.theoryReferenceJson(topic))
+}
+
+// The melody notation, read from the parser's own tables (for help and editor integrations)
+export function getNotationGrammar(): NotationGrammar {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.notationGrammarJson())
+}
+
+// Groove of a MIDI performance, per beat subdivision; throws on an unreadable file or one without notes
+export function extractGrooveFromMidi(bytes: Uint8Array, subdivisions: number): GrooveTemplate {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.extractGrooveFromMidiJson(bytes, subdivisions))
+}
+
+// Safe mode decision for this launch, from the sentinel the previous one left (null after a clean exit)
+export function getStartupDiagnostics(previousSentinel: string | null): StartupDiagnostics {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.startupDiagnosticsJson(previousSentinel))
+}
+
+// The sentinel to store for this launch
+export function launchSentinel(previousSentinel: string | null, nowMs: number): StartupSentinel {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.launchSentinelJson(previousSentinel, nowMs))
+}
+
+// Watches an AudioContext's clock for stalls and underruns; sample it a few times a second
+export function createAudioWatchdog(): AudioWatchdog {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return new wasmModule.AudioWatchdog()
+}
+
+export function createChordCapture(): ChordCapture {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return new wasmModule.ChordCapture()
+}
+
+// Preset instrument and voice ranges, voices first
+export function getPitchRangePresets(): PitchRange[] {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.pitchRangePresetsJson())
+}
+
+// An instrument or voice range preset by name (e.g. "flute", "alto voice"); throws on an unknown one
+export function getInstrumentRange(name: string): PitchRange {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.instrumentRangeJson(name))
+}
+
+// Bundled odd-meter grooves, for Playback > Groove Preset
+export function getGroovePresets(): GroovePreset[] {
+  if (!initialized) {
+    throw new Error('WASM not initialized. Call initWasm() first.')
+  }
+  return JSON.parse(wasmModule.groovePresetsJson())
+}
+
+export type { AccentPreset, AudioWatchdog, CommandMatch, FollowMode, GroovePreset, GrooveTemplate, Instrument, Mozart, NotationGrammar, PitchRange, PitchView, Polyrhythm, TheoryReference, TheoryTopic } from './types'
@@ -0,0 +1,70 @@
+// Decoder for the packed binary note encoding (mozart-core src/packed.rs)
+//
+// Only available when the WASM package is built with the `packed-notes`
+// feature; readNotes/readNoteWindow fall back to JSON otherwise, and for
+// songs too small to benefit. The constants below mirror the crate and are
+// checked against it by packed.rs's tests.
+
+import type { ColorLabel, IndexedNote, Mozart, Note, Take } from './types'
+
+const PACKED_VERSION = 3
+const HEADER_LEN = 8
+const FLAG_INDEXED = 1
+const TAKES: (Take | undefined)[] = [undefined, 'A', 'B']
+// Matches ColorLabel::ALL in the crate, after 0 for none
+const COLORS: (ColorLabel | undefined)[] = [
+  undefined, 'red', 'orange', 'yellow', 'green', 'blue', 'purple', 'pink', 'gray',
+]
+
+// Matches PACKED_MIN_NOTES in the crate
+const PACKED_MIN_NOTES = 500
+
+export function decodePackedNotes(bytes: Uint8Array): IndexedNote[] {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength)
+  if (bytes.length < HEADER_LEN || bytes[0] !== 0x4d || bytes[1] !== 0x5a) {
+    throw new Error('Not a packed note payload')
+  }
+  if (bytes[2] !== PACKED_VERSION) {
+    throw new Error(`Unsupported packed note version ${bytes[2]}`)
+  }
+
+  const indexed = (bytes[3] & FLAG_INDEXED) !== 0
+  const count = view.getUint32(4, true)
+  const recordLen = indexed ? 19 : 15
+  const notes: IndexedNote[] = new Array(count)
+  for (let i = 0, at = HEADER_LEN; i < count; i++, at += recordLen) {
+    const index = indexed ? view.getUint32(at, true) : i
+    const base = indexed ? at + 4 : at
+    notes[i] = {
+      index,
+      start_tick: view.getUint32(base, true),
+      duration_ticks: view.getUint32(base + 4, true),
+      pitch: bytes[base + 8],
+      velocity: bytes[base + 9],
+      voice: bytes[base + 10],
+      pan: bytes[base + 11],
+      probability: bytes[base + 12],
+    }
+    const take = TAKES[bytes[base + 13]]
+    if (take) notes[i].take = take
+    const color = COLORS[bytes[base + 14]]
+    if (color) notes[i].color = color
+  }
+  return notes
+}
+
+// All notes of the song, packed when that pays off
+export function readNotes(mozart: Mozart): Note[] {
+  if (mozart.getNotesPacked && mozart.noteCount() >= PACKED_MIN_NOTES) {
+    return decodePackedNotes(mozart.getNotesPacked()).map(({ index: _, ...note }) => note)
+  }
+  return JSON.parse(mozart.getNotesJson())
+}
+
+// Notes overlapping a tick range with their indices, packed when that pays off
+export function readNoteWindow(mozart: Mozart, startTick: number, endTick: number): IndexedNote[] {
+  if (mozart.getNoteWindowPacked && mozart.noteCount() >= PACKED_MIN_NOTES) {
+    return decodePackedNotes(mozart.getNoteWindowPacked(startTick, endTick))
+  }
+  return JSON.parse(mozart.getNoteWindowJson(startTick, endTick))
+}
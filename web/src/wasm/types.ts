@@ -1,11 +1,253 @@
 // TypeScript types matching the Rust WASM exports
 
-export interface Note {
-  pitch: number
-  start_tick: number
-  duration_ticks: number
-  velocity: number
-  voice: number
+// JSON payload types are declared in mozart-core (src/typescript.rs), checked
+// against their serde output by its tests, and emitted into the package .d.ts
+import type {
+  AccentPreset,
+  Accidental,
+  Annotation,
+  AppSettings,
+  AudioDiagnostics,
+  AudioFault,
+  AudioSettings,
+  AuditionNote,
+  CadenceInfo,
+  CallResponseConfig,
+  CallResponseEvent,
+  CallResponseInfo,
+  CallResponsePhase,
+  CallResponseResult,
+  CapturedKey,
+  ChartChord,
+  Chord,
+  ChordInfo,
+  ChordQuality,
+  ChordSuggestion,
+  ChordSymbol,
+  CleanupReport,
+  Click,
+  ColorLabel,
+  CommandInfo,
+  CommandMatch,
+  ComparedVersion,
+  Comparison,
+  ComparisonMode,
+  ComparisonSegment,
+  CompletionKind,
+  Consonance,
+  Continuation,
+  ContinuationConfig,
+  CounterpointConfig,
+  CounterpointReport,
+  CounterpointRule,
+  DiatonicChord,
+  Difficulty,
+  DurationRule,
+  Ending,
+  FoldedNote,
+  FollowMode,
+  GroovePreset,
+  GrooveSlot,
+  GrooveTemplate,
+  HarmonicFunction,
+  HarmonizedSpan,
+  HistogramBucket,
+  IndexedNote,
+  Instrument,
+  IntervalInfo,
+  IssueKind,
+  Jump,
+  JumpEnd,
+  JumpKind,
+  KeyChord,
+  KeyInfo,
+  LossSummary,
+  Lyric,
+  Macro,
+  MacroLibrary,
+  MacroStep,
+  Marker,
+  MatchedRegion,
+  MelodyConfig,
+  MelodyReport,
+  MelodyToken,
+  MetronomeSettings,
+  ModulationKind,
+  ModulationPlan,
+  NotationGrammar,
+  Note,
+  NoteFeedback,
+  NoteMerge,
+  NoteVerdict,
+  OctaveRange,
+  Outcome,
+  Pattern,
+  PitchHistogram,
+  PitchRange,
+  PitchView,
+  Polyrhythm,
+  PresetLibrary,
+  RangeWarning,
+  Reharmonization,
+  ReharmonizeConstraints,
+  Relaxation,
+  RepairOptions,
+  RepairReport,
+  Repeat,
+  RepeatStructure,
+  RouteChord,
+  ScaleDirection,
+  ScaleInfo,
+  SimilarityReport,
+  SongChange,
+  SongDefaults,
+  SplitPiece,
+  SongInfo,
+  StartupDiagnostics,
+  StartupSentinel,
+  StartupStage,
+  StrumDirection,
+  Take,
+  TempoAlignment,
+  TheoryReference,
+  TheoryTopic,
+  TokenKind,
+  TokenRule,
+  TokenSuggestion,
+  Track,
+  TutorialState,
+  TutorialStep,
+  ValidationIssue,
+  Variation,
+  VariationKind,
+  VariationParams,
+  ViewState,
+  VoiceMeter,
+  WatchFolderSettings,
+} from './pkg/mozart_core'
+
+export type {
+  AccentPreset,
+  Accidental,
+  Annotation,
+  AppSettings,
+  AudioDiagnostics,
+  AudioFault,
+  AudioSettings,
+  AuditionNote,
+  CadenceInfo,
+  CallResponseConfig,
+  CallResponseEvent,
+  CallResponseInfo,
+  CallResponsePhase,
+  CallResponseResult,
+  CapturedKey,
+  ChartChord,
+  Chord,
+  ChordInfo,
+  ChordQuality,
+  ChordSuggestion,
+  ChordSymbol,
+  CleanupReport,
+  Click,
+  ColorLabel,
+  CommandInfo,
+  CommandMatch,
+  ComparedVersion,
+  Comparison,
+  ComparisonMode,
+  ComparisonSegment,
+  CompletionKind,
+  Consonance,
+  Continuation,
+  ContinuationConfig,
+  CounterpointConfig,
+  CounterpointReport,
+  CounterpointRule,
+  DiatonicChord,
+  Difficulty,
+  DurationRule,
+  Ending,
+  FoldedNote,
+  FollowMode,
+  GroovePreset,
+  GrooveSlot,
+  GrooveTemplate,
+  HarmonicFunction,
+  HarmonizedSpan,
+  HistogramBucket,
+  IndexedNote,
+  Instrument,
+  IntervalInfo,
+  IssueKind,
+  Jump,
+  JumpEnd,
+  JumpKind,
+  KeyChord,
+  KeyInfo,
+  LossSummary,
+  Lyric,
+  Macro,
+  MacroLibrary,
+  MacroStep,
+  Marker,
+  MatchedRegion,
+  MelodyConfig,
+  MelodyReport,
+  MelodyToken,
+  MetronomeSettings,
+  ModulationKind,
+  ModulationPlan,
+  NotationGrammar,
+  Note,
+  NoteFeedback,
+  NoteMerge,
+  NoteVerdict,
+  OctaveRange,
+  Outcome,
+  Pattern,
+  PitchHistogram,
+  PitchRange,
+  PitchView,
+  Polyrhythm,
+  PresetLibrary,
+  RangeWarning,
+  Reharmonization,
+  ReharmonizeConstraints,
+  Relaxation,
+  RepairOptions,
+  RepairReport,
+  Repeat,
+  RepeatStructure,
+  RouteChord,
+  ScaleDirection,
+  ScaleInfo,
+  SimilarityReport,
+  SongChange,
+  SongDefaults,
+  SplitPiece,
+  SongInfo,
+  StartupDiagnostics,
+  StartupSentinel,
+  StartupStage,
+  StrumDirection,
+  Take,
+  TempoAlignment,
+  TheoryReference,
+  TheoryTopic,
+  TokenKind,
+  TokenRule,
+  TokenSuggestion,
+  Track,
+  TutorialState,
+  TutorialStep,
+  ValidationIssue,
+  Variation,
+  VariationKind,
+  VariationParams,
+  ViewState,
+  VoiceMeter,
+  WatchFolderSettings,
 }
 
 export interface Mozart {
@@ -17,24 +259,120 @@ export interface Mozart {
   tempo: number
   getTimeSignature(): string
   setTimeSignature(ts: string): void
+  getVoiceTimeSignature(voice: number): string // The voice's own meter, or the song's
+  setVoiceTimeSignature(voice: number, ts: string): void // '' follows the song's meter
+  getVoiceMetersJson(): string // VoiceMeter[]
+  // Tracks: named voices with their own instrument and mix; voice-less calls make the track on first use
+  getTracksJson(): string // Track[]
+  addTrack(name: string): number // Its voice; throws past 16 tracks
+  removeTrack(voice: number): number // Notes removed with it
+  renameTrack(voice: number, name: string): void
+  setTrackInstrument(voice: number, instrument: string): void // '' plays the song's instrument
+  setTrackVolume(voice: number, volume: number): void // 0-127; 100 as written
+  muteTrack(voice: number, mute: boolean): void
+  soloTrack(voice: number, solo: boolean): void
+  addTempoChange(tick: number, tempo: number): void
+  clearTempoChanges(): void
+  tempoAtTick(tick: number): number
+  accentDynamics: boolean
+  readonly rhythmPitch: number // MIDI pitch rhythm hits ("xq") are entered at
+  setRhythmPitch(pitch: number | undefined): void // undefined for the default (middle C)
+  readonly instrument: Instrument
+  setInstrument(name: string): void // Throws on an unknown instrument
   getTimeSignatureNumerator(): number
   getTimeSignatureDenominator(): number
   getKey(): string
   setKey(key: string): void
+  getPitchRangeJson(): string // PitchRange | null
+  setPitchRange(low: number, high: number): void // Throws on a range outside 0-127
+  setPitchRangePreset(name: string): void // Throws on an unknown preset
+  clearPitchRange(): void
+  getOutOfRangeNotesJson(): string // number[]
+  foldIntoRangeJson(low: number, high: number): string // FoldedNote[]; throws on a range narrower than an octave
 
   // Notes
   noteCount(): number
   addNote(pitch: number, startTick: number, durationTicks: number): void
   addNoteWithVelocity(pitch: number, startTick: number, durationTicks: number, velocity: number): void
   addNoteWithVoice(pitch: number, startTick: number, durationTicks: number, velocity: number, voice: number): void
+  setNotePitch(index: number, pitch: number): void
+  setNoteVelocity(index: number, velocity: number): void
+  setNoteDuration(index: number, durationTicks: number): void
+  updateNoteJson(index: number, json: string): number // New index after re-sorting
+  moveNotes(indices: Uint32Array, deltaTicks: number, deltaPitch: number): void
+  addNotesJson(json: string): number
+  addChordJson(keysJson: string, startTick: number, durationTicks: number): number // CapturedKey[], as one undo step
+  setNotePan(index: number, pan: number): boolean
+  // Experimental: notes that play only on some passes
+  setNoteProbability(index: number, probability: number): void // 0-100%
+  setNoteTake(index: number, take: string): void // 'A', 'B', or '' for none
+  hasChanceNotes(): boolean
+  freezeArrangement(pass: number): number // Notes dropped
+  hasPlaybackEffects(): boolean
+  flattenPlaybackEffects(): number // Notes changed
+  setNoteColor(indices: Uint32Array, color: string): number // Color label or '' to clear; playback ignores it
   removeNote(index: number): boolean
-  clearNotes(): void
+  splitNote(index: number, atTick: number): number
+  joinNotes(indices: Uint32Array): number
+  makeLegato(indices: Uint32Array, overlapTicks: number): number
+  addGap(indices: Uint32Array, gapTicks: number): number
+  strumChords(indices: Uint32Array, spreadTicks: number, direction: StrumDirection): number
+  extractGrooveJson(indices: Uint32Array, subdivisions: number): string // GrooveTemplate; throws without notes
+  applyGrooveJson(templateJson: string, indices: Uint32Array): number // Notes changed
+  // Chord voicings of the selected notes; each returns the number of chords changed
+  invertChordUp(indices: Uint32Array): number
+  invertChordDown(indices: Uint32Array): number
+  openVoicing(indices: Uint32Array): number
+  closeVoicing(indices: Uint32Array): number
+  getDiatonicChordsJson(): string // DiatonicChord[], one per degree of the song's key
+  suggestNextChordsJson(progressionJson: string): string // ChordSuggestion[]; throws on a chord outside the key
+  reharmonizeMeasureJson(measure: number, constraintsJson: string): string // Reharmonization[]
+  planModulationJson(toKey: string, startMeasure: number): string // ModulationPlan[]; throws if already in that key
+  applyModulationJson(planJson: string): number // Notes added
+  generateCounterpointJson(configJson: string): string // CounterpointReport; throws without a cantus firmus
+  generateMelodyJson(configJson: string): string // MelodyReport; throws on an unknown chord
+  loadGroovePreset(name: string): string // GroovePreset; throws on an unknown name
+  // Candidates are kept until one is applied or they are cleared
+  generateVariationsJson(indices: Uint32Array, count: number, paramsJson: string): string // Variation[]
+  variationAuditionJson(index: number): string // AuditionNote[]
+  applyVariation(index: number): number // Notes written; throws if the phrase changed since
+  clearVariations(): void
+  // A continuation is kept as a preview until accepted or rejected
+  continueMelodyJson(configJson: string): string // Continuation; throws without a melody
+  continuationAuditionJson(): string // AuditionNote[]
+  acceptContinuation(): number // Notes added; throws if the melody changed since
+  rejectContinuation(): void
+  importChordChartJson(text: string): string // ChartChord[]; throws on an unknown chord
+  setChordStrum(tick: number, spreadTicks: number, direction: StrumDirection): void
+  removeChordStrum(tick: number): boolean
+  parseChordJson(symbol: string): string // Chord; throws on an unknown chord
+  getChordsJson(): string // ChordSymbol[]
+  setChord(tick: number, symbol: string): void // Replaces the chord at that tick; throws on an unknown chord
+  removeChord(tick: number): boolean
+  getPatternsJson(): string // Pattern[]
+  getPatternChain(): string // e.g. "A A B A"; '' without a chain
+  createPattern(name: string, firstMeasure: number, count: number): void // Throws on a taken name or a bad measure
+  clonePattern(name: string, newName: string): void
+  movePattern(from: number, to: number): void // Indices into the pattern list
+  removePattern(name: string): boolean
+  setPatternChain(text: string): void // Throws on an unknown pattern
+  getPlaybackNotesJson(): string
+  getPlaybackNotesForPassJson(pass: number): string // Chance notes and take lanes as that pass plays them
+  clearNotes(confirm: boolean): string // Outcome<null>
+  deleteMeasures(first: number, count: number, confirm: boolean): string // Outcome<number>, notes removed; throws on measure 0
+  checkNewSong(confirm: boolean): string // Outcome<null>; needs confirmation over unsaved changes
   getNotesJson(): string
   getNoteJson(index: number): string | undefined
+  getNotesInRangeJson(startTick: number, endTick: number): string
+  getNoteWindowJson(startTick: number, endTick: number): string // IndexedNote[], unclipped
+  // Packed binary variants, present when built with the `packed-notes` feature (see packed.ts)
+  getNotesPacked?(): Uint8Array
+  getNoteWindowPacked?(startTick: number, endTick: number): Uint8Array
 
   // Melody
   parseMelody(melody: string): number
   formatMelody(): string
+  suggestNextTokensJson(partialText: string): string // TokenSuggestion[]
 
   // Transposition
   transposeChromatic(semitones: number): void
@@ -42,35 +380,177 @@ export interface Mozart {
   transposeDiatonicWithKeyChange(targetKey: string, degrees: number): void
   invert(pivot: number): void
 
+  // Validation
+  validateSongJson(): string
+  repairSong(optionsJson: string): string
+  cleanupSongJson(dryRun: boolean): string // CleanupReport
+  normalizeVelocities(min: number, max: number): number
+  compressVelocities(ratio: number, threshold: number): number
+
+  // View state
+  getViewStateJson(): string
+  setViewStateJson(json: string): void
+  getVisiblePitchRange(pitchViewJson: string): Uint8Array // [low, high] piano roll rows
+  getLoopRegion(): Uint32Array // [start, end] written ticks, or empty when not looping
+  loopCurrentMeasure(tick: number): Uint32Array // Loops tick's measure; returns [start, end]
+  loopNextMeasure(tick: number): Uint32Array // Steps the loop forward by its length
+  loopPreviousMeasure(tick: number): Uint32Array
+  extendLoopByMeasure(tick: number): Uint32Array
+  clearLoop(): boolean
+
+  // Change events
+  takeChangesJson(): string // SongChange[] made since the last call
+
+  // Undo/redo
+  undo(): boolean
+  redo(): boolean
+  canUndo(): boolean
+  canRedo(): boolean
+  undoLabel(): string | undefined
+  redoLabel(): string | undefined
+  markSaved(): void
+  isDirty(): boolean
+  setUndoLimit(limit: number): void
+  setUndoMemoryLimit(bytes: number): void
+  setUndoCoalesceWindow(windowMs: number): void
+  undoMemoryUsage(): number
+
+  // Tutorial
+  tutorialStart(): void
+  tutorialStop(): void
+  tutorialStateJson(): string // TutorialState | null
+  tutorialNext(): void // Throws if the step has not been done
+  tutorialSkip(): void
+
+  // Practice (seconds are counted from the start of the call)
+  startCallResponseJson(configJson: string): string // CallResponseInfo; throws if there is nothing to play
+  callResponseNoteOn(pitch: number, velocity: number, seconds: number): void
+  advanceCallResponseJson(seconds: number): string // CallResponseEvent[]
+  stopCallResponseJson(): string // CallResponseResult | null
+
+  // Auditions, at the song's tempo
+  scaleAuditionJson(root: string, scaleType: string, direction: ScaleDirection): string // AuditionNote[]
+  chordAuditionJson(root: string, quality: string, inversion: number): string // AuditionNote[]
+  diatonicChordAuditionJson(degree: number, seventh: boolean): string // AuditionNote[]; throws outside 1-7
+
+  // Register heatmap
+  getPitchHistogramJson(bucketTicks: number): string // PitchHistogram; throws on zero-length buckets
+
   // Accents
+  getAccentPreviewJson(): string
+  getVoiceAccentPreviewJson(voice: number): string // Click[] in the voice's meter
   getAccents(): Uint8Array
   setAccents(accents: Uint8Array | number[]): void
   cycleAccent(beat: number): void
+  getGroupings(): Uint8Array
+  getPulseGrouping(): Uint8Array
+  setPulseGrouping(groups: Uint8Array): void
+  snapToPulse(tick: number): number
   getAccentVisual(): string
 
   // Duration info
   durationTicks(): number
   durationSeconds(): number
+  durationWithTempoMap(): number
+  durationToEndOfLastMeasure(): number
+  remainingSeconds(tick: number): number
+  getSongInfoJson(): string
   measureCount(): number
   ticksPerBeat(): number
   ticksPerMeasure(): number
 
+  // Position conversion
+  tickToSeconds(tick: number): number
+  secondsToTick(seconds: number): number
+  positionToMbt(tick: number): string
+  mbtToTick(measure: number, beat: number, tick: number): number
+  parsePosition(position: string): number
+  formatTransportTime(tick: number): string
+  measureStartTick(measure: number): number
+
+  // Markers
+  addMarker(name: string, tick: number): void
+  removeMarker(name: string): boolean
+  setSectionColor(name: string, color: string): void // Colors the marker's section; '' to clear
+  getMarkersJson(): string
+  markerTick(name: string): number | undefined
+  nextMarkerTick(tick: number): number | undefined
+  prevMarkerTick(tick: number): number | undefined
+
+  // Lyrics
+  setLyrics(text: string): number // Syllables placed
+  getLyricsText(): string
+  exportLrc(syllableTiming: boolean): string // Throws if the song has no lyrics
+  exportChordPro(): string // Throws if the song has no lyrics or chords
+
+  // Annotations
+  getAnnotationsJson(): string // Annotation[]
+  addAnnotation(tick: number, endTick: number | undefined, text: string): number // Index; throws on blank text
+  removeAnnotation(index: number): boolean
+
+  // Repeats (written form; playback and MIDI export unfold them)
+  getRepeatsJson(): string // RepeatStructure
+  addRepeat(spec: string): void // "5-8 x3"; throws on a bad spec
+  addEnding(spec: string): void // "9-10 2"; throws on a bad spec
+  setJump(spec: string): void // "D.S. al Coda 24", '' to remove; throws without its signs
+  placeRepeatSign(spec: string): void // "segno 3", "fine 12", "to coda 16", "coda 20"
+  removeRepeatsAt(measure: number): boolean
+  clearRepeats(): boolean
+  getMeasureOrder(): Uint32Array // Written measures in played order
+  writtenTick(playedTick: number): number
+  playedTick(writtenTick: number): number
+
   // Serialization
   toJson(): string
+  importJson(json: string): void // Undoable replacement of the whole song
+  // Layer another song onto voices of its own; return those voices as JSON number[]
+  mergeSongJson(json: string, alignment: TempoAlignment): string
+  mergeMidi(bytes: Uint8Array, alignment: TempoAlignment): string
+  // Compare the melody (voice 0) with another song's (JSON SimilarityReport); throws under two notes
+  compareSongJson(json: string): string
+  compareMidi(bytes: Uint8Array): string
+  // The song and another version laid out to be played against each other (JSON Comparison)
+  getComparisonPlaybackJson(json: string, mode: ComparisonMode): string
+  getComparisonPlaybackMidi(bytes: Uint8Array, mode: ComparisonMode): string
+  // Preview a split (JSON SplitPiece[]), then cut the song into pieces (JSON Song[]); throws without notes
+  planSplitJson(ticks: Uint32Array): string // Ticks on the played timeline
+  planSectionsJson(): string // Throws without section markers
+  splitSongJson(planJson: string): string
   toMidi(): Uint8Array
+  toMidiWithAccentDynamics(accentDynamics: boolean): Uint8Array
+  toPracticeWav(rate: number): Uint8Array // Pitch kept; throws outside 0.25-2, or for songs over 30 minutes
 
   // Static utility methods (on the class, not instance)
   // These are accessed via the module, not the instance
 }
 
+// Audio engine health checks (AudioWatchdog in the WASM module)
+export interface ChordCapture {
+  readonly windowMs: number // Keys pressed within this long of the first join its chord
+  press(pitch: number, velocity: number, nowMs: number): string // CapturedKey[] of the previous chord if its window closed, or ''
+  poll(nowMs: number): string // CapturedKey[] once the window has passed, or ''
+  flush(): string // CapturedKey[] now, or '' without keys
+  free(): void
+}
+
+export interface AudioWatchdog {
+  observe(nowMs: number, audioTime: number, state: string): string // AudioDiagnostics, or '' while healthy
+  deviceLost(nowMs: number, state: string): string // AudioDiagnostics
+  restart(): void // After the engine was re-created
+  free(): void
+}
+
 // Utility function types
 export interface MozartStatic {
   new(): Mozart
   withTitle(title: string): Mozart
+  withDefaults(title: string, defaultsJson: string): Mozart
   fromJson(json: string): Mozart
+  fromMidi(bytes: Uint8Array): Mozart
   midiToFrequency(midi: number): number
   midiToNoteName(midi: number): string
   noteNameToMidi(name: string): number
+  formatDuration(seconds: number): string
 }
 
 // Constants